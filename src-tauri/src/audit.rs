@@ -0,0 +1,539 @@
+use crate::error::FontError;
+use crate::font_parser::{
+    extract_glyph_outlines, get_font_bytes, parse_composite_components, parse_loca_offsets,
+    parse_svg_path_cmds, CancelToken, FontCache, SvgCmd,
+};
+use crate::gsub::substitution_outputs;
+use crate::validate::Severity;
+use serde::Serialize;
+use skrifa::raw::types::Tag;
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::HashSet;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UnreachableGlyph {
+    pub glyph_id: u32,
+    pub name: String,
+    pub byte_size_in_glyf: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UnreachableGlyphReport {
+    pub glyphs: Vec<UnreachableGlyph>,
+    pub total_wasted_bytes: u32,
+}
+
+/// Find glyphs that are not mapped in cmap, not used as a component by any
+/// composite, and not produced by any GSUB substitution. .notdef is always
+/// kept. This is a single pass, not a full closure: a component of an
+/// unreachable composite is still counted as referenced.
+fn find_unreachable_glyphs(bytes: &[u8]) -> Result<UnreachableGlyphReport, FontError> {
+    let raw = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+
+    let mut reachable: HashSet<u32> = HashSet::new();
+    reachable.insert(0);
+    reachable.extend(font.charmap().mappings().map(|(_, gid)| gid.to_u32()));
+    reachable.extend(substitution_outputs(&raw)?);
+
+    // Per-glyph record sizes from loca; CFF fonts have no glyf and report 0
+    let glyf = raw.table_data(Tag::new(b"glyf"));
+    let loca = raw.table_data(Tag::new(b"loca"));
+    let sizes: Vec<u32> = match (glyf, loca, raw.head()) {
+        (Some(glyf), Some(loca), Ok(head)) => {
+            let glyf = glyf.as_bytes();
+            let is_long = head.index_to_loc_format() != 0;
+            let offsets = parse_loca_offsets(loca.as_bytes(), num_glyphs + 1, is_long);
+            for w in offsets.windows(2) {
+                let (start, end) = (w[0] as usize, w[1] as usize);
+                if start + 10 >= end || end > glyf.len() {
+                    continue;
+                }
+                let num_contours = i16::from_be_bytes([glyf[start], glyf[start + 1]]);
+                if num_contours < 0 {
+                    for comp in parse_composite_components(&glyf[start + 10..end]) {
+                        reachable.insert(comp.glyph_id);
+                    }
+                }
+            }
+            offsets
+                .windows(2)
+                .map(|w| w[1].saturating_sub(w[0]))
+                .collect()
+        }
+        _ => vec![0; num_glyphs],
+    };
+
+    let names = font.glyph_names();
+    let glyphs: Vec<UnreachableGlyph> = (0..num_glyphs as u32)
+        .filter(|gid| !reachable.contains(gid))
+        .map(|gid| UnreachableGlyph {
+            glyph_id: gid,
+            name: names
+                .get(GlyphId::new(gid))
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| format!("gid{}", gid)),
+            byte_size_in_glyf: sizes.get(gid as usize).copied().unwrap_or(0),
+        })
+        .collect();
+    let total_wasted_bytes = glyphs.iter().map(|g| g.byte_size_in_glyf).sum();
+
+    Ok(UnreachableGlyphReport {
+        glyphs,
+        total_wasted_bytes,
+    })
+}
+
+pub fn audit_unreachable_glyphs(
+    file_path: &str,
+    cache: &FontCache,
+) -> Result<UnreachableGlyphReport, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    find_unreachable_glyphs(&bytes)
+}
+
+/// Segments each curve is flattened into for areas and containment.
+const FLATTEN_STEPS: usize = 8;
+
+/// Points further than this many ems from the origin are reported.
+const EXTREME_EMS: f32 = 4.0;
+
+/// One contour of a glyph, as drawn.
+pub(crate) struct FlatContour {
+    /// On-curve points in drawing order, without the closing repeat of the
+    /// start point.
+    pub(crate) points: Vec<(f32, f32)>,
+    /// `points` with curves flattened, for area and containment tests.
+    pub(crate) polygon: Vec<(f32, f32)>,
+    pub(crate) closed: bool,
+}
+
+/// Split drawn path commands (Y up) into contours.
+pub(crate) fn flat_contours(cmds: &[SvgCmd]) -> Vec<FlatContour> {
+    let mut contours: Vec<FlatContour> = Vec::new();
+    let mut current = (0.0, 0.0);
+    for cmd in cmds {
+        if let SvgCmd::MoveTo(x, y) = *cmd {
+            contours.push(FlatContour {
+                points: vec![(x, y)],
+                polygon: vec![(x, y)],
+                closed: false,
+            });
+            current = (x, y);
+            continue;
+        }
+        let Some(contour) = contours.last_mut() else {
+            continue;
+        };
+        let end = match *cmd {
+            SvgCmd::LineTo(x, y) => {
+                contour.polygon.push((x, y));
+                (x, y)
+            }
+            SvgCmd::QuadTo(cx, cy, x, y) => {
+                for i in 1..=FLATTEN_STEPS {
+                    let t = i as f32 / FLATTEN_STEPS as f32;
+                    let mt = 1.0 - t;
+                    contour.polygon.push((
+                        mt * mt * current.0 + 2.0 * mt * t * cx + t * t * x,
+                        mt * mt * current.1 + 2.0 * mt * t * cy + t * t * y,
+                    ));
+                }
+                (x, y)
+            }
+            SvgCmd::CurveTo(c0x, c0y, c1x, c1y, x, y) => {
+                for i in 1..=FLATTEN_STEPS {
+                    let t = i as f32 / FLATTEN_STEPS as f32;
+                    let mt = 1.0 - t;
+                    let (a, b, c, d) =
+                        (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+                    contour.polygon.push((
+                        a * current.0 + b * c0x + c * c1x + d * x,
+                        a * current.1 + b * c0y + c * c1y + d * y,
+                    ));
+                }
+                (x, y)
+            }
+            SvgCmd::Close => {
+                contour.closed = true;
+                // The segment back to the start repeats the start point
+                if contour.points.len() > 1 && contour.points.first() == contour.points.last() {
+                    contour.points.pop();
+                }
+                continue;
+            }
+            SvgCmd::MoveTo(..) => unreachable!(),
+        };
+        contour.points.push(end);
+        current = end;
+    }
+    contours
+}
+
+/// Shoelace area of a polygon: positive when wound counter-clockwise (Y up).
+pub(crate) fn signed_area(polygon: &[(f32, f32)]) -> f32 {
+    let n = polygon.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f32>()
+        / 2.0
+}
+
+fn contains(polygon: &[(f32, f32)], (x, y): (f32, f32)) -> bool {
+    let n = polygon.len();
+    let mut inside = false;
+    for i in 0..n {
+        let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+        if (a.1 > y) != (b.1 > y) && x < a.0 + (y - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// The innermost contour enclosing each contour, if any. A contour counts as
+/// inside another when its first point is and the other is the larger one;
+/// the parent is the smallest such container.
+pub(crate) fn contour_parents(contours: &[FlatContour], areas: &[f32]) -> Vec<Option<usize>> {
+    (0..contours.len())
+        .map(|i| {
+            let &probe = contours[i].polygon.first()?;
+            (0..contours.len())
+                .filter(|&j| {
+                    j != i
+                        && areas[j].abs() > areas[i].abs()
+                        && contains(&contours[j].polygon, probe)
+                })
+                .min_by(|&a, &b| areas[a].abs().total_cmp(&areas[b].abs()))
+        })
+        .collect()
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlineProblem {
+    /// The contour ends without closing.
+    OpenContour,
+    /// A contour runs the same way as the contour around it, so the
+    /// counter fills in instead of being cut out.
+    HoleWoundLikeOuter,
+    /// An outermost contour runs against the format's convention
+    /// (clockwise for glyf, counter-clockwise for CFF).
+    ReversedOuter,
+    ZeroArea,
+    DuplicatePoint,
+    ExtremeCoordinate,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OutlineFinding {
+    pub problem: OutlineProblem,
+    pub severity: Severity,
+    /// Index of the contour within the glyph.
+    pub contour: usize,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GlyphOutlineFindings {
+    pub glyph_id: u32,
+    pub glyph_name: String,
+    pub findings: Vec<OutlineFinding>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct OutlineQualityReport {
+    /// Glyphs with at least one finding, by glyph ID.
+    pub glyphs: Vec<GlyphOutlineFindings>,
+    pub glyphs_checked: usize,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+fn audit_contours(
+    contours: &[FlatContour],
+    units_per_em: f32,
+    outers_clockwise: bool,
+) -> Vec<OutlineFinding> {
+    let mut findings = Vec::new();
+    let mut push = |problem, severity, contour, message: String| {
+        findings.push(OutlineFinding {
+            problem,
+            severity,
+            contour,
+            message,
+        })
+    };
+    let areas: Vec<f32> = contours.iter().map(|c| signed_area(&c.polygon)).collect();
+    let parents = contour_parents(contours, &areas);
+    let limit = units_per_em * EXTREME_EMS;
+
+    for (i, contour) in contours.iter().enumerate() {
+        if !contour.closed {
+            push(
+                OutlineProblem::OpenContour,
+                Severity::Error,
+                i,
+                "Contour is not closed".to_string(),
+            );
+        }
+        if areas[i].abs() < 1.0 {
+            push(
+                OutlineProblem::ZeroArea,
+                Severity::Warning,
+                i,
+                format!(
+                    "Contour of {} points encloses no area",
+                    contour.points.len()
+                ),
+            );
+        } else {
+            match parents[i] {
+                Some(parent) if areas[parent].abs() >= 1.0 => {
+                    if (areas[i] > 0.0) == (areas[parent] > 0.0) {
+                        push(
+                            OutlineProblem::HoleWoundLikeOuter,
+                            Severity::Error,
+                            i,
+                            format!("Contour runs the same way as contour {} around it", parent),
+                        );
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    if (areas[i] < 0.0) != outers_clockwise {
+                        push(
+                            OutlineProblem::ReversedOuter,
+                            Severity::Warning,
+                            i,
+                            format!(
+                                "Outer contour runs {}",
+                                if outers_clockwise {
+                                    "counter-clockwise"
+                                } else {
+                                    "clockwise"
+                                }
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+        if let Some(index) = contour.points.windows(2).position(|w| w[0] == w[1]) {
+            let (x, y) = contour.points[index];
+            push(
+                OutlineProblem::DuplicatePoint,
+                Severity::Warning,
+                i,
+                format!(
+                    "Point {} repeats the one before it at ({}, {})",
+                    index + 1,
+                    x,
+                    y
+                ),
+            );
+        }
+        if let Some(&(x, y)) = contour
+            .points
+            .iter()
+            .find(|(x, y)| x.abs() > limit || y.abs() > limit)
+        {
+            push(
+                OutlineProblem::ExtremeCoordinate,
+                Severity::Warning,
+                i,
+                format!(
+                    "Point ({}, {}) lies more than {} ems out",
+                    x, y, EXTREME_EMS
+                ),
+            );
+        }
+    }
+    findings
+}
+
+/// Check every outline of the font for open contours, winding that does not
+/// match nesting, empty contours, repeated points and far-out coordinates.
+/// Reads the cached outlines when all are cached, else draws them once.
+pub fn audit_outline_quality(
+    file_path: &str,
+    cache: &FontCache,
+) -> Result<OutlineQualityReport, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let font = FontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = font.maxp().map_or(0, |m| m.num_glyphs() as u32);
+    let paths: Vec<(u32, String)> = if cache.cached_bounds(file_path).is_some() {
+        let ids: Vec<u32> = (0..num_glyphs).collect();
+        let mut paths: Vec<(u32, String)> = cache
+            .cached_svg_paths(file_path, &ids)
+            .into_iter()
+            .collect();
+        paths.sort_unstable_by_key(|&(gid, _)| gid);
+        paths
+    } else {
+        extract_glyph_outlines(&bytes, &|_, _| {}, &CancelToken::default())?
+            .into_iter()
+            .filter(|o| !o.svg_path.is_empty())
+            .map(|o| (o.glyph_id, o.svg_path))
+            .collect()
+    };
+
+    let units_per_em = font.head().map_or(1000, |h| h.units_per_em()) as f32;
+    let outers_clockwise = font.table_data(Tag::new(b"glyf")).is_some();
+    let names = font.glyph_names();
+    let mut report = OutlineQualityReport {
+        glyphs_checked: paths.len(),
+        ..Default::default()
+    };
+    for (glyph_id, path) in paths {
+        let contours = flat_contours(&parse_svg_path_cmds(&path)?);
+        let findings = audit_contours(&contours, units_per_em, outers_clockwise);
+        if findings.is_empty() {
+            continue;
+        }
+        for finding in &findings {
+            match finding.severity {
+                Severity::Error => report.errors += 1,
+                Severity::Warning => report.warnings += 1,
+            }
+        }
+        report.glyphs.push(GlyphOutlineFindings {
+            glyph_id,
+            glyph_name: names
+                .get(GlyphId::new(glyph_id))
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| format!("gid{}", glyph_id)),
+            findings,
+        });
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{
+        composite_glyph, simple_glyph, single_subst_gsub, TestComponent, TestFontBuilder,
+    };
+
+    #[test]
+    fn test_unreachable_glyphs() {
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let comp = composite_glyph(&[TestComponent {
+            glyph_id: 2,
+            dx: 10,
+            dy: 0,
+            transform: None,
+        }]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500) // 0: .notdef
+            .glyph(bar.clone(), 500) // 1: mapped
+            .glyph(bar.clone(), 500) // 2: component of 3
+            .glyph(comp, 500) // 3: mapped composite
+            .glyph(bar.clone(), 500) // 4: GSUB output of 1
+            .glyph(bar.clone(), 500) // 5: unreachable
+            .map('a' as u32, 1)
+            .map('b' as u32, 3)
+            .table(b"GSUB", single_subst_gsub(&[(1, 4)]))
+            .build();
+
+        let report = find_unreachable_glyphs(&font).unwrap();
+        let ids: Vec<u32> = report.glyphs.iter().map(|g| g.glyph_id).collect();
+        assert_eq!(ids, vec![5]);
+        let padded = bar.len().next_multiple_of(4) as u32;
+        assert_eq!(report.glyphs[0].byte_size_in_glyf, padded);
+        assert_eq!(report.total_wasted_bytes, padded);
+        assert_eq!(report.glyphs[0].name, "gid5");
+    }
+
+    fn problems(path: &str, outers_clockwise: bool) -> Vec<(OutlineProblem, usize)> {
+        let contours = flat_contours(&parse_svg_path_cmds(path).unwrap());
+        audit_contours(&contours, 1000.0, outers_clockwise)
+            .into_iter()
+            .map(|f| (f.problem, f.contour))
+            .collect()
+    }
+
+    #[test]
+    fn test_outline_quality_findings() {
+        // SVG paths are Y down: "M0 0 L0 -100 L100 -100 L100 0 Z" runs
+        // clockwise in font space
+        let outer = "M0 0 L0 -700 L500 -700 L500 0 Z ";
+        let hole_cw = "M100 -100 L100 -600 L400 -600 L400 -100 Z";
+        let hole_ccw = "M100 -100 L400 -100 L400 -600 L100 -600 Z";
+        assert!(problems(&format!("{}{}", outer, hole_ccw), true).is_empty());
+        assert_eq!(
+            problems(&format!("{}{}", outer, hole_cw), true),
+            vec![(OutlineProblem::HoleWoundLikeOuter, 1)]
+        );
+        // The same glyph read as CFF has its outer reversed, and the hole
+        // still nests correctly
+        assert_eq!(
+            problems(&format!("{}{}", outer, hole_ccw), false),
+            vec![(OutlineProblem::ReversedOuter, 0)]
+        );
+
+        let path = "M0 0 L0 -700 L0 -700 L500 -700 L500 0 \
+                    M600 0 L700 0 L800 0 Z \
+                    M2000 0 L2000 -5000 L2010 -5000 Z";
+        assert_eq!(
+            problems(path, true),
+            vec![
+                (OutlineProblem::OpenContour, 0),
+                (OutlineProblem::DuplicatePoint, 0),
+                (OutlineProblem::ZeroArea, 1),
+                (OutlineProblem::ExtremeCoordinate, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_audit_outline_quality_reports_glyphs() {
+        // Both contours clockwise: the counter fills in
+        let nested = simple_glyph(&[
+            &[
+                (0, 0, true),
+                (0, 700, true),
+                (500, 700, true),
+                (500, 0, true),
+            ],
+            &[
+                (100, 100, true),
+                (100, 600, true),
+                (400, 600, true),
+                (400, 100, true),
+            ],
+        ]);
+        let square = simple_glyph(&[&[
+            (0, 0, true),
+            (0, 700, true),
+            (500, 700, true),
+            (500, 0, true),
+        ]]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(square, 500)
+            .glyph(nested, 500)
+            .build();
+        let cache = FontCache::new();
+        cache.insert("quality.ttf".into(), font);
+
+        let report = audit_outline_quality("quality.ttf", &cache).unwrap();
+        assert_eq!(report.glyphs_checked, 2);
+        assert_eq!((report.errors, report.warnings), (1, 0));
+        assert_eq!(report.glyphs[0].glyph_id, 2);
+        assert_eq!(report.glyphs[0].glyph_name, "gid2");
+        assert_eq!(
+            report.glyphs[0].findings[0].problem,
+            OutlineProblem::HoleWoundLikeOuter
+        );
+    }
+}
@@ -0,0 +1,1153 @@
+use crate::error::FontError;
+use crate::font_parser::{get_font_bytes, FontCache, SvgCmd};
+use serde::Serialize;
+use skrifa::raw::tables::cff::Cff;
+use skrifa::raw::tables::cff2::Cff2;
+use skrifa::raw::tables::postscript::{dict, FdSelect, Index, Index1, Index2};
+use skrifa::raw::{FontRead, FontRef as RawFontRef, TableProvider};
+use skrifa::GlyphId;
+
+/// Type 2 charstring limits (CFF spec, Appendix B).
+const MAX_STACK: usize = 48;
+const MAX_SUBR_DEPTH: u8 = 10;
+
+/// One decompiled charstring operator with the operands it consumed.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CharstringOp {
+    pub op: String,
+    pub operands: Vec<f64>,
+    /// Subroutine nesting level (0 = the glyph's own charstring).
+    pub depth: u8,
+    /// Biased subroutine index for callsubr / callgsubr.
+    pub subr_index: Option<i32>,
+    /// hintmask / cntrmask bits, one character per declared stem.
+    pub mask: Option<String>,
+}
+
+fn operator_name(b0: u8, escape: Option<u8>) -> String {
+    let name = match escape {
+        None => match b0 {
+            1 => "hstem",
+            3 => "vstem",
+            4 => "vmoveto",
+            5 => "rlineto",
+            6 => "hlineto",
+            7 => "vlineto",
+            8 => "rrcurveto",
+            10 => "callsubr",
+            11 => "return",
+            14 => "endchar",
+            15 => "vsindex",
+            16 => "blend",
+            18 => "hstemhm",
+            19 => "hintmask",
+            20 => "cntrmask",
+            21 => "rmoveto",
+            22 => "hmoveto",
+            23 => "vstemhm",
+            24 => "rcurveline",
+            25 => "rlinecurve",
+            26 => "vvcurveto",
+            27 => "hhcurveto",
+            29 => "callgsubr",
+            30 => "vhcurveto",
+            31 => "hvcurveto",
+            _ => return format!("reserved{}", b0),
+        },
+        Some(b1) => match b1 {
+            3 => "and",
+            4 => "or",
+            5 => "not",
+            9 => "abs",
+            10 => "add",
+            11 => "sub",
+            12 => "div",
+            14 => "neg",
+            15 => "eq",
+            18 => "drop",
+            20 => "put",
+            21 => "get",
+            22 => "ifelse",
+            23 => "random",
+            24 => "mul",
+            26 => "sqrt",
+            27 => "dup",
+            28 => "exch",
+            29 => "index",
+            30 => "roll",
+            34 => "hflex",
+            35 => "flex",
+            36 => "hflex1",
+            37 => "flex1",
+            _ => return format!("reserved12_{}", b1),
+        },
+    };
+    name.to_string()
+}
+
+struct Decompiler<'a> {
+    global_subrs: &'a Index<'a>,
+    local_subrs: &'a Index<'a>,
+    stack: Vec<f64>,
+    num_stems: usize,
+    width_seen: bool,
+    finished: bool,
+    ops: Vec<CharstringOp>,
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(global_subrs: &'a Index<'a>, local_subrs: &'a Index<'a>) -> Self {
+        Self {
+            global_subrs,
+            local_subrs,
+            stack: Vec::new(),
+            num_stems: 0,
+            width_seen: false,
+            finished: false,
+            ops: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, value: f64) -> Result<(), FontError> {
+        if self.stack.len() >= MAX_STACK {
+            return Err(FontError::malformed("CFF ", "Charstring stack overflow"));
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn emit(&mut self, op: String, operands: Vec<f64>, depth: u8) {
+        self.ops.push(CharstringOp {
+            op,
+            operands,
+            depth,
+            subr_index: None,
+            mask: None,
+        });
+    }
+
+    /// The first stack-clearing operator may carry the advance width as an
+    /// extra leading operand; split it off into its own "width" entry.
+    fn take_width(&mut self, op: &str, depth: u8) {
+        if self.width_seen {
+            return;
+        }
+        let n = self.stack.len();
+        let has_width = match op {
+            "hstem" | "vstem" | "hstemhm" | "vstemhm" | "hintmask" | "cntrmask" => n % 2 == 1,
+            "rmoveto" => n > 2,
+            "hmoveto" | "vmoveto" => n > 1,
+            "endchar" => n == 1 || n == 5,
+            _ => return,
+        };
+        self.width_seen = true;
+        if has_width {
+            let width = self.stack.remove(0);
+            self.emit("width".into(), vec![width], depth);
+        }
+    }
+
+    fn run(&mut self, data: &[u8], depth: u8) -> Result<(), FontError> {
+        if depth > MAX_SUBR_DEPTH {
+            return Err(FontError::malformed(
+                "CFF ",
+                "Charstring subroutine nesting is too deep",
+            ));
+        }
+        let byte = |pos: usize| -> Result<u8, FontError> {
+            data.get(pos)
+                .copied()
+                .ok_or_else(|| FontError::malformed("CFF ", "Truncated charstring"))
+        };
+        let mut pos = 0;
+        while pos < data.len() && !self.finished {
+            let b0 = data[pos];
+            pos += 1;
+            match b0 {
+                32..=246 => self.push(b0 as f64 - 139.0)?,
+                247..=250 => {
+                    let v = (b0 as i32 - 247) * 256 + byte(pos)? as i32 + 108;
+                    pos += 1;
+                    self.push(v as f64)?;
+                }
+                251..=254 => {
+                    let v = -(b0 as i32 - 251) * 256 - byte(pos)? as i32 - 108;
+                    pos += 1;
+                    self.push(v as f64)?;
+                }
+                28 => {
+                    let v = i16::from_be_bytes([byte(pos)?, byte(pos + 1)?]);
+                    pos += 2;
+                    self.push(v as f64)?;
+                }
+                255 => {
+                    let v = i32::from_be_bytes([
+                        byte(pos)?,
+                        byte(pos + 1)?,
+                        byte(pos + 2)?,
+                        byte(pos + 3)?,
+                    ]);
+                    pos += 4;
+                    self.push(v as f64 / 65536.0)?;
+                }
+                10 | 29 => {
+                    let raw = self.stack.pop().ok_or_else(|| {
+                        FontError::malformed("CFF ", "callsubr with an empty stack")
+                    })?;
+                    let subrs = if b0 == 10 {
+                        self.local_subrs
+                    } else {
+                        self.global_subrs
+                    };
+                    let index = raw as i32 + subrs.subr_bias();
+                    let subr = usize::try_from(index)
+                        .ok()
+                        .and_then(|i| subrs.get(i).ok())
+                        .ok_or_else(|| {
+                            FontError::malformed(
+                                "CFF ",
+                                format!("Invalid subroutine index {}", index),
+                            )
+                        })?;
+                    self.ops.push(CharstringOp {
+                        op: operator_name(b0, None),
+                        operands: vec![raw],
+                        depth,
+                        subr_index: Some(index),
+                        mask: None,
+                    });
+                    self.run(subr, depth + 1)?;
+                }
+                11 => {
+                    self.emit(operator_name(b0, None), Vec::new(), depth);
+                    return Ok(());
+                }
+                12 => {
+                    let b1 = byte(pos)?;
+                    pos += 1;
+                    let name = operator_name(b0, Some(b1));
+                    if matches!(b1, 34..=37) {
+                        let operands = std::mem::take(&mut self.stack);
+                        self.emit(name, operands, depth);
+                    } else {
+                        // Arithmetic and storage operators are not evaluated
+                        // (they are deprecated); the stack is left untouched.
+                        self.emit(name, Vec::new(), depth);
+                    }
+                }
+                19 | 20 => {
+                    let name = operator_name(b0, None);
+                    self.take_width(&name, depth);
+                    // Operands before the first hintmask are implicit vstems
+                    self.num_stems += self.stack.len() / 2;
+                    let mask_len = self.num_stems.div_ceil(8);
+                    let bytes = data
+                        .get(pos..pos + mask_len)
+                        .ok_or_else(|| FontError::malformed("CFF ", "Truncated hintmask"))?;
+                    pos += mask_len;
+                    let mask: String = bytes
+                        .iter()
+                        .flat_map(|b| (0..8).rev().map(move |bit| (b >> bit) & 1))
+                        .take(self.num_stems)
+                        .map(|bit| if bit == 1 { '1' } else { '0' })
+                        .collect();
+                    let operands = std::mem::take(&mut self.stack);
+                    self.ops.push(CharstringOp {
+                        op: name,
+                        operands,
+                        depth,
+                        subr_index: None,
+                        mask: Some(mask),
+                    });
+                }
+                0..=31 => {
+                    let name = operator_name(b0, None);
+                    self.take_width(&name, depth);
+                    if matches!(b0, 1 | 3 | 18 | 23) {
+                        self.num_stems += self.stack.len() / 2;
+                    }
+                    let operands = std::mem::take(&mut self.stack);
+                    self.emit(name, operands, depth);
+                    if b0 == 14 {
+                        self.finished = true;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decompile a Type 2 charstring. Subroutines are always followed (stem
+/// counts and operands can come from them); with `expand_subrs` false only
+/// the glyph's own operators and the call sites are returned.
+fn decompile_charstring(
+    charstring: &[u8],
+    global_subrs: &Index<'_>,
+    local_subrs: &Index<'_>,
+    expand_subrs: bool,
+) -> Result<Vec<CharstringOp>, FontError> {
+    let mut decompiler = Decompiler::new(global_subrs, local_subrs);
+    decompiler.run(charstring, 0)?;
+    let mut ops = decompiler.ops;
+    if !expand_subrs {
+        ops.retain(|op| op.depth == 0);
+    }
+    Ok(ops)
+}
+
+/// Locate the glyph's charstring, its Private DICT (selected through FDSelect
+/// for CID-keyed fonts; empty if there is none) and that DICT's local subrs.
+fn charstring_and_local_subrs<'a>(
+    cff: &Cff<'a>,
+    glyph_id: u32,
+) -> Result<(&'a [u8], &'a [u8], Index<'a>), FontError> {
+    let data = cff.offset_data();
+    let top_dict = cff.top_dicts().get(0).map_err(|e| {
+        FontError::malformed("CFF ", format!("Failed to read CFF top DICT: {:?}", e))
+    })?;
+
+    let mut charstrings_offset = None;
+    let mut private_range = None;
+    let mut fd_array_offset = None;
+    let mut fd_select_offset = None;
+    for entry in dict::entries(top_dict, None).flatten() {
+        match entry {
+            dict::Entry::CharstringsOffset(o) => charstrings_offset = Some(o),
+            dict::Entry::PrivateDictRange(r) => private_range = Some(r),
+            dict::Entry::FdArrayOffset(o) => fd_array_offset = Some(o),
+            dict::Entry::FdSelectOffset(o) => fd_select_offset = Some(o),
+            _ => {}
+        }
+    }
+
+    let charstrings_offset = charstrings_offset
+        .ok_or_else(|| FontError::malformed("CFF ", "CFF top DICT has no CharStrings"))?;
+    let charstrings = data
+        .split_off(charstrings_offset)
+        .ok_or_else(|| FontError::malformed("CFF ", "CharStrings offset out of bounds"))
+        .and_then(|d| {
+            Index1::read(d).map_err(|e| {
+                FontError::malformed("CFF ", format!("Failed to read CharStrings: {:?}", e))
+            })
+        })?;
+    let charstring = charstrings
+        .get(glyph_id as usize)
+        .map_err(|_| FontError::GlyphNotFound { gid: glyph_id })?;
+
+    if let (Some(fd_array_offset), Some(fd_select_offset)) = (fd_array_offset, fd_select_offset) {
+        let fd_select = data
+            .split_off(fd_select_offset)
+            .and_then(|d| FdSelect::read(d).ok())
+            .ok_or_else(|| FontError::malformed("CFF ", "Failed to read FDSelect"))?;
+        let fd_index = fd_select
+            .font_index(GlyphId::new(glyph_id))
+            .ok_or_else(|| {
+                FontError::malformed("CFF ", format!("Glyph {} has no FDSelect entry", glyph_id))
+            })?;
+        let font_dict = data
+            .split_off(fd_array_offset)
+            .and_then(|d| Index1::read(d).ok())
+            .and_then(|fd_array| fd_array.get(fd_index as usize).ok())
+            .ok_or_else(|| FontError::malformed("CFF ", "Failed to read FDArray"))?;
+        private_range = dict::entries(font_dict, None)
+            .flatten()
+            .find_map(|entry| match entry {
+                dict::Entry::PrivateDictRange(r) => Some(r),
+                _ => None,
+            });
+    }
+
+    let mut local_subrs = Index::Empty;
+    let mut private_dict: &[u8] = &[];
+    if let Some(range) = private_range {
+        let bytes = data.as_bytes();
+        private_dict = bytes
+            .get(range.clone())
+            .ok_or_else(|| FontError::malformed("CFF ", "Private DICT out of bounds"))?;
+        let subrs_offset =
+            dict::entries(private_dict, None)
+                .flatten()
+                .find_map(|entry| match entry {
+                    dict::Entry::SubrsOffset(o) => Some(o),
+                    _ => None,
+                });
+        if let Some(offset) = subrs_offset {
+            let subrs = bytes
+                .get(range.start + offset..)
+                .ok_or_else(|| FontError::malformed("CFF ", "Local Subrs out of bounds"))?;
+            local_subrs = Index::new(subrs, false).map_err(|e| {
+                FontError::malformed("CFF ", format!("Failed to read local Subrs: {:?}", e))
+            })?;
+        }
+    }
+
+    Ok((charstring, private_dict, local_subrs))
+}
+
+/// The glyph's own charstring, without the subroutines it calls.
+pub(crate) fn glyph_charstring<'a>(cff: &Cff<'a>, glyph_id: u32) -> Result<&'a [u8], FontError> {
+    charstring_and_local_subrs(cff, glyph_id).map(|(charstring, _, _)| charstring)
+}
+
+/// The glyph's own CFF2 charstring, found through the top DICT's CharStrings.
+pub(crate) fn glyph_charstring2<'a>(cff2: &Cff2<'a>, glyph_id: u32) -> Result<&'a [u8], FontError> {
+    let offset = dict::entries(cff2.top_dict_data(), None)
+        .flatten()
+        .find_map(|entry| match entry {
+            dict::Entry::CharstringsOffset(o) => Some(o),
+            _ => None,
+        })
+        .ok_or_else(|| FontError::malformed("CFF2", "CFF2 top DICT has no CharStrings"))?;
+    let charstrings = cff2
+        .offset_data()
+        .split_off(offset)
+        .ok_or_else(|| FontError::malformed("CFF2", "CharStrings offset out of bounds"))
+        .and_then(|d| {
+            Index2::read(d).map_err(|e| {
+                FontError::malformed("CFF2", format!("Failed to read CharStrings: {:?}", e))
+            })
+        })?;
+    charstrings
+        .get(glyph_id as usize)
+        .map_err(|_| FontError::GlyphNotFound { gid: glyph_id })
+}
+
+pub fn get_cff_charstring(
+    file_path: &str,
+    glyph_id: u32,
+    expand_subrs: bool,
+    cache: &FontCache,
+) -> Result<Vec<CharstringOp>, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let cff = font.cff().map_err(|e| FontError::table("CFF ", e))?;
+    let (charstring, _, local_subrs) = charstring_and_local_subrs(&cff, glyph_id)?;
+    let global_subrs = Index::from(cff.global_subrs());
+    decompile_charstring(charstring, &global_subrs, &local_subrs, expand_subrs)
+}
+
+// ── Writing ──────────────────────────────────────────────────────────────────
+
+// Top / Font / Private DICT operators that hold offsets (CFF spec, Table 9-10)
+const OP_CHARSET: u16 = 15;
+const OP_ENCODING: u16 = 16;
+const OP_CHARSTRINGS: u16 = 17;
+const OP_PRIVATE: u16 = 18;
+const OP_SUBRS: u16 = 19;
+const OP_FD_ARRAY: u16 = 0x0C24;
+const OP_FD_SELECT: u16 = 0x0C25;
+
+/// Append a Type 2 charstring integer operand, clamped to the 16-bit range.
+fn push_charstring_int(buf: &mut Vec<u8>, value: i32) {
+    match value {
+        -107..=107 => buf.push((value + 139) as u8),
+        108..=1131 => {
+            let v = value - 108;
+            buf.extend([(v >> 8) as u8 + 247, v as u8]);
+        }
+        -1131..=-108 => {
+            let v = -value - 108;
+            buf.extend([(v >> 8) as u8 + 251, v as u8]);
+        }
+        _ => {
+            buf.push(28);
+            buf.extend((value.clamp(i16::MIN as i32, i16::MAX as i32) as i16).to_be_bytes());
+        }
+    }
+}
+
+/// Append the operands moving `current` to `point`.
+fn push_delta(cs: &mut Vec<u8>, current: &mut (i32, i32), point: (i32, i32)) {
+    push_charstring_int(cs, point.0 - current.0);
+    push_charstring_int(cs, point.1 - current.1);
+    *current = point;
+}
+
+/// Encode outline commands (font units, Y-up) as an unhinted Type 2
+/// charstring. Quadratic segments are promoted to cubics; contours are closed
+/// implicitly. `width` is the optional leading advance-width operand.
+fn build_charstring(cmds: &[SvgCmd], width: Option<i32>) -> Vec<u8> {
+    const RLINETO: u8 = 5;
+    const RRCURVETO: u8 = 8;
+    const ENDCHAR: u8 = 14;
+    const RMOVETO: u8 = 21;
+
+    let mut cs = Vec::new();
+    if let Some(width) = width {
+        push_charstring_int(&mut cs, width);
+    }
+    let round = |v: f32| v.round() as i32;
+    let mut current = (0, 0);
+    for cmd in cmds {
+        match *cmd {
+            SvgCmd::MoveTo(x, y) => {
+                push_delta(&mut cs, &mut current, (round(x), round(y)));
+                cs.push(RMOVETO);
+            }
+            SvgCmd::LineTo(x, y) => {
+                push_delta(&mut cs, &mut current, (round(x), round(y)));
+                cs.push(RLINETO);
+            }
+            SvgCmd::QuadTo(qx, qy, x, y) => {
+                let (sx, sy) = (current.0 as f32, current.1 as f32);
+                let (ex, ey) = (round(x), round(y));
+                let c1 = (sx + (qx - sx) * 2.0 / 3.0, sy + (qy - sy) * 2.0 / 3.0);
+                let c2 = (
+                    ex as f32 + (qx - ex as f32) * 2.0 / 3.0,
+                    ey as f32 + (qy - ey as f32) * 2.0 / 3.0,
+                );
+                push_delta(&mut cs, &mut current, (round(c1.0), round(c1.1)));
+                push_delta(&mut cs, &mut current, (round(c2.0), round(c2.1)));
+                push_delta(&mut cs, &mut current, (ex, ey));
+                cs.push(RRCURVETO);
+            }
+            SvgCmd::CurveTo(x1, y1, x2, y2, x, y) => {
+                push_delta(&mut cs, &mut current, (round(x1), round(y1)));
+                push_delta(&mut cs, &mut current, (round(x2), round(y2)));
+                push_delta(&mut cs, &mut current, (round(x), round(y)));
+                cs.push(RRCURVETO);
+            }
+            SvgCmd::Close => {}
+        }
+    }
+    cs.push(ENDCHAR);
+    cs
+}
+
+/// One DICT entry: the raw encoding of each operand, then the operator
+/// (escaped operators as 0x0C00 | b1).
+struct DictEntry {
+    operands: Vec<Vec<u8>>,
+    op: u16,
+}
+
+fn parse_dict(data: &[u8]) -> Result<Vec<DictEntry>, FontError> {
+    let mut entries = Vec::new();
+    let mut operands = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let b0 = data[pos];
+        let len = match b0 {
+            0..=21 => {
+                let op = if b0 == 12 {
+                    pos += 1;
+                    0x0C00
+                        | *data.get(pos).ok_or_else(|| {
+                            FontError::malformed("CFF ", "Truncated DICT operator")
+                        })? as u16
+                } else {
+                    b0 as u16
+                };
+                pos += 1;
+                entries.push(DictEntry {
+                    operands: std::mem::take(&mut operands),
+                    op,
+                });
+                continue;
+            }
+            28 => 3,
+            29 => 5,
+            30 => {
+                // Real number: nibbles up to and including an 0xF terminator
+                let end = data[pos + 1..]
+                    .iter()
+                    .position(|b| b >> 4 == 0xF || b & 0xF == 0xF)
+                    .ok_or_else(|| {
+                        FontError::malformed("CFF ", "Unterminated real number in DICT")
+                    })?;
+                end + 2
+            }
+            32..=246 => 1,
+            247..=254 => 2,
+            _ => {
+                return Err(FontError::malformed(
+                    "CFF ",
+                    format!("Invalid DICT byte {}", b0),
+                ))
+            }
+        };
+        let operand = data
+            .get(pos..pos + len)
+            .ok_or_else(|| FontError::malformed("CFF ", "Truncated DICT operand"))?;
+        operands.push(operand.to_vec());
+        pos += len;
+    }
+    Ok(entries)
+}
+
+fn dict_int(operand: &[u8]) -> Option<i32> {
+    Some(match *operand {
+        [b0 @ 32..=246] => b0 as i32 - 139,
+        [b0 @ 247..=250, b1] => (b0 as i32 - 247) * 256 + b1 as i32 + 108,
+        [b0 @ 251..=254, b1] => -(b0 as i32 - 251) * 256 - b1 as i32 - 108,
+        [28, a, b] => i16::from_be_bytes([a, b]) as i32,
+        [29, a, b, c, d] => i32::from_be_bytes([a, b, c, d]),
+        _ => return None,
+    })
+}
+
+fn encode_dict(entries: &[DictEntry]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for entry in entries {
+        for operand in &entry.operands {
+            out.extend_from_slice(operand);
+        }
+        if entry.op > 0xFF {
+            out.extend([12, entry.op as u8]);
+        } else {
+            out.push(entry.op as u8);
+        }
+    }
+    out
+}
+
+/// The integer operands of `op`, if present.
+fn dict_ints(entries: &[DictEntry], op: u16) -> Option<Vec<usize>> {
+    let entry = entries.iter().find(|e| e.op == op)?;
+    entry
+        .operands
+        .iter()
+        .map(|o| dict_int(o).and_then(|v| usize::try_from(v).ok()))
+        .collect()
+}
+
+/// Replace the operands of `op` with 5-byte integers, so the DICT's length no
+/// longer depends on the values and offsets can be filled in after layout.
+fn set_dict_ints(entries: &mut [DictEntry], op: u16, values: &[usize]) {
+    if let Some(entry) = entries.iter_mut().find(|e| e.op == op) {
+        entry.operands = values
+            .iter()
+            .map(|&v| {
+                let mut operand = vec![29];
+                operand.extend((v as i32).to_be_bytes());
+                operand
+            })
+            .collect();
+    }
+}
+
+fn read_u16_at(data: &[u8], pos: usize) -> Result<usize, FontError> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+        .ok_or_else(|| FontError::malformed("CFF ", "CFF data out of bounds"))
+}
+
+/// The items of the INDEX at `pos`, and the INDEX's total length.
+fn read_index(data: &[u8], pos: usize) -> Result<(Vec<&[u8]>, usize), FontError> {
+    let count = read_u16_at(data, pos)?;
+    if count == 0 {
+        return Ok((Vec::new(), 2));
+    }
+    let off_size = *data
+        .get(pos + 2)
+        .ok_or_else(|| FontError::malformed("CFF ", "CFF data out of bounds"))?
+        as usize;
+    if !(1..=4).contains(&off_size) {
+        return Err(FontError::malformed(
+            "CFF ",
+            format!("Invalid INDEX offset size {}", off_size),
+        ));
+    }
+    let offsets_start = pos + 3;
+    let data_start = offsets_start + (count + 1) * off_size - 1;
+    let offset = |i: usize| -> Result<usize, FontError> {
+        let start = offsets_start + i * off_size;
+        let bytes = data
+            .get(start..start + off_size)
+            .ok_or_else(|| FontError::malformed("CFF ", "CFF data out of bounds"))?;
+        Ok(bytes.iter().fold(0, |acc, &b| (acc << 8) | b as usize))
+    };
+    let mut items = Vec::with_capacity(count);
+    for i in 0..count {
+        let (start, end) = (offset(i)?, offset(i + 1)?);
+        let item = data
+            .get(data_start + start..data_start + end)
+            .ok_or_else(|| FontError::malformed("CFF ", "INDEX item out of bounds"))?;
+        items.push(item);
+    }
+    Ok((items, data_start + offset(count)? - pos))
+}
+
+fn build_index(items: &[&[u8]]) -> Vec<u8> {
+    let mut out = (items.len() as u16).to_be_bytes().to_vec();
+    if items.is_empty() {
+        return out;
+    }
+    let last = 1 + items.iter().map(|i| i.len()).sum::<usize>();
+    let off_size = match last {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x1_0000..=0xFF_FFFF => 3,
+        _ => 4,
+    };
+    out.push(off_size as u8);
+    let mut offset = 1;
+    out.extend(&(offset as u32).to_be_bytes()[4 - off_size..]);
+    for item in items {
+        offset += item.len();
+        out.extend(&(offset as u32).to_be_bytes()[4 - off_size..]);
+    }
+    for item in items {
+        out.extend_from_slice(item);
+    }
+    out
+}
+
+fn byte_at(data: &[u8], pos: usize) -> Result<usize, FontError> {
+    data.get(pos)
+        .map(|&b| b as usize)
+        .ok_or_else(|| FontError::malformed("CFF ", "CFF data out of bounds"))
+}
+
+/// Length of a custom charset (formats 0-2) covering `num_glyphs` glyphs.
+fn charset_len(data: &[u8], pos: usize, num_glyphs: usize) -> Result<usize, FontError> {
+    let format = byte_at(data, pos)?;
+    if format == 0 {
+        return Ok(1 + 2 * num_glyphs.saturating_sub(1));
+    }
+    let count_size = match format {
+        1 => 1,
+        2 => 2,
+        _ => {
+            return Err(FontError::malformed(
+                "CFF ",
+                format!("Unknown charset format {}", format),
+            ))
+        }
+    };
+    let (mut p, mut covered) = (pos + 1, 0);
+    while covered < num_glyphs.saturating_sub(1) {
+        let n_left = if count_size == 1 {
+            byte_at(data, p + 2)?
+        } else {
+            read_u16_at(data, p + 2)?
+        };
+        covered += n_left + 1;
+        p += 2 + count_size;
+    }
+    Ok(p - pos)
+}
+
+/// Length of a custom encoding (formats 0-1, with optional supplements).
+fn encoding_len(data: &[u8], pos: usize) -> Result<usize, FontError> {
+    let format = byte_at(data, pos)?;
+    let count = byte_at(data, pos + 1)?;
+    let mut len = match format & 0x7F {
+        0 => 2 + count,
+        1 => 2 + 2 * count,
+        f => {
+            return Err(FontError::malformed(
+                "CFF ",
+                format!("Unknown encoding format {}", f),
+            ))
+        }
+    };
+    if format & 0x80 != 0 {
+        len += 1 + 3 * byte_at(data, pos + len)?;
+    }
+    Ok(len)
+}
+
+fn fd_select_len(data: &[u8], pos: usize, num_glyphs: usize) -> Result<usize, FontError> {
+    match byte_at(data, pos)? {
+        0 => Ok(1 + num_glyphs),
+        3 => Ok(1 + 2 + 3 * read_u16_at(data, pos + 1)? + 2),
+        f => Err(FontError::malformed(
+            "CFF ",
+            format!("Unknown FDSelect format {}", f),
+        )),
+    }
+}
+
+fn slice_at(data: &[u8], pos: usize, len: usize) -> Result<&[u8], FontError> {
+    data.get(pos..pos + len)
+        .ok_or_else(|| FontError::malformed("CFF ", "CFF data out of bounds"))
+}
+
+/// A Private DICT (re-encoded with a placeholder Subrs offset) plus its local
+/// subrs, laid out back to back.
+struct PrivateBlob<'a> {
+    original_offset: usize,
+    dict: Vec<DictEntry>,
+    subrs: Option<&'a [u8]>,
+}
+
+impl PrivateBlob<'_> {
+    fn encode(&mut self) -> Vec<u8> {
+        // Subrs are placed right after the DICT; the offset is relative to it
+        set_dict_ints(&mut self.dict, OP_SUBRS, &[0]);
+        let dict_len = encode_dict(&self.dict).len();
+        set_dict_ints(&mut self.dict, OP_SUBRS, &[dict_len]);
+        encode_dict(&self.dict)
+    }
+}
+
+/// Where the offset-referenced structures of a rebuilt CFF table start.
+#[derive(Default)]
+struct CffLayout {
+    charset: Option<usize>,
+    encoding: Option<usize>,
+    fd_select: Option<usize>,
+    charstrings: usize,
+    fd_array: Option<usize>,
+    privates: Vec<usize>,
+}
+
+/// Rebuild a (single-font) CFF table with glyph `glyph_id`'s charstring
+/// replaced. Everything else is copied verbatim; every offset is recomputed.
+fn replace_charstring(
+    table: &[u8],
+    glyph_id: u32,
+    charstring: &[u8],
+) -> Result<Vec<u8>, FontError> {
+    let header_size = byte_at(table, 2)?;
+    let (_, name_len) = read_index(table, header_size)?;
+    let top_start = header_size + name_len;
+    let (top_dicts, top_len) = read_index(table, top_start)?;
+    let [top_dict] = top_dicts[..] else {
+        return Err(FontError::unsupported("CFF tables with several fonts"));
+    };
+    // String INDEX and Global Subrs are copied as one block
+    let strings_start = top_start + top_len;
+    let (_, strings_len) = read_index(table, strings_start)?;
+    let (_, gsubrs_len) = read_index(table, strings_start + strings_len)?;
+    let shared = slice_at(table, strings_start, strings_len + gsubrs_len)?;
+
+    let mut top = parse_dict(top_dict)?;
+    let offset_of = |dict: &[DictEntry], op| dict_ints(dict, op).and_then(|v| v.last().copied());
+
+    let charstrings_offset = offset_of(&top, OP_CHARSTRINGS)
+        .ok_or_else(|| FontError::malformed("CFF ", "CFF top DICT has no CharStrings"))?;
+    let (mut charstrings, _) = read_index(table, charstrings_offset)?;
+    let num_glyphs = charstrings.len();
+    *charstrings
+        .get_mut(glyph_id as usize)
+        .ok_or(FontError::GlyphNotFound { gid: glyph_id })? = charstring;
+
+    // Offsets 0-2 (charset) and 0-1 (encoding) name predefined tables
+    let charset = match offset_of(&top, OP_CHARSET) {
+        Some(o) if o > 2 => Some(slice_at(table, o, charset_len(table, o, num_glyphs)?)?),
+        _ => None,
+    };
+    let encoding = match offset_of(&top, OP_ENCODING) {
+        Some(o) if o > 1 => Some(slice_at(table, o, encoding_len(table, o)?)?),
+        _ => None,
+    };
+    let fd_select = match offset_of(&top, OP_FD_SELECT) {
+        Some(o) => Some(slice_at(table, o, fd_select_len(table, o, num_glyphs)?)?),
+        None => None,
+    };
+    let mut font_dicts = match offset_of(&top, OP_FD_ARRAY) {
+        Some(o) => read_index(table, o)?
+            .0
+            .into_iter()
+            .map(parse_dict)
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+
+    // Private DICTs belong to the Top DICT (name-keyed) or to each Font DICT
+    // (CID-keyed); Font DICTs sharing one Private DICT keep sharing it
+    let mut privates: Vec<PrivateBlob> = Vec::new();
+    let mut owner_private = Vec::new();
+    let owners = if font_dicts.is_empty() {
+        std::slice::from_ref(&top)
+    } else {
+        &font_dicts[..]
+    };
+    for dict in owners {
+        let Some(&[size, offset]) = dict_ints(dict, OP_PRIVATE).as_deref() else {
+            owner_private.push(None);
+            continue;
+        };
+        if let Some(i) = privates.iter().position(|p| p.original_offset == offset) {
+            owner_private.push(Some(i));
+            continue;
+        }
+        let dict = parse_dict(slice_at(table, offset, size)?)?;
+        let subrs = match offset_of(&dict, OP_SUBRS) {
+            Some(rel) => {
+                let (_, len) = read_index(table, offset + rel)?;
+                Some(slice_at(table, offset + rel, len)?)
+            }
+            None => None,
+        };
+        privates.push(PrivateBlob {
+            original_offset: offset,
+            dict,
+            subrs,
+        });
+        owner_private.push(Some(privates.len() - 1));
+    }
+    let private_bytes: Vec<Vec<u8>> = privates.iter_mut().map(|p| p.encode()).collect();
+
+    // All offsets are written as fixed 5-byte operands, so DICT lengths do not
+    // depend on the layout: apply a placeholder layout to measure, then the real one
+    let apply = |top: &mut Vec<DictEntry>, font_dicts: &mut [Vec<DictEntry>], at: &CffLayout| {
+        let targets = [
+            (OP_CHARSET, at.charset),
+            (OP_ENCODING, at.encoding),
+            (OP_FD_SELECT, at.fd_select),
+            (OP_CHARSTRINGS, Some(at.charstrings)),
+            (OP_FD_ARRAY, at.fd_array),
+        ];
+        for (op, offset) in targets {
+            if let Some(offset) = offset {
+                set_dict_ints(top, op, &[offset]);
+            }
+        }
+        let owners = if font_dicts.is_empty() {
+            std::slice::from_mut(top)
+        } else {
+            font_dicts
+        };
+        for (dict, private) in owners.iter_mut().zip(&owner_private) {
+            if let Some(i) = *private {
+                let offset = at.privates.get(i).copied().unwrap_or(0);
+                set_dict_ints(dict, OP_PRIVATE, &[private_bytes[i].len(), offset]);
+            }
+        }
+    };
+    let fd_array_index = |font_dicts: &[Vec<DictEntry>]| {
+        let encoded: Vec<Vec<u8>> = font_dicts.iter().map(|d| encode_dict(d)).collect();
+        build_index(&encoded.iter().map(Vec::as_slice).collect::<Vec<_>>())
+    };
+
+    let mut layout = CffLayout {
+        charset: charset.map(|_| 0),
+        encoding: encoding.map(|_| 0),
+        fd_select: fd_select.map(|_| 0),
+        fd_array: (!font_dicts.is_empty()).then_some(0),
+        ..Default::default()
+    };
+    apply(&mut top, &mut font_dicts, &layout);
+
+    // header, Name, Top DICT, Strings + GSubrs, then the offset-referenced data
+    let charstrings_index = build_index(&charstrings);
+    let mut pos = top_start + build_index(&[&encode_dict(&top)]).len() + shared.len();
+    let mut place = |len: usize| {
+        let at = pos;
+        pos += len;
+        at
+    };
+    layout.charset = charset.map(|c| place(c.len()));
+    layout.encoding = encoding.map(|e| place(e.len()));
+    layout.fd_select = fd_select.map(|f| place(f.len()));
+    layout.charstrings = place(charstrings_index.len());
+    if layout.fd_array.is_some() {
+        layout.fd_array = Some(place(fd_array_index(&font_dicts).len()));
+    }
+    layout.privates = privates
+        .iter()
+        .zip(&private_bytes)
+        .map(|(p, bytes)| place(bytes.len() + p.subrs.map_or(0, <[u8]>::len)))
+        .collect();
+    apply(&mut top, &mut font_dicts, &layout);
+
+    let mut out = table[..top_start].to_vec();
+    out.extend(build_index(&[&encode_dict(&top)]));
+    out.extend_from_slice(shared);
+    for blob in [charset, encoding, fd_select].into_iter().flatten() {
+        out.extend_from_slice(blob);
+    }
+    out.extend(charstrings_index);
+    if layout.fd_array.is_some() {
+        out.extend(fd_array_index(&font_dicts));
+    }
+    for (p, bytes) in privates.iter().zip(private_bytes) {
+        out.extend(bytes);
+        out.extend_from_slice(p.subrs.unwrap_or_default());
+    }
+    Ok(out)
+}
+
+/// Re-encode glyph `glyph_id` from edited outline commands (font units, Y-up)
+/// and rebuild the CFF table around it. The glyph's hints are dropped; the
+/// returned warnings say so when it had any.
+pub(crate) fn rebuild_cff_with_glyph(
+    font: &RawFontRef<'_>,
+    glyph_id: u32,
+    cmds: &[SvgCmd],
+) -> Result<(Vec<u8>, Vec<String>), FontError> {
+    let cff = font.cff().map_err(|e| FontError::table("CFF ", e))?;
+    let (old, private_dict, local_subrs) =
+        charstring_and_local_subrs(&cff, glyph_id).map_err(|e| match e {
+            FontError::GlyphNotFound { .. } => FontError::unsupported("adding glyphs to CFF fonts"),
+            e => e,
+        })?;
+
+    let global_subrs = Index::from(cff.global_subrs());
+    let had_hints = decompile_charstring(old, &global_subrs, &local_subrs, true)
+        .map(|ops| {
+            ops.iter().any(|op| {
+                matches!(
+                    op.op.as_str(),
+                    "hstem" | "vstem" | "hstemhm" | "vstemhm" | "hintmask" | "cntrmask"
+                )
+            })
+        })
+        .unwrap_or(false);
+
+    // Keep the advance in hmtx; it is written only when it differs from defaultWidthX
+    let (mut default_width, mut nominal_width) = (0, 0);
+    for entry in dict::entries(private_dict, None).flatten() {
+        match entry {
+            dict::Entry::DefaultWidthX(w) => default_width = w.to_f64().round() as i32,
+            dict::Entry::NominalWidthX(w) => nominal_width = w.to_f64().round() as i32,
+            _ => {}
+        }
+    }
+    let advance = font
+        .hmtx()
+        .ok()
+        .and_then(|hmtx| hmtx.advance(GlyphId::new(glyph_id)))
+        .map_or(default_width, |a| a as i32);
+    let width = (advance != default_width).then_some(advance - nominal_width);
+
+    let charstring = build_charstring(cmds, width);
+    let table = replace_charstring(cff.offset_data().as_bytes(), glyph_id, &charstring)?;
+    let mut warnings = Vec::new();
+    if had_hints {
+        warnings.push(format!("Hints for glyph {} were dropped", glyph_id));
+    }
+    Ok((table, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{cff_index as index, cff_table};
+
+    fn int(v: i16) -> Vec<u8> {
+        let mut b = vec![28];
+        b.extend(v.to_be_bytes());
+        b
+    }
+
+    #[test]
+    fn test_decompile_width_stems_and_hintmask() {
+        // width 500, hstem 0 20, implicit vstem 10 30 on hintmask, rmoveto, endchar
+        let mut cs = Vec::new();
+        for v in [500, 0, 20] {
+            cs.extend(int(v));
+        }
+        cs.push(1);
+        cs.extend(int(10));
+        cs.extend(int(30));
+        cs.extend([19, 0b1010_0000]);
+        cs.extend([139 + 5, 139 + 7, 21, 14]);
+
+        let ops = decompile_charstring(&cs, &Index::Empty, &Index::Empty, false).unwrap();
+        let names: Vec<&str> = ops.iter().map(|o| o.op.as_str()).collect();
+        assert_eq!(names, ["width", "hstem", "hintmask", "rmoveto", "endchar"]);
+        assert_eq!(ops[0].operands, [500.0]);
+        assert_eq!(ops[1].operands, [0.0, 20.0]);
+        assert_eq!(ops[2].operands, [10.0, 30.0]);
+        assert_eq!(ops[2].mask.as_deref(), Some("10"));
+        assert_eq!(ops[3].operands, [5.0, 7.0]);
+    }
+
+    #[test]
+    fn test_decompile_subr_bias_and_expansion() {
+        // Local subr 0 draws a line and returns; bias is 107 for small INDEXes
+        let subr: &[u8] = &[139 + 1, 139 + 2, 5, 11];
+        let local_bytes = index(&[subr]);
+        let local = Index::new(&local_bytes, false).unwrap();
+        let mut cs = vec![139, 139, 21];
+        cs.extend(int(-107));
+        cs.extend([10, 14]);
+
+        let annotated = decompile_charstring(&cs, &Index::Empty, &local, false).unwrap();
+        let names: Vec<&str> = annotated.iter().map(|o| o.op.as_str()).collect();
+        assert_eq!(names, ["rmoveto", "callsubr", "endchar"]);
+        assert_eq!(annotated[1].subr_index, Some(0));
+        assert_eq!(annotated[1].operands, [-107.0]);
+
+        let expanded = decompile_charstring(&cs, &Index::Empty, &local, true).unwrap();
+        let names: Vec<(&str, u8)> = expanded.iter().map(|o| (o.op.as_str(), o.depth)).collect();
+        assert_eq!(
+            names,
+            [
+                ("rmoveto", 0),
+                ("callsubr", 0),
+                ("rlineto", 1),
+                ("return", 1),
+                ("endchar", 0)
+            ]
+        );
+        assert_eq!(expanded[2].operands, [1.0, 2.0]);
+    }
+
+    /// Two glyphs: 0 calls local subr 0, 1 has an hstem.
+    fn two_glyph_cff() -> Vec<u8> {
+        let subr: &[u8] = &[139 + 1, 139 + 2, 5, 11];
+        let mut glyph0 = vec![139, 139, 21];
+        glyph0.extend(int(-107));
+        glyph0.extend([10, 14]);
+        let mut glyph1 = int(0);
+        glyph1.extend(int(20));
+        glyph1.extend([1, 139 + 10, 139 + 10, 21, 14]);
+        cff_table(&[&glyph0, &glyph1], &[subr])
+    }
+
+    fn decompile_glyph(table: &[u8], glyph_id: u32) -> Vec<CharstringOp> {
+        let cff = Cff::read(skrifa::raw::FontData::new(table)).unwrap();
+        let (cs, _, local) = charstring_and_local_subrs(&cff, glyph_id).unwrap();
+        decompile_charstring(cs, &Index::from(cff.global_subrs()), &local, true).unwrap()
+    }
+
+    #[test]
+    fn test_rebuild_cff_with_edited_glyph() {
+        use crate::test_fonts::TestFontBuilder;
+
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(Vec::new(), 600)
+            .table(b"CFF ", two_glyph_cff())
+            .build();
+        let font = RawFontRef::new(&font).unwrap();
+        let cmds = [
+            SvgCmd::MoveTo(10.0, 0.0),
+            SvgCmd::LineTo(10.0, 100.0),
+            SvgCmd::QuadTo(40.0, 130.0, 70.0, 100.0),
+            SvgCmd::Close,
+        ];
+
+        let (table, warnings) = rebuild_cff_with_glyph(&font, 1, &cmds).unwrap();
+        assert_eq!(warnings, ["Hints for glyph 1 were dropped"]);
+
+        // Advance 600 differs from defaultWidthX, so width = 600 - nominalWidthX
+        let ops = decompile_glyph(&table, 1);
+        let ops: Vec<(&str, Vec<f64>)> = ops
+            .iter()
+            .map(|o| (o.op.as_str(), o.operands.clone()))
+            .collect();
+        assert_eq!(
+            ops,
+            [
+                ("width", vec![500.0]),
+                ("rmoveto", vec![10.0, 0.0]),
+                ("rlineto", vec![0.0, 100.0]),
+                ("rrcurveto", vec![20.0, 20.0, 20.0, 0.0, 20.0, -20.0]),
+                ("endchar", vec![]),
+            ]
+        );
+
+        // Glyph 0 and its relocated local subr are untouched
+        let names: Vec<String> = decompile_glyph(&table, 0)
+            .into_iter()
+            .map(|o| o.op)
+            .collect();
+        assert_eq!(
+            names,
+            ["rmoveto", "callsubr", "rlineto", "return", "endchar"]
+        );
+
+        assert!(rebuild_cff_with_glyph(&font, 0, &cmds)
+            .unwrap()
+            .1
+            .is_empty());
+        assert!(rebuild_cff_with_glyph(&font, 2, &cmds).is_err());
+    }
+}
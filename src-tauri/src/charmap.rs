@@ -0,0 +1,224 @@
+use crate::error::FontError;
+use crate::font_parser::{
+    extract_glyph_outlines, get_font_bytes, write_atomically, CancelToken, FontCache,
+};
+use serde::{Deserialize, Serialize};
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CharacterMapFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct CharacterMapOptions {
+    /// Follow the mapped rows with the glyphs no codepoint reaches.
+    pub include_unmapped: bool,
+}
+
+/// One row of the export. Unmapped glyphs have no codepoint or character.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct CharacterMapRow {
+    /// Uppercase hex, at least four digits, without a `U+` prefix.
+    codepoint: Option<String>,
+    /// The character itself, unless it is a control or invisible character.
+    character: Option<String>,
+    glyph_id: u32,
+    glyph_name: String,
+    advance_width: u16,
+    has_outline: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct CharacterMapDocument {
+    mapped: Vec<CharacterMapRow>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unmapped: Option<Vec<CharacterMapRow>>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharacterMapSummary {
+    pub codepoints: usize,
+    /// Unmapped glyphs written; 0 unless asked for.
+    pub unmapped_glyphs: usize,
+}
+
+fn printable(codepoint: u32) -> Option<String> {
+    let ch = char::from_u32(codepoint)?;
+    (ch == ' ' || !(ch.is_control() || ch.is_whitespace())).then(|| ch.to_string())
+}
+
+/// A CSV field, quoted when it holds a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(document: &CharacterMapDocument) -> String {
+    let mut csv =
+        String::from("codepoint,character,glyph_id,glyph_name,advance_width,has_outline\n");
+    let rows = document
+        .mapped
+        .iter()
+        .chain(document.unmapped.iter().flatten());
+    for row in rows {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{}",
+            row.codepoint.as_deref().unwrap_or(""),
+            csv_field(row.character.as_deref().unwrap_or("")),
+            row.glyph_id,
+            csv_field(&row.glyph_name),
+            row.advance_width,
+            row.has_outline
+        );
+    }
+    csv
+}
+
+/// Write the cmap of `file_path` to `destination`, one row per codepoint in
+/// codepoint order. Codepoints and advances come from the cached derived
+/// data, outlines from the glyph grid's cache when it is complete.
+pub fn export_character_map(
+    file_path: &str,
+    destination: &str,
+    format: CharacterMapFormat,
+    options: &CharacterMapOptions,
+    cache: &FontCache,
+) -> Result<CharacterMapSummary, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let derived = cache.derived_for(file_path, &bytes)?;
+    let font = FontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let names = font.glyph_names();
+    let metrics = derived.metrics()?;
+    let drawn: HashSet<u32> = match cache.cached_bounds(file_path) {
+        Some(bounds) => bounds.into_iter().map(|(gid, _)| gid).collect(),
+        None => extract_glyph_outlines(&bytes, &|_, _| {}, &CancelToken::default())?
+            .into_iter()
+            .filter(|o| o.bounds.is_some())
+            .map(|o| o.glyph_id)
+            .collect(),
+    };
+
+    let row = |codepoint: Option<u32>, glyph_id: u32| CharacterMapRow {
+        codepoint: codepoint.map(|cp| format!("{:04X}", cp)),
+        character: codepoint.and_then(printable),
+        glyph_id,
+        glyph_name: names
+            .get(GlyphId::new(glyph_id))
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| format!("gid{}", glyph_id)),
+        advance_width: metrics
+            .get(glyph_id as usize)
+            .map_or(0, |&(advance, _)| advance),
+        has_outline: drawn.contains(&glyph_id),
+    };
+
+    let mut mappings: Vec<(u32, u32)> = (0..derived.num_glyphs)
+        .flat_map(|gid| derived.codepoints(gid).iter().map(move |&cp| (cp, gid)))
+        .collect();
+    mappings.sort_unstable();
+    let mapped: Vec<CharacterMapRow> = mappings
+        .into_iter()
+        .map(|(cp, gid)| row(Some(cp), gid))
+        .collect();
+    let unmapped = options.include_unmapped.then(|| {
+        (0..derived.num_glyphs)
+            .filter(|&gid| derived.codepoints(gid).is_empty())
+            .map(|gid| row(None, gid))
+            .collect::<Vec<_>>()
+    });
+    let document = CharacterMapDocument { mapped, unmapped };
+
+    let text = match format {
+        CharacterMapFormat::Csv => to_csv(&document),
+        CharacterMapFormat::Json => serde_json::to_string_pretty(&document)
+            .map_err(|e| FontError::other(format!("Failed to serialize character map: {}", e)))?,
+    };
+    write_atomically(std::path::Path::new(destination), text.as_bytes())?;
+    Ok(CharacterMapSummary {
+        codepoints: document.mapped.len(),
+        unmapped_glyphs: document.unmapped.map_or(0, |rows| rows.len()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{simple_glyph, TestFontBuilder};
+
+    #[test]
+    fn test_export_character_map_csv_and_json() {
+        let dir = std::env::temp_dir().join(format!("typebrew-charmap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(bar.clone(), 600)
+            .glyph(Vec::new(), 250)
+            .glyph(bar, 700)
+            .map('a' as u32, 1)
+            .map('A' as u32, 1)
+            .map(',' as u32, 2)
+            .map(0x09, 2)
+            .build();
+        let cache = FontCache::new();
+        cache.insert("font.ttf".to_string(), font);
+
+        let csv_path = dir.join("map.csv");
+        let summary = export_character_map(
+            "font.ttf",
+            csv_path.to_str().unwrap(),
+            CharacterMapFormat::Csv,
+            &CharacterMapOptions {
+                include_unmapped: true,
+            },
+            &cache,
+        )
+        .unwrap();
+        assert_eq!(
+            summary,
+            CharacterMapSummary {
+                codepoints: 4,
+                unmapped_glyphs: 2,
+            }
+        );
+        assert_eq!(
+            std::fs::read_to_string(&csv_path).unwrap(),
+            "\
+codepoint,character,glyph_id,glyph_name,advance_width,has_outline
+0009,,2,gid2,250,false
+002C,\",\",2,gid2,250,false
+0041,A,1,gid1,600,true
+0061,a,1,gid1,600,true
+,,0,gid0,500,false
+,,3,gid3,700,true
+"
+        );
+
+        let json_path = dir.join("map.json");
+        export_character_map(
+            "font.ttf",
+            json_path.to_str().unwrap(),
+            CharacterMapFormat::Json,
+            &CharacterMapOptions::default(),
+            &cache,
+        )
+        .unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(json["mapped"].as_array().unwrap().len(), 4);
+        assert_eq!(json["mapped"][2]["character"], "A");
+        assert_eq!(json["mapped"][0]["character"], serde_json::Value::Null);
+        assert!(json.get("unmapped").is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
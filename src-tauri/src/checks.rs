@@ -0,0 +1,1009 @@
+use crate::error::FontError;
+use crate::font_parser::{
+    count_glyphs_with_instructions, get_font_bytes, parse_loca_offsets, FontCache, GlyphBounds,
+    SvgPathPen,
+};
+use crate::merge::read_metrics;
+use crate::metrics::font_bounds;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use skrifa::instance::{LocationRef, Size};
+use skrifa::outline::DrawSettings;
+use skrifa::raw::types::{GlyphId16, Tag};
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::string::StringId;
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::{BTreeMap, HashMap};
+
+/// Advances wider than this many ems are almost always a mistake.
+const MAX_ADVANCE_EMS: u32 = 3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckProfile {
+    /// Pre-flight for fonts served on the web or handed to a font service.
+    Webfont,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CheckResult {
+    /// Stable identifier, e.g. "whitespace_widths".
+    pub id: String,
+    pub status: CheckStatus,
+    pub message: String,
+    /// The measured values behind the verdict.
+    pub values: BTreeMap<String, i64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CheckReport {
+    pub profile: CheckProfile,
+    pub checks: Vec<CheckResult>,
+    pub passed: usize,
+    pub warnings: usize,
+    pub failures: usize,
+}
+
+/// What the checks look at, read once.
+struct CheckContext<'a> {
+    raw: RawFontRef<'a>,
+    font: FontRef<'a>,
+    units_per_em: u16,
+    metrics: Vec<(u16, i16)>,
+    bounds: HashMap<u32, GlyphBounds>,
+}
+
+impl CheckContext<'_> {
+    fn name(&self, id: StringId) -> Option<String> {
+        self.font
+            .localized_strings(id)
+            .english_or_first()
+            .map(|s| s.to_string())
+            .filter(|s| !s.trim().is_empty())
+    }
+
+    fn advance_of(&self, ch: char) -> Option<u16> {
+        let gid = self.font.charmap().map(ch)?.to_u32();
+        self.metrics.get(gid as usize).map(|&(advance, _)| advance)
+    }
+}
+
+fn result(id: &str, status: CheckStatus, message: &str, values: &[(&str, i64)]) -> CheckResult {
+    CheckResult {
+        id: id.to_string(),
+        status,
+        message: message.to_string(),
+        values: values.iter().map(|&(k, v)| (k.to_string(), v)).collect(),
+    }
+}
+
+/// Installable (0) or editable embedding; restricted and print-only fonts
+/// must not be served.
+fn check_fs_type(cx: &CheckContext<'_>) -> CheckResult {
+    let Ok(os2) = cx.raw.os2() else {
+        return result("fs_type", CheckStatus::Fail, "Font has no OS/2 table", &[]);
+    };
+    let fs_type = os2.fs_type();
+    let values = [("fs_type", fs_type as i64)];
+    if fs_type & 0x0008 != 0 || fs_type & 0x000F == 0 {
+        result(
+            "fs_type",
+            CheckStatus::Pass,
+            "Embedding is installable or editable",
+            &values,
+        )
+    } else {
+        result(
+            "fs_type",
+            CheckStatus::Fail,
+            "Embedding is restricted or preview-and-print only",
+            &values,
+        )
+    }
+}
+
+/// The subfamily name must say what the OS/2 bold and italic bits say.
+fn check_style_names(cx: &CheckContext<'_>) -> CheckResult {
+    let id = "style_names";
+    let Ok(os2) = cx.raw.os2() else {
+        return result(id, CheckStatus::Fail, "Font has no OS/2 table", &[]);
+    };
+    let fs_selection = os2.fs_selection().bits();
+    let values = [("fs_selection", fs_selection as i64)];
+    if cx.name(StringId::FAMILY_NAME).is_none() {
+        return result(
+            id,
+            CheckStatus::Fail,
+            "Family name (ID 1) is missing",
+            &values,
+        );
+    }
+    let Some(subfamily) = cx.name(StringId::SUBFAMILY_NAME) else {
+        return result(
+            id,
+            CheckStatus::Fail,
+            "Subfamily name (ID 2) is missing",
+            &values,
+        );
+    };
+    let (bold, italic) = (fs_selection & 0x0020 != 0, fs_selection & 0x0001 != 0);
+    let expected = match (bold, italic) {
+        (false, false) => "Regular",
+        (true, false) => "Bold",
+        (false, true) => "Italic",
+        (true, true) => "Bold Italic",
+    };
+    if !subfamily.eq_ignore_ascii_case(expected) {
+        let message = format!(
+            "Subfamily \"{}\" does not match the OS/2 style bits, which say \"{}\"",
+            subfamily, expected
+        );
+        return result(id, CheckStatus::Fail, &message, &values);
+    }
+    if (fs_selection & 0x0040 != 0) != (!bold && !italic) {
+        return result(
+            id,
+            CheckStatus::Warn,
+            "fsSelection REGULAR must be set exactly when BOLD and ITALIC are not",
+            &values,
+        );
+    }
+    result(
+        id,
+        CheckStatus::Pass,
+        "Subfamily name matches the style bits",
+        &values,
+    )
+}
+
+/// head.macStyle bold and italic must agree with OS/2.fsSelection.
+fn check_mac_style(cx: &CheckContext<'_>) -> CheckResult {
+    let id = "mac_style";
+    let (Ok(head), Ok(os2)) = (cx.raw.head(), cx.raw.os2()) else {
+        return result(id, CheckStatus::Fail, "Font has no head or OS/2 table", &[]);
+    };
+    let mac_style = head.mac_style().bits();
+    let fs_selection = os2.fs_selection().bits();
+    let values = [
+        ("mac_style", mac_style as i64),
+        ("fs_selection", fs_selection as i64),
+    ];
+    let agree = (mac_style & 0x0001 != 0) == (fs_selection & 0x0020 != 0)
+        && (mac_style & 0x0002 != 0) == (fs_selection & 0x0001 != 0);
+    if agree {
+        result(
+            id,
+            CheckStatus::Pass,
+            "macStyle agrees with fsSelection",
+            &values,
+        )
+    } else {
+        result(
+            id,
+            CheckStatus::Fail,
+            "macStyle and fsSelection disagree on bold or italic",
+            &values,
+        )
+    }
+}
+
+/// usWinAscent and usWinDescent must cover every outline or Windows clips it.
+fn check_win_metrics(cx: &CheckContext<'_>) -> CheckResult {
+    let id = "win_metrics";
+    let Ok(os2) = cx.raw.os2() else {
+        return result(id, CheckStatus::Fail, "Font has no OS/2 table", &[]);
+    };
+    let (y_min, y_max) = cx
+        .bounds
+        .values()
+        .map(|b| (b.y_min.floor() as i64, b.y_max.ceil() as i64))
+        .fold((0, 0), |(lo, hi), (y_min, y_max)| {
+            (lo.min(y_min), hi.max(y_max))
+        });
+    let values = [
+        ("us_win_ascent", os2.us_win_ascent() as i64),
+        ("us_win_descent", os2.us_win_descent() as i64),
+        ("y_max", y_max),
+        ("y_min", y_min),
+    ];
+    if (os2.us_win_ascent() as i64) < y_max || (os2.us_win_descent() as i64) < -y_min {
+        result(
+            id,
+            CheckStatus::Fail,
+            "usWinAscent/usWinDescent do not cover the glyph extremes; Windows clips them",
+            &values,
+        )
+    } else {
+        result(
+            id,
+            CheckStatus::Pass,
+            "Win metrics cover every glyph",
+            &values,
+        )
+    }
+}
+
+fn check_advance_widths(cx: &CheckContext<'_>) -> CheckResult {
+    let id = "advance_widths";
+    let limit = cx.units_per_em as u32 * MAX_ADVANCE_EMS;
+    let widest = cx
+        .metrics
+        .iter()
+        .enumerate()
+        .max_by_key(|&(_, &(advance, _))| advance);
+    let Some((glyph_id, &(advance, _))) = widest else {
+        return result(id, CheckStatus::Pass, "Font has no glyph metrics", &[]);
+    };
+    let values = [
+        ("glyph_id", glyph_id as i64),
+        ("advance_width", advance as i64),
+        ("limit", limit as i64),
+    ];
+    if advance as u32 > limit {
+        let message = format!("Widest glyph advances more than {} ems", MAX_ADVANCE_EMS);
+        result(id, CheckStatus::Warn, &message, &values)
+    } else {
+        result(
+            id,
+            CheckStatus::Pass,
+            "Every advance is within bounds",
+            &values,
+        )
+    }
+}
+
+/// Space and no-break space must both exist and be equally wide.
+fn check_whitespace(cx: &CheckContext<'_>) -> CheckResult {
+    let id = "whitespace_widths";
+    let Some(space) = cx.advance_of(' ') else {
+        return result(id, CheckStatus::Fail, "U+0020 SPACE is not mapped", &[]);
+    };
+    let Some(nbsp) = cx.advance_of('\u{A0}') else {
+        return result(
+            id,
+            CheckStatus::Fail,
+            "U+00A0 NO-BREAK SPACE is not mapped",
+            &[("space", space as i64)],
+        );
+    };
+    let values = [("space", space as i64), ("nbsp", nbsp as i64)];
+    if space != nbsp {
+        result(
+            id,
+            CheckStatus::Fail,
+            "Space and no-break space differ in width",
+            &values,
+        )
+    } else if space == 0 {
+        result(id, CheckStatus::Fail, "Space has no width", &values)
+    } else {
+        result(
+            id,
+            CheckStatus::Pass,
+            "Space and no-break space match",
+            &values,
+        )
+    }
+}
+
+fn check_notdef(cx: &CheckContext<'_>) -> CheckResult {
+    if cx.bounds.contains_key(&0) {
+        result("notdef", CheckStatus::Pass, ".notdef has an outline", &[])
+    } else {
+        result(
+            "notdef",
+            CheckStatus::Fail,
+            ".notdef is empty; missing characters render as nothing",
+            &[],
+        )
+    }
+}
+
+/// TrueType hinting without gasp leaves the rendering mode to each rasterizer.
+fn check_gasp(cx: &CheckContext<'_>) -> CheckResult {
+    let id = "gasp";
+    let non_empty = |tag: &[u8; 4]| {
+        cx.raw
+            .table_data(Tag::new(tag))
+            .is_some_and(|t| !t.is_empty())
+    };
+    let instructed = count_glyphs_with_instructions(&cx.font);
+    let hinted = non_empty(b"fpgm") || non_empty(b"prep") || instructed > 0;
+    let values = [("glyphs_with_instructions", instructed as i64)];
+    match (hinted, non_empty(b"gasp")) {
+        (false, _) => result(
+            id,
+            CheckStatus::Pass,
+            "Font carries no TrueType hints",
+            &values,
+        ),
+        (true, true) => result(
+            id,
+            CheckStatus::Pass,
+            "Hinted font has a gasp table",
+            &values,
+        ),
+        (true, false) => result(
+            id,
+            CheckStatus::Warn,
+            "Hinted font has no gasp table",
+            &values,
+        ),
+    }
+}
+
+fn check_loca(cx: &CheckContext<'_>) -> CheckResult {
+    let id = "loca_monotonic";
+    let (Some(loca), Some(glyf)) = (
+        cx.raw.table_data(Tag::new(b"loca")),
+        cx.raw.table_data(Tag::new(b"glyf")),
+    ) else {
+        return result(id, CheckStatus::Pass, "Font has no glyf outlines", &[]);
+    };
+    let (Ok(head), Ok(maxp)) = (cx.raw.head(), cx.raw.maxp()) else {
+        return result(id, CheckStatus::Fail, "Font has no head or maxp table", &[]);
+    };
+    let num_glyphs = maxp.num_glyphs() as usize;
+    let offsets = parse_loca_offsets(
+        loca.as_bytes(),
+        num_glyphs + 1,
+        head.index_to_loc_format() != 0,
+    );
+    if let Some(index) = offsets.windows(2).position(|w| w[1] < w[0]) {
+        return result(
+            id,
+            CheckStatus::Fail,
+            "loca offsets decrease",
+            &[
+                ("glyph_id", index as i64),
+                ("offset", offsets[index] as i64),
+                ("next_offset", offsets[index + 1] as i64),
+            ],
+        );
+    }
+    let end = offsets.last().copied().unwrap_or(0);
+    let values = [("end", end as i64), ("glyf_length", glyf.len() as i64)];
+    if end as usize > glyf.len() {
+        result(
+            id,
+            CheckStatus::Fail,
+            "loca points past the end of glyf",
+            &values,
+        )
+    } else {
+        result(id, CheckStatus::Pass, "loca offsets are monotonic", &values)
+    }
+}
+
+fn check_post_names(cx: &CheckContext<'_>) -> CheckResult {
+    let id = "unique_glyph_names";
+    let Ok(post) = cx.raw.post() else {
+        return result(id, CheckStatus::Fail, "Font has no post table", &[]);
+    };
+    let num_glyphs = cx.metrics.len().min(u16::MAX as usize + 1);
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    let mut duplicates = 0;
+    let mut first_duplicate = None;
+    for gid in 0..num_glyphs {
+        let Some(name) = post.glyph_name(GlyphId16::new(gid as u16)) else {
+            continue;
+        };
+        if let Some(&earlier) = seen.get(name) {
+            duplicates += 1;
+            first_duplicate.get_or_insert((earlier, gid));
+        } else {
+            seen.insert(name, gid);
+        }
+    }
+    if seen.is_empty() {
+        return result(id, CheckStatus::Pass, "post stores no glyph names", &[]);
+    }
+    match first_duplicate {
+        None => result(id, CheckStatus::Pass, "Glyph names are unique", &[]),
+        Some((first, second)) => result(
+            id,
+            CheckStatus::Fail,
+            "Several glyphs share a post name",
+            &[
+                ("duplicates", duplicates),
+                ("glyph_id", first as i64),
+                ("duplicate_glyph_id", second as i64),
+            ],
+        ),
+    }
+}
+
+fn check_license(cx: &CheckContext<'_>) -> CheckResult {
+    let id = "license";
+    let description = cx.name(StringId::LICENSE_DESCRIPTION).is_some();
+    let url = cx.name(StringId::LICENSE_URL).is_some();
+    let values = [
+        ("has_description", description as i64),
+        ("has_url", url as i64),
+    ];
+    match (description, url) {
+        (true, true) => result(
+            id,
+            CheckStatus::Pass,
+            "License description and URL present",
+            &values,
+        ),
+        (true, false) => result(
+            id,
+            CheckStatus::Warn,
+            "License URL (ID 14) is missing",
+            &values,
+        ),
+        (false, _) => result(
+            id,
+            CheckStatus::Fail,
+            "License description (ID 13) is missing",
+            &values,
+        ),
+    }
+}
+
+/// The name table version string must agree with head.fontRevision to the
+/// three decimals it is written with.
+fn check_version(cx: &CheckContext<'_>) -> CheckResult {
+    let id = "version";
+    let Ok(head) = cx.raw.head() else {
+        return result(id, CheckStatus::Fail, "Font has no head table", &[]);
+    };
+    let revision = (head.font_revision().to_f64() * 1000.0).round() as i64;
+    let Some(version) = cx.name(StringId::VERSION_STRING) else {
+        return result(
+            id,
+            CheckStatus::Fail,
+            "Version string (ID 5) is missing",
+            &[("font_revision_thousandths", revision)],
+        );
+    };
+    let number = version
+        .trim()
+        .strip_prefix("Version ")
+        .and_then(|v| v.split([';', ' ']).next())
+        .and_then(|v| v.parse::<f64>().ok());
+    let Some(number) = number else {
+        let message = format!(
+            "Version string \"{}\" does not start with \"Version N.NNN\"",
+            version
+        );
+        return result(
+            id,
+            CheckStatus::Warn,
+            &message,
+            &[("font_revision_thousandths", revision)],
+        );
+    };
+    let named = (number * 1000.0).round() as i64;
+    let values = [
+        ("font_revision_thousandths", revision),
+        ("name_version_thousandths", named),
+    ];
+    if named == revision {
+        result(
+            id,
+            CheckStatus::Pass,
+            "Version string matches fontRevision",
+            &values,
+        )
+    } else {
+        result(
+            id,
+            CheckStatus::Fail,
+            "Version string and head.fontRevision disagree",
+            &values,
+        )
+    }
+}
+
+fn run_checks(
+    bytes: &[u8],
+    bounds: Vec<(u32, GlyphBounds)>,
+    profile: CheckProfile,
+) -> Result<CheckReport, FontError> {
+    let raw = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+    let cx = CheckContext {
+        units_per_em: raw.head().map_or(1000, |h| h.units_per_em()),
+        metrics: read_metrics(&raw, num_glyphs)?,
+        bounds: bounds.into_iter().collect(),
+        raw,
+        font,
+    };
+
+    let checks: &[fn(&CheckContext<'_>) -> CheckResult] = match profile {
+        CheckProfile::Webfont => &[
+            check_fs_type,
+            check_style_names,
+            check_mac_style,
+            check_win_metrics,
+            check_advance_widths,
+            check_whitespace,
+            check_notdef,
+            check_gasp,
+            check_loca,
+            check_post_names,
+            check_license,
+            check_version,
+        ],
+    };
+    let checks: Vec<CheckResult> = checks.iter().map(|check| check(&cx)).collect();
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    Ok(CheckReport {
+        profile,
+        passed: count(CheckStatus::Pass),
+        warnings: count(CheckStatus::Warn),
+        failures: count(CheckStatus::Fail),
+        checks,
+    })
+}
+
+/// Run the publishing checks of `profile` on the font at `file_path`. Unlike
+/// validation these are opinions about a shippable font, not structure.
+pub fn run_font_checks(
+    file_path: &str,
+    profile: CheckProfile,
+    cache: &FontCache,
+) -> Result<CheckReport, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let bounds = font_bounds(file_path, &bytes, cache)?;
+    run_checks(&bytes, bounds, profile)
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RequiredGlyphsOptions {
+    /// Also look for every printable Basic Latin character, as text fonts
+    /// should cover.
+    pub basic_latin: bool,
+}
+
+/// A command call that fixes a problem: invoke `command` with `args` plus
+/// the font's `filePath`.
+#[derive(Serialize, Debug, Clone)]
+pub struct FixSuggestion {
+    pub label: String,
+    pub command: String,
+    pub args: serde_json::Value,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RequiredGlyph {
+    /// "notdef", "space", "nbsp" or "cr".
+    pub id: String,
+    pub codepoint: Option<u32>,
+    pub glyph_id: Option<u32>,
+    pub advance_width: Option<u16>,
+    pub contours: Option<usize>,
+    pub status: CheckStatus,
+    pub message: String,
+    pub fix: Option<FixSuggestion>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RequiredGlyphsReport {
+    pub glyphs: Vec<RequiredGlyph>,
+    /// Printable Basic Latin codepoints without a glyph; None unless asked.
+    pub missing_basic_latin: Option<Vec<u32>>,
+}
+
+/// What the required-glyph items measure and how their fixes are phrased.
+struct RequiredContext<'a> {
+    font: FontRef<'a>,
+    metrics: Vec<(u16, i16)>,
+    units_per_em: u16,
+    /// The outline table saves go to, None for fonts without outlines.
+    table_name: Option<&'static str>,
+}
+
+impl RequiredContext<'_> {
+    fn contours(&self, gid: u32) -> usize {
+        let mut pen = SvgPathPen::new();
+        if let Some(glyph) = self.font.outline_glyphs().get(GlyphId::new(gid)) {
+            let settings = DrawSettings::unhinted(Size::unscaled(), LocationRef::default());
+            let _ = glyph.draw(settings, &mut pen);
+        }
+        pen.into_path().matches('M').count()
+    }
+
+    fn advance(&self, gid: u32) -> u16 {
+        self.metrics
+            .get(gid as usize)
+            .map_or(0, |&(advance, _)| advance)
+    }
+
+    /// Measure the glyph `codepoint` maps to, or report it missing.
+    fn item(&self, id: &str, codepoint: Option<u32>, gid: Option<u32>) -> RequiredGlyph {
+        RequiredGlyph {
+            id: id.to_string(),
+            codepoint,
+            glyph_id: gid,
+            advance_width: gid.map(|gid| self.advance(gid)),
+            contours: gid.map(|gid| self.contours(gid)),
+            status: CheckStatus::Pass,
+            message: String::new(),
+            fix: None,
+        }
+    }
+
+    /// Append an empty glyph of `advance` mapped from `codepoint`.
+    fn add_blank(&self, codepoint: u32, advance: u16) -> Option<FixSuggestion> {
+        Some(FixSuggestion {
+            label: format!(
+                "Add an empty glyph for U+{:04X}, {} units wide",
+                codepoint, advance
+            ),
+            command: "save_glyph_outline".to_string(),
+            args: json!({
+                "glyphId": self.metrics.len(),
+                "svgPath": "",
+                "tableName": self.table_name?,
+                "advanceWidth": advance,
+                "unicode": codepoint,
+            }),
+        })
+    }
+
+    fn clear_outline(&self, gid: u32) -> Option<FixSuggestion> {
+        Some(FixSuggestion {
+            label: "Remove the glyph's contours".to_string(),
+            command: "save_glyph_outline".to_string(),
+            args: json!({
+                "glyphId": gid,
+                "svgPath": "",
+                "tableName": self.table_name?,
+            }),
+        })
+    }
+
+    fn set_advance(&self, gid: u32, advance: u16) -> Option<FixSuggestion> {
+        Some(FixSuggestion {
+            label: format!("Set the advance width to {}", advance),
+            command: "update_glyph_metrics_bulk".to_string(),
+            args: json!({ "edits": [{ "glyph_id": gid, "advance_width": advance }] }),
+        })
+    }
+
+    /// A hollow box filling the advance of glyph 0, in the winding the
+    /// outline format expects.
+    fn notdef_box(&self) -> Option<FixSuggestion> {
+        let table_name = self.table_name?;
+        let upem = self.units_per_em as i32;
+        let width = match self.advance(0) as i32 {
+            0 => upem / 2,
+            advance => advance,
+        };
+        let (margin, stroke, height) = (upem / 20, (upem / 20).max(1), upem * 7 / 10);
+        let (x0, x1) = (margin, (width - margin).max(margin + 3 * stroke));
+        let rect = |x0: i32, y0: i32, x1: i32, y1: i32, clockwise: bool| {
+            // SVG paths run Y down; this order is clockwise in font space
+            let mut points = vec![(x0, y0), (x0, y1), (x1, y1), (x1, y0)];
+            if !clockwise {
+                points[1..].reverse();
+            }
+            let mut path = String::new();
+            for (i, (x, y)) in points.into_iter().enumerate() {
+                path.push_str(&format!("{}{} {} ", if i == 0 { 'M' } else { 'L' }, x, -y));
+            }
+            path + "Z"
+        };
+        let glyf = table_name == "glyf";
+        let svg_path = format!(
+            "{} {}",
+            rect(x0, 0, x1, height, glyf),
+            rect(x0 + stroke, stroke, x1 - stroke, height - stroke, !glyf)
+        );
+        Some(FixSuggestion {
+            label: "Draw a hollow box into .notdef".to_string(),
+            command: "save_glyph_outline".to_string(),
+            args: json!({ "glyphId": 0, "svgPath": svg_path, "tableName": table_name }),
+        })
+    }
+}
+
+fn fail(item: &mut RequiredGlyph, status: CheckStatus, message: &str, fix: Option<FixSuggestion>) {
+    item.status = status;
+    item.message = message.to_string();
+    item.fix = fix;
+}
+
+fn required_glyphs(
+    bytes: &[u8],
+    options: &RequiredGlyphsOptions,
+) -> Result<RequiredGlyphsReport, FontError> {
+    let raw = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+    let has_table = |tag: &[u8; 4]| raw.table_data(Tag::new(tag)).is_some();
+    let cx = RequiredContext {
+        metrics: read_metrics(&raw, num_glyphs)?,
+        units_per_em: raw.head().map_or(1000, |h| h.units_per_em()),
+        table_name: [("glyf", b"glyf"), ("CFF ", b"CFF "), ("CFF2", b"CFF2")]
+            .into_iter()
+            .find(|(_, tag)| has_table(tag))
+            .map(|(name, _)| name),
+        font,
+    };
+    let charmap = cx.font.charmap();
+    let lookup = |cp: u32| charmap.map(cp).map(|gid| gid.to_u32());
+    let upem = cx.units_per_em;
+    let default_space = upem / 4;
+    let mut glyphs = Vec::new();
+
+    let mut notdef = cx.item("notdef", None, (num_glyphs > 0).then_some(0));
+    if num_glyphs == 0 {
+        fail(&mut notdef, CheckStatus::Fail, "Font has no glyphs", None);
+    } else if notdef.contours == Some(0) {
+        let message = ".notdef is empty; missing characters render as nothing";
+        fail(&mut notdef, CheckStatus::Fail, message, cx.notdef_box());
+    } else {
+        notdef.message = ".notdef has an outline".to_string();
+    }
+    glyphs.push(notdef);
+
+    let space_gid = lookup(0x20);
+    let mut space = cx.item("space", Some(0x20), space_gid);
+    let space_advance = space.advance_width.filter(|&a| a > 0);
+    match space_gid {
+        None => {
+            let fix = cx.add_blank(0x20, default_space);
+            fail(
+                &mut space,
+                CheckStatus::Fail,
+                "U+0020 SPACE is not mapped",
+                fix,
+            );
+        }
+        Some(gid) if space.contours != Some(0) => {
+            let message = "Space draws contours";
+            fail(
+                &mut space,
+                CheckStatus::Fail,
+                message,
+                cx.clear_outline(gid),
+            );
+        }
+        Some(gid) => match space.advance_width {
+            Some(advance) if advance == 0 || advance > upem => {
+                let message = "Space is not between 0 and 1 em wide";
+                fail(
+                    &mut space,
+                    CheckStatus::Warn,
+                    message,
+                    cx.set_advance(gid, default_space),
+                );
+            }
+            _ => space.message = "Space is empty and sensibly wide".to_string(),
+        },
+    }
+    glyphs.push(space);
+
+    // Both should match the space; a font without one gets the default
+    let target = space_advance.unwrap_or(default_space);
+    for (id, codepoint, name) in [
+        ("nbsp", 0xA0, "U+00A0 NO-BREAK SPACE"),
+        ("cr", 0x0D, "U+000D CARRIAGE RETURN"),
+    ] {
+        let gid = lookup(codepoint);
+        let mut item = cx.item(id, Some(codepoint), gid);
+        match gid {
+            None => {
+                let message = format!("{} is not mapped", name);
+                let status = if id == "cr" {
+                    CheckStatus::Warn
+                } else {
+                    CheckStatus::Fail
+                };
+                fail(&mut item, status, &message, cx.add_blank(codepoint, target));
+            }
+            Some(gid) if item.contours != Some(0) => {
+                let message = format!("{} draws contours", name);
+                fail(
+                    &mut item,
+                    CheckStatus::Fail,
+                    &message,
+                    cx.clear_outline(gid),
+                );
+            }
+            Some(gid)
+                if id == "nbsp"
+                    && item.advance_width != space_advance
+                    && space_advance.is_some() =>
+            {
+                let message = "No-break space and space differ in width";
+                fail(
+                    &mut item,
+                    CheckStatus::Fail,
+                    message,
+                    cx.set_advance(gid, target),
+                );
+            }
+            Some(_) => item.message = format!("{} is present", name),
+        }
+        glyphs.push(item);
+    }
+
+    let missing_basic_latin = options
+        .basic_latin
+        .then(|| (0x21..=0x7E).filter(|&cp| lookup(cp).is_none()).collect());
+    Ok(RequiredGlyphsReport {
+        glyphs,
+        missing_basic_latin,
+    })
+}
+
+/// Check the glyphs text stacks take for granted (.notdef, space, no-break
+/// space and carriage return), with a fix for each problem that maps onto an
+/// existing editing command.
+pub fn check_required_glyphs(
+    file_path: &str,
+    options: &RequiredGlyphsOptions,
+    cache: &FontCache,
+) -> Result<RequiredGlyphsReport, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    required_glyphs(&bytes, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font_parser::extract_glyph_outlines;
+    use crate::font_parser::CancelToken;
+    use crate::test_fonts::{name_table, simple_glyph, TestFontBuilder};
+    use write_fonts::tables::os2::{Os2, SelectionFlags};
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    #[test]
+    fn test_webfont_profile() {
+        let tall = simple_glyph(&[&[(50, -250, true), (50, 900, true), (450, 900, true)]]);
+        let os2 = Os2 {
+            fs_type: 0x0002,
+            fs_selection: SelectionFlags::from_bits_truncate(0x0020),
+            us_win_ascent: 800,
+            us_win_descent: 300,
+            ..Default::default()
+        };
+        let (family, style) = (utf16("Test"), utf16("Regular"));
+        let (version, license) = (utf16("Version 1.500"), utf16("OFL"));
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(Vec::new(), 250)
+            .glyph(Vec::new(), 300)
+            .glyph(tall, 5000)
+            .map(' ' as u32, 1)
+            .map(0xA0, 2)
+            .map('A' as u32, 3)
+            .table(
+                b"name",
+                name_table(&[
+                    (3, 1, 0x409, 1, &family),
+                    (3, 1, 0x409, 2, &style),
+                    (3, 1, 0x409, 5, &version),
+                    (3, 1, 0x409, 13, &license),
+                ]),
+            )
+            .table(b"OS/2", write_fonts::dump_table(&os2).unwrap())
+            .build();
+        let bounds = extract_glyph_outlines(&font, &|_, _| {}, &CancelToken::default())
+            .unwrap()
+            .into_iter()
+            .filter_map(|o| Some((o.glyph_id, o.bounds?)))
+            .collect();
+
+        let report = run_checks(&font, bounds, CheckProfile::Webfont).unwrap();
+        assert_eq!(report.checks.len(), 12);
+        let status: HashMap<&str, CheckStatus> = report
+            .checks
+            .iter()
+            .map(|c| (c.id.as_str(), c.status))
+            .collect();
+        use CheckStatus::*;
+        assert_eq!(status["fs_type"], Fail);
+        assert_eq!(status["style_names"], Fail);
+        assert_eq!(status["mac_style"], Fail);
+        assert_eq!(status["win_metrics"], Fail);
+        assert_eq!(status["advance_widths"], Warn);
+        assert_eq!(status["whitespace_widths"], Fail);
+        assert_eq!(status["notdef"], Fail);
+        assert_eq!(status["gasp"], Pass);
+        assert_eq!(status["loca_monotonic"], Pass);
+        assert_eq!(status["license"], Warn);
+        let version = report.checks.iter().find(|c| c.id == "version").unwrap();
+        assert_eq!(version.status, Fail);
+        assert_eq!(version.values["name_version_thousandths"], 1500);
+        let win = report
+            .checks
+            .iter()
+            .find(|c| c.id == "win_metrics")
+            .unwrap();
+        assert_eq!((win.values["y_max"], win.values["y_min"]), (900, -250));
+        assert_eq!(
+            report.passed + report.warnings + report.failures,
+            report.checks.len()
+        );
+    }
+
+    #[test]
+    fn test_required_glyphs_pass() {
+        let notdef = simple_glyph(&[&[(50, 0, true), (50, 700, true), (450, 700, true)]]);
+        let font = TestFontBuilder::new()
+            .glyph(notdef, 500)
+            .glyph(Vec::new(), 250)
+            .glyph(Vec::new(), 250)
+            .map(' ' as u32, 1)
+            .map(0xA0, 2)
+            .map(0x0D, 1)
+            .build();
+        let report = required_glyphs(&font, &RequiredGlyphsOptions::default()).unwrap();
+        assert!(report
+            .glyphs
+            .iter()
+            .all(|g| g.status == CheckStatus::Pass && g.fix.is_none()));
+        assert_eq!(report.glyphs[1].advance_width, Some(250));
+        assert_eq!(report.missing_basic_latin, None);
+    }
+
+    #[test]
+    fn test_required_glyphs_fail_with_fixes() {
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 600)
+            .glyph(Vec::new(), 250)
+            .glyph(bar.clone(), 300)
+            .glyph(bar, 500)
+            .map(' ' as u32, 1)
+            .map(0xA0, 2)
+            .map('A' as u32, 3)
+            .build();
+        let report = required_glyphs(&font, &RequiredGlyphsOptions { basic_latin: true }).unwrap();
+        let by_id: HashMap<&str, &RequiredGlyph> =
+            report.glyphs.iter().map(|g| (g.id.as_str(), g)).collect();
+
+        let notdef = by_id["notdef"];
+        assert_eq!(notdef.status, CheckStatus::Fail);
+        let fix = notdef.fix.as_ref().unwrap();
+        assert_eq!(fix.command, "save_glyph_outline");
+        assert_eq!(fix.args["glyphId"], 0);
+        // The box is two closed contours inside the 600-unit advance
+        let svg_path = fix.args["svgPath"].as_str().unwrap();
+        assert_eq!(svg_path.matches('Z').count(), 2);
+        assert!(svg_path.starts_with("M50 0 L50 -700 L550 -700"));
+
+        assert_eq!(by_id["space"].status, CheckStatus::Pass);
+        // The no-break space draws contours; clearing them comes first
+        let nbsp = by_id["nbsp"];
+        assert_eq!((nbsp.status, nbsp.contours), (CheckStatus::Fail, Some(1)));
+        assert_eq!(nbsp.fix.as_ref().unwrap().args["svgPath"], "");
+
+        let cr = by_id["cr"];
+        assert_eq!((cr.status, cr.glyph_id), (CheckStatus::Warn, None));
+        let fix = cr.fix.as_ref().unwrap();
+        assert_eq!(fix.args["glyphId"], 4);
+        assert_eq!(fix.args["advanceWidth"], 250);
+        assert_eq!(fix.args["unicode"], 0x0D);
+
+        let missing = report.missing_basic_latin.unwrap();
+        assert_eq!(missing.len(), 93);
+        assert!(!missing.contains(&('A' as u32)));
+    }
+}
@@ -0,0 +1,345 @@
+use crate::error::FontError;
+use crate::font_parser::{get_font_bytes, write_atomically, FontCache};
+use serde::Serialize;
+use skrifa::raw::types::Tag;
+use skrifa::raw::{FileRef, FontRef as RawFontRef, TableProvider};
+use skrifa::string::StringId;
+use skrifa::{FontRef, MetadataProvider};
+use std::collections::HashMap;
+use std::fs;
+use write_fonts::FontBuilder;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CollectionMember {
+    pub index: u32,
+    /// Cache key to pass as `file_path` to every other command.
+    pub file_path: String,
+    pub family_name: String,
+    pub style_name: String,
+}
+
+/// Cache key for member `index` of the collection at `file_path`. Members are
+/// cached and edited as standalone SFNTs under this key.
+pub fn member_key(file_path: &str, index: u32) -> String {
+    format!("{}#{}", file_path, index)
+}
+
+pub fn is_collection(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"ttcf")
+}
+
+/// Every member of a collection, in header order.
+fn collection_fonts(bytes: &[u8]) -> Result<Vec<RawFontRef<'_>>, FontError> {
+    match FileRef::new(bytes).map_err(FontError::invalid_font)? {
+        FileRef::Collection(collection) => collection
+            .iter()
+            .map(|font| font.map_err(FontError::invalid_font))
+            .collect(),
+        FileRef::Font(_) => Err(FontError::invalid_data("Not a font collection")),
+    }
+}
+
+/// Copy one member out of a collection as a standalone SFNT.
+pub fn extract_member(bytes: &[u8], index: u32) -> Result<Vec<u8>, FontError> {
+    let fonts = collection_fonts(bytes)?;
+    let font = fonts
+        .get(index as usize)
+        .ok_or_else(|| FontError::input(format!("Collection has no font at index {}", index)))?;
+    member_to_sfnt(font)
+}
+
+fn member_to_sfnt(font: &RawFontRef<'_>) -> Result<Vec<u8>, FontError> {
+    let mut builder = FontBuilder::new();
+    for record in font.table_directory.table_records() {
+        let data = font.table_data(record.tag()).ok_or_else(|| {
+            FontError::malformed(
+                &record.tag().to_string(),
+                "extends past the end of the font",
+            )
+        })?;
+        builder.add_raw(record.tag(), data.as_bytes().to_vec());
+    }
+    Ok(builder.build())
+}
+
+/// Pack standalone SFNTs into a TrueType collection (version 1.0, no DSIG).
+/// Byte-identical tables with the same tag are stored once and shared.
+pub fn build_collection(members: &[Vec<u8>]) -> Result<Vec<u8>, FontError> {
+    let fonts = members
+        .iter()
+        .map(|bytes| RawFontRef::new(bytes).map_err(FontError::invalid_font))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let header_len = 12 + 4 * fonts.len();
+    let directories_len: usize = fonts
+        .iter()
+        .map(|f| 12 + 16 * f.table_directory.table_records().len())
+        .sum();
+
+    let mut directories = Vec::with_capacity(directories_len);
+    let mut offsets = Vec::with_capacity(fonts.len());
+    let mut data = Vec::new();
+    let mut shared: HashMap<(Tag, &[u8]), u32> = HashMap::new();
+    for font in &fonts {
+        offsets.push((header_len + directories.len()) as u32);
+        let records = font.table_directory.table_records();
+        let num_tables = records.len() as u16;
+        let entry_selector = num_tables.max(1).ilog2() as u16;
+        let search_range = 16u16 << entry_selector;
+        directories.extend(font.table_directory.sfnt_version().to_be_bytes());
+        directories.extend(num_tables.to_be_bytes());
+        directories.extend(search_range.to_be_bytes());
+        directories.extend(entry_selector.to_be_bytes());
+        directories.extend((num_tables * 16 - search_range).to_be_bytes());
+
+        for record in records {
+            let table = font
+                .table_data(record.tag())
+                .ok_or_else(|| {
+                    FontError::malformed(
+                        &record.tag().to_string(),
+                        "extends past the end of the font",
+                    )
+                })?
+                .as_bytes();
+            let offset = *shared.entry((record.tag(), table)).or_insert_with(|| {
+                let offset = (header_len + directories_len + data.len()) as u32;
+                data.extend_from_slice(table);
+                data.resize(data.len().next_multiple_of(4), 0);
+                offset
+            });
+            directories.extend(record.tag().to_be_bytes());
+            directories.extend(record.checksum().to_be_bytes());
+            directories.extend(offset.to_be_bytes());
+            directories.extend((table.len() as u32).to_be_bytes());
+        }
+    }
+
+    let mut out = Vec::with_capacity(header_len + directories_len + data.len());
+    out.extend(b"ttcf");
+    out.extend(0x0001_0000u32.to_be_bytes());
+    out.extend((fonts.len() as u32).to_be_bytes());
+    for offset in offsets {
+        out.extend(offset.to_be_bytes());
+    }
+    out.extend(directories);
+    out.extend(data);
+    Ok(out)
+}
+
+/// Rebuild the collection at `file_path` with member `index` replaced by `bytes`.
+/// The other members are taken from disk, so they keep any earlier saved edits.
+pub fn replace_member(file_path: &str, index: u32, bytes: &[u8]) -> Result<Vec<u8>, FontError> {
+    let current = fs::read(file_path).map_err(|e| FontError::io(file_path, e))?;
+    let fonts = collection_fonts(&current)?;
+    if index as usize >= fonts.len() {
+        return Err(FontError::input(format!(
+            "Collection has no font at index {}",
+            index
+        )));
+    }
+    let members = fonts
+        .iter()
+        .enumerate()
+        .map(|(i, font)| {
+            if i == index as usize {
+                Ok(bytes.to_vec())
+            } else {
+                member_to_sfnt(font)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    build_collection(&members)
+}
+
+pub fn list_collection_fonts(file_path: &str) -> Result<Vec<CollectionMember>, FontError> {
+    let bytes = fs::read(file_path).map_err(|e| FontError::io(file_path, e))?;
+    let count = collection_fonts(&bytes)?.len() as u32;
+    (0..count)
+        .map(|index| {
+            let font = FontRef::from_index(&bytes, index).map_err(FontError::invalid_font)?;
+            let name = |id: StringId, fallback: &str| {
+                font.localized_strings(id)
+                    .english_or_first()
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| fallback.to_string())
+            };
+            Ok(CollectionMember {
+                index,
+                file_path: member_key(file_path, index),
+                family_name: name(StringId::FAMILY_NAME, "Unknown"),
+                style_name: name(StringId::SUBFAMILY_NAME, "Regular"),
+            })
+        })
+        .collect()
+}
+
+/// Pack several open fonts (paths or member keys) into one collection file.
+/// All members must share units_per_em.
+pub fn export_ttc(
+    font_paths: &[String],
+    destination: &str,
+    cache: &FontCache,
+) -> Result<(), FontError> {
+    if font_paths.len() < 2 {
+        return Err(FontError::input("A collection needs at least two fonts"));
+    }
+    let mut members = Vec::with_capacity(font_paths.len());
+    let mut units_per_em = None;
+    for file_path in font_paths {
+        let bytes = get_font_bytes(file_path, cache)?;
+        let upem = RawFontRef::new(&bytes)
+            .map_err(FontError::invalid_font)?
+            .head()
+            .map_err(|e| FontError::table("head", e))?
+            .units_per_em();
+        match units_per_em {
+            Some(first) if first != upem => {
+                return Err(FontError::input(format!(
+                    "{} has {} units per em, but the first font has {}",
+                    file_path, upem, first
+                )));
+            }
+            _ => units_per_em = Some(upem),
+        }
+        members.push(bytes.to_vec());
+    }
+    let ttc = build_collection(&members)?;
+    write_atomically(std::path::Path::new(destination), &ttc)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font_parser::{parse_font, write_font_file, FontCache};
+    use crate::test_fonts::{simple_glyph, TestFontBuilder};
+
+    fn two_weights() -> (Vec<u8>, Vec<u8>) {
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let wide = simple_glyph(&[&[(0, 0, true), (0, 500, true), (300, 500, true)]]);
+        let regular = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(bar, 500)
+            .build();
+        let bold = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(wide, 500)
+            .build();
+        (regular, bold)
+    }
+
+    fn table_bytes(font: &[u8]) -> Vec<(Tag, Vec<u8>)> {
+        let font = RawFontRef::new(font).unwrap();
+        font.table_directory
+            .table_records()
+            .iter()
+            .map(|r| {
+                (
+                    r.tag(),
+                    font.table_data(r.tag()).unwrap().as_bytes().to_vec(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build_collection_shares_identical_tables() {
+        let (regular, bold) = two_weights();
+        let ttc = build_collection(&[regular.clone(), bold.clone()]).unwrap();
+        assert!(is_collection(&ttc));
+        assert_eq!(
+            table_bytes(&extract_member(&ttc, 0).unwrap()),
+            table_bytes(&regular)
+        );
+        assert_eq!(
+            table_bytes(&extract_member(&ttc, 1).unwrap()),
+            table_bytes(&bold)
+        );
+        assert!(extract_member(&ttc, 2).is_err());
+
+        // Only glyf (and head, through its checksum adjustment) differs, so
+        // every other table is stored once
+        let offsets = |index| {
+            let font = RawFontRef::from_index(&ttc, index).unwrap();
+            font.table_directory
+                .table_records()
+                .iter()
+                .map(|r| (r.tag(), r.offset()))
+                .collect::<Vec<_>>()
+        };
+        for ((tag, a), (_, b)) in offsets(0).into_iter().zip(offsets(1)) {
+            let differs = tag == Tag::new(b"glyf") || tag == Tag::new(b"head");
+            assert_eq!(a != b, differs, "table {}", tag);
+        }
+    }
+
+    #[test]
+    fn test_edit_member_rebuilds_collection() {
+        let dir = std::env::temp_dir().join(format!("typebrew-ttc-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("family.ttc");
+        let path = path.to_str().unwrap();
+        let (regular, bold) = two_weights();
+        fs::write(path, build_collection(&[regular.clone(), bold]).unwrap()).unwrap();
+
+        let members = list_collection_fonts(path).unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[1].file_path, member_key(path, 1));
+
+        let cache = FontCache::new();
+        let metadata = parse_font(path, Some(1), &cache).unwrap();
+        assert_eq!(metadata.file_path, member_key(path, 1));
+        assert_eq!(metadata.collection_index, Some(1));
+
+        write_font_file(&metadata.file_path, regular.clone(), &cache).unwrap();
+        let ttc = fs::read(path).unwrap();
+        assert_eq!(
+            table_bytes(&extract_member(&ttc, 0).unwrap()),
+            table_bytes(&regular)
+        );
+        assert_eq!(
+            table_bytes(&extract_member(&ttc, 1).unwrap()),
+            table_bytes(&regular)
+        );
+        assert!(cache.status(&metadata.file_path).modified);
+        assert!(dir.join("family.ttc.bak").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_ttc_requires_matching_units_per_em() {
+        let (regular, bold) = two_weights();
+        let cache = FontCache::new();
+        cache.insert("regular.ttf".to_string(), regular.clone());
+        cache.insert("bold.ttf".to_string(), bold.clone());
+        let odd = TestFontBuilder::new()
+            .units_per_em(2048)
+            .glyph(Vec::new(), 500)
+            .build();
+        cache.insert("odd.ttf".to_string(), odd);
+
+        let dir = std::env::temp_dir().join(format!("typebrew-export-ttc-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let destination = dir.join("family.ttc");
+        let destination = destination.to_str().unwrap();
+
+        let paths = ["regular.ttf".to_string(), "bold.ttf".to_string()];
+        export_ttc(&paths, destination, &cache).unwrap();
+        let ttc = fs::read(destination).unwrap();
+        assert_eq!(list_collection_fonts(destination).unwrap().len(), 2);
+        assert_eq!(
+            table_bytes(&extract_member(&ttc, 1).unwrap()),
+            table_bytes(&bold)
+        );
+
+        let paths = ["regular.ttf".to_string(), "odd.ttf".to_string()];
+        let err = export_ttc(&paths, destination, &cache).unwrap_err();
+        assert_eq!(err.kind(), "invalid_input");
+        assert!(err.to_string().contains("units per em"), "{}", err);
+        assert!(export_ttc(&paths[..1], destination, &cache).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,424 @@
+use crate::error::FontError;
+use crate::font_parser::{get_font_bytes, BoundsPen, FontCache, GlyphBounds, SvgPathPen};
+use crate::raster::{Bitmap, EdgePen};
+use serde::{Deserialize, Serialize};
+use skrifa::instance::{LocationRef, Size};
+use skrifa::outline::{DrawSettings, OutlineGlyphCollection};
+use skrifa::raw::TableProvider;
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::{HashMap, HashSet};
+
+/// Raster resolution of the outline comparison, in pixels per em.
+const DIFF_PPEM: f32 = 128.0;
+
+/// Outline distances below this are rasterization noise, not edits.
+const DISTANCE_EPSILON: f32 = 0.001;
+
+/// How the glyphs of the two fonts are paired up.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GlyphMatching {
+    /// Same glyph name; fonts without a post table fall back to "gidN".
+    Name,
+    /// Same lowest codepoint; unmapped glyphs are not compared.
+    Codepoint,
+    /// Same glyph ID.
+    GlyphId,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GlyphDiffOptions {
+    /// How many of the most-changed glyphs carry both SVG paths.
+    pub top_n: usize,
+    /// List identical pairs too, after the changed ones.
+    pub include_unchanged: bool,
+}
+
+impl Default for GlyphDiffOptions {
+    fn default() -> Self {
+        Self {
+            top_n: 20,
+            include_unchanged: false,
+        }
+    }
+}
+
+/// One matched pair. Values of font B are scaled to font A's unitsPerEm so
+/// the two sides compare directly.
+#[derive(Serialize, Debug, Clone)]
+pub struct GlyphDiff {
+    /// The name, "U+XXXX" or glyph ID the pair was matched on.
+    pub key: String,
+    pub glyph_id_a: u32,
+    pub glyph_id_b: u32,
+    pub advance_a: f32,
+    pub advance_b: f32,
+    pub lsb_a: f32,
+    pub lsb_b: f32,
+    pub bounds_a: Option<GlyphBounds>,
+    pub bounds_b: Option<GlyphBounds>,
+    pub advance_changed: bool,
+    pub lsb_changed: bool,
+    pub bounds_changed: bool,
+    /// Area of the symmetric difference of the two filled outlines over the
+    /// area of their union: 0 for the same shape, 1 for disjoint ones.
+    pub outline_distance: f32,
+    /// SVG paths (Y negated, font A's units) for the top-ranked pairs.
+    pub svg_path_a: Option<String>,
+    pub svg_path_b: Option<String>,
+}
+
+impl GlyphDiff {
+    fn is_changed(&self) -> bool {
+        self.advance_changed
+            || self.lsb_changed
+            || self.bounds_changed
+            || self.outline_distance >= DISTANCE_EPSILON
+    }
+
+    /// Metric change in em, ranking pairs whose outlines moved equally.
+    fn metric_change(&self, units_per_em: f32) -> f32 {
+        ((self.advance_a - self.advance_b).abs() + (self.lsb_a - self.lsb_b).abs()) / units_per_em
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GlyphDiffReport {
+    pub matched: usize,
+    pub changed: usize,
+    /// Keys of font A with no counterpart in font B, and the reverse.
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    /// Changed pairs, most changed first.
+    pub glyphs: Vec<GlyphDiff>,
+}
+
+/// One side of the comparison.
+struct DiffFont<'a> {
+    font: FontRef<'a>,
+    outlines: OutlineGlyphCollection<'a>,
+    units_per_em: f32,
+    /// Factor from this font's units to font A's.
+    scale: f32,
+}
+
+impl<'a> DiffFont<'a> {
+    fn new(bytes: &'a [u8], units_per_em_a: Option<f32>) -> Result<Self, FontError> {
+        let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+        let units_per_em = font
+            .metrics(Size::unscaled(), LocationRef::default())
+            .units_per_em as f32;
+        let units_per_em = units_per_em.max(1.0);
+        Ok(Self {
+            outlines: font.outline_glyphs(),
+            scale: units_per_em_a.map_or(1.0, |upem| upem / units_per_em),
+            units_per_em,
+            font,
+        })
+    }
+
+    /// Glyph IDs keyed the way `matching` pairs them.
+    fn keys(&self, matching: GlyphMatching) -> Vec<(String, u32)> {
+        let num_glyphs = self.font.maxp().map_or(0, |m| m.num_glyphs() as u32);
+        match matching {
+            GlyphMatching::Name => {
+                let names = self.font.glyph_names();
+                (0..num_glyphs)
+                    .map(|gid| {
+                        let name = names
+                            .get(GlyphId::new(gid))
+                            .map(|n| n.to_string())
+                            .unwrap_or_else(|| format!("gid{}", gid));
+                        (name, gid)
+                    })
+                    .collect()
+            }
+            GlyphMatching::Codepoint => {
+                // Mappings come in codepoint order; keep each glyph's lowest
+                let mut seen = HashSet::new();
+                self.font
+                    .charmap()
+                    .mappings()
+                    .filter(|&(_, gid)| seen.insert(gid))
+                    .map(|(cp, gid)| (format!("U+{:04X}", cp), gid.to_u32()))
+                    .collect()
+            }
+            GlyphMatching::GlyphId => (0..num_glyphs).map(|gid| (gid.to_string(), gid)).collect(),
+        }
+    }
+
+    /// (advance, lsb, bounds) in font A's units.
+    fn measure(&self, gid: u32) -> (f32, f32, Option<GlyphBounds>) {
+        let gid = GlyphId::new(gid);
+        let metrics = self
+            .font
+            .glyph_metrics(Size::unscaled(), LocationRef::default());
+        let advance = metrics.advance_width(gid).unwrap_or(0.0) * self.scale;
+        let lsb = metrics.left_side_bearing(gid).unwrap_or(0.0) * self.scale;
+        let mut pen = BoundsPen::default();
+        if let Some(glyph) = self.outlines.get(gid) {
+            let settings = DrawSettings::unhinted(Size::unscaled(), LocationRef::default());
+            let _ = glyph.draw(settings, &mut pen);
+        }
+        let bounds = pen.bounds.map(|b| GlyphBounds {
+            x_min: b.x_min * self.scale,
+            y_min: b.y_min * self.scale,
+            x_max: b.x_max * self.scale,
+            y_max: b.y_max * self.scale,
+        });
+        (advance, lsb, bounds)
+    }
+
+    /// Draw `gid` at `ppem` into `pen`; both fonts land on the same em grid.
+    fn draw(&self, gid: u32, ppem: f32, pen: &mut impl skrifa::outline::OutlinePen) {
+        if let Some(glyph) = self.outlines.get(GlyphId::new(gid)) {
+            let _ = glyph.draw(
+                DrawSettings::unhinted(Size::new(ppem), LocationRef::default()),
+                pen,
+            );
+        }
+    }
+
+    fn svg_path(&self, gid: u32, units_per_em_a: f32) -> String {
+        let mut pen = SvgPathPen::new();
+        self.draw(gid, units_per_em_a, &mut pen);
+        pen.into_path()
+    }
+}
+
+fn union(a: &Option<GlyphBounds>, b: &Option<GlyphBounds>) -> Option<GlyphBounds> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(GlyphBounds {
+            x_min: a.x_min.min(b.x_min),
+            y_min: a.y_min.min(b.y_min),
+            x_max: a.x_max.max(b.x_max),
+            y_max: a.y_max.max(b.y_max),
+        }),
+        (a, b) => a.clone().or_else(|| b.clone()),
+    }
+}
+
+fn bounds_differ(a: &Option<GlyphBounds>, b: &Option<GlyphBounds>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => [
+            a.x_min - b.x_min,
+            a.y_min - b.y_min,
+            a.x_max - b.x_max,
+            a.y_max - b.y_max,
+        ]
+        .iter()
+        .any(|d| d.abs() > 0.5),
+        (None, None) => false,
+        _ => true,
+    }
+}
+
+/// Rasterize both outlines onto one grid covering their union and compare
+/// coverage pixel by pixel.
+fn outline_distance(
+    a: &DiffFont<'_>,
+    gid_a: u32,
+    b: &DiffFont<'_>,
+    gid_b: u32,
+    union: &GlyphBounds,
+    units_per_em_a: f32,
+) -> f32 {
+    let to_px = DIFF_PPEM / units_per_em_a;
+    let width = ((union.x_max - union.x_min) * to_px).ceil() as u32 + 2;
+    let height = ((union.y_max - union.y_min) * to_px).ceil() as u32 + 2;
+    let render = |font: &DiffFont<'_>, gid: u32| {
+        let mut pen = EdgePen::new();
+        pen.set_origin(1.0 - union.x_min * to_px, 1.0 + union.y_max * to_px);
+        font.draw(gid, DIFF_PPEM, &mut pen);
+        let mut bitmap = Bitmap::new(width, height);
+        bitmap.fill(&pen, 4);
+        bitmap
+    };
+    let (raster_a, raster_b) = (render(a, gid_a), render(b, gid_b));
+    let (mut difference, mut total) = (0u64, 0u64);
+    for (&ca, &cb) in raster_a.coverage.iter().zip(&raster_b.coverage) {
+        difference += ca.abs_diff(cb) as u64;
+        total += ca.max(cb) as u64;
+    }
+    if total == 0 {
+        0.0
+    } else {
+        difference as f32 / total as f32
+    }
+}
+
+fn diff_glyphs(
+    bytes_a: &[u8],
+    bytes_b: &[u8],
+    matching: GlyphMatching,
+    options: &GlyphDiffOptions,
+) -> Result<GlyphDiffReport, FontError> {
+    let a = DiffFont::new(bytes_a, None)?;
+    let b = DiffFont::new(bytes_b, Some(a.units_per_em))?;
+    let upem = a.units_per_em;
+
+    let keys_a = a.keys(matching);
+    let keys_b: HashMap<String, u32> = b.keys(matching).into_iter().collect();
+    let mut paired = HashSet::new();
+    let mut only_in_a = Vec::new();
+    let mut glyphs = Vec::new();
+    for (key, gid_a) in keys_a {
+        let Some(&gid_b) = keys_b.get(&key) else {
+            only_in_a.push(key);
+            continue;
+        };
+        paired.insert(key.clone());
+        let (advance_a, lsb_a, bounds_a) = a.measure(gid_a);
+        let (advance_b, lsb_b, bounds_b) = b.measure(gid_b);
+        let outline_distance = match union(&bounds_a, &bounds_b) {
+            Some(union) if bounds_a.is_some() && bounds_b.is_some() => {
+                outline_distance(&a, gid_a, &b, gid_b, &union, upem)
+            }
+            Some(_) => 1.0,
+            None => 0.0,
+        };
+        glyphs.push(GlyphDiff {
+            key,
+            glyph_id_a: gid_a,
+            glyph_id_b: gid_b,
+            advance_changed: (advance_a - advance_b).abs() > 0.5,
+            lsb_changed: (lsb_a - lsb_b).abs() > 0.5,
+            bounds_changed: bounds_differ(&bounds_a, &bounds_b),
+            advance_a,
+            advance_b,
+            lsb_a,
+            lsb_b,
+            bounds_a,
+            bounds_b,
+            outline_distance,
+            svg_path_a: None,
+            svg_path_b: None,
+        });
+    }
+    let mut only_in_b: Vec<String> = keys_b
+        .into_keys()
+        .filter(|key| !paired.contains(key))
+        .collect();
+    only_in_b.sort();
+
+    let matched = glyphs.len();
+    let changed = glyphs.iter().filter(|g| g.is_changed()).count();
+    if !options.include_unchanged {
+        glyphs.retain(GlyphDiff::is_changed);
+    }
+    glyphs.sort_by(|x, y| {
+        y.outline_distance
+            .total_cmp(&x.outline_distance)
+            .then(y.metric_change(upem).total_cmp(&x.metric_change(upem)))
+            .then(x.glyph_id_a.cmp(&y.glyph_id_a))
+    });
+    for glyph in glyphs.iter_mut().take(options.top_n) {
+        if glyph.is_changed() {
+            glyph.svg_path_a = Some(a.svg_path(glyph.glyph_id_a, upem));
+            glyph.svg_path_b = Some(b.svg_path(glyph.glyph_id_b, upem));
+        }
+    }
+
+    Ok(GlyphDiffReport {
+        matched,
+        changed,
+        only_in_a,
+        only_in_b,
+        glyphs,
+    })
+}
+
+/// Compare the outlines and metrics of the glyphs two fonts share, ranking
+/// the pairs by how far their shapes moved apart.
+pub fn diff_glyph_outlines(
+    path_a: &str,
+    path_b: &str,
+    matching: GlyphMatching,
+    options: &GlyphDiffOptions,
+    cache: &FontCache,
+) -> Result<GlyphDiffReport, FontError> {
+    let bytes_a = get_font_bytes(path_a, cache)?;
+    let bytes_b = get_font_bytes(path_b, cache)?;
+    diff_glyphs(&bytes_a, &bytes_b, matching, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{simple_glyph, TestFontBuilder};
+
+    fn rect(x0: i16, y0: i16, x1: i16, y1: i16) -> Vec<u8> {
+        simple_glyph(&[&[
+            (x0, y0, true),
+            (x0, y1, true),
+            (x1, y1, true),
+            (x1, y0, true),
+        ]])
+    }
+
+    #[test]
+    fn test_diff_ranks_reshaped_glyphs_first() {
+        let font_a = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(rect(50, 0, 450, 700), 500)
+            .glyph(rect(50, 0, 450, 500), 500)
+            .glyph(rect(50, 0, 450, 500), 500)
+            .map('A' as u32, 1)
+            .map('B' as u32, 2)
+            .map('C' as u32, 3)
+            .build();
+        // A unchanged, B half as wide, C only wider, D new
+        let font_b = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(rect(50, 0, 450, 700), 500)
+            .glyph(rect(50, 0, 250, 500), 500)
+            .glyph(rect(50, 0, 450, 500), 600)
+            .glyph(rect(50, 0, 450, 500), 500)
+            .map('A' as u32, 1)
+            .map('B' as u32, 2)
+            .map('C' as u32, 3)
+            .map('D' as u32, 4)
+            .build();
+
+        let report = diff_glyphs(
+            &font_a,
+            &font_b,
+            GlyphMatching::Codepoint,
+            &GlyphDiffOptions {
+                top_n: 1,
+                ..GlyphDiffOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(report.matched, 3);
+        assert_eq!(report.changed, 2);
+        assert_eq!(report.only_in_b, vec!["U+0044".to_string()]);
+        let keys: Vec<&str> = report.glyphs.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, vec!["U+0042", "U+0043"]);
+
+        let reshaped = &report.glyphs[0];
+        assert!((reshaped.outline_distance - 0.5).abs() < 0.02);
+        assert!(reshaped.bounds_changed && !reshaped.advance_changed);
+        assert!(reshaped.svg_path_a.is_some() && reshaped.svg_path_b.is_some());
+        let widened = &report.glyphs[1];
+        assert_eq!(widened.outline_distance, 0.0);
+        assert!(widened.advance_changed);
+        assert_eq!(widened.svg_path_a, None);
+
+        // By glyph ID the extra glyph in B is the only difference in membership
+        let report = diff_glyphs(
+            &font_a,
+            &font_b,
+            GlyphMatching::GlyphId,
+            &GlyphDiffOptions {
+                include_unchanged: true,
+                ..GlyphDiffOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(report.matched, 4);
+        assert_eq!(report.glyphs.len(), 4);
+        assert_eq!(report.only_in_b, vec!["4".to_string()]);
+    }
+}
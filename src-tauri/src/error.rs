@@ -0,0 +1,187 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::json;
+use skrifa::raw::ReadError;
+use std::fmt::{Debug, Display};
+use thiserror::Error;
+
+/// Error returned by font operations and Tauri commands. It reaches the
+/// frontend as `{ kind, message, context }`, so callers can branch on `kind`
+/// instead of matching message text.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum FontError {
+    #[error("Failed to read {path}: {message}")]
+    Io { path: String, message: String },
+    #[error("Invalid font file: {message}")]
+    InvalidFont { message: String },
+    #[error("Table '{tag}' not found in font")]
+    TableMissing { tag: String },
+    #[error("Failed to read {tag} table: {message}")]
+    TableMalformed { tag: String, message: String },
+    #[error("Glyph {gid} not found")]
+    GlyphNotFound { gid: u32 },
+    #[error("Unsupported: {what}")]
+    Unsupported { what: String },
+    /// A caller-supplied argument was rejected.
+    #[error("{message}")]
+    InvalidInput { message: String },
+    /// Compiling a table or assembling the font failed.
+    #[error("Failed to build {tag} table: {message}")]
+    BuildFailed { tag: String, message: String },
+    #[error("Failed to write {path}: {message}")]
+    WriteFailed { path: String, message: String },
+    #[error("{}: {path}", crate::font_parser::CHANGED_ON_DISK)]
+    ChangedOnDisk { path: String },
+    #[error("Operation cancelled")]
+    Cancelled,
+    /// Failures outside the font data, such as the window system or a
+    /// background task.
+    #[error("{message}")]
+    Other { message: String },
+}
+
+impl FontError {
+    pub fn io(path: &str, e: impl Display) -> Self {
+        Self::Io {
+            path: path.to_string(),
+            message: e.to_string(),
+        }
+    }
+
+    pub fn invalid_font(e: impl Debug) -> Self {
+        Self::InvalidFont {
+            message: format!("{:?}", e),
+        }
+    }
+
+    /// Bytes that are not a font we can read, for a reason we describe.
+    pub fn invalid_data(message: impl Into<String>) -> Self {
+        Self::InvalidFont {
+            message: message.into(),
+        }
+    }
+
+    /// A table failed to parse; a missing table gets its own kind.
+    pub fn table(tag: &str, e: ReadError) -> Self {
+        match e {
+            ReadError::TableIsMissing(_) => Self::missing(tag),
+            e => Self::TableMalformed {
+                tag: tag.to_string(),
+                message: format!("{:?}", e),
+            },
+        }
+    }
+
+    /// A table whose data breaks its format, found by our own parsing.
+    pub fn malformed(tag: &str, message: impl Display) -> Self {
+        Self::TableMalformed {
+            tag: tag.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn missing(tag: &str) -> Self {
+        Self::TableMissing {
+            tag: tag.to_string(),
+        }
+    }
+
+    pub fn unsupported(what: impl Into<String>) -> Self {
+        Self::Unsupported { what: what.into() }
+    }
+
+    pub fn input(message: impl Into<String>) -> Self {
+        Self::InvalidInput {
+            message: message.into(),
+        }
+    }
+
+    pub fn build(tag: &str, e: impl Debug) -> Self {
+        Self::BuildFailed {
+            tag: tag.to_string(),
+            message: format!("{:?}", e),
+        }
+    }
+
+    pub fn write(path: &str, e: impl Display) -> Self {
+        Self::WriteFailed {
+            path: path.to_string(),
+            message: e.to_string(),
+        }
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        Self::Other {
+            message: message.into(),
+        }
+    }
+
+    /// Stable snake_case name the frontend branches on.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Io { .. } => "io",
+            Self::InvalidFont { .. } => "invalid_font",
+            Self::TableMissing { .. } => "table_missing",
+            Self::TableMalformed { .. } => "table_malformed",
+            Self::GlyphNotFound { .. } => "glyph_not_found",
+            Self::Unsupported { .. } => "unsupported",
+            Self::InvalidInput { .. } => "invalid_input",
+            Self::BuildFailed { .. } => "build_failed",
+            Self::WriteFailed { .. } => "write_failed",
+            Self::ChangedOnDisk { .. } => "changed_on_disk",
+            Self::Cancelled => "cancelled",
+            Self::Other { .. } => "other",
+        }
+    }
+
+    /// Structured fields of the error, for the frontend to act on.
+    fn context(&self) -> serde_json::Value {
+        match self {
+            Self::Io { path, .. } | Self::WriteFailed { path, .. } => json!({ "path": path }),
+            Self::ChangedOnDisk { path } => json!({ "path": path }),
+            Self::TableMissing { tag }
+            | Self::TableMalformed { tag, .. }
+            | Self::BuildFailed { tag, .. } => json!({ "tag": tag }),
+            Self::GlyphNotFound { gid } => json!({ "gid": gid }),
+            Self::Unsupported { what } => json!({ "what": what }),
+            Self::InvalidFont { .. }
+            | Self::InvalidInput { .. }
+            | Self::Cancelled
+            | Self::Other { .. } => json!({}),
+        }
+    }
+}
+
+impl Serialize for FontError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("FontError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("context", &self.context())?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_font_error_serializes_kind_message_context() {
+        let value = serde_json::to_value(FontError::missing("glyf")).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "kind": "table_missing",
+                "message": "Table 'glyf' not found in font",
+                "context": { "tag": "glyf" },
+            })
+        );
+
+        let missing =
+            FontError::table("GSUB", ReadError::TableIsMissing(skrifa::Tag::new(b"GSUB")));
+        assert_eq!(missing.kind(), "table_missing");
+        let malformed = FontError::table("head", ReadError::OutOfBounds);
+        assert_eq!(malformed.kind(), "table_malformed");
+        assert_eq!(FontError::Cancelled.to_string(), "Operation cancelled");
+    }
+}
@@ -0,0 +1,714 @@
+use crate::collection::{is_collection, member_key};
+use crate::error::FontError;
+use crate::font_parser::{get_font_bytes, FontCache};
+use crate::woff::decode_font_container;
+use serde::Serialize;
+use skrifa::raw::{FileRef, TableProvider};
+use skrifa::string::StringId;
+use skrifa::{FontRef, MetadataProvider};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Extensions picked up by a directory scan, lowercase.
+const FONT_EXTENSIONS: [&str; 6] = ["ttf", "otf", "woff", "woff2", "ttc", "otc"];
+
+/// Whether `path` names a font file the app can open, by extension.
+pub(crate) fn has_font_extension(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| FONT_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+}
+
+/// Metadata of one font found by a scan, read without caching the font.
+#[derive(Serialize, Debug, Clone)]
+pub struct FamilyMember {
+    /// Path to pass to parse_font; a member key for collection members.
+    pub file_path: String,
+    pub file_name: String,
+    pub collection_index: Option<u32>,
+    pub family_name: String,
+    pub style_name: String,
+    pub weight_class: Option<u16>,
+    pub width_class: Option<u16>,
+    pub is_italic: bool,
+    pub units_per_em: u16,
+    pub variation_axes: Vec<String>,
+}
+
+impl FamilyMember {
+    /// The upright regular style: named so, or weight 400 at normal width.
+    fn is_regular(&self) -> bool {
+        !self.is_italic
+            && (self.style_name.eq_ignore_ascii_case("regular")
+                || (self.weight_class == Some(400) && self.width_class.unwrap_or(5) == 5))
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FamilyIssue {
+    /// Styles disagree on head.unitsPerEm.
+    MixedUnitsPerEm { values: Vec<u16> },
+    /// Several fonts claim the same subfamily name.
+    DuplicateStyle {
+        style_name: String,
+        file_paths: Vec<String>,
+    },
+    /// No upright regular and no variable font to stand in for one.
+    MissingRegular,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FontFamily {
+    pub family_name: String,
+    /// Ordered by width, weight, then upright before italic.
+    pub members: Vec<FamilyMember>,
+    pub issues: Vec<FamilyIssue>,
+}
+
+/// A font file the scan could not read.
+#[derive(Serialize, Debug, Clone)]
+pub struct SkippedFile {
+    pub file_path: String,
+    pub error: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct DirectoryScan {
+    /// Families by name.
+    pub families: Vec<FontFamily>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+fn read_member(
+    font: &FontRef<'_>,
+    file_path: String,
+    file_name: &str,
+    collection_index: Option<u32>,
+) -> FamilyMember {
+    // Typographic names group the styles beyond the four RIBBI ones
+    let name = |ids: [StringId; 2]| {
+        ids.iter().find_map(|&id| {
+            font.localized_strings(id)
+                .english_or_first()
+                .map(|s| s.to_string())
+        })
+    };
+    let family_name = name([StringId::TYPOGRAPHIC_FAMILY_NAME, StringId::FAMILY_NAME])
+        .unwrap_or_else(|| {
+            Path::new(file_name)
+                .file_stem()
+                .map_or_else(String::new, |s| s.to_string_lossy().into_owned())
+        });
+    let style_name = name([
+        StringId::TYPOGRAPHIC_SUBFAMILY_NAME,
+        StringId::SUBFAMILY_NAME,
+    ])
+    .unwrap_or_else(|| "Regular".to_string());
+    let head = font.head().ok();
+    let os2 = font.os2().ok();
+    FamilyMember {
+        file_path,
+        file_name: file_name.to_string(),
+        collection_index,
+        family_name,
+        style_name,
+        weight_class: os2.as_ref().map(|os2| os2.us_weight_class()),
+        width_class: os2.as_ref().map(|os2| os2.us_width_class()),
+        is_italic: match (&os2, &head) {
+            (Some(os2), _) => os2.fs_selection().bits() & 0x0001 != 0,
+            (None, Some(head)) => head.mac_style().bits() & 0x0002 != 0,
+            (None, None) => false,
+        },
+        units_per_em: head.as_ref().map_or(0, |h| h.units_per_em()),
+        variation_axes: font
+            .fvar()
+            .ok()
+            .and_then(|fvar| fvar.axes().ok())
+            .map(|axes| axes.iter().map(|a| a.axis_tag().to_string()).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Every font in one file: one for an SFNT or WOFF, each member of a
+/// collection.
+fn read_file_members(path: &Path) -> Result<Vec<FamilyMember>, FontError> {
+    let file_path = path.to_string_lossy().into_owned();
+    let file_name = path
+        .file_name()
+        .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+    let bytes = fs::read(path).map_err(|e| FontError::io(&file_path, e))?;
+    let (bytes, _) = decode_font_container(bytes)?;
+    if is_collection(&bytes) {
+        let count = match FileRef::new(&bytes).map_err(FontError::invalid_font)? {
+            FileRef::Collection(collection) => collection.len(),
+            FileRef::Font(_) => 1,
+        };
+        (0..count)
+            .map(|index| {
+                let font = FontRef::from_index(&bytes, index).map_err(FontError::invalid_font)?;
+                let key = member_key(&file_path, index);
+                Ok(read_member(&font, key, &file_name, Some(index)))
+            })
+            .collect()
+    } else {
+        let font = FontRef::new(&bytes).map_err(FontError::invalid_font)?;
+        Ok(vec![read_member(&font, file_path, &file_name, None)])
+    }
+}
+
+fn family_issues(members: &[FamilyMember]) -> Vec<FamilyIssue> {
+    let mut issues = Vec::new();
+    let mut units_per_em: Vec<u16> = members.iter().map(|m| m.units_per_em).collect();
+    units_per_em.sort_unstable();
+    units_per_em.dedup();
+    if units_per_em.len() > 1 {
+        issues.push(FamilyIssue::MixedUnitsPerEm {
+            values: units_per_em,
+        });
+    }
+
+    let mut by_style: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for member in members {
+        by_style
+            .entry(member.style_name.clone())
+            .or_default()
+            .push(member.file_path.clone());
+    }
+    for (style_name, file_paths) in by_style {
+        if file_paths.len() > 1 {
+            issues.push(FamilyIssue::DuplicateStyle {
+                style_name,
+                file_paths,
+            });
+        }
+    }
+
+    let has_variable = members.iter().any(|m| !m.variation_axes.is_empty());
+    if !has_variable && !members.iter().any(FamilyMember::is_regular) {
+        issues.push(FamilyIssue::MissingRegular);
+    }
+    issues
+}
+
+/// Read the fonts directly inside `dir_path` (not its subdirectories) and
+/// group them into families, flagging inconsistencies between the styles of
+/// each. Nothing is cached; members are opened with parse_font as needed.
+pub fn scan_font_directory(dir_path: &str) -> Result<DirectoryScan, FontError> {
+    let entries = fs::read_dir(dir_path).map_err(|e| FontError::io(dir_path, e))?;
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && has_font_extension(path))
+        .collect();
+    paths.sort();
+
+    let mut scan = DirectoryScan::default();
+    let mut families: BTreeMap<String, Vec<FamilyMember>> = BTreeMap::new();
+    for path in paths {
+        match read_file_members(&path) {
+            Ok(members) => {
+                for member in members {
+                    families
+                        .entry(member.family_name.clone())
+                        .or_default()
+                        .push(member);
+                }
+            }
+            Err(error) => scan.skipped.push(SkippedFile {
+                file_path: path.to_string_lossy().into_owned(),
+                error: error.to_string(),
+            }),
+        }
+    }
+
+    scan.families = families
+        .into_iter()
+        .map(|(family_name, mut members)| {
+            members.sort_by(|a, b| {
+                (a.width_class, a.weight_class, a.is_italic, &a.style_name).cmp(&(
+                    b.width_class,
+                    b.weight_class,
+                    b.is_italic,
+                    &b.style_name,
+                ))
+            });
+            FontFamily {
+                issues: family_issues(&members),
+                family_name,
+                members,
+            }
+        })
+        .collect();
+    Ok(scan)
+}
+
+/// How the cells of a consistency row are judged.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsistencyRule {
+    /// Every style should share one value; cells off the most common value
+    /// are flagged.
+    Agree,
+    /// Each style is judged on its own, against its other records.
+    PerFont,
+    /// Values should order the styles the way their names do.
+    NameOrder,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum FieldValue {
+    Number(i64),
+    Text(String),
+}
+
+/// One row of the consistency matrix.
+#[derive(Serialize, Debug, Clone)]
+pub struct ConsistencyRow {
+    /// Table and field, e.g. "OS/2.usWinAscent".
+    pub field: String,
+    pub rule: ConsistencyRule,
+    /// One per font, in report order; None where the table or record is
+    /// missing.
+    pub values: Vec<Option<FieldValue>>,
+    pub mismatched: Vec<bool>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FamilyConsistencyReport {
+    /// The matrix columns, in the order the paths were given.
+    pub fonts: Vec<FamilyMember>,
+    pub rows: Vec<ConsistencyRow>,
+    /// Rows with at least one flagged cell.
+    pub mismatched_rows: usize,
+}
+
+type NumericField = (&'static str, fn(&FontRef<'_>) -> Option<i64>);
+
+/// Metrics that have to match across styles for lines to lay out alike.
+const AGREED_FIELDS: [NumericField; 11] = [
+    ("head.unitsPerEm", |f| {
+        f.head().ok().map(|t| t.units_per_em() as i64)
+    }),
+    ("hhea.ascender", |f| {
+        f.hhea().ok().map(|t| t.ascender().to_i16() as i64)
+    }),
+    ("hhea.descender", |f| {
+        f.hhea().ok().map(|t| t.descender().to_i16() as i64)
+    }),
+    ("hhea.lineGap", |f| {
+        f.hhea().ok().map(|t| t.line_gap().to_i16() as i64)
+    }),
+    ("OS/2.sTypoAscender", |f| {
+        f.os2().ok().map(|t| t.s_typo_ascender() as i64)
+    }),
+    ("OS/2.sTypoDescender", |f| {
+        f.os2().ok().map(|t| t.s_typo_descender() as i64)
+    }),
+    ("OS/2.sTypoLineGap", |f| {
+        f.os2().ok().map(|t| t.s_typo_line_gap() as i64)
+    }),
+    ("OS/2.usWinAscent", |f| {
+        f.os2().ok().map(|t| t.us_win_ascent() as i64)
+    }),
+    ("OS/2.usWinDescent", |f| {
+        f.os2().ok().map(|t| t.us_win_descent() as i64)
+    }),
+    ("post.underlinePosition", |f| {
+        f.post()
+            .ok()
+            .map(|t| t.underline_position().to_i16() as i64)
+    }),
+    ("post.underlineThickness", |f| {
+        f.post()
+            .ok()
+            .map(|t| t.underline_thickness().to_i16() as i64)
+    }),
+];
+
+/// Weight and width classes implied by words in a style name, longest
+/// words first so "SemiBold" is not read as "Bold".
+const WEIGHT_WORDS: [(&str, u16); 15] = [
+    ("extralight", 200),
+    ("ultralight", 200),
+    ("semibold", 600),
+    ("demibold", 600),
+    ("extrabold", 800),
+    ("ultrabold", 800),
+    ("hairline", 100),
+    ("regular", 400),
+    ("medium", 500),
+    ("black", 900),
+    ("heavy", 900),
+    ("light", 300),
+    ("thin", 100),
+    ("book", 400),
+    ("bold", 700),
+];
+const WIDTH_WORDS: [(&str, u16); 8] = [
+    ("ultracondensed", 1),
+    ("extracondensed", 2),
+    ("semicondensed", 4),
+    ("condensed", 3),
+    ("semiexpanded", 6),
+    ("extraexpanded", 8),
+    ("ultraexpanded", 9),
+    ("expanded", 7),
+];
+
+/// The class a style name implies, `default` when it names none.
+fn implied_class(style_name: &str, words: &[(&str, u16)], default: u16) -> u16 {
+    let name: String = style_name
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+    words
+        .iter()
+        .find(|(word, _)| name.contains(word))
+        .map_or(default, |&(_, class)| class)
+}
+
+/// Flag the cells that differ from the most common value, the first
+/// style's value winning ties.
+fn flag_outliers(values: &[Option<FieldValue>]) -> Vec<bool> {
+    let count = |value: &Option<FieldValue>| values.iter().filter(|v| *v == value).count();
+    let common = values.iter().rev().max_by_key(|v| count(v));
+    values.iter().map(|v| Some(v) != common).collect()
+}
+
+/// Flag every pair of styles whose values order them differently from
+/// their names, and styles missing the value.
+fn flag_misordered(implied: &[u16], actual: &[Option<u16>]) -> Vec<bool> {
+    let mut flags: Vec<bool> = actual.iter().map(Option::is_none).collect();
+    for i in 0..actual.len() {
+        for j in i + 1..actual.len() {
+            if let (Some(a), Some(b)) = (actual[i], actual[j]) {
+                if implied[i].cmp(&implied[j]) != a.cmp(&b) {
+                    flags[i] = true;
+                    flags[j] = true;
+                }
+            }
+        }
+    }
+    flags
+}
+
+/// Style bits that contradict each other or the style name.
+fn style_bits_incoherent(font: &FontRef<'_>, member: &FamilyMember) -> bool {
+    let mac = font.head().map_or(0, |h| h.mac_style().bits());
+    let (mac_bold, mac_italic) = (mac & 0x0001 != 0, mac & 0x0002 != 0);
+    let name = member.style_name.to_lowercase();
+    let named_italic = name.contains("italic") || name.contains("oblique");
+    match font.os2() {
+        Ok(os2) => {
+            let fs = os2.fs_selection().bits();
+            let (italic, bold, regular) = (fs & 0x0001 != 0, fs & 0x0020 != 0, fs & 0x0040 != 0);
+            italic != mac_italic
+                || bold != mac_bold
+                || (regular && (italic || bold))
+                || italic != named_italic
+        }
+        Err(_) => mac_italic != named_italic,
+    }
+}
+
+fn localized_name(font: &FontRef<'_>, id: StringId) -> Option<String> {
+    font.localized_strings(id)
+        .english_or_first()
+        .map(|s| s.to_string())
+}
+
+fn consistency_rows(fonts: &[(FontRef<'_>, FamilyMember)]) -> Vec<ConsistencyRow> {
+    let row = |field: &str, rule, values: Vec<Option<FieldValue>>, mismatched| ConsistencyRow {
+        field: field.to_string(),
+        rule,
+        values,
+        mismatched,
+    };
+    let mut rows = Vec::new();
+    for (field, read) in AGREED_FIELDS {
+        let values: Vec<_> = fonts
+            .iter()
+            .map(|(font, _)| read(font).map(FieldValue::Number))
+            .collect();
+        let mismatched = flag_outliers(&values);
+        rows.push(row(field, ConsistencyRule::Agree, values, mismatched));
+    }
+
+    // The typographic family must match; legacy family names may add the
+    // style to it ("Sans Light") but not start over
+    let typographic: Vec<_> = fonts
+        .iter()
+        .map(|(font, _)| {
+            localized_name(font, StringId::TYPOGRAPHIC_FAMILY_NAME)
+                .or_else(|| localized_name(font, StringId::FAMILY_NAME))
+                .map(FieldValue::Text)
+        })
+        .collect();
+    let legacy: Vec<_> = fonts
+        .iter()
+        .map(|(font, _)| localized_name(font, StringId::FAMILY_NAME))
+        .collect();
+    let legacy_mismatched = legacy
+        .iter()
+        .zip(&typographic)
+        .map(|(legacy, family)| match (legacy, family) {
+            (Some(legacy), Some(FieldValue::Text(family))) => {
+                legacy != family && !legacy.starts_with(&format!("{} ", family))
+            }
+            _ => true,
+        })
+        .collect();
+    let mismatched = flag_outliers(&typographic);
+    rows.push(row(
+        "name.16",
+        ConsistencyRule::Agree,
+        typographic,
+        mismatched,
+    ));
+    rows.push(row(
+        "name.1",
+        ConsistencyRule::PerFont,
+        legacy
+            .into_iter()
+            .map(|n| n.map(FieldValue::Text))
+            .collect(),
+        legacy_mismatched,
+    ));
+
+    let incoherent: Vec<bool> = fonts
+        .iter()
+        .map(|(font, member)| style_bits_incoherent(font, member))
+        .collect();
+    let fs_selection = fonts
+        .iter()
+        .map(|(font, _)| {
+            let os2 = font.os2().ok()?;
+            Some(FieldValue::Number(os2.fs_selection().bits() as i64))
+        })
+        .collect();
+    let mac_style = fonts
+        .iter()
+        .map(|(font, _)| {
+            let head = font.head().ok()?;
+            Some(FieldValue::Number(head.mac_style().bits() as i64))
+        })
+        .collect();
+    rows.push(row(
+        "OS/2.fsSelection",
+        ConsistencyRule::PerFont,
+        fs_selection,
+        incoherent.clone(),
+    ));
+    rows.push(row(
+        "head.macStyle",
+        ConsistencyRule::PerFont,
+        mac_style,
+        incoherent,
+    ));
+
+    for (field, words, default, read) in [
+        (
+            "OS/2.usWeightClass",
+            &WEIGHT_WORDS[..],
+            400,
+            (|m: &FamilyMember| m.weight_class) as fn(&FamilyMember) -> Option<u16>,
+        ),
+        ("OS/2.usWidthClass", &WIDTH_WORDS[..], 5, |m| m.width_class),
+    ] {
+        let implied: Vec<u16> = fonts
+            .iter()
+            .map(|(_, m)| implied_class(&m.style_name, words, default))
+            .collect();
+        let actual: Vec<Option<u16>> = fonts.iter().map(|(_, m)| read(m)).collect();
+        let mismatched = flag_misordered(&implied, &actual);
+        let values = actual
+            .into_iter()
+            .map(|v| v.map(|v| FieldValue::Number(v as i64)))
+            .collect();
+        rows.push(row(field, ConsistencyRule::NameOrder, values, mismatched));
+    }
+    rows
+}
+
+/// Compare the styles of one family side by side: shared metrics, family
+/// names, style bits and weight/width classes, with the cells that break
+/// consistency flagged.
+pub fn check_family_consistency(
+    file_paths: &[String],
+    cache: &FontCache,
+) -> Result<FamilyConsistencyReport, FontError> {
+    if file_paths.is_empty() {
+        return Err(FontError::input("No fonts to compare"));
+    }
+    let bytes = file_paths
+        .iter()
+        .map(|path| get_font_bytes(path, cache))
+        .collect::<Result<Vec<_>, _>>()?;
+    let fonts = file_paths
+        .iter()
+        .zip(&bytes)
+        .map(|(path, bytes)| {
+            let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+            let file_name = Path::new(path)
+                .file_name()
+                .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+            let member = read_member(&font, path.clone(), &file_name, None);
+            Ok((font, member))
+        })
+        .collect::<Result<Vec<_>, FontError>>()?;
+    let rows = consistency_rows(&fonts);
+    Ok(FamilyConsistencyReport {
+        mismatched_rows: rows.iter().filter(|r| r.mismatched.contains(&true)).count(),
+        fonts: fonts.into_iter().map(|(_, member)| member).collect(),
+        rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{name_table, TestFontBuilder};
+    use write_fonts::tables::os2::{Os2, SelectionFlags};
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    fn font_with(names: &[(u16, &str)], os2: Os2, upem: u16) -> Vec<u8> {
+        let names: Vec<(u16, Vec<u8>)> = names.iter().map(|&(id, s)| (id, utf16(s))).collect();
+        let records: Vec<_> = names
+            .iter()
+            .map(|(id, s)| (3, 1, 0x409, *id, s.as_slice()))
+            .collect();
+        TestFontBuilder::new()
+            .units_per_em(upem)
+            .glyph(Vec::new(), 500)
+            .table(b"name", name_table(&records))
+            .table(b"OS/2", write_fonts::dump_table(&os2).unwrap())
+            .build()
+    }
+
+    fn styled_font(family: &str, style: &str, weight: u16, italic: bool, upem: u16) -> Vec<u8> {
+        let os2 = Os2 {
+            us_weight_class: weight,
+            us_width_class: 5,
+            fs_selection: SelectionFlags::from_bits_truncate(if italic { 0x0001 } else { 0x0040 }),
+            ..Default::default()
+        };
+        font_with(&[(1, family), (2, style)], os2, upem)
+    }
+
+    #[test]
+    fn test_scan_groups_families_and_flags_issues() {
+        let dir = std::env::temp_dir().join(format!("typebrew-family-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, bytes: &[u8]| fs::write(dir.join(name), bytes).unwrap();
+        write(
+            "Sans-Bold.ttf",
+            &styled_font("Sans", "Bold", 700, false, 1000),
+        );
+        write(
+            "Sans-Regular.otf",
+            &styled_font("Sans", "Regular", 400, false, 1000),
+        );
+        write(
+            "Sans-Italic.ttf",
+            &styled_font("Sans", "Italic", 400, true, 2048),
+        );
+        write(
+            "Sans-Italic-copy.ttf",
+            &styled_font("Sans", "Italic", 400, true, 1000),
+        );
+        let serif = crate::woff::sfnt_to_woff(&styled_font("Serif", "Bold", 700, false, 1000));
+        write("Serif-Bold.woff", &serif.unwrap());
+        write("broken.ttf", b"not a font");
+        write("notes.txt", b"ignored");
+
+        let scan = scan_font_directory(dir.to_str().unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(scan.skipped.len(), 1);
+        assert!(scan.skipped[0].file_path.ends_with("broken.ttf"));
+        let names: Vec<&str> = scan
+            .families
+            .iter()
+            .map(|f| f.family_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Sans", "Serif"]);
+
+        let sans = &scan.families[0];
+        let styles: Vec<&str> = sans.members.iter().map(|m| m.style_name.as_str()).collect();
+        assert_eq!(styles, vec!["Regular", "Italic", "Italic", "Bold"]);
+        assert_eq!(sans.issues.len(), 2);
+        assert_eq!(
+            sans.issues[0],
+            FamilyIssue::MixedUnitsPerEm {
+                values: vec![1000, 2048]
+            }
+        );
+        assert!(matches!(
+            &sans.issues[1],
+            FamilyIssue::DuplicateStyle { style_name, file_paths }
+                if style_name == "Italic" && file_paths.len() == 2
+        ));
+
+        let serif = &scan.families[1];
+        assert_eq!(serif.members[0].weight_class, Some(700));
+        assert_eq!(serif.issues, vec![FamilyIssue::MissingRegular]);
+    }
+
+    #[test]
+    fn test_family_consistency_flags_mismatched_cells() {
+        let os2 = |weight: u16, fs_selection: u16, win_ascent: u16| Os2 {
+            us_weight_class: weight,
+            us_width_class: 5,
+            fs_selection: SelectionFlags::from_bits_truncate(fs_selection),
+            us_win_ascent: win_ascent,
+            ..Default::default()
+        };
+        let cache = FontCache::new();
+        let regular = font_with(&[(1, "Sans"), (2, "Regular")], os2(400, 0x40, 900), 1000);
+        // Bold bit without the matching macStyle bit, and taller win metrics
+        let bold = font_with(&[(1, "Sans"), (2, "Bold")], os2(700, 0x20, 950), 1000);
+        // A Light heavier than the Regular
+        let light = font_with(
+            &[
+                (1, "Sans Light"),
+                (2, "Regular"),
+                (16, "Sans"),
+                (17, "Light"),
+            ],
+            os2(500, 0x40, 900),
+            1000,
+        );
+        cache.insert("Regular.ttf".to_string(), regular);
+        cache.insert("Bold.ttf".to_string(), bold);
+        cache.insert("Light.ttf".to_string(), light);
+        let paths: Vec<String> = ["Regular.ttf", "Bold.ttf", "Light.ttf"]
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+
+        let report = check_family_consistency(&paths, &cache).unwrap();
+        let styles: Vec<&str> = report.fonts.iter().map(|m| m.style_name.as_str()).collect();
+        assert_eq!(styles, vec!["Regular", "Bold", "Light"]);
+        let row = |field: &str| report.rows.iter().find(|r| r.field == field).unwrap();
+
+        let win_ascent = row("OS/2.usWinAscent");
+        assert_eq!(win_ascent.values[1], Some(FieldValue::Number(950)));
+        assert_eq!(win_ascent.mismatched, vec![false, true, false]);
+        assert_eq!(row("hhea.ascender").mismatched, vec![false; 3]);
+        assert_eq!(row("post.underlinePosition").values, vec![None; 3]);
+        assert_eq!(row("name.16").mismatched, vec![false; 3]);
+        assert_eq!(row("name.1").mismatched, vec![false; 3]);
+        assert_eq!(row("head.macStyle").mismatched, vec![false, true, false]);
+        assert_eq!(
+            row("OS/2.usWeightClass").mismatched,
+            vec![true, false, true]
+        );
+        assert_eq!(row("OS/2.usWidthClass").mismatched, vec![false; 3]);
+        assert_eq!(report.mismatched_rows, 4);
+    }
+}
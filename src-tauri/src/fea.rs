@@ -0,0 +1,1998 @@
+use crate::error::FontError;
+use crate::font_parser::{
+    editable_font_bytes, get_font_bytes, save_edited_font, write_atomically, FontCache,
+};
+use crate::gpos::{anchor_coordinates, for_each_position_lookup};
+use crate::gsub::for_each_substitution_lookup;
+use serde::{Deserialize, Serialize};
+use skrifa::raw::tables::gpos::{PairPos, PositionSubtables, SinglePos, ValueRecord};
+use skrifa::raw::tables::gsub::{SingleSubst, SubstitutionSubtables};
+use skrifa::raw::tables::layout::{FeatureList, ScriptList};
+use skrifa::raw::types::Tag;
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::{GlyphId, MetadataProvider};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use write_fonts::from_obj::ToOwnedTable;
+use write_fonts::tables::gpos::{
+    Class1Record, Class2Record, Gpos, PairPos as WritePairPos, PairSet, PairValueRecord,
+    PositionLookup, PositionLookupList, SinglePos as WriteSinglePos,
+    ValueRecord as WriteValueRecord,
+};
+use write_fonts::tables::gsub::{
+    Gsub, Ligature, LigatureSet, LigatureSubstFormat1, SingleSubst as WriteSingleSubst,
+    SubstitutionLookup, SubstitutionLookupList,
+};
+use write_fonts::tables::layout::{
+    ClassDef, CoverageTable, Feature, FeatureList as WriteFeatureList, FeatureRecord, LangSys,
+    LangSysRecord, Lookup, LookupFlag, Script, ScriptList as WriteScriptList, ScriptRecord,
+};
+use write_fonts::types::GlyphId16;
+use write_fonts::FontBuilder;
+
+const DFLT: Tag = Tag::new(b"DFLT");
+const DFLT_LANGUAGE: Tag = Tag::new(b"dflt");
+
+/// A tag as feature files write it, without the padding spaces.
+fn fea_tag(tag: Tag) -> String {
+    tag.to_string().trim_end().to_string()
+}
+
+/// Glyph names as feature files refer to them: post/CFF names, or `gidN`.
+struct GlyphNames(Vec<String>);
+
+impl GlyphNames {
+    fn new(font: &skrifa::FontRef, num_glyphs: u32) -> Self {
+        let names = font.glyph_names();
+        GlyphNames(
+            (0..num_glyphs)
+                .map(|gid| {
+                    names
+                        .get(GlyphId::new(gid))
+                        .map(|n| n.to_string())
+                        .unwrap_or_else(|| format!("gid{}", gid))
+                })
+                .collect(),
+        )
+    }
+
+    fn name(&self, gid: u32) -> String {
+        self.0
+            .get(gid as usize)
+            .cloned()
+            .unwrap_or_else(|| format!("gid{}", gid))
+    }
+
+    /// A bracketed class, even for a single glyph, so pair rules stay
+    /// class pairs.
+    fn class(&self, gids: &[u32]) -> String {
+        let names: Vec<String> = gids.iter().map(|&gid| self.name(gid)).collect();
+        format!("[{}]", names.join(" "))
+    }
+}
+
+/// One (script, language) a feature is registered under, with its lookups.
+struct FeatureUse {
+    feature: Tag,
+    script: Tag,
+    language: Tag,
+    lookups: Vec<u16>,
+}
+
+/// Every feature of a GSUB/GPOS script list, per script and language
+/// system, in script list order. The required feature is listed like any
+/// other.
+fn feature_uses(scripts: &ScriptList, features: &FeatureList) -> Vec<FeatureUse> {
+    let mut uses = Vec::new();
+    for script_record in scripts.script_records() {
+        let Ok(script) = script_record.script(scripts.offset_data()) else {
+            continue;
+        };
+        let mut systems = Vec::new();
+        if let Some(Ok(lang_sys)) = script.default_lang_sys() {
+            systems.push((DFLT_LANGUAGE, lang_sys));
+        }
+        for record in script.lang_sys_records() {
+            if let Ok(lang_sys) = record.lang_sys(script.offset_data()) {
+                systems.push((record.lang_sys_tag(), lang_sys));
+            }
+        }
+        for (language, lang_sys) in systems {
+            let required = Some(lang_sys.required_feature_index()).filter(|&i| i != 0xFFFF);
+            let indices = required
+                .into_iter()
+                .chain(lang_sys.feature_indices().iter().map(|i| i.get()));
+            for index in indices {
+                let Some(record) = features.feature_records().get(index as usize) else {
+                    continue;
+                };
+                let Ok(feature) = record.feature(features.offset_data()) else {
+                    continue;
+                };
+                uses.push(FeatureUse {
+                    feature: record.feature_tag(),
+                    script: script_record.script_tag(),
+                    language,
+                    lookups: feature
+                        .lookup_list_indices()
+                        .iter()
+                        .map(|i| i.get())
+                        .collect(),
+                });
+            }
+        }
+    }
+    uses
+}
+
+/// `lookupflag` statement for the flag bits a feature file can name.
+/// Mark filtering sets and attachment classes need GDEF classes we don't
+/// export, so they are left as a comment.
+fn lookup_flag_lines(flag: u16) -> Vec<String> {
+    let names: Vec<&str> = [
+        (0x0001, "RightToLeft"),
+        (0x0002, "IgnoreBaseGlyphs"),
+        (0x0004, "IgnoreLigatures"),
+        (0x0008, "IgnoreMarks"),
+    ]
+    .iter()
+    .filter(|(bit, _)| flag & bit != 0)
+    .map(|(_, name)| *name)
+    .collect();
+    let mut lines = Vec::new();
+    if !names.is_empty() {
+        lines.push(format!("lookupflag {};", names.join(" ")));
+    }
+    if flag & 0xFF10 != 0 {
+        lines.push(format!(
+            "# lookupflag 0x{:04X}: mark filtering set / attachment class not exported",
+            flag
+        ));
+    }
+    lines
+}
+
+/// A value record as a feature file writes it: a bare advance when that is
+/// all it holds, otherwise `<xPlacement yPlacement xAdvance yAdvance>`.
+/// None when it holds nothing.
+fn value_record(record: &ValueRecord) -> Option<String> {
+    match (
+        record.x_placement(),
+        record.y_placement(),
+        record.x_advance(),
+        record.y_advance(),
+    ) {
+        (None, None, None, None) => None,
+        (None, None, Some(x), None) => Some(x.to_string()),
+        (xp, yp, xa, ya) => Some(format!(
+            "<{} {} {} {}>",
+            xp.unwrap_or(0),
+            yp.unwrap_or(0),
+            xa.unwrap_or(0),
+            ya.unwrap_or(0)
+        )),
+    }
+}
+
+fn is_zero(record: &ValueRecord) -> bool {
+    [
+        record.x_placement(),
+        record.y_placement(),
+        record.x_advance(),
+        record.y_advance(),
+    ]
+    .iter()
+    .all(|v| v.unwrap_or(0) == 0)
+}
+
+/// `pos` rule for a pair, or None when neither side adjusts anything.
+fn pair_rule(left: &str, right: &str, first: &ValueRecord, second: &ValueRecord) -> Option<String> {
+    match (value_record(first), value_record(second)) {
+        (None, None) => None,
+        (Some(first), None) => Some(format!("pos {} {} {};", left, right, first)),
+        (first, Some(second)) => Some(format!(
+            "pos {} {} {} {};",
+            left,
+            first.unwrap_or_else(|| "<0 0 0 0>".to_string()),
+            right,
+            second
+        )),
+    }
+}
+
+/// Rules of a GSUB lookup this export understands; None for the other types.
+fn substitution_rules(
+    subtables: SubstitutionSubtables<'_>,
+    names: &GlyphNames,
+) -> Option<Vec<String>> {
+    let mut rules = Vec::new();
+    match subtables {
+        SubstitutionSubtables::Single(subtables) => {
+            for sub in subtables.iter().flatten() {
+                match sub {
+                    SingleSubst::Format1(sub) => {
+                        let Ok(coverage) = sub.coverage() else {
+                            continue;
+                        };
+                        let delta = sub.delta_glyph_id() as i32;
+                        for gid in coverage.iter() {
+                            let out = (gid.to_u16() as i32 + delta).rem_euclid(0x10000);
+                            rules.push(format!(
+                                "sub {} by {};",
+                                names.name(gid.to_u32()),
+                                names.name(out as u32)
+                            ));
+                        }
+                    }
+                    SingleSubst::Format2(sub) => {
+                        let Ok(coverage) = sub.coverage() else {
+                            continue;
+                        };
+                        for (gid, out) in coverage.iter().zip(sub.substitute_glyph_ids()) {
+                            rules.push(format!(
+                                "sub {} by {};",
+                                names.name(gid.to_u32()),
+                                names.name(out.get().to_u32())
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        SubstitutionSubtables::Multiple(subtables) => {
+            for sub in subtables.iter().flatten() {
+                let Ok(coverage) = sub.coverage() else {
+                    continue;
+                };
+                for (gid, sequence) in coverage.iter().zip(sub.sequences().iter()) {
+                    let Ok(sequence) = sequence else {
+                        continue;
+                    };
+                    let outputs: Vec<String> = sequence
+                        .substitute_glyph_ids()
+                        .iter()
+                        .map(|g| names.name(g.get().to_u32()))
+                        .collect();
+                    rules.push(format!(
+                        "sub {} by {};",
+                        names.name(gid.to_u32()),
+                        outputs.join(" ")
+                    ));
+                }
+            }
+        }
+        SubstitutionSubtables::Alternate(subtables) => {
+            for sub in subtables.iter().flatten() {
+                let Ok(coverage) = sub.coverage() else {
+                    continue;
+                };
+                for (gid, set) in coverage.iter().zip(sub.alternate_sets().iter()) {
+                    let Ok(set) = set else {
+                        continue;
+                    };
+                    let alternates: Vec<u32> = set
+                        .alternate_glyph_ids()
+                        .iter()
+                        .map(|g| g.get().to_u32())
+                        .collect();
+                    rules.push(format!(
+                        "sub {} from {};",
+                        names.name(gid.to_u32()),
+                        names.class(&alternates)
+                    ));
+                }
+            }
+        }
+        SubstitutionSubtables::Ligature(subtables) => {
+            for sub in subtables.iter().flatten() {
+                let Ok(coverage) = sub.coverage() else {
+                    continue;
+                };
+                for (first, set) in coverage.iter().zip(sub.ligature_sets().iter()) {
+                    for ligature in set.iter().flat_map(|set| set.ligatures().iter().flatten()) {
+                        let mut inputs = vec![names.name(first.to_u32())];
+                        inputs.extend(
+                            ligature
+                                .component_glyph_ids()
+                                .iter()
+                                .map(|g| names.name(g.get().to_u32())),
+                        );
+                        rules.push(format!(
+                            "sub {} by {};",
+                            inputs.join(" "),
+                            names.name(ligature.ligature_glyph().to_u32())
+                        ));
+                    }
+                }
+            }
+        }
+        SubstitutionSubtables::Contextual(_)
+        | SubstitutionSubtables::ChainContextual(_)
+        | SubstitutionSubtables::Reverse(_) => return None,
+    }
+    Some(rules)
+}
+
+/// Like `substitution_rules`, for GPOS. Mark-to-base lookups also add
+/// their markClass definitions to `prelude`.
+fn position_rules(
+    lookup_index: u16,
+    subtables: PositionSubtables<'_>,
+    names: &GlyphNames,
+    prelude: &mut Vec<String>,
+) -> Option<Vec<String>> {
+    let mut rules = Vec::new();
+    match subtables {
+        PositionSubtables::Single(subtables) => {
+            for sub in subtables.iter().flatten() {
+                let coverage = match &sub {
+                    SinglePos::Format1(sub) => sub.coverage(),
+                    SinglePos::Format2(sub) => sub.coverage(),
+                };
+                let Ok(coverage) = coverage else {
+                    continue;
+                };
+                for (i, gid) in coverage.iter().enumerate() {
+                    let value = match &sub {
+                        SinglePos::Format1(sub) => Some(sub.value_record()),
+                        SinglePos::Format2(sub) => sub.value_records().get(i).ok(),
+                    };
+                    if let Some(value) = value.as_ref().and_then(value_record) {
+                        rules.push(format!("pos {} {};", names.name(gid.to_u32()), value));
+                    }
+                }
+            }
+        }
+        PositionSubtables::Pair(subtables) => {
+            for (index, sub) in subtables.iter().flatten().enumerate() {
+                if index > 0 {
+                    rules.push("subtable;".to_string());
+                }
+                match sub {
+                    PairPos::Format1(sub) => {
+                        let Ok(coverage) = sub.coverage() else {
+                            continue;
+                        };
+                        for (left, set) in coverage.iter().zip(sub.pair_sets().iter()) {
+                            let Ok(set) = set else {
+                                continue;
+                            };
+                            for record in set.pair_value_records().iter().flatten() {
+                                rules.extend(pair_rule(
+                                    &names.name(left.to_u32()),
+                                    &names.name(record.second_glyph().to_u32()),
+                                    record.value_record1(),
+                                    record.value_record2(),
+                                ));
+                            }
+                        }
+                    }
+                    PairPos::Format2(sub) => {
+                        let (Ok(coverage), Ok(class_def1), Ok(class_def2)) =
+                            (sub.coverage(), sub.class_def1(), sub.class_def2())
+                        else {
+                            continue;
+                        };
+                        let mut left: BTreeMap<u16, Vec<u32>> = BTreeMap::new();
+                        for gid in coverage.iter() {
+                            left.entry(class_def1.get(gid))
+                                .or_default()
+                                .push(gid.to_u32());
+                        }
+                        let mut right: BTreeMap<u16, Vec<u32>> = BTreeMap::new();
+                        for (gid, class) in class_def2.iter() {
+                            right.entry(class).or_default().push(gid.to_u32());
+                        }
+                        let mut catch_all = false;
+                        for (&class1, lefts) in &left {
+                            let Ok(row) = sub.class1_records().get(class1 as usize) else {
+                                continue;
+                            };
+                            for (class2, record) in row.class2_records().iter().enumerate() {
+                                let Ok(record) = record else {
+                                    continue;
+                                };
+                                let (first, second) =
+                                    (record.value_record1(), record.value_record2());
+                                // Class matrices are dense; cells no rule set hold zeros
+                                if is_zero(first) && is_zero(second) {
+                                    continue;
+                                }
+                                if class2 == 0 {
+                                    catch_all |= pair_rule("", "", first, second).is_some();
+                                    continue;
+                                }
+                                let Some(rights) = right.get(&(class2 as u16)) else {
+                                    continue;
+                                };
+                                rules.extend(pair_rule(
+                                    &names.class(lefts),
+                                    &names.class(rights),
+                                    first,
+                                    second,
+                                ));
+                            }
+                        }
+                        if catch_all {
+                            rules.push(
+                                "# values against right class 0 (every other glyph) not exported"
+                                    .to_string(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        PositionSubtables::MarkToBase(subtables) => {
+            for (index, sub) in subtables.iter().flatten().enumerate() {
+                let class_name =
+                    |class: u16| format!("@gpos{}_{}_mark{}", lookup_index, index, class);
+                if let (Ok(coverage), Ok(marks)) = (sub.mark_coverage(), sub.mark_array()) {
+                    for (gid, record) in coverage.iter().zip(marks.mark_records()) {
+                        if let Ok(anchor) = record.mark_anchor(marks.offset_data()) {
+                            let (x, y) = anchor_coordinates(&anchor);
+                            prelude.push(format!(
+                                "markClass {} <anchor {} {}> {};",
+                                names.name(gid.to_u32()),
+                                x,
+                                y,
+                                class_name(record.mark_class())
+                            ));
+                        }
+                    }
+                }
+                let (Ok(coverage), Ok(bases)) = (sub.base_coverage(), sub.base_array()) else {
+                    continue;
+                };
+                for (gid, record) in coverage.iter().zip(bases.base_records().iter()) {
+                    let Ok(record) = record else {
+                        continue;
+                    };
+                    let anchors: Vec<String> = record
+                        .base_anchors(bases.offset_data())
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(class, anchor)| {
+                            let (x, y) = anchor_coordinates(&anchor?.ok()?);
+                            Some(format!(
+                                "<anchor {} {}> mark {}",
+                                x,
+                                y,
+                                class_name(class as u16)
+                            ))
+                        })
+                        .collect();
+                    if !anchors.is_empty() {
+                        rules.push(format!(
+                            "pos base {} {};",
+                            names.name(gid.to_u32()),
+                            anchors.join(" ")
+                        ));
+                    }
+                }
+            }
+        }
+        PositionSubtables::Cursive(_)
+        | PositionSubtables::MarkToLig(_)
+        | PositionSubtables::MarkToMark(_)
+        | PositionSubtables::Contextual(_)
+        | PositionSubtables::ChainContextual(_) => return None,
+    }
+    Some(rules)
+}
+
+fn lookup_type_name(is_gsub: bool, lookup_type: u16) -> &'static str {
+    match (is_gsub, lookup_type) {
+        (true, 5) => "contextual substitution",
+        (true, 6) => "chaining contextual substitution",
+        (true, 8) => "reverse chaining substitution",
+        (false, 3) => "cursive attachment",
+        (false, 5) => "mark-to-ligature attachment",
+        (false, 6) => "mark-to-mark attachment",
+        (false, 7) => "contextual positioning",
+        (false, 8) => "chaining contextual positioning",
+        _ => "unknown",
+    }
+}
+
+/// What one table contributes: its feature registrations, and which of its
+/// lookups were written as `lookup` blocks.
+struct TableExport {
+    prefix: &'static str,
+    uses: Vec<FeatureUse>,
+    exported: Vec<bool>,
+}
+
+/// Serialize GSUB and GPOS as a feature file. Lookups of types with no
+/// conversion here are replaced by a comment; the returned warnings list
+/// them.
+fn font_to_fea(bytes: &[u8]) -> Result<(String, Vec<String>), FontError> {
+    let font = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let skrifa_font = skrifa::FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as u32;
+    let names = GlyphNames::new(&skrifa_font, num_glyphs);
+
+    let mut lookups = String::new();
+    let mut warnings = Vec::new();
+    let mut tables = Vec::new();
+
+    let mut emit = |prefix: &str,
+                    index: u16,
+                    lookup_type: u16,
+                    flag: u16,
+                    prelude: Vec<String>,
+                    rules: Option<Vec<String>>,
+                    warnings: &mut Vec<String>| {
+        let is_gsub = prefix == "gsub";
+        let Some(rules) = rules else {
+            let message = format!(
+                "{} lookup {} ({}, type {}) not exported",
+                prefix.to_uppercase(),
+                index,
+                lookup_type_name(is_gsub, lookup_type),
+                lookup_type
+            );
+            let _ = writeln!(lookups, "# {}\n", message);
+            warnings.push(message);
+            return false;
+        };
+        for line in prelude {
+            let _ = writeln!(lookups, "{}", line);
+        }
+        let _ = writeln!(lookups, "lookup {}{} {{", prefix, index);
+        for line in lookup_flag_lines(flag).iter().chain(&rules) {
+            let _ = writeln!(lookups, "    {}", line);
+        }
+        let _ = writeln!(lookups, "}} {}{};\n", prefix, index);
+        true
+    };
+
+    if let Ok(gsub) = font.gsub() {
+        let lookup_list = gsub
+            .lookup_list()
+            .map_err(|e| FontError::table("GSUB", e))?;
+        let flags: Vec<u16> = lookup_list
+            .lookups()
+            .iter()
+            .map(|l| l.map_or(0, |l| l.lookup_flag().to_bits()))
+            .collect();
+        let mut exported = vec![false; flags.len()];
+        for_each_substitution_lookup(&font, |index, lookup_type, subtables| {
+            let rules = substitution_rules(subtables, &names);
+            exported[index as usize] = emit(
+                "gsub",
+                index,
+                lookup_type,
+                flags[index as usize],
+                Vec::new(),
+                rules,
+                &mut warnings,
+            );
+        })?;
+        let (scripts, features) = (
+            gsub.script_list()
+                .map_err(|e| FontError::table("GSUB", e))?,
+            gsub.feature_list()
+                .map_err(|e| FontError::table("GSUB", e))?,
+        );
+        tables.push(TableExport {
+            prefix: "gsub",
+            uses: feature_uses(&scripts, &features),
+            exported,
+        });
+    }
+    if let Ok(gpos) = font.gpos() {
+        let lookup_list = gpos
+            .lookup_list()
+            .map_err(|e| FontError::table("GPOS", e))?;
+        let flags: Vec<u16> = lookup_list
+            .lookups()
+            .iter()
+            .map(|l| l.map_or(0, |l| l.lookup_flag().to_bits()))
+            .collect();
+        let mut exported = vec![false; flags.len()];
+        for_each_position_lookup(&font, |index, lookup_type, subtables| {
+            let mut prelude = Vec::new();
+            let rules = position_rules(index, subtables, &names, &mut prelude);
+            exported[index as usize] = emit(
+                "gpos",
+                index,
+                lookup_type,
+                flags[index as usize],
+                prelude,
+                rules,
+                &mut warnings,
+            );
+        })?;
+        let (scripts, features) = (
+            gpos.script_list()
+                .map_err(|e| FontError::table("GPOS", e))?,
+            gpos.feature_list()
+                .map_err(|e| FontError::table("GPOS", e))?,
+        );
+        tables.push(TableExport {
+            prefix: "gpos",
+            uses: feature_uses(&scripts, &features),
+            exported,
+        });
+    }
+
+    // languagesystem statements, DFLT first as feature files require
+    let mut systems: Vec<(Tag, Tag)> = Vec::new();
+    for feature_use in tables.iter().flat_map(|t| &t.uses) {
+        let system = (feature_use.script, feature_use.language);
+        if !systems.contains(&system) {
+            systems.push(system);
+        }
+    }
+    systems.sort_by_key(|&(script, _)| script != DFLT);
+
+    let mut fea = String::new();
+    for (script, language) in &systems {
+        let _ = writeln!(
+            fea,
+            "languagesystem {} {};",
+            fea_tag(*script),
+            fea_tag(*language)
+        );
+    }
+    if !systems.is_empty() {
+        fea.push('\n');
+    }
+    fea.push_str(&lookups);
+
+    for table in &tables {
+        let mut order: Vec<Tag> = Vec::new();
+        for feature_use in &table.uses {
+            if !order.contains(&feature_use.feature) {
+                order.push(feature_use.feature);
+            }
+        }
+        for feature in order {
+            let _ = writeln!(fea, "feature {} {{", fea_tag(feature));
+            let mut current_script = None;
+            for feature_use in table.uses.iter().filter(|u| u.feature == feature) {
+                if current_script != Some(feature_use.script) {
+                    let _ = writeln!(fea, "    script {};", fea_tag(feature_use.script));
+                    current_script = Some(feature_use.script);
+                }
+                if feature_use.language == DFLT_LANGUAGE {
+                    let _ = writeln!(fea, "    language dflt;");
+                } else {
+                    let _ = writeln!(
+                        fea,
+                        "    language {} exclude_dflt;",
+                        fea_tag(feature_use.language)
+                    );
+                }
+                for &index in &feature_use.lookups {
+                    if table.exported.get(index as usize) == Some(&true) {
+                        let _ = writeln!(fea, "        lookup {}{};", table.prefix, index);
+                    } else {
+                        let _ = writeln!(
+                            fea,
+                            "        # lookup {}{}; (not exported)",
+                            table.prefix, index
+                        );
+                    }
+                }
+            }
+            let _ = writeln!(fea, "}} {};\n", fea_tag(feature));
+        }
+    }
+    Ok((fea, warnings))
+}
+
+/// Write the GSUB and GPOS of `file_path` to `destination` as an Adobe
+/// feature file. Returns a warning for each lookup left out.
+pub fn export_features(
+    file_path: &str,
+    destination: &str,
+    cache: &FontCache,
+) -> Result<Vec<String>, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let (fea, warnings) = font_to_fea(&bytes)?;
+    write_atomically(std::path::Path::new(destination), fea.as_bytes())?;
+    Ok(warnings)
+}
+
+/// Whether imported features add to or take the place of the font's
+/// features with the same tag.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FeatureImportMode {
+    /// Run the new lookups after the feature's current ones.
+    #[default]
+    Merge,
+    /// Drop the feature's current lookups first. They stay in the lookup
+    /// list, unreferenced, since contextual lookups may still call them.
+    Replace,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FeatureImportOptions {
+    pub mode: FeatureImportMode,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FeatureImportReport {
+    /// Tags of the feature blocks, in order of first appearance.
+    pub features: Vec<String>,
+    pub gsub_lookups: usize,
+    pub gpos_lookups: usize,
+    pub rules: usize,
+}
+
+/// A glyph or class operand. `is_class` holds for bracketed and named
+/// classes even of one glyph, which makes pair rules class pairs.
+struct GlyphSet {
+    glyphs: Vec<u16>,
+    is_class: bool,
+}
+
+/// Class pairs of one PairPos format 2 subtable. Classes are numbered from
+/// 1 in list order; class 0 is every other glyph.
+#[derive(Default)]
+struct ClassPairs {
+    first: Vec<Vec<u16>>,
+    second: Vec<Vec<u16>>,
+    values: BTreeMap<(u16, u16), WriteValueRecord>,
+}
+
+/// Index of `class` among `classes`, or the index it would take when it
+/// overlaps none of them. None when it overlaps one without equalling it.
+fn class_slot(classes: &[Vec<u16>], class: &[u16]) -> Option<usize> {
+    let mut sorted = class.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    for (index, existing) in classes.iter().enumerate() {
+        if *existing == sorted {
+            return Some(index);
+        }
+        if existing.iter().any(|g| sorted.binary_search(g).is_ok()) {
+            return None;
+        }
+    }
+    Some(classes.len())
+}
+
+/// Rules of one lookup as the source lists them.
+enum FeaRules {
+    Single(BTreeMap<u16, u16>),
+    Ligature(Vec<(Vec<u16>, u16)>),
+    SinglePos(BTreeMap<u16, WriteValueRecord>),
+    Pair {
+        glyph_pairs: BTreeMap<(u16, u16), WriteValueRecord>,
+        class_pairs: Vec<ClassPairs>,
+    },
+}
+
+impl FeaRules {
+    fn is_gsub(&self) -> bool {
+        matches!(self, FeaRules::Single(_) | FeaRules::Ligature(_))
+    }
+}
+
+/// One rule as parsed, before it joins a lookup.
+enum FeaRule {
+    Single(Vec<(u16, u16)>),
+    Ligature(Vec<(Vec<u16>, u16)>),
+    SinglePos(Vec<u16>, WriteValueRecord),
+    Pair(GlyphSet, GlyphSet, WriteValueRecord),
+}
+
+struct FeaLookup {
+    flag: u16,
+    rules: FeaRules,
+}
+
+struct FeaFeature {
+    tag: Tag,
+    lookups: Vec<FeaLookup>,
+}
+
+/// The supported subset of the feature file syntax: `languagesystem`,
+/// glyph class definitions, and feature blocks of `lookupflag`, single and
+/// ligature `sub`, and single and pair `pos` rules.
+struct FeaParser<'a> {
+    tokens: Vec<(usize, String)>,
+    pos: usize,
+    names: &'a GlyphNames,
+    glyphs: HashMap<&'a str, u16>,
+    classes: HashMap<String, Vec<u16>>,
+    systems: Vec<(Tag, Tag)>,
+    features: Vec<FeaFeature>,
+    rules: usize,
+}
+
+/// Split feature source into `(line, token)`, dropping comments. Brackets,
+/// braces, angle brackets, `;`, `=` and `'` are tokens of their own.
+fn tokenize_fea(source: &str) -> Vec<(usize, String)> {
+    let mut tokens = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let code = line.split('#').next().unwrap_or("");
+        let mut token = String::new();
+        for ch in code.chars() {
+            if ch.is_whitespace() || "[]{}<>;='".contains(ch) {
+                if !token.is_empty() {
+                    tokens.push((index + 1, std::mem::take(&mut token)));
+                }
+                if !ch.is_whitespace() {
+                    tokens.push((index + 1, ch.to_string()));
+                }
+            } else {
+                token.push(ch);
+            }
+        }
+        if !token.is_empty() {
+            tokens.push((index + 1, token));
+        }
+    }
+    tokens
+}
+
+impl<'a> FeaParser<'a> {
+    fn new(source: &str, names: &'a GlyphNames) -> Self {
+        let glyphs = names
+            .0
+            .iter()
+            .enumerate()
+            .map(|(gid, name)| (name.as_str(), gid as u16))
+            .collect();
+        FeaParser {
+            tokens: tokenize_fea(source),
+            pos: 0,
+            names,
+            glyphs,
+            classes: HashMap::new(),
+            systems: Vec::new(),
+            features: Vec::new(),
+            rules: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|(_, token)| token.as_str())
+    }
+
+    /// Line of the next token, or of the last one at the end of the source.
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(1, |(line, _)| *line)
+    }
+
+    fn next(&mut self) -> Result<(usize, String), FontError> {
+        let token =
+            self.tokens.get(self.pos).cloned().ok_or_else(|| {
+                FontError::input("Feature source ends in the middle of a statement")
+            })?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), FontError> {
+        let (line, token) = self.next()?;
+        if token != expected {
+            return Err(FontError::input(format!(
+                "Expected '{}' on line {}, found '{}'",
+                expected, line, token
+            )));
+        }
+        Ok(())
+    }
+
+    fn tag(&mut self) -> Result<Tag, FontError> {
+        let (line, token) = self.next()?;
+        if token.len() > 4 || !token.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(FontError::input(format!(
+                "'{}' on line {} is not a tag",
+                token, line
+            )));
+        }
+        Tag::new_checked(token.as_bytes())
+            .map_err(|_| FontError::input(format!("'{}' on line {} is not a tag", token, line)))
+    }
+
+    fn glyph(&self, name: &str, line: usize) -> Result<u16, FontError> {
+        let name = name.strip_prefix('\\').unwrap_or(name);
+        if let Some(&gid) = self.glyphs.get(name) {
+            return Ok(gid);
+        }
+        name.strip_prefix("gid")
+            .and_then(|n| n.parse::<u16>().ok())
+            .filter(|&gid| (gid as usize) < self.names.0.len())
+            .ok_or_else(|| FontError::input(format!("Unknown glyph '{}' on line {}", name, line)))
+    }
+
+    fn class(&self, name: &str, line: usize) -> Result<Vec<u16>, FontError> {
+        self.classes.get(name).cloned().ok_or_else(|| {
+            FontError::input(format!("Unknown glyph class '{}' on line {}", name, line))
+        })
+    }
+
+    /// A glyph name, a named class (`@name`) or a bracketed class.
+    fn glyph_set(&mut self) -> Result<GlyphSet, FontError> {
+        let (line, token) = self.next()?;
+        if token == "[" {
+            let mut glyphs = Vec::new();
+            loop {
+                let (line, token) = self.next()?;
+                match token.as_str() {
+                    "]" => break,
+                    t if t.starts_with('@') => glyphs.extend(self.class(t, line)?),
+                    t if t.len() == 1 && "[]{}<>;='".contains(t) => {
+                        return Err(FontError::input(format!(
+                            "Unexpected '{}' in glyph class on line {}",
+                            t, line
+                        )))
+                    }
+                    t => glyphs.push(self.glyph(t, line)?),
+                }
+            }
+            return Ok(GlyphSet {
+                glyphs,
+                is_class: true,
+            });
+        }
+        if token.starts_with('@') {
+            return Ok(GlyphSet {
+                glyphs: self.class(&token, line)?,
+                is_class: true,
+            });
+        }
+        if token == "'" {
+            return Err(FontError::unsupported(format!(
+                "contextual rules in feature files (line {})",
+                line
+            )));
+        }
+        Ok(GlyphSet {
+            glyphs: vec![self.glyph(&token, line)?],
+            is_class: false,
+        })
+    }
+
+    fn number(&mut self) -> Result<i16, FontError> {
+        let (line, token) = self.next()?;
+        token.parse().map_err(|_| {
+            FontError::input(format!(
+                "Expected a value in -32768..=32767 on line {}, found '{}'",
+                line, token
+            ))
+        })
+    }
+
+    /// A bare number (an advance) or `<xPlacement yPlacement xAdvance
+    /// yAdvance>`.
+    fn value(&mut self) -> Result<WriteValueRecord, FontError> {
+        if self.peek() != Some("<") {
+            return Ok(WriteValueRecord::new().with_x_advance(self.number()?));
+        }
+        self.expect("<")?;
+        let record = WriteValueRecord::new()
+            .with_x_placement(self.number()?)
+            .with_y_placement(self.number()?)
+            .with_x_advance(self.number()?)
+            .with_y_advance(self.number()?);
+        self.expect(">")?;
+        Ok(record)
+    }
+
+    fn is_value_next(&self) -> bool {
+        self.peek()
+            .is_some_and(|t| t == "<" || t.parse::<i16>().is_ok())
+    }
+
+    fn parse(mut self) -> Result<Self, FontError> {
+        while self.pos < self.tokens.len() {
+            let (line, token) = self.next()?;
+            match token.as_str() {
+                "languagesystem" => {
+                    let system = (self.tag()?, self.tag()?);
+                    self.expect(";")?;
+                    if !self.systems.contains(&system) {
+                        self.systems.push(system);
+                    }
+                }
+                "feature" => {
+                    let tag = self.tag()?;
+                    self.expect("{")?;
+                    self.feature_block(tag)?;
+                }
+                t if t.starts_with('@') => self.class_definition(t.to_string())?,
+                t => {
+                    return Err(FontError::unsupported(format!(
+                        "'{}' statements in feature files (line {})",
+                        t, line
+                    )))
+                }
+            }
+        }
+        if self.systems.is_empty() {
+            self.systems.push((DFLT, DFLT_LANGUAGE));
+        }
+        Ok(self)
+    }
+
+    fn class_definition(&mut self, name: String) -> Result<(), FontError> {
+        self.expect("=")?;
+        let set = self.glyph_set()?;
+        self.expect(";")?;
+        self.classes.insert(name, set.glyphs);
+        Ok(())
+    }
+
+    fn feature_block(&mut self, tag: Tag) -> Result<(), FontError> {
+        let index = match self.features.iter().position(|f| f.tag == tag) {
+            Some(index) => index,
+            None => {
+                self.features.push(FeaFeature {
+                    tag,
+                    lookups: Vec::new(),
+                });
+                self.features.len() - 1
+            }
+        };
+        let mut flag = 0u16;
+        // Rules after a lookupflag start a new lookup even of the same type
+        let mut new_lookup = true;
+        loop {
+            let (line, token) = self.next()?;
+            let rule = match token.as_str() {
+                "}" => {
+                    let closing = self.tag()?;
+                    if closing != tag {
+                        return Err(FontError::input(format!(
+                            "Feature '{}' is closed as '{}' on line {}",
+                            fea_tag(tag),
+                            fea_tag(closing),
+                            self.line()
+                        )));
+                    }
+                    return self.expect(";");
+                }
+                "lookupflag" => {
+                    flag = self.lookup_flag(line)?;
+                    new_lookup = true;
+                    continue;
+                }
+                "sub" | "substitute" => self.substitution(line)?,
+                "pos" | "position" => self.positioning()?,
+                t if t.starts_with('@') => {
+                    self.class_definition(t.to_string())?;
+                    continue;
+                }
+                t => {
+                    return Err(FontError::unsupported(format!(
+                        "'{}' statements in feature files (line {})",
+                        t, line
+                    )))
+                }
+            };
+            self.rules += 1;
+            let lookups = &mut self.features[index].lookups;
+            let fits = |rules: &FeaRules| {
+                matches!(
+                    (rules, &rule),
+                    (FeaRules::Single(_), FeaRule::Single(_))
+                        | (FeaRules::Ligature(_), FeaRule::Ligature(_))
+                        | (FeaRules::SinglePos(_), FeaRule::SinglePos(..))
+                        | (FeaRules::Pair { .. }, FeaRule::Pair(..))
+                )
+            };
+            if new_lookup || !lookups.last().is_some_and(|l| fits(&l.rules)) {
+                let rules = match rule {
+                    FeaRule::Single(_) => FeaRules::Single(BTreeMap::new()),
+                    FeaRule::Ligature(_) => FeaRules::Ligature(Vec::new()),
+                    FeaRule::SinglePos(..) => FeaRules::SinglePos(BTreeMap::new()),
+                    FeaRule::Pair(..) => FeaRules::Pair {
+                        glyph_pairs: BTreeMap::new(),
+                        class_pairs: Vec::new(),
+                    },
+                };
+                lookups.push(FeaLookup { flag, rules });
+                new_lookup = false;
+            }
+            if let Some(lookup) = lookups.last_mut() {
+                add_rule(&mut lookup.rules, rule, self.names, line)?;
+            }
+        }
+    }
+
+    /// `lookupflag` with flag names or a number. Flags needing GDEF classes
+    /// are not supported.
+    fn lookup_flag(&mut self, line: usize) -> Result<u16, FontError> {
+        let mut flag = 0u16;
+        loop {
+            let (_, token) = self.next()?;
+            flag |= match token.as_str() {
+                ";" => return Ok(flag),
+                "RightToLeft" => 0x0001,
+                "IgnoreBaseGlyphs" => 0x0002,
+                "IgnoreLigatures" => 0x0004,
+                "IgnoreMarks" => 0x0008,
+                t => match t.parse::<u16>() {
+                    Ok(bits) if bits <= 0x000F => bits,
+                    _ => {
+                        return Err(FontError::unsupported(format!(
+                            "lookupflag '{}' on line {}",
+                            t, line
+                        )))
+                    }
+                },
+            };
+        }
+    }
+
+    fn substitution(&mut self, line: usize) -> Result<FeaRule, FontError> {
+        let mut input = Vec::new();
+        while self.peek() != Some("by") {
+            if self.peek() == Some(";") {
+                return Err(FontError::input(format!(
+                    "Substitution on line {} has no 'by'",
+                    line
+                )));
+            }
+            input.push(self.glyph_set()?);
+        }
+        self.expect("by")?;
+        let mut output = Vec::new();
+        while self.peek() != Some(";") {
+            output.push(self.glyph_set()?);
+        }
+        self.expect(";")?;
+
+        match (input.as_slice(), output.as_slice()) {
+            ([from], [to]) => {
+                let pairs: Vec<(u16, u16)> = if to.glyphs.len() == 1 && !to.is_class {
+                    from.glyphs.iter().map(|&g| (g, to.glyphs[0])).collect()
+                } else if from.glyphs.len() == to.glyphs.len() {
+                    from.glyphs
+                        .iter()
+                        .copied()
+                        .zip(to.glyphs.iter().copied())
+                        .collect()
+                } else {
+                    return Err(FontError::input(format!(
+                        "Substitution on line {} replaces {} glyphs with {}",
+                        line,
+                        from.glyphs.len(),
+                        to.glyphs.len()
+                    )));
+                };
+                Ok(FeaRule::Single(pairs))
+            }
+            ([_, _, ..], [to]) if !to.is_class => {
+                // Every combination of the input classes forms the ligature
+                let mut sequences: Vec<Vec<u16>> = vec![Vec::new()];
+                for set in &input {
+                    sequences = sequences
+                        .iter()
+                        .flat_map(|seq| {
+                            set.glyphs.iter().map(move |&g| {
+                                let mut seq = seq.clone();
+                                seq.push(g);
+                                seq
+                            })
+                        })
+                        .collect();
+                }
+                Ok(FeaRule::Ligature(
+                    sequences
+                        .into_iter()
+                        .map(|seq| (seq, to.glyphs[0]))
+                        .collect(),
+                ))
+            }
+            ([_], [_, _, ..]) => Err(FontError::unsupported(format!(
+                "multiple substitutions in feature files (line {})",
+                line
+            ))),
+            _ => Err(FontError::input(format!(
+                "Substitution on line {} is not a single or ligature substitution",
+                line
+            ))),
+        }
+    }
+
+    fn positioning(&mut self) -> Result<FeaRule, FontError> {
+        let first = self.glyph_set()?;
+        if self.is_value_next() {
+            let value = self.value()?;
+            self.expect(";")?;
+            return Ok(FeaRule::SinglePos(first.glyphs, value));
+        }
+        let second = self.glyph_set()?;
+        let value = self.value()?;
+        self.expect(";")?;
+        Ok(FeaRule::Pair(first, second, value))
+    }
+}
+
+/// Add `rule` to the lookup it was parsed into. A glyph or pair given two
+/// different values in one lookup is an error; class pairs overlapping an
+/// earlier class go to a new subtable.
+fn add_rule(
+    rules: &mut FeaRules,
+    rule: FeaRule,
+    names: &GlyphNames,
+    line: usize,
+) -> Result<(), FontError> {
+    let twice = |what: String| {
+        FontError::input(format!(
+            "{} on line {} already has a rule in this lookup",
+            what, line
+        ))
+    };
+    match (rules, rule) {
+        (FeaRules::Single(map), FeaRule::Single(pairs)) => {
+            for (from, to) in pairs {
+                if map.insert(from, to).is_some_and(|old| old != to) {
+                    return Err(twice(format!("Glyph {}", names.name(from as u32))));
+                }
+            }
+        }
+        (FeaRules::Ligature(ligatures), FeaRule::Ligature(new)) => {
+            for (sequence, ligature) in new {
+                if ligatures
+                    .iter()
+                    .any(|(s, l)| *s == sequence && *l != ligature)
+                {
+                    let names: Vec<String> =
+                        sequence.iter().map(|&g| names.name(g as u32)).collect();
+                    return Err(twice(format!("Sequence {}", names.join(" "))));
+                }
+                ligatures.push((sequence, ligature));
+            }
+        }
+        (FeaRules::SinglePos(map), FeaRule::SinglePos(glyphs, value)) => {
+            for glyph in glyphs {
+                if map
+                    .insert(glyph, value.clone())
+                    .is_some_and(|old| old != value)
+                {
+                    return Err(twice(format!("Glyph {}", names.name(glyph as u32))));
+                }
+            }
+        }
+        (
+            FeaRules::Pair {
+                glyph_pairs,
+                class_pairs,
+            },
+            FeaRule::Pair(first, second, value),
+        ) => {
+            if !first.is_class && !second.is_class {
+                let key = (first.glyphs[0], second.glyphs[0]);
+                if glyph_pairs
+                    .insert(key, value.clone())
+                    .is_some_and(|old| old != value)
+                {
+                    return Err(twice(format!(
+                        "Pair {} {}",
+                        names.name(key.0 as u32),
+                        names.name(key.1 as u32)
+                    )));
+                }
+                return Ok(());
+            }
+            let slots = |pairs: &ClassPairs| {
+                Some((
+                    class_slot(&pairs.first, &first.glyphs)?,
+                    class_slot(&pairs.second, &second.glyphs)?,
+                ))
+            };
+            let (c1, c2) = match class_pairs.last().and_then(slots) {
+                Some(slots) => slots,
+                None => {
+                    class_pairs.push(ClassPairs::default());
+                    (0, 0)
+                }
+            };
+            let Some(pairs) = class_pairs.last_mut() else {
+                return Ok(());
+            };
+            for (classes, slot, glyphs) in [
+                (&mut pairs.first, c1, &first.glyphs),
+                (&mut pairs.second, c2, &second.glyphs),
+            ] {
+                if slot == classes.len() {
+                    let mut class = glyphs.clone();
+                    class.sort_unstable();
+                    class.dedup();
+                    classes.push(class);
+                }
+            }
+            let key = (c1 as u16 + 1, c2 as u16 + 1);
+            if pairs
+                .values
+                .insert(key, value.clone())
+                .is_some_and(|old| old != value)
+            {
+                return Err(twice("Class pair".to_string()));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Give every record the fields any of them sets, zero where unset: a
+/// subtable stores all its value records in one format.
+fn unify_values<'r>(records: impl IntoIterator<Item = &'r mut WriteValueRecord>) {
+    let mut records: Vec<_> = records.into_iter().collect();
+    let used = |field: fn(&WriteValueRecord) -> Option<i16>| {
+        records.iter().any(|record| field(record).is_some())
+    };
+    let fields = [
+        used(|r| r.x_placement),
+        used(|r| r.y_placement),
+        used(|r| r.x_advance),
+        used(|r| r.y_advance),
+    ];
+    for record in &mut records {
+        let values = [
+            &mut record.x_placement,
+            &mut record.y_placement,
+            &mut record.x_advance,
+            &mut record.y_advance,
+        ];
+        for (value, used) in values.into_iter().zip(fields) {
+            if used {
+                value.get_or_insert(0);
+            }
+        }
+    }
+}
+
+fn coverage(glyphs: impl IntoIterator<Item = u16>) -> CoverageTable {
+    glyphs.into_iter().map(GlyphId16::new).collect()
+}
+
+fn substitution_lookup(flag: LookupFlag, rules: FeaRules) -> Option<SubstitutionLookup> {
+    match rules {
+        FeaRules::Single(map) => {
+            let subtable = WriteSingleSubst::format_2(
+                coverage(map.keys().copied()),
+                map.values().copied().map(GlyphId16::new).collect(),
+            );
+            Some(SubstitutionLookup::Single(Lookup::new(
+                flag,
+                vec![subtable],
+            )))
+        }
+        FeaRules::Ligature(ligatures) => {
+            let mut sets = BTreeMap::<u16, Vec<(Vec<u16>, u16)>>::new();
+            for (sequence, ligature) in ligatures {
+                sets.entry(sequence[0])
+                    .or_default()
+                    .push((sequence, ligature));
+            }
+            // Longer ligatures first, or a shorter one would always win
+            for set in sets.values_mut() {
+                set.sort_by_key(|(sequence, _)| std::cmp::Reverse(sequence.len()));
+            }
+            let subtable = LigatureSubstFormat1::new(
+                coverage(sets.keys().copied()),
+                sets.into_values()
+                    .map(|set| {
+                        LigatureSet::new(
+                            set.into_iter()
+                                .map(|(sequence, ligature)| {
+                                    Ligature::new(
+                                        GlyphId16::new(ligature),
+                                        sequence[1..].iter().copied().map(GlyphId16::new).collect(),
+                                    )
+                                })
+                                .collect(),
+                        )
+                    })
+                    .collect(),
+            );
+            Some(SubstitutionLookup::Ligature(Lookup::new(
+                flag,
+                vec![subtable],
+            )))
+        }
+        _ => None,
+    }
+}
+
+fn position_lookup(flag: LookupFlag, rules: FeaRules) -> Option<PositionLookup> {
+    match rules {
+        FeaRules::SinglePos(mut map) => {
+            unify_values(map.values_mut());
+            let subtable = WriteSinglePos::format_2(
+                coverage(map.keys().copied()),
+                map.into_values().collect(),
+            );
+            Some(PositionLookup::Single(Lookup::new(flag, vec![subtable])))
+        }
+        FeaRules::Pair {
+            mut glyph_pairs,
+            class_pairs,
+        } => {
+            let mut subtables = Vec::new();
+            // Glyph pairs come first so they override class pairs
+            if !glyph_pairs.is_empty() {
+                unify_values(glyph_pairs.values_mut());
+                let mut sets = BTreeMap::<u16, Vec<PairValueRecord>>::new();
+                for ((first, second), value) in glyph_pairs {
+                    sets.entry(first).or_default().push(PairValueRecord::new(
+                        GlyphId16::new(second),
+                        value,
+                        WriteValueRecord::new(),
+                    ));
+                }
+                subtables.push(WritePairPos::format_1(
+                    coverage(sets.keys().copied()),
+                    sets.into_values().map(PairSet::new).collect(),
+                ));
+            }
+            for pairs in class_pairs {
+                let mut matrix = vec![
+                    vec![WriteValueRecord::new(); pairs.second.len() + 1];
+                    pairs.first.len() + 1
+                ];
+                for (&(c1, c2), value) in &pairs.values {
+                    matrix[c1 as usize][c2 as usize] = value.clone();
+                }
+                unify_values(matrix.iter_mut().flatten());
+                let class_def = |classes: &[Vec<u16>]| -> ClassDef {
+                    classes
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(index, class)| {
+                            class
+                                .iter()
+                                .map(move |&g| (GlyphId16::new(g), index as u16 + 1))
+                        })
+                        .collect()
+                };
+                let mut covered: Vec<u16> = pairs.first.iter().flatten().copied().collect();
+                covered.sort_unstable();
+                subtables.push(WritePairPos::format_2(
+                    coverage(covered),
+                    class_def(&pairs.first),
+                    class_def(&pairs.second),
+                    matrix
+                        .into_iter()
+                        .map(|row| {
+                            Class1Record::new(
+                                row.into_iter()
+                                    .map(|value| Class2Record::new(value, WriteValueRecord::new()))
+                                    .collect(),
+                            )
+                        })
+                        .collect(),
+                ));
+            }
+            Some(PositionLookup::Pair(Lookup::new(flag, subtables)))
+        }
+        _ => None,
+    }
+}
+
+/// Point the `tag` features at `lookups`, after their current lookups or
+/// in their place per `mode`, adding a feature if there is none. Then make
+/// sure each language system uses one, adding scripts and languages.
+fn register_feature(
+    scripts: &mut WriteScriptList,
+    features: &mut WriteFeatureList,
+    tag: Tag,
+    lookups: &[u16],
+    systems: &[(Tag, Tag)],
+    mode: FeatureImportMode,
+) {
+    let records = &mut features.feature_records;
+    let mut tagged = Vec::new();
+    for (index, record) in records.iter_mut().enumerate() {
+        if record.feature_tag == tag {
+            if mode == FeatureImportMode::Replace {
+                record.feature.lookup_list_indices.clear();
+            }
+            record.feature.lookup_list_indices.extend(lookups);
+            tagged.push(index as u16);
+        }
+    }
+    if tagged.is_empty() {
+        tagged.push(records.len() as u16);
+        records.push(FeatureRecord::new(
+            tag,
+            Feature::new(None, lookups.to_vec()),
+        ));
+    }
+
+    let scripts = &mut scripts.script_records;
+    for &(script_tag, language) in systems {
+        let index = match scripts.binary_search_by_key(&script_tag, |r| r.script_tag) {
+            Ok(index) => index,
+            Err(index) => {
+                scripts.insert(
+                    index,
+                    ScriptRecord::new(script_tag, Script::new(None, Vec::new())),
+                );
+                index
+            }
+        };
+        let script: &mut Script = &mut scripts[index].script;
+        let lang_sys: &mut LangSys = if language == DFLT_LANGUAGE {
+            if script.default_lang_sys.as_ref().is_none() {
+                script.default_lang_sys.set(LangSys::new(Vec::new()));
+            }
+            match script.default_lang_sys.as_mut() {
+                Some(lang_sys) => lang_sys,
+                None => continue,
+            }
+        } else {
+            let records = &mut script.lang_sys_records;
+            let index = match records.binary_search_by_key(&language, |r| r.lang_sys_tag) {
+                Ok(index) => index,
+                Err(index) => {
+                    records.insert(
+                        index,
+                        LangSysRecord::new(language, LangSys::new(Vec::new())),
+                    );
+                    index
+                }
+            };
+            &mut records[index].lang_sys
+        };
+        if !lang_sys.feature_indices.iter().any(|i| tagged.contains(i)) {
+            lang_sys.feature_indices.push(tagged[0]);
+            lang_sys.feature_indices.sort_unstable();
+        }
+    }
+}
+
+/// Compile `source` into the GSUB and GPOS of `bytes`. Returns the new font.
+fn compile_fea(
+    bytes: &[u8],
+    source: &str,
+    options: &FeatureImportOptions,
+) -> Result<(Vec<u8>, FeatureImportReport), FontError> {
+    let font = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let skrifa_font = skrifa::FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as u32;
+    let names = GlyphNames::new(&skrifa_font, num_glyphs);
+    let parsed = FeaParser::new(source, &names).parse()?;
+
+    let mut report = FeatureImportReport {
+        features: parsed.features.iter().map(|f| fea_tag(f.tag)).collect(),
+        gsub_lookups: 0,
+        gpos_lookups: 0,
+        rules: parsed.rules,
+    };
+    let mut gsub: Option<Gsub> = None;
+    let mut gpos: Option<Gpos> = None;
+    for feature in parsed.features {
+        let (mut gsub_indices, mut gpos_indices) = (Vec::new(), Vec::new());
+        for lookup in feature.lookups {
+            let flag = LookupFlag::from_bits_truncate(lookup.flag);
+            if lookup.rules.is_gsub() {
+                let table = gsub.get_or_insert_with(|| match font.gsub() {
+                    Ok(gsub) => gsub.to_owned_table(),
+                    Err(_) => Gsub::new(
+                        WriteScriptList::default(),
+                        WriteFeatureList::default(),
+                        SubstitutionLookupList::default(),
+                    ),
+                });
+                let lookups = &mut table.lookup_list.lookups;
+                gsub_indices.push(
+                    u16::try_from(lookups.len())
+                        .map_err(|_| FontError::input("GSUB has no room for another lookup"))?,
+                );
+                lookups.extend(substitution_lookup(flag, lookup.rules).map(Into::into));
+                report.gsub_lookups += 1;
+            } else {
+                let table = gpos.get_or_insert_with(|| match font.gpos() {
+                    Ok(gpos) => gpos.to_owned_table(),
+                    Err(_) => Gpos::new(
+                        WriteScriptList::default(),
+                        WriteFeatureList::default(),
+                        PositionLookupList::default(),
+                    ),
+                });
+                let lookups = &mut table.lookup_list.lookups;
+                gpos_indices.push(
+                    u16::try_from(lookups.len())
+                        .map_err(|_| FontError::input("GPOS has no room for another lookup"))?,
+                );
+                lookups.extend(position_lookup(flag, lookup.rules).map(Into::into));
+                report.gpos_lookups += 1;
+            }
+        }
+        if let (Some(gsub), false) = (gsub.as_mut(), gsub_indices.is_empty()) {
+            register_feature(
+                &mut gsub.script_list,
+                &mut gsub.feature_list,
+                feature.tag,
+                &gsub_indices,
+                &parsed.systems,
+                options.mode,
+            );
+        }
+        if let (Some(gpos), false) = (gpos.as_mut(), gpos_indices.is_empty()) {
+            register_feature(
+                &mut gpos.script_list,
+                &mut gpos.feature_list,
+                feature.tag,
+                &gpos_indices,
+                &parsed.systems,
+                options.mode,
+            );
+        }
+    }
+
+    let mut builder = FontBuilder::new();
+    if let Some(gsub) = &gsub {
+        builder
+            .add_table(gsub)
+            .map_err(|e| FontError::build("GSUB", e))?;
+    }
+    if let Some(gpos) = &gpos {
+        builder
+            .add_table(gpos)
+            .map_err(|e| FontError::build("GPOS", e))?;
+    }
+    Ok((builder.copy_missing_tables(font).build(), report))
+}
+
+/// Compile a feature file snippet into the GSUB/GPOS of `file_path`; see
+/// [`FeaParser`] for the syntax it takes. Glyphs are named as
+/// [`export_features`] writes them.
+pub fn import_features(
+    file_path: &str,
+    fea_source: &str,
+    options: &FeatureImportOptions,
+    cache: &FontCache,
+) -> Result<FeatureImportReport, FontError> {
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let (new_bytes, report) = compile_fea(&bytes, fea_source, options)?;
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::TestFontBuilder;
+    use write_fonts::tables::gpos::{
+        AnchorTable, BaseArray, BaseRecord, CursivePosFormat1, EntryExitRecord, Gpos, MarkArray,
+        MarkBasePosFormat1, MarkRecord, PairPos as WritePairPos, PairSet, PairValueRecord,
+        PositionLookup, PositionLookupList, ValueRecord as WriteValueRecord,
+    };
+    use write_fonts::tables::gsub::{
+        Gsub, Ligature, LigatureSet, LigatureSubstFormat1, SingleSubst as WriteSingleSubst,
+        SubstitutionLookup, SubstitutionLookupList,
+    };
+    use write_fonts::tables::layout::{
+        Feature, FeatureList, FeatureRecord, LangSys, LangSysRecord, Lookup, LookupFlag, Script,
+        ScriptList, ScriptRecord,
+    };
+    use write_fonts::types::GlyphId16;
+
+    fn gids(ids: &[u16]) -> Vec<GlyphId16> {
+        ids.iter().copied().map(GlyphId16::new).collect()
+    }
+
+    fn features(records: &[(&[u8; 4], Vec<u16>)]) -> FeatureList {
+        FeatureList::new(
+            records
+                .iter()
+                .map(|(tag, lookups)| {
+                    FeatureRecord::new(Tag::new(tag), Feature::new(None, lookups.clone()))
+                })
+                .collect(),
+        )
+    }
+
+    /// GSUB: liga (f i -> fi, ignoring marks) everywhere; smcp (f -> F)
+    /// for latn only, Turkish included. GPOS: kern (a pair, then a cursive
+    /// lookup) and mark (mark-to-base), both for DFLT.
+    fn font_with_layout() -> Vec<u8> {
+        let gsub = Gsub::new(
+            ScriptList::new(vec![
+                ScriptRecord::new(
+                    Tag::new(b"DFLT"),
+                    Script::new(Some(LangSys::new(vec![0])), vec![]),
+                ),
+                ScriptRecord::new(
+                    Tag::new(b"latn"),
+                    Script::new(
+                        Some(LangSys::new(vec![0, 1])),
+                        vec![LangSysRecord::new(Tag::new(b"TRK "), LangSys::new(vec![1]))],
+                    ),
+                ),
+            ]),
+            features(&[(b"liga", vec![0]), (b"smcp", vec![1])]),
+            SubstitutionLookupList::new(vec![
+                SubstitutionLookup::Ligature(Lookup::new(
+                    LookupFlag::IGNORE_MARKS,
+                    vec![LigatureSubstFormat1::new(
+                        gids(&[1]).into(),
+                        vec![LigatureSet::new(vec![Ligature::new(
+                            GlyphId16::new(3),
+                            gids(&[2]),
+                        )])],
+                    )],
+                )),
+                SubstitutionLookup::Single(Lookup::new(
+                    LookupFlag::empty(),
+                    vec![WriteSingleSubst::format_2(gids(&[1]).into(), gids(&[4]))],
+                )),
+            ]),
+        );
+        let gpos = Gpos::new(
+            ScriptList::new(vec![ScriptRecord::new(
+                Tag::new(b"DFLT"),
+                Script::new(Some(LangSys::new(vec![0, 1])), vec![]),
+            )]),
+            features(&[(b"kern", vec![0, 1]), (b"mark", vec![2])]),
+            PositionLookupList::new(vec![
+                PositionLookup::Pair(Lookup::new(
+                    LookupFlag::empty(),
+                    vec![WritePairPos::format_1(
+                        gids(&[1]).into(),
+                        vec![PairSet::new(vec![PairValueRecord::new(
+                            GlyphId16::new(2),
+                            WriteValueRecord::new().with_x_advance(-50),
+                            WriteValueRecord::new(),
+                        )])],
+                    )],
+                )),
+                PositionLookup::Cursive(Lookup::new(
+                    LookupFlag::empty(),
+                    vec![CursivePosFormat1::new(
+                        gids(&[1]).into(),
+                        vec![EntryExitRecord::new(
+                            None,
+                            Some(AnchorTable::format_1(0, 0)),
+                        )],
+                    )],
+                )),
+                PositionLookup::MarkToBase(Lookup::new(
+                    LookupFlag::empty(),
+                    vec![MarkBasePosFormat1::new(
+                        gids(&[5]).into(),
+                        gids(&[1]).into(),
+                        MarkArray::new(vec![MarkRecord::new(0, AnchorTable::format_1(50, 0))]),
+                        BaseArray::new(vec![BaseRecord::new(vec![Some(AnchorTable::format_1(
+                            250, 700,
+                        ))])]),
+                    )],
+                )),
+            ]),
+        );
+        let mut builder = TestFontBuilder::new();
+        for _ in 0..6 {
+            builder = builder.glyph(Vec::new(), 500);
+        }
+        builder
+            .table(b"GSUB", write_fonts::dump_table(&gsub).unwrap())
+            .table(b"GPOS", write_fonts::dump_table(&gpos).unwrap())
+            .build()
+    }
+
+    #[test]
+    fn test_layout_tables_export_as_feature_file() {
+        let (fea, warnings) = font_to_fea(&font_with_layout()).unwrap();
+        let expected = "\
+languagesystem DFLT dflt;
+languagesystem latn dflt;
+languagesystem latn TRK;
+
+lookup gsub0 {
+    lookupflag IgnoreMarks;
+    sub gid1 gid2 by gid3;
+} gsub0;
+
+lookup gsub1 {
+    sub gid1 by gid4;
+} gsub1;
+
+lookup gpos0 {
+    pos gid1 gid2 -50;
+} gpos0;
+
+# GPOS lookup 1 (cursive attachment, type 3) not exported
+
+markClass gid5 <anchor 50 0> @gpos2_0_mark0;
+lookup gpos2 {
+    pos base gid1 <anchor 250 700> mark @gpos2_0_mark0;
+} gpos2;
+
+feature liga {
+    script DFLT;
+    language dflt;
+        lookup gsub0;
+    script latn;
+    language dflt;
+        lookup gsub0;
+} liga;
+
+feature smcp {
+    script latn;
+    language dflt;
+        lookup gsub1;
+    language TRK exclude_dflt;
+        lookup gsub1;
+} smcp;
+
+feature kern {
+    script DFLT;
+    language dflt;
+        lookup gpos0;
+        # lookup gpos1; (not exported)
+} kern;
+
+feature mark {
+    script DFLT;
+    language dflt;
+        lookup gpos2;
+} mark;
+
+";
+        assert_eq!(fea, expected);
+        assert_eq!(
+            warnings,
+            vec!["GPOS lookup 1 (cursive attachment, type 3) not exported"]
+        );
+    }
+
+    #[test]
+    fn test_font_without_layout_exports_empty_file() {
+        let font = TestFontBuilder::new().glyph(Vec::new(), 500).build();
+        assert_eq!(font_to_fea(&font).unwrap(), (String::new(), Vec::new()));
+    }
+
+    fn import(font: &[u8], source: &str, mode: FeatureImportMode) -> Result<Vec<u8>, FontError> {
+        compile_fea(font, source, &FeatureImportOptions { mode }).map(|(font, _)| font)
+    }
+
+    #[test]
+    fn test_imported_features_export_back() {
+        let mut builder = TestFontBuilder::new();
+        for _ in 0..6 {
+            builder = builder.glyph(Vec::new(), 500);
+        }
+        let source = "\
+languagesystem DFLT dflt;
+languagesystem latn dflt;
+@left = [gid1 gid2];  # a named class
+
+feature liga {
+    lookupflag IgnoreMarks;
+    sub gid1 gid2 by gid3;
+    sub gid1 gid2 gid4 by gid5;
+} liga;
+
+feature kern {
+    pos gid1 gid2 -50;
+    pos @left [gid4 gid5] <0 0 -20 0>;
+    pos gid3 10;
+} kern;
+";
+        let (font, report) =
+            compile_fea(&builder.build(), source, &FeatureImportOptions::default()).unwrap();
+        assert_eq!(
+            report,
+            FeatureImportReport {
+                features: vec!["liga".to_string(), "kern".to_string()],
+                gsub_lookups: 1,
+                gpos_lookups: 2,
+                rules: 5,
+            }
+        );
+        let (fea, warnings) = font_to_fea(&font).unwrap();
+        let expected = "\
+languagesystem DFLT dflt;
+languagesystem latn dflt;
+
+lookup gsub0 {
+    lookupflag IgnoreMarks;
+    sub gid1 gid2 gid4 by gid5;
+    sub gid1 gid2 by gid3;
+} gsub0;
+
+lookup gpos0 {
+    pos gid1 gid2 -50;
+    subtable;
+    pos [gid1 gid2] [gid4 gid5] <0 0 -20 0>;
+} gpos0;
+
+lookup gpos1 {
+    pos gid3 10;
+} gpos1;
+
+feature liga {
+    script DFLT;
+    language dflt;
+        lookup gsub0;
+    script latn;
+    language dflt;
+        lookup gsub0;
+} liga;
+
+feature kern {
+    script DFLT;
+    language dflt;
+        lookup gpos0;
+        lookup gpos1;
+    script latn;
+    language dflt;
+        lookup gpos0;
+        lookup gpos1;
+} kern;
+
+";
+        assert_eq!(fea, expected);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_import_merges_or_replaces_features() {
+        let font = font_with_layout();
+        let source = "feature liga { sub gid4 gid5 by gid3; } liga;";
+        let liga_lookups = |font: &[u8]| {
+            let font = RawFontRef::new(font).unwrap();
+            let gsub = font.gsub().unwrap();
+            let features = gsub.feature_list().unwrap();
+            let record = features
+                .feature_records()
+                .iter()
+                .find(|r| r.feature_tag() == Tag::new(b"liga"))
+                .unwrap();
+            let feature = record.feature(features.offset_data()).unwrap();
+            let lookups: Vec<u16> = feature
+                .lookup_list_indices()
+                .iter()
+                .map(|i| i.get())
+                .collect();
+            lookups
+        };
+        let merged = import(&font, source, FeatureImportMode::Merge).unwrap();
+        assert_eq!(liga_lookups(&merged), vec![0, 2]);
+        let replaced = import(&font, source, FeatureImportMode::Replace).unwrap();
+        assert_eq!(liga_lookups(&replaced), vec![2]);
+        // GPOS is left alone
+        let gpos = |font: &[u8]| font_to_fea(font).unwrap().0.contains("pos gid1 gid2 -50;");
+        assert!(gpos(&merged) && gpos(&replaced));
+    }
+
+    #[test]
+    fn test_import_errors_name_the_line() {
+        let font = font_with_layout();
+        let err = import(
+            &font,
+            "feature liga {\n    sub gid1 nosuch by gid3;\n} liga;",
+            FeatureImportMode::Merge,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Unknown glyph 'nosuch' on line 2");
+        let err = import(
+            &font,
+            "feature ccmp { sub gid3 by gid1 gid2; } ccmp;",
+            FeatureImportMode::Merge,
+        )
+        .unwrap_err();
+        assert!(matches!(err, FontError::Unsupported { .. }), "{}", err);
+        let err = import(
+            &font,
+            "feature kern { pos gid1 gid2 -50; pos gid1 gid2 -60; } kern;",
+            FeatureImportMode::Merge,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Pair gid1 gid2"), "{}", err);
+    }
+}
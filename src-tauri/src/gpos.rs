@@ -0,0 +1,879 @@
+use crate::error::FontError;
+use crate::font_parser::{editable_font_bytes, get_font_bytes, save_edited_font, FontCache};
+use crate::kern::KernPair;
+use serde::Serialize;
+use skrifa::raw::tables::gpos::{AnchorTable, PairPos as RawPairPos, PositionSubtables};
+use skrifa::raw::types::Tag;
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::GlyphId;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use write_fonts::from_obj::ToOwnedTable;
+use write_fonts::tables::gpos::{
+    ExtensionPosFormat1, ExtensionSubtable, Gpos, PairPos, PairPosFormat1, PairSet,
+    PairValueRecord, PositionLookup, ValueFormat, ValueRecord,
+};
+use write_fonts::tables::layout::CoverageTable;
+use write_fonts::types::GlyphId16;
+use write_fonts::FontBuilder;
+
+/// Visit every GPOS lookup as `(lookup_index, lookup_type, subtables)`.
+/// Extension lookups (type 9) are unwrapped, so `lookup_type` is always the
+/// effective type (1–8). Fonts without GPOS visit nothing.
+pub fn for_each_position_lookup<'a>(
+    font: &RawFontRef<'a>,
+    mut visit: impl FnMut(u16, u16, PositionSubtables<'a>),
+) -> Result<(), FontError> {
+    let gpos = match font.gpos() {
+        Ok(gpos) => gpos,
+        Err(_) => return Ok(()),
+    };
+    let lookups = gpos
+        .lookup_list()
+        .map_err(|e| FontError::table("GPOS", e))?;
+    for (index, lookup) in lookups.lookups().iter().enumerate() {
+        let Ok(lookup) = lookup else {
+            continue;
+        };
+        let Ok(subtables) = lookup.subtables() else {
+            continue;
+        };
+        let lookup_type = match &subtables {
+            PositionSubtables::Single(_) => 1,
+            PositionSubtables::Pair(_) => 2,
+            PositionSubtables::Cursive(_) => 3,
+            PositionSubtables::MarkToBase(_) => 4,
+            PositionSubtables::MarkToLig(_) => 5,
+            PositionSubtables::MarkToMark(_) => 6,
+            PositionSubtables::Contextual(_) => 7,
+            PositionSubtables::ChainContextual(_) => 8,
+        };
+        visit(index as u16, lookup_type, subtables);
+    }
+    Ok(())
+}
+
+/// Default coordinates of an anchor; device / variation adjustments are ignored.
+pub fn anchor_coordinates(anchor: &AnchorTable<'_>) -> (i16, i16) {
+    match anchor {
+        AnchorTable::Format1(a) => (a.x_coordinate(), a.y_coordinate()),
+        AnchorTable::Format2(a) => (a.x_coordinate(), a.y_coordinate()),
+        AnchorTable::Format3(a) => (a.x_coordinate(), a.y_coordinate()),
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnchorRole {
+    /// Base glyph in a mark-to-base lookup.
+    Base,
+    /// Ligature glyph in a mark-to-ligature lookup.
+    Ligature,
+    /// Mark acting as the base in a mark-to-mark lookup.
+    BaseMark,
+    /// Mark glyph being attached (any of the three lookup types).
+    Mark,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GlyphAnchor {
+    pub lookup_index: u16,
+    pub lookup_type: u16,
+    pub role: AnchorRole,
+    pub mark_class: u16,
+    /// Ligature component index for mark-to-ligature anchors.
+    pub ligature_component: Option<u16>,
+    pub x: i16,
+    pub y: i16,
+}
+
+fn collect_glyph_anchors(bytes: &[u8], glyph_id: u32) -> Result<Vec<GlyphAnchor>, FontError> {
+    let font = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let gid = GlyphId::from(glyph_id);
+    let mut anchors = Vec::new();
+
+    for_each_position_lookup(&font, |lookup_index, lookup_type, subtables| {
+        let mut push = |role, mark_class, ligature_component, anchor: &AnchorTable<'_>| {
+            let (x, y) = anchor_coordinates(anchor);
+            anchors.push(GlyphAnchor {
+                lookup_index,
+                lookup_type,
+                role,
+                mark_class,
+                ligature_component,
+                x,
+                y,
+            });
+        };
+
+        match subtables {
+            PositionSubtables::MarkToBase(subtables) => {
+                for sub in subtables.iter().flatten() {
+                    if let (Ok(cov), Ok(marks)) = (sub.mark_coverage(), sub.mark_array()) {
+                        if let Some(idx) = cov.get(gid) {
+                            if let Some(rec) = marks.mark_records().get(idx as usize) {
+                                if let Ok(anchor) = rec.mark_anchor(marks.offset_data()) {
+                                    push(AnchorRole::Mark, rec.mark_class(), None, &anchor);
+                                }
+                            }
+                        }
+                    }
+                    if let (Ok(cov), Ok(bases)) = (sub.base_coverage(), sub.base_array()) {
+                        let Some(idx) = cov.get(gid) else {
+                            continue;
+                        };
+                        let Ok(rec) = bases.base_records().get(idx as usize) else {
+                            continue;
+                        };
+                        for (class, anchor) in
+                            rec.base_anchors(bases.offset_data()).iter().enumerate()
+                        {
+                            if let Some(Ok(anchor)) = anchor {
+                                push(AnchorRole::Base, class as u16, None, &anchor);
+                            }
+                        }
+                    }
+                }
+            }
+            PositionSubtables::MarkToLig(subtables) => {
+                for sub in subtables.iter().flatten() {
+                    if let (Ok(cov), Ok(marks)) = (sub.mark_coverage(), sub.mark_array()) {
+                        if let Some(idx) = cov.get(gid) {
+                            if let Some(rec) = marks.mark_records().get(idx as usize) {
+                                if let Ok(anchor) = rec.mark_anchor(marks.offset_data()) {
+                                    push(AnchorRole::Mark, rec.mark_class(), None, &anchor);
+                                }
+                            }
+                        }
+                    }
+                    if let (Ok(cov), Ok(ligs)) = (sub.ligature_coverage(), sub.ligature_array()) {
+                        let Some(idx) = cov.get(gid) else {
+                            continue;
+                        };
+                        let Ok(attach) = ligs.ligature_attaches().get(idx as usize) else {
+                            continue;
+                        };
+                        for (component, rec) in attach.component_records().iter().enumerate() {
+                            let Ok(rec) = rec else {
+                                continue;
+                            };
+                            let anchors = rec.ligature_anchors(attach.offset_data());
+                            for (class, anchor) in anchors.iter().enumerate() {
+                                if let Some(Ok(anchor)) = anchor {
+                                    push(
+                                        AnchorRole::Ligature,
+                                        class as u16,
+                                        Some(component as u16),
+                                        &anchor,
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            PositionSubtables::MarkToMark(subtables) => {
+                for sub in subtables.iter().flatten() {
+                    if let (Ok(cov), Ok(marks)) = (sub.mark1_coverage(), sub.mark1_array()) {
+                        if let Some(idx) = cov.get(gid) {
+                            if let Some(rec) = marks.mark_records().get(idx as usize) {
+                                if let Ok(anchor) = rec.mark_anchor(marks.offset_data()) {
+                                    push(AnchorRole::Mark, rec.mark_class(), None, &anchor);
+                                }
+                            }
+                        }
+                    }
+                    if let (Ok(cov), Ok(marks2)) = (sub.mark2_coverage(), sub.mark2_array()) {
+                        let Some(idx) = cov.get(gid) else {
+                            continue;
+                        };
+                        let Ok(rec) = marks2.mark2_records().get(idx as usize) else {
+                            continue;
+                        };
+                        for (class, anchor) in
+                            rec.mark2_anchors(marks2.offset_data()).iter().enumerate()
+                        {
+                            if let Some(Ok(anchor)) = anchor {
+                                push(AnchorRole::BaseMark, class as u16, None, &anchor);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    })?;
+
+    Ok(anchors)
+}
+
+pub fn get_glyph_anchors(
+    file_path: &str,
+    glyph_id: u32,
+    cache: &FontCache,
+) -> Result<Vec<GlyphAnchor>, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    collect_glyph_anchors(&bytes, glyph_id)
+}
+
+/// Where a pair's kerning comes from within one lookup.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KerningSource {
+    pub lookup_index: u16,
+    pub subtable_index: u16,
+    /// PairPos format: 1 for a glyph pair record, 2 for a class pair.
+    pub format: u16,
+    /// Left and right class in a format 2 subtable. Class 0 is the catch-all
+    /// "every other glyph" class.
+    pub classes: Option<(u16, u16)>,
+    pub value: i16,
+}
+
+/// The xAdvance adjustment a shaper applies between two glyphs: each pair
+/// lookup of the 'kern' feature contributes its first matching subtable.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct GposKerning {
+    pub left: u16,
+    pub right: u16,
+    pub value: i32,
+    pub sources: Vec<KerningSource>,
+}
+
+const KERN_FEATURE: Tag = Tag::new(b"kern");
+
+/// Indices of the lookups referenced by any 'kern' feature.
+fn kern_feature_lookups(font: &RawFontRef<'_>) -> Result<BTreeSet<u16>, FontError> {
+    let Ok(gpos) = font.gpos() else {
+        return Ok(BTreeSet::new());
+    };
+    let features = gpos
+        .feature_list()
+        .map_err(|e| FontError::table("GPOS", e))?;
+    let mut lookups = BTreeSet::new();
+    for record in features.feature_records() {
+        if record.feature_tag() != KERN_FEATURE {
+            continue;
+        }
+        let feature = record
+            .feature(features.offset_data())
+            .map_err(|e| FontError::table("GPOS", e))?;
+        lookups.extend(feature.lookup_list_indices().iter().map(|i| i.get()));
+    }
+    Ok(lookups)
+}
+
+/// The PairPos format of every subtable of each kern pair lookup, by lookup
+/// index.
+pub(crate) fn kern_pair_lookups(
+    font: &RawFontRef<'_>,
+) -> Result<BTreeMap<u16, Vec<u16>>, FontError> {
+    let kern = kern_feature_lookups(font)?;
+    let mut lookups = BTreeMap::new();
+    for_each_position_lookup(font, |index, _, subtables| {
+        if let (true, PositionSubtables::Pair(subtables)) = (kern.contains(&index), subtables) {
+            let formats = subtables
+                .iter()
+                .map(|sub| match sub {
+                    Ok(RawPairPos::Format2(_)) => 2,
+                    _ => 1,
+                })
+                .collect();
+            lookups.insert(index, formats);
+        }
+    })?;
+    Ok(lookups)
+}
+
+/// The first subtable of a pair lookup that applies to (left, right):
+/// format 1 only when it has a record for the pair, format 2 whenever the
+/// left glyph is covered.
+fn match_pair<'a>(
+    lookup_index: u16,
+    subtables: impl Iterator<Item = Result<RawPairPos<'a>, skrifa::raw::ReadError>>,
+    left: GlyphId,
+    right: GlyphId,
+) -> Result<Option<KerningSource>, FontError> {
+    for (index, subtable) in subtables.enumerate() {
+        let subtable_index = index as u16;
+        match subtable.map_err(|e| FontError::table("GPOS", e))? {
+            RawPairPos::Format1(table) => {
+                let coverage = table.coverage().map_err(|e| FontError::table("GPOS", e))?;
+                let Some(set_index) = coverage.get(left) else {
+                    continue;
+                };
+                let set = table
+                    .pair_sets()
+                    .get(set_index as usize)
+                    .map_err(|e| FontError::table("GPOS", e))?;
+                for record in set.pair_value_records().iter() {
+                    let record = record.map_err(|e| FontError::table("GPOS", e))?;
+                    if record.second_glyph().to_u32() == right.to_u32() {
+                        let value = record.value_record1().x_advance().unwrap_or(0);
+                        return Ok(Some(KerningSource {
+                            lookup_index,
+                            subtable_index,
+                            format: 1,
+                            classes: None,
+                            value,
+                        }));
+                    }
+                }
+            }
+            RawPairPos::Format2(table) => {
+                let coverage = table.coverage().map_err(|e| FontError::table("GPOS", e))?;
+                if coverage.get(left).is_none() {
+                    continue;
+                }
+                let class1 = table
+                    .class_def1()
+                    .map_err(|e| FontError::table("GPOS", e))?
+                    .get(left);
+                let class2 = table
+                    .class_def2()
+                    .map_err(|e| FontError::table("GPOS", e))?
+                    .get(right);
+                let value = table
+                    .class1_records()
+                    .get(class1 as usize)
+                    .and_then(|row| row.class2_records().get(class2 as usize))
+                    .map_err(|e| FontError::table("GPOS", e))?
+                    .value_record1()
+                    .x_advance()
+                    .unwrap_or(0);
+                return Ok(Some(KerningSource {
+                    lookup_index,
+                    subtable_index,
+                    format: 2,
+                    classes: Some((class1, class2)),
+                    value,
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) fn resolve_kerning(
+    font: &RawFontRef<'_>,
+    lookups: &BTreeMap<u16, Vec<u16>>,
+    left: u16,
+    right: u16,
+) -> Result<GposKerning, FontError> {
+    let mut sources = Vec::new();
+    let mut failure = None;
+    for_each_position_lookup(font, |lookup_index, _, subtables| {
+        let PositionSubtables::Pair(subtables) = subtables else {
+            return;
+        };
+        if failure.is_some() || !lookups.contains_key(&lookup_index) {
+            return;
+        }
+        match match_pair(
+            lookup_index,
+            subtables.iter(),
+            GlyphId::new(left as u32),
+            GlyphId::new(right as u32),
+        ) {
+            Ok(Some(source)) => sources.push(source),
+            Ok(None) => {}
+            Err(e) => failure = Some(e),
+        }
+    })?;
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    Ok(GposKerning {
+        left,
+        right,
+        value: sources.iter().map(|s| s.value as i32).sum(),
+        sources,
+    })
+}
+
+/// The effective GPOS kerning between two glyphs, class pairs and extension
+/// lookups included.
+pub fn get_gpos_kerning(
+    file_path: &str,
+    left: u16,
+    right: u16,
+    cache: &FontCache,
+) -> Result<GposKerning, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    resolve_kerning(&font, &kern_pair_lookups(&font)?, left, right)
+}
+
+/// Value records of one side of a pair all share a format; widen it to
+/// carry xAdvance when it does not yet.
+fn ensure_x_advance<'a>(records: impl Iterator<Item = &'a mut ValueRecord>) {
+    for record in records {
+        let format = record.format();
+        if !format.contains(ValueFormat::X_ADVANCE) {
+            record.set_explicit_value_format(format | ValueFormat::X_ADVANCE);
+        }
+        record.x_advance.get_or_insert(0);
+    }
+}
+
+/// Set or add glyph pair records in a format 1 subtable, keeping the
+/// coverage and each pair set sorted.
+fn set_format1_pairs(table: &mut PairPosFormat1, pairs: &[(u16, u16, i16)]) {
+    let format2 = table
+        .pair_sets
+        .first()
+        .and_then(|set| set.pair_value_records.first())
+        .map_or(ValueFormat::empty(), |r| r.value_record2.format());
+    ensure_x_advance(
+        table
+            .pair_sets
+            .iter_mut()
+            .flat_map(|set| set.pair_value_records.iter_mut())
+            .map(|r| &mut r.value_record1),
+    );
+    let format1 = table
+        .pair_sets
+        .first()
+        .and_then(|set| set.pair_value_records.first())
+        .map_or(ValueFormat::X_ADVANCE, |r| r.value_record1.format());
+
+    let mut glyphs: Vec<GlyphId16> = table.coverage.iter().collect();
+    for &(left, right, value) in pairs {
+        let (left, right) = (GlyphId16::new(left), GlyphId16::new(right));
+        let set_index = match glyphs.binary_search(&left) {
+            Ok(index) => index,
+            Err(index) => {
+                glyphs.insert(index, left);
+                table
+                    .pair_sets
+                    .insert(index, PairSet::new(Vec::new()).into());
+                index
+            }
+        };
+        let records = &mut table.pair_sets[set_index].pair_value_records;
+        match records.binary_search_by_key(&right, |r| r.second_glyph) {
+            Ok(index) => records[index].value_record1.x_advance = Some(value),
+            Err(index) => records.insert(
+                index,
+                PairValueRecord::new(
+                    right,
+                    ValueRecord::new()
+                        .with_explicit_value_format(format1)
+                        .with_x_advance(value),
+                    ValueRecord::new().with_explicit_value_format(format2),
+                ),
+            ),
+        }
+    }
+    table.coverage = glyphs.into_iter().collect::<CoverageTable>().into();
+}
+
+/// The PairPos subtables of a lookup, looking through extension subtables.
+fn pair_subtables_mut(lookup: &mut PositionLookup) -> Vec<&mut PairPos> {
+    match lookup {
+        PositionLookup::Pair(lookup) => lookup.subtables.iter_mut().map(|s| &mut **s).collect(),
+        PositionLookup::Extension(lookup) => lookup
+            .subtables
+            .iter_mut()
+            .filter_map(|s| match &mut **s {
+                ExtensionSubtable::Pair(ext) => Some(&mut *ext.extension),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn insert_pair_subtable(lookup: &mut PositionLookup, index: usize, subtable: PairPos) {
+    match lookup {
+        PositionLookup::Pair(lookup) => lookup.subtables.insert(index, subtable.into()),
+        PositionLookup::Extension(lookup) => lookup.subtables.insert(
+            index,
+            ExtensionSubtable::Pair(ExtensionPosFormat1::new(2, subtable)).into(),
+        ),
+        _ => {}
+    }
+}
+
+/// Set the effective kerning of each pair by adjusting the record a shaper
+/// reads first: the glyph pair record, or the class pair value (which moves
+/// every pair in those two classes). Pairs without a record of their own are
+/// added to a format 1 subtable ahead of any class subtable that would
+/// otherwise catch them, creating that subtable when needed. Lookups are
+/// never created; fonts without a GPOS 'kern' feature are rejected.
+pub fn update_gpos_kerning(
+    file_path: &str,
+    pairs: &[KernPair],
+    cache: &FontCache,
+) -> Result<Vec<GposKerning>, FontError> {
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let lookups = kern_pair_lookups(&font)?;
+    let Some((&first_lookup, _)) = lookups.iter().next() else {
+        return Err(FontError::unsupported(
+            "editing GPOS kerning in a font without a 'kern' pair lookup",
+        ));
+    };
+
+    let mut seen = HashSet::new();
+    // (lookup, subtable, class1, class2) -> value
+    let mut class_edits = BTreeMap::<(u16, u16, u16, u16), i16>::new();
+    // (lookup, subtable) -> pairs for an existing format 1 subtable
+    let mut pair_edits = BTreeMap::<(u16, u16), Vec<(u16, u16, i16)>>::new();
+    // (lookup, position) -> pairs for a new format 1 subtable
+    let mut new_subtables = BTreeMap::<(u16, u16), Vec<(u16, u16, i16)>>::new();
+    for pair in pairs {
+        if !seen.insert((pair.left, pair.right)) {
+            return Err(FontError::input(format!(
+                "Pair {} {} is listed more than once",
+                pair.left, pair.right
+            )));
+        }
+        let current = resolve_kerning(&font, &lookups, pair.left, pair.right)?;
+        let delta = pair.value as i32 - current.value;
+        if delta == 0 {
+            continue;
+        }
+        let new_value = |old: i16| {
+            i16::try_from(old as i32 + delta).map_err(|_| {
+                FontError::input(format!(
+                    "Kerning for pair {} {} does not fit in int16",
+                    pair.left, pair.right
+                ))
+            })
+        };
+        let edit = (pair.left, pair.right);
+        match current.sources.first() {
+            Some(&KerningSource {
+                lookup_index,
+                subtable_index,
+                classes: None,
+                value,
+                ..
+            }) => pair_edits
+                .entry((lookup_index, subtable_index))
+                .or_default()
+                .push((edit.0, edit.1, new_value(value)?)),
+            Some(&KerningSource {
+                lookup_index,
+                subtable_index,
+                classes: Some((class1, class2)),
+                value,
+                ..
+            }) if class1 != 0 && class2 != 0 => {
+                let value = new_value(value)?;
+                let key = (lookup_index, subtable_index, class1, class2);
+                if class_edits.insert(key, value).is_some_and(|v| v != value) {
+                    return Err(FontError::input(format!(
+                        "Pair {} {} shares a class pair with another edit that sets a different value",
+                        pair.left, pair.right
+                    )));
+                }
+            }
+            source => {
+                // Nothing specific to the pair yet: use a glyph pair subtable
+                // that comes before whatever currently catches the pair
+                let (lookup_index, before) = match source {
+                    Some(s) => (s.lookup_index, s.subtable_index as usize),
+                    None => (first_lookup, usize::MAX),
+                };
+                let value = new_value(source.map_or(0, |s| s.value))?;
+                let formats = &lookups[&lookup_index];
+                let existing = formats.iter().take(before).position(|&format| format == 1);
+                match existing {
+                    Some(index) => pair_edits.entry((lookup_index, index as u16)),
+                    None => new_subtables.entry((lookup_index, before.min(formats.len()) as u16)),
+                }
+                .or_default()
+                .push((edit.0, edit.1, value));
+            }
+        }
+    }
+
+    let mut gpos: Gpos = font
+        .gpos()
+        .map_err(|e| FontError::table("GPOS", e))?
+        .to_owned_table();
+    let lookup_list = &mut gpos.lookup_list.lookups;
+    for (&(lookup, subtable, class1, class2), &value) in &class_edits {
+        if let Some(PairPos::Format2(table)) = pair_subtables_mut(&mut lookup_list[lookup as usize])
+            .into_iter()
+            .nth(subtable as usize)
+        {
+            ensure_x_advance(
+                table
+                    .class1_records
+                    .iter_mut()
+                    .flat_map(|row| row.class2_records.iter_mut())
+                    .map(|r| &mut r.value_record1),
+            );
+            table.class1_records[class1 as usize].class2_records[class2 as usize]
+                .value_record1
+                .x_advance = Some(value);
+        }
+    }
+    for (&(lookup, subtable), edits) in &pair_edits {
+        if let Some(PairPos::Format1(table)) = pair_subtables_mut(&mut lookup_list[lookup as usize])
+            .into_iter()
+            .nth(subtable as usize)
+        {
+            set_format1_pairs(table, edits);
+        }
+    }
+    // Back to front, so earlier positions in the same lookup stay valid
+    for (&(lookup, position), edits) in new_subtables.iter().rev() {
+        let mut table = PairPosFormat1::new(CoverageTable::default(), Vec::new());
+        set_format1_pairs(&mut table, edits);
+        insert_pair_subtable(
+            &mut lookup_list[lookup as usize],
+            position as usize,
+            PairPos::Format1(table),
+        );
+    }
+
+    let new_bytes = FontBuilder::new()
+        .add_table(&gpos)
+        .map_err(|e| FontError::build("GPOS", e))?
+        .copy_missing_tables(font)
+        .build();
+    let font = RawFontRef::new(&new_bytes).map_err(FontError::invalid_font)?;
+    let lookups = kern_pair_lookups(&font)?;
+    let results = pairs
+        .iter()
+        .map(|pair| resolve_kerning(&font, &lookups, pair.left, pair.right))
+        .collect::<Result<Vec<_>, _>>()?;
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{mark_to_base_gpos, simple_glyph, TestFontBuilder};
+
+    fn font_with_marks() -> Vec<u8> {
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(bar.clone(), 500)
+            .glyph(bar, 0)
+            .table(b"GPOS", mark_to_base_gpos(1, (250, 700), 2, (50, 0)))
+            .build()
+    }
+
+    #[test]
+    fn test_mark_to_base_anchors() {
+        let font = font_with_marks();
+
+        let base = collect_glyph_anchors(&font, 1).unwrap();
+        assert_eq!(base.len(), 1);
+        assert_eq!(base[0].role, AnchorRole::Base);
+        assert_eq!(base[0].lookup_type, 4);
+        assert_eq!((base[0].x, base[0].y), (250, 700));
+
+        let mark = collect_glyph_anchors(&font, 2).unwrap();
+        assert_eq!(mark.len(), 1);
+        assert_eq!(mark[0].role, AnchorRole::Mark);
+        assert_eq!((mark[0].x, mark[0].y), (50, 0));
+
+        assert!(collect_glyph_anchors(&font, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_font_without_gpos_has_no_anchors() {
+        let font = TestFontBuilder::new().glyph(Vec::new(), 500).build();
+        assert!(collect_glyph_anchors(&font, 0).unwrap().is_empty());
+    }
+
+    /// Lookup 0 holds a class subtable ([3 4] x [5 6] = -80) followed by a
+    /// glyph pair subtable (1 2 = -50); lookup 1 is an extension lookup with
+    /// 1 2 = -10 and 7 8 = 20. Both belong to 'kern'.
+    fn font_with_kerning() -> Vec<u8> {
+        use write_fonts::tables::gpos::{Class1Record, Class2Record, PositionLookupList};
+        use write_fonts::tables::layout::{
+            ClassDef, Feature, FeatureList, FeatureRecord, LangSys, Lookup, LookupFlag, Script,
+            ScriptList, ScriptRecord,
+        };
+
+        let x = |value: i16| ValueRecord::new().with_x_advance(value);
+        let class_def = |classes: &[(u16, u16)]| -> ClassDef {
+            classes
+                .iter()
+                .map(|&(gid, class)| (GlyphId16::new(gid), class))
+                .collect()
+        };
+        let row = |values: &[i16]| {
+            Class1Record::new(
+                values
+                    .iter()
+                    .map(|&v| Class2Record::new(x(v), ValueRecord::new()))
+                    .collect(),
+            )
+        };
+        let glyph_pairs = |pairs: &[(u16, &[(u16, i16)])]| {
+            PairPos::format_1(
+                pairs
+                    .iter()
+                    .map(|&(left, _)| GlyphId16::new(left))
+                    .collect(),
+                pairs
+                    .iter()
+                    .map(|(_, rights)| {
+                        PairSet::new(
+                            rights
+                                .iter()
+                                .map(|&(right, v)| {
+                                    PairValueRecord::new(
+                                        GlyphId16::new(right),
+                                        x(v),
+                                        ValueRecord::new(),
+                                    )
+                                })
+                                .collect(),
+                        )
+                    })
+                    .collect(),
+            )
+        };
+        let classes = PairPos::format_2(
+            [3, 4].map(GlyphId16::new).into_iter().collect(),
+            class_def(&[(3, 1), (4, 1)]),
+            class_def(&[(5, 1), (6, 1)]),
+            vec![row(&[0, 0]), row(&[0, -80])],
+        );
+        let lookup0 = PositionLookup::Pair(Lookup::new(
+            LookupFlag::empty(),
+            vec![classes, glyph_pairs(&[(1, &[(2, -50)])])],
+        ));
+        let lookup1 = PositionLookup::Extension(Lookup::new(
+            LookupFlag::empty(),
+            vec![ExtensionSubtable::Pair(ExtensionPosFormat1::new(
+                2,
+                glyph_pairs(&[(1, &[(2, -10)]), (7, &[(8, 20)])]),
+            ))],
+        ));
+        let gpos = Gpos::new(
+            ScriptList::new(vec![ScriptRecord::new(
+                Tag::new(b"DFLT"),
+                Script::new(Some(LangSys::new(vec![0])), Vec::new()),
+            )]),
+            FeatureList::new(vec![FeatureRecord::new(
+                KERN_FEATURE,
+                Feature::new(None, vec![0, 1]),
+            )]),
+            PositionLookupList::new(vec![lookup0, lookup1]),
+        );
+        let mut builder = TestFontBuilder::new();
+        for _ in 0..10 {
+            builder = builder.glyph(Vec::new(), 500);
+        }
+        builder
+            .table(b"GPOS", write_fonts::dump_table(&gpos).unwrap())
+            .build()
+    }
+
+    #[test]
+    fn test_gpos_kerning_resolves_classes_and_extensions() {
+        let font = font_with_kerning();
+        let font = RawFontRef::new(&font).unwrap();
+        let lookups = kern_pair_lookups(&font).unwrap();
+        let kerning = |left, right| resolve_kerning(&font, &lookups, left, right).unwrap();
+
+        let both = kerning(1, 2);
+        assert_eq!(both.value, -60);
+        assert_eq!(
+            both.sources
+                .iter()
+                .map(|s| s.lookup_index)
+                .collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        let class_pair = kerning(3, 6);
+        assert_eq!(class_pair.value, -80);
+        assert_eq!(class_pair.sources[0].classes, Some((1, 1)));
+        // The class subtable catches 3 7 through class 0, with no adjustment
+        let caught = kerning(3, 7);
+        assert_eq!(caught.value, 0);
+        assert_eq!(caught.sources[0].classes, Some((1, 0)));
+        assert_eq!(kerning(7, 8).value, 20);
+        assert!(kerning(9, 9).sources.is_empty());
+    }
+
+    #[test]
+    fn test_update_gpos_kerning() {
+        let dir = std::env::temp_dir().join(format!("typebrew-gpos-kern-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, font_with_kerning()).unwrap();
+        let cache = FontCache::new();
+        let pair = |left, right, value| KernPair { left, right, value };
+
+        let results = update_gpos_kerning(
+            path,
+            &[
+                pair(1, 2, -70),
+                pair(4, 5, -100),
+                pair(3, 7, 15),
+                pair(9, 1, 30),
+            ],
+            &cache,
+        )
+        .unwrap();
+        assert_eq!(
+            results.iter().map(|r| r.value).collect::<Vec<_>>(),
+            vec![-70, -100, 15, 30]
+        );
+
+        let get = |left, right| get_gpos_kerning(path, left, right, &cache).unwrap();
+        // The glyph pair record absorbed the change; lookup 1 is untouched
+        assert_eq!(
+            get(1, 2)
+                .sources
+                .iter()
+                .map(|s| s.value)
+                .collect::<Vec<_>>(),
+            vec![-60, -10]
+        );
+        // Class pair edits move the whole class
+        assert_eq!(get(3, 6).value, -100);
+        // 3 7 gets a new glyph pair subtable ahead of the class subtable
+        let inserted = get(3, 7);
+        assert_eq!(
+            (
+                inserted.sources[0].subtable_index,
+                inserted.sources[0].format
+            ),
+            (0, 1)
+        );
+        // 9 1 joins the existing glyph pair subtable, now third
+        assert_eq!(get(9, 1).sources[0].subtable_index, 2);
+        assert_eq!(get(7, 8).value, 20);
+
+        // Two edits landing on one class pair cannot both win
+        assert!(matches!(
+            update_gpos_kerning(path, &[pair(3, 5, -1), pair(4, 6, -2)], &cache),
+            Err(FontError::InvalidInput { .. })
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_update_gpos_kerning_needs_kern_lookup() {
+        let dir = std::env::temp_dir().join(format!("typebrew-gpos-none-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, font_with_marks()).unwrap();
+        let cache = FontCache::new();
+        let pair = KernPair {
+            left: 1,
+            right: 2,
+            value: -20,
+        };
+        assert!(matches!(
+            update_gpos_kerning(path, &[pair], &cache),
+            Err(FontError::Unsupported { .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
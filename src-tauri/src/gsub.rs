@@ -0,0 +1,348 @@
+use crate::error::FontError;
+use crate::font_parser::{get_font_bytes, FontCache};
+use serde::Serialize;
+use skrifa::raw::tables::gsub::{SingleSubst, SubstitutionSubtables};
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Visit every GSUB lookup as `(lookup_index, lookup_type, subtables)`.
+/// Extension lookups (type 7) are unwrapped, so `lookup_type` is always the
+/// effective type. Fonts without GSUB visit nothing.
+pub fn for_each_substitution_lookup<'a>(
+    font: &RawFontRef<'a>,
+    mut visit: impl FnMut(u16, u16, SubstitutionSubtables<'a>),
+) -> Result<(), FontError> {
+    let gsub = match font.gsub() {
+        Ok(gsub) => gsub,
+        Err(_) => return Ok(()),
+    };
+    let lookups = gsub
+        .lookup_list()
+        .map_err(|e| FontError::table("GSUB", e))?;
+    for (index, lookup) in lookups.lookups().iter().enumerate() {
+        let Ok(lookup) = lookup else {
+            continue;
+        };
+        let Ok(subtables) = lookup.subtables() else {
+            continue;
+        };
+        let lookup_type = match &subtables {
+            SubstitutionSubtables::Single(_) => 1,
+            SubstitutionSubtables::Multiple(_) => 2,
+            SubstitutionSubtables::Alternate(_) => 3,
+            SubstitutionSubtables::Ligature(_) => 4,
+            SubstitutionSubtables::Contextual(_) => 5,
+            SubstitutionSubtables::ChainContextual(_) => 6,
+            SubstitutionSubtables::Reverse(_) => 8,
+        };
+        visit(index as u16, lookup_type, subtables);
+    }
+    Ok(())
+}
+
+/// Every glyph that some GSUB lookup can produce as output.
+/// Contextual lookups only reference other lookups, so they add nothing here.
+pub fn substitution_outputs(font: &RawFontRef<'_>) -> Result<HashSet<u32>, FontError> {
+    let mut outputs = HashSet::new();
+    for_each_substitution_lookup(font, |_, _, subtables| match subtables {
+        SubstitutionSubtables::Single(subtables) => {
+            for sub in subtables.iter().flatten() {
+                match sub {
+                    SingleSubst::Format1(sub) => {
+                        let Ok(coverage) = sub.coverage() else {
+                            continue;
+                        };
+                        let delta = sub.delta_glyph_id() as i32;
+                        for gid in coverage.iter() {
+                            // Deltas wrap modulo 65536
+                            let out = (gid.to_u16() as i32 + delta).rem_euclid(0x10000);
+                            outputs.insert(out as u32);
+                        }
+                    }
+                    SingleSubst::Format2(sub) => {
+                        outputs.extend(sub.substitute_glyph_ids().iter().map(|g| g.get().to_u32()));
+                    }
+                }
+            }
+        }
+        SubstitutionSubtables::Multiple(subtables) => {
+            for sub in subtables.iter().flatten() {
+                for seq in sub.sequences().iter().flatten() {
+                    outputs.extend(seq.substitute_glyph_ids().iter().map(|g| g.get().to_u32()));
+                }
+            }
+        }
+        SubstitutionSubtables::Alternate(subtables) => {
+            for sub in subtables.iter().flatten() {
+                for set in sub.alternate_sets().iter().flatten() {
+                    outputs.extend(set.alternate_glyph_ids().iter().map(|g| g.get().to_u32()));
+                }
+            }
+        }
+        SubstitutionSubtables::Ligature(subtables) => {
+            for sub in subtables.iter().flatten() {
+                for set in sub.ligature_sets().iter().flatten() {
+                    for lig in set.ligatures().iter().flatten() {
+                        outputs.insert(lig.ligature_glyph().to_u32());
+                    }
+                }
+            }
+        }
+        SubstitutionSubtables::Reverse(subtables) => {
+            for sub in subtables.iter().flatten() {
+                outputs.extend(sub.substitute_glyph_ids().iter().map(|g| g.get().to_u32()));
+            }
+        }
+        SubstitutionSubtables::Contextual(_) | SubstitutionSubtables::ChainContextual(_) => {}
+    })?;
+    Ok(outputs)
+}
+
+/// One ligature rule: `component_glyph_ids` in order become
+/// `ligature_glyph_id`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LigatureEntry {
+    pub component_glyph_ids: Vec<u32>,
+    /// Post/CFF names, or `gidN` for unnamed glyphs.
+    pub component_names: Vec<String>,
+    pub ligature_glyph_id: u32,
+    /// The characters that type the components, when every component has a
+    /// cmap entry.
+    pub text: Option<String>,
+    /// Features whose lookups hold the rule, sorted.
+    pub feature_tags: Vec<String>,
+    /// The ligature glyph's outline (font units, Y negated), when asked for
+    /// and already in the outline cache.
+    pub svg_path: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LigatureReport {
+    pub ligatures: Vec<LigatureEntry>,
+    /// Contextual and chained-context lookups, which were not analyzed for
+    /// the ligatures they trigger.
+    pub skipped_contextual_lookups: usize,
+}
+
+/// Feature tags referencing each GSUB lookup.
+fn lookup_feature_tags(font: &RawFontRef<'_>) -> HashMap<u16, BTreeSet<String>> {
+    let mut tags: HashMap<u16, BTreeSet<String>> = HashMap::new();
+    let Ok(features) = font.gsub().and_then(|gsub| gsub.feature_list()) else {
+        return tags;
+    };
+    for record in features.feature_records() {
+        let Ok(feature) = record.feature(features.offset_data()) else {
+            continue;
+        };
+        let tag = record.feature_tag().to_string().trim_end().to_string();
+        for index in feature.lookup_list_indices() {
+            tags.entry(index.get()).or_default().insert(tag.clone());
+        }
+    }
+    tags
+}
+
+/// Every rule of the GSUB ligature lookups, in lookup order. A rule found in
+/// several lookups is listed once, with the features of all of them.
+fn collect_ligatures(bytes: &[u8]) -> Result<LigatureReport, FontError> {
+    let font = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let skrifa_font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let names = skrifa_font.glyph_names();
+    let glyph_name = |gid: u32| {
+        names
+            .get(GlyphId::new(gid))
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| format!("gid{}", gid))
+    };
+    let mut characters: HashMap<u32, char> = HashMap::new();
+    for (codepoint, gid) in skrifa_font.charmap().mappings() {
+        if let Some(ch) = char::from_u32(codepoint) {
+            // Mappings come in codepoint order; keep the lowest
+            characters.entry(gid.to_u32()).or_insert(ch);
+        }
+    }
+    let feature_tags = lookup_feature_tags(&font);
+
+    let mut rules: Vec<(Vec<u32>, u32, BTreeSet<String>)> = Vec::new();
+    let mut skipped_contextual_lookups = 0;
+    for_each_substitution_lookup(&font, |index, _, subtables| match subtables {
+        SubstitutionSubtables::Ligature(subtables) => {
+            let tags = feature_tags.get(&index).cloned().unwrap_or_default();
+            for sub in subtables.iter().flatten() {
+                let Ok(coverage) = sub.coverage() else {
+                    continue;
+                };
+                for (first, set) in coverage.iter().zip(sub.ligature_sets().iter()) {
+                    let Ok(set) = set else {
+                        continue;
+                    };
+                    for lig in set.ligatures().iter().flatten() {
+                        let components: Vec<u32> = std::iter::once(first.to_u32())
+                            .chain(lig.component_glyph_ids().iter().map(|g| g.get().to_u32()))
+                            .collect();
+                        let ligature = lig.ligature_glyph().to_u32();
+                        match rules
+                            .iter_mut()
+                            .find(|(c, l, _)| *c == components && *l == ligature)
+                        {
+                            Some((_, _, existing)) => existing.extend(tags.iter().cloned()),
+                            None => rules.push((components, ligature, tags.clone())),
+                        }
+                    }
+                }
+            }
+        }
+        SubstitutionSubtables::Contextual(_) | SubstitutionSubtables::ChainContextual(_) => {
+            skipped_contextual_lookups += 1;
+        }
+        _ => {}
+    })?;
+
+    let ligatures = rules
+        .into_iter()
+        .map(|(components, ligature, tags)| LigatureEntry {
+            component_names: components.iter().map(|&g| glyph_name(g)).collect(),
+            text: components
+                .iter()
+                .map(|g| characters.get(g))
+                .collect::<Option<String>>(),
+            component_glyph_ids: components,
+            ligature_glyph_id: ligature,
+            feature_tags: tags.into_iter().collect(),
+            svg_path: None,
+        })
+        .collect();
+    Ok(LigatureReport {
+        ligatures,
+        skipped_contextual_lookups,
+    })
+}
+
+/// List the ligatures of `file_path`'s GSUB for a gallery. With
+/// `include_paths`, outlines the glyph grid has already extracted are
+/// attached; nothing is drawn here.
+pub fn get_ligatures(
+    file_path: &str,
+    include_paths: bool,
+    cache: &FontCache,
+) -> Result<LigatureReport, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let mut report = collect_ligatures(&bytes)?;
+    if include_paths {
+        let ids: Vec<u32> = report
+            .ligatures
+            .iter()
+            .map(|l| l.ligature_glyph_id)
+            .collect();
+        let paths = cache.cached_svg_paths(file_path, &ids);
+        for ligature in &mut report.ligatures {
+            ligature.svg_path = paths.get(&ligature.ligature_glyph_id).cloned();
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::TestFontBuilder;
+    use write_fonts::tables::gsub::{
+        Gsub, Ligature, LigatureSet, LigatureSubstFormat1, SubstitutionLookup,
+        SubstitutionLookupList,
+    };
+    use write_fonts::tables::layout::{
+        Feature, FeatureList, FeatureRecord, LangSys, Lookup, LookupFlag, Script, ScriptList,
+        ScriptRecord, SequenceContext, SequenceLookupRecord,
+    };
+    use write_fonts::types::{GlyphId16, Tag};
+
+    fn ligature_lookup(rules: &[(&[u16], u16)]) -> SubstitutionLookup {
+        // One rule per first glyph is enough here
+        let sets = rules
+            .iter()
+            .map(|(components, ligature)| {
+                LigatureSet::new(vec![Ligature::new(
+                    GlyphId16::new(*ligature),
+                    components[1..]
+                        .iter()
+                        .copied()
+                        .map(GlyphId16::new)
+                        .collect(),
+                )])
+            })
+            .collect();
+        let coverage = rules
+            .iter()
+            .map(|(components, _)| GlyphId16::new(components[0]))
+            .collect();
+        SubstitutionLookup::Ligature(Lookup::new(
+            LookupFlag::empty(),
+            vec![LigatureSubstFormat1::new(coverage, sets)],
+        ))
+    }
+
+    #[test]
+    fn test_ligatures_with_features_and_text() {
+        // liga: f i -> fi; dlig: f i -> fi again and c t -> ct; calt: a
+        // contextual lookup calling the dlig one
+        let gsub = Gsub::new(
+            ScriptList::new(vec![ScriptRecord::new(
+                Tag::new(b"DFLT"),
+                Script::new(Some(LangSys::new(vec![0, 1, 2])), vec![]),
+            )]),
+            FeatureList::new(vec![
+                FeatureRecord::new(Tag::new(b"calt"), Feature::new(None, vec![2])),
+                FeatureRecord::new(Tag::new(b"dlig"), Feature::new(None, vec![1])),
+                FeatureRecord::new(Tag::new(b"liga"), Feature::new(None, vec![0])),
+            ]),
+            SubstitutionLookupList::new(vec![
+                ligature_lookup(&[(&[1, 2], 5)]),
+                ligature_lookup(&[(&[1, 2], 5), (&[3, 4], 6)]),
+                SubstitutionLookup::Contextual(Lookup::new(
+                    LookupFlag::empty(),
+                    vec![SequenceContext::format_3(
+                        vec![[GlyphId16::new(3)].into_iter().collect()],
+                        vec![SequenceLookupRecord::new(0, 1)],
+                    )
+                    .into()],
+                )),
+            ]),
+        );
+        let mut builder = TestFontBuilder::new();
+        for _ in 0..7 {
+            builder = builder.glyph(Vec::new(), 500);
+        }
+        let font = builder
+            .map('f' as u32, 1)
+            .map('i' as u32, 2)
+            .map('c' as u32, 3)
+            .table(b"GSUB", write_fonts::dump_table(&gsub).unwrap())
+            .build();
+
+        let report = collect_ligatures(&font).unwrap();
+        assert_eq!(report.skipped_contextual_lookups, 1);
+        assert_eq!(
+            report.ligatures,
+            vec![
+                LigatureEntry {
+                    component_glyph_ids: vec![1, 2],
+                    component_names: vec!["gid1".to_string(), "gid2".to_string()],
+                    ligature_glyph_id: 5,
+                    text: Some("fi".to_string()),
+                    feature_tags: vec!["dlig".to_string(), "liga".to_string()],
+                    svg_path: None,
+                },
+                // gid4 has no cmap entry, so no text
+                LigatureEntry {
+                    component_glyph_ids: vec![3, 4],
+                    component_names: vec!["gid3".to_string(), "gid4".to_string()],
+                    ligature_glyph_id: 6,
+                    text: None,
+                    feature_tags: vec!["dlig".to_string()],
+                    svg_path: None,
+                },
+            ]
+        );
+    }
+}
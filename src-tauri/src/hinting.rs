@@ -0,0 +1,1565 @@
+use crate::error::FontError;
+use crate::font_parser::{
+    edit_glyf_record, editable_font_bytes, get_font_bytes, map_glyf_records, save_edited_font,
+    FontCache,
+};
+use crate::metrics::glyph_y_extent;
+use serde::{Deserialize, Serialize};
+use skrifa::raw::tables::gasp::GaspRangeBehavior;
+use skrifa::raw::tables::glyf::{CurvePoint, Glyph};
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::GlyphId;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Display;
+use write_fonts::from_obj::ToOwnedTable;
+use write_fonts::tables::head::{Flags, Head};
+use write_fonts::tables::maxp::Maxp;
+use write_fonts::types::Tag;
+use write_fonts::FontBuilder;
+
+/// TrueType instructions by mnemonic: first opcode and the number of flag
+/// bits folded into it (`MIRP[01101]` is 0xE0 + 0b01101). The push
+/// instructions are handled separately since their operands follow them.
+const OPCODES: &[(&str, u8, u32)] = &[
+    ("SVTCA", 0x00, 1),
+    ("SPVTCA", 0x02, 1),
+    ("SFVTCA", 0x04, 1),
+    ("SPVTL", 0x06, 1),
+    ("SFVTL", 0x08, 1),
+    ("SPVFS", 0x0A, 0),
+    ("SFVFS", 0x0B, 0),
+    ("GPV", 0x0C, 0),
+    ("GFV", 0x0D, 0),
+    ("SFVTPV", 0x0E, 0),
+    ("ISECT", 0x0F, 0),
+    ("SRP0", 0x10, 0),
+    ("SRP1", 0x11, 0),
+    ("SRP2", 0x12, 0),
+    ("SZP0", 0x13, 0),
+    ("SZP1", 0x14, 0),
+    ("SZP2", 0x15, 0),
+    ("SZPS", 0x16, 0),
+    ("SLOOP", 0x17, 0),
+    ("RTG", 0x18, 0),
+    ("RTHG", 0x19, 0),
+    ("SMD", 0x1A, 0),
+    ("ELSE", 0x1B, 0),
+    ("JMPR", 0x1C, 0),
+    ("SCVTCI", 0x1D, 0),
+    ("SSWCI", 0x1E, 0),
+    ("SSW", 0x1F, 0),
+    ("DUP", 0x20, 0),
+    ("POP", 0x21, 0),
+    ("CLEAR", 0x22, 0),
+    ("SWAP", 0x23, 0),
+    ("DEPTH", 0x24, 0),
+    ("CINDEX", 0x25, 0),
+    ("MINDEX", 0x26, 0),
+    ("ALIGNPTS", 0x27, 0),
+    ("UTP", 0x29, 0),
+    ("LOOPCALL", 0x2A, 0),
+    ("CALL", 0x2B, 0),
+    ("FDEF", 0x2C, 0),
+    ("ENDF", 0x2D, 0),
+    ("MDAP", 0x2E, 1),
+    ("IUP", 0x30, 1),
+    ("SHP", 0x32, 1),
+    ("SHC", 0x34, 1),
+    ("SHZ", 0x36, 1),
+    ("SHPIX", 0x38, 0),
+    ("IP", 0x39, 0),
+    ("MSIRP", 0x3A, 1),
+    ("ALIGNRP", 0x3C, 0),
+    ("RTDG", 0x3D, 0),
+    ("MIAP", 0x3E, 1),
+    ("WS", 0x42, 0),
+    ("RS", 0x43, 0),
+    ("WCVTP", 0x44, 0),
+    ("RCVT", 0x45, 0),
+    ("GC", 0x46, 1),
+    ("SCFS", 0x48, 0),
+    ("MD", 0x49, 1),
+    ("MPPEM", 0x4B, 0),
+    ("MPS", 0x4C, 0),
+    ("FLIPON", 0x4D, 0),
+    ("FLIPOFF", 0x4E, 0),
+    ("DEBUG", 0x4F, 0),
+    ("LT", 0x50, 0),
+    ("LTEQ", 0x51, 0),
+    ("GT", 0x52, 0),
+    ("GTEQ", 0x53, 0),
+    ("EQ", 0x54, 0),
+    ("NEQ", 0x55, 0),
+    ("ODD", 0x56, 0),
+    ("EVEN", 0x57, 0),
+    ("IF", 0x58, 0),
+    ("EIF", 0x59, 0),
+    ("AND", 0x5A, 0),
+    ("OR", 0x5B, 0),
+    ("NOT", 0x5C, 0),
+    ("DELTAP1", 0x5D, 0),
+    ("SDB", 0x5E, 0),
+    ("SDS", 0x5F, 0),
+    ("ADD", 0x60, 0),
+    ("SUB", 0x61, 0),
+    ("DIV", 0x62, 0),
+    ("MUL", 0x63, 0),
+    ("ABS", 0x64, 0),
+    ("NEG", 0x65, 0),
+    ("FLOOR", 0x66, 0),
+    ("CEILING", 0x67, 0),
+    ("ROUND", 0x68, 2),
+    ("NROUND", 0x6C, 2),
+    ("WCVTF", 0x70, 0),
+    ("DELTAP2", 0x71, 0),
+    ("DELTAP3", 0x72, 0),
+    ("DELTAC1", 0x73, 0),
+    ("DELTAC2", 0x74, 0),
+    ("DELTAC3", 0x75, 0),
+    ("SROUND", 0x76, 0),
+    ("S45ROUND", 0x77, 0),
+    ("JROT", 0x78, 0),
+    ("JROF", 0x79, 0),
+    ("ROFF", 0x7A, 0),
+    ("RUTG", 0x7C, 0),
+    ("RDTG", 0x7D, 0),
+    ("SANGW", 0x7E, 0),
+    ("AA", 0x7F, 0),
+    ("FLIPPT", 0x80, 0),
+    ("FLIPRGON", 0x81, 0),
+    ("FLIPRGOFF", 0x82, 0),
+    ("SCANCTRL", 0x85, 0),
+    ("SDPVTL", 0x86, 1),
+    ("GETINFO", 0x88, 0),
+    ("IDEF", 0x89, 0),
+    ("ROLL", 0x8A, 0),
+    ("MAX", 0x8B, 0),
+    ("MIN", 0x8C, 0),
+    ("SCANTYPE", 0x8D, 0),
+    ("INSTCTRL", 0x8E, 0),
+    ("GETVARIATION", 0x91, 0),
+    ("MDRP", 0xC0, 5),
+    ("MIRP", 0xE0, 5),
+];
+
+const NPUSHB: u8 = 0x40;
+const NPUSHW: u8 = 0x41;
+const PUSHB: u8 = 0xB0;
+const PUSHW: u8 = 0xB8;
+
+/// Text formats accepted for a glyph program.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InstructionFormat {
+    /// Raw bytecode as hex digits; whitespace is ignored.
+    Hex,
+    /// One mnemonic per instruction, ttx style: flag bits in brackets
+    /// (`MIRP[01101]`, `SVTCA[0]`) and push values after the push
+    /// (`PUSHB[ ] 1 2`, or `PUSH 1 300` to pick the encoding).
+    /// `/* ... */` comments are skipped.
+    Assembly,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct InstructionUpdate {
+    pub glyph_id: u32,
+    pub old_length: usize,
+    pub new_length: usize,
+    pub max_size_of_instructions: u16,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PushKind {
+    /// `PUSH`: bytes when every value fits, words otherwise.
+    Auto,
+    Bytes,
+    Words,
+}
+
+enum Item {
+    Op {
+        opcode: u8,
+        line: usize,
+    },
+    Push {
+        kind: PushKind,
+        values: Vec<i32>,
+        line: usize,
+    },
+}
+
+/// Reject programs whose FDEF/IDEF ... ENDF or IF ... ELSE ... EIF blocks do
+/// not pair up. `ops` yields each opcode with where it came from, for the
+/// error message.
+fn check_nesting<L: Display>(ops: impl IntoIterator<Item = (u8, L)>) -> Result<(), FontError> {
+    // Open blocks: the opening mnemonic, whether an ELSE was seen, and where
+    let mut open: Vec<(&str, bool, L)> = Vec::new();
+    for (opcode, at) in ops {
+        match opcode {
+            0x2C | 0x89 => {
+                let name = if opcode == 0x2C { "FDEF" } else { "IDEF" };
+                if let Some((outer, _, _)) = open.iter().find(|(n, _, _)| *n != "IF") {
+                    return Err(FontError::input(format!(
+                        "{} at {} is nested inside another {}",
+                        name, at, outer
+                    )));
+                }
+                open.push((name, false, at));
+            }
+            0x2D => match open.pop() {
+                Some(("FDEF" | "IDEF", _, _)) => {}
+                Some((name, _, from)) => {
+                    return Err(FontError::input(format!(
+                        "ENDF at {} closes the {} opened at {}",
+                        at, name, from
+                    )))
+                }
+                None => return Err(FontError::input(format!("ENDF at {} without FDEF", at))),
+            },
+            0x58 => open.push(("IF", false, at)),
+            0x1B => match open.last_mut() {
+                Some(("IF", seen_else @ false, _)) => *seen_else = true,
+                Some(("IF", true, _)) => {
+                    return Err(FontError::input(format!("Second ELSE at {} in one IF", at)))
+                }
+                _ => return Err(FontError::input(format!("ELSE at {} outside an IF", at))),
+            },
+            0x59 => match open.pop() {
+                Some(("IF", _, _)) => {}
+                Some((name, _, from)) => {
+                    return Err(FontError::input(format!(
+                        "EIF at {} closes the {} opened at {}",
+                        at, name, from
+                    )))
+                }
+                None => return Err(FontError::input(format!("EIF at {} without IF", at))),
+            },
+            _ => {}
+        }
+    }
+    match open.pop() {
+        Some((name, _, from)) => Err(FontError::input(format!(
+            "{} opened at {} is never closed",
+            name, from
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Walk bytecode into `(opcode, offset)` pairs, skipping push data.
+fn decode(code: &[u8]) -> Result<Vec<(u8, usize)>, FontError> {
+    let mut ops = Vec::new();
+    let mut pos = 0;
+    while pos < code.len() {
+        let opcode = code[pos];
+        let data = match opcode {
+            NPUSHB | NPUSHW => {
+                let count = *code
+                    .get(pos + 1)
+                    .ok_or_else(|| FontError::input(format!("Push at byte {} has no count", pos)))?
+                    as usize;
+                1 + if opcode == NPUSHB { count } else { count * 2 }
+            }
+            0xB0..=0xB7 => (opcode - PUSHB + 1) as usize,
+            0xB8..=0xBF => (opcode - PUSHW + 1) as usize * 2,
+            _ => 0,
+        };
+        if pos + 1 + data > code.len() {
+            return Err(FontError::input(format!(
+                "Push at byte {} runs past the end of the program",
+                pos
+            )));
+        }
+        ops.push((opcode, pos));
+        pos += 1 + data;
+    }
+    Ok(ops)
+}
+
+fn parse_hex(text: &str) -> Result<Vec<u8>, FontError> {
+    let digits: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(FontError::input(
+            "Hex instructions have an odd number of digits",
+        ));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| {
+                    FontError::input(format!(
+                        "'{}' is not a hex byte",
+                        String::from_utf8_lossy(pair)
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Split assembly into `(line, token)`, dropping comments and keeping
+/// bracketed flags (`PUSHB[ ]`) attached to their mnemonic.
+fn tokenize(text: &str) -> Result<Vec<(usize, String)>, FontError> {
+    let mut tokens = Vec::new();
+    let mut in_comment = false;
+    for (index, line) in text.lines().enumerate() {
+        let mut chars = line.chars().peekable();
+        let mut token = String::new();
+        let mut in_brackets = false;
+        while let Some(ch) = chars.next() {
+            if in_comment {
+                if ch == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    in_comment = false;
+                }
+            } else if ch == '/' && chars.peek() == Some(&'*') {
+                chars.next();
+                in_comment = true;
+                if !token.is_empty() {
+                    tokens.push((index + 1, std::mem::take(&mut token)));
+                }
+            } else if ch.is_whitespace() {
+                if !in_brackets && !token.is_empty() {
+                    tokens.push((index + 1, std::mem::take(&mut token)));
+                }
+            } else {
+                match ch {
+                    '[' => in_brackets = true,
+                    ']' => in_brackets = false,
+                    _ => {}
+                }
+                token.push(ch);
+            }
+        }
+        if in_brackets {
+            return Err(FontError::input(format!(
+                "Unclosed '[' on line {}",
+                index + 1
+            )));
+        }
+        if !token.is_empty() {
+            tokens.push((index + 1, token));
+        }
+    }
+    if in_comment {
+        return Err(FontError::input("Unclosed /* comment"));
+    }
+    Ok(tokens)
+}
+
+fn parse_items(text: &str) -> Result<Vec<Item>, FontError> {
+    let mut items: Vec<Item> = Vec::new();
+    for (line, token) in tokenize(text)? {
+        if let Ok(value) = token.parse::<i32>() {
+            match items.last_mut() {
+                Some(Item::Push { values, .. }) => values.push(value),
+                _ => {
+                    return Err(FontError::input(format!(
+                        "Value {} on line {} does not follow a push instruction",
+                        value, line
+                    )))
+                }
+            }
+            continue;
+        }
+        let upper = token.to_ascii_uppercase();
+        let (name, flags) = match upper.split_once('[') {
+            Some((name, rest)) => (name, rest.trim_end_matches(']').trim()),
+            None => (upper.as_str(), ""),
+        };
+        let push = match name {
+            "PUSH" => Some(PushKind::Auto),
+            "PUSHB" | "NPUSHB" => Some(PushKind::Bytes),
+            "PUSHW" | "NPUSHW" => Some(PushKind::Words),
+            _ => None,
+        };
+        if let Some(kind) = push {
+            items.push(Item::Push {
+                kind,
+                values: Vec::new(),
+                line,
+            });
+            continue;
+        }
+        let &(_, base, bits) = OPCODES
+            .iter()
+            .find(|(mnemonic, _, _)| *mnemonic == name)
+            .ok_or_else(|| {
+                FontError::input(format!("Unknown instruction '{}' on line {}", token, line))
+            })?;
+        let flag_value = if bits == 0 && flags.is_empty() {
+            0
+        } else if flags.len() == bits as usize && flags.bytes().all(|b| b == b'0' || b == b'1') {
+            u8::from_str_radix(flags, 2).unwrap_or(0)
+        } else {
+            return Err(FontError::input(format!(
+                "{} on line {} takes {} flag bits, e.g. {}[{}]",
+                name,
+                line,
+                bits,
+                name,
+                "0".repeat(bits as usize)
+            )));
+        };
+        items.push(Item::Op {
+            opcode: base + flag_value,
+            line,
+        });
+    }
+    Ok(items)
+}
+
+fn encode_push(
+    kind: PushKind,
+    values: &[i32],
+    line: usize,
+    code: &mut Vec<u8>,
+) -> Result<(), FontError> {
+    if values.is_empty() {
+        return Err(FontError::input(format!(
+            "Push on line {} has no values",
+            line
+        )));
+    }
+    let fits_byte = values.iter().all(|v| (0..=255).contains(v));
+    let bytes = match kind {
+        PushKind::Auto => fits_byte,
+        PushKind::Bytes if !fits_byte => {
+            return Err(FontError::input(format!(
+                "Byte push on line {} has a value outside 0..=255",
+                line
+            )))
+        }
+        PushKind::Bytes => true,
+        PushKind::Words => false,
+    };
+    if !bytes && values.iter().any(|&v| i16::try_from(v).is_err()) {
+        return Err(FontError::input(format!(
+            "Word push on line {} has a value outside the int16 range",
+            line
+        )));
+    }
+    for chunk in values.chunks(255) {
+        match (bytes, chunk.len()) {
+            (true, n @ 1..=8) => code.push(PUSHB + n as u8 - 1),
+            (false, n @ 1..=8) => code.push(PUSHW + n as u8 - 1),
+            (true, n) => code.extend([NPUSHB, n as u8]),
+            (false, n) => code.extend([NPUSHW, n as u8]),
+        }
+        for &v in chunk {
+            if bytes {
+                code.push(v as u8);
+            } else {
+                code.extend((v as i16).to_be_bytes());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Assemble mnemonic text into TrueType bytecode.
+pub(crate) fn assemble(text: &str) -> Result<Vec<u8>, FontError> {
+    let items = parse_items(text)?;
+    check_nesting(items.iter().filter_map(|item| match item {
+        Item::Op { opcode, line } => Some((*opcode, format!("line {}", line))),
+        Item::Push { .. } => None,
+    }))?;
+    let mut code = Vec::new();
+    for item in &items {
+        match item {
+            Item::Op { opcode, .. } => code.push(*opcode),
+            Item::Push { kind, values, line } => encode_push(*kind, values, *line, &mut code)?,
+        }
+    }
+    Ok(code)
+}
+
+/// Parse and validate a glyph program in either format.
+pub(crate) fn parse_instructions(
+    text: &str,
+    format: InstructionFormat,
+) -> Result<Vec<u8>, FontError> {
+    let code = match format {
+        InstructionFormat::Hex => {
+            let code = parse_hex(text)?;
+            let ops = decode(&code)?;
+            check_nesting(
+                ops.into_iter()
+                    .map(|(op, pos)| (op, format!("byte {}", pos))),
+            )?;
+            code
+        }
+        InstructionFormat::Assembly => assemble(text)?,
+    };
+    if code.len() > u16::MAX as usize {
+        return Err(FontError::input(format!(
+            "Program of {} bytes exceeds the 65535 byte limit",
+            code.len()
+        )));
+    }
+    Ok(code)
+}
+
+/// A glyf record with its instruction block replaced by `code`, and the old
+/// block's length. Composite glyphs carry the block after their last
+/// component, flagged by WE_HAVE_INSTRUCTIONS.
+fn splice_instructions(record: &[u8], code: &[u8]) -> Result<(Vec<u8>, usize), FontError> {
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+    const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+    let malformed = || FontError::TableMalformed {
+        tag: "glyf".to_string(),
+        message: "glyph record is truncated".to_string(),
+    };
+    let read_u16 = |pos: usize| {
+        record
+            .get(pos..pos + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(malformed)
+    };
+    if record.len() < 10 {
+        return Err(FontError::input(
+            "Glyph has no outline to attach instructions to",
+        ));
+    }
+    let mut new_record = Vec::with_capacity(record.len() + code.len());
+    let num_contours = i16::from_be_bytes([record[0], record[1]]);
+    let old_length;
+    let rest;
+    if num_contours >= 0 {
+        let length_pos = 10 + num_contours as usize * 2;
+        old_length = read_u16(length_pos)? as usize;
+        rest = length_pos + 2 + old_length;
+        new_record.extend_from_slice(&record[..length_pos]);
+    } else {
+        let mut pos = 10;
+        let mut flag_positions = Vec::new();
+        let mut has_instructions = false;
+        loop {
+            let flags = read_u16(pos)?;
+            flag_positions.push(pos);
+            has_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+            pos += 4 + if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                4
+            } else {
+                2
+            };
+            pos += if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                8
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                4
+            } else if flags & WE_HAVE_A_SCALE != 0 {
+                2
+            } else {
+                0
+            };
+            if flags & MORE_COMPONENTS == 0 {
+                break;
+            }
+        }
+        old_length = if has_instructions {
+            read_u16(pos)? as usize
+        } else {
+            0
+        };
+        rest = if has_instructions {
+            pos + 2 + old_length
+        } else {
+            pos
+        };
+        new_record.extend_from_slice(record.get(..pos).ok_or_else(malformed)?);
+        let last = *flag_positions.last().unwrap_or(&10);
+        for flag_pos in flag_positions {
+            let mut flags = u16::from_be_bytes([new_record[flag_pos], new_record[flag_pos + 1]]);
+            flags &= !WE_HAVE_INSTRUCTIONS;
+            if flag_pos == last && !code.is_empty() {
+                flags |= WE_HAVE_INSTRUCTIONS;
+            }
+            new_record[flag_pos..flag_pos + 2].copy_from_slice(&flags.to_be_bytes());
+        }
+    }
+    if num_contours >= 0 || !code.is_empty() {
+        new_record.extend((code.len() as u16).to_be_bytes());
+        new_record.extend_from_slice(code);
+    }
+    new_record.extend_from_slice(record.get(rest..).ok_or_else(malformed)?);
+    Ok((new_record, old_length))
+}
+
+/// Replace the glyph's own TrueType program with `instructions` (hex or
+/// assembly, see `InstructionFormat`; empty removes it). maxp's
+/// maxSizeOfInstructions grows to fit the new program.
+pub fn update_glyph_instructions(
+    file_path: &str,
+    glyph_id: u32,
+    instructions: &str,
+    format: InstructionFormat,
+    cache: &FontCache,
+) -> Result<InstructionUpdate, FontError> {
+    let code = parse_instructions(instructions, format)?;
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let mut old_length = 0;
+    let new_bytes = edit_glyf_record(&bytes, glyph_id, |record| {
+        let (new_record, length) = splice_instructions(record, &code)?;
+        old_length = length;
+        Ok(new_record)
+    })?;
+
+    let font = RawFontRef::new(&new_bytes).map_err(FontError::invalid_font)?;
+    let mut maxp: Maxp = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .to_owned_table();
+    // Version 0.5 maxp (CFF fonts) has no instruction limits to maintain
+    let Some(max_size) = maxp.max_size_of_instructions.as_mut() else {
+        return Err(FontError::unsupported(
+            "glyph instructions in a font with a version 0.5 maxp",
+        ));
+    };
+    *max_size = (*max_size).max(code.len() as u16);
+    let max_size_of_instructions = *max_size;
+    let new_bytes = FontBuilder::new()
+        .add_table(&maxp)
+        .map_err(|e| FontError::build("maxp", e))?
+        .copy_missing_tables(font)
+        .build();
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(InstructionUpdate {
+        glyph_id,
+        old_length,
+        new_length: code.len(),
+        max_size_of_instructions,
+    })
+}
+
+// Opcodes the autohinter emits
+const SVTCA_Y: u8 = 0x00;
+const SRP0: u8 = 0x10;
+const SWAP: u8 = 0x23;
+const MDAP_ROUND: u8 = 0x2F;
+const IUP_Y: u8 = 0x30;
+const MIAP_ROUND: u8 = 0x3F;
+const WCVTP: u8 = 0x44;
+const RCVT: u8 = 0x45;
+const ADD: u8 = 0x60;
+const SUB: u8 = 0x61;
+const ROUND_GRAY: u8 = 0x68;
+const SCANCTRL: u8 = 0x85;
+const MAX: u8 = 0x8B;
+const SCANTYPE: u8 = 0x8D;
+/// MIRP[01100]: keep rp0, minimum distance, round with cut-in, gray.
+const MIRP_STEM: u8 = 0xEC;
+
+/// Distinct horizontal stem widths given their own cvt entry.
+const MAX_STEM_WIDTHS: usize = 3;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct AutohintOptions {
+    /// Only write the cvt, prep and gasp tables; glyph programs are left
+    /// alone. Meant for fonts without instructions of their own.
+    pub skeleton_only: bool,
+}
+
+/// A blue zone: the flat height that straight glyphs reach and the overshoot
+/// of round ones past it, with their cvt entries.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlueZone {
+    pub name: String,
+    pub flat: i16,
+    pub flat_cvt: u16,
+    pub overshoot: Option<i16>,
+    pub overshoot_cvt: Option<u16>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AutohintReport {
+    pub zones: Vec<BlueZone>,
+    /// Most common horizontal stem thicknesses, in cvt order after the zones.
+    pub stem_widths: Vec<i16>,
+    pub glyphs_hinted: usize,
+    pub prep_length: usize,
+    pub max_size_of_instructions: u16,
+}
+
+/// Bytecode under construction, tracking the stack depth for maxp.
+#[derive(Default)]
+struct Program {
+    code: Vec<u8>,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Program {
+    fn push(&mut self, values: &[i32]) -> Result<(), FontError> {
+        encode_push(PushKind::Auto, values, 0, &mut self.code)?;
+        self.depth += values.len();
+        self.max_depth = self.max_depth.max(self.depth);
+        Ok(())
+    }
+
+    fn op(&mut self, opcode: u8, pops: usize, pushes: usize) {
+        self.code.push(opcode);
+        self.depth = self.depth - pops + pushes;
+        self.max_depth = self.max_depth.max(self.depth);
+    }
+}
+
+/// Baseline, x-height and cap-height zones measured on 'H', 'x', 'o' and
+/// 'O', falling back to the OS/2 heights. Overshoots further than 1/20 em
+/// from the flat height are not taken as overshoots.
+fn detect_zones(font: &RawFontRef<'_>, upem: u16) -> Vec<(&'static str, i16, Option<i16>)> {
+    let extent = |ch| glyph_y_extent(font, ch);
+    let os2 = font.os2().ok();
+    let positive = |v: Option<i16>| v.filter(|&v| v > 0);
+    let near = |flat: i16, over: Option<i16>| {
+        over.filter(|&o| o != flat && (o as i32 - flat as i32).abs() * 20 <= upem as i32)
+    };
+
+    let mut zones = Vec::new();
+    let base = extent('H')
+        .or_else(|| extent('x'))
+        .map_or(0, |(bottom, _)| bottom);
+    let round_bottom = extent('o')
+        .or_else(|| extent('O'))
+        .map(|(bottom, _)| bottom);
+    zones.push((
+        "baseline",
+        base,
+        near(base, round_bottom.filter(|&b| b < base)),
+    ));
+    let x_flat = extent('x')
+        .map(|(_, top)| top)
+        .or_else(|| positive(os2.as_ref().and_then(|t| t.sx_height())));
+    if let Some(flat) = x_flat {
+        let over = extent('o').map(|(_, top)| top).filter(|&top| top > flat);
+        zones.push(("x_height", flat, near(flat, over)));
+    }
+    let cap_flat = extent('H')
+        .map(|(_, top)| top)
+        .or_else(|| positive(os2.as_ref().and_then(|t| t.s_cap_height())));
+    if let Some(flat) = cap_flat {
+        let over = extent('O').map(|(_, top)| top).filter(|&top| top > flat);
+        zones.push(("cap_height", flat, near(flat, over)));
+    }
+    zones
+}
+
+/// A horizontal run of on-curve points. With TrueType's clockwise outer
+/// contours, runs going right to left are the bottom edges of ink.
+struct Segment {
+    y: i32,
+    x_min: i32,
+    x_max: i32,
+    bottom: bool,
+    points: Vec<u16>,
+}
+
+/// The points of a simple glyph and the last point index of each contour.
+struct GlyphPoints {
+    points: Vec<CurvePoint>,
+    ends: Vec<usize>,
+}
+
+impl GlyphPoints {
+    fn contours(&self) -> impl Iterator<Item = std::ops::Range<usize>> + '_ {
+        let mut start = 0;
+        self.ends.iter().map(move |&end| {
+            let range = start..end + 1;
+            start = end + 1;
+            range
+        })
+    }
+
+    fn segments(&self, min_length: i32) -> Vec<Segment> {
+        let mut segments: Vec<Segment> = Vec::new();
+        for contour in self.contours() {
+            let n = contour.len();
+            let first_of_contour = segments.len();
+            for i in 0..n {
+                let (a, b) = (contour.start + i, contour.start + (i + 1) % n);
+                let (p, q) = (self.points[a], self.points[b]);
+                if !p.on_curve || !q.on_curve || p.y != q.y || p.x == q.x {
+                    continue;
+                }
+                let bottom = q.x < p.x;
+                // Collinear runs continue the previous segment
+                if let Some(last) = segments[first_of_contour..].last_mut() {
+                    if last.points.last() == Some(&(a as u16))
+                        && last.y == p.y as i32
+                        && last.bottom == bottom
+                    {
+                        last.x_min = last.x_min.min(q.x as i32);
+                        last.x_max = last.x_max.max(q.x as i32);
+                        last.points.push(b as u16);
+                        continue;
+                    }
+                }
+                segments.push(Segment {
+                    y: p.y as i32,
+                    x_min: p.x.min(q.x) as i32,
+                    x_max: p.x.max(q.x) as i32,
+                    bottom,
+                    points: vec![a as u16, b as u16],
+                });
+            }
+        }
+        segments.retain(|s| s.x_max - s.x_min >= min_length);
+        segments
+    }
+
+    /// On-curve points at a local vertical extreme of their contour.
+    fn extremes(&self) -> Vec<usize> {
+        let mut extremes = Vec::new();
+        for contour in self.contours() {
+            let n = contour.len();
+            for i in 0..n {
+                let p = self.points[contour.start + i];
+                let prev = self.points[contour.start + (i + n - 1) % n];
+                let next = self.points[contour.start + (i + 1) % n];
+                let top = p.y >= prev.y && p.y >= next.y;
+                let bottom = p.y <= prev.y && p.y <= next.y;
+                if p.on_curve && (top || bottom) {
+                    extremes.push(contour.start + i);
+                }
+            }
+        }
+        extremes
+    }
+}
+
+/// Pair each bottom edge with the nearest top edge above it that overlaps
+/// it horizontally, up to `max_width` away: `(bottom, top, width)`.
+fn pair_stems(segments: &[Segment], max_width: i32) -> Vec<(usize, usize, i32)> {
+    let mut stems = Vec::new();
+    for (b, bottom) in segments.iter().enumerate().filter(|(_, s)| s.bottom) {
+        let top = segments
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| !t.bottom && t.y > bottom.y && t.y - bottom.y <= max_width)
+            .filter(|(_, t)| t.x_min.max(bottom.x_min) < t.x_max.min(bottom.x_max))
+            .min_by_key(|(_, t)| t.y - bottom.y);
+        if let Some((t, top)) = top {
+            stems.push((b, t, top.y - bottom.y));
+        }
+    }
+    stems
+}
+
+/// Zone cvt entries: flat height and cvt, and the optional overshoot.
+type ZoneCvt = ((i16, u16), Option<(i16, u16)>);
+
+/// A y-only glyph program: points on blue zones snap to the rounded zone
+/// heights, stems keep their cvt width (at least a pixel) from whichever
+/// edge is already placed, and IUP smooths everything else. Empty when the
+/// glyph has nothing to hint.
+fn glyph_program(
+    glyph: &GlyphPoints,
+    zones: &[ZoneCvt],
+    stem_cvts: &[(i32, u16)],
+    upem: u16,
+) -> Result<Program, FontError> {
+    let tolerance = (upem / 100).max(1) as i32;
+    let mut program = Program::default();
+    let mut touched = HashSet::new();
+    program.op(SVTCA_Y, 0, 0);
+
+    for point in glyph.extremes() {
+        let y = glyph.points[point].y as i32;
+        let zone_cvt = zones.iter().find_map(|&((flat, flat_cvt), over)| {
+            let (lo, hi) = match over {
+                Some((o, _)) => (flat.min(o) as i32, flat.max(o) as i32),
+                None => (flat as i32, flat as i32),
+            };
+            if y < lo - tolerance || y > hi + tolerance {
+                return None;
+            }
+            Some(match over {
+                Some((o, cvt)) if (y - o as i32).abs() < (y - flat as i32).abs() => cvt,
+                _ => flat_cvt,
+            })
+        });
+        if let Some(cvt) = zone_cvt {
+            program.push(&[point as i32, cvt as i32])?;
+            program.op(MIAP_ROUND, 2, 0);
+            touched.insert(point as u16);
+        }
+    }
+
+    let segments = glyph.segments((upem / 50).max(1) as i32);
+    let mut stems = pair_stems(&segments, upem as i32 / 4);
+    stems.sort_by_key(|&(bottom, _, _)| segments[bottom].y);
+    for (bottom, top, width) in stems {
+        let Some(&(_, cvt)) = stem_cvts
+            .iter()
+            .min_by_key(|(stem_width, _)| (stem_width - width).abs())
+        else {
+            break;
+        };
+        let (bottom, top) = (&segments[bottom].points, &segments[top].points);
+        let is_touched = |points: &[u16]| points.iter().any(|p| touched.contains(p));
+        let (anchor, moved) = match (is_touched(bottom), is_touched(top)) {
+            (true, true) => continue,
+            (true, false) => (bottom[0], top),
+            (false, true) => (top[0], bottom),
+            (false, false) => {
+                for &point in bottom {
+                    program.push(&[point as i32])?;
+                    program.op(MDAP_ROUND, 1, 0);
+                    touched.insert(point);
+                }
+                (bottom[0], top)
+            }
+        };
+        program.push(&[anchor as i32])?;
+        program.op(SRP0, 1, 0);
+        for &point in moved {
+            program.push(&[point as i32, cvt as i32])?;
+            program.op(MIRP_STEM, 2, 0);
+            touched.insert(point);
+        }
+    }
+
+    if touched.is_empty() {
+        return Ok(Program::default());
+    }
+    program.op(IUP_Y, 0, 0);
+    Ok(program)
+}
+
+/// The prep program: dropout control on, overshoots kept only once they
+/// round to a pixel, zone heights rounded, and stem widths rounded to at
+/// least one pixel so thin bars do not vanish.
+fn prep_program(zones: &[ZoneCvt], stem_cvts: &[(i32, u16)]) -> Result<Program, FontError> {
+    let mut prep = Program::default();
+    prep.push(&[511])?;
+    prep.op(SCANCTRL, 1, 0);
+    prep.push(&[4])?;
+    prep.op(SCANTYPE, 1, 0);
+    // overshoot = round(flat) + round(overshoot - flat), from unrounded values
+    for &((_, flat), over) in zones {
+        let Some((_, over)) = over else { continue };
+        let (flat, over) = (flat as i32, over as i32);
+        prep.push(&[over, flat, over])?;
+        prep.op(RCVT, 1, 1);
+        prep.push(&[flat])?;
+        prep.op(RCVT, 1, 1);
+        prep.op(SUB, 2, 1);
+        prep.op(ROUND_GRAY, 1, 1);
+        prep.op(SWAP, 2, 2);
+        prep.op(RCVT, 1, 1);
+        prep.op(ROUND_GRAY, 1, 1);
+        prep.op(ADD, 2, 1);
+        prep.op(WCVTP, 2, 0);
+    }
+    for &((_, flat), _) in zones {
+        prep.push(&[flat as i32, flat as i32])?;
+        prep.op(RCVT, 1, 1);
+        prep.op(ROUND_GRAY, 1, 1);
+        prep.op(WCVTP, 2, 0);
+    }
+    for &(_, cvt) in stem_cvts {
+        prep.push(&[cvt as i32, cvt as i32])?;
+        prep.op(RCVT, 1, 1);
+        prep.op(ROUND_GRAY, 1, 1);
+        prep.push(&[64])?;
+        prep.op(MAX, 2, 1);
+        prep.op(WCVTP, 2, 0);
+    }
+    Ok(prep)
+}
+
+/// Hint a TrueType font in y only: detect blue zones and horizontal stems,
+/// write them to cvt with a prep that rounds them, and give every simple
+/// glyph a program anchoring its zone points and stems (composites lose
+/// their own programs and follow their components). Replaces cvt, prep and
+/// gasp; fpgm is left as is.
+pub fn autohint_font(
+    file_path: &str,
+    options: &AutohintOptions,
+    cache: &FontCache,
+) -> Result<AutohintReport, FontError> {
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let glyf = font
+        .glyf()
+        .map_err(|_| FontError::unsupported("autohinting a font without TrueType outlines"))?;
+    let loca = font.loca(None).map_err(|e| FontError::table("loca", e))?;
+    let upem = font
+        .head()
+        .map_err(|e| FontError::table("head", e))?
+        .units_per_em();
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as u32;
+
+    let mut glyphs = Vec::with_capacity(num_glyphs as usize);
+    let mut has_instructions = false;
+    for gid in 0..num_glyphs {
+        let glyph = loca
+            .get_glyf(GlyphId::new(gid), &glyf)
+            .map_err(|e| FontError::table("glyf", e))?;
+        glyphs.push(match glyph {
+            Some(Glyph::Simple(simple)) => {
+                has_instructions |= !simple.instructions().is_empty();
+                Some(GlyphPoints {
+                    points: simple.points().collect(),
+                    ends: simple
+                        .end_pts_of_contours()
+                        .iter()
+                        .map(|e| e.get() as usize)
+                        .collect(),
+                })
+            }
+            Some(Glyph::Composite(composite)) => {
+                has_instructions |= composite.instructions().is_some_and(|i| !i.is_empty());
+                None
+            }
+            None => None,
+        });
+    }
+    if options.skeleton_only && has_instructions {
+        return Err(FontError::input(
+            "Font already has glyph instructions tied to its own cvt; run the full pass to replace them",
+        ));
+    }
+
+    // cvt: each zone's flat height and overshoot, then the stem widths
+    let mut cvt: Vec<i16> = Vec::new();
+    let mut zones = Vec::new();
+    let mut zone_cvts: Vec<ZoneCvt> = Vec::new();
+    for (name, flat, overshoot) in detect_zones(&font, upem) {
+        let flat_cvt = cvt.len() as u16;
+        cvt.push(flat);
+        let overshoot_cvt = overshoot.map(|o| {
+            cvt.push(o);
+            cvt.len() as u16 - 1
+        });
+        zone_cvts.push(((flat, flat_cvt), overshoot.zip(overshoot_cvt)));
+        zones.push(BlueZone {
+            name: name.to_string(),
+            flat,
+            flat_cvt,
+            overshoot,
+            overshoot_cvt,
+        });
+    }
+    let mut widths = BTreeMap::<i32, usize>::new();
+    for glyph in glyphs.iter().flatten() {
+        let segments = glyph.segments((upem / 50).max(1) as i32);
+        for (_, _, width) in pair_stems(&segments, upem as i32 / 4) {
+            *widths.entry(width).or_default() += 1;
+        }
+    }
+    let mut by_count: Vec<(i32, usize)> = widths.into_iter().collect();
+    by_count.sort_by_key(|&(width, count)| (std::cmp::Reverse(count), width));
+    let stem_cvts: Vec<(i32, u16)> = by_count
+        .iter()
+        .take(MAX_STEM_WIDTHS)
+        .map(|&(width, _)| {
+            cvt.push(width as i16);
+            (width, cvt.len() as u16 - 1)
+        })
+        .collect();
+
+    let prep = prep_program(&zone_cvts, &stem_cvts)?;
+    let mut max_depth = prep.max_depth;
+    let mut max_size = 0;
+    let mut glyphs_hinted = 0;
+    let hinted_bytes = if options.skeleton_only {
+        bytes.to_vec()
+    } else {
+        let programs = glyphs
+            .iter()
+            .map(|glyph| match glyph {
+                Some(glyph) => glyph_program(glyph, &zone_cvts, &stem_cvts, upem),
+                None => Ok(Program::default()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for program in programs.iter().filter(|p| !p.code.is_empty()) {
+            glyphs_hinted += 1;
+            max_depth = max_depth.max(program.max_depth);
+            max_size = max_size.max(program.code.len());
+        }
+        map_glyf_records(&bytes, |gid, record| {
+            if record.len() < 10 {
+                return Ok(record.to_vec());
+            }
+            let code = &programs[gid as usize].code;
+            splice_instructions(record, code).map(|(record, _)| record)
+        })?
+    };
+
+    let font = RawFontRef::new(&hinted_bytes).map_err(FontError::invalid_font)?;
+    let mut maxp: Maxp = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .to_owned_table();
+    let (Some(max_stack), Some(max_instructions)) = (
+        maxp.max_stack_elements.as_mut(),
+        maxp.max_size_of_instructions.as_mut(),
+    ) else {
+        return Err(FontError::unsupported(
+            "hinting a font with a version 0.5 maxp",
+        ));
+    };
+    *max_stack = (*max_stack).max(max_depth as u16);
+    if !options.skeleton_only {
+        *max_instructions = max_size as u16;
+    }
+    let max_size_of_instructions = *max_instructions;
+    let mut head: Head = font
+        .head()
+        .map_err(|e| FontError::table("head", e))?
+        .to_owned_table();
+    head.flags |= Flags::FORCE_INTEGER_PPEM;
+
+    let cvt_bytes: Vec<u8> = cvt.iter().flat_map(|v| v.to_be_bytes()).collect();
+    // gasp: grid-fit and smooth at every size, symmetric in ClearType
+    let gasp = vec![0, 1, 0, 1, 0xFF, 0xFF, 0, 0x0F];
+    let prep_length = prep.code.len();
+    let new_bytes = FontBuilder::new()
+        .add_raw(Tag::new(b"cvt "), cvt_bytes)
+        .add_raw(Tag::new(b"prep"), prep.code)
+        .add_raw(Tag::new(b"gasp"), gasp)
+        .add_table(&maxp)
+        .map_err(|e| FontError::build("maxp", e))?
+        .add_table(&head)
+        .map_err(|e| FontError::build("head", e))?
+        .copy_missing_tables(font)
+        .build();
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(AutohintReport {
+        zones,
+        stem_widths: stem_cvts.iter().map(|&(width, _)| width as i16).collect(),
+        glyphs_hinted,
+        prep_length,
+        max_size_of_instructions,
+    })
+}
+
+/// What the gasp table asks a rasterizer to do at one size.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GaspBehavior {
+    pub ppem: u16,
+    /// gasp version; None when the font has no (readable) gasp table.
+    pub version: Option<u16>,
+    /// Index of the range covering `ppem` and its inclusive bounds; None
+    /// without a gasp table or past the last range.
+    pub range_index: Option<usize>,
+    pub range_min_ppem: Option<u16>,
+    pub range_max_ppem: Option<u16>,
+    pub gridfit: bool,
+    pub grayscale: bool,
+    /// ClearType flags, only defined from version 1.
+    pub symmetric_gridfit: bool,
+    pub symmetric_smoothing: bool,
+}
+
+impl GaspBehavior {
+    /// Whether a rasterizer grid-fits at this size: ClearType-style smooth
+    /// rendering reads the symmetric gridfit flag of version 1 tables,
+    /// everything else the plain gridfit flag.
+    pub(crate) fn gridfits(&self, smooth: bool) -> bool {
+        if smooth && self.version.is_some_and(|v| v >= 1) {
+            self.symmetric_gridfit
+        } else {
+            self.gridfit
+        }
+    }
+}
+
+pub(crate) fn gasp_behavior(font: &RawFontRef, ppem: u16) -> GaspBehavior {
+    // Without guidance rasterizers grid-fit and smooth at every size
+    let mut behavior = GaspBehavior {
+        ppem,
+        version: None,
+        range_index: None,
+        range_min_ppem: None,
+        range_max_ppem: None,
+        gridfit: true,
+        grayscale: true,
+        symmetric_gridfit: false,
+        symmetric_smoothing: false,
+    };
+    let Ok(gasp) = font.gasp() else {
+        return behavior;
+    };
+    behavior.version = Some(gasp.version());
+    let mut min_ppem = 0u16;
+    for (index, range) in gasp.gasp_ranges().iter().enumerate() {
+        let max_ppem = range.range_max_ppem();
+        if ppem <= max_ppem {
+            let flags = range.range_gasp_behavior();
+            let cleartype = gasp.version() >= 1;
+            behavior.range_index = Some(index);
+            behavior.range_min_ppem = Some(min_ppem);
+            behavior.range_max_ppem = Some(max_ppem);
+            behavior.gridfit = flags.contains(GaspRangeBehavior::GASP_GRIDFIT);
+            behavior.grayscale = flags.contains(GaspRangeBehavior::GASP_DOGRAY);
+            behavior.symmetric_gridfit =
+                cleartype && flags.contains(GaspRangeBehavior::GASP_SYMMETRIC_GRIDFIT);
+            behavior.symmetric_smoothing =
+                cleartype && flags.contains(GaspRangeBehavior::GASP_SYMMETRIC_SMOOTHING);
+            break;
+        }
+        min_ppem = max_ppem.saturating_add(1);
+    }
+    behavior
+}
+
+/// Resolve the gasp range and flags that apply at `ppem`.
+pub fn get_gasp_behavior(
+    file_path: &str,
+    ppem: u16,
+    cache: &FontCache,
+) -> Result<GaspBehavior, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    Ok(gasp_behavior(&font, ppem))
+}
+
+/// [`get_gasp_behavior`] for every size in `min_ppem..=max_ppem`.
+pub fn get_gasp_behaviors(
+    file_path: &str,
+    min_ppem: u16,
+    max_ppem: u16,
+    cache: &FontCache,
+) -> Result<Vec<GaspBehavior>, FontError> {
+    if min_ppem > max_ppem {
+        return Err(FontError::input(format!(
+            "empty ppem range {}..={}",
+            min_ppem, max_ppem
+        )));
+    }
+    let bytes = get_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    Ok((min_ppem..=max_ppem)
+        .map(|ppem| gasp_behavior(&font, ppem))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raster::{Bitmap, EdgePen};
+    use crate::test_fonts::{composite_glyph, simple_glyph, TestComponent, TestFontBuilder};
+    use skrifa::instance::{LocationRef, Size};
+    use skrifa::outline::{DrawSettings, Engine, HintingInstance, HintingOptions, Target};
+    use skrifa::{FontRef, MetadataProvider};
+
+    #[test]
+    fn test_assemble() {
+        let text = "SVTCA[0]\n\
+                    PUSHB[ ]\t/* 2 values pushed */\n\
+                    3 0\n\
+                    MIRP[01101] MDAP[1]\n\
+                    PUSH 1 -2\n\
+                    PUSH 0 1 2 3 4 5 6 7 8\n\
+                    IF[ ] ELSE EIF IUP[0]";
+        let mut expected = vec![0x00, 0xB1, 3, 0, 0xED, 0x2F, 0xB9, 0, 1, 0xFF, 0xFE];
+        expected.extend([0x40, 9, 0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        expected.extend([0x58, 0x1B, 0x59, 0x30]);
+        assert_eq!(assemble(text).unwrap(), expected);
+        assert_eq!(
+            parse_instructions("00 b1 03 00\ned2f", InstructionFormat::Hex).unwrap(),
+            vec![0x00, 0xB1, 3, 0, 0xED, 0x2F]
+        );
+
+        let message = |text: &str, format| match parse_instructions(text, format) {
+            Err(FontError::InvalidInput { message }) => message,
+            other => panic!("expected an input error, got {:?}", other),
+        };
+        assert_eq!(
+            message("FDEF\nIF\nENDF", InstructionFormat::Assembly),
+            "ENDF at line 3 closes the IF opened at line 2"
+        );
+        assert_eq!(
+            message("PUSHB[ ] 1\nFDEF", InstructionFormat::Assembly),
+            "FDEF opened at line 2 is never closed"
+        );
+        assert_eq!(
+            message("IF ELSE ELSE EIF", InstructionFormat::Assembly),
+            "Second ELSE at line 1 in one IF"
+        );
+        assert_eq!(
+            message("MIRP[011]", InstructionFormat::Assembly),
+            "MIRP on line 1 takes 5 flag bits, e.g. MIRP[00000]"
+        );
+        assert_eq!(
+            message("PUSHB[ ] 256", InstructionFormat::Assembly),
+            "Byte push on line 1 has a value outside 0..=255"
+        );
+        assert_eq!(
+            message("59", InstructionFormat::Hex),
+            "EIF at byte 0 without IF"
+        );
+        assert_eq!(
+            message("b2 01 02", InstructionFormat::Hex),
+            "Push at byte 0 runs past the end of the program"
+        );
+    }
+
+    #[test]
+    fn test_update_glyph_instructions() {
+        let dir =
+            std::env::temp_dir().join(format!("typebrew-instructions-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        let square = simple_glyph(&[&[(0, 0, true), (0, 500, true), (500, 500, true)]]);
+        let composite = composite_glyph(&[
+            TestComponent {
+                glyph_id: 1,
+                dx: 0,
+                dy: 0,
+                transform: None,
+            },
+            TestComponent {
+                glyph_id: 1,
+                dx: 600,
+                dy: 0,
+                transform: None,
+            },
+        ]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(square, 600)
+            .glyph(composite, 1200)
+            .build();
+        std::fs::write(path, font).unwrap();
+        let cache = FontCache::new();
+
+        let update = update_glyph_instructions(
+            path,
+            1,
+            "SVTCA[0] PUSHB[ ] 0 MDAP[1] IUP[0]",
+            InstructionFormat::Assembly,
+            &cache,
+        )
+        .unwrap();
+        assert_eq!(
+            update,
+            InstructionUpdate {
+                glyph_id: 1,
+                old_length: 0,
+                new_length: 5,
+                max_size_of_instructions: 5,
+            }
+        );
+        update_glyph_instructions(path, 2, "00 30", InstructionFormat::Hex, &cache).unwrap();
+
+        let glyph = |bytes: &[u8], gid: u32| {
+            let font = RawFontRef::new(bytes).unwrap();
+            let glyf = font.glyf().unwrap();
+            let record = font.loca(None).unwrap().get_glyf(GlyphId::new(gid), &glyf);
+            match record.unwrap().unwrap() {
+                Glyph::Simple(g) => (g.instructions().to_vec(), g.num_points()),
+                Glyph::Composite(g) => (
+                    g.instructions().map(<[u8]>::to_vec).unwrap_or_default(),
+                    g.components().count(),
+                ),
+            }
+        };
+        let bytes = get_font_bytes(path, &cache).unwrap();
+        assert_eq!(glyph(&bytes, 1), (vec![0x00, 0xB0, 0, 0x2F, 0x30], 3));
+        assert_eq!(glyph(&bytes, 2), (vec![0x00, 0x30], 2));
+
+        // Shrinking programs leave the maxp limit alone
+        let update =
+            update_glyph_instructions(path, 2, "", InstructionFormat::Hex, &cache).unwrap();
+        assert_eq!((update.old_length, update.max_size_of_instructions), (2, 5));
+        let bytes = get_font_bytes(path, &cache).unwrap();
+        assert_eq!(glyph(&bytes, 2), (Vec::new(), 2));
+        assert!(matches!(
+            update_glyph_instructions(path, 0, "00", InstructionFormat::Hex, &cache),
+            Err(FontError::InvalidInput { .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Horizontal bars crossed by a vertical line through x = 250 units, and
+    /// how many of them reach full ink in at least one pixel row.
+    fn solid_bars(bytes: &[u8], gid: u32, ppem: f32, hinted: bool) -> (usize, usize) {
+        let font = FontRef::new(bytes).unwrap();
+        let outlines = font.outline_glyphs();
+        let glyph = outlines.get(GlyphId::new(gid)).unwrap();
+        let options = HintingOptions {
+            engine: Engine::Interpreter,
+            target: Target::Mono,
+        };
+        let instance =
+            HintingInstance::new(&outlines, Size::new(ppem), LocationRef::default(), options)
+                .unwrap();
+        let settings = if hinted {
+            DrawSettings::hinted(&instance, false)
+        } else {
+            DrawSettings::unhinted(Size::new(ppem), LocationRef::default())
+        };
+        let mut pen = EdgePen::new();
+        pen.set_origin(2.0, ppem + 2.0);
+        glyph.draw(settings, &mut pen).unwrap();
+        let mut bitmap = Bitmap::new(ppem as u32 + 4, ppem as u32 + 4);
+        bitmap.fill(&pen, 4);
+
+        let column = (2.0 + ppem * 0.25) as u32;
+        let (mut bars, mut solid, mut run_max) = (0, 0, 0);
+        for row in 0..=bitmap.height {
+            let coverage = if row < bitmap.height {
+                bitmap.coverage[(row * bitmap.width + column) as usize]
+            } else {
+                0
+            };
+            if coverage > 0 {
+                run_max = run_max.max(coverage);
+            } else if run_max > 0 {
+                bars += 1;
+                solid += (run_max >= 192) as usize;
+                run_max = 0;
+            }
+        }
+        (bars, solid)
+    }
+
+    #[test]
+    fn test_autohint_keeps_thin_bars() {
+        let dir = std::env::temp_dir().join(format!("typebrew-autohint-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        let rect = |x0: i16, y0: i16, x1: i16, y1: i16| -> [(i16, i16, bool); 4] {
+            [
+                (x0, y0, true),
+                (x0, y1, true),
+                (x1, y1, true),
+                (x1, y0, true),
+            ]
+        };
+        let h = simple_glyph(&[&rect(50, 0, 550, 700)]);
+        let x = simple_glyph(&[&rect(50, 0, 450, 500)]);
+        // Three 45 unit bars: on the baseline, floating, and at cap height
+        let bars = simple_glyph(&[
+            &rect(50, 0, 450, 45),
+            &rect(50, 330, 450, 375),
+            &rect(50, 655, 450, 700),
+        ]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(h, 600)
+            .glyph(x, 500)
+            .glyph(bars, 500)
+            .map('H' as u32, 1)
+            .map('x' as u32, 2)
+            .build();
+        std::fs::write(path, font).unwrap();
+        let cache = FontCache::new();
+        let unhinted = get_font_bytes(path, &cache).unwrap();
+
+        let report = autohint_font(path, &AutohintOptions::default(), &cache).unwrap();
+        let zones: Vec<(&str, i16, Option<i16>)> = report
+            .zones
+            .iter()
+            .map(|z| (z.name.as_str(), z.flat, z.overshoot))
+            .collect();
+        assert_eq!(
+            zones,
+            vec![
+                ("baseline", 0, None),
+                ("x_height", 500, None),
+                ("cap_height", 700, None),
+            ]
+        );
+        assert_eq!(report.stem_widths, vec![45]);
+        assert_eq!(report.glyphs_hinted, 3);
+
+        // Below a pixel thick, unhinted bars smear into gray rows; hinted
+        // ones keep a solid row at every size from 10 to 16 ppem
+        let hinted = get_font_bytes(path, &cache).unwrap();
+        let mut before = 0;
+        let mut after = 0;
+        for ppem in 10..=16 {
+            let (bars, solid) = solid_bars(&unhinted, 3, ppem as f32, false);
+            assert_eq!(bars, 3);
+            before += solid;
+            let (bars, solid) = solid_bars(&hinted, 3, ppem as f32, true);
+            assert_eq!(bars, 3);
+            after += solid;
+        }
+        assert_eq!((before, after), (0, 21));
+
+        let font = RawFontRef::new(&hinted).unwrap();
+        assert_eq!(
+            font.maxp().unwrap().max_size_of_instructions(),
+            Some(report.max_size_of_instructions)
+        );
+        assert!(font
+            .head()
+            .unwrap()
+            .flags()
+            .contains(skrifa::raw::tables::head::Flags::FORCE_INTEGER_PPEM));
+        let skeleton = AutohintOptions {
+            skeleton_only: true,
+        };
+        assert!(matches!(
+            autohint_font(path, &skeleton, &cache),
+            Err(FontError::InvalidInput { .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_gasp_behavior() {
+        let gasp = |version: u16, ranges: &[(u16, u16)]| {
+            let mut table = [version, ranges.len() as u16].to_vec();
+            table.extend(ranges.iter().flat_map(|&(max, flags)| [max, flags]));
+            let bytes: Vec<u8> = table.iter().flat_map(|v| v.to_be_bytes()).collect();
+            TestFontBuilder::new()
+                .glyph(Vec::new(), 500)
+                .table(b"gasp", bytes)
+                .build()
+        };
+
+        let font = gasp(1, &[(8, 0x0002), (16, 0x000B), (0xFFFF, 0x000F)]);
+        let font = RawFontRef::new(&font).unwrap();
+        let small = gasp_behavior(&font, 8);
+        assert_eq!(small.range_index, Some(0));
+        assert_eq!(
+            (small.range_min_ppem, small.range_max_ppem),
+            (Some(0), Some(8))
+        );
+        assert!(!small.gridfit && small.grayscale);
+        assert!(!small.gridfits(false) && !small.gridfits(true));
+        let mid = gasp_behavior(&font, 9);
+        assert_eq!(
+            (mid.range_min_ppem, mid.range_max_ppem),
+            (Some(9), Some(16))
+        );
+        assert!(mid.gridfits(false) && !mid.gridfits(true));
+        assert!(mid.symmetric_smoothing);
+        assert_eq!(gasp_behavior(&font, 300).range_index, Some(2));
+
+        // Version 0 has no ClearType flags, and sizes past the last range
+        // fall back to the defaults
+        let font = gasp(0, &[(20, 0x000F)]);
+        let font = RawFontRef::new(&font).unwrap();
+        let v0 = gasp_behavior(&font, 12);
+        assert!(v0.gridfit && !v0.symmetric_gridfit && !v0.symmetric_smoothing);
+        assert!(v0.gridfits(true));
+        let past = gasp_behavior(&font, 21);
+        assert_eq!(past.version, Some(0));
+        assert_eq!(past.range_index, None);
+        assert!(past.gridfit && past.grayscale);
+
+        let none = TestFontBuilder::new().glyph(Vec::new(), 500).build();
+        let none = gasp_behavior(&RawFontRef::new(&none).unwrap(), 12);
+        assert_eq!(none.version, None);
+        assert!(none.gridfits(false) && none.gridfits(true));
+    }
+}
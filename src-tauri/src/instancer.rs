@@ -0,0 +1,630 @@
+use crate::error::FontError;
+use crate::font_parser::{get_font_bytes, write_atomically, FontCache};
+use crate::woff::encode_simple_glyph;
+use skrifa::instance::{Location, Size};
+use skrifa::outline::{DrawSettings, OutlinePen};
+use skrifa::raw::tables::mvar::tags;
+use skrifa::raw::types::Tag;
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::string::StringId;
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::{BTreeSet, HashMap};
+use write_fonts::from_obj::ToOwnedTable;
+use write_fonts::tables::head::Head;
+use write_fonts::tables::hhea::Hhea;
+use write_fonts::tables::hmtx::{Hmtx, LongMetric};
+use write_fonts::tables::maxp::Maxp;
+use write_fonts::tables::name::{Name, NameRecord};
+use write_fonts::tables::os2::Os2;
+use write_fonts::tables::post::Post;
+use write_fonts::types::{FWord, UfWord};
+use write_fonts::FontBuilder;
+
+/// Variation tables that mean nothing once every axis is pinned. DSIG no
+/// longer matches the changed bytes, and the device tables are per-glyph
+/// caches of the old outlines.
+const DROPPED_TABLES: [&[u8; 4]; 12] = [
+    b"fvar", b"gvar", b"avar", b"cvar", b"HVAR", b"VVAR", b"MVAR", b"STAT", b"DSIG", b"hdmx",
+    b"LTSH", b"VDMX",
+];
+
+/// Collects a glyph's drawn outline as TrueType contours. Quadratic segments
+/// keep their off-curve points; cubics cannot be represented in glyf.
+#[derive(Default)]
+struct ContourPen {
+    contours: Vec<Vec<(f32, f32, bool)>>,
+    has_cubics: bool,
+}
+
+impl OutlinePen for ContourPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.contours.push(vec![(x, y, true)]);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        if let Some(contour) = self.contours.last_mut() {
+            contour.push((x, y, true));
+        }
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        if let Some(contour) = self.contours.last_mut() {
+            contour.push((cx0, cy0, false));
+            contour.push((x, y, true));
+        }
+    }
+
+    fn curve_to(&mut self, _cx0: f32, _cy0: f32, _cx1: f32, _cy1: f32, _x: f32, _y: f32) {
+        self.has_cubics = true;
+    }
+
+    fn close(&mut self) {
+        // The segment back to the start point repeats it; glyf closes implicitly
+        if let Some(contour) = self.contours.last_mut() {
+            if contour.len() > 1 && contour.first() == contour.last() {
+                contour.pop();
+            }
+        }
+    }
+}
+
+/// Round a drawn contour to font units, dropping on-curve points that sit
+/// exactly between two off-curve points since glyf implies those.
+fn round_contour(contour: &[(f32, f32, bool)]) -> Vec<(i32, i32, bool)> {
+    let rounded: Vec<(i32, i32, bool)> = contour
+        .iter()
+        .map(|&(x, y, on)| (x.round() as i32, y.round() as i32, on))
+        .collect();
+    let n = rounded.len();
+    if n < 3 {
+        return rounded;
+    }
+    (0..n)
+        .filter(|&i| {
+            let (x, y, on) = rounded[i];
+            let (px, py, prev_on) = rounded[(i + n - 1) % n];
+            let (nx, ny, next_on) = rounded[(i + 1) % n];
+            !(on && !prev_on && !next_on && px + nx == 2 * x && py + ny == 2 * y)
+        })
+        .map(|i| rounded[i])
+        .collect()
+}
+
+/// A glyph compiled at the pinned location.
+struct InstanceGlyph {
+    record: Vec<u8>,
+    bbox: Option<[i16; 4]>,
+    advance: u16,
+    points: u16,
+    contours: u16,
+}
+
+/// OS/2 usWidthClass for a wdth axis value, by nearest percentage.
+fn width_class(wdth: f32) -> u16 {
+    const WIDTHS: [f32; 9] = [50.0, 62.5, 75.0, 87.5, 100.0, 112.5, 125.0, 150.0, 200.0];
+    let mut best = 0;
+    for (i, w) in WIDTHS.iter().enumerate() {
+        if (w - wdth).abs() < (WIDTHS[best] - wdth).abs() {
+            best = i;
+        }
+    }
+    best as u16 + 1
+}
+
+/// Name strings for the named instance the pinned location matches.
+struct InstanceNames {
+    family: String,
+    subfamily: String,
+    postscript: String,
+}
+
+fn matching_instance_names(font: &FontRef<'_>, pinned: &[f32]) -> Option<InstanceNames> {
+    let string = |id: StringId| {
+        font.localized_strings(id)
+            .english_or_first()
+            .map(|s| s.chars().collect::<String>())
+    };
+    let instance = font.named_instances().iter().find(|instance| {
+        instance
+            .user_coords()
+            .zip(pinned)
+            .all(|(coord, value)| (coord - value).abs() < 0.01)
+    })?;
+    let family =
+        string(StringId::TYPOGRAPHIC_FAMILY_NAME).or_else(|| string(StringId::FAMILY_NAME))?;
+    let subfamily = string(instance.subfamily_name_id())?;
+    let postscript = instance
+        .postscript_name_id()
+        .and_then(string)
+        .unwrap_or_else(|| format!("{}-{}", family, subfamily).replace(' ', ""));
+    Some(InstanceNames {
+        family,
+        subfamily,
+        postscript,
+    })
+}
+
+/// Rewrite the family, style, full and PostScript names (IDs 1, 2, 4, 6 and
+/// the typographic 16/17) for a static instance. Styles outside
+/// Regular/Bold/Italic/Bold Italic fold into the legacy family name.
+/// Translations of the rewritten IDs and the variations prefix (ID 25) are
+/// dropped since they describe the variable font.
+fn instance_name_table(font: &RawFontRef<'_>, names: &InstanceNames) -> Result<Name, FontError> {
+    const REWRITTEN: [u16; 7] = [1, 2, 4, 6, 16, 17, 25];
+    let name = font.name().map_err(|e| FontError::table("name", e))?;
+    let is_english = |platform: u16, language: u16| match platform {
+        0 => true,
+        1 => language == 0,
+        3 => language == 0x409,
+        _ => false,
+    };
+
+    let ribbi = matches!(
+        names.subfamily.as_str(),
+        "Regular" | "Bold" | "Italic" | "Bold Italic"
+    );
+    let full_name = format!("{} {}", names.family, names.subfamily);
+    let mut values: Vec<(u16, String)> =
+        vec![(4, full_name.clone()), (6, names.postscript.clone())];
+    if ribbi {
+        values.push((1, names.family.clone()));
+        values.push((2, names.subfamily.clone()));
+    } else {
+        let legacy_style = if names.subfamily.contains("Italic") {
+            "Italic"
+        } else {
+            "Regular"
+        };
+        let legacy_family = full_name.replace(" Italic", "");
+        values.push((1, legacy_family));
+        values.push((2, legacy_style.to_string()));
+        values.push((16, names.family.clone()));
+        values.push((17, names.subfamily.clone()));
+    }
+
+    let mut records = Vec::new();
+    let mut english_keys = BTreeSet::new();
+    for record in name.name_record() {
+        let (platform, encoding, language) = (
+            record.platform_id(),
+            record.encoding_id(),
+            record.language_id(),
+        );
+        let name_id = record.name_id().to_u16();
+        if REWRITTEN.contains(&name_id) {
+            if name_id == 1 && is_english(platform, language) {
+                english_keys.insert((platform, encoding, language));
+            }
+            continue;
+        }
+        let string = record
+            .string(name.string_data())
+            .map(|s| s.chars().collect::<String>())
+            .unwrap_or_default();
+        records.push(NameRecord::new(
+            platform,
+            encoding,
+            language,
+            name_id.into(),
+            string.into(),
+        ));
+    }
+    for &(platform, encoding, language) in &english_keys {
+        for (name_id, value) in &values {
+            records.push(NameRecord::new(
+                platform,
+                encoding,
+                language,
+                (*name_id).into(),
+                value.clone().into(),
+            ));
+        }
+    }
+    records.sort_by_key(|r| (r.platform_id, r.encoding_id, r.language_id, r.name_id));
+    Ok(Name::new(records))
+}
+
+fn instantiate_font_bytes(
+    bytes: &[u8],
+    axis_values: &HashMap<String, f32>,
+) -> Result<Vec<u8>, FontError> {
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let raw = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    if raw.fvar().is_err() {
+        return Err(FontError::missing("fvar"));
+    }
+    if raw.table_data(Tag::new(b"glyf")).is_none() {
+        return Err(FontError::unsupported(
+            "instancing variable fonts without glyf outlines",
+        ));
+    }
+
+    // Every axis gets a value: the requested one, or its default
+    let axes = font.axes();
+    for tag in axis_values.keys() {
+        if !axes.iter().any(|axis| axis.tag().to_string() == *tag) {
+            return Err(FontError::input(format!("Font has no {} axis", tag)));
+        }
+    }
+    let mut pinned = Vec::with_capacity(axes.len());
+    for axis in axes.iter() {
+        let tag = axis.tag().to_string();
+        let value = axis_values
+            .get(&tag)
+            .copied()
+            .unwrap_or(axis.default_value());
+        if value < axis.min_value() || value > axis.max_value() {
+            return Err(FontError::input(format!(
+                "{} value {} is outside the axis range {}..={}",
+                tag,
+                value,
+                axis.min_value(),
+                axis.max_value()
+            )));
+        }
+        pinned.push(value);
+    }
+    let settings: Vec<(&str, f32)> = axis_values
+        .iter()
+        .map(|(tag, v)| (tag.as_str(), *v))
+        .collect();
+    let location: Location = axes.location(settings);
+    let coords = location.coords();
+
+    // Outlines: drawn at the location, composites come out decomposed
+    let num_glyphs = raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs();
+    let outlines = font.outline_glyphs();
+    let glyph_metrics = font.glyph_metrics(Size::unscaled(), &location);
+    let has_hvar = raw.hvar().is_ok();
+    let mut glyphs = Vec::with_capacity(num_glyphs as usize);
+    for gid in 0..num_glyphs as u32 {
+        let gid = GlyphId::new(gid);
+        let mut pen = ContourPen::default();
+        let mut advance = glyph_metrics.advance_width(gid).unwrap_or(0.0);
+        let mut overlap = false;
+        if let Some(outline) = outlines.get(gid) {
+            let adjusted = outline
+                .draw(
+                    DrawSettings::unhinted(Size::unscaled(), &location),
+                    &mut pen,
+                )
+                .map_err(|e| {
+                    FontError::malformed("glyf", format!("glyph {}: {:?}", gid.to_u32(), e))
+                })?;
+            // Without HVAR the advance moves with gvar's phantom points
+            if !has_hvar {
+                advance = adjusted.advance_width.unwrap_or(advance);
+            }
+            overlap = adjusted.has_overlaps;
+        }
+        if pen.has_cubics {
+            return Err(FontError::unsupported(format!(
+                "Glyph {} has cubic curves, which glyf cannot hold",
+                gid.to_u32()
+            )));
+        }
+
+        let contours: Vec<Vec<(i32, i32, bool)>> = pen
+            .contours
+            .iter()
+            .map(|c| round_contour(c))
+            .filter(|c| !c.is_empty())
+            .collect();
+        let points: Vec<(i32, i32, bool)> = contours.iter().flatten().copied().collect();
+        let advance = advance.round().clamp(0.0, u16::MAX as f32) as u16;
+        if points.is_empty() {
+            glyphs.push(InstanceGlyph {
+                record: Vec::new(),
+                bbox: None,
+                advance,
+                points: 0,
+                contours: 0,
+            });
+            continue;
+        }
+        let mut end_points = Vec::with_capacity(contours.len());
+        let mut total = 0;
+        for contour in &contours {
+            total += contour.len();
+            end_points.push(total as u16 - 1);
+        }
+        let clamp = |v: i32| v.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        let bbox = [
+            clamp(points.iter().map(|p| p.0).min().unwrap()),
+            clamp(points.iter().map(|p| p.1).min().unwrap()),
+            clamp(points.iter().map(|p| p.0).max().unwrap()),
+            clamp(points.iter().map(|p| p.1).max().unwrap()),
+        ];
+        glyphs.push(InstanceGlyph {
+            record: encode_simple_glyph(&end_points, &points, &[], bbox, overlap),
+            bbox: Some(bbox),
+            advance,
+            points: points.len() as u16,
+            contours: contours.len() as u16,
+        });
+    }
+
+    let mut glyf = Vec::new();
+    let mut loca: Vec<u8> = 0u32.to_be_bytes().to_vec();
+    for glyph in &glyphs {
+        glyf.extend(&glyph.record);
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+        loca.extend((glyf.len() as u32).to_be_bytes());
+    }
+
+    // Metrics: every glyph gets a long metric; lsb is the glyph's xMin
+    let hmtx = Hmtx::new(
+        glyphs
+            .iter()
+            .map(|g| LongMetric::new(g.advance, g.bbox.map_or(0, |b| b[0])))
+            .collect(),
+        Vec::new(),
+    );
+    let mvar = raw.mvar().ok();
+    let delta = |tag: Tag| -> i32 {
+        mvar.as_ref()
+            .and_then(|mvar| mvar.metric_delta(tag, coords).ok())
+            .map_or(0, |d| d.to_f64().round() as i32)
+    };
+    let shift = |value: i16, tag: Tag| {
+        (value as i32 + delta(tag)).clamp(i16::MIN as i32, i16::MAX as i32) as i16
+    };
+
+    let mut hhea: Hhea = raw
+        .hhea()
+        .map_err(|e| FontError::table("hhea", e))?
+        .to_owned_table();
+    hhea.number_of_h_metrics = glyphs.len() as u16;
+    hhea.caret_slope_rise = shift(hhea.caret_slope_rise, tags::HCRS);
+    hhea.caret_slope_run = shift(hhea.caret_slope_run, tags::HCRN);
+    hhea.caret_offset = shift(hhea.caret_offset, tags::HCOF);
+    hhea.advance_width_max = UfWord::new(glyphs.iter().map(|g| g.advance).max().unwrap_or(0));
+    let outlined = || glyphs.iter().filter_map(|g| g.bbox.map(|b| (g.advance, b)));
+    hhea.min_left_side_bearing = FWord::new(outlined().map(|(_, b)| b[0]).min().unwrap_or(0));
+    hhea.min_right_side_bearing = FWord::new(
+        outlined()
+            .map(|(advance, b)| {
+                (advance as i32 - b[2] as i32).clamp(i16::MIN as i32, 0x7FFF) as i16
+            })
+            .min()
+            .unwrap_or(0),
+    );
+    hhea.x_max_extent = FWord::new(outlined().map(|(_, b)| b[2]).max().unwrap_or(0));
+
+    let mut head: Head = raw
+        .head()
+        .map_err(|e| FontError::table("head", e))?
+        .to_owned_table();
+    head.index_to_loc_format = 1;
+    let bboxes = || glyphs.iter().filter_map(|g| g.bbox);
+    head.x_min = bboxes().map(|b| b[0]).min().unwrap_or(0);
+    head.y_min = bboxes().map(|b| b[1]).min().unwrap_or(0);
+    head.x_max = bboxes().map(|b| b[2]).max().unwrap_or(0);
+    head.y_max = bboxes().map(|b| b[3]).max().unwrap_or(0);
+
+    // maxp: composites are gone, so their limits drop to zero
+    let mut maxp: Maxp = raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .to_owned_table();
+    if maxp.max_points.is_some() {
+        maxp.max_points = glyphs.iter().map(|g| g.points).max();
+        maxp.max_contours = glyphs.iter().map(|g| g.contours).max();
+        maxp.max_composite_points = Some(0);
+        maxp.max_composite_contours = Some(0);
+        maxp.max_component_elements = Some(0);
+        maxp.max_component_depth = Some(0);
+    }
+
+    let mut builder = FontBuilder::new();
+    builder
+        .add_raw(Tag::new(b"glyf"), glyf)
+        .add_raw(Tag::new(b"loca"), loca)
+        .add_table(&head)
+        .map_err(|e| FontError::build("head", e))?
+        .add_table(&hhea)
+        .map_err(|e| FontError::build("hhea", e))?
+        .add_table(&hmtx)
+        .map_err(|e| FontError::build("hmtx", e))?
+        .add_table(&maxp)
+        .map_err(|e| FontError::build("maxp", e))?;
+
+    if let Ok(os2) = raw.os2() {
+        let mut os2: Os2 = os2.to_owned_table();
+        os2.s_typo_ascender = shift(os2.s_typo_ascender, tags::HASC);
+        os2.s_typo_descender = shift(os2.s_typo_descender, tags::HDSC);
+        os2.s_typo_line_gap = shift(os2.s_typo_line_gap, tags::HLGP);
+        os2.us_win_ascent = (os2.us_win_ascent as i32 + delta(tags::HCLA)).clamp(0, 0xFFFF) as u16;
+        os2.us_win_descent =
+            (os2.us_win_descent as i32 + delta(tags::HCLD)).clamp(0, 0xFFFF) as u16;
+        os2.y_subscript_x_size = shift(os2.y_subscript_x_size, tags::SBXS);
+        os2.y_subscript_y_size = shift(os2.y_subscript_y_size, tags::SBYS);
+        os2.y_subscript_x_offset = shift(os2.y_subscript_x_offset, tags::SBXO);
+        os2.y_subscript_y_offset = shift(os2.y_subscript_y_offset, tags::SBYO);
+        os2.y_superscript_x_size = shift(os2.y_superscript_x_size, tags::SPXS);
+        os2.y_superscript_y_size = shift(os2.y_superscript_y_size, tags::SPYS);
+        os2.y_superscript_x_offset = shift(os2.y_superscript_x_offset, tags::SPXO);
+        os2.y_superscript_y_offset = shift(os2.y_superscript_y_offset, tags::SPYO);
+        os2.y_strikeout_size = shift(os2.y_strikeout_size, tags::STRS);
+        os2.y_strikeout_position = shift(os2.y_strikeout_position, tags::STRO);
+        os2.sx_height = os2.sx_height.map(|v| shift(v, tags::XHGT));
+        os2.s_cap_height = os2.s_cap_height.map(|v| shift(v, tags::CPHT));
+        for (axis, value) in axes.iter().zip(&pinned) {
+            if axis.tag() == Tag::new(b"wght") {
+                os2.us_weight_class = value.round().clamp(1.0, 1000.0) as u16;
+            } else if axis.tag() == Tag::new(b"wdth") {
+                os2.us_width_class = width_class(*value);
+            }
+        }
+        builder
+            .add_table(&os2)
+            .map_err(|e| FontError::build("OS/2", e))?;
+    }
+    if let Ok(post) = raw.post() {
+        let mut post: Post = post.to_owned_table();
+        post.underline_position = FWord::new(shift(post.underline_position.to_i16(), tags::UNDO));
+        post.underline_thickness = FWord::new(shift(post.underline_thickness.to_i16(), tags::UNDS));
+        builder
+            .add_table(&post)
+            .map_err(|e| FontError::build("post", e))?;
+    }
+    if let Some(names) = matching_instance_names(&font, &pinned) {
+        builder
+            .add_table(&instance_name_table(&raw, &names)?)
+            .map_err(|e| FontError::build("name", e))?;
+    }
+
+    for record in raw.table_directory.table_records() {
+        let tag = record.tag();
+        if !builder.contains(tag) && !DROPPED_TABLES.iter().any(|t| Tag::new(t) == tag) {
+            if let Some(data) = raw.table_data(tag) {
+                builder.add_raw(tag, data.as_bytes().to_vec());
+            }
+        }
+    }
+    Ok(builder.build())
+}
+
+/// Pin every axis of a TrueType variable font to the given user-space values
+/// (missing axes stay at their default) and write a static font to
+/// `destination`. Composite glyphs are decomposed and hinting instructions
+/// dropped; GDEF/GPOS variations keep their default-location values.
+pub fn instantiate_variable_font(
+    file_path: &str,
+    destination: &str,
+    axis_values: &HashMap<String, f32>,
+    cache: &FontCache,
+) -> Result<(), FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let instance = instantiate_font_bytes(&bytes, axis_values)?;
+    write_atomically(std::path::Path::new(destination), &instance)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{simple_glyph, TestFontBuilder};
+    use skrifa::raw::tables::glyf::Glyph;
+
+    /// fvar with a wght axis (100..400..900) and a "Black" instance at 900.
+    fn fvar_table() -> Vec<u8> {
+        let mut data = Vec::new();
+        for v in [1u16, 0, 16, 2, 1, 20, 1, 8] {
+            data.extend(v.to_be_bytes());
+        }
+        data.extend(b"wght");
+        for v in [100i32, 400, 900] {
+            data.extend((v << 16).to_be_bytes());
+        }
+        data.extend([0u8, 0, 1, 0]); // flags, axisNameID 256
+        data.extend([1u8, 1, 0, 0]); // subfamilyNameID 257, flags
+        data.extend((900i32 << 16).to_be_bytes());
+        data
+    }
+
+    /// gvar moving glyph 1's third point and its advance 100 units right at
+    /// the wght maximum.
+    fn gvar_table() -> Vec<u8> {
+        let mut data = Vec::new();
+        for v in [1u16, 0, 1, 0] {
+            data.extend(v.to_be_bytes());
+        }
+        data.extend(26u32.to_be_bytes()); // sharedTuplesOffset
+        for v in [2u16, 0] {
+            data.extend(v.to_be_bytes());
+        }
+        data.extend(26u32.to_be_bytes()); // glyphVariationDataArrayOffset
+        for v in [0u16, 0, 10] {
+            data.extend(v.to_be_bytes());
+        }
+        // One tuple with an embedded peak and private "all points" numbers
+        for v in [1u16, 10, 10, 0xA000, 0x4000] {
+            data.extend(v.to_be_bytes());
+        }
+        data.push(0); // all points
+        data.extend([0x06, 0, 0, 100, 0, 100, 0, 0]); // x deltas, incl. phantoms
+        data.push(0x86); // y deltas: seven zeros
+        data
+    }
+
+    fn name_table() -> Vec<u8> {
+        let records = [
+            (1, "Test"),
+            (2, "Regular"),
+            (4, "Test Regular"),
+            (6, "Test-Regular"),
+            (256, "Weight"),
+            (257, "Black"),
+        ]
+        .into_iter()
+        .map(|(id, s)| NameRecord::new(3, 1, 0x409, id.into(), s.to_string().into()))
+        .collect();
+        write_fonts::dump_table(&Name::new(records)).unwrap()
+    }
+
+    fn variable_font() -> Vec<u8> {
+        TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(
+                simple_glyph(&[&[(0, 0, true), (0, 500, true), (500, 500, true)]]),
+                600,
+            )
+            .map('A' as u32, 1)
+            .table(b"fvar", fvar_table())
+            .table(b"gvar", gvar_table())
+            .table(b"name", name_table())
+            .build()
+    }
+
+    #[test]
+    fn test_instantiate_variable_font() {
+        let bytes = variable_font();
+        let axis_values = HashMap::from([("wght".to_string(), 900.0)]);
+        let instance = instantiate_font_bytes(&bytes, &axis_values).unwrap();
+
+        let raw = RawFontRef::new(&instance).unwrap();
+        for tag in [b"fvar", b"gvar"] {
+            assert!(raw.table_data(Tag::new(tag)).is_none());
+        }
+        assert_eq!(raw.hmtx().unwrap().advance(GlyphId::new(1)), Some(700));
+        let loca = raw.loca(None).unwrap();
+        let glyf = raw.glyf().unwrap();
+        let Some(Glyph::Simple(simple)) = loca.get_glyf(GlyphId::new(1), &glyf).unwrap() else {
+            panic!("expected a simple glyph");
+        };
+        assert_eq!(simple.x_max(), 600);
+        assert_eq!(raw.head().unwrap().x_max(), 600);
+
+        // The location matches the Black instance, a non-RIBBI style
+        let font = FontRef::new(&instance).unwrap();
+        let name = |id: u16| {
+            font.localized_strings(StringId::new(id))
+                .english_or_first()
+                .map(|s| s.chars().collect::<String>())
+        };
+        assert_eq!(name(1).as_deref(), Some("Test Black"));
+        assert_eq!(name(2).as_deref(), Some("Regular"));
+        assert_eq!(name(6).as_deref(), Some("Test-Black"));
+        assert_eq!(name(16).as_deref(), Some("Test"));
+        assert_eq!(name(17).as_deref(), Some("Black"));
+    }
+
+    #[test]
+    fn test_instantiate_variable_font_errors() {
+        let bytes = variable_font();
+        let out_of_range = HashMap::from([("wght".to_string(), 1000.0)]);
+        assert!(instantiate_font_bytes(&bytes, &out_of_range)
+            .unwrap_err()
+            .to_string()
+            .contains("outside the axis range"));
+        let unknown = HashMap::from([("wdth".to_string(), 100.0)]);
+        assert_eq!(
+            instantiate_font_bytes(&bytes, &unknown).unwrap_err(),
+            FontError::input("Font has no wdth axis")
+        );
+    }
+}
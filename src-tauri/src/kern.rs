@@ -0,0 +1,621 @@
+use crate::error::FontError;
+use crate::font_parser::{editable_font_bytes, save_edited_font, FontCache};
+use serde::{Deserialize, Serialize};
+use skrifa::raw::tables::kern::{Subtable, SubtableKind};
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use write_fonts::from_obj::ToOwnedTable;
+use write_fonts::tables::gpos::{
+    Class1Record, Class2Record, Gpos, PairPos, PairSet, PairValueRecord, PositionLookup,
+    PositionLookupList, ValueRecord,
+};
+use write_fonts::tables::layout::{
+    ClassDef, CoverageTable, Feature, FeatureList, FeatureRecord, LangSys, Lookup, LookupFlag,
+    Script, ScriptList, ScriptRecord,
+};
+use write_fonts::types::{GlyphId16, Tag};
+use write_fonts::FontBuilder;
+
+/// One kerning pair: the adjustment in font units applied between `left`
+/// and `right`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KernPair {
+    pub left: u16,
+    pub right: u16,
+    pub value: i16,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KernTableSummary {
+    pub pairs: usize,
+    pub subtables: usize,
+}
+
+const KERN_TABLE_HEADER_LEN: usize = 4;
+const SUBTABLE_HEADER_LEN: usize = 6;
+const FORMAT0_HEADER_LEN: usize = 8;
+const PAIR_LEN: usize = 6;
+/// Coverage for a horizontal format 0 subtable holding kerning values.
+const COVERAGE_HORIZONTAL_FORMAT0: u16 = 0x0001;
+
+/// Most pairs one format 0 subtable holds: its length field is a uint16.
+pub(crate) const MAX_PAIRS_PER_SUBTABLE: usize =
+    (u16::MAX as usize - SUBTABLE_HEADER_LEN - FORMAT0_HEADER_LEN) / PAIR_LEN;
+
+/// The nPairs-derived binary search fields of a format 0 subtable:
+/// (searchRange, entrySelector, rangeShift).
+fn search_fields(n_pairs: usize) -> (u16, u16, u16) {
+    if n_pairs == 0 {
+        return (0, 0, 0);
+    }
+    let entry_selector = n_pairs.ilog2();
+    let search_range = PAIR_LEN << entry_selector;
+    (
+        search_range as u16,
+        entry_selector as u16,
+        (n_pairs * PAIR_LEN - search_range) as u16,
+    )
+}
+
+fn format0_subtable(pairs: &[KernPair]) -> Vec<u8> {
+    let length = SUBTABLE_HEADER_LEN + FORMAT0_HEADER_LEN + pairs.len() * PAIR_LEN;
+    let (search_range, entry_selector, range_shift) = search_fields(pairs.len());
+    let mut buf = Vec::with_capacity(length);
+    buf.extend(0u16.to_be_bytes()); // version
+    buf.extend((length as u16).to_be_bytes());
+    buf.extend(COVERAGE_HORIZONTAL_FORMAT0.to_be_bytes());
+    buf.extend((pairs.len() as u16).to_be_bytes());
+    buf.extend(search_range.to_be_bytes());
+    buf.extend(entry_selector.to_be_bytes());
+    buf.extend(range_shift.to_be_bytes());
+    for pair in pairs {
+        buf.extend(pair.left.to_be_bytes());
+        buf.extend(pair.right.to_be_bytes());
+        buf.extend(pair.value.to_be_bytes());
+    }
+    buf
+}
+
+/// Encode `pairs` as a version 0 kern table of horizontal format 0
+/// subtables. Pairs are sorted by (left, right) as lookups binary search
+/// them, and split over as many subtables as the uint16 length allows.
+pub(crate) fn build_kern_table(
+    pairs: &[KernPair],
+    num_glyphs: usize,
+) -> Result<(Vec<u8>, KernTableSummary), FontError> {
+    let mut seen = HashSet::with_capacity(pairs.len());
+    for pair in pairs {
+        for gid in [pair.left, pair.right] {
+            if gid as usize >= num_glyphs {
+                return Err(FontError::GlyphNotFound { gid: gid as u32 });
+            }
+        }
+        if !seen.insert((pair.left, pair.right)) {
+            return Err(FontError::input(format!(
+                "Pair {} {} is listed more than once",
+                pair.left, pair.right
+            )));
+        }
+    }
+    let mut sorted = pairs.to_vec();
+    sorted.sort_unstable_by_key(|p| (p.left, p.right));
+
+    let chunks: Vec<&[KernPair]> = sorted.chunks(MAX_PAIRS_PER_SUBTABLE).collect();
+    let subtables = u16::try_from(chunks.len())
+        .map_err(|_| FontError::input("Too many kerning pairs for one kern table"))?;
+    let mut table = Vec::with_capacity(
+        KERN_TABLE_HEADER_LEN
+            + chunks.len() * (SUBTABLE_HEADER_LEN + FORMAT0_HEADER_LEN)
+            + sorted.len() * PAIR_LEN,
+    );
+    table.extend(0u16.to_be_bytes()); // version
+    table.extend(subtables.to_be_bytes());
+    for chunk in &chunks {
+        table.extend(format0_subtable(chunk));
+    }
+    Ok((
+        table,
+        KernTableSummary {
+            pairs: sorted.len(),
+            subtables: chunks.len(),
+        },
+    ))
+}
+
+/// Replace the font's kern table with one built from `pairs`, e.g. a list
+/// pasted from a spacing tool. Any existing kern table is discarded.
+pub fn create_kern_from_list(
+    file_path: &str,
+    pairs: &[KernPair],
+    cache: &FontCache,
+) -> Result<KernTableSummary, FontError> {
+    if pairs.is_empty() {
+        return Err(FontError::input("No kerning pairs given"));
+    }
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+    let (kern, summary) = build_kern_table(pairs, num_glyphs)?;
+    let new_bytes = FontBuilder::new()
+        .add_raw(Tag::new(b"kern"), kern)
+        .copy_missing_tables(font)
+        .build();
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(summary)
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KernConversionSummary {
+    pub pairs: usize,
+    /// PairPos format used: 1 for glyph pairs, 2 for class pairs.
+    pub format: u16,
+    pub left_classes: usize,
+    pub right_classes: usize,
+    /// kern subtables that could not be carried over (formats other than 0,
+    /// vertical, cross-stream or minimum-value subtables).
+    pub skipped_subtables: usize,
+    pub lookup_index: u16,
+}
+
+/// Kerning values keyed by (left, right) glyph.
+type KernPairMap = BTreeMap<(u16, u16), i16>;
+
+const KERN_FEATURE: Tag = Tag::new(b"kern");
+/// Scripts the converted lookup is registered under.
+const KERN_SCRIPTS: [Tag; 2] = [Tag::new(b"DFLT"), Tag::new(b"latn")];
+const COVERAGE_MINIMUM: u16 = 0x0002;
+const COVERAGE_OVERRIDE: u16 = 0x0008;
+
+/// The horizontal kerning of the font's format 0 kern subtables, combined
+/// the way the spec applies them (summed, unless a subtable overrides), and
+/// the number of subtables that were skipped. Zero-valued pairs are dropped.
+pub(crate) fn read_kern_pairs(font: &RawFontRef<'_>) -> Result<(KernPairMap, usize), FontError> {
+    let kern = font.kern().map_err(|e| FontError::table("kern", e))?;
+    let mut pairs = BTreeMap::<(u16, u16), i16>::new();
+    let mut skipped = 0;
+    for subtable in kern.subtables() {
+        let subtable = subtable.map_err(|e| FontError::table("kern", e))?;
+        let coverage = match &subtable {
+            Subtable::Ot(table) => table.coverage(),
+            Subtable::Aat(_) => 0,
+        };
+        let table = match subtable.kind() {
+            Ok(SubtableKind::Format0(table))
+                if subtable.is_horizontal()
+                    && !subtable.is_cross_stream()
+                    && coverage & COVERAGE_MINIMUM == 0 =>
+            {
+                table
+            }
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+        for pair in table.pairs() {
+            let key = (pair.left().to_u16(), pair.right().to_u16());
+            let entry = pairs.entry(key).or_insert(0);
+            *entry = if coverage & COVERAGE_OVERRIDE != 0 {
+                pair.value()
+            } else {
+                entry.saturating_add(pair.value())
+            };
+        }
+    }
+    pairs.retain(|_, value| *value != 0);
+    Ok((pairs, skipped))
+}
+
+/// Left and right glyph classes for `pairs`: glyphs land in one class when
+/// they kern identically against everything, which makes the class matrix
+/// exact. Classes are numbered from 1 so class 0 stays "everything else".
+struct PairClasses {
+    left: BTreeMap<u16, u16>,
+    right: BTreeMap<u16, u16>,
+    left_count: usize,
+    right_count: usize,
+}
+
+fn group_by_profile(profiles: BTreeMap<u16, Vec<(u16, i16)>>) -> (BTreeMap<u16, u16>, usize) {
+    let mut classes = HashMap::new();
+    let mut assigned = BTreeMap::new();
+    for (gid, profile) in profiles {
+        let next = classes.len() as u16 + 1;
+        assigned.insert(gid, *classes.entry(profile).or_insert(next));
+    }
+    (assigned, classes.len())
+}
+
+fn classify_pairs(pairs: &KernPairMap) -> PairClasses {
+    let mut rows = BTreeMap::<u16, Vec<(u16, i16)>>::new();
+    let mut columns = BTreeMap::<u16, Vec<(u16, i16)>>::new();
+    for (&(left, right), &value) in pairs {
+        rows.entry(left).or_default().push((right, value));
+        columns.entry(right).or_default().push((left, value));
+    }
+    let (left, left_count) = group_by_profile(rows);
+    let (right, right_count) = group_by_profile(columns);
+    PairClasses {
+        left,
+        right,
+        left_count,
+        right_count,
+    }
+}
+
+/// Rough encoded size of a ClassDef: the smaller of a format 1 array over
+/// the glyph span and format 2 runs of equal class.
+fn class_def_size(classes: &BTreeMap<u16, u16>) -> usize {
+    let (Some(first), Some(last)) = (classes.keys().next(), classes.keys().next_back()) else {
+        return 4;
+    };
+    let mut runs = 0;
+    let mut prev = None;
+    for (&gid, &class) in classes {
+        if prev != Some((gid.wrapping_sub(1), class)) {
+            runs += 1;
+        }
+        prev = Some((gid, class));
+    }
+    (6 + 2 * (*last as usize - *first as usize + 1)).min(4 + 6 * runs)
+}
+
+/// A kern lookup for `pairs`, in whichever PairPos format encodes smaller.
+/// Returns the lookup and the format chosen.
+fn pair_pos_lookup(pairs: &KernPairMap) -> (PositionLookup, u16, PairClasses) {
+    let classes = classify_pairs(pairs);
+    let coverage_size = 4 + 2 * classes.left.len();
+    let format1_size = 10 + coverage_size + 4 * classes.left.len() + 4 * pairs.len();
+    let format2_size = 16
+        + coverage_size
+        + class_def_size(&classes.left)
+        + class_def_size(&classes.right)
+        + 2 * (classes.left_count + 1) * (classes.right_count + 1);
+
+    let coverage: CoverageTable = classes.left.keys().map(|&g| GlyphId16::new(g)).collect();
+    let (subtable, format) = if format2_size < format1_size {
+        let mut matrix = vec![vec![0i16; classes.right_count + 1]; classes.left_count + 1];
+        for (&(left, right), &value) in pairs {
+            matrix[classes.left[&left] as usize][classes.right[&right] as usize] = value;
+        }
+        let class_def = |map: &BTreeMap<u16, u16>| -> ClassDef {
+            map.iter()
+                .map(|(&gid, &class)| (GlyphId16::new(gid), class))
+                .collect()
+        };
+        let records = matrix
+            .into_iter()
+            .map(|row| {
+                Class1Record::new(
+                    row.into_iter()
+                        .map(|value| {
+                            Class2Record::new(
+                                ValueRecord::new().with_x_advance(value),
+                                ValueRecord::new(),
+                            )
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+        (
+            PairPos::format_2(
+                coverage,
+                class_def(&classes.left),
+                class_def(&classes.right),
+                records,
+            ),
+            2,
+        )
+    } else {
+        let mut sets = BTreeMap::<u16, Vec<PairValueRecord>>::new();
+        for (&(left, right), &value) in pairs {
+            sets.entry(left).or_default().push(PairValueRecord::new(
+                GlyphId16::new(right),
+                ValueRecord::new().with_x_advance(value),
+                ValueRecord::new(),
+            ));
+        }
+        (
+            PairPos::format_1(coverage, sets.into_values().map(PairSet::new).collect()),
+            1,
+        )
+    };
+    let lookup = PositionLookup::Pair(Lookup::new(LookupFlag::empty(), vec![subtable]));
+    (lookup, format, classes)
+}
+
+/// Append `lookup` to `gpos` and hook it into every 'kern' feature, adding
+/// one if there is none, then make sure the default and language systems of
+/// DFLT and latn use a 'kern' feature. Returns the new lookup's index.
+fn add_kern_lookup(gpos: &mut Gpos, lookup: PositionLookup) -> Result<u16, FontError> {
+    let lookup_index = u16::try_from(gpos.lookup_list.lookups.len())
+        .map_err(|_| FontError::input("GPOS has no room for another lookup"))?;
+    gpos.lookup_list.lookups.push(lookup.into());
+
+    let features = &mut gpos.feature_list.feature_records;
+    let mut kern_features = Vec::new();
+    for (index, record) in features.iter_mut().enumerate() {
+        if record.feature_tag == KERN_FEATURE {
+            record.feature.lookup_list_indices.push(lookup_index);
+            kern_features.push(index as u16);
+        }
+    }
+    if kern_features.is_empty() {
+        kern_features.push(features.len() as u16);
+        features.push(FeatureRecord::new(
+            KERN_FEATURE,
+            Feature::new(None, vec![lookup_index]),
+        ));
+    }
+
+    let scripts = &mut gpos.script_list.script_records;
+    for tag in KERN_SCRIPTS {
+        let index = match scripts.binary_search_by_key(&tag, |r| r.script_tag) {
+            Ok(index) => index,
+            Err(index) => {
+                scripts.insert(index, ScriptRecord::new(tag, Script::new(None, Vec::new())));
+                index
+            }
+        };
+        let script: &mut Script = &mut scripts[index].script;
+        if script.default_lang_sys.as_ref().is_none() {
+            script.default_lang_sys.set(LangSys::new(Vec::new()));
+        }
+        let lang_systems = script
+            .default_lang_sys
+            .as_mut()
+            .into_iter()
+            .chain(script.lang_sys_records.iter_mut().map(|r| &mut *r.lang_sys));
+        for lang_sys in lang_systems {
+            if !lang_sys
+                .feature_indices
+                .iter()
+                .any(|i| kern_features.contains(i))
+            {
+                lang_sys.feature_indices.push(kern_features[0]);
+                lang_sys.feature_indices.sort_unstable();
+            }
+        }
+    }
+    Ok(lookup_index)
+}
+
+/// Carry the kern table's pairs over to a GPOS 'kern' lookup, since shapers
+/// ignore kern in fonts that have GPOS. The kern table itself is kept for
+/// older applications.
+pub fn convert_kern_to_gpos(
+    file_path: &str,
+    cache: &FontCache,
+) -> Result<KernConversionSummary, FontError> {
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let (pairs, skipped_subtables) = read_kern_pairs(&font)?;
+    if pairs.is_empty() {
+        return Err(FontError::input("The kern table has no pairs to convert"));
+    }
+    let mut gpos: Gpos = match font.gpos() {
+        Ok(gpos) => gpos.to_owned_table(),
+        Err(_) => Gpos::new(
+            ScriptList::default(),
+            FeatureList::default(),
+            PositionLookupList::default(),
+        ),
+    };
+    let (lookup, format, classes) = pair_pos_lookup(&pairs);
+    let lookup_index = add_kern_lookup(&mut gpos, lookup)?;
+    let new_bytes = FontBuilder::new()
+        .add_table(&gpos)
+        .map_err(|e| FontError::build("GPOS", e))?
+        .copy_missing_tables(font)
+        .build();
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(KernConversionSummary {
+        pairs: pairs.len(),
+        format,
+        left_classes: classes.left_count,
+        right_classes: classes.right_count,
+        skipped_subtables,
+        lookup_index,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font_parser::get_font_bytes;
+    use crate::gpos::{kern_pair_lookups, resolve_kerning};
+    use crate::test_fonts::TestFontBuilder;
+    use skrifa::GlyphId;
+
+    #[test]
+    fn test_search_fields() {
+        assert_eq!(search_fields(1), (6, 0, 0));
+        assert_eq!(search_fields(5), (24, 2, 6));
+        assert_eq!(search_fields(8), (48, 3, 0));
+        assert_eq!(search_fields(MAX_PAIRS_PER_SUBTABLE), (49152, 13, 16368));
+    }
+
+    #[test]
+    fn test_create_kern_from_list_splits_large_lists() {
+        let dir = std::env::temp_dir().join(format!("typebrew-kern-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        let mut builder = TestFontBuilder::new();
+        for _ in 0..120 {
+            builder = builder.glyph(Vec::new(), 500);
+        }
+        std::fs::write(path, builder.build()).unwrap();
+
+        // 12,000 pairs in reverse order: more than one subtable holds
+        let value = |left: u16, right: u16| (left as i16 - right as i16) * 3 - 1;
+        let pairs: Vec<KernPair> = (0..120u16)
+            .rev()
+            .flat_map(|left| (0..100u16).rev().map(move |right| (left, right)))
+            .map(|(left, right)| KernPair {
+                left,
+                right,
+                value: value(left, right),
+            })
+            .collect();
+        let cache = FontCache::new();
+        let summary = create_kern_from_list(path, &pairs, &cache).unwrap();
+        assert_eq!(
+            summary,
+            KernTableSummary {
+                pairs: 12_000,
+                subtables: 2
+            }
+        );
+
+        let bytes = get_font_bytes(path, &cache).unwrap();
+        let font = RawFontRef::new(&bytes).unwrap();
+        let kern = font.kern().unwrap();
+        let subtables: Vec<_> = kern
+            .subtables()
+            .map(|s| match s.unwrap().kind().unwrap() {
+                SubtableKind::Format0(table) => table,
+                _ => panic!("expected format 0"),
+            })
+            .collect();
+        assert_eq!(subtables.len(), 2);
+        assert_eq!(subtables[0].n_pairs() as usize, MAX_PAIRS_PER_SUBTABLE);
+        let lookup = |left: u16, right: u16| {
+            subtables
+                .iter()
+                .find_map(|t| t.kerning(GlyphId::new(left as u32), GlyphId::new(right as u32)))
+        };
+        // Both ends, the split point and some pairs in between
+        for (left, right) in [(0, 0), (119, 99), (109, 19), (109, 20), (57, 3), (88, 64)] {
+            assert_eq!(lookup(left, right), Some(value(left, right) as i32));
+        }
+        assert_eq!(lookup(3, 100), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_kern_table_rejects_bad_pairs() {
+        let pair = |left, right| KernPair {
+            left,
+            right,
+            value: -40,
+        };
+        assert!(matches!(
+            build_kern_table(&[pair(1, 2), pair(1, 2)], 3),
+            Err(FontError::InvalidInput { .. })
+        ));
+        assert!(matches!(
+            build_kern_table(&[pair(1, 3)], 3),
+            Err(FontError::GlyphNotFound { gid: 3 })
+        ));
+    }
+
+    /// The effective GPOS kerning for (left, right), or None when no kern
+    /// lookup has anything for the pair.
+    fn gpos_kerning(bytes: &[u8], left: u16, right: u16) -> Option<i32> {
+        let font = RawFontRef::new(bytes).unwrap();
+        let lookups = kern_pair_lookups(&font).unwrap();
+        let kerning = resolve_kerning(&font, &lookups, left, right).unwrap();
+        (!kerning.sources.is_empty()).then_some(kerning.value)
+    }
+
+    #[test]
+    fn test_convert_kern_to_gpos() {
+        let dir = std::env::temp_dir().join(format!("typebrew-kern-gpos-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        let mut builder = TestFontBuilder::new();
+        for _ in 0..40 {
+            builder = builder.glyph(Vec::new(), 500);
+        }
+        let font = builder.build();
+        let cache = FontCache::new();
+
+        // Glyphs 1-10 kern alike against 20-29: classes pay off
+        let mut pairs: Vec<KernPair> = (1..=10)
+            .flat_map(|left| (20..30).map(move |right| (left, right)))
+            .map(|(left, right)| KernPair {
+                left,
+                right,
+                value: if right < 25 { -30 } else { -60 },
+            })
+            .collect();
+        pairs.push(KernPair {
+            left: 11,
+            right: 20,
+            value: 15,
+        });
+        let (kern, _) = build_kern_table(&pairs, 40).unwrap();
+        let classed = FontBuilder::new()
+            .add_raw(Tag::new(b"kern"), kern)
+            .copy_missing_tables(RawFontRef::new(&font).unwrap())
+            .build();
+        std::fs::write(path, &classed).unwrap();
+        let summary = convert_kern_to_gpos(path, &cache).unwrap();
+        assert_eq!(
+            summary,
+            KernConversionSummary {
+                pairs: 101,
+                format: 2,
+                left_classes: 2,
+                right_classes: 3,
+                skipped_subtables: 0,
+                lookup_index: 0,
+            }
+        );
+        let bytes = get_font_bytes(path, &cache).unwrap();
+        assert_eq!(gpos_kerning(&bytes, 3, 21), Some(-30));
+        assert_eq!(gpos_kerning(&bytes, 10, 29), Some(-60));
+        assert_eq!(gpos_kerning(&bytes, 11, 20), Some(15));
+        assert_eq!(gpos_kerning(&bytes, 11, 21), Some(0));
+        assert_eq!(gpos_kerning(&bytes, 12, 20), None);
+        let gpos = RawFontRef::new(&bytes).unwrap().gpos().unwrap();
+        let scripts: Vec<Tag> = gpos
+            .script_list()
+            .unwrap()
+            .script_records()
+            .iter()
+            .map(|r| r.script_tag())
+            .collect();
+        assert_eq!(scripts, vec![Tag::new(b"DFLT"), Tag::new(b"latn")]);
+        let features = gpos.feature_list().unwrap();
+        assert_eq!(features.feature_records()[0].feature_tag(), KERN_FEATURE);
+
+        // Unrelated pairs share nothing, so plain glyph pairs are smaller; a
+        // second conversion extends the existing feature
+        let pairs = [(30, 2, -20), (31, 3, 40), (35, 1, -10)]
+            .map(|(left, right, value)| KernPair { left, right, value });
+        let (kern, _) = build_kern_table(&pairs, 40).unwrap();
+        let flat = FontBuilder::new()
+            .add_raw(Tag::new(b"kern"), kern)
+            .copy_missing_tables(RawFontRef::new(&bytes).unwrap())
+            .build();
+        std::fs::write(path, &flat).unwrap();
+        let cache = FontCache::new();
+        let summary = convert_kern_to_gpos(path, &cache).unwrap();
+        assert_eq!((summary.format, summary.lookup_index), (1, 1));
+        let bytes = get_font_bytes(path, &cache).unwrap();
+        let gpos = RawFontRef::new(&bytes).unwrap().gpos().unwrap();
+        let features = gpos.feature_list().unwrap();
+        assert_eq!(features.feature_records().len(), 1);
+        let kern_feature = features.feature_records()[0]
+            .feature(features.offset_data())
+            .unwrap();
+        let indices: Vec<u16> = kern_feature
+            .lookup_list_indices()
+            .iter()
+            .map(|i| i.get())
+            .collect();
+        assert_eq!(indices, vec![0, 1]);
+        assert_eq!(gpos_kerning(&bytes, 35, 1), Some(-10));
+        assert_eq!(gpos_kerning(&bytes, 3, 21), Some(-30));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+use crate::error::FontError;
+use crate::font_parser::{get_font_bytes, FontCache};
+use serde::Serialize;
+use skrifa::{FontRef, MetadataProvider};
+
+/// A language and the characters text in it can't do without. Exemplars
+/// list base characters, with `x-y` spans for contiguous runs; cased
+/// scripts list lowercase only and need the uppercase forms too.
+struct Language {
+    tag: &'static str,
+    name: &'static str,
+    script: &'static str,
+    exemplars: &'static str,
+    cased: bool,
+}
+
+const fn latin(tag: &'static str, name: &'static str, exemplars: &'static str) -> Language {
+    Language {
+        tag,
+        name,
+        script: "Latin",
+        exemplars,
+        cased: true,
+    }
+}
+
+const fn cyrillic(tag: &'static str, name: &'static str, exemplars: &'static str) -> Language {
+    Language {
+        tag,
+        name,
+        script: "Cyrillic",
+        exemplars,
+        cased: true,
+    }
+}
+
+const fn uncased(
+    tag: &'static str,
+    name: &'static str,
+    script: &'static str,
+    exemplars: &'static str,
+) -> Language {
+    Language {
+        tag,
+        name,
+        script,
+        exemplars,
+        cased: false,
+    }
+}
+
+// Main exemplar sets after CLDR, trimmed to letters. CJK sets are samples of
+// the most frequent characters rather than full repertoires.
+const LANGUAGES: &[Language] = &[
+    latin("en", "English", "a-z"),
+    latin("fr", "French", "a-zàâæçéèêëîïôœùûüÿ"),
+    latin("de", "German", "a-zäöüß"),
+    latin("es", "Spanish", "a-záéíñóúü"),
+    latin("pt", "Portuguese", "a-záàâãçéêíóôõú"),
+    latin("it", "Italian", "a-zàèéìòù"),
+    latin("nl", "Dutch", "a-záäéëíïóöúü"),
+    latin("ca", "Catalan", "a-zàçèéíïòóúü"),
+    latin("sv", "Swedish", "a-zåäö"),
+    latin("nb", "Norwegian", "a-zåæø"),
+    latin("da", "Danish", "a-zåæø"),
+    latin("fi", "Finnish", "a-zåäöšž"),
+    latin("is", "Icelandic", "abdefghijklmnoprstuvxyáðéíóúýþæö"),
+    latin("pl", "Polish", "a-ząćęłńóśźż"),
+    latin("cs", "Czech", "a-záčďéěíňóřšťúůýž"),
+    latin("sk", "Slovak", "a-záäčďéíĺľňóôŕšťúýž"),
+    latin("hu", "Hungarian", "a-záéíóöőúüű"),
+    latin("ro", "Romanian", "a-zăâîșț"),
+    latin("hr", "Croatian", "a-zčćđšž"),
+    latin("sl", "Slovenian", "a-zčšž"),
+    latin("lt", "Lithuanian", "a-ząčęėįšųūž"),
+    latin("lv", "Latvian", "a-zāčēģīķļņšūž"),
+    latin("et", "Estonian", "a-zäõöšüž"),
+    // Dotted capital I has no lowercase of its own to derive it from
+    latin("tr", "Turkish", "a-zçğıİöşü"),
+    latin("id", "Indonesian", "a-z"),
+    latin(
+        "vi",
+        "Vietnamese",
+        "a-zàáâãèéêìíòóôõùúýăđĩũơưạ-ỹ",
+    ),
+    cyrillic("ru", "Russian", "а-яё"),
+    cyrillic("uk", "Ukrainian", "а-щьюяєіїґ"),
+    cyrillic("be", "Belarusian", "абвгдеёжзйклмнопрстуўфхцчшыьэюяі"),
+    cyrillic("bg", "Bulgarian", "а-ъьюя"),
+    cyrillic("sr", "Serbian", "абвгдђежзијклљмнњопрстћуфхцчџш"),
+    cyrillic("mk", "Macedonian", "абвгдѓежзѕијклљмнњопрстќуфхцчџш"),
+    cyrillic("kk", "Kazakh", "а-яёіғқңүұһәө"),
+    Language {
+        tag: "el",
+        name: "Greek",
+        script: "Greek",
+        exemplars: "α-ωάέήίόύώϊϋΐΰ",
+        cased: true,
+    },
+    uncased("he", "Hebrew", "Hebrew", "א-ת"),
+    uncased("ar", "Arabic", "Arabic", "ء-غف-ي"),
+    uncased("fa", "Persian", "Arabic", "آابپتثجچحخدذرزژسشصضطظعغفقکگلمنوهی"),
+    uncased("ur", "Urdu", "Arabic", "آابپتٹثجچحخدڈذرڑزژسشصضطظعغفقکگلمنںوہھءیے"),
+    uncased(
+        "ja",
+        "Japanese",
+        "Japanese",
+        "ぁ-ゖァ-ヺー日本人一二三四五十年月火水木金土大小中上下山川田学生先名国語何時分今円",
+    ),
+    uncased(
+        "zh-Hans",
+        "Chinese (Simplified)",
+        "Han",
+        "的一是不了人我在有他这中大来上国个到说们为子和你地出道也时年得就那要下以生会自着去之过家学对可",
+    ),
+    uncased(
+        "zh-Hant",
+        "Chinese (Traditional)",
+        "Han",
+        "的一是不了人我在有他這中大來上國個到說們為子和你地出道也時年得就那要下以生會自著去之過家學對可",
+    ),
+    uncased(
+        "ko",
+        "Korean",
+        "Hangul",
+        "가각간갈감강개거것게고과관구국그근기나난내너네는늘다대더도동된되들등라로를리마만많말모무문물미바반방보부사상서성소수시아안않어없에여연오와요우운원위으은을음의이인일입있자작장저전정제조주지진하한할해했현화",
+    ),
+];
+
+/// How much of a language's exemplar set the font's cmap covers.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SupportStatus {
+    Supported,
+    Partial,
+    Unsupported,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LanguageSupport {
+    /// BCP 47 tag.
+    pub tag: String,
+    pub name: String,
+    pub script: String,
+    pub status: SupportStatus,
+    /// Exemplar codepoints the font lacks, ascending; only listed for
+    /// partial support.
+    pub missing: Vec<u32>,
+    /// Size of the exemplar set, uppercase forms included.
+    pub exemplar_count: u32,
+}
+
+/// Expand an exemplar string into codepoints, adding uppercase forms for a
+/// cased script.
+fn exemplar_codepoints(language: &Language) -> Vec<u32> {
+    let chars: Vec<char> = language.exemplars.chars().collect();
+    let mut codepoints = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars.get(i + 1) == Some(&'-') && i + 2 < chars.len() {
+            codepoints.extend(chars[i] as u32..=chars[i + 2] as u32);
+            i += 3;
+        } else {
+            codepoints.push(chars[i] as u32);
+            i += 1;
+        }
+    }
+    if language.cased {
+        let upper: Vec<u32> = codepoints
+            .iter()
+            .filter_map(|&cp| char::from_u32(cp))
+            .flat_map(char::to_uppercase)
+            .map(|c| c as u32)
+            .collect();
+        codepoints.extend(upper);
+    }
+    codepoints.sort_unstable();
+    codepoints.dedup();
+    codepoints
+}
+
+/// Check every built-in language against the cmap of `bytes`, sorted
+/// supported first, then partial by fewest missing, then unsupported.
+fn language_support(bytes: &[u8]) -> Result<Vec<LanguageSupport>, FontError> {
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let charmap = font.charmap();
+
+    let mut report: Vec<LanguageSupport> = LANGUAGES
+        .iter()
+        .map(|language| {
+            let exemplars = exemplar_codepoints(language);
+            let missing: Vec<u32> = exemplars
+                .iter()
+                .copied()
+                .filter(|&cp| charmap.map(cp).is_none())
+                .collect();
+            let status = if missing.is_empty() {
+                SupportStatus::Supported
+            } else if missing.len() < exemplars.len() {
+                SupportStatus::Partial
+            } else {
+                SupportStatus::Unsupported
+            };
+            LanguageSupport {
+                tag: language.tag.to_string(),
+                name: language.name.to_string(),
+                script: language.script.to_string(),
+                status,
+                missing: if status == SupportStatus::Partial {
+                    missing
+                } else {
+                    Vec::new()
+                },
+                exemplar_count: exemplars.len() as u32,
+            }
+        })
+        .collect();
+    report.sort_by(|a, b| {
+        (a.status, a.missing.len(), &a.name).cmp(&(b.status, b.missing.len(), &b.name))
+    });
+    Ok(report)
+}
+
+pub fn get_language_support(
+    file_path: &str,
+    cache: &FontCache,
+) -> Result<Vec<LanguageSupport>, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    language_support(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::TestFontBuilder;
+
+    #[test]
+    fn test_language_support_sorted_by_status() {
+        let mut builder = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(Vec::new(), 500);
+        for c in ('a'..='z').chain('A'..='Z').chain("äöüßÄÖÜẞåÅ".chars()) {
+            builder = builder.map(c as u32, 1);
+        }
+        let report = language_support(&builder.build()).unwrap();
+        let find = |tag: &str| report.iter().find(|l| l.tag == tag).unwrap();
+
+        assert_eq!(find("en").status, SupportStatus::Supported);
+        assert_eq!(find("de").status, SupportStatus::Supported);
+        assert_eq!(find("en").exemplar_count, 52);
+        // Swedish needs only å, ä and ö on top of a-z; Danish lacks æ and ø
+        assert_eq!(find("sv").status, SupportStatus::Supported);
+        let danish = find("da");
+        assert_eq!(danish.status, SupportStatus::Partial);
+        assert_eq!(danish.missing, vec![0xC6, 0xD8, 0xE6, 0xF8]);
+        let russian = find("ru");
+        assert_eq!(russian.status, SupportStatus::Unsupported);
+        assert!(russian.missing.is_empty());
+
+        // Supported, then partial by fewest missing, then unsupported
+        let ranks: Vec<(SupportStatus, usize)> =
+            report.iter().map(|l| (l.status, l.missing.len())).collect();
+        let mut sorted = ranks.clone();
+        sorted.sort();
+        assert_eq!(ranks, sorted);
+        assert_eq!(report.len(), LANGUAGES.len());
+    }
+
+    #[test]
+    fn test_exemplar_ranges_and_case() {
+        let greek = LANGUAGES.iter().find(|l| l.tag == "el").unwrap();
+        let codepoints = exemplar_codepoints(greek);
+        // α-ω holds final sigma, whose capital is Σ like σ's
+        assert!(codepoints.contains(&0x03C2) && codepoints.contains(&0x03A3));
+        assert!(codepoints.contains(&0x0391) && codepoints.contains(&0x03A9));
+        let turkish = LANGUAGES.iter().find(|l| l.tag == "tr").unwrap();
+        let codepoints = exemplar_codepoints(turkish);
+        assert!(codepoints.contains(&0x0130) && codepoints.contains(&0x0131));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::family::has_font_extension;
+use parking_lot::Mutex;
+use std::path::Path;
+
+/// Font files among command-line arguments. The program name and flags are
+/// skipped and relative paths resolve against `cwd`, which for a forwarded
+/// second launch is that process's directory, not ours.
+pub fn font_paths_from_args(args: impl IntoIterator<Item = String>, cwd: &Path) -> Vec<String> {
+    args.into_iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|arg| cwd.join(arg))
+        .filter(|path| has_font_extension(path) && path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Fonts handed to the app before the main window's frontend could listen
+/// for them. Once the frontend takes the queue, later paths are not queued
+/// but sent to it as events.
+pub struct LaunchQueue(Mutex<Option<Vec<String>>>);
+
+impl Default for LaunchQueue {
+    fn default() -> Self {
+        Self(Mutex::new(Some(Vec::new())))
+    }
+}
+
+impl LaunchQueue {
+    /// Queue `paths` while the frontend is loading; once it has taken the
+    /// queue they are returned for the caller to send instead.
+    pub fn queue(&self, paths: Vec<String>) -> Vec<String> {
+        match &mut *self.0.lock() {
+            Some(queued) => {
+                queued.extend(paths);
+                Vec::new()
+            }
+            None => paths,
+        }
+    }
+
+    /// Hand over the queued paths; only the first call gets any.
+    pub fn take(&self) -> Vec<String> {
+        self.0.lock().take().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_font_paths_from_args() {
+        let dir = std::env::temp_dir().join(format!("typebrew-launch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Sans.TTF"), b"").unwrap();
+        fs::write(dir.join("Serif.woff2"), b"").unwrap();
+        fs::write(dir.join("notes.txt"), b"").unwrap();
+        let absolute = dir.join("Serif.woff2").to_string_lossy().into_owned();
+        let args = ["typebrew", "--verbose", "Sans.TTF", "notes.txt", "Gone.otf"]
+            .into_iter()
+            .map(String::from)
+            .chain([absolute.clone()]);
+
+        let paths = font_paths_from_args(args, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let sans = dir.join("Sans.TTF").to_string_lossy().into_owned();
+        assert_eq!(paths, vec![sans, absolute]);
+    }
+
+    #[test]
+    fn test_launch_queue_hands_over_once() {
+        let queue = LaunchQueue::default();
+        assert!(queue.queue(vec!["a.ttf".into()]).is_empty());
+        assert!(queue.queue(vec!["b.ttf".into()]).is_empty());
+        assert_eq!(queue.take(), vec!["a.ttf", "b.ttf"]);
+        // The frontend is listening now; paths come straight back
+        assert_eq!(queue.queue(vec!["c.ttf".into()]), vec!["c.ttf"]);
+        assert!(queue.take().is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::gpos::{kern_pair_lookups, resolve_kerning};
+use crate::kern::read_kern_pairs;
+use skrifa::instance::{LocationRef, Size};
+use skrifa::raw::FontRef as RawFontRef;
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::BTreeMap;
+
+/// A glyph placed on a single horizontal line.
+#[derive(Debug, Clone)]
+pub struct PositionedGlyph {
+    pub glyph_id: u32,
+    pub ch: char,
+    /// Pen position of the glyph origin, in the units of the requested size.
+    pub x: f32,
+    pub advance: f32,
+}
+
+/// Lay out `text` on one line: map each character through cmap (unmapped
+/// characters fall back to .notdef) and place glyphs by their advances.
+/// With `round_advances` each advance is snapped to whole pixels, matching
+/// what hinted rasterizers do.
+pub fn layout_text(
+    font: &FontRef<'_>,
+    text: &str,
+    size: Size,
+    round_advances: bool,
+) -> Vec<PositionedGlyph> {
+    let charmap = font.charmap();
+    let metrics = font.glyph_metrics(size, LocationRef::default());
+
+    let mut x = 0.0f32;
+    let mut glyphs = Vec::with_capacity(text.len());
+    for ch in text.chars() {
+        let gid = charmap.map(ch).unwrap_or(GlyphId::NOTDEF);
+        let mut advance = metrics.advance_width(gid).unwrap_or(0.0);
+        if round_advances {
+            advance = advance.round();
+        }
+        glyphs.push(PositionedGlyph {
+            glyph_id: gid.to_u32(),
+            ch,
+            x,
+            advance,
+        });
+        x += advance;
+    }
+    glyphs
+}
+
+/// Total advance of a laid-out line.
+pub fn line_width(glyphs: &[PositionedGlyph]) -> f32 {
+    glyphs.last().map(|g| g.x + g.advance).unwrap_or(0.0)
+}
+
+/// Pair kerning for laying out lines: the GPOS 'kern' pair lookups when the
+/// font has any, since shapers then ignore the kern table, else kern.
+/// Contextual and class-less adjustments beyond pairs are not applied.
+pub struct Kerning<'a> {
+    font: RawFontRef<'a>,
+    lookups: BTreeMap<u16, Vec<u16>>,
+    pairs: BTreeMap<(u16, u16), i16>,
+}
+
+impl<'a> Kerning<'a> {
+    pub fn new(font: RawFontRef<'a>) -> Self {
+        let lookups = kern_pair_lookups(&font).unwrap_or_default();
+        let pairs = if lookups.is_empty() {
+            read_kern_pairs(&font)
+                .map(|(pairs, _)| pairs)
+                .unwrap_or_default()
+        } else {
+            BTreeMap::new()
+        };
+        Self {
+            font,
+            lookups,
+            pairs,
+        }
+    }
+
+    /// Adjustment in font units between two glyphs; unreadable data counts
+    /// as no kerning.
+    pub fn value(&self, left: u32, right: u32) -> i32 {
+        let (Ok(left), Ok(right)) = (u16::try_from(left), u16::try_from(right)) else {
+            return 0;
+        };
+        if self.lookups.is_empty() {
+            return self.pairs.get(&(left, right)).copied().unwrap_or(0) as i32;
+        }
+        resolve_kerning(&self.font, &self.lookups, left, right).map_or(0, |k| k.value)
+    }
+
+    /// Move glyphs laid out by [`layout_text`] by the kerning of each pair,
+    /// folded into the left glyph's advance. `scale` converts font units to
+    /// the units of the layout.
+    pub fn apply(&self, glyphs: &mut [PositionedGlyph], scale: f32) {
+        let mut shift = 0.0;
+        for i in 0..glyphs.len() {
+            glyphs[i].x += shift;
+            if let Some(next) = glyphs.get(i + 1).map(|g| g.glyph_id) {
+                let adjust = self.value(glyphs[i].glyph_id, next) as f32 * scale;
+                glyphs[i].advance += adjust;
+                shift += adjust;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{simple_glyph, TestFontBuilder};
+
+    #[test]
+    fn test_layout_text_positions_and_notdef() {
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let font_bytes = TestFontBuilder::new()
+            .glyph(Vec::new(), 300)
+            .glyph(bar, 600)
+            .map('a' as u32, 1)
+            .build();
+        let font = FontRef::new(&font_bytes).unwrap();
+
+        let glyphs = layout_text(&font, "aza", Size::unscaled(), false);
+        let ids: Vec<u32> = glyphs.iter().map(|g| g.glyph_id).collect();
+        assert_eq!(ids, vec![1, 0, 1]);
+        assert_eq!(glyphs[1].x, 600.0);
+        assert_eq!(glyphs[2].x, 900.0);
+        assert_eq!(line_width(&glyphs), 1500.0);
+
+        // 600 units at 10px/1000upem = 6px, 300 → 3px
+        let scaled = layout_text(&font, "az", Size::new(10.0), true);
+        assert_eq!(line_width(&scaled), 9.0);
+    }
+
+    #[test]
+    fn test_kerning_folds_into_advances() {
+        use crate::kern::{build_kern_table, KernPair};
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let pair = KernPair {
+            left: 1,
+            right: 2,
+            value: -100,
+        };
+        let (kern, _) = build_kern_table(&[pair], 3).unwrap();
+        let font_bytes = TestFontBuilder::new()
+            .glyph(Vec::new(), 300)
+            .glyph(bar.clone(), 600)
+            .glyph(bar, 500)
+            .map('a' as u32, 1)
+            .map('b' as u32, 2)
+            .table(b"kern", kern)
+            .build();
+        let font = FontRef::new(&font_bytes).unwrap();
+        let kerning = Kerning::new(RawFontRef::new(&font_bytes).unwrap());
+        assert_eq!(kerning.value(1, 2), -100);
+        assert_eq!(kerning.value(2, 1), 0);
+
+        let mut glyphs = layout_text(&font, "aba", Size::new(10.0), false);
+        kerning.apply(&mut glyphs, 0.01);
+        assert_eq!(glyphs[0].advance, 5.0);
+        assert_eq!(glyphs[1].x, 5.0);
+        assert_eq!(glyphs[2].x, 10.0);
+        assert_eq!(line_width(&glyphs), 16.0);
+    }
+}
@@ -1,39 +1,532 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+mod audit;
+mod cff;
+mod charmap;
+mod checks;
+mod collection;
+mod diff;
+mod error;
+mod family;
+mod fea;
 mod font_parser;
+mod gpos;
+mod gsub;
+mod hinting;
+mod instancer;
+mod kern;
+mod languages;
+mod launch;
+mod layout;
+mod merge;
+mod metrics;
+mod raster;
+mod recent;
+mod specimen;
+mod svg;
+#[cfg(test)]
+mod test_fonts;
+mod ttx;
+mod ufo;
+mod validate;
+mod woff;
+mod xml;
 
+use error::FontError;
 use font_parser::{
     CompositeOffsetUpdate, FontCache, GlyphOutlineData, HeadTableUpdate, HheaTableUpdate,
     MaxpTableUpdate, NameTableUpdate,
 };
+use launch::LaunchQueue;
+use parking_lot::Mutex;
+use recent::RecentFonts;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, Ordering};
 use tauri::ipc::Response;
-use tauri::menu::{MenuBuilder, MenuItemBuilder, SubmenuBuilder};
-use tauri::{Emitter, State};
+use tauri::menu::{
+    MenuBuilder, MenuItem, MenuItemBuilder, PredefinedMenuItem, Submenu, SubmenuBuilder,
+};
+use tauri::{
+    AppHandle, Emitter, Manager, State, WebviewUrl, WebviewWindow, WebviewWindowBuilder,
+    WindowEvent, Wry,
+};
+
+#[derive(Clone, Serialize)]
+struct FontStatusChanged {
+    file_path: String,
+    status: font_parser::FontStatus,
+}
+
+// Notify the frontend (title bar dirty dot) after every write to a font file
+fn emit_status_changed(app: &AppHandle, file_path: &str, cache: &FontCache) {
+    let _ = app.emit(
+        "font:status-changed",
+        FontStatusChanged {
+            file_path: file_path.to_string(),
+            status: cache.status(file_path),
+        },
+    );
+}
+
+#[derive(Clone, Serialize)]
+struct ExtractProgress {
+    path: String,
+    done: u32,
+    total: u32,
+}
+
+// Progress callback for a long operation on `path`; the frontend shows it as
+// a progress bar
+fn emit_progress<'a>(app: &'a AppHandle, path: &'a str) -> impl Fn(u32, u32) + 'a {
+    move |done, total| {
+        let _ = app.emit(
+            "font:extract-progress",
+            ExtractProgress {
+                path: path.to_string(),
+                done,
+                total,
+            },
+        );
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct FontChangedOnDisk {
+    file_path: String,
+    /// Whether the cache already re-read the file (reload policy).
+    reloaded: bool,
+}
+
+/// How often open fonts are checked for changes made by other programs.
+const DISK_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+// Poll open fonts for external changes and tell the frontend once per change
+fn watch_open_fonts(app: AppHandle) {
+    let mut notified: HashSet<String> = HashSet::new();
+    loop {
+        std::thread::sleep(DISK_POLL_INTERVAL);
+        let cache = app.state::<FontCache>();
+        let stale = cache.stale_paths();
+        for path in &stale {
+            if !notified.insert(path.clone()) {
+                continue;
+            }
+            // Deferred edits not committed yet are never thrown away unasked
+            let reloaded = cache.external_change_policy()
+                == font_parser::ExternalChangePolicy::Reload
+                && !cache.has_pending_changes(path)
+                && {
+                    let lock = cache.write_lock(path);
+                    let _guard = lock.lock();
+                    cache.reload(path).is_ok()
+                };
+            let _ = app.emit(
+                "font:changed-on-disk",
+                FontChangedOnDisk {
+                    file_path: path.clone(),
+                    reloaded,
+                },
+            );
+            if reloaded {
+                notified.remove(path);
+                emit_status_changed(&app, path, &cache);
+            }
+        }
+        notified.retain(|path| stale.contains(path));
+    }
+}
+
+/// Cancellation tokens of running operations, by caller-chosen operation id.
+#[derive(Default)]
+struct Operations(Mutex<HashMap<String, font_parser::CancelToken>>);
+
+impl Operations {
+    // Run `work` with a token that `cancel_operation(id)` trips; without an id
+    // the operation simply cannot be cancelled
+    fn run<T>(
+        &self,
+        id: Option<String>,
+        work: impl FnOnce(&font_parser::CancelToken) -> Result<T, FontError>,
+    ) -> Result<T, FontError> {
+        let token = font_parser::CancelToken::default();
+        if let Some(id) = &id {
+            self.0.lock().insert(id.clone(), token.clone());
+        }
+        let result = work(&token);
+        if let Some(id) = &id {
+            self.0.lock().remove(id);
+        }
+        result
+    }
+
+    /// Trip the token of `id`; false when no such operation is running.
+    fn cancel(&self, id: &str) -> bool {
+        match self.0.lock().get(id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[tauri::command]
+fn cancel_operation(operation_id: String, operations: State<Operations>) -> bool {
+    operations.cancel(&operation_id)
+}
+
+/// Font paths open in each window, by window label. A font's cache entry is
+/// only dropped once no window shows it.
+#[derive(Default)]
+struct WindowFonts(Mutex<HashMap<String, HashSet<String>>>);
+
+impl WindowFonts {
+    fn open(&self, window: &str, path: &str) {
+        let mut windows = self.0.lock();
+        windows
+            .entry(window.to_string())
+            .or_default()
+            .insert(path.to_string());
+    }
+
+    /// Forget `paths` for `window` and return those no other window has open.
+    fn close(&self, window: &str, paths: &[String]) -> Vec<String> {
+        let mut windows = self.0.lock();
+        if let Some(open) = windows.get_mut(window) {
+            for path in paths {
+                open.remove(path);
+            }
+        }
+        paths
+            .iter()
+            .filter(|path| !windows.values().any(|open| open.contains(*path)))
+            .cloned()
+            .collect()
+    }
+
+    /// Forget a closed window and return the paths only it had open.
+    fn close_window(&self, window: &str) -> Vec<String> {
+        let paths: Vec<String> = self
+            .0
+            .lock()
+            .remove(window)
+            .map(|open| open.into_iter().collect())
+            .unwrap_or_default();
+        self.close(window, &paths)
+    }
+}
+
+// Menu events go to the focused window only, so each window acts on its own font
+fn emit_to_focused<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    let focused = app
+        .webview_windows()
+        .into_values()
+        .find(|window| window.is_focused().unwrap_or(false))
+        .map(|window| window.label().to_string())
+        .unwrap_or_else(|| "main".to_string());
+    let _ = app.emit_to(focused.as_str(), event, payload);
+}
+
+// Open fonts handed over by the command line or the OS: queued while the main
+// window loads, then sent as "menu:open-font" events carrying the path
+fn open_font_paths(app: &AppHandle, paths: Vec<String>) {
+    for path in app.state::<LaunchQueue>().queue(paths) {
+        emit_to_focused(app, "menu:open-font", Some(path));
+    }
+}
+
+/// Menu id prefix of "Open Recent" items; the rest of the id is the font path.
+const RECENT_ITEM_PREFIX: &str = "recent:";
+const CLEAR_RECENT_ID: &str = "clear_recent";
+
+/// The File > Open Recent submenu, rebuilt whenever the recent list changes.
+struct RecentMenu(Submenu<Wry>);
+
+// Menus cannot be edited item by item in place, so replace every entry
+fn fill_recent_menu(
+    app: &AppHandle,
+    submenu: &Submenu<Wry>,
+    paths: &[String],
+) -> tauri::Result<()> {
+    for item in submenu.items()? {
+        submenu.remove(&item)?;
+    }
+    if paths.is_empty() {
+        let empty = MenuItemBuilder::with_id("no_recent", "No Recent Fonts")
+            .enabled(false)
+            .build(app)?;
+        submenu.append(&empty)?;
+        return Ok(());
+    }
+    for path in paths {
+        let label = std::path::Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(path);
+        let item = MenuItemBuilder::with_id(format!("{}{}", RECENT_ITEM_PREFIX, path), label)
+            .build(app)?;
+        submenu.append(&item)?;
+    }
+    submenu.append(&PredefinedMenuItem::separator(app)?)?;
+    submenu.append(&MenuItemBuilder::with_id(CLEAR_RECENT_ID, "Clear Menu").build(app)?)?;
+    Ok(())
+}
+
+fn rebuild_recent_menu(app: &AppHandle, paths: &[String]) {
+    if let Some(menu) = app.try_state::<RecentMenu>() {
+        let _ = fill_recent_menu(app, &menu.0, paths);
+    }
+}
+
+#[tauri::command]
+fn take_launch_fonts(queue: State<LaunchQueue>) -> Vec<String> {
+    queue.take()
+}
+
+#[tauri::command]
+fn get_recent_fonts(recent: State<RecentFonts>) -> Vec<String> {
+    recent.list()
+}
+
+#[tauri::command]
+fn clear_recent_fonts(recent: State<RecentFonts>, app: AppHandle) -> Result<(), FontError> {
+    recent.clear()?;
+    rebuild_recent_menu(&app, &[]);
+    Ok(())
+}
+
+// Run CPU-bound font work on the blocking pool so the window stays responsive
+// and other commands can be in flight meanwhile. Managed state is looked up
+// inside the task because `State` borrows cannot be held across the await.
+async fn run_blocking<T, F>(app: AppHandle, work: F) -> Result<T, FontError>
+where
+    T: Send + 'static,
+    F: FnOnce(&AppHandle, &FontCache) -> Result<T, FontError> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || work(&app, &app.state::<FontCache>()))
+        .await
+        .map_err(|e| FontError::other(format!("Background task failed: {:?}", e)))?
+}
+
+// Like `run_blocking`, holding the write lock of `file_path` for the whole
+// task so two edits of one file cannot interleave their read-modify-write
+async fn run_blocking_write<T, F>(
+    app: AppHandle,
+    file_path: String,
+    work: F,
+) -> Result<T, FontError>
+where
+    T: Send + 'static,
+    F: FnOnce(&AppHandle, &FontCache) -> Result<T, FontError> + Send + 'static,
+{
+    run_blocking(app, move |app, cache| {
+        let lock = cache.write_lock(&file_path);
+        let _guard = lock.lock();
+        work(app, cache)
+    })
+    .await
+}
+
+static NEXT_WINDOW: AtomicU32 = AtomicU32::new(1);
+
+// Window creation must not block the main thread, hence async
+#[tauri::command]
+async fn open_font_window(app: AppHandle, file_path: Option<String>) -> Result<String, FontError> {
+    let label = format!("font-{}", NEXT_WINDOW.fetch_add(1, Ordering::Relaxed));
+    // The new window's frontend opens this font on load
+    let script = format!(
+        "window.__TYPEBREW_INITIAL_FONT__ = {};",
+        serde_json::to_string(&file_path).map_err(|e| FontError::other(e.to_string()))?
+    );
+    WebviewWindowBuilder::new(&app, &label, WebviewUrl::App("index.html".into()))
+        .title("typebrew")
+        .inner_size(1200.0, 800.0)
+        .initialization_script(&script)
+        .build()
+        .map_err(|e| FontError::other(format!("Failed to open window: {:?}", e)))?;
+    Ok(label)
+}
+
+#[tauri::command]
+fn set_external_change_policy(policy: font_parser::ExternalChangePolicy, cache: State<FontCache>) {
+    cache.set_external_change_policy(policy);
+}
+
+#[tauri::command]
+fn set_update_head_modified(enabled: bool, cache: State<FontCache>) {
+    cache.set_update_modified(enabled);
+}
+
+#[tauri::command]
+fn set_recalc_hhea(enabled: bool, cache: State<FontCache>) {
+    cache.set_recalc_hhea(enabled);
+}
+
+#[tauri::command]
+async fn parse_font_file(
+    file_path: String,
+    font_index: Option<u32>,
+    window: WebviewWindow,
+) -> Result<font_parser::FontMetadata, FontError> {
+    let label = window.label().to_string();
+    run_blocking(window.app_handle().clone(), move |app, cache| {
+        let metadata = font_parser::parse_font(&file_path, font_index, cache)?;
+        app.state::<WindowFonts>().open(&label, &metadata.file_path);
+        // A font that opened fine is worth remembering even if saving the list fails
+        if let Ok(paths) = app.state::<RecentFonts>().add(&file_path) {
+            rebuild_recent_menu(app, &paths);
+        }
+        Ok(metadata)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn parse_font_bytes(
+    bytes: Vec<u8>,
+    display_name: String,
+    window: WebviewWindow,
+) -> Result<font_parser::FontMetadata, FontError> {
+    let label = window.label().to_string();
+    run_blocking(window.app_handle().clone(), move |app, cache| {
+        let metadata = font_parser::parse_font_bytes(bytes, &display_name, cache)?;
+        // Closed with the window like any font; there is no path to remember
+        app.state::<WindowFonts>().open(&label, &metadata.file_path);
+        Ok(metadata)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn reload_font(
+    file_path: String,
+    app: AppHandle,
+) -> Result<font_parser::FontMetadata, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let metadata = font_parser::reload_font(&file_path, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(metadata)
+    })
+    .await
+}
 
 #[tauri::command]
-fn parse_font_file(
+fn close_font(
     file_path: String,
     cache: State<FontCache>,
-) -> Result<font_parser::FontMetadata, String> {
-    font_parser::parse_font(&file_path, &cache)
+    window: WebviewWindow,
+    window_fonts: State<WindowFonts>,
+) {
+    for path in window_fonts.close(window.label(), &[file_path]) {
+        cache.remove(&path);
+    }
+}
+
+#[tauri::command]
+fn clear_all_fonts(cache: State<FontCache>) {
+    cache.clear();
+}
+
+#[tauri::command]
+fn get_cache_stats(cache: State<FontCache>) -> Vec<font_parser::CacheEntryStats> {
+    cache.stats()
+}
+
+#[tauri::command]
+fn set_cache_limit(bytes: u64, cache: State<FontCache>) {
+    cache.set_limit(bytes);
+}
+
+#[tauri::command]
+fn set_mmap_threshold(bytes: u64, cache: State<FontCache>) {
+    cache.set_mmap_threshold(bytes);
+}
+
+#[tauri::command]
+fn set_paged_outline_threshold(glyphs: u32, cache: State<FontCache>) {
+    cache.set_paged_outline_threshold(glyphs);
+}
+
+#[tauri::command]
+fn scan_font_directory(dir_path: String) -> Result<family::DirectoryScan, FontError> {
+    family::scan_font_directory(&dir_path)
 }
 
 #[tauri::command]
-fn get_font_table(
+async fn check_family_consistency(
+    file_paths: Vec<String>,
+    app: AppHandle,
+) -> Result<family::FamilyConsistencyReport, FontError> {
+    run_blocking(app, move |_, cache| {
+        family::check_family_consistency(&file_paths, cache)
+    })
+    .await
+}
+
+#[tauri::command]
+fn list_collection_fonts(
+    file_path: String,
+) -> Result<Vec<collection::CollectionMember>, FontError> {
+    Ok(collection::list_collection_fonts(&file_path)?)
+}
+
+#[tauri::command]
+async fn get_font_table(
     file_path: String,
     table_name: String,
-    cache: State<FontCache>,
-) -> Result<String, String> {
-    font_parser::get_table_content(&file_path, &table_name, &cache)
+    app: AppHandle,
+) -> Result<String, FontError> {
+    run_blocking(app, move |_, cache| {
+        font_parser::get_table_content(&file_path, &table_name, cache)
+    })
+    .await
+}
+
+#[derive(Clone, Serialize)]
+struct OutlinesExtracted {
+    path: String,
 }
 
+// A page request extracts only the outlines it needs; the rest of the font is
+// then extracted in the background so later pages come from the cache
 #[tauri::command]
-fn get_glyph_outlines(
+async fn get_glyph_outlines(
     file_path: String,
     offset: u32,
     limit: u32,
-    cache: State<FontCache>,
-) -> Result<Response, String> {
-    let bytes = font_parser::get_glyph_outlines_binary(&file_path, offset, limit, &cache)?;
+    encoding: Option<font_parser::OutlineEncoding>,
+    requested_version: Option<u16>,
+    operation_id: Option<String>,
+    app: AppHandle,
+) -> Result<Response, FontError> {
+    let bytes = run_blocking(app, move |app, cache| {
+        let progress = emit_progress(app, &file_path);
+        let page = app.state::<Operations>().run(operation_id, |cancel| {
+            font_parser::get_glyph_outlines_binary(
+                &file_path,
+                offset,
+                limit,
+                encoding.unwrap_or_default(),
+                requested_version,
+                cache,
+                &progress,
+                cancel,
+            )
+        })?;
+        let app = app.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let cache = app.state::<FontCache>();
+            if let Ok(true) = font_parser::fill_glyph_outlines(&file_path, &cache) {
+                let _ = app.emit(
+                    "font:outlines-extracted",
+                    OutlinesExtracted { path: file_path },
+                );
+            }
+        });
+        Ok(page)
+    })
+    .await?;
     Ok(Response::new(bytes))
 }
 
@@ -41,89 +534,950 @@ fn get_glyph_outlines(
 fn get_glyph_outline_data(
     file_path: String,
     glyph_id: u32,
+    location: Option<HashMap<String, f32>>,
     cache: State<FontCache>,
-) -> Result<GlyphOutlineData, String> {
-    font_parser::get_glyph_outline_data(&file_path, glyph_id, &cache)
+) -> Result<GlyphOutlineData, FontError> {
+    font_parser::get_glyph_outline_data(&file_path, glyph_id, location.as_ref(), &cache)
 }
 
 #[tauri::command]
-fn update_head_table(
+async fn update_head_table(
     file_path: String,
     updates: HeadTableUpdate,
-    cache: State<FontCache>,
-) -> Result<(), String> {
-    font_parser::update_head_table(&file_path, &updates, &cache)
+    app: AppHandle,
+) -> Result<(), FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        font_parser::update_head_table(&file_path, &updates, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(())
+    })
+    .await
 }
 
 #[tauri::command]
-fn update_hhea_table(
+async fn update_hhea_table(
     file_path: String,
     updates: HheaTableUpdate,
-    cache: State<FontCache>,
-) -> Result<(), String> {
-    font_parser::update_hhea_table(&file_path, &updates, &cache)
+    app: AppHandle,
+) -> Result<(), FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        font_parser::update_hhea_table(&file_path, &updates, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(())
+    })
+    .await
 }
 
 #[tauri::command]
-fn update_maxp_table(
+async fn update_maxp_table(
     file_path: String,
     updates: MaxpTableUpdate,
-    cache: State<FontCache>,
-) -> Result<(), String> {
-    font_parser::update_maxp_table(&file_path, &updates, &cache)
+    app: AppHandle,
+) -> Result<(), FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        font_parser::update_maxp_table(&file_path, &updates, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn update_glyph_metrics_bulk(
+    file_path: String,
+    edits: Vec<metrics::GlyphMetricsEdit>,
+    app: AppHandle,
+) -> Result<Vec<metrics::GlyphMetricsChange>, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let changes = metrics::update_glyph_metrics_bulk(&file_path, &edits, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(changes)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn update_number_of_h_metrics(
+    file_path: String,
+    new_count: u16,
+    equalize: Option<bool>,
+    app: AppHandle,
+) -> Result<(), FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        metrics::update_number_of_h_metrics(
+            &file_path,
+            new_count,
+            equalize.unwrap_or(false),
+            cache,
+        )?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn recalc_head_bbox(
+    file_path: String,
+    app: AppHandle,
+) -> Result<font_parser::GlyphBounds, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let bounds = font_parser::recalc_head_bbox(&file_path, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(bounds)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn recalc_hhea(file_path: String, app: AppHandle) -> Result<metrics::HheaExtents, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let extents = metrics::recalc_hhea(&file_path, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(extents)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn set_monospaced(
+    file_path: String,
+    advance_width: u16,
+    center: bool,
+    app: AppHandle,
+) -> Result<metrics::MonospaceReport, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let report = metrics::set_monospaced(&file_path, advance_width, center, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(report)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn clear_monospaced(file_path: String, app: AppHandle) -> Result<(), FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        metrics::clear_monospaced(&file_path, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn check_vertical_metrics(
+    file_path: String,
+    app: AppHandle,
+) -> Result<metrics::VerticalMetrics, FontError> {
+    run_blocking(app, move |_, cache| {
+        metrics::check_vertical_metrics(&file_path, cache)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn apply_vertical_metrics_strategy(
+    file_path: String,
+    strategy: metrics::VerticalMetricsStrategy,
+    app: AppHandle,
+) -> Result<metrics::VerticalMetrics, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let metrics = metrics::apply_vertical_metrics_strategy(&file_path, strategy, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(metrics)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn autospace_glyphs(
+    file_path: String,
+    rules: Vec<metrics::SpacingRule>,
+    app: AppHandle,
+) -> Result<Vec<metrics::AutospaceChange>, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let changes = metrics::autospace_glyphs(&file_path, &rules, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(changes)
+    })
+    .await
 }
 
 #[tauri::command]
-fn update_name_table(
+async fn suggest_decoration_metrics(
+    file_path: String,
+    app: AppHandle,
+) -> Result<metrics::DecorationReport, FontError> {
+    run_blocking(app, move |_, cache| {
+        metrics::suggest_decoration_metrics(&file_path, cache)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn update_decoration_metrics(
+    file_path: String,
+    underline_position: i16,
+    underline_thickness: i16,
+    strikeout_size: i16,
+    strikeout_position: i16,
+    app: AppHandle,
+) -> Result<metrics::DecorationReport, FontError> {
+    let decoration = metrics::DecorationMetrics {
+        underline_position,
+        underline_thickness,
+        strikeout_size,
+        strikeout_position,
+    };
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let report = metrics::update_decoration_metrics(&file_path, decoration, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(report)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn suggest_sub_superscript_metrics(
+    file_path: String,
+    app: AppHandle,
+) -> Result<metrics::ScriptMetricsReport, FontError> {
+    run_blocking(app, move |_, cache| {
+        metrics::suggest_sub_superscript_metrics(&file_path, cache)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn update_sub_superscript_metrics(
+    file_path: String,
+    metrics: metrics::ScriptMetrics,
+    app: AppHandle,
+) -> Result<metrics::ScriptMetricsReport, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let report = metrics::update_sub_superscript_metrics(&file_path, metrics, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(report)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn create_kern_from_list(
+    file_path: String,
+    pairs: Vec<kern::KernPair>,
+    app: AppHandle,
+) -> Result<kern::KernTableSummary, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let summary = kern::create_kern_from_list(&file_path, &pairs, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(summary)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn convert_kern_to_gpos(
+    file_path: String,
+    app: AppHandle,
+) -> Result<kern::KernConversionSummary, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let summary = kern::convert_kern_to_gpos(&file_path, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(summary)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn update_name_table(
     file_path: String,
     updates: NameTableUpdate,
-    cache: State<FontCache>,
-) -> Result<(), String> {
-    font_parser::update_name_table(&file_path, &updates, &cache)
+    app: AppHandle,
+) -> Result<(), FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        font_parser::update_name_table(&file_path, &updates, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(())
+    })
+    .await
 }
 
 #[tauri::command]
-fn save_glyph_outline(
+#[allow(clippy::too_many_arguments)]
+async fn save_glyph_outline(
     file_path: String,
     glyph_id: u32,
     svg_path: String,
     table_name: String,
-    cache: State<FontCache>,
-) -> Result<(), String> {
+    advance_width: Option<u16>,
+    unicode: Option<u32>,
+    overwrite_mapping: Option<bool>,
+    normalize: Option<font_parser::OutlineNormalization>,
+    repair_loca: Option<bool>,
+    app: AppHandle,
+) -> Result<Vec<String>, FontError> {
     let args = font_parser::SaveGlyphOutlineArgs {
         glyph_id,
         svg_path,
         table_name,
+        advance_width,
+        unicode,
+        overwrite_mapping: overwrite_mapping.unwrap_or(false),
+        normalize,
+        repair_loca: repair_loca.unwrap_or(false),
     };
-    font_parser::save_glyph_outline(&file_path, &args, &cache)
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let warnings = font_parser::save_glyph_outline(&file_path, &args, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(warnings)
+    })
+    .await
 }
 
 #[tauri::command]
 fn check_font_hinting(
     file_path: String,
     cache: State<FontCache>,
-) -> Result<font_parser::HintingInfo, String> {
+) -> Result<font_parser::HintingInfo, FontError> {
     font_parser::check_font_hinting(&file_path, &cache)
 }
 
+#[tauri::command]
+fn get_glyph_source_info(
+    file_path: String,
+    glyph_id: u32,
+    cache: State<FontCache>,
+) -> Result<font_parser::GlyphSourceInfo, FontError> {
+    font_parser::get_glyph_source_info(&file_path, glyph_id, &cache)
+}
+
+#[tauri::command]
+fn get_language_support(
+    file_path: String,
+    cache: State<FontCache>,
+) -> Result<Vec<languages::LanguageSupport>, FontError> {
+    languages::get_language_support(&file_path, &cache)
+}
+
+#[tauri::command]
+fn get_ligatures(
+    file_path: String,
+    include_paths: Option<bool>,
+    cache: State<FontCache>,
+) -> Result<gsub::LigatureReport, FontError> {
+    gsub::get_ligatures(&file_path, include_paths.unwrap_or(false), &cache)
+}
+
+#[tauri::command]
+fn diff_glyph_outlines(
+    path_a: String,
+    path_b: String,
+    glyph_matching: diff::GlyphMatching,
+    options: Option<diff::GlyphDiffOptions>,
+    cache: State<FontCache>,
+) -> Result<diff::GlyphDiffReport, FontError> {
+    diff::diff_glyph_outlines(
+        &path_a,
+        &path_b,
+        glyph_matching,
+        &options.unwrap_or_default(),
+        &cache,
+    )
+}
+
 #[tauri::command]
 fn get_hinted_glyph_outlines(
     file_path: String,
     glyph_id: u32,
     px_sizes: Vec<f32>,
+    settings: Option<font_parser::HintingSettings>,
+    honor_gasp: Option<bool>,
+    cache: State<FontCache>,
+) -> Result<font_parser::HintedGlyphOutlines, FontError> {
+    font_parser::get_hinted_glyph_outlines(
+        &file_path,
+        glyph_id,
+        px_sizes,
+        settings.unwrap_or_default(),
+        honor_gasp.unwrap_or(false),
+        &cache,
+    )
+}
+
+#[tauri::command]
+fn compare_hinted_unhinted(
+    file_path: String,
+    glyph_id: u32,
+    ppem: f32,
+    cache: State<FontCache>,
+) -> Result<font_parser::HintedComparison, FontError> {
+    font_parser::compare_hinted_unhinted(&file_path, glyph_id, ppem, &cache)
+}
+
+#[tauri::command]
+fn get_gasp_behavior(
+    file_path: String,
+    ppem: u16,
+    cache: State<FontCache>,
+) -> Result<hinting::GaspBehavior, FontError> {
+    hinting::get_gasp_behavior(&file_path, ppem, &cache)
+}
+
+#[tauri::command]
+fn get_gasp_behaviors(
+    file_path: String,
+    min_ppem: u16,
+    max_ppem: u16,
     cache: State<FontCache>,
-) -> Result<Vec<String>, String> {
-    font_parser::get_hinted_glyph_outlines(&file_path, glyph_id, px_sizes, &cache)
+) -> Result<Vec<hinting::GaspBehavior>, FontError> {
+    hinting::get_gasp_behaviors(&file_path, min_ppem, max_ppem, &cache)
+}
+
+#[tauri::command]
+async fn update_glyph_instructions(
+    file_path: String,
+    glyph_id: u32,
+    instructions: String,
+    format: hinting::InstructionFormat,
+    app: AppHandle,
+) -> Result<hinting::InstructionUpdate, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let update =
+            hinting::update_glyph_instructions(&file_path, glyph_id, &instructions, format, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(update)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn autohint_font(
+    file_path: String,
+    options: hinting::AutohintOptions,
+    app: AppHandle,
+) -> Result<hinting::AutohintReport, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let report = hinting::autohint_font(&file_path, &options, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(report)
+    })
+    .await
 }
 
 #[tauri::command]
-fn update_composite_offsets(
+async fn update_composite_offsets(
     file_path: String,
     composite_glyph_id: u32,
     components: Vec<CompositeOffsetUpdate>,
+    repair_loca: Option<bool>,
+    app: AppHandle,
+) -> Result<Vec<String>, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let warnings = font_parser::update_composite_offsets(
+            &file_path,
+            composite_glyph_id,
+            components,
+            repair_loca.unwrap_or(false),
+            cache,
+        )?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(warnings)
+    })
+    .await
+}
+
+#[tauri::command]
+fn render_glyph_png(
+    file_path: String,
+    glyph_id: u32,
+    ppem: f32,
+    options: Option<raster::RenderOptions>,
+    cache: State<FontCache>,
+) -> Result<Response, FontError> {
+    let options = options.unwrap_or_default();
+    let bytes = raster::render_glyph_png(&file_path, glyph_id, ppem, &options, &cache)?;
+    Ok(Response::new(bytes))
+}
+
+#[tauri::command]
+fn render_hinted_glyph_grid(
+    file_path: String,
+    glyph_id: u32,
+    ppem: f32,
+    mode: Option<raster::GridMode>,
+    honor_gasp: Option<bool>,
+    cache: State<FontCache>,
+) -> Result<raster::HintedGlyphGrid, FontError> {
+    Ok(raster::render_hinted_glyph_grid(
+        &file_path,
+        glyph_id,
+        ppem,
+        mode.unwrap_or_default(),
+        honor_gasp.unwrap_or(false),
+        &cache,
+    )?)
+}
+
+#[tauri::command]
+async fn get_waterfall(
+    file_path: String,
+    text: String,
+    sizes: Vec<f32>,
+    mode: Option<raster::WaterfallMode>,
+    hinted: Option<bool>,
+    app: AppHandle,
+) -> Result<Vec<raster::WaterfallLine>, FontError> {
+    run_blocking(app, move |_, cache| {
+        Ok(raster::get_waterfall(
+            &file_path,
+            &text,
+            &sizes,
+            mode.unwrap_or_default(),
+            hinted.unwrap_or(true),
+            cache,
+        )?)
+    })
+    .await
+}
+
+#[tauri::command]
+fn get_glyph_anchors(
+    file_path: String,
+    glyph_id: u32,
     cache: State<FontCache>,
-) -> Result<(), String> {
-    font_parser::update_composite_offsets(&file_path, composite_glyph_id, components, &cache)
+) -> Result<Vec<gpos::GlyphAnchor>, FontError> {
+    Ok(gpos::get_glyph_anchors(&file_path, glyph_id, &cache)?)
+}
+
+#[tauri::command]
+fn get_gpos_kerning(
+    file_path: String,
+    left_glyph: u16,
+    right_glyph: u16,
+    cache: State<FontCache>,
+) -> Result<gpos::GposKerning, FontError> {
+    gpos::get_gpos_kerning(&file_path, left_glyph, right_glyph, &cache)
+}
+
+#[tauri::command]
+async fn update_gpos_kerning(
+    file_path: String,
+    pairs: Vec<kern::KernPair>,
+    app: AppHandle,
+) -> Result<Vec<gpos::GposKerning>, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let results = gpos::update_gpos_kerning(&file_path, &pairs, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(results)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn audit_unreachable_glyphs(
+    file_path: String,
+    app: AppHandle,
+) -> Result<audit::UnreachableGlyphReport, FontError> {
+    run_blocking(app, move |_, cache| {
+        Ok(audit::audit_unreachable_glyphs(&file_path, cache)?)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn audit_outline_quality(
+    file_path: String,
+    app: AppHandle,
+) -> Result<audit::OutlineQualityReport, FontError> {
+    run_blocking(app, move |_, cache| {
+        audit::audit_outline_quality(&file_path, cache)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn run_font_checks(
+    file_path: String,
+    profile: checks::CheckProfile,
+    app: AppHandle,
+) -> Result<checks::CheckReport, FontError> {
+    run_blocking(app, move |_, cache| {
+        checks::run_font_checks(&file_path, profile, cache)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn check_required_glyphs(
+    file_path: String,
+    options: Option<checks::RequiredGlyphsOptions>,
+    app: AppHandle,
+) -> Result<checks::RequiredGlyphsReport, FontError> {
+    let options = options.unwrap_or_default();
+    run_blocking(app, move |_, cache| {
+        checks::check_required_glyphs(&file_path, &options, cache)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn validate_font(
+    file_path: String,
+    app: AppHandle,
+) -> Result<validate::ValidationReport, FontError> {
+    run_blocking(app, move |_, cache| {
+        Ok(validate::validate_font(&file_path, cache)?)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn verify_checksums(
+    file_path: String,
+    app: AppHandle,
+) -> Result<validate::ChecksumReport, FontError> {
+    run_blocking(app, move |_, cache| {
+        Ok(validate::verify_checksums(&file_path, cache)?)
+    })
+    .await
+}
+
+#[tauri::command]
+fn get_cff_charstring(
+    file_path: String,
+    glyph_id: u32,
+    expand_subrs: Option<bool>,
+    cache: State<FontCache>,
+) -> Result<Vec<cff::CharstringOp>, FontError> {
+    Ok(cff::get_cff_charstring(
+        &file_path,
+        glyph_id,
+        expand_subrs.unwrap_or(false),
+        &cache,
+    )?)
+}
+
+#[tauri::command]
+fn get_glyph_for_char(
+    file_path: String,
+    text: String,
+    cache: State<FontCache>,
+) -> Result<Vec<font_parser::CharGlyphMapping>, FontError> {
+    font_parser::get_glyph_for_char(&file_path, &text, &cache)
+}
+
+#[tauri::command]
+async fn save_font_as(
+    file_path: String,
+    destination_path: String,
+    overwrite: Option<bool>,
+    app: AppHandle,
+) -> Result<font_parser::FontMetadata, FontError> {
+    run_blocking_write(app, destination_path.clone(), move |app, cache| {
+        let metadata = font_parser::save_font_as(
+            &file_path,
+            &destination_path,
+            overwrite.unwrap_or(false),
+            cache,
+        )?;
+        emit_status_changed(app, &destination_path, cache);
+        Ok(metadata)
+    })
+    .await
+}
+
+// File > Save: commits edits held back in deferred mode. Direct edits are
+// already on disk, and a font without a file needs Save As
+#[tauri::command]
+async fn flush_pending_edits(
+    file_path: String,
+    app: AppHandle,
+) -> Result<font_parser::FontStatus, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let status = cache.status(&file_path);
+        if !status.pending_changes || status.in_memory {
+            return Ok(status);
+        }
+        let status = font_parser::commit_font(&file_path, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(status)
+    })
+    .await
+}
+
+#[tauri::command]
+fn set_edit_mode(
+    file_path: String,
+    mode: font_parser::EditMode,
+    app: AppHandle,
+) -> Result<font_parser::FontStatus, FontError> {
+    let cache = app.state::<FontCache>();
+    cache.set_edit_mode(&file_path, mode)?;
+    emit_status_changed(&app, &file_path, &cache);
+    Ok(cache.status(&file_path))
+}
+
+#[tauri::command]
+async fn commit_font(
+    file_path: String,
+    app: AppHandle,
+) -> Result<font_parser::FontStatus, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let status = font_parser::commit_font(&file_path, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(status)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn discard_changes(
+    file_path: String,
+    app: AppHandle,
+) -> Result<font_parser::FontStatus, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let status = font_parser::discard_changes(&file_path, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(status)
+    })
+    .await
+}
+
+/// Menu items the frontend greys out while no font is open (or the open font
+/// cannot be exported that way), by menu id.
+struct ToggleableMenuItems(HashMap<String, MenuItem<Wry>>);
+
+#[tauri::command]
+fn set_menu_item_enabled(
+    id: String,
+    enabled: bool,
+    items: State<ToggleableMenuItems>,
+) -> Result<(), FontError> {
+    let item = items
+        .0
+        .get(&id)
+        .ok_or_else(|| FontError::input(format!("Unknown menu item: {}", id)))?;
+    item.set_enabled(enabled)
+        .map_err(|e| FontError::other(format!("Failed to update menu item: {:?}", e)))
+}
+
+#[tauri::command]
+fn get_font_status(file_path: String, cache: State<FontCache>) -> font_parser::FontStatus {
+    cache.status(&file_path)
+}
+
+#[tauri::command]
+async fn revert_font(
+    file_path: String,
+    app: AppHandle,
+) -> Result<font_parser::FontStatus, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let status = font_parser::revert_font(&file_path, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(status)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_woff(
+    file_path: String,
+    destination: String,
+    app: AppHandle,
+) -> Result<(), FontError> {
+    run_blocking_write(app, destination.clone(), move |_, cache| {
+        Ok(woff::export_woff(&file_path, &destination, cache)?)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_glyph_svgs(
+    file_path: String,
+    destination_dir: String,
+    operation_id: Option<String>,
+    app: AppHandle,
+) -> Result<u32, FontError> {
+    run_blocking(app, move |app, cache| {
+        let progress = emit_progress(app, &file_path);
+        app.state::<Operations>().run(operation_id, |cancel| {
+            svg::export_glyph_svgs(&file_path, &destination_dir, cache, &progress, cancel)
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_woff2(
+    file_path: String,
+    destination: String,
+    app: AppHandle,
+) -> Result<(), FontError> {
+    run_blocking_write(app, destination.clone(), move |_, cache| {
+        Ok(woff::export_woff2(&file_path, &destination, cache)?)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_ttc(
+    font_paths: Vec<String>,
+    destination: String,
+    app: AppHandle,
+) -> Result<(), FontError> {
+    run_blocking_write(app, destination.clone(), move |_, cache| {
+        Ok(collection::export_ttc(&font_paths, &destination, cache)?)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_ttx(
+    file_path: String,
+    destination: String,
+    tables: Option<Vec<String>>,
+    app: AppHandle,
+) -> Result<(), FontError> {
+    run_blocking_write(app, destination.clone(), move |_, cache| {
+        Ok(ttx::export_ttx(
+            &file_path,
+            &destination,
+            tables.as_deref(),
+            cache,
+        )?)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_features(
+    file_path: String,
+    destination: String,
+    app: AppHandle,
+) -> Result<Vec<String>, FontError> {
+    run_blocking_write(app, destination.clone(), move |_, cache| {
+        fea::export_features(&file_path, &destination, cache)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_character_map(
+    file_path: String,
+    destination: String,
+    format: charmap::CharacterMapFormat,
+    options: Option<charmap::CharacterMapOptions>,
+    app: AppHandle,
+) -> Result<charmap::CharacterMapSummary, FontError> {
+    run_blocking_write(app, destination.clone(), move |_, cache| {
+        charmap::export_character_map(
+            &file_path,
+            &destination,
+            format,
+            &options.unwrap_or_default(),
+            cache,
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_specimen(
+    file_path: String,
+    destination: String,
+    options: Option<specimen::SpecimenOptions>,
+    app: AppHandle,
+) -> Result<specimen::SpecimenSize, FontError> {
+    run_blocking_write(app, destination.clone(), move |_, cache| {
+        specimen::export_specimen(
+            &file_path,
+            &destination,
+            &options.unwrap_or_default(),
+            cache,
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+async fn import_features(
+    file_path: String,
+    fea_source: String,
+    options: fea::FeatureImportOptions,
+    app: AppHandle,
+) -> Result<fea::FeatureImportReport, FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        let report = fea::import_features(&file_path, &fea_source, &options, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(report)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn import_ttx_table(
+    file_path: String,
+    table_tag: String,
+    ttx_xml: String,
+    app: AppHandle,
+) -> Result<(), FontError> {
+    run_blocking_write(app, file_path.clone(), move |app, cache| {
+        ttx::import_ttx_table(&file_path, &table_tag, &ttx_xml, cache)?;
+        emit_status_changed(app, &file_path, cache);
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn merge_fonts(
+    base_path: String,
+    addition_path: String,
+    destination: String,
+    options: merge::MergeOptions,
+    app: AppHandle,
+) -> Result<merge::MergeReport, FontError> {
+    run_blocking_write(app, destination.clone(), move |_, cache| {
+        Ok(merge::merge_fonts(
+            &base_path,
+            &addition_path,
+            &destination,
+            &options,
+            cache,
+        )?)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn instantiate_variable_font(
+    file_path: String,
+    destination: String,
+    axis_values: HashMap<String, f32>,
+    app: AppHandle,
+) -> Result<(), FontError> {
+    run_blocking_write(app, destination.clone(), move |_, cache| {
+        Ok(instancer::instantiate_variable_font(
+            &file_path,
+            &destination,
+            &axis_values,
+            cache,
+        )?)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn import_ufo(
+    source_dir: String,
+    destination_ttf: String,
+    app: AppHandle,
+) -> Result<Vec<String>, FontError> {
+    run_blocking_write(app, destination_ttf.clone(), move |_, _| {
+        Ok(ufo::import_ufo(&source_dir, &destination_ttf)?)
+    })
+    .await
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -131,18 +1485,90 @@ pub fn run() {
     // Initialize font cache
     let font_cache = FontCache::new();
 
-    tauri::Builder::default()
+    let builder = tauri::Builder::default();
+    // A second launch (opening a file while running) hands its arguments
+    // over to this instance and exits; the plugin has to come first
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+        open_font_paths(
+            app,
+            launch::font_paths_from_args(args, std::path::Path::new(&cwd)),
+        );
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.unminimize();
+            let _ = window.set_focus();
+        }
+    }));
+
+    builder
         .manage(font_cache)
+        .manage(WindowFonts::default())
+        .manage(Operations::default())
+        .manage(LaunchQueue::default())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .setup(|app| {
+            let recent = RecentFonts::load(app.path().app_config_dir()?.join("recent_fonts.json"));
+            let open_recent = SubmenuBuilder::new(app, "Open Recent").build()?;
+            fill_recent_menu(app.handle(), &open_recent, &recent.list())?;
+            app.manage(recent);
+            app.manage(RecentMenu(open_recent.clone()));
+
             let open_font = MenuItemBuilder::with_id("open_font", "Open Font…")
                 .accelerator("CmdOrCtrl+O")
                 .build(app)?;
+            let new_window = MenuItemBuilder::with_id("new_window", "Open in New Window")
+                .accelerator("CmdOrCtrl+Shift+N")
+                .build(app)?;
+            let import_ufo = MenuItemBuilder::with_id("import_ufo", "Import UFO…").build(app)?;
+            let import_ttx =
+                MenuItemBuilder::with_id("import_ttx", "Import TTX Table…").build(app)?;
+            let merge_font = MenuItemBuilder::with_id("merge_font", "Merge Font…").build(app)?;
+            // Save items start disabled; the start page has no font to save
+            let save_font = MenuItemBuilder::with_id("save_font", "Save")
+                .accelerator("CmdOrCtrl+S")
+                .enabled(false)
+                .build(app)?;
+            let save_font_as = MenuItemBuilder::with_id("save_font_as", "Save As…")
+                .accelerator("CmdOrCtrl+Shift+S")
+                .enabled(false)
+                .build(app)?;
+            let export_woff = MenuItemBuilder::with_id("export_woff", "WOFF…")
+                .enabled(false)
+                .build(app)?;
+            let export_woff2 = MenuItemBuilder::with_id("export_woff2", "WOFF2…")
+                .enabled(false)
+                .build(app)?;
+            let export_svg = MenuItemBuilder::with_id("export_svg", "Glyphs as SVG…")
+                .enabled(false)
+                .build(app)?;
+            let export_ttx = MenuItemBuilder::with_id("export_ttx", "TTX…")
+                .enabled(false)
+                .build(app)?;
+            let export_ttc = MenuItemBuilder::with_id("export_ttc", "Collection (TTC)…")
+                .enabled(false)
+                .build(app)?;
+
+            let export_menu = SubmenuBuilder::new(app, "Export")
+                .item(&export_woff)
+                .item(&export_woff2)
+                .item(&export_svg)
+                .item(&export_ttx)
+                .separator()
+                .item(&export_ttc)
+                .build()?;
 
             let file_menu = SubmenuBuilder::new(app, "File")
                 .item(&open_font)
+                .item(&open_recent)
+                .item(&new_window)
+                .item(&import_ufo)
+                .item(&import_ttx)
+                .item(&merge_font)
+                .item(&save_font)
+                .item(&save_font_as)
+                .item(&export_menu)
                 .separator()
                 .quit()
                 .build()?;
@@ -150,29 +1576,180 @@ pub fn run() {
             let menu = MenuBuilder::new(app).item(&file_menu).build()?;
 
             app.set_menu(menu)?;
+            app.manage(ToggleableMenuItems(HashMap::from(
+                [
+                    &save_font,
+                    &save_font_as,
+                    &export_woff,
+                    &export_woff2,
+                    &export_svg,
+                    &export_ttx,
+                    &export_ttc,
+                ]
+                .map(|item| (item.id().as_ref().to_string(), item.clone())),
+            )));
 
             app.on_menu_event(move |app_handle, event| {
                 if event.id() == open_font.id() {
-                    let _ = app_handle.emit("menu:open-font", ());
+                    emit_to_focused(app_handle, "menu:open-font", None::<String>);
+                } else if event.id() == new_window.id() {
+                    emit_to_focused(app_handle, "menu:open-in-new-window", ());
+                } else if event.id() == import_ufo.id() {
+                    emit_to_focused(app_handle, "menu:import-ufo", ());
+                } else if event.id() == import_ttx.id() {
+                    emit_to_focused(app_handle, "menu:import-ttx", ());
+                } else if event.id() == merge_font.id() {
+                    emit_to_focused(app_handle, "menu:merge-font", ());
+                } else if event.id() == save_font.id() {
+                    emit_to_focused(app_handle, "menu:save-font", ());
+                } else if event.id() == save_font_as.id() {
+                    emit_to_focused(app_handle, "menu:save-font-as", ());
+                } else if event.id() == export_woff.id() {
+                    emit_to_focused(app_handle, "menu:export-font", "woff");
+                } else if event.id() == export_woff2.id() {
+                    emit_to_focused(app_handle, "menu:export-font", "woff2");
+                } else if event.id() == export_svg.id() {
+                    emit_to_focused(app_handle, "menu:export-font", "svg");
+                } else if event.id() == export_ttx.id() {
+                    emit_to_focused(app_handle, "menu:export-font", "ttx");
+                } else if event.id() == export_ttc.id() {
+                    emit_to_focused(app_handle, "menu:export-font", "ttc");
+                } else if let Some(path) = event.id().as_ref().strip_prefix(RECENT_ITEM_PREFIX) {
+                    emit_to_focused(app_handle, "menu:open-recent", path);
+                } else if event.id().as_ref() == CLEAR_RECENT_ID {
+                    if app_handle.state::<RecentFonts>().clear().is_ok() {
+                        rebuild_recent_menu(app_handle, &[]);
+                    }
                 }
             });
 
+            let cwd = std::env::current_dir().unwrap_or_default();
+            open_font_paths(
+                app.handle(),
+                launch::font_paths_from_args(std::env::args(), &cwd),
+            );
+
+            let handle = app.handle().clone();
+            std::thread::spawn(move || watch_open_fonts(handle));
+
             Ok(())
         })
+        .on_window_event(|window, event| {
+            if let WindowEvent::Destroyed = event {
+                let cache = window.state::<FontCache>();
+                for path in window.state::<WindowFonts>().close_window(window.label()) {
+                    cache.remove(&path);
+                }
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             parse_font_file,
+            parse_font_bytes,
+            reload_font,
+            close_font,
+            clear_all_fonts,
+            get_cache_stats,
+            set_cache_limit,
+            set_mmap_threshold,
+            set_paged_outline_threshold,
+            set_external_change_policy,
+            set_update_head_modified,
+            set_recalc_hhea,
+            cancel_operation,
+            open_font_window,
+            take_launch_fonts,
+            get_recent_fonts,
+            clear_recent_fonts,
             get_font_table,
             get_glyph_outlines,
             get_glyph_outline_data,
             update_head_table,
             update_hhea_table,
             update_maxp_table,
+            update_glyph_metrics_bulk,
+            update_number_of_h_metrics,
+            recalc_hhea,
+            recalc_head_bbox,
+            set_monospaced,
+            clear_monospaced,
+            check_vertical_metrics,
+            apply_vertical_metrics_strategy,
+            autospace_glyphs,
+            suggest_decoration_metrics,
+            update_decoration_metrics,
+            suggest_sub_superscript_metrics,
+            update_sub_superscript_metrics,
+            create_kern_from_list,
+            convert_kern_to_gpos,
             update_name_table,
             save_glyph_outline,
             update_composite_offsets,
             check_font_hinting,
-            get_hinted_glyph_outlines
+            get_glyph_source_info,
+            get_language_support,
+            get_ligatures,
+            diff_glyph_outlines,
+            get_hinted_glyph_outlines,
+            compare_hinted_unhinted,
+            get_gasp_behavior,
+            get_gasp_behaviors,
+            update_glyph_instructions,
+            autohint_font,
+            render_glyph_png,
+            render_hinted_glyph_grid,
+            get_waterfall,
+            get_glyph_anchors,
+            get_gpos_kerning,
+            update_gpos_kerning,
+            audit_unreachable_glyphs,
+            audit_outline_quality,
+            validate_font,
+            run_font_checks,
+            check_required_glyphs,
+            verify_checksums,
+            get_cff_charstring,
+            get_glyph_for_char,
+            save_font_as,
+            flush_pending_edits,
+            set_edit_mode,
+            commit_font,
+            discard_changes,
+            set_menu_item_enabled,
+            get_font_status,
+            revert_font,
+            export_woff,
+            export_woff2,
+            export_glyph_svgs,
+            list_collection_fonts,
+            scan_font_directory,
+            check_family_consistency,
+            export_ttc,
+            export_ttx,
+            export_features,
+            import_features,
+            export_character_map,
+            export_specimen,
+            import_ttx_table,
+            merge_fonts,
+            instantiate_variable_font,
+            import_ufo
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Finder hands over files (double-click, Open With) as URLs,
+            // both at launch and while running
+            #[cfg(target_os = "macos")]
+            if let tauri::RunEvent::Opened { urls } = event {
+                let paths = urls
+                    .into_iter()
+                    .filter_map(|url| url.to_file_path().ok())
+                    .filter(|path| family::has_font_extension(path))
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect();
+                open_font_paths(app, paths);
+            }
+            #[cfg(not(target_os = "macos"))]
+            let _ = (app, event);
+        });
 }
@@ -0,0 +1,649 @@
+use crate::error::FontError;
+use crate::font_parser::{get_font_bytes, write_atomically, FontCache};
+use crate::woff::encode_simple_glyph;
+use serde::{Deserialize, Serialize};
+use skrifa::raw::tables::glyf::Glyph;
+use skrifa::raw::types::Tag;
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::{BTreeSet, HashMap};
+use write_fonts::from_obj::ToOwnedTable;
+use write_fonts::tables::cmap::{Cmap, CmapSubtable};
+use write_fonts::tables::head::Head;
+use write_fonts::tables::hhea::Hhea;
+use write_fonts::tables::hmtx::{Hmtx, LongMetric};
+use write_fonts::tables::maxp::Maxp;
+use write_fonts::tables::os2::Os2;
+use write_fonts::tables::post::Post;
+use write_fonts::types::{FWord, UfWord};
+use write_fonts::FontBuilder;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct MergeOptions {
+    /// Import glyphs whose name already exists in the base under a new name
+    /// ("a.1") instead of skipping them.
+    #[serde(default)]
+    pub rename_collisions: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct MergeReport {
+    /// Glyphs appended to the base, including components pulled in by composites.
+    pub imported: usize,
+    /// Glyphs left out because the base already has a glyph with that name.
+    pub skipped: usize,
+    /// Imported glyphs that were given a new name.
+    pub renamed: usize,
+    /// Imported glyphs rescaled to the base's units per em.
+    pub scaled: usize,
+    /// Codepoints newly mapped in the merged cmap.
+    pub mapped_codepoints: usize,
+    /// Per-glyph tables of the base that no longer match and were dropped.
+    pub dropped_tables: Vec<String>,
+}
+
+/// Tables indexed by glyph ID that cannot simply be extended; the merged
+/// font goes without them. DSIG no longer matches the changed bytes.
+const DROPPED_TABLES: [&[u8; 4]; 6] = [b"hdmx", b"LTSH", b"VDMX", b"vhea", b"vmtx", b"DSIG"];
+
+/// Where an addition glyph ends up in the merged font.
+#[derive(Clone, Copy)]
+enum Placement {
+    Appended(u16),
+    /// Skipped for a name collision; composites use the base glyph instead.
+    Base(u16),
+}
+
+/// Rewrite composite component records with new glyph IDs and x/y offsets
+/// scaled by `scale`. Instructions are dropped since they depend on the
+/// source font's fpgm and cvt.
+fn rewrite_composite(
+    data: &[u8],
+    placement: &HashMap<u16, Placement>,
+    scale: f64,
+) -> Result<Vec<u8>, FontError> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+    const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+    let truncated = || FontError::malformed("glyf", "Composite glyph record is truncated");
+    let read_u16 = |pos: usize| {
+        data.get(pos..pos + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(truncated)
+    };
+    let scaled = |v: i32| (v as f64 * scale).round() as i16;
+
+    let mut out = data.get(..10).ok_or_else(truncated)?.to_vec();
+    for i in 0..4 {
+        let v = read_u16(2 + i * 2)? as i16;
+        out[2 + i * 2..4 + i * 2].copy_from_slice(&scaled(v as i32).to_be_bytes());
+    }
+    let mut pos = 10;
+    loop {
+        let flags = read_u16(pos)?;
+        let gid = read_u16(pos + 2)?;
+        let new_gid = match placement.get(&gid) {
+            Some(Placement::Appended(g) | Placement::Base(g)) => *g,
+            None => {
+                return Err(FontError::malformed(
+                    "glyf",
+                    format!("Component glyph {} was not imported", gid),
+                ))
+            }
+        };
+        pos += 4;
+        let words = flags & ARG_1_AND_2_ARE_WORDS != 0;
+        let args = data
+            .get(pos..pos + if words { 4 } else { 2 })
+            .ok_or_else(truncated)?;
+        pos += args.len();
+        let mut new_flags = flags & !WE_HAVE_INSTRUCTIONS;
+        let new_args = if flags & ARGS_ARE_XY_VALUES != 0 {
+            let (dx, dy) = if words {
+                (
+                    i16::from_be_bytes([args[0], args[1]]) as i32,
+                    i16::from_be_bytes([args[2], args[3]]) as i32,
+                )
+            } else {
+                (args[0] as i8 as i32, args[1] as i8 as i32)
+            };
+            new_flags |= ARG_1_AND_2_ARE_WORDS;
+            [scaled(dx).to_be_bytes(), scaled(dy).to_be_bytes()].concat()
+        } else {
+            // Point numbers are unaffected by scaling
+            args.to_vec()
+        };
+        let transform_len = if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            8
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            4
+        } else if flags & WE_HAVE_A_SCALE != 0 {
+            2
+        } else {
+            0
+        };
+        let transform = data.get(pos..pos + transform_len).ok_or_else(truncated)?;
+        pos += transform_len;
+
+        out.extend(new_flags.to_be_bytes());
+        out.extend(new_gid.to_be_bytes());
+        out.extend(new_args);
+        out.extend(transform);
+        if flags & MORE_COMPONENTS == 0 {
+            return Ok(out);
+        }
+    }
+}
+
+/// Per-glyph advance and side bearing from raw hmtx bytes.
+pub(crate) fn read_metrics(
+    font: &RawFontRef<'_>,
+    num_glyphs: usize,
+) -> Result<Vec<(u16, i16)>, FontError> {
+    let long_count = font
+        .hhea()
+        .map_err(|e| FontError::table("hhea", e))?
+        .number_of_h_metrics() as usize;
+    let hmtx = font
+        .table_data(Tag::new(b"hmtx"))
+        .ok_or_else(|| FontError::missing("hmtx"))?;
+    read_long_metrics(hmtx.as_bytes(), "hmtx", long_count, num_glyphs)
+}
+
+/// Per-glyph (advance, side bearing) from hmtx or vmtx bytes whose first
+/// `long_count` entries carry an advance; later glyphs repeat the last one.
+pub(crate) fn read_long_metrics(
+    data: &[u8],
+    tag: &str,
+    long_count: usize,
+    num_glyphs: usize,
+) -> Result<Vec<(u16, i16)>, FontError> {
+    let read_u16 = |offset: usize| {
+        data.get(offset..offset + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(|| FontError::malformed(tag, "truncated"))
+    };
+    let mut metrics = Vec::with_capacity(num_glyphs);
+    let mut advance = 0;
+    for gid in 0..num_glyphs {
+        let bearing_offset = if gid < long_count {
+            advance = read_u16(gid * 4)?;
+            gid * 4 + 2
+        } else {
+            long_count * 4 + (gid - long_count) * 2
+        };
+        metrics.push((advance, read_u16(bearing_offset)? as i16));
+    }
+    Ok(metrics)
+}
+
+/// A glyph compiled for the merged font.
+struct MergedGlyph {
+    record: Vec<u8>,
+    bbox: Option<[i16; 4]>,
+    advance: u16,
+    name: String,
+}
+
+fn merge_font_bytes(
+    base_bytes: &[u8],
+    addition_bytes: &[u8],
+    options: &MergeOptions,
+) -> Result<(Vec<u8>, MergeReport), FontError> {
+    let base_raw = RawFontRef::new(base_bytes)
+        .map_err(|e| FontError::invalid_data(format!("Invalid base font: {:?}", e)))?;
+    let add_raw = RawFontRef::new(addition_bytes)
+        .map_err(|e| FontError::invalid_data(format!("Invalid addition font: {:?}", e)))?;
+    for (label, font) in [("base", &base_raw), ("addition", &add_raw)] {
+        if font.table_data(Tag::new(b"glyf")).is_none() {
+            return Err(FontError::input(format!(
+                "The {} font has no glyf table; only TrueType fonts can be merged",
+                label
+            )));
+        }
+    }
+    if base_raw.table_data(Tag::new(b"gvar")).is_some() {
+        return Err(FontError::unsupported("merging into a variable font"));
+    }
+    let base = FontRef::new(base_bytes).map_err(FontError::invalid_font)?;
+    let addition = FontRef::new(addition_bytes).map_err(FontError::invalid_font)?;
+
+    let base_head = base_raw.head().map_err(|e| FontError::table("head", e))?;
+    let add_upem = add_raw
+        .head()
+        .map_err(|e| FontError::table("head", e))?
+        .units_per_em();
+    let scale = base_head.units_per_em() as f64 / add_upem as f64;
+    let base_count = base_raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+    let add_count = add_raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+
+    // Codepoints the base lacks, and the addition glyphs that draw them
+    let base_mappings: HashMap<u32, GlyphId> = base.charmap().mappings().collect();
+    let wanted: Vec<(u32, u16)> = addition
+        .charmap()
+        .mappings()
+        .filter(|(cp, gid)| !base_mappings.contains_key(cp) && gid.to_u32() != 0)
+        .map(|(cp, gid)| (cp, gid.to_u32() as u16))
+        .collect();
+
+    // Close over composite components
+    let add_loca = add_raw
+        .loca(None)
+        .map_err(|e| FontError::table("loca", e))?;
+    let add_glyf = add_raw.glyf().map_err(|e| FontError::table("glyf", e))?;
+    let mut needed: BTreeSet<u16> = wanted.iter().map(|&(_, gid)| gid).collect();
+    let mut pending: Vec<u16> = needed.iter().copied().collect();
+    while let Some(gid) = pending.pop() {
+        let glyph = add_loca
+            .get_glyf(GlyphId::new(gid as u32), &add_glyf)
+            .map_err(|e| FontError::malformed("glyf", format!("glyph {}: {:?}", gid, e)))?;
+        if let Some(Glyph::Composite(composite)) = glyph {
+            for component in composite.components() {
+                let component = component.glyph.to_u32() as u16;
+                if (component as usize) < add_count && needed.insert(component) {
+                    pending.push(component);
+                }
+            }
+        }
+    }
+
+    // Glyph names decide collisions only when the base stores real names
+    let base_names = base.glyph_names();
+    let mut taken: HashMap<String, u16> = HashMap::new();
+    for gid in 0..base_count as u32 {
+        if let Some(name) = base_names.get(GlyphId::new(gid)) {
+            if !name.is_synthesized() {
+                taken.entry(name.as_str().to_string()).or_insert(gid as u16);
+            }
+        }
+    }
+    let add_names = addition.glyph_names();
+    let mut report = MergeReport::default();
+    let mut placement: HashMap<u16, Placement> = HashMap::new();
+    let mut new_names = Vec::new();
+    for &gid in &needed {
+        let mut name = add_names
+            .get(GlyphId::new(gid as u32))
+            .map_or_else(|| format!("glyph{:05}", gid), |n| n.as_str().to_string());
+        if let Some(&existing) = taken.get(&name) {
+            if !options.rename_collisions {
+                placement.insert(gid, Placement::Base(existing));
+                report.skipped += 1;
+                continue;
+            }
+            let stem = name.clone();
+            let mut n = 1;
+            while taken.contains_key(&name) {
+                name = format!("{}.{}", stem, n);
+                n += 1;
+            }
+            report.renamed += 1;
+        }
+        let new_gid = base_count + new_names.len();
+        if new_gid > u16::MAX as usize {
+            return Err(FontError::input(
+                "The merged font would have more than 65535 glyphs",
+            ));
+        }
+        taken.insert(name.clone(), new_gid as u16);
+        placement.insert(gid, Placement::Appended(new_gid as u16));
+        new_names.push((gid, name));
+    }
+
+    // Compile the appended glyphs in the base's units per em
+    let add_metrics = read_metrics(&add_raw, add_count)?;
+    let mut merged = Vec::with_capacity(new_names.len());
+    for (gid, name) in new_names {
+        let glyph = add_loca
+            .get_glyf(GlyphId::new(gid as u32), &add_glyf)
+            .map_err(|e| FontError::malformed("glyf", format!("glyph {}: {:?}", gid, e)))?;
+        let (record, bbox) = match glyph {
+            None => (Vec::new(), None),
+            Some(Glyph::Simple(simple)) => {
+                let points: Vec<(i32, i32, bool)> = simple
+                    .points()
+                    .map(|p| {
+                        (
+                            (p.x as f64 * scale).round() as i32,
+                            (p.y as f64 * scale).round() as i32,
+                            p.on_curve,
+                        )
+                    })
+                    .collect();
+                let Some(&(x, y, _)) = points.first() else {
+                    merged.push(MergedGlyph {
+                        record: Vec::new(),
+                        bbox: None,
+                        advance: 0,
+                        name,
+                    });
+                    continue;
+                };
+                let bbox = points.iter().fold(
+                    [x as i16, y as i16, x as i16, y as i16],
+                    |b, &(x, y, _)| {
+                        let (x, y) = (x as i16, y as i16);
+                        [b[0].min(x), b[1].min(y), b[2].max(x), b[3].max(y)]
+                    },
+                );
+                let end_points: Vec<u16> = simple
+                    .end_pts_of_contours()
+                    .iter()
+                    .map(|e| e.get())
+                    .collect();
+                let record = encode_simple_glyph(
+                    &end_points,
+                    &points,
+                    &[],
+                    bbox,
+                    simple.has_overlapping_contours(),
+                );
+                (record, Some(bbox))
+            }
+            Some(Glyph::Composite(composite)) => {
+                let record =
+                    rewrite_composite(composite.offset_data().as_bytes(), &placement, scale)?;
+                let bbox = [0, 1, 2, 3]
+                    .map(|i| i16::from_be_bytes([record[2 + i * 2], record[3 + i * 2]]));
+                (record, Some(bbox))
+            }
+        };
+        let advance = (add_metrics[gid as usize].0 as f64 * scale).round() as u16;
+        merged.push(MergedGlyph {
+            record,
+            bbox,
+            advance,
+            name,
+        });
+    }
+    report.imported = merged.len();
+    if add_upem != base_head.units_per_em() {
+        report.scaled = merged.len();
+    }
+
+    // glyf and loca: the base's records unchanged, then the new ones
+    let base_loca = base_raw
+        .table_data(Tag::new(b"loca"))
+        .ok_or_else(|| FontError::missing("loca"))?;
+    let base_offsets = crate::font_parser::parse_loca_offsets(
+        base_loca.as_bytes(),
+        base_count + 1,
+        base_head.index_to_loc_format() == 1,
+    );
+    let base_glyf = base_raw.table_data(Tag::new(b"glyf")).unwrap();
+    let base_end = (*base_offsets.last().unwrap_or(&0) as usize).min(base_glyf.len());
+    let mut glyf = base_glyf.as_bytes()[..base_end].to_vec();
+    let mut loca: Vec<u8> = base_offsets.iter().flat_map(|o| o.to_be_bytes()).collect();
+    glyf.resize(glyf.len().next_multiple_of(4), 0);
+    loca.truncate(base_count * 4);
+    loca.extend((glyf.len() as u32).to_be_bytes());
+    for glyph in &merged {
+        glyf.extend(&glyph.record);
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+        loca.extend((glyf.len() as u32).to_be_bytes());
+    }
+    let total = base_count + merged.len();
+
+    // Metrics: every glyph gets a long metric; lsb is the glyph's xMin
+    let mut metrics = read_metrics(&base_raw, base_count)?;
+    metrics.extend(
+        merged
+            .iter()
+            .map(|g| (g.advance, g.bbox.map_or(0, |b| b[0]))),
+    );
+    let hmtx = Hmtx::new(
+        metrics
+            .iter()
+            .map(|&(advance, lsb)| LongMetric::new(advance, lsb))
+            .collect(),
+        Vec::new(),
+    );
+    let mut hhea: Hhea = base_raw
+        .hhea()
+        .map_err(|e| FontError::table("hhea", e))?
+        .to_owned_table();
+    hhea.number_of_h_metrics = total as u16;
+    for glyph in &merged {
+        hhea.advance_width_max = hhea.advance_width_max.max(UfWord::new(glyph.advance));
+        if let Some([x_min, _, x_max, _]) = glyph.bbox {
+            let rsb = (glyph.advance as i32 - x_max as i32).clamp(i16::MIN as i32, 0x7FFF) as i16;
+            hhea.min_left_side_bearing = hhea.min_left_side_bearing.min(FWord::new(x_min));
+            hhea.min_right_side_bearing = hhea.min_right_side_bearing.min(FWord::new(rsb));
+            hhea.x_max_extent = hhea.x_max_extent.max(FWord::new(x_max));
+        }
+    }
+
+    let mut head: Head = base_head.to_owned_table();
+    head.index_to_loc_format = 1;
+    for [x_min, y_min, x_max, y_max] in merged.iter().filter_map(|g| g.bbox) {
+        head.x_min = head.x_min.min(x_min);
+        head.y_min = head.y_min.min(y_min);
+        head.x_max = head.x_max.max(x_max);
+        head.y_max = head.y_max.max(y_max);
+    }
+
+    // maxp limits are raised to the addition's, which bound the new glyphs
+    let mut maxp: Maxp = base_raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .to_owned_table();
+    let add_maxp: Maxp = add_raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .to_owned_table();
+    maxp.num_glyphs = total as u16;
+    for (field, added) in [
+        (&mut maxp.max_points, add_maxp.max_points),
+        (&mut maxp.max_contours, add_maxp.max_contours),
+        (
+            &mut maxp.max_composite_points,
+            add_maxp.max_composite_points,
+        ),
+        (
+            &mut maxp.max_composite_contours,
+            add_maxp.max_composite_contours,
+        ),
+        (
+            &mut maxp.max_component_elements,
+            add_maxp.max_component_elements,
+        ),
+        (&mut maxp.max_component_depth, add_maxp.max_component_depth),
+    ] {
+        if let (Some(current), Some(added)) = (field.as_mut(), added) {
+            *current = (*current).max(added);
+        }
+    }
+
+    // cmap: Unicode subtables rebuilt from the merged mappings, others kept
+    let mut mappings: Vec<(char, write_fonts::types::GlyphId)> = base_mappings
+        .iter()
+        .filter_map(|(&cp, &gid)| Some((char::from_u32(cp)?, gid)))
+        .collect();
+    for &(cp, gid) in &wanted {
+        if let (Some(ch), Some(Placement::Appended(new_gid))) =
+            (char::from_u32(cp), placement.get(&gid))
+        {
+            mappings.push((ch, GlyphId::new(*new_gid as u32)));
+            report.mapped_codepoints += 1;
+        }
+    }
+    let unicode =
+        Cmap::from_mappings(mappings.iter().copied()).map_err(|e| FontError::build("cmap", e))?;
+    let mut cmap: Cmap = base_raw
+        .cmap()
+        .map_err(|e| FontError::table("cmap", e))?
+        .to_owned_table();
+    cmap.encoding_records.retain(|r| {
+        !matches!(
+            *r.subtable,
+            CmapSubtable::Format4(_) | CmapSubtable::Format12(_)
+        )
+    });
+    cmap.encoding_records.extend(unicode.encoding_records);
+    cmap.encoding_records
+        .sort_by_key(|r| (r.platform_id as u16, r.encoding_id));
+
+    let mut builder = FontBuilder::new();
+    builder
+        .add_raw(Tag::new(b"glyf"), glyf)
+        .add_raw(Tag::new(b"loca"), loca)
+        .add_table(&head)
+        .map_err(|e| FontError::build("head", e))?
+        .add_table(&hhea)
+        .map_err(|e| FontError::build("hhea", e))?
+        .add_table(&hmtx)
+        .map_err(|e| FontError::build("hmtx", e))?
+        .add_table(&maxp)
+        .map_err(|e| FontError::build("maxp", e))?
+        .add_table(&cmap)
+        .map_err(|e| FontError::build("cmap", e))?;
+
+    if let Ok(os2) = base_raw.os2() {
+        let mut os2: Os2 = os2.to_owned_table();
+        let codes = mappings
+            .iter()
+            .map(|&(ch, _)| (ch as u32).min(0xFFFF) as u16);
+        os2.us_first_char_index = codes.clone().min().unwrap_or(0);
+        os2.us_last_char_index = codes.max().unwrap_or(0);
+        builder
+            .add_table(&os2)
+            .map_err(|e| FontError::build("OS/2", e))?;
+    }
+    // A post table with glyph names needs names for the new glyphs too
+    if let Ok(post) = base_raw.post() {
+        if post.version().to_major_minor() == (2, 0) {
+            let old: Post = post.to_owned_table();
+            let names: Vec<String> = (0..base_count as u32)
+                .map(|gid| {
+                    base_names
+                        .get(GlyphId::new(gid))
+                        .map_or_else(|| format!("glyph{:05}", gid), |n| n.as_str().to_string())
+                })
+                .chain(merged.iter().map(|g| g.name.clone()))
+                .collect();
+            let post = Post {
+                italic_angle: old.italic_angle,
+                underline_position: old.underline_position,
+                underline_thickness: old.underline_thickness,
+                is_fixed_pitch: old.is_fixed_pitch,
+                min_mem_type42: old.min_mem_type42,
+                max_mem_type42: old.max_mem_type42,
+                min_mem_type1: old.min_mem_type1,
+                max_mem_type1: old.max_mem_type1,
+                ..Post::new_v2(names.iter().map(String::as_str))
+            };
+            builder
+                .add_table(&post)
+                .map_err(|e| FontError::build("post", e))?;
+        }
+    }
+    for tag in DROPPED_TABLES {
+        let tag = Tag::new(tag);
+        if base_raw.table_data(tag).is_some() {
+            report.dropped_tables.push(tag.to_string());
+        }
+    }
+    for record in base_raw.table_directory.table_records() {
+        let tag = record.tag();
+        if !builder.contains(tag) && !report.dropped_tables.contains(&tag.to_string()) {
+            if let Some(data) = base_raw.table_data(tag) {
+                builder.add_raw(tag, data.as_bytes().to_vec());
+            }
+        }
+    }
+    Ok((builder.build(), report))
+}
+
+/// Copy glyphs for the codepoints the base lacks from `addition_path` into
+/// `base_path`, writing the result to `destination`. Metrics tables other
+/// than hmtx stay the base's; imported glyphs lose their instructions.
+pub fn merge_fonts(
+    base_path: &str,
+    addition_path: &str,
+    destination: &str,
+    options: &MergeOptions,
+    cache: &FontCache,
+) -> Result<MergeReport, FontError> {
+    let base = get_font_bytes(base_path, cache)?;
+    let addition = get_font_bytes(addition_path, cache)?;
+    let (merged, report) = merge_font_bytes(&base, &addition, options)?;
+    write_atomically(std::path::Path::new(destination), &merged)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{composite_glyph, simple_glyph, TestComponent, TestFontBuilder};
+
+    #[test]
+    fn test_merge_fonts() {
+        let square = simple_glyph(&[&[(0, 0, true), (0, 500, true), (500, 500, true)]]);
+        let base = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(square.clone(), 600)
+            .map('A' as u32, 1)
+            .build();
+        // Twice the units per em: everything is halved on the way in
+        let addition = TestFontBuilder::new()
+            .units_per_em(2000)
+            .glyph(Vec::new(), 1000)
+            .glyph(square.clone(), 1200)
+            .glyph(square, 1400)
+            .glyph(
+                composite_glyph(&[TestComponent {
+                    glyph_id: 2,
+                    dx: 100,
+                    dy: -40,
+                    transform: None,
+                }]),
+                1600,
+            )
+            .map('A' as u32, 1)
+            .map(0x0416, 3)
+            .build();
+
+        let (merged, report) =
+            merge_font_bytes(&base, &addition, &MergeOptions::default()).unwrap();
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.scaled, 2);
+        assert_eq!(report.mapped_codepoints, 1);
+
+        let font = FontRef::new(&merged).unwrap();
+        let raw = RawFontRef::new(&merged).unwrap();
+        assert_eq!(raw.maxp().unwrap().num_glyphs(), 4);
+        // 'A' keeps the base glyph; the composite lands after its component
+        assert_eq!(font.charmap().map('A'), Some(GlyphId::new(1)));
+        assert_eq!(font.charmap().map('\u{416}'), Some(GlyphId::new(3)));
+        assert_eq!(raw.hmtx().unwrap().advance(GlyphId::new(2)), Some(700));
+        assert_eq!(raw.hmtx().unwrap().advance(GlyphId::new(3)), Some(800));
+
+        let loca = raw.loca(None).unwrap();
+        let glyf = raw.glyf().unwrap();
+        let Some(Glyph::Simple(simple)) = loca.get_glyf(GlyphId::new(2), &glyf).unwrap() else {
+            panic!("expected a simple glyph");
+        };
+        assert_eq!(simple.y_max(), 250);
+        let Some(Glyph::Composite(composite)) = loca.get_glyf(GlyphId::new(3), &glyf).unwrap()
+        else {
+            panic!("expected a composite glyph");
+        };
+        let component = composite.components().next().unwrap();
+        assert_eq!(component.glyph.to_u32(), 2);
+        assert!(matches!(
+            component.anchor,
+            skrifa::raw::tables::glyf::Anchor::Offset { x: 50, y: -20 }
+        ));
+    }
+}
@@ -0,0 +1,2116 @@
+use crate::error::FontError;
+use crate::font_parser::{
+    editable_font_bytes, extract_glyph_outlines, get_font_bytes, parse_composite_components,
+    parse_loca_offsets, save_edited_font, shift_glyphs_x, CancelToken, FontCache, GlyphBounds,
+};
+use crate::merge::read_metrics;
+use crate::raster::EdgePen;
+use crate::validate::{Severity, ValidationReport};
+use serde::{Deserialize, Serialize};
+use skrifa::instance::{LocationRef, Size};
+use skrifa::outline::DrawSettings;
+use skrifa::raw::types::Tag;
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::{FontRef, MetadataProvider};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use write_fonts::from_obj::ToOwnedTable;
+use write_fonts::tables::hhea::Hhea;
+use write_fonts::tables::hmtx::{Hmtx, LongMetric};
+use write_fonts::tables::os2::{Os2, SelectionFlags};
+use write_fonts::tables::post::Post;
+use write_fonts::tables::vhea::Vhea;
+use write_fonts::tables::vmtx::Vmtx;
+use write_fonts::types::{FWord, UfWord};
+use write_fonts::FontBuilder;
+
+/// One glyph's metric edit. Absolute values and deltas for the same field are
+/// mutually exclusive; fields left out keep their current value.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct GlyphMetricsEdit {
+    pub glyph_id: u32,
+    pub advance_width: Option<u16>,
+    pub lsb: Option<i16>,
+    pub delta_advance: Option<i32>,
+    pub delta_lsb: Option<i32>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GlyphMetricsChange {
+    pub glyph_id: u32,
+    pub old_advance_width: u16,
+    pub new_advance_width: u16,
+    pub old_lsb: i16,
+    pub new_lsb: i16,
+}
+
+/// The hhea fields summarising hmtx and the glyph outlines.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HheaExtents {
+    pub advance_width_max: u16,
+    pub min_left_side_bearing: i16,
+    pub min_right_side_bearing: i16,
+    pub x_max_extent: i16,
+}
+
+/// Summarise `metrics` (advance, lsb per glyph) over the glyphs in `bounds`.
+/// Only glyphs with contours count towards the side bearings and extent.
+fn hhea_extents(metrics: &[(u16, i16)], bounds: &[(u32, GlyphBounds)]) -> HheaExtents {
+    let mut min_lsb = None::<i32>;
+    let mut min_rsb = None::<i32>;
+    let mut max_extent = None::<i32>;
+    for (gid, b) in bounds {
+        let Some(&(advance, lsb)) = metrics.get(*gid as usize) else {
+            continue;
+        };
+        if b.x_min == b.x_max && b.y_min == b.y_max {
+            continue;
+        }
+        let lsb = lsb as i32;
+        let extent = lsb + (b.x_max - b.x_min).round() as i32;
+        min_lsb = Some(min_lsb.map_or(lsb, |m| m.min(lsb)));
+        min_rsb = Some(min_rsb.map_or(advance as i32 - extent, |m| m.min(advance as i32 - extent)));
+        max_extent = Some(max_extent.map_or(extent, |m| m.max(extent)));
+    }
+    let clamp = |v: Option<i32>| v.unwrap_or(0).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    HheaExtents {
+        advance_width_max: metrics.iter().map(|m| m.0).max().unwrap_or(0),
+        min_left_side_bearing: clamp(min_lsb),
+        min_right_side_bearing: clamp(min_rsb),
+        x_max_extent: clamp(max_extent),
+    }
+}
+
+/// Outline bounds of every glyph with contours, extracted from `bytes`.
+fn outline_bounds(bytes: &[u8]) -> Result<Vec<(u32, GlyphBounds)>, FontError> {
+    Ok(
+        extract_glyph_outlines(bytes, &|_, _| {}, &CancelToken::default())?
+            .into_iter()
+            .filter_map(|o| Some((o.glyph_id, o.bounds?)))
+            .collect(),
+    )
+}
+
+/// Rewrite hhea's advanceWidthMax, minLeftSideBearing, minRightSideBearing
+/// and xMaxExtent from hmtx and `bounds`.
+pub(crate) fn recalc_hhea_bytes(
+    bytes: &[u8],
+    bounds: &[(u32, GlyphBounds)],
+) -> Result<(Vec<u8>, HheaExtents), FontError> {
+    let font = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+    let extents = hhea_extents(&read_metrics(&font, num_glyphs)?, bounds);
+
+    let mut hhea: Hhea = font
+        .hhea()
+        .map_err(|e| FontError::table("hhea", e))?
+        .to_owned_table();
+    hhea.advance_width_max = UfWord::new(extents.advance_width_max);
+    hhea.min_left_side_bearing = FWord::new(extents.min_left_side_bearing);
+    hhea.min_right_side_bearing = FWord::new(extents.min_right_side_bearing);
+    hhea.x_max_extent = FWord::new(extents.x_max_extent);
+    let new_bytes = FontBuilder::new()
+        .add_table(&hhea)
+        .map_err(|e| FontError::build("hhea", e))?
+        .copy_missing_tables(font)
+        .build();
+    Ok((new_bytes, extents))
+}
+
+/// Recompute the hhea aggregates of the font at `file_path` and save it.
+/// Bounds come from the cached outlines when present, otherwise a fresh pass.
+pub fn recalc_hhea(file_path: &str, cache: &FontCache) -> Result<HheaExtents, FontError> {
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let bounds = match cache.cached_bounds(file_path) {
+        Some(bounds) => bounds,
+        None => outline_bounds(&bytes)?,
+    };
+    let (new_bytes, extents) = recalc_hhea_bytes(&bytes, &bounds)?;
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(extents)
+}
+
+/// Edited font bytes with hhea recomputed, when the cache has that turned on
+/// (see [`FontCache::set_recalc_hhea`]). `cached_bounds` may be given when the
+/// edit left the outlines of the font as they were.
+pub(crate) fn maintain_hhea(
+    bytes: Vec<u8>,
+    cached_bounds: Option<Vec<(u32, GlyphBounds)>>,
+    cache: &FontCache,
+) -> Result<Vec<u8>, FontError> {
+    if !cache.recalc_hhea() {
+        return Ok(bytes);
+    }
+    let bounds = match cached_bounds {
+        Some(bounds) => bounds,
+        None => outline_bounds(&bytes)?,
+    };
+    Ok(recalc_hhea_bytes(&bytes, &bounds)?.0)
+}
+
+/// OS/2 xAvgCharWidth as the current spec defines it: the rounded mean of
+/// every non-zero advance width (zero-width marks and empty glyphs are left
+/// out). 0 when no glyph has an advance.
+pub(crate) fn x_avg_char_width(font: &RawFontRef<'_>) -> Result<i16, FontError> {
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+    let advances: Vec<i64> = read_metrics(font, num_glyphs)?
+        .into_iter()
+        .filter(|&(advance, _)| advance != 0)
+        .map(|(advance, _)| advance as i64)
+        .collect();
+    if advances.is_empty() {
+        return Ok(0);
+    }
+    let mean = advances.iter().sum::<i64>() as f64 / advances.len() as f64;
+    Ok(mean.round() as i16)
+}
+
+/// Font bytes with OS/2 xAvgCharWidth brought up to date. Fonts without an
+/// OS/2 table, or whose value is already right, come back unchanged.
+pub(crate) fn recalc_x_avg_char_width(bytes: Vec<u8>) -> Result<Vec<u8>, FontError> {
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let Ok(os2) = font.os2() else {
+        return Ok(bytes);
+    };
+    let x_avg = x_avg_char_width(&font)?;
+    if os2.x_avg_char_width() == x_avg {
+        return Ok(bytes);
+    }
+    let mut os2: Os2 = os2.to_owned_table();
+    os2.x_avg_char_width = x_avg;
+    Ok(FontBuilder::new()
+        .add_table(&os2)
+        .map_err(|e| FontError::build("OS/2", e))?
+        .copy_missing_tables(font)
+        .build())
+}
+
+fn apply_edit(
+    (advance, lsb): (u16, i16),
+    edit: &GlyphMetricsEdit,
+) -> Result<(u16, i16), FontError> {
+    let gid = edit.glyph_id;
+    let advance = match (edit.advance_width, edit.delta_advance) {
+        (Some(_), Some(_)) => {
+            return Err(FontError::input(format!(
+                "Glyph {} sets both advance_width and delta_advance",
+                gid
+            )))
+        }
+        (Some(value), None) => value,
+        (None, Some(delta)) => u16::try_from(advance as i32 + delta).map_err(|_| {
+            FontError::input(format!(
+                "Advance of glyph {} would be {}, outside 0..=65535",
+                gid,
+                advance as i32 + delta
+            ))
+        })?,
+        (None, None) => advance,
+    };
+    let lsb = match (edit.lsb, edit.delta_lsb) {
+        (Some(_), Some(_)) => {
+            return Err(FontError::input(format!(
+                "Glyph {} sets both lsb and delta_lsb",
+                gid
+            )))
+        }
+        (Some(value), None) => value,
+        (None, Some(delta)) => i16::try_from(lsb as i32 + delta).map_err(|_| {
+            FontError::input(format!(
+                "Side bearing of glyph {} would be {}, outside the int16 range",
+                gid,
+                lsb as i32 + delta
+            ))
+        })?,
+        (None, None) => lsb,
+    };
+    Ok((advance, lsb))
+}
+
+/// Number of long metrics needed for `metrics`: trailing glyphs sharing one
+/// advance are stored as side bearings only.
+fn long_metric_count(metrics: &[(u16, i16)]) -> usize {
+    let mut long_count = metrics.len().max(1);
+    while long_count > 1 && metrics[long_count - 1].0 == metrics[long_count - 2].0 {
+        long_count -= 1;
+    }
+    long_count.min(metrics.len())
+}
+
+/// hmtx holding `metrics` (advance, lsb) for every glyph, with trailing
+/// glyphs sharing one advance stored as side bearings only, and the hhea of
+/// `font` with numberOfHMetrics and advanceWidthMax to match.
+pub(crate) fn hmtx_with_hhea(
+    font: &RawFontRef<'_>,
+    metrics: &[(u16, i16)],
+) -> Result<(Hmtx, Hhea), FontError> {
+    hmtx_with_long_count(font, metrics, long_metric_count(metrics))
+}
+
+/// [`hmtx_with_hhea`] with the first `long_count` glyphs stored as long
+/// metrics; the glyphs after them must share the last long advance.
+fn hmtx_with_long_count(
+    font: &RawFontRef<'_>,
+    metrics: &[(u16, i16)],
+    long_count: usize,
+) -> Result<(Hmtx, Hhea), FontError> {
+    let hmtx = Hmtx::new(
+        metrics[..long_count]
+            .iter()
+            .map(|&(advance, lsb)| LongMetric::new(advance, lsb))
+            .collect(),
+        metrics[long_count..].iter().map(|&(_, lsb)| lsb).collect(),
+    );
+    let mut hhea: Hhea = font
+        .hhea()
+        .map_err(|e| FontError::table("hhea", e))?
+        .to_owned_table();
+    hhea.number_of_h_metrics = long_count as u16;
+    hhea.advance_width_max = UfWord::new(metrics.iter().map(|m| m.0).max().unwrap_or(0));
+    Ok((hmtx, hhea))
+}
+
+/// The vertical counterpart of [`hmtx_with_hhea`]: vmtx holding `metrics`
+/// (advance height, tsb) and the vhea of `font` to match.
+pub(crate) fn vmtx_with_vhea(
+    font: &RawFontRef<'_>,
+    metrics: &[(u16, i16)],
+) -> Result<(Vmtx, Vhea), FontError> {
+    let long_count = long_metric_count(metrics);
+    let vmtx = Vmtx::new(
+        metrics[..long_count]
+            .iter()
+            .map(|&(advance, tsb)| LongMetric::new(advance, tsb))
+            .collect(),
+        metrics[long_count..].iter().map(|&(_, tsb)| tsb).collect(),
+    );
+    let mut vhea: Vhea = font
+        .vhea()
+        .map_err(|e| FontError::table("vhea", e))?
+        .to_owned_table();
+    vhea.number_of_long_ver_metrics = long_count as u16;
+    vhea.advance_height_max = UfWord::new(metrics.iter().map(|m| m.0).max().unwrap_or(0));
+    Ok((vmtx, vhea))
+}
+
+/// Apply `edits` to hmtx in memory, returning the new font bytes and the
+/// per-glyph changes in edit order. Trailing glyphs sharing one advance are
+/// stored as side bearings only, so numberOfHMetrics may shrink or grow.
+pub(crate) fn update_glyph_metrics_bytes(
+    bytes: &[u8],
+    edits: &[GlyphMetricsEdit],
+) -> Result<(Vec<u8>, Vec<GlyphMetricsChange>), FontError> {
+    let font = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+    let mut metrics = read_metrics(&font, num_glyphs)?;
+
+    let mut seen = HashSet::new();
+    let mut changes = Vec::with_capacity(edits.len());
+    for edit in edits {
+        let gid = edit.glyph_id;
+        if !seen.insert(gid) {
+            return Err(FontError::input(format!(
+                "Glyph {} has more than one metrics edit",
+                gid
+            )));
+        }
+        let old = *metrics
+            .get(gid as usize)
+            .ok_or(FontError::GlyphNotFound { gid })?;
+        let new = apply_edit(old, edit)?;
+        metrics[gid as usize] = new;
+        changes.push(GlyphMetricsChange {
+            glyph_id: gid,
+            old_advance_width: old.0,
+            new_advance_width: new.0,
+            old_lsb: old.1,
+            new_lsb: new.1,
+        });
+    }
+
+    let (hmtx, hhea) = hmtx_with_hhea(&font, &metrics)?;
+    let new_bytes = FontBuilder::new()
+        .add_table(&hmtx)
+        .map_err(|e| FontError::build("hmtx", e))?
+        .add_table(&hhea)
+        .map_err(|e| FontError::build("hhea", e))?
+        .copy_missing_tables(font)
+        .build();
+    Ok((new_bytes, changes))
+}
+
+/// Restructure hmtx to store `long_count` long metrics. Growing turns side
+/// bearing entries into long metrics with the advance they inherited.
+/// Shrinking needs the dropped advances to equal the last long one; with
+/// `equalize` they are set to it instead of refusing.
+pub(crate) fn set_number_of_h_metrics_bytes(
+    bytes: &[u8],
+    long_count: u16,
+    equalize: bool,
+) -> Result<Vec<u8>, FontError> {
+    let font = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+    let long_count = long_count as usize;
+    if long_count == 0 || long_count > num_glyphs {
+        return Err(FontError::input(format!(
+            "numberOfHMetrics must be between 1 and numGlyphs ({}), got {}",
+            num_glyphs, long_count
+        )));
+    }
+    let mut metrics = read_metrics(&font, num_glyphs)?;
+
+    let shared = metrics[long_count - 1].0;
+    let differing = (long_count..num_glyphs).find(|&gid| metrics[gid].0 != shared);
+    if let Some(gid) = differing {
+        if !equalize {
+            return Err(FontError::input(format!(
+                "Glyph {} has advance {}, but every glyph from {} on would share glyph {}'s \
+                 advance {}; equalize them or keep more long metrics",
+                gid,
+                metrics[gid].0,
+                long_count,
+                long_count - 1,
+                shared
+            )));
+        }
+        for metric in &mut metrics[long_count..] {
+            metric.0 = shared;
+        }
+    }
+
+    let (hmtx, hhea) = hmtx_with_long_count(&font, &metrics, long_count)?;
+    Ok(FontBuilder::new()
+        .add_table(&hmtx)
+        .map_err(|e| FontError::build("hmtx", e))?
+        .add_table(&hhea)
+        .map_err(|e| FontError::build("hhea", e))?
+        .copy_missing_tables(font)
+        .build())
+}
+
+/// Set hhea.numberOfHMetrics, restructuring hmtx to match (see
+/// [`set_number_of_h_metrics_bytes`]).
+pub fn update_number_of_h_metrics(
+    file_path: &str,
+    long_count: u16,
+    equalize: bool,
+    cache: &FontCache,
+) -> Result<(), FontError> {
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let new_bytes = set_number_of_h_metrics_bytes(&bytes, long_count, equalize)?;
+    let new_bytes = recalc_x_avg_char_width(new_bytes)?;
+    let new_bytes = maintain_hhea(new_bytes, cache.cached_bounds(file_path), cache)?;
+    save_edited_font(file_path, new_bytes, cache)
+}
+
+/// Apply many advance / side bearing edits with a single font rewrite.
+pub fn update_glyph_metrics_bulk(
+    file_path: &str,
+    edits: &[GlyphMetricsEdit],
+    cache: &FontCache,
+) -> Result<Vec<GlyphMetricsChange>, FontError> {
+    if edits.is_empty() {
+        return Ok(Vec::new());
+    }
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let (new_bytes, changes) = update_glyph_metrics_bytes(&bytes, edits)?;
+    let new_bytes = recalc_x_avg_char_width(new_bytes)?;
+    // Metrics edits leave the outlines alone, so cached bounds still apply
+    let new_bytes = maintain_hhea(new_bytes, cache.cached_bounds(file_path), cache)?;
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(changes)
+}
+
+/// A glyph whose outline does not fit in the monospaced advance.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct WideGlyph {
+    pub glyph_id: u32,
+    pub width: i32,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct MonospaceReport {
+    pub advance_width: u16,
+    pub glyphs_changed: usize,
+    pub too_wide: Vec<WideGlyph>,
+}
+
+/// PANOSE family kinds that have a monospaced value in byte 3, with that value
+/// and the proportional value to fall back to when the flag is cleared.
+/// Latin Text has no single proportional value, so it goes back to "any".
+fn panose_spacing(family: u8) -> Option<(u8, u8)> {
+    match family {
+        2 => Some((9, 0)),
+        3 | 5 => Some((3, 2)),
+        _ => None,
+    }
+}
+
+/// Font bytes with post.isFixedPitch and the OS/2 PANOSE spacing set to
+/// `fixed`. Missing tables are skipped, and PANOSE is left alone for family
+/// kinds without a spacing byte, or when clearing a value that is not
+/// monospaced.
+fn set_fixed_pitch_flags(bytes: Vec<u8>, fixed: bool) -> Result<Vec<u8>, FontError> {
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let mut builder = FontBuilder::new();
+    if let Ok(post) = font.post() {
+        let mut post: Post = post.to_owned_table();
+        post.is_fixed_pitch = fixed as u32;
+        builder
+            .add_table(&post)
+            .map_err(|e| FontError::build("post", e))?;
+    }
+    if let Ok(os2) = font.os2() {
+        let mut os2: Os2 = os2.to_owned_table();
+        if let Some((mono, proportional)) = panose_spacing(os2.panose_10[0]) {
+            if fixed {
+                os2.panose_10[3] = mono;
+            } else if os2.panose_10[3] == mono {
+                os2.panose_10[3] = proportional;
+            }
+        }
+        builder
+            .add_table(&os2)
+            .map_err(|e| FontError::build("OS/2", e))?;
+    }
+    Ok(builder.copy_missing_tables(font).build())
+}
+
+/// Give every glyph with an advance the same `advance_width` and mark the font
+/// fixed-pitch. Zero-width glyphs (marks) keep their zero advance. With
+/// `center`, outlines are moved so each sits in the middle of the new advance
+/// (TrueType outlines only). Glyphs wider than the advance are reported, not
+/// changed.
+pub fn set_monospaced(
+    file_path: &str,
+    advance_width: u16,
+    center: bool,
+    cache: &FontCache,
+) -> Result<MonospaceReport, FontError> {
+    if advance_width == 0 {
+        return Err(FontError::input("Monospaced advance width must be above 0"));
+    }
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+    if center && font.glyf().is_err() {
+        return Err(FontError::unsupported(
+            "centering outlines in a font without TrueType outlines",
+        ));
+    }
+    let derived = cache.derived_for(file_path, &bytes)?;
+    let metrics = derived.metrics()?;
+
+    let mut x_extents = vec![None; num_glyphs];
+    for (gid, b) in outline_bounds(&bytes)? {
+        if let Some(slot) = x_extents.get_mut(gid as usize) {
+            *slot = Some((b.x_min.floor() as i32, b.x_max.ceil() as i32));
+        }
+    }
+
+    let mut edits = Vec::new();
+    let mut shifts = vec![0i16; num_glyphs];
+    let mut too_wide = Vec::new();
+    for (gid, &(advance, lsb)) in metrics.iter().enumerate() {
+        if advance == 0 {
+            continue;
+        }
+        let mut new_lsb = lsb;
+        if let Some((x_min, x_max)) = x_extents[gid] {
+            let width = x_max - x_min;
+            if width > advance_width as i32 {
+                too_wide.push(WideGlyph {
+                    glyph_id: gid as u32,
+                    width,
+                });
+            }
+            if center {
+                let centered = ((advance_width as i32 - width) as f64 / 2.0).round() as i32;
+                shifts[gid] = i16::try_from(centered - x_min).map_err(|_| {
+                    FontError::input(format!("Glyph {} cannot be moved that far", gid))
+                })?;
+                new_lsb = centered as i16;
+            }
+        }
+        if advance != advance_width || new_lsb != lsb {
+            edits.push(GlyphMetricsEdit {
+                glyph_id: gid as u32,
+                advance_width: Some(advance_width),
+                lsb: Some(new_lsb),
+                ..Default::default()
+            });
+        }
+    }
+
+    let mut new_bytes = bytes.to_vec();
+    if shifts.iter().any(|&dx| dx != 0) {
+        new_bytes = shift_glyphs_x(&new_bytes, &shifts)?;
+    }
+    let (new_bytes, _) = update_glyph_metrics_bytes(&new_bytes, &edits)?;
+    let new_bytes = set_fixed_pitch_flags(new_bytes, true)?;
+    let new_bytes = recalc_x_avg_char_width(new_bytes)?;
+    let cached_bounds = if center {
+        None
+    } else {
+        cache.cached_bounds(file_path)
+    };
+    let new_bytes = maintain_hhea(new_bytes, cached_bounds, cache)?;
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(MonospaceReport {
+        advance_width,
+        glyphs_changed: edits.len(),
+        too_wide,
+    })
+}
+
+/// Clear the fixed-pitch flags set by [`set_monospaced`]. Advances are left
+/// as they are.
+pub fn clear_monospaced(file_path: &str, cache: &FontCache) -> Result<(), FontError> {
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let new_bytes = set_fixed_pitch_flags(bytes.to_vec(), false)?;
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(())
+}
+
+/// The line spacing values of hhea and OS/2 side by side with the glyph
+/// extremes they should account for. OS/2 fields are absent when the font has
+/// no OS/2 table.
+#[derive(Serialize, Debug, Clone)]
+pub struct VerticalMetrics {
+    pub hhea_ascender: i16,
+    pub hhea_descender: i16,
+    pub hhea_line_gap: i16,
+    pub typo_ascender: Option<i16>,
+    pub typo_descender: Option<i16>,
+    pub typo_line_gap: Option<i16>,
+    pub win_ascent: Option<u16>,
+    pub win_descent: Option<u16>,
+    pub use_typo_metrics: bool,
+    /// Lowest and highest outline points; absent when no glyph has contours.
+    pub glyph_y_min: Option<i32>,
+    pub glyph_y_max: Option<i32>,
+    pub problems: ValidationReport,
+}
+
+/// Lowest and highest points over all outlines in `bounds`.
+fn y_extremes(bounds: &[(u32, GlyphBounds)]) -> Option<(i32, i32)> {
+    bounds
+        .iter()
+        .filter(|(_, b)| b.x_min != b.x_max || b.y_min != b.y_max)
+        .map(|(_, b)| (b.y_min.floor() as i32, b.y_max.ceil() as i32))
+        .reduce(|(lo, hi), (y_min, y_max)| (lo.min(y_min), hi.max(y_max)))
+}
+
+fn vertical_metrics_bytes(
+    bytes: &[u8],
+    bounds: &[(u32, GlyphBounds)],
+) -> Result<VerticalMetrics, FontError> {
+    let font = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let hhea = font.hhea().map_err(|e| FontError::table("hhea", e))?;
+    let os2 = font.os2().ok();
+    let extremes = y_extremes(bounds);
+    let mut metrics = VerticalMetrics {
+        hhea_ascender: hhea.ascender().to_i16(),
+        hhea_descender: hhea.descender().to_i16(),
+        hhea_line_gap: hhea.line_gap().to_i16(),
+        typo_ascender: os2.as_ref().map(|t| t.s_typo_ascender()),
+        typo_descender: os2.as_ref().map(|t| t.s_typo_descender()),
+        typo_line_gap: os2.as_ref().map(|t| t.s_typo_line_gap()),
+        win_ascent: os2.as_ref().map(|t| t.us_win_ascent()),
+        win_descent: os2.as_ref().map(|t| t.us_win_descent()),
+        use_typo_metrics: os2
+            .as_ref()
+            .is_some_and(|t| t.fs_selection().contains(SelectionFlags::USE_TYPO_METRICS)),
+        glyph_y_min: extremes.map(|(lo, _)| lo),
+        glyph_y_max: extremes.map(|(_, hi)| hi),
+        problems: ValidationReport::default(),
+    };
+
+    let problems = &mut metrics.problems;
+    if metrics.hhea_line_gap < 0 {
+        problems.push(
+            Severity::Warning,
+            "hhea",
+            "hhea lineGap is negative",
+            &[("line_gap", metrics.hhea_line_gap as i64)],
+        );
+    }
+    let Some(os2) = os2 else {
+        problems.push(Severity::Error, "OS/2", "Font has no OS/2 table", &[]);
+        return Ok(metrics);
+    };
+    if let Some((y_min, y_max)) = extremes {
+        if (os2.us_win_ascent() as i32) < y_max {
+            problems.push(
+                Severity::Warning,
+                "OS/2",
+                "usWinAscent is below the tallest glyph; Windows clips it",
+                &[
+                    ("us_win_ascent", os2.us_win_ascent() as i64),
+                    ("y_max", y_max as i64),
+                ],
+            );
+        }
+        if (os2.us_win_descent() as i32) < -y_min {
+            problems.push(
+                Severity::Warning,
+                "OS/2",
+                "usWinDescent is above the lowest glyph; Windows clips it",
+                &[
+                    ("us_win_descent", os2.us_win_descent() as i64),
+                    ("y_min", y_min as i64),
+                ],
+            );
+        }
+    }
+    let typo = (
+        os2.s_typo_ascender(),
+        os2.s_typo_descender(),
+        os2.s_typo_line_gap(),
+    );
+    let hhea_values = (
+        metrics.hhea_ascender,
+        metrics.hhea_descender,
+        metrics.hhea_line_gap,
+    );
+    if !metrics.use_typo_metrics && typo != hhea_values {
+        problems.push(
+            Severity::Warning,
+            "OS/2",
+            "hhea and typo metrics differ while USE_TYPO_METRICS is off, so line spacing varies by platform",
+            &[
+                ("hhea_ascender", hhea_values.0 as i64),
+                ("hhea_descender", hhea_values.1 as i64),
+                ("hhea_line_gap", hhea_values.2 as i64),
+                ("typo_ascender", typo.0 as i64),
+                ("typo_descender", typo.1 as i64),
+                ("typo_line_gap", typo.2 as i64),
+            ],
+        );
+    }
+    if typo.2 < 0 {
+        problems.push(
+            Severity::Warning,
+            "OS/2",
+            "sTypoLineGap is negative",
+            &[("typo_line_gap", typo.2 as i64)],
+        );
+    }
+    Ok(metrics)
+}
+
+/// Bounds of the outlines for the font at `file_path`, from the cache when
+/// they have been extracted already.
+pub(crate) fn font_bounds(
+    file_path: &str,
+    bytes: &[u8],
+    cache: &FontCache,
+) -> Result<Vec<(u32, GlyphBounds)>, FontError> {
+    match cache.cached_bounds(file_path) {
+        Some(bounds) => Ok(bounds),
+        None => outline_bounds(bytes),
+    }
+}
+
+/// Report the hhea, typo and win line metrics and the usual ways they
+/// disagree with each other or with the outlines.
+pub fn check_vertical_metrics(
+    file_path: &str,
+    cache: &FontCache,
+) -> Result<VerticalMetrics, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let bounds = font_bounds(file_path, &bytes, cache)?;
+    vertical_metrics_bytes(&bytes, &bounds)
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VerticalMetricsStrategy {
+    /// Keep the typo ascender and descender, fold the typo line gap into
+    /// them, copy the result to hhea and cover the outlines with the win
+    /// values (the Google Fonts recommendation).
+    GoogleFonts,
+    /// Set hhea, typo and win values to the outline extremes with no line gap.
+    BoundingBox,
+}
+
+/// Rewrite hhea and OS/2 so all three sets of line metrics agree under
+/// `strategy`, with USE_TYPO_METRICS turned on. OS/2 tables older than
+/// version 4 are upgraded so the flag is honoured.
+pub fn apply_vertical_metrics_strategy(
+    file_path: &str,
+    strategy: VerticalMetricsStrategy,
+    cache: &FontCache,
+) -> Result<VerticalMetrics, FontError> {
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let bounds = font_bounds(file_path, &bytes, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let mut hhea: Hhea = font
+        .hhea()
+        .map_err(|e| FontError::table("hhea", e))?
+        .to_owned_table();
+    let mut os2: Os2 = font
+        .os2()
+        .map_err(|e| FontError::table("OS/2", e))?
+        .to_owned_table();
+    let extremes = y_extremes(&bounds);
+
+    let (ascender, descender) = match strategy {
+        VerticalMetricsStrategy::GoogleFonts => {
+            let gap = os2.s_typo_line_gap.max(0) as i32;
+            (
+                os2.s_typo_ascender as i32 + gap - gap / 2,
+                os2.s_typo_descender as i32 - gap / 2,
+            )
+        }
+        VerticalMetricsStrategy::BoundingBox => extremes
+            .map(|(lo, hi)| (hi, lo))
+            .ok_or_else(|| FontError::input("Font has no outlines to take a bounding box from"))?,
+    };
+    let to_i16 = |v: i32| {
+        i16::try_from(v)
+            .map_err(|_| FontError::input(format!("Line metric {} is outside the int16 range", v)))
+    };
+    os2.s_typo_ascender = to_i16(ascender)?;
+    os2.s_typo_descender = to_i16(descender)?;
+    os2.s_typo_line_gap = 0;
+    hhea.ascender = FWord::new(os2.s_typo_ascender);
+    hhea.descender = FWord::new(os2.s_typo_descender);
+    hhea.line_gap = FWord::new(0);
+    if let Some((y_min, y_max)) = extremes {
+        os2.us_win_ascent = y_max.clamp(0, 0xFFFF) as u16;
+        os2.us_win_descent = (-y_min).clamp(0, 0xFFFF) as u16;
+    }
+    os2.fs_selection |= SelectionFlags::USE_TYPO_METRICS;
+    if os2.sx_height.is_none() {
+        os2.ul_code_page_range_1.get_or_insert(0);
+        os2.ul_code_page_range_2.get_or_insert(0);
+        os2.sx_height = Some(0);
+        os2.s_cap_height = Some(0);
+        os2.us_default_char = Some(0);
+        os2.us_break_char = Some(0x20);
+        os2.us_max_context = Some(0);
+    }
+
+    let new_bytes = FontBuilder::new()
+        .add_table(&hhea)
+        .map_err(|e| FontError::build("hhea", e))?
+        .add_table(&os2)
+        .map_err(|e| FontError::build("OS/2", e))?
+        .copy_missing_tables(font)
+        .build();
+    let metrics = vertical_metrics_bytes(&new_bytes, &bounds)?;
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(metrics)
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GlyphCategory {
+    Uppercase,
+    Lowercase,
+    Digit,
+    Punctuation,
+}
+
+impl GlyphCategory {
+    fn contains(self, ch: char) -> bool {
+        match self {
+            Self::Uppercase => ch.is_uppercase(),
+            Self::Lowercase => ch.is_lowercase(),
+            Self::Digit => ch.is_numeric(),
+            Self::Punctuation => ch.is_ascii_punctuation(),
+        }
+    }
+}
+
+/// The glyphs a spacing rule applies to: every glyph mapped from a character
+/// in a category, or an explicit list.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum GlyphSelection {
+    Category(GlyphCategory),
+    Glyphs(Vec<u32>),
+}
+
+/// Give the selected glyphs the side bearings of `reference`, each scaled by
+/// its factor (1.0 copies them; 0.8 gives round letters 80%).
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpacingRule {
+    pub glyphs: GlyphSelection,
+    pub reference: u32,
+    #[serde(default = "unit_factor")]
+    pub lsb_factor: f32,
+    #[serde(default = "unit_factor")]
+    pub rsb_factor: f32,
+}
+
+fn unit_factor() -> f32 {
+    1.0
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AutospaceChange {
+    pub glyph_id: u32,
+    /// Base component whose adjustment a composite glyph followed.
+    pub inherited_from: Option<u32>,
+    pub old_advance_width: u16,
+    pub new_advance_width: u16,
+    pub old_lsb: i16,
+    pub new_lsb: i16,
+    pub old_rsb: i32,
+    pub new_rsb: i32,
+}
+
+/// First component of every composite glyph, which is the base glyph in
+/// accented composites.
+fn composite_bases(font: &RawFontRef<'_>, num_glyphs: usize) -> HashMap<u32, u32> {
+    let mut bases = HashMap::new();
+    let (Some(glyf), Some(loca), Ok(head)) = (
+        font.table_data(Tag::new(b"glyf")),
+        font.table_data(Tag::new(b"loca")),
+        font.head(),
+    ) else {
+        return bases;
+    };
+    let glyf = glyf.as_bytes();
+    let offsets = parse_loca_offsets(
+        loca.as_bytes(),
+        num_glyphs + 1,
+        head.index_to_loc_format() != 0,
+    );
+    for (gid, w) in offsets.windows(2).enumerate() {
+        let Some(record) = glyf.get(w[0] as usize..w[1] as usize) else {
+            continue;
+        };
+        if record.len() > 10 && i16::from_be_bytes([record[0], record[1]]) < 0 {
+            if let Some(first) = parse_composite_components(&record[10..]).first() {
+                bases.insert(gid as u32, first.glyph_id);
+            }
+        }
+    }
+    bases
+}
+
+/// Respace glyphs from reference side bearings measured on the outlines.
+/// Outlines move so that the new lsb is their left edge; composite glyphs
+/// are not measured but move and widen exactly as their base component
+/// does. Later rules override earlier ones for the same glyph. Everything is
+/// applied in one write; glyphs without contours are left alone.
+pub fn autospace_glyphs(
+    file_path: &str,
+    rules: &[SpacingRule],
+    cache: &FontCache,
+) -> Result<Vec<AutospaceChange>, FontError> {
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    if font.glyf().is_err() {
+        return Err(FontError::unsupported(
+            "autospacing a font without TrueType outlines",
+        ));
+    }
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+    let derived = cache.derived_for(file_path, &bytes)?;
+    let metrics = derived.metrics()?;
+    let mut x_extents = vec![None; num_glyphs];
+    for (gid, b) in font_bounds(file_path, &bytes, cache)? {
+        if let Some(slot) = x_extents.get_mut(gid as usize) {
+            *slot = Some((b.x_min.floor() as i32, b.x_max.ceil() as i32));
+        }
+    }
+    let bases = composite_bases(&font, num_glyphs);
+    let mapped: Vec<(char, u32)> = FontRef::new(&bytes)
+        .map_err(FontError::invalid_font)?
+        .charmap()
+        .mappings()
+        .filter_map(|(cp, gid)| Some((char::from_u32(cp)?, gid.to_u32())))
+        .collect();
+
+    // Target (lsb, rsb) per measured glyph; the last matching rule wins
+    let mut targets = BTreeMap::<u32, (i32, i32)>::new();
+    for rule in rules {
+        let reference = rule.reference;
+        let (advance, _) = *metrics
+            .get(reference as usize)
+            .ok_or(FontError::GlyphNotFound { gid: reference })?;
+        let (x_min, x_max) = x_extents[reference as usize].ok_or_else(|| {
+            FontError::input(format!(
+                "Reference glyph {} has no outline to measure",
+                reference
+            ))
+        })?;
+        let target = (
+            (x_min as f32 * rule.lsb_factor).round() as i32,
+            ((advance as i32 - x_max) as f32 * rule.rsb_factor).round() as i32,
+        );
+        let selected: Vec<u32> = match &rule.glyphs {
+            GlyphSelection::Category(category) => mapped
+                .iter()
+                .filter(|(ch, _)| category.contains(*ch))
+                .map(|&(_, gid)| gid)
+                .collect(),
+            GlyphSelection::Glyphs(glyphs) => glyphs.clone(),
+        };
+        for gid in selected {
+            if gid as usize >= num_glyphs {
+                return Err(FontError::GlyphNotFound { gid });
+            }
+            if !bases.contains_key(&gid) && x_extents[gid as usize].is_some() {
+                targets.insert(gid, target);
+            }
+        }
+    }
+
+    // (shift, advance delta) per glyph: measured glyphs first, then
+    // composites following their base, however deeply nested
+    let mut adjustments = HashMap::<u32, (i32, i32)>::new();
+    for (&gid, &(lsb, rsb)) in &targets {
+        let (advance, _) = metrics[gid as usize];
+        let (x_min, x_max) = x_extents[gid as usize].unwrap_or_default();
+        let new_advance = lsb + (x_max - x_min) + rsb;
+        adjustments.insert(gid, (lsb - x_min, new_advance - advance as i32));
+    }
+    let mut inherited = BTreeMap::new();
+    for &composite in bases.keys() {
+        let mut base = composite;
+        for _ in 0..16 {
+            match bases.get(&base) {
+                Some(&next) => base = next,
+                None => break,
+            }
+        }
+        if let Some(&adjustment) = adjustments.get(&base) {
+            inherited.insert(composite, (base, adjustment));
+        }
+    }
+
+    let mut shifts = vec![0i16; num_glyphs];
+    let mut edits = Vec::new();
+    let mut changes = Vec::new();
+    let measured = adjustments.iter().map(|(&gid, &a)| (gid, None, a));
+    let followers = inherited
+        .iter()
+        .map(|(&gid, &(base, a))| (gid, Some(base), a));
+    let mut all: Vec<_> = measured.chain(followers).collect();
+    all.sort_unstable_by_key(|&(gid, _, _)| gid);
+    for (gid, inherited_from, (shift, delta_advance)) in all {
+        let (advance, lsb) = metrics[gid as usize];
+        let x_max = x_extents[gid as usize].map_or(0, |(_, x_max)| x_max);
+        let new_advance = u16::try_from(advance as i32 + delta_advance).map_err(|_| {
+            FontError::input(format!(
+                "Glyph {} would get advance {}, outside 0..=65535",
+                gid,
+                advance as i32 + delta_advance
+            ))
+        })?;
+        let overflow = || FontError::input(format!("Glyph {} cannot be moved that far", gid));
+        let new_lsb = i16::try_from(lsb as i32 + shift).map_err(|_| overflow())?;
+        shifts[gid as usize] = i16::try_from(shift).map_err(|_| overflow())?;
+        changes.push(AutospaceChange {
+            glyph_id: gid,
+            inherited_from,
+            old_advance_width: advance,
+            new_advance_width: new_advance,
+            old_lsb: lsb,
+            new_lsb,
+            old_rsb: advance as i32 - x_max,
+            new_rsb: new_advance as i32 - (x_max + shift),
+        });
+        edits.push(GlyphMetricsEdit {
+            glyph_id: gid,
+            advance_width: Some(new_advance),
+            lsb: Some(new_lsb),
+            ..Default::default()
+        });
+    }
+    if edits.is_empty() {
+        return Ok(changes);
+    }
+
+    let new_bytes = shift_glyphs_x(&bytes, &shifts)?;
+    let (new_bytes, _) = update_glyph_metrics_bytes(&new_bytes, &edits)?;
+    let new_bytes = recalc_x_avg_char_width(new_bytes)?;
+    let new_bytes = maintain_hhea(new_bytes, None, cache)?;
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(changes)
+}
+
+/// Underline (post) and strikeout (OS/2) placement, in font units. Positions
+/// are the top edge of the stroke relative to the baseline.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecorationMetrics {
+    pub underline_position: i16,
+    pub underline_thickness: i16,
+    pub strikeout_size: i16,
+    pub strikeout_position: i16,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DecorationReport {
+    pub current: DecorationMetrics,
+    pub suggested: DecorationMetrics,
+    /// The 'l' or 'I' glyph whose vertical stem was measured, and the stem
+    /// width; absent when neither glyph has a clean stem.
+    pub stem_glyph: Option<u32>,
+    pub stem_width: Option<i32>,
+    pub x_height: i16,
+    pub problems: ValidationReport,
+}
+
+/// Where a design height came from.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HeightSource {
+    /// OS/2 sxHeight or sCapHeight (version 2 and later).
+    Os2,
+    /// Top of the 'x' or 'H' outline.
+    Outline,
+    /// A fixed share of the em.
+    Estimate,
+}
+
+/// Bottom and top of the outline of the glyph mapped from `ch`.
+pub(crate) fn glyph_y_extent(font: &FontRef<'_>, ch: char) -> Option<(i16, i16)> {
+    let glyph = font.outline_glyphs().get(font.charmap().map(ch)?)?;
+    let mut pen = EdgePen::new();
+    let settings = DrawSettings::unhinted(Size::unscaled(), LocationRef::default());
+    glyph.draw(settings, &mut pen).ok()?;
+    let (bottom, top) = pen.y_extent()?;
+    Some((bottom.round() as i16, top.round() as i16))
+}
+
+/// Top of the outline of the glyph mapped from `ch`, if it is above the
+/// baseline.
+fn glyph_top(font: &FontRef<'_>, ch: char) -> Option<i16> {
+    glyph_y_extent(font, ch)
+        .map(|(_, top)| top)
+        .filter(|&top| top > 0)
+}
+
+/// A design height from the OS/2 field when it is set, else measured from
+/// the glyph for `ch`, else `em_share` of the em.
+fn design_height(
+    font: &FontRef<'_>,
+    os2_value: Option<i16>,
+    ch: char,
+    em_share: f32,
+    upem: u16,
+) -> (i16, HeightSource) {
+    if let Some(value) = os2_value.filter(|&v| v > 0) {
+        return (value, HeightSource::Os2);
+    }
+    match glyph_top(font, ch) {
+        Some(top) => (top, HeightSource::Outline),
+        None => (
+            (upem as f32 * em_share).round() as i16,
+            HeightSource::Estimate,
+        ),
+    }
+}
+
+/// The font's x-height; OS/2 tables before version 2 have no sxHeight, so
+/// those fonts are measured on 'x'.
+fn x_height(font: &FontRef<'_>, upem: u16) -> (i16, HeightSource) {
+    let os2_value = font.os2().ok().and_then(|t| t.sx_height());
+    design_height(font, os2_value, 'x', 0.5, upem)
+}
+
+fn cap_height(font: &FontRef<'_>, upem: u16) -> (i16, HeightSource) {
+    let os2_value = font.os2().ok().and_then(|t| t.s_cap_height());
+    design_height(font, os2_value, 'H', 0.7, upem)
+}
+
+/// Width of the vertical stem of 'l' (or 'I' when there is no usable 'l'),
+/// taken as the median single ink span across the glyph at a few heights
+/// below `x_height`, clear of serifs.
+fn measure_stem(font: &FontRef<'_>, x_height: i16) -> Option<(u32, f32)> {
+    let outlines = font.outline_glyphs();
+    let charmap = font.charmap();
+    for ch in ['l', 'I'] {
+        let Some(glyph) = charmap
+            .map(ch)
+            .and_then(|gid| outlines.get(gid).map(|g| (gid, g)))
+        else {
+            continue;
+        };
+        let (gid, glyph) = glyph;
+        let mut pen = EdgePen::new();
+        let settings = DrawSettings::unhinted(Size::unscaled(), LocationRef::default());
+        if glyph.draw(settings, &mut pen).is_err() {
+            continue;
+        }
+        let mut widths: Vec<f32> = [0.35, 0.5, 0.65]
+            .iter()
+            .filter_map(|f| match pen.ink_spans(x_height as f32 * f).as_slice() {
+                [(start, end)] => Some(end - start),
+                _ => None,
+            })
+            .collect();
+        if widths.is_empty() {
+            continue;
+        }
+        widths.sort_by(f32::total_cmp);
+        return Some((gid.to_u32(), widths[widths.len() / 2]));
+    }
+    None
+}
+
+fn check_decoration(metrics: &DecorationMetrics, upem: u16, x_height: i16) -> ValidationReport {
+    let mut problems = ValidationReport::default();
+    let upem = upem as i32;
+    let thickness = metrics.underline_thickness as i32;
+    if thickness <= 0 {
+        problems.push(
+            Severity::Error,
+            "post",
+            "underlineThickness is not positive",
+            &[("underline_thickness", thickness as i64)],
+        );
+    } else if thickness * 50 < upem || thickness * 8 > upem {
+        problems.push(
+            Severity::Warning,
+            "post",
+            "underlineThickness is far from the usual 1/20 em",
+            &[
+                ("underline_thickness", thickness as i64),
+                ("units_per_em", upem as i64),
+            ],
+        );
+    }
+    if metrics.underline_position >= 0 {
+        problems.push(
+            Severity::Warning,
+            "post",
+            "underlinePosition is not below the baseline",
+            &[("underline_position", metrics.underline_position as i64)],
+        );
+    }
+    let size = metrics.strikeout_size as i32;
+    if size <= 0 {
+        problems.push(
+            Severity::Error,
+            "OS/2",
+            "yStrikeoutSize is not positive",
+            &[("strikeout_size", size as i64)],
+        );
+    } else {
+        let center = metrics.strikeout_position as i32 - size / 2;
+        let x_height = x_height as i32;
+        if center * 10 < x_height * 3 || center * 10 > x_height * 7 {
+            problems.push(
+                Severity::Warning,
+                "OS/2",
+                "Strikeout is not centred near half the x-height",
+                &[
+                    ("strikeout_center", center as i64),
+                    ("x_height", x_height as i64),
+                ],
+            );
+        }
+    }
+    problems
+}
+
+fn decoration_report(bytes: &[u8]) -> Result<DecorationReport, FontError> {
+    let font = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let upem = font
+        .head()
+        .map_err(|e| FontError::table("head", e))?
+        .units_per_em();
+    let post = font.post().map_err(|e| FontError::table("post", e))?;
+    let os2 = font.os2().map_err(|e| FontError::table("OS/2", e))?;
+    let current = DecorationMetrics {
+        underline_position: post.underline_position().to_i16(),
+        underline_thickness: post.underline_thickness().to_i16(),
+        strikeout_size: os2.y_strikeout_size(),
+        strikeout_position: os2.y_strikeout_position(),
+    };
+    let (x_height, _) = x_height(&font, upem);
+    let stem = measure_stem(&font, x_height);
+
+    // Strokes about 60% of the stem, kept within a sane range of the em
+    let em = upem as f32;
+    let thickness = stem
+        .map_or(em * 0.05, |(_, width)| {
+            (width * 0.6).clamp(em / 40.0, em / 10.0)
+        })
+        .round() as i16;
+    let suggested = DecorationMetrics {
+        underline_position: (-em * 0.075).round() as i16,
+        underline_thickness: thickness,
+        strikeout_size: thickness,
+        strikeout_position: ((x_height as f32 + thickness as f32) / 2.0).round() as i16,
+    };
+    Ok(DecorationReport {
+        problems: check_decoration(&current, upem, x_height),
+        current,
+        suggested,
+        stem_glyph: stem.map(|(gid, _)| gid),
+        stem_width: stem.map(|(_, width)| width.round() as i32),
+        x_height,
+    })
+}
+
+/// Current underline and strikeout metrics, what is unusual about them, and
+/// values suggested from the em size, x-height and measured stem width.
+pub fn suggest_decoration_metrics(
+    file_path: &str,
+    cache: &FontCache,
+) -> Result<DecorationReport, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    decoration_report(&bytes)
+}
+
+/// Write the underline metrics into post and the strikeout metrics into
+/// OS/2 in one save. Stroke sizes must be positive.
+pub fn update_decoration_metrics(
+    file_path: &str,
+    metrics: DecorationMetrics,
+    cache: &FontCache,
+) -> Result<DecorationReport, FontError> {
+    if metrics.underline_thickness <= 0 || metrics.strikeout_size <= 0 {
+        return Err(FontError::input(
+            "Underline thickness and strikeout size must be positive",
+        ));
+    }
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let mut post: Post = font
+        .post()
+        .map_err(|e| FontError::table("post", e))?
+        .to_owned_table();
+    let mut os2: Os2 = font
+        .os2()
+        .map_err(|e| FontError::table("OS/2", e))?
+        .to_owned_table();
+    post.underline_position = FWord::new(metrics.underline_position);
+    post.underline_thickness = FWord::new(metrics.underline_thickness);
+    os2.y_strikeout_size = metrics.strikeout_size;
+    os2.y_strikeout_position = metrics.strikeout_position;
+
+    let new_bytes = FontBuilder::new()
+        .add_table(&post)
+        .map_err(|e| FontError::build("post", e))?
+        .add_table(&os2)
+        .map_err(|e| FontError::build("OS/2", e))?
+        .copy_missing_tables(font)
+        .build();
+    let report = decoration_report(&new_bytes)?;
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(report)
+}
+
+/// The OS/2 subscript and superscript octet: sizes are the scaled em, and
+/// offsets move the scaled glyphs (subscript y offsets are positive downward).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptMetrics {
+    pub subscript_x_size: i16,
+    pub subscript_y_size: i16,
+    pub subscript_x_offset: i16,
+    pub subscript_y_offset: i16,
+    pub superscript_x_size: i16,
+    pub superscript_y_size: i16,
+    pub superscript_x_offset: i16,
+    pub superscript_y_offset: i16,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ScriptMetricsReport {
+    pub current: ScriptMetrics,
+    pub suggested: ScriptMetrics,
+    pub x_height: i16,
+    pub x_height_source: HeightSource,
+    pub cap_height: i16,
+    pub cap_height_source: HeightSource,
+}
+
+fn script_metrics_report(bytes: &[u8]) -> Result<ScriptMetricsReport, FontError> {
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let upem = font
+        .head()
+        .map_err(|e| FontError::table("head", e))?
+        .units_per_em();
+    let os2 = font.os2().map_err(|e| FontError::table("OS/2", e))?;
+    let current = ScriptMetrics {
+        subscript_x_size: os2.y_subscript_x_size(),
+        subscript_y_size: os2.y_subscript_y_size(),
+        subscript_x_offset: os2.y_subscript_x_offset(),
+        subscript_y_offset: os2.y_subscript_y_offset(),
+        superscript_x_size: os2.y_superscript_x_size(),
+        superscript_y_size: os2.y_superscript_y_size(),
+        superscript_x_offset: os2.y_superscript_x_offset(),
+        superscript_y_offset: os2.y_superscript_y_offset(),
+    };
+    let (x_height, x_height_source) = x_height(&font, upem);
+    let (cap_height, cap_height_source) = cap_height(&font, upem);
+
+    // Scripts at 65% of the em. Superscripts are raised until their scaled
+    // x-height reaches the cap height; subscripts drop by 30% of the
+    // x-height. Italic fonts slide both along the slant.
+    const SCALE: f32 = 0.65;
+    let size = (upem as f32 * SCALE).round() as i16;
+    let superscript_y_offset = (cap_height as f32 - x_height as f32 * SCALE)
+        .max(0.0)
+        .round() as i16;
+    let subscript_y_offset = (x_height as f32 * 0.3).round() as i16;
+    let slant = -(font
+        .post()
+        .map_or(0.0, |post| post.italic_angle().to_f64())
+        .to_radians()
+        .tan()) as f32;
+    let suggested = ScriptMetrics {
+        subscript_x_size: size,
+        subscript_y_size: size,
+        subscript_x_offset: (-(subscript_y_offset as f32) * slant).round() as i16,
+        subscript_y_offset,
+        superscript_x_size: size,
+        superscript_y_size: size,
+        superscript_x_offset: (superscript_y_offset as f32 * slant).round() as i16,
+        superscript_y_offset,
+    };
+    Ok(ScriptMetricsReport {
+        current,
+        suggested,
+        x_height,
+        x_height_source,
+        cap_height,
+        cap_height_source,
+    })
+}
+
+/// Current OS/2 subscript and superscript metrics next to values computed
+/// from the em size, x-height and cap height.
+pub fn suggest_sub_superscript_metrics(
+    file_path: &str,
+    cache: &FontCache,
+) -> Result<ScriptMetricsReport, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    script_metrics_report(&bytes)
+}
+
+/// Write the subscript and superscript octet into OS/2. Sizes must be
+/// positive.
+pub fn update_sub_superscript_metrics(
+    file_path: &str,
+    metrics: ScriptMetrics,
+    cache: &FontCache,
+) -> Result<ScriptMetricsReport, FontError> {
+    let sizes = [
+        metrics.subscript_x_size,
+        metrics.subscript_y_size,
+        metrics.superscript_x_size,
+        metrics.superscript_y_size,
+    ];
+    if sizes.iter().any(|&size| size <= 0) {
+        return Err(FontError::input(
+            "Subscript and superscript sizes must be positive",
+        ));
+    }
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let font = RawFontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let mut os2: Os2 = font
+        .os2()
+        .map_err(|e| FontError::table("OS/2", e))?
+        .to_owned_table();
+    os2.y_subscript_x_size = metrics.subscript_x_size;
+    os2.y_subscript_y_size = metrics.subscript_y_size;
+    os2.y_subscript_x_offset = metrics.subscript_x_offset;
+    os2.y_subscript_y_offset = metrics.subscript_y_offset;
+    os2.y_superscript_x_size = metrics.superscript_x_size;
+    os2.y_superscript_y_size = metrics.superscript_y_size;
+    os2.y_superscript_x_offset = metrics.superscript_x_offset;
+    os2.y_superscript_y_offset = metrics.superscript_y_offset;
+
+    let new_bytes = FontBuilder::new()
+        .add_table(&os2)
+        .map_err(|e| FontError::build("OS/2", e))?
+        .copy_missing_tables(font)
+        .build();
+    let report = script_metrics_report(&new_bytes)?;
+    save_edited_font(file_path, new_bytes, cache)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::TestFontBuilder;
+
+    fn edit(glyph_id: u32) -> GlyphMetricsEdit {
+        GlyphMetricsEdit {
+            glyph_id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_update_glyph_metrics_bytes() {
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(Vec::new(), 500)
+            .glyph(Vec::new(), 500)
+            .build();
+        let edits = [
+            GlyphMetricsEdit {
+                delta_advance: Some(10),
+                ..edit(1)
+            },
+            GlyphMetricsEdit {
+                advance_width: Some(510),
+                delta_lsb: Some(-20),
+                ..edit(2)
+            },
+        ];
+        let (bytes, changes) = update_glyph_metrics_bytes(&font, &edits).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                GlyphMetricsChange {
+                    glyph_id: 1,
+                    old_advance_width: 500,
+                    new_advance_width: 510,
+                    old_lsb: 0,
+                    new_lsb: 0,
+                },
+                GlyphMetricsChange {
+                    glyph_id: 2,
+                    old_advance_width: 500,
+                    new_advance_width: 510,
+                    old_lsb: 0,
+                    new_lsb: -20,
+                },
+            ]
+        );
+
+        // The last two glyphs share an advance, so only two long metrics remain
+        let font = RawFontRef::new(&bytes).unwrap();
+        let hhea = font.hhea().unwrap();
+        assert_eq!(hhea.number_of_h_metrics(), 2);
+        assert_eq!(hhea.advance_width_max().to_u16(), 510);
+        assert_eq!(
+            read_metrics(&font, 3).unwrap(),
+            vec![(500, 0), (510, 0), (510, -20)]
+        );
+    }
+
+    #[test]
+    fn test_number_of_h_metrics_restructures_hmtx() {
+        use crate::font_parser::parse_font;
+        use crate::validate::{validate_font, Severity};
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("typebrew-hmetrics-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(Vec::new(), 600)
+            .glyph(Vec::new(), 700)
+            .glyph(Vec::new(), 700)
+            .build();
+        fs::write(path, font).unwrap();
+        let cache = FontCache::new();
+        parse_font(path, None, &cache).unwrap();
+        let layout = || {
+            let bytes = get_font_bytes(path, &cache).unwrap();
+            let font = RawFontRef::new(&bytes).unwrap();
+            let hmtx_len = font.table_data(Tag::new(b"hmtx")).unwrap().len();
+            let long_count = font.hhea().unwrap().number_of_h_metrics();
+            let advances: Vec<u16> = read_metrics(&font, 4)
+                .unwrap()
+                .iter()
+                .map(|m| m.0)
+                .collect();
+            (long_count, hmtx_len, advances)
+        };
+        let consistent = || {
+            let report = validate_font(path, &cache).unwrap();
+            !report
+                .findings
+                .iter()
+                .any(|f| f.severity == Severity::Error && (f.table == "hhea" || f.table == "hmtx"))
+        };
+
+        update_number_of_h_metrics(path, 3, false, &cache).unwrap();
+        assert_eq!(layout(), (3, 3 * 4 + 2, vec![500, 600, 700, 700]));
+        assert!(consistent());
+
+        // Glyph 1's advance would be lost
+        let err = update_number_of_h_metrics(path, 1, false, &cache).unwrap_err();
+        assert!(
+            err.to_string().contains("Glyph 1 has advance 600"),
+            "{}",
+            err
+        );
+        assert_eq!(layout().0, 3);
+
+        update_number_of_h_metrics(path, 1, true, &cache).unwrap();
+        assert_eq!(layout(), (1, 4 + 3 * 2, vec![500; 4]));
+        assert!(consistent());
+
+        // Growing gives each glyph its inherited advance
+        update_number_of_h_metrics(path, 4, false, &cache).unwrap();
+        assert_eq!(layout(), (4, 4 * 4, vec![500; 4]));
+        assert!(consistent());
+
+        assert!(update_number_of_h_metrics(path, 0, false, &cache).is_err());
+        assert!(update_number_of_h_metrics(path, 5, false, &cache).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recalc_hhea_clears_validation_warnings() {
+        use crate::font_parser::{parse_font, save_glyph_outline, SaveGlyphOutlineArgs};
+        use crate::test_fonts::simple_glyph;
+        use crate::validate::validate_font;
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("typebrew-hhea-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        let bar = simple_glyph(&[&[(50, 0, true), (50, 700, true), (250, 700, true)]]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(bar, 600)
+            .build();
+        fs::write(path, font).unwrap();
+        let hhea_warnings = |cache: &FontCache| {
+            validate_font(path, cache)
+                .unwrap()
+                .findings
+                .into_iter()
+                .filter(|f| f.table == "hhea")
+                .count()
+        };
+
+        let cache = FontCache::new();
+        parse_font(path, None, &cache).unwrap();
+        assert!(hhea_warnings(&cache) > 0);
+
+        let extents = recalc_hhea(path, &cache).unwrap();
+        assert_eq!(
+            extents,
+            HheaExtents {
+                advance_width_max: 600,
+                min_left_side_bearing: 50,
+                min_right_side_bearing: 350,
+                x_max_extent: 250,
+            }
+        );
+        assert_eq!(hhea_warnings(&cache), 0);
+
+        // With recalculation on, outline edits keep hhea in step
+        cache.set_recalc_hhea(true);
+        let args = SaveGlyphOutlineArgs {
+            glyph_id: 1,
+            svg_path: "M50 0 L50 -700 L550 -700 Z".to_string(),
+            table_name: "glyf".to_string(),
+            ..Default::default()
+        };
+        save_glyph_outline(path, &args, &cache).unwrap();
+        assert_eq!(hhea_warnings(&cache), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recalc_x_avg_char_width_skips_zero_advances() {
+        let os2 = write_fonts::dump_table(&Os2::default()).unwrap();
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 0)
+            .glyph(Vec::new(), 500)
+            .glyph(Vec::new(), 600)
+            .glyph(Vec::new(), 0)
+            .glyph(Vec::new(), 651)
+            .table(b"OS/2", os2)
+            .build();
+
+        // (500 + 600 + 651) / 3 = 583.67; zero-width glyphs would pull it to 350
+        let bytes = recalc_x_avg_char_width(font).unwrap();
+        let font = RawFontRef::new(&bytes).unwrap();
+        assert_eq!(font.os2().unwrap().x_avg_char_width(), 584);
+
+        let (edited, _) = update_glyph_metrics_bytes(
+            &bytes,
+            &[GlyphMetricsEdit {
+                advance_width: Some(949),
+                ..edit(4)
+            }],
+        )
+        .unwrap();
+        let edited = recalc_x_avg_char_width(edited).unwrap();
+        let font = RawFontRef::new(&edited).unwrap();
+        assert_eq!(font.os2().unwrap().x_avg_char_width(), 683);
+    }
+
+    #[test]
+    fn test_update_glyph_metrics_rejects_bad_edits() {
+        let font = TestFontBuilder::new().glyph(Vec::new(), 500).build();
+        let rejected =
+            |edits: &[GlyphMetricsEdit]| update_glyph_metrics_bytes(&font, edits).unwrap_err();
+
+        let twice = [edit(0), edit(0)];
+        assert_eq!(rejected(&twice).kind(), "invalid_input");
+        let both = [GlyphMetricsEdit {
+            advance_width: Some(600),
+            delta_advance: Some(10),
+            ..edit(0)
+        }];
+        assert_eq!(rejected(&both).kind(), "invalid_input");
+        let negative = [GlyphMetricsEdit {
+            delta_advance: Some(-501),
+            ..edit(0)
+        }];
+        assert_eq!(rejected(&negative).kind(), "invalid_input");
+        assert_eq!(rejected(&[edit(5)]), FontError::GlyphNotFound { gid: 5 });
+    }
+
+    #[test]
+    fn test_set_monospaced() {
+        use crate::test_fonts::{composite_glyph, simple_glyph, TestComponent};
+
+        let dir = std::env::temp_dir().join(format!("typebrew-mono-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        // post version 3 with isFixedPitch = 0
+        let mut post = vec![0, 3, 0, 0];
+        post.extend([0u8; 28]);
+        let mut os2 = Os2::default();
+        os2.panose_10[0] = 2;
+        let os2 = write_fonts::dump_table(&os2).unwrap();
+        let bar = simple_glyph(&[&[(100, 0, true), (100, 500, true), (200, 500, true)]]);
+        let wide = simple_glyph(&[&[(0, 0, true), (350, 700, false), (700, 0, true)]]);
+        let accent = simple_glyph(&[&[(-80, 600, true), (-20, 700, true), (-50, 650, true)]]);
+        let mut composite = composite_glyph(&[TestComponent {
+            glyph_id: 1,
+            dx: 50,
+            dy: 0,
+            transform: None,
+        }]);
+        // Outlines are placed relative to the header xMin, so it has to be real
+        composite[2..10].copy_from_slice(&[0, 150, 0, 0, 0, 250, 1, 244]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(bar, 300)
+            .glyph(wide, 800)
+            .glyph(composite, 400)
+            .glyph(accent, 0)
+            .table(b"post", post)
+            .table(b"OS/2", os2)
+            .build();
+        std::fs::write(path, font).unwrap();
+        let cache = FontCache::new();
+
+        let report = set_monospaced(path, 600, true, &cache).unwrap();
+        assert_eq!(report.glyphs_changed, 4);
+        assert_eq!(
+            report.too_wide,
+            vec![WideGlyph {
+                glyph_id: 2,
+                width: 700
+            }]
+        );
+        let bytes = get_font_bytes(path, &cache).unwrap();
+        let font = RawFontRef::new(&bytes).unwrap();
+        let metrics = read_metrics(&font, 5).unwrap();
+        assert_eq!(
+            metrics,
+            vec![(600, 0), (600, 250), (600, -50), (600, 250), (0, -80)]
+        );
+        assert_eq!(font.post().unwrap().is_fixed_pitch(), 1);
+        assert_eq!(font.os2().unwrap().panose_10()[3], 9);
+        assert_eq!(font.hhea().unwrap().advance_width_max().to_u16(), 600);
+        // The composite sits where its own centering put it, not where its
+        // component's shift would have taken it
+        let x_range = |gid: u32| {
+            let (_, b) = outline_bounds(&bytes)
+                .unwrap()
+                .into_iter()
+                .find(|(g, _)| *g == gid)
+                .unwrap();
+            (b.x_min, b.x_max)
+        };
+        assert_eq!(x_range(1), (250.0, 350.0));
+        assert_eq!(x_range(2), (-50.0, 650.0));
+        assert_eq!(x_range(3), (250.0, 350.0));
+        assert_eq!(x_range(4), (-80.0, -20.0));
+
+        clear_monospaced(path, &cache).unwrap();
+        let bytes = get_font_bytes(path, &cache).unwrap();
+        let font = RawFontRef::new(&bytes).unwrap();
+        assert_eq!(font.post().unwrap().is_fixed_pitch(), 0);
+        assert_eq!(font.os2().unwrap().panose_10()[3], 0);
+        assert_eq!(read_metrics(&font, 5).unwrap()[1], (600, 250));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_vertical_metrics_strategies() {
+        use crate::test_fonts::simple_glyph;
+
+        let dir = std::env::temp_dir().join(format!("typebrew-vmetrics-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        let os2 = Os2 {
+            s_typo_ascender: 800,
+            s_typo_descender: -200,
+            s_typo_line_gap: 101,
+            us_win_ascent: 850,
+            us_win_descent: 250,
+            ..Default::default()
+        };
+        let tall = simple_glyph(&[&[(0, -200, true), (0, 900, true), (300, 900, true)]]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(tall, 500)
+            .table(b"OS/2", write_fonts::dump_table(&os2).unwrap())
+            .build();
+        std::fs::write(path, font).unwrap();
+        let cache = FontCache::new();
+
+        let before = check_vertical_metrics(path, &cache).unwrap();
+        assert_eq!(
+            (before.glyph_y_min, before.glyph_y_max),
+            (Some(-200), Some(900))
+        );
+        assert!(!before.use_typo_metrics);
+        let messages: Vec<&str> = before
+            .problems
+            .findings
+            .iter()
+            .map(|f| f.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "usWinAscent is below the tallest glyph; Windows clips it",
+                "hhea and typo metrics differ while USE_TYPO_METRICS is off, so line spacing varies by platform",
+            ]
+        );
+
+        // The 101 unit gap is split between ascender and descender
+        let after =
+            apply_vertical_metrics_strategy(path, VerticalMetricsStrategy::GoogleFonts, &cache)
+                .unwrap();
+        assert_eq!(
+            (
+                after.typo_ascender,
+                after.typo_descender,
+                after.typo_line_gap
+            ),
+            (Some(851), Some(-250), Some(0))
+        );
+        assert_eq!(
+            (
+                after.hhea_ascender,
+                after.hhea_descender,
+                after.hhea_line_gap
+            ),
+            (851, -250, 0)
+        );
+        assert_eq!(
+            (after.win_ascent, after.win_descent),
+            (Some(900), Some(200))
+        );
+        assert!(after.use_typo_metrics);
+        assert!(after.problems.findings.is_empty());
+        let bytes = get_font_bytes(path, &cache).unwrap();
+        assert_eq!(RawFontRef::new(&bytes).unwrap().os2().unwrap().version(), 4);
+
+        let after =
+            apply_vertical_metrics_strategy(path, VerticalMetricsStrategy::BoundingBox, &cache)
+                .unwrap();
+        assert_eq!(
+            (after.typo_ascender, after.typo_descender),
+            (Some(900), Some(-200))
+        );
+        assert_eq!((after.hhea_ascender, after.hhea_descender), (900, -200));
+        assert!(after.problems.findings.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_autospace_glyphs() {
+        use crate::test_fonts::{composite_glyph, simple_glyph, TestComponent};
+
+        let dir = std::env::temp_dir().join(format!("typebrew-autospace-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        let h = simple_glyph(&[&[(50, 0, true), (50, 700, true), (550, 700, true)]]);
+        let o = simple_glyph(&[&[(10, 0, true), (10, 700, true), (510, 700, true)]]);
+        let mut o_accent = composite_glyph(&[TestComponent {
+            glyph_id: 2,
+            dx: 0,
+            dy: 0,
+            transform: None,
+        }]);
+        o_accent[2..10].copy_from_slice(&[0, 10, 0, 0, 1, 254, 2, 188]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(h, 600)
+            .glyph(o, 560)
+            .glyph(o_accent, 560)
+            .map('H' as u32, 1)
+            .map('O' as u32, 2)
+            .build();
+        std::fs::write(path, font).unwrap();
+        let cache = FontCache::new();
+
+        let rules = [
+            SpacingRule {
+                glyphs: GlyphSelection::Category(GlyphCategory::Uppercase),
+                reference: 1,
+                lsb_factor: 1.0,
+                rsb_factor: 1.0,
+            },
+            SpacingRule {
+                glyphs: GlyphSelection::Glyphs(vec![2]),
+                reference: 1,
+                lsb_factor: 0.8,
+                rsb_factor: 0.8,
+            },
+        ];
+        let changes = autospace_glyphs(path, &rules, &cache).unwrap();
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|c| {
+                (
+                    c.glyph_id,
+                    c.inherited_from,
+                    c.new_advance_width,
+                    c.new_lsb,
+                    c.new_rsb,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, None, 600, 50, 50),
+                (2, None, 580, 40, 40),
+                (3, Some(2), 580, 40, 40),
+            ]
+        );
+        let bytes = get_font_bytes(path, &cache).unwrap();
+        let font = RawFontRef::new(&bytes).unwrap();
+        assert_eq!(
+            read_metrics(&font, 4).unwrap(),
+            vec![(500, 0), (600, 50), (580, 40), (580, 40)]
+        );
+        let bounds = outline_bounds(&bytes).unwrap();
+        let x_range = |gid: u32| {
+            let (_, b) = bounds.iter().find(|(g, _)| *g == gid).unwrap();
+            (b.x_min, b.x_max)
+        };
+        assert_eq!(x_range(2), (40.0, 540.0));
+        assert_eq!(x_range(3), (40.0, 540.0));
+
+        let bad = SpacingRule {
+            glyphs: GlyphSelection::Glyphs(vec![2]),
+            reference: 0,
+            lsb_factor: 1.0,
+            rsb_factor: 1.0,
+        };
+        assert!(matches!(
+            autospace_glyphs(path, &[bad], &cache),
+            Err(FontError::InvalidInput { .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_decoration_metrics() {
+        use crate::test_fonts::simple_glyph;
+
+        let dir = std::env::temp_dir().join(format!("typebrew-decoration-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        // post version 3 with the underline 50 units above the baseline and
+        // no thickness
+        let mut post = vec![0, 3, 0, 0, 0, 0, 0, 0, 0, 50];
+        post.extend([0u8; 22]);
+        let os2 = Os2 {
+            y_strikeout_size: 50,
+            y_strikeout_position: 450,
+            ul_code_page_range_1: Some(0),
+            ul_code_page_range_2: Some(0),
+            sx_height: Some(500),
+            s_cap_height: Some(700),
+            us_default_char: Some(0),
+            us_break_char: Some(0x20),
+            us_max_context: Some(0),
+            ..Default::default()
+        };
+        // An 80 unit stem with a wider foot serif overlapping it
+        let l = simple_glyph(&[
+            &[
+                (100, 0, true),
+                (100, 750, true),
+                (180, 750, true),
+                (180, 0, true),
+            ],
+            &[
+                (60, 0, true),
+                (60, 40, true),
+                (220, 40, true),
+                (220, 0, true),
+            ],
+        ]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(l, 280)
+            .map('l' as u32, 1)
+            .table(b"post", post)
+            .table(b"OS/2", write_fonts::dump_table(&os2).unwrap())
+            .build();
+        std::fs::write(path, font).unwrap();
+        let cache = FontCache::new();
+
+        let report = suggest_decoration_metrics(path, &cache).unwrap();
+        assert_eq!((report.stem_glyph, report.stem_width), (Some(1), Some(80)));
+        assert_eq!(report.x_height, 500);
+        assert_eq!(
+            report.suggested,
+            DecorationMetrics {
+                underline_position: -75,
+                underline_thickness: 48,
+                strikeout_size: 48,
+                strikeout_position: 274,
+            }
+        );
+        let messages: Vec<&str> = report
+            .problems
+            .findings
+            .iter()
+            .map(|f| f.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "underlineThickness is not positive",
+                "underlinePosition is not below the baseline",
+                "Strikeout is not centred near half the x-height",
+            ]
+        );
+
+        let updated = update_decoration_metrics(path, report.suggested, &cache).unwrap();
+        assert_eq!(updated.current, report.suggested);
+        assert!(updated.problems.findings.is_empty());
+        let bytes = get_font_bytes(path, &cache).unwrap();
+        let font = RawFontRef::new(&bytes).unwrap();
+        assert_eq!(font.post().unwrap().underline_thickness().to_i16(), 48);
+        assert_eq!(font.os2().unwrap().y_strikeout_position(), 274);
+
+        let flat = DecorationMetrics {
+            strikeout_size: 0,
+            ..report.suggested
+        };
+        assert!(matches!(
+            update_decoration_metrics(path, flat, &cache),
+            Err(FontError::InvalidInput { .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sub_superscript_metrics_measure_old_os2() {
+        use crate::test_fonts::simple_glyph;
+
+        let dir = std::env::temp_dir().join(format!("typebrew-scripts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("font.ttf");
+        let path = path.to_str().unwrap();
+        // Version 1 OS/2 has no sxHeight or sCapHeight
+        let os2 = Os2 {
+            ul_code_page_range_1: Some(0),
+            ul_code_page_range_2: Some(0),
+            ..Default::default()
+        };
+        // post version 3 with a -12 degree italic angle
+        let mut post = vec![0, 3, 0, 0, 0xFF, 0xF4, 0, 0];
+        post.extend([0u8; 24]);
+        let x = simple_glyph(&[&[(0, 0, true), (250, 480, true), (500, 0, true)]]);
+        let h = simple_glyph(&[&[
+            (0, 0, true),
+            (0, 700, true),
+            (600, 700, true),
+            (600, 0, true),
+        ]]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(x, 500)
+            .glyph(h, 600)
+            .map('x' as u32, 1)
+            .map('H' as u32, 2)
+            .table(b"OS/2", write_fonts::dump_table(&os2).unwrap())
+            .table(b"post", post)
+            .build();
+        std::fs::write(path, font).unwrap();
+        let cache = FontCache::new();
+
+        let report = suggest_sub_superscript_metrics(path, &cache).unwrap();
+        assert_eq!(
+            (report.x_height, report.x_height_source),
+            (480, HeightSource::Outline)
+        );
+        assert_eq!(
+            (report.cap_height, report.cap_height_source),
+            (700, HeightSource::Outline)
+        );
+        assert_eq!(report.current.superscript_y_size, 0);
+        let expected = ScriptMetrics {
+            subscript_x_size: 650,
+            subscript_y_size: 650,
+            subscript_x_offset: -31,
+            subscript_y_offset: 144,
+            superscript_x_size: 650,
+            superscript_y_size: 650,
+            superscript_x_offset: 82,
+            superscript_y_offset: 388,
+        };
+        assert_eq!(report.suggested, expected);
+
+        let updated = update_sub_superscript_metrics(path, report.suggested, &cache).unwrap();
+        assert_eq!(updated.current, expected);
+        let bytes = get_font_bytes(path, &cache).unwrap();
+        let os2 = RawFontRef::new(&bytes).unwrap().os2().unwrap();
+        assert_eq!(os2.version(), 1);
+        assert_eq!(os2.y_superscript_y_offset(), 388);
+
+        let squashed = ScriptMetrics {
+            subscript_y_size: 0,
+            ..expected
+        };
+        assert!(matches!(
+            update_sub_superscript_metrics(path, squashed, &cache),
+            Err(FontError::InvalidInput { .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
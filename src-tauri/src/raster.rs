@@ -0,0 +1,1036 @@
+use crate::error::FontError;
+use crate::font_parser::{get_font_bytes, FontCache, SvgPathPen};
+use crate::hinting::gasp_behavior;
+use crate::layout::{layout_text, line_width};
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use skrifa::instance::{LocationRef, Size};
+use skrifa::metrics::Metrics;
+use skrifa::outline::{
+    DrawSettings, Engine, HintingInstance, HintingOptions, OutlineGlyphCollection, OutlinePen,
+    Target,
+};
+use skrifa::raw::TableProvider;
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::HashMap;
+
+/// Number of line segments used to flatten each quadratic / cubic curve.
+const CURVE_SEGMENTS: usize = 16;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelFormat {
+    /// Black ink on a white background, one byte per pixel.
+    #[default]
+    Gray,
+    /// Black ink on a transparent background, with coloured metric guides.
+    Rgba,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RenderOptions {
+    /// Samples per pixel along each axis (1 = aliased, 4 = 16 samples per pixel).
+    pub oversample: u8,
+    /// Draw through the font's hinting instructions at the requested ppem.
+    pub hinted: bool,
+    /// Bake baseline, ascender/descender and advance guides into the image.
+    pub guides: bool,
+    pub format: PixelFormat,
+    /// Blank border in pixels around the glyph box.
+    pub padding: u32,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            oversample: 4,
+            hinted: false,
+            guides: false,
+            format: PixelFormat::Gray,
+            padding: 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Edge {
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+}
+
+/// Pen that flattens outlines into line edges in device space (y down),
+/// translated by the given origin.
+pub struct EdgePen {
+    edges: Vec<Edge>,
+    origin_x: f32,
+    origin_y: f32,
+    start: (f32, f32),
+    current: (f32, f32),
+}
+
+impl EdgePen {
+    pub fn new() -> Self {
+        Self {
+            edges: Vec::new(),
+            origin_x: 0.0,
+            origin_y: 0.0,
+            start: (0.0, 0.0),
+            current: (0.0, 0.0),
+        }
+    }
+
+    /// Set the device-space position of the glyph origin for subsequent drawing.
+    pub fn set_origin(&mut self, x: f32, y: f32) {
+        self.origin_x = x;
+        self.origin_y = y;
+    }
+
+    fn to_device(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.origin_x + x, self.origin_y - y)
+    }
+
+    fn push_line(&mut self, to: (f32, f32)) {
+        let from = self.current;
+        if from.1 != to.1 {
+            self.edges.push(Edge {
+                x0: from.0,
+                y0: from.1,
+                x1: to.0,
+                y1: to.1,
+            });
+        }
+        self.current = to;
+    }
+
+    /// Sorted edge crossings of the device row `y`, with winding directions.
+    fn crossings(&self, y: f32, out: &mut Vec<(f32, i32)>) {
+        out.clear();
+        for e in &self.edges {
+            let (top, bottom, dir) = if e.y0 < e.y1 {
+                (e.y0, e.y1, 1)
+            } else {
+                (e.y1, e.y0, -1)
+            };
+            if y < top || y >= bottom {
+                continue;
+            }
+            let x = e.x0 + (y - e.y0) * (e.x1 - e.x0) / (e.y1 - e.y0);
+            out.push((x, dir));
+        }
+        out.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+
+    /// Lowest and highest glyph-space y reached by the outline, relative to
+    /// the origin.
+    pub(crate) fn y_extent(&self) -> Option<(f32, f32)> {
+        self.edges
+            .iter()
+            .flat_map(|e| [e.y0, e.y1])
+            .map(|y| self.origin_y - y)
+            .fold(None, |acc, y| match acc {
+                None => Some((y, y)),
+                Some((lo, hi)) => Some((y.min(lo), y.max(hi))),
+            })
+    }
+
+    /// Inked spans `(x_start, x_end)` along the horizontal line at glyph-space
+    /// height `y` under the non-zero winding rule, relative to the origin.
+    pub(crate) fn ink_spans(&self, y: f32) -> Vec<(f32, f32)> {
+        let mut crossings = Vec::new();
+        self.crossings(self.origin_y - y, &mut crossings);
+        let mut spans: Vec<(f32, f32)> = Vec::new();
+        let mut winding = 0;
+        for pair in crossings.windows(2) {
+            winding += pair[0].1;
+            if winding == 0 {
+                continue;
+            }
+            let (start, end) = (pair[0].0 - self.origin_x, pair[1].0 - self.origin_x);
+            match spans.last_mut() {
+                Some(last) if last.1 == start => last.1 = end,
+                _ => spans.push((start, end)),
+            }
+        }
+        spans
+    }
+}
+
+impl OutlinePen for EdgePen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        if self.current != self.start {
+            self.push_line(self.start);
+        }
+        let p = self.to_device(x, y);
+        self.start = p;
+        self.current = p;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.to_device(x, y);
+        self.push_line(p);
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        let p0 = self.current;
+        let c = self.to_device(cx0, cy0);
+        let p1 = self.to_device(x, y);
+        for i in 1..=CURVE_SEGMENTS {
+            let t = i as f32 / CURVE_SEGMENTS as f32;
+            let mt = 1.0 - t;
+            let px = mt * mt * p0.0 + 2.0 * mt * t * c.0 + t * t * p1.0;
+            let py = mt * mt * p0.1 + 2.0 * mt * t * c.1 + t * t * p1.1;
+            self.push_line((px, py));
+        }
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        let p0 = self.current;
+        let c0 = self.to_device(cx0, cy0);
+        let c1 = self.to_device(cx1, cy1);
+        let p1 = self.to_device(x, y);
+        for i in 1..=CURVE_SEGMENTS {
+            let t = i as f32 / CURVE_SEGMENTS as f32;
+            let mt = 1.0 - t;
+            let a = mt * mt * mt;
+            let b = 3.0 * mt * mt * t;
+            let c = 3.0 * mt * t * t;
+            let d = t * t * t;
+            let px = a * p0.0 + b * c0.0 + c * c1.0 + d * p1.0;
+            let py = a * p0.1 + b * c0.1 + c * c1.1 + d * p1.1;
+            self.push_line((px, py));
+        }
+    }
+
+    fn close(&mut self) {
+        if self.current != self.start {
+            self.push_line(self.start);
+        }
+    }
+}
+
+/// 8-bit coverage bitmap (0 = empty, 255 = fully inked), row-major, y down.
+pub struct Bitmap {
+    pub width: u32,
+    pub height: u32,
+    pub coverage: Vec<u8>,
+}
+
+impl Bitmap {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            coverage: vec![0; (width * height) as usize],
+        }
+    }
+
+    /// Fill the edges collected by `pen` with the non-zero winding rule, adding
+    /// coverage onto what is already in the bitmap.
+    pub fn fill(&mut self, pen: &EdgePen, oversample: u8) {
+        let os = oversample.max(1) as u32;
+        let samples_per_pixel = (os * os) as f32;
+        let width = self.width as usize;
+        let mut acc = vec![0u16; width];
+        let mut crossings: Vec<(f32, i32)> = Vec::new();
+
+        for row in 0..self.height {
+            acc.iter_mut().for_each(|a| *a = 0);
+            for sub in 0..os {
+                let yc = row as f32 + (sub as f32 + 0.5) / os as f32;
+                pen.crossings(yc, &mut crossings);
+                if crossings.is_empty() {
+                    continue;
+                }
+
+                let mut winding = 0;
+                for pair in crossings.windows(2) {
+                    winding += pair[0].1;
+                    if winding == 0 {
+                        continue;
+                    }
+                    // Sample columns whose centres fall inside [x_start, x_end)
+                    let x_start = pair[0].0 * os as f32 - 0.5;
+                    let x_end = pair[1].0 * os as f32 - 0.5;
+                    let first = x_start.ceil().max(0.0) as u32;
+                    let last = (x_end.ceil() as i64).min((self.width * os) as i64);
+                    for sx in first as i64..last {
+                        acc[(sx as u32 / os) as usize] += 1;
+                    }
+                }
+            }
+            let base = row as usize * width;
+            for (x, &count) in acc.iter().enumerate() {
+                if count > 0 {
+                    let add = (count as f32 / samples_per_pixel * 255.0).round() as u16;
+                    let v = self.coverage[base + x] as u16 + add;
+                    self.coverage[base + x] = v.min(255) as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Horizontal or vertical guide line baked into RGBA output.
+struct Guide {
+    horizontal: bool,
+    position: i64,
+    color: [u8; 3],
+}
+
+fn compose_pixels(bitmap: &Bitmap, format: PixelFormat, guides: &[Guide]) -> Vec<u8> {
+    let (w, h) = (bitmap.width as i64, bitmap.height as i64);
+    match format {
+        PixelFormat::Gray => {
+            let mut out: Vec<u8> = bitmap.coverage.iter().map(|c| 255 - c).collect();
+            for g in guides {
+                let shade = 200u8;
+                let (count, pos) = if g.horizontal {
+                    (w, g.position)
+                } else {
+                    (h, g.position)
+                };
+                if pos < 0 || pos >= if g.horizontal { h } else { w } {
+                    continue;
+                }
+                for i in 0..count {
+                    let idx = if g.horizontal {
+                        pos * w + i
+                    } else {
+                        i * w + pos
+                    } as usize;
+                    out[idx] = out[idx].min(shade);
+                }
+            }
+            out
+        }
+        PixelFormat::Rgba => {
+            let mut out = vec![0u8; bitmap.coverage.len() * 4];
+            for g in guides {
+                let (count, pos) = if g.horizontal {
+                    (w, g.position)
+                } else {
+                    (h, g.position)
+                };
+                if pos < 0 || pos >= if g.horizontal { h } else { w } {
+                    continue;
+                }
+                for i in 0..count {
+                    let idx = if g.horizontal {
+                        pos * w + i
+                    } else {
+                        i * w + pos
+                    } as usize
+                        * 4;
+                    out[idx..idx + 3].copy_from_slice(&g.color);
+                    out[idx + 3] = 160;
+                }
+            }
+            // Ink composited over the guides
+            for (i, &c) in bitmap.coverage.iter().enumerate() {
+                if c == 0 {
+                    continue;
+                }
+                let px = &mut out[i * 4..i * 4 + 4];
+                let a = c as f32 / 255.0;
+                for channel in px.iter_mut().take(3) {
+                    *channel = (*channel as f32 * (1.0 - a)).round() as u8;
+                }
+                px[3] = (c as f32 + px[3] as f32 * (1.0 - a)).round().min(255.0) as u8;
+            }
+            out
+        }
+    }
+}
+
+/// Encode a bitmap as PNG in the requested pixel format.
+fn encode_png(
+    bitmap: &Bitmap,
+    format: PixelFormat,
+    guides: &[Guide],
+) -> Result<Vec<u8>, FontError> {
+    let pixels = compose_pixels(bitmap, format, guides);
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, bitmap.width, bitmap.height);
+        encoder.set_color(match format {
+            PixelFormat::Gray => png::ColorType::Grayscale,
+            PixelFormat::Rgba => png::ColorType::Rgba,
+        });
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| FontError::other(format!("Failed to write PNG header: {}", e)))?;
+        writer
+            .write_image_data(&pixels)
+            .map_err(|e| FontError::other(format!("Failed to write PNG data: {}", e)))?;
+    }
+    Ok(out)
+}
+
+/// Encode a plain coverage bitmap as a black-on-white grayscale PNG.
+pub fn encode_bitmap_png(bitmap: &Bitmap) -> Result<Vec<u8>, FontError> {
+    encode_png(bitmap, PixelFormat::Gray, &[])
+}
+
+fn mono_hinting_instance(
+    outlines: &OutlineGlyphCollection<'_>,
+    ppem: f32,
+) -> Result<HintingInstance, FontError> {
+    let options = HintingOptions {
+        engine: Engine::Interpreter,
+        target: Target::Mono,
+    };
+    HintingInstance::new(outlines, Size::new(ppem), LocationRef::default(), options)
+        .map_err(|e| FontError::invalid_data(format!("Hint init {}px: {:?}", ppem, e)))
+}
+
+/// Bitmap size and pixel-aligned glyph origin that fit a glyph drawn at the
+/// origin into `probe`, its advance and the font's ascent and descent, with
+/// `padding` blank pixels around.
+struct Canvas {
+    origin_x: f32,
+    origin_y: f32,
+    width: u32,
+    height: u32,
+}
+
+impl Canvas {
+    fn around(probe: &EdgePen, advance: f32, metrics: &Metrics, padding: u32) -> Self {
+        let (mut x_min, mut x_max) = (0.0f32, advance.max(0.0));
+        let (mut y_min, mut y_max) = (-metrics.ascent, -metrics.descent);
+        for e in &probe.edges {
+            x_min = x_min.min(e.x0.min(e.x1));
+            x_max = x_max.max(e.x0.max(e.x1));
+            y_min = y_min.min(e.y0.min(e.y1));
+            y_max = y_max.max(e.y0.max(e.y1));
+        }
+        let pad = padding as f32;
+        Self {
+            origin_x: (pad - x_min.floor()).round(),
+            origin_y: (pad - y_min.floor()).round(),
+            width: (x_max.ceil() - x_min.floor() + 2.0 * pad).max(1.0) as u32,
+            height: (y_max.ceil() - y_min.floor() + 2.0 * pad).max(1.0) as u32,
+        }
+    }
+}
+
+fn check_ppem(ppem: f32) -> Result<(), FontError> {
+    if ppem > 0.0 && ppem <= 2048.0 {
+        Ok(())
+    } else {
+        Err(FontError::input(format!(
+            "ppem {} out of range (0, 2048]",
+            ppem
+        )))
+    }
+}
+
+fn rasterize_glyph_png(
+    bytes: &[u8],
+    glyph_id: u32,
+    ppem: f32,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, FontError> {
+    check_ppem(ppem)?;
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let outlines = font.outline_glyphs();
+    let gid = GlyphId::from(glyph_id);
+    let glyph = outlines
+        .get(gid)
+        .ok_or(FontError::GlyphNotFound { gid: glyph_id })?;
+
+    let size = Size::new(ppem);
+    let metrics = font.metrics(size, LocationRef::default());
+    let mut advance = font
+        .glyph_metrics(size, LocationRef::default())
+        .advance_width(gid)
+        .unwrap_or(0.0);
+
+    // First pass at the origin to learn the device-space extents
+    let instance = if options.hinted {
+        Some(mono_hinting_instance(&outlines, ppem)?)
+    } else {
+        None
+    };
+    let draw = |pen: &mut EdgePen| {
+        let settings = match &instance {
+            Some(instance) => DrawSettings::hinted(instance, false),
+            None => DrawSettings::unhinted(size, LocationRef::default()),
+        };
+        glyph.draw(settings, pen).map_err(|e| {
+            FontError::invalid_data(format!("Failed to draw glyph {}: {:?}", glyph_id, e))
+        })
+    };
+
+    let mut probe = EdgePen::new();
+    let adjusted = draw(&mut probe)?;
+    if let Some(aw) = adjusted.advance_width {
+        advance = aw;
+    }
+
+    let canvas = Canvas::around(&probe, advance, &metrics, options.padding);
+    let (origin_x, origin_y) = (canvas.origin_x, canvas.origin_y);
+    let mut pen = EdgePen::new();
+    pen.set_origin(origin_x, origin_y);
+    draw(&mut pen)?;
+
+    let mut bitmap = Bitmap::new(canvas.width, canvas.height);
+    bitmap.fill(&pen, options.oversample);
+
+    let guides = if options.guides {
+        vec![
+            Guide {
+                horizontal: true,
+                position: origin_y as i64,
+                color: [220, 40, 40],
+            },
+            Guide {
+                horizontal: true,
+                position: (origin_y - metrics.ascent).round() as i64,
+                color: [40, 120, 220],
+            },
+            Guide {
+                horizontal: true,
+                position: (origin_y - metrics.descent).round() as i64,
+                color: [40, 120, 220],
+            },
+            Guide {
+                horizontal: false,
+                position: origin_x as i64,
+                color: [40, 160, 80],
+            },
+            Guide {
+                horizontal: false,
+                position: (origin_x + advance).round() as i64,
+                color: [40, 160, 80],
+            },
+        ]
+    } else {
+        Vec::new()
+    };
+
+    encode_png(&bitmap, options.format, &guides)
+}
+
+pub fn render_glyph_png(
+    file_path: &str,
+    glyph_id: u32,
+    ppem: f32,
+    options: &RenderOptions,
+    cache: &FontCache,
+) -> Result<Vec<u8>, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    rasterize_glyph_png(&bytes, glyph_id, ppem, options)
+}
+
+// ── Hinted pixel grid ─────────────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GridMode {
+    /// On/off pixels, lit when the pixel centre is inside the outline.
+    Mono,
+    /// 8-bit coverage from 4x4 samples per pixel.
+    #[default]
+    Gray,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HintedGlyphGrid {
+    pub ppem: f32,
+    pub mode: GridMode,
+    pub width: u32,
+    pub height: u32,
+    /// Pixel position of the glyph origin (left side bearing line on the
+    /// baseline), counted from the top-left corner.
+    pub origin_x: i32,
+    pub origin_y: i32,
+    /// False when gasp is honored and turns grid-fitting off at this size;
+    /// the pixels and outline are then merely scaled.
+    pub hinted: bool,
+    /// Hinted advance in pixels.
+    pub advance: f32,
+    /// Base64 of the pixels, rows top to bottom. Gray: one coverage byte
+    /// per pixel. Mono: one bit per pixel, most significant bit first, each
+    /// row padded to a whole byte.
+    pub pixels: String,
+    /// Hinted outline in pixels relative to the glyph origin (Y negated),
+    /// to overlay on the grid.
+    pub svg_path: String,
+}
+
+/// Rows of one bit per pixel, most significant bit first.
+fn pack_mono(bitmap: &Bitmap) -> Vec<u8> {
+    let stride = bitmap.width.div_ceil(8) as usize;
+    let mut packed = vec![0u8; stride * bitmap.height as usize];
+    for (y, row) in bitmap.coverage.chunks(bitmap.width as usize).enumerate() {
+        for (x, &c) in row.iter().enumerate() {
+            if c >= 128 {
+                packed[y * stride + x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+    }
+    packed
+}
+
+fn rasterize_hinted_grid(
+    bytes: &[u8],
+    glyph_id: u32,
+    ppem: f32,
+    mode: GridMode,
+    honor_gasp: bool,
+) -> Result<HintedGlyphGrid, FontError> {
+    check_ppem(ppem)?;
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let outlines = font.outline_glyphs();
+    let glyph = outlines
+        .get(GlyphId::from(glyph_id))
+        .ok_or(FontError::GlyphNotFound { gid: glyph_id })?;
+    let size = Size::new(ppem);
+    let metrics = font.metrics(size, LocationRef::default());
+    let hinted = !honor_gasp || gasp_behavior(&font, ppem.round() as u16).gridfits(false);
+    let instance = if hinted {
+        Some(mono_hinting_instance(&outlines, ppem)?)
+    } else {
+        None
+    };
+    // Hinted outlines come out in pixels around the origin; a whole-pixel
+    // origin keeps them on the bitmap's grid
+    let settings = || match &instance {
+        Some(instance) => DrawSettings::hinted(instance, false),
+        None => DrawSettings::unhinted(size, LocationRef::default()),
+    };
+    let failed = |e| FontError::invalid_data(format!("Failed to draw glyph {}: {:?}", glyph_id, e));
+
+    let mut svg = SvgPathPen::new();
+    let adjusted = glyph.draw(settings(), &mut svg).map_err(failed)?;
+    let advance = adjusted.advance_width.unwrap_or_else(|| {
+        font.glyph_metrics(size, LocationRef::default())
+            .advance_width(GlyphId::from(glyph_id))
+            .unwrap_or(0.0)
+    });
+    let mut probe = EdgePen::new();
+    glyph.draw(settings(), &mut probe).map_err(failed)?;
+    let canvas = Canvas::around(&probe, advance, &metrics, 1);
+    let mut pen = EdgePen::new();
+    pen.set_origin(canvas.origin_x, canvas.origin_y);
+    glyph.draw(settings(), &mut pen).map_err(failed)?;
+
+    let mut bitmap = Bitmap::new(canvas.width, canvas.height);
+    let pixels = match mode {
+        GridMode::Mono => {
+            bitmap.fill(&pen, 1);
+            pack_mono(&bitmap)
+        }
+        GridMode::Gray => {
+            bitmap.fill(&pen, 4);
+            bitmap.coverage
+        }
+    };
+    Ok(HintedGlyphGrid {
+        ppem,
+        mode,
+        width: canvas.width,
+        height: canvas.height,
+        origin_x: canvas.origin_x as i32,
+        origin_y: canvas.origin_y as i32,
+        hinted,
+        advance,
+        pixels: base64::engine::general_purpose::STANDARD.encode(pixels),
+        svg_path: svg.into_path(),
+    })
+}
+
+/// Rasterize the glyph hinted at `ppem` onto the pixel grid, together with
+/// the hinted outline for overlaying. With `honor_gasp`, sizes the gasp
+/// table leaves un-grid-fitted are drawn unhinted, as a rasterizer would.
+pub fn render_hinted_glyph_grid(
+    file_path: &str,
+    glyph_id: u32,
+    ppem: f32,
+    mode: GridMode,
+    honor_gasp: bool,
+    cache: &FontCache,
+) -> Result<HintedGlyphGrid, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    rasterize_hinted_grid(&bytes, glyph_id, ppem, mode, honor_gasp)
+}
+
+// ── Waterfall ─────────────────────────────────────────────────────────────────
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WaterfallMode {
+    /// Positioned SVG paths per glyph.
+    #[default]
+    Outlines,
+    /// One rendered PNG strip per size, as a data URL.
+    Png,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WaterfallGlyph {
+    pub glyph_id: u32,
+    /// Pen position in pixels from the start of the line.
+    pub x: f32,
+    /// Outline in SVG coordinates (Y negated); None in PNG mode.
+    pub svg_path: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WaterfallLine {
+    pub ppem: f32,
+    /// Line advance in pixels.
+    pub width: f32,
+    pub ascent: f32,
+    pub descent: f32,
+    /// Pixels per path unit: 1 for hinted paths (already in pixels),
+    /// ppem / units_per_em for unhinted paths (in font units).
+    pub path_scale: f32,
+    pub glyphs: Vec<WaterfallGlyph>,
+    pub png: Option<String>,
+}
+
+fn build_waterfall(
+    bytes: &[u8],
+    text: &str,
+    sizes: &[f32],
+    mode: WaterfallMode,
+    hinted: bool,
+    mut unscaled_paths: HashMap<u32, String>,
+) -> Result<Vec<WaterfallLine>, FontError> {
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let outlines = font.outline_glyphs();
+    let units_per_em = font.head().map(|h| h.units_per_em()).unwrap_or(1000).max(1) as f32;
+
+    let mut lines = Vec::with_capacity(sizes.len());
+    for &ppem in sizes {
+        check_ppem(ppem)?;
+        let size = Size::new(ppem);
+        // One hinting instance per size, shared by every glyph on the line
+        let instance = if hinted {
+            Some(mono_hinting_instance(&outlines, ppem)?)
+        } else {
+            None
+        };
+        let settings = || match &instance {
+            Some(instance) => DrawSettings::hinted(instance, false),
+            None => DrawSettings::unhinted(size, LocationRef::default()),
+        };
+
+        let placed = layout_text(&font, text, size, hinted);
+        let metrics = font.metrics(size, LocationRef::default());
+        let width = line_width(&placed);
+
+        let mut line = WaterfallLine {
+            ppem,
+            width,
+            ascent: metrics.ascent,
+            descent: metrics.descent,
+            path_scale: if hinted { 1.0 } else { ppem / units_per_em },
+            glyphs: Vec::with_capacity(placed.len()),
+            png: None,
+        };
+
+        match mode {
+            WaterfallMode::Outlines => {
+                for g in &placed {
+                    let gid = GlyphId::from(g.glyph_id);
+                    let svg_path = if hinted {
+                        let mut pen = SvgPathPen::new();
+                        if let Some(glyph) = outlines.get(gid) {
+                            let _ = glyph.draw(settings(), &mut pen);
+                        }
+                        pen.into_path()
+                    } else {
+                        // Unscaled paths are size-independent: draw each glyph once
+                        unscaled_paths
+                            .entry(g.glyph_id)
+                            .or_insert_with(|| {
+                                let mut pen = SvgPathPen::new();
+                                if let Some(glyph) = outlines.get(gid) {
+                                    let settings = DrawSettings::unhinted(
+                                        Size::unscaled(),
+                                        LocationRef::default(),
+                                    );
+                                    let _ = glyph.draw(settings, &mut pen);
+                                }
+                                pen.into_path()
+                            })
+                            .clone()
+                    };
+                    line.glyphs.push(WaterfallGlyph {
+                        glyph_id: g.glyph_id,
+                        x: g.x,
+                        svg_path: Some(svg_path),
+                    });
+                }
+            }
+            WaterfallMode::Png => {
+                let pad = 2.0;
+                let baseline = (pad + metrics.ascent).ceil();
+                let mut pen = EdgePen::new();
+                for g in &placed {
+                    pen.set_origin(pad + g.x, baseline);
+                    if let Some(glyph) = outlines.get(GlyphId::from(g.glyph_id)) {
+                        let _ = glyph.draw(settings(), &mut pen);
+                    }
+                    line.glyphs.push(WaterfallGlyph {
+                        glyph_id: g.glyph_id,
+                        x: g.x,
+                        svg_path: None,
+                    });
+                }
+                let img_width = (width + 2.0 * pad).ceil().max(1.0) as u32;
+                let img_height = (baseline - metrics.descent + pad).ceil().max(1.0) as u32;
+                let mut bitmap = Bitmap::new(img_width, img_height);
+                bitmap.fill(&pen, 4);
+                let png = encode_bitmap_png(&bitmap)?;
+                line.png = Some(format!(
+                    "data:image/png;base64,{}",
+                    base64::engine::general_purpose::STANDARD.encode(png)
+                ));
+            }
+        }
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+pub fn get_waterfall(
+    file_path: &str,
+    text: &str,
+    sizes: &[f32],
+    mode: WaterfallMode,
+    hinted: bool,
+    cache: &FontCache,
+) -> Result<Vec<WaterfallLine>, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+
+    // Reuse paths the glyph grid has already extracted for the unhinted mode
+    let unscaled_paths = if mode == WaterfallMode::Outlines && !hinted {
+        let font = FontRef::new(&bytes).map_err(FontError::invalid_font)?;
+        let charmap = font.charmap();
+        let mut ids: Vec<u32> = text
+            .chars()
+            .map(|ch| charmap.map(ch).unwrap_or(GlyphId::NOTDEF).to_u32())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        cache.cached_svg_paths(file_path, &ids)
+    } else {
+        HashMap::new()
+    };
+
+    build_waterfall(&bytes, text, sizes, mode, hinted, unscaled_paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square_pen(x0: f32, y0: f32, x1: f32, y1: f32) -> EdgePen {
+        let mut pen = EdgePen::new();
+        pen.move_to(x0, y0);
+        pen.line_to(x1, y0);
+        pen.line_to(x1, y1);
+        pen.line_to(x0, y1);
+        pen.close();
+        pen
+    }
+
+    #[test]
+    fn test_fill_pixel_aligned_square() {
+        // Square from (1,1) to (3,3) in y-down device space (y negated by the pen)
+        let pen = square_pen(1.0, -1.0, 3.0, -3.0);
+        let mut bitmap = Bitmap::new(4, 4);
+        bitmap.fill(&pen, 4);
+        let row = |y: usize| bitmap.coverage[y * 4..y * 4 + 4].to_vec();
+        assert_eq!(row(0), vec![0, 0, 0, 0]);
+        assert_eq!(row(1), vec![0, 255, 255, 0]);
+        assert_eq!(row(2), vec![0, 255, 255, 0]);
+        assert_eq!(row(3), vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_fill_half_pixel_coverage() {
+        let pen = square_pen(0.0, 0.0, 0.5, -1.0);
+        let mut bitmap = Bitmap::new(1, 1);
+        bitmap.fill(&pen, 4);
+        assert_eq!(bitmap.coverage[0], 128);
+    }
+
+    #[test]
+    fn test_fill_nonzero_winding_keeps_counter_open() {
+        // Outer square clockwise, inner square counter-clockwise → hole
+        let mut pen = square_pen(0.0, 0.0, 6.0, -6.0);
+        pen.move_to(2.0, -2.0);
+        pen.line_to(2.0, -4.0);
+        pen.line_to(4.0, -4.0);
+        pen.line_to(4.0, -2.0);
+        pen.close();
+        let mut bitmap = Bitmap::new(6, 6);
+        bitmap.fill(&pen, 2);
+        assert_eq!(bitmap.coverage[6 + 1], 255);
+        assert_eq!(bitmap.coverage[2 * 6 + 2], 0);
+    }
+
+    #[test]
+    fn test_render_glyph_png_signature() {
+        use crate::test_fonts::{simple_glyph, TestFontBuilder};
+
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(
+                simple_glyph(&[&[
+                    (50, 0, true),
+                    (50, 700, true),
+                    (450, 700, true),
+                    (450, 0, true),
+                ]]),
+                500,
+            )
+            .build();
+        let options = RenderOptions {
+            guides: true,
+            format: PixelFormat::Rgba,
+            ..Default::default()
+        };
+        let png = rasterize_glyph_png(&font, 1, 32.0, &options).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert!(rasterize_glyph_png(&font, 7, 32.0, &options).is_err());
+    }
+
+    #[test]
+    fn test_waterfall_lines_per_size() {
+        use crate::test_fonts::{simple_glyph, TestFontBuilder};
+
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 300)
+            .glyph(bar, 600)
+            .map('a' as u32, 1)
+            .build();
+
+        let sizes = [10.0, 20.0];
+        let lines = build_waterfall(
+            &font,
+            "aa",
+            &sizes,
+            WaterfallMode::Outlines,
+            false,
+            HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!((lines[1].width - 24.0).abs() < 1e-3);
+        assert!((lines[1].glyphs[1].x - 12.0).abs() < 1e-3);
+        assert_eq!(lines[0].path_scale, 0.01);
+        // Unhinted paths are shared across sizes
+        assert_eq!(lines[0].glyphs[0].svg_path, lines[1].glyphs[0].svg_path);
+
+        let strips = build_waterfall(
+            &font,
+            "aa",
+            &sizes,
+            WaterfallMode::Png,
+            false,
+            HashMap::new(),
+        )
+        .unwrap();
+        assert!(strips.iter().all(|l| l
+            .png
+            .as_deref()
+            .unwrap()
+            .starts_with("data:image/png;base64,")));
+
+        assert!(build_waterfall(
+            &font,
+            "a",
+            &[0.0],
+            WaterfallMode::Png,
+            false,
+            HashMap::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_hinted_glyph_grid() {
+        use crate::test_fonts::{simple_glyph, TestFontBuilder};
+
+        // A 500 unit square is exactly 5x5 pixels at 10 ppem
+        let square = simple_glyph(&[&[
+            (100, 0, true),
+            (100, 500, true),
+            (600, 500, true),
+            (600, 0, true),
+        ]]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(square, 700)
+            .build();
+
+        let gray = rasterize_hinted_grid(&font, 1, 10.0, GridMode::Gray, false).unwrap();
+        assert_eq!(gray.advance, 7.0);
+        let coverage = base64::engine::general_purpose::STANDARD
+            .decode(&gray.pixels)
+            .unwrap();
+        assert_eq!(coverage.len(), (gray.width * gray.height) as usize);
+        let lit: Vec<(i32, i32)> = (0..gray.height as i32)
+            .flat_map(|y| (0..gray.width as i32).map(move |x| (x, y)))
+            .filter(|&(x, y)| coverage[(y * gray.width as i32 + x) as usize] > 0)
+            .map(|(x, y)| (x - gray.origin_x, gray.origin_y - y))
+            .collect();
+        let expected: Vec<(i32, i32)> = (1..=5)
+            .rev()
+            .flat_map(|y| (1..=5).map(move |x| (x, y)))
+            .collect();
+        assert_eq!(lit, expected);
+        assert!(gray.svg_path.starts_with("M1 -0 L1 -5"));
+
+        let mono = rasterize_hinted_grid(&font, 1, 10.0, GridMode::Mono, false).unwrap();
+        let bits = base64::engine::general_purpose::STANDARD
+            .decode(&mono.pixels)
+            .unwrap();
+        let stride = mono.width.div_ceil(8) as usize;
+        assert_eq!(bits.len(), stride * mono.height as usize);
+        let row = (mono.origin_y - 1) as usize;
+        let lit_in_row: Vec<usize> = (0..mono.width as usize)
+            .filter(|&x| bits[row * stride + x / 8] & (0x80 >> (x % 8)) != 0)
+            .map(|x| x - mono.origin_x as usize)
+            .collect();
+        assert_eq!(lit_in_row, vec![1, 2, 3, 4, 5]);
+        assert_eq!(bits.iter().map(|b| b.count_ones()).sum::<u32>(), 25);
+        assert!(mono.hinted);
+
+        // gasp: smoothing only up to 12 ppem
+        let gasp = [0u8, 0, 0, 1, 0, 12, 0, 2].to_vec();
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(
+                simple_glyph(&[&[(0, 0, true), (0, 500, true), (500, 0, true)]]),
+                700,
+            )
+            .table(b"gasp", gasp)
+            .build();
+        assert!(
+            !rasterize_hinted_grid(&font, 1, 10.0, GridMode::Gray, true)
+                .unwrap()
+                .hinted
+        );
+        assert!(
+            rasterize_hinted_grid(&font, 1, 10.0, GridMode::Gray, false)
+                .unwrap()
+                .hinted
+        );
+        assert!(
+            rasterize_hinted_grid(&font, 1, 13.0, GridMode::Gray, true)
+                .unwrap()
+                .hinted
+        );
+    }
+}
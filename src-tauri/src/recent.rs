@@ -0,0 +1,107 @@
+use crate::error::FontError;
+use crate::font_parser::write_atomically;
+use parking_lot::Mutex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Most entries kept in the recent fonts list.
+pub const MAX_RECENT_FONTS: usize = 10;
+
+/// Recently opened font paths, most recent first, persisted as a JSON array.
+pub struct RecentFonts {
+    store: PathBuf,
+    paths: Mutex<Vec<String>>,
+}
+
+impl RecentFonts {
+    /// Load the list from `store`; a missing or unreadable file starts empty.
+    pub fn load(store: PathBuf) -> Self {
+        let paths = fs::read(&store)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Vec<String>>(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            store,
+            paths: Mutex::new(paths),
+        }
+    }
+
+    /// Current list with paths that no longer exist pruned.
+    pub fn list(&self) -> Vec<String> {
+        let mut paths = self.paths.lock();
+        let before = paths.len();
+        paths.retain(|path| Path::new(path).exists());
+        if paths.len() != before {
+            // Pruning is best effort; the list is rebuilt on next add anyway
+            let _ = self.save(&paths);
+        }
+        paths.clone()
+    }
+
+    /// Move `path` to the front, dropping the oldest entries past the cap.
+    pub fn add(&self, path: &str) -> Result<Vec<String>, FontError> {
+        let mut paths = self.paths.lock();
+        paths.retain(|p| p != path && Path::new(p).exists());
+        paths.insert(0, path.to_string());
+        paths.truncate(MAX_RECENT_FONTS);
+        self.save(&paths)?;
+        Ok(paths.clone())
+    }
+
+    pub fn clear(&self) -> Result<(), FontError> {
+        let mut paths = self.paths.lock();
+        paths.clear();
+        self.save(&paths)
+    }
+
+    fn save(&self, paths: &[String]) -> Result<(), FontError> {
+        if let Some(dir) = self.store.parent() {
+            fs::create_dir_all(dir).map_err(|e| FontError::write(&dir.to_string_lossy(), e))?;
+        }
+        let json = serde_json::to_vec_pretty(paths)
+            .map_err(|e| FontError::other(format!("Failed to serialize recent fonts: {}", e)))?;
+        write_atomically(&self.store, &json)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_fonts_dedup_cap_and_prune() {
+        let dir = std::env::temp_dir().join(format!("typebrew-recent-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let fonts: Vec<String> = (0..12)
+            .map(|i| {
+                let path = dir.join(format!("font{}.ttf", i));
+                fs::write(&path, b"").unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+        let store = dir.join("config").join("recent_fonts.json");
+
+        let recent = RecentFonts::load(store.clone());
+        for font in &fonts {
+            recent.add(font).unwrap();
+        }
+        let list = recent.add(&fonts[5]).unwrap();
+        assert_eq!(list.len(), MAX_RECENT_FONTS);
+        assert_eq!(list[0], fonts[5]);
+        assert_eq!(list[1], fonts[11]);
+        assert_eq!(list.iter().filter(|p| **p == fonts[5]).count(), 1);
+
+        // Persisted, and pruned once the file is gone
+        fs::remove_file(&fonts[11]).unwrap();
+        let reloaded = RecentFonts::load(store.clone());
+        assert_eq!(reloaded.paths.lock().len(), MAX_RECENT_FONTS);
+        let list = reloaded.list();
+        assert_eq!(list.len(), MAX_RECENT_FONTS - 1);
+        assert!(!list.contains(&fonts[11]));
+
+        reloaded.clear().unwrap();
+        assert!(RecentFonts::load(store).list().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
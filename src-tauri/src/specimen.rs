@@ -0,0 +1,419 @@
+use crate::error::FontError;
+use crate::font_parser::{get_font_bytes, write_atomically, FontCache};
+use crate::layout::{layout_text, Kerning};
+use crate::raster::{encode_bitmap_png, Bitmap, EdgePen};
+use serde::{Deserialize, Serialize};
+use skrifa::instance::{LocationRef, Size};
+use skrifa::outline::{DrawSettings, OutlineGlyphCollection};
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::string::StringId;
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::HashSet;
+
+const DEFAULT_SAMPLES: &[&str] = &[
+    "The quick brown fox jumps over the lazy dog.",
+    "Sphinx of black quartz, judge my vow!",
+    "Pack my box with five dozen liquor jugs.",
+    "0123456789 & (?!) @ #%",
+];
+
+/// Coverage of rules and grid lines: a light gray once inverted.
+const RULE_SHADE: u8 = 48;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SpecimenOptions {
+    /// Page width in pixels.
+    pub width: u32,
+    /// Page height in pixels, cutting off what does not fit. None grows the
+    /// page to its content.
+    pub height: Option<u32>,
+    /// Blank border around the content, in pixels.
+    pub margin: u32,
+    /// Font name and version at the top.
+    pub header: bool,
+    /// Grid of the mapped glyphs in codepoint order.
+    pub character_set: bool,
+    /// Most glyphs the grid shows.
+    pub character_set_limit: usize,
+    /// Sample lines, one per size.
+    pub samples: bool,
+    /// Text of the sample lines, used in turn; built-in pangrams when empty.
+    pub sample_text: Vec<String>,
+    /// Sample sizes in pixels per em, top to bottom.
+    pub sample_sizes: Vec<f32>,
+}
+
+impl Default for SpecimenOptions {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: None,
+            margin: 48,
+            header: true,
+            character_set: true,
+            character_set_limit: 256,
+            samples: true,
+            sample_text: Vec::new(),
+            sample_sizes: vec![72.0, 48.0, 32.0, 24.0, 16.0, 12.0],
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpecimenSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A rasterized strip of the page and where its top-left corner goes.
+struct Placed {
+    x: u32,
+    y: u32,
+    bitmap: Bitmap,
+}
+
+/// Lays the specimen out top to bottom, collecting strips and rules in page
+/// coordinates; the page itself is only allocated once its height is known.
+struct Specimen<'a> {
+    font: FontRef<'a>,
+    outlines: OutlineGlyphCollection<'a>,
+    kerning: Kerning<'a>,
+    units_per_em: f32,
+    margin: f32,
+    content_width: f32,
+    y: f32,
+    strips: Vec<Placed>,
+    // (x, y, width, height) filled with RULE_SHADE
+    rules: Vec<(u32, u32, u32, u32)>,
+}
+
+impl<'a> Specimen<'a> {
+    /// Rasterize `text` as one kerned line at `ppem`, left-aligned at the
+    /// margin with its ascent at the cursor, and move the cursor a line down.
+    /// Glyphs that would cross the right margin are left out.
+    fn text_line(&mut self, text: &str, ppem: f32) {
+        let size = Size::new(ppem);
+        let metrics = self.font.metrics(size, LocationRef::default());
+        let mut placed = layout_text(&self.font, text, size, false);
+        self.kerning.apply(&mut placed, ppem / self.units_per_em);
+
+        let baseline = metrics.ascent.max(0.0).ceil();
+        let height = (baseline - metrics.descent.min(0.0)).ceil().max(1.0);
+        let mut pen = EdgePen::new();
+        for g in placed
+            .iter()
+            .take_while(|g| g.x + g.advance <= self.content_width)
+        {
+            pen.set_origin(g.x, baseline);
+            if let Some(glyph) = self.outlines.get(GlyphId::new(g.glyph_id)) {
+                let settings = DrawSettings::unhinted(size, LocationRef::default());
+                let _ = glyph.draw(settings, &mut pen);
+            }
+        }
+        let mut bitmap = Bitmap::new(self.content_width.ceil() as u32, height as u32);
+        bitmap.fill(&pen, 4);
+        self.strips.push(Placed {
+            x: self.margin as u32,
+            y: self.y.round() as u32,
+            bitmap,
+        });
+        self.y += (metrics.ascent - metrics.descent + metrics.leading).max(height);
+    }
+
+    fn rule(&mut self, gap: f32) {
+        self.y += gap;
+        self.rules.push((
+            self.margin as u32,
+            self.y.round() as u32,
+            self.content_width as u32,
+            1,
+        ));
+        self.y += gap;
+    }
+
+    /// A grid of `glyphs`, each centered on its advance in a square cell
+    /// with a light border; one strip per row of cells.
+    fn glyph_grid(&mut self, glyphs: &[u32]) {
+        const CELL: f32 = 64.0;
+        let columns = ((self.content_width / CELL) as usize).max(1);
+        let ppem = CELL * 0.6;
+        let size = Size::new(ppem);
+        let metrics = self.font.metrics(size, LocationRef::default());
+        let glyph_metrics = self.font.glyph_metrics(size, LocationRef::default());
+        // Center the ascent-descent band vertically
+        let baseline = ((CELL + metrics.ascent + metrics.descent) / 2.0).round();
+
+        for row in glyphs.chunks(columns) {
+            let top = self.y.round() as u32;
+            let mut pen = EdgePen::new();
+            for (column, &gid) in row.iter().enumerate() {
+                let gid = GlyphId::new(gid);
+                let advance = glyph_metrics.advance_width(gid).unwrap_or(0.0);
+                let cell_x = column as f32 * CELL;
+                pen.set_origin((cell_x + (CELL - advance) / 2.0).round(), baseline);
+                if let Some(glyph) = self.outlines.get(gid) {
+                    let settings = DrawSettings::unhinted(size, LocationRef::default());
+                    let _ = glyph.draw(settings, &mut pen);
+                }
+                let x = self.margin as u32 + cell_x as u32;
+                let cell = CELL as u32;
+                self.rules.extend([
+                    (x, top, cell + 1, 1),
+                    (x, top + cell, cell + 1, 1),
+                    (x, top, 1, cell + 1),
+                    (x + cell, top, 1, cell + 1),
+                ]);
+            }
+            let mut bitmap = Bitmap::new((columns as f32 * CELL) as u32, CELL as u32);
+            bitmap.fill(&pen, 4);
+            self.strips.push(Placed {
+                x: self.margin as u32,
+                y: top,
+                bitmap,
+            });
+            self.y += CELL;
+        }
+    }
+}
+
+/// Mapped glyphs in codepoint order, each once, leaving out controls and
+/// spaces, which would only show empty cells.
+fn character_set(font: &FontRef<'_>, limit: usize) -> Vec<u32> {
+    let mut seen = HashSet::new();
+    font.charmap()
+        .mappings()
+        .filter(|&(cp, _)| {
+            char::from_u32(cp).is_some_and(|c| !c.is_control() && !c.is_whitespace())
+        })
+        .map(|(_, gid)| gid.to_u32())
+        .filter(|&gid| gid != 0 && seen.insert(gid))
+        .take(limit)
+        .collect()
+}
+
+fn check_options(options: &SpecimenOptions) -> Result<(), FontError> {
+    if !(200..=8000).contains(&options.width) {
+        return Err(FontError::input(format!(
+            "Specimen width {} is outside 200..=8000 pixels",
+            options.width
+        )));
+    }
+    if options.height.is_some_and(|h| !(100..=20000).contains(&h)) {
+        return Err(FontError::input(
+            "Specimen height must be within 100..=20000 pixels",
+        ));
+    }
+    if options.margin * 2 + 100 > options.width {
+        return Err(FontError::input(format!(
+            "A {} pixel margin leaves no room on a {} pixel wide page",
+            options.margin, options.width
+        )));
+    }
+    if let Some(size) = options
+        .sample_sizes
+        .iter()
+        .find(|&&s| !(s > 0.0 && s <= 512.0))
+    {
+        return Err(FontError::input(format!(
+            "Sample size {} is outside (0, 512] pixels",
+            size
+        )));
+    }
+    Ok(())
+}
+
+/// Compose the specimen page for `bytes`. `fallback_name` titles fonts
+/// without a name table.
+fn render_specimen(
+    bytes: &[u8],
+    fallback_name: &str,
+    options: &SpecimenOptions,
+) -> Result<Bitmap, FontError> {
+    check_options(options)?;
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let raw = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let units_per_em = raw.head().map_or(1000, |h| h.units_per_em()).max(1) as f32;
+    let margin = options.margin as f32;
+    let mut specimen = Specimen {
+        outlines: font.outline_glyphs(),
+        kerning: Kerning::new(raw.clone()),
+        units_per_em,
+        margin,
+        content_width: options.width as f32 - 2.0 * margin,
+        y: margin,
+        strips: Vec::new(),
+        rules: Vec::new(),
+        font: font.clone(),
+    };
+
+    let mut sections = 0;
+    if options.header {
+        let name = |id: StringId| {
+            font.localized_strings(id)
+                .english_or_first()
+                .map(|s| s.to_string())
+        };
+        let title = name(StringId::FULL_NAME)
+            .or_else(|| name(StringId::FAMILY_NAME))
+            .unwrap_or_else(|| fallback_name.to_string());
+        specimen.text_line(&title, 48.0);
+        let glyph_count = raw.maxp().map_or(0, |m| m.num_glyphs());
+        let details = match name(StringId::VERSION_STRING) {
+            Some(version) => format!("{} - {} glyphs", version, glyph_count),
+            None => format!("{} glyphs", glyph_count),
+        };
+        specimen.text_line(&details, 16.0);
+        sections += 1;
+    }
+    if options.character_set {
+        let glyphs = character_set(&font, options.character_set_limit);
+        if !glyphs.is_empty() {
+            if sections > 0 {
+                specimen.rule(20.0);
+            }
+            specimen.glyph_grid(&glyphs);
+            sections += 1;
+        }
+    }
+    if options.samples && !options.sample_sizes.is_empty() {
+        if sections > 0 {
+            specimen.rule(20.0);
+        }
+        let custom: Vec<&str> = options
+            .sample_text
+            .iter()
+            .map(String::as_str)
+            .filter(|t| !t.trim().is_empty())
+            .collect();
+        let samples = if custom.is_empty() {
+            DEFAULT_SAMPLES
+        } else {
+            &custom[..]
+        };
+        for (i, &ppem) in options.sample_sizes.iter().enumerate() {
+            specimen.text_line(samples[i % samples.len()], ppem);
+            specimen.y += 8.0;
+        }
+    }
+
+    let height = match options.height {
+        Some(height) => height,
+        None => (specimen.y + margin).ceil() as u32,
+    };
+    if height > 20000 {
+        return Err(FontError::input(format!(
+            "The specimen would be {} pixels tall; lower character_set_limit or set a height",
+            height
+        )));
+    }
+    let mut page = Bitmap::new(options.width, height);
+    let width = options.width;
+    let mut ink = |x: u32, y: u32, coverage: u8| {
+        if x < width && y < height {
+            let pixel = &mut page.coverage[(y * width + x) as usize];
+            *pixel = (*pixel).max(coverage);
+        }
+    };
+    for &(x, y, w, h) in &specimen.rules {
+        for py in y..y + h {
+            for px in x..x + w {
+                ink(px, py, RULE_SHADE);
+            }
+        }
+    }
+    for strip in &specimen.strips {
+        let bitmap = &strip.bitmap;
+        for (i, &coverage) in bitmap.coverage.iter().enumerate() {
+            if coverage > 0 {
+                let (sx, sy) = (i as u32 % bitmap.width, i as u32 / bitmap.width);
+                ink(strip.x + sx, strip.y + sy, coverage);
+            }
+        }
+    }
+    Ok(page)
+}
+
+/// Render a specimen of `file_path` (name header, character grid and sample
+/// lines, each optional) and write it to `destination` as a grayscale PNG.
+pub fn export_specimen(
+    file_path: &str,
+    destination: &str,
+    options: &SpecimenOptions,
+    cache: &FontCache,
+) -> Result<SpecimenSize, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let fallback_name = std::path::Path::new(file_path).file_stem().map_or_else(
+        || file_path.to_string(),
+        |s| s.to_string_lossy().into_owned(),
+    );
+    let page = render_specimen(&bytes, &fallback_name, options)?;
+    let png = encode_bitmap_png(&page)?;
+    write_atomically(std::path::Path::new(destination), &png)?;
+    Ok(SpecimenSize {
+        width: page.width,
+        height: page.height,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{simple_glyph, TestFontBuilder};
+
+    #[test]
+    fn test_specimen_sections_stack_and_fit() {
+        let square = simple_glyph(&[&[
+            (50, 0, true),
+            (50, 600, true),
+            (450, 600, true),
+            (450, 0, true),
+        ]]);
+        let mut builder = TestFontBuilder::new().glyph(Vec::new(), 500);
+        for c in 'A'..='Z' {
+            builder = builder.glyph(square.clone(), 500);
+            builder = builder.map(c as u32, c as u16 - 'A' as u16 + 1);
+        }
+        let font = builder.map(' ' as u32, 0).build();
+
+        let options = SpecimenOptions {
+            width: 600,
+            margin: 20,
+            sample_text: vec!["ABC ABC".to_string()],
+            sample_sizes: vec![40.0, 20.0],
+            ..SpecimenOptions::default()
+        };
+        let page = render_specimen(&font, "Test", &options).unwrap();
+        assert_eq!(page.width, 600);
+        // 26 glyphs in 8 columns of 64px make 4 grid rows
+        assert!(page.height > 20 + 4 * 64 + 20, "{}", page.height);
+        let inked_rows = (0..page.height)
+            .filter(|y| {
+                let row = &page.coverage[(y * 600) as usize..((y + 1) * 600) as usize];
+                row.iter().any(|&c| c > RULE_SHADE)
+            })
+            .count();
+        assert!(inked_rows > 4 * 30, "{}", inked_rows);
+        // Nothing is drawn into the margins
+        assert!((0..page.height).all(|y| page.coverage[(y * 600) as usize] == 0));
+
+        let grid_only = SpecimenOptions {
+            header: false,
+            samples: false,
+            ..options.clone()
+        };
+        let page = render_specimen(&font, "Test", &grid_only).unwrap();
+        assert_eq!(page.height, 20 + 4 * 64 + 20);
+
+        let fixed = SpecimenOptions {
+            height: Some(150),
+            ..options
+        };
+        assert_eq!(render_specimen(&font, "Test", &fixed).unwrap().height, 150);
+        let too_wide_margin = SpecimenOptions {
+            margin: 600,
+            ..SpecimenOptions::default()
+        };
+        assert!(render_specimen(&font, "Test", &too_wide_margin).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::error::FontError;
+use crate::font_parser::{
+    extract_glyph_outlines, get_font_bytes, CancelToken, FontCache, Progress, PROGRESS_INTERVAL,
+};
+use skrifa::raw::TableProvider;
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Characters allowed in an exported file name; anything else becomes `_`.
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Standalone SVG document for one glyph. The path is already in font units
+/// with Y negated, so the view box spans the advance and ascender..descender.
+fn glyph_svg(svg_path: &str, advance: f32, ascender: i16, descender: i16) -> String {
+    let height = ascender as i32 - descender as i32;
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 {} {} {}\">\n  <path d=\"{}\"/>\n</svg>\n",
+        -(ascender as i32),
+        advance.max(0.0),
+        height,
+        svg_path.trim_end()
+    )
+}
+
+/// Write every glyph with an outline to `destination_dir` as `<name>.svg`,
+/// named after the post table (or `gid<N>`). Returns how many were written.
+/// Progress covers outline extraction, the bulk of the work. A cancelled
+/// export removes the files it already wrote.
+pub fn export_glyph_svgs(
+    file_path: &str,
+    destination_dir: &str,
+    cache: &FontCache,
+    progress: Progress,
+    cancel: &CancelToken,
+) -> Result<u32, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+
+    let font = FontRef::new(&bytes).map_err(FontError::invalid_font)?;
+    let (ascender, descender) = font
+        .hhea()
+        .map(|hhea| (hhea.ascender().to_i16(), hhea.descender().to_i16()))
+        .map_err(|e| FontError::table("hhea", e))?;
+    let names = font.glyph_names();
+    let outlines = extract_glyph_outlines(&bytes, progress, cancel)?;
+
+    let dir = Path::new(destination_dir);
+    fs::create_dir_all(dir).map_err(|e| FontError::write(destination_dir, e))?;
+
+    let mut used: HashSet<String> = HashSet::new();
+    let mut written = Vec::with_capacity(outlines.len());
+    for (i, outline) in outlines.iter().enumerate() {
+        if (i as u32).is_multiple_of(PROGRESS_INTERVAL) && cancel.is_cancelled() {
+            for path in &written {
+                let _ = fs::remove_file(path);
+            }
+            return Err(FontError::Cancelled);
+        }
+        let gid = outline.glyph_id;
+        let mut stem = names
+            .get(GlyphId::new(gid))
+            .map(|n| file_stem(&n.to_string()))
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| format!("gid{}", gid));
+        // Sanitizing can collide, and some file systems ignore case
+        if !used.insert(stem.to_lowercase()) {
+            stem = format!("{}_gid{}", stem, gid);
+            used.insert(stem.to_lowercase());
+        }
+        let svg = glyph_svg(
+            &outline.svg_path,
+            outline.advance_width,
+            ascender,
+            descender,
+        );
+        let path = dir.join(format!("{}.svg", stem));
+        fs::write(&path, svg).map_err(|e| FontError::write(&path.to_string_lossy(), e))?;
+        written.push(path);
+    }
+
+    Ok(outlines.len() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{simple_glyph, TestFontBuilder};
+
+    #[test]
+    fn test_export_glyph_svgs() {
+        let dir = std::env::temp_dir().join(format!("typebrew-svg-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let font_path = dir.join("font.ttf");
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(bar.clone(), 600)
+            .glyph(bar, 700)
+            .build();
+        fs::write(&font_path, font).unwrap();
+
+        let out = dir.join("svgs");
+        let cache = FontCache::new();
+        let reports = std::cell::RefCell::new(Vec::new());
+        let written = export_glyph_svgs(
+            font_path.to_str().unwrap(),
+            out.to_str().unwrap(),
+            &cache,
+            &|done, total| reports.borrow_mut().push((done, total)),
+            &CancelToken::default(),
+        )
+        .unwrap();
+        // The empty .notdef outline is skipped
+        assert_eq!(written, 2);
+        assert_eq!(reports.into_inner(), vec![(0, 3), (3, 3)]);
+
+        let svg = fs::read_to_string(out.join("gid1.svg")).unwrap();
+        assert!(svg.contains("viewBox=\"0 -800 600 1000\""), "{}", svg);
+        assert!(svg.contains("L0 -500 L100 -500 Z\"/>"), "{}", svg);
+        assert!(out.join("gid2.svg").exists());
+        assert!(!out.join("gid0.svg").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_stem_sanitizes() {
+        assert_eq!(file_stem("a.sc"), "a.sc");
+        assert_eq!(file_stem("uni0041/ x"), "uni0041__x");
+    }
+}
@@ -0,0 +1,478 @@
+//! Minimal in-memory TrueType fonts for unit tests.
+//!
+//! Every table is written as raw bytes so the fixtures stay independent of the
+//! write-fonts table structs that the code under test exercises.
+
+use write_fonts::types::Tag;
+use write_fonts::FontBuilder;
+
+/// A point in a simple glyph contour: (x, y, on_curve).
+pub type TestPoint = (i16, i16, bool);
+
+/// One component of a composite glyph.
+pub struct TestComponent {
+    pub glyph_id: u16,
+    pub dx: i16,
+    pub dy: i16,
+    /// Optional 2×2 transform (xx, xy, yx, yy) written as F2Dot14.
+    pub transform: Option<[f32; 4]>,
+}
+
+struct TestGlyph {
+    data: Vec<u8>,
+    advance: u16,
+    lsb: i16,
+}
+
+pub struct TestFontBuilder {
+    glyphs: Vec<TestGlyph>,
+    mappings: Vec<(u32, u16)>,
+    variants: Vec<(u32, u32, u16)>,
+    extra_tables: Vec<(Tag, Vec<u8>)>,
+    units_per_em: u16,
+    long_loca: bool,
+    cff: Option<Vec<u8>>,
+}
+
+fn push_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend(v.to_be_bytes());
+}
+
+fn push_i16(buf: &mut Vec<u8>, v: i16) {
+    buf.extend(v.to_be_bytes());
+}
+
+fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend(v.to_be_bytes());
+}
+
+/// Encode a simple glyph record (uncompressed flags and 16-bit deltas).
+pub fn simple_glyph(contours: &[&[TestPoint]]) -> Vec<u8> {
+    let pts: Vec<TestPoint> = contours.iter().flat_map(|c| c.iter().copied()).collect();
+    if pts.is_empty() {
+        return Vec::new();
+    }
+    let mut buf = Vec::new();
+    push_i16(&mut buf, contours.len() as i16);
+    push_i16(&mut buf, pts.iter().map(|p| p.0).min().unwrap());
+    push_i16(&mut buf, pts.iter().map(|p| p.1).min().unwrap());
+    push_i16(&mut buf, pts.iter().map(|p| p.0).max().unwrap());
+    push_i16(&mut buf, pts.iter().map(|p| p.1).max().unwrap());
+    let mut end = 0usize;
+    for c in contours {
+        end += c.len();
+        push_u16(&mut buf, (end - 1) as u16);
+    }
+    push_u16(&mut buf, 0);
+    for p in &pts {
+        buf.push(p.2 as u8);
+    }
+    let mut prev = 0i16;
+    for p in &pts {
+        push_i16(&mut buf, p.0 - prev);
+        prev = p.0;
+    }
+    prev = 0;
+    for p in &pts {
+        push_i16(&mut buf, p.1 - prev);
+        prev = p.1;
+    }
+    buf
+}
+
+/// Encode a composite glyph record. The header bbox is left at zero; skrifa
+/// does not rely on it when drawing.
+pub fn composite_glyph(components: &[TestComponent]) -> Vec<u8> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+    let mut buf = Vec::new();
+    push_i16(&mut buf, -1);
+    buf.extend([0u8; 8]);
+    for (i, comp) in components.iter().enumerate() {
+        let mut flags = ARG_1_AND_2_ARE_WORDS | ARGS_ARE_XY_VALUES;
+        if i + 1 < components.len() {
+            flags |= MORE_COMPONENTS;
+        }
+        if comp.transform.is_some() {
+            flags |= WE_HAVE_A_TWO_BY_TWO;
+        }
+        push_u16(&mut buf, flags);
+        push_u16(&mut buf, comp.glyph_id);
+        push_i16(&mut buf, comp.dx);
+        push_i16(&mut buf, comp.dy);
+        if let Some(m) = comp.transform {
+            for v in m {
+                push_i16(&mut buf, (v * 16384.0).round() as i16);
+            }
+        }
+    }
+    buf
+}
+
+/// A format 0 name table holding `records` as (platform, encoding, language,
+/// name ID, string bytes), stored in the order given.
+pub fn name_table(records: &[(u16, u16, u16, u16, &[u8])]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_u16(&mut buf, 0); // version
+    push_u16(&mut buf, records.len() as u16);
+    push_u16(&mut buf, 6 + records.len() as u16 * 12); // storageOffset
+    let mut storage = Vec::new();
+    for &(platform, encoding, language, name_id, string) in records {
+        for v in [platform, encoding, language, name_id] {
+            push_u16(&mut buf, v);
+        }
+        push_u16(&mut buf, string.len() as u16);
+        push_u16(&mut buf, storage.len() as u16);
+        storage.extend_from_slice(string);
+    }
+    buf.extend(storage);
+    buf
+}
+
+/// Wrap one subtable in a GSUB/GPOS table with no scripts or features and a
+/// single lookup of `lookup_type`.
+fn single_lookup_table(lookup_type: u16, subtable: Vec<u8>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_u32(&mut buf, 0x0001_0000);
+    push_u16(&mut buf, 10); // scriptList
+    push_u16(&mut buf, 12); // featureList
+    push_u16(&mut buf, 14); // lookupList
+    push_u16(&mut buf, 0); // scriptCount
+    push_u16(&mut buf, 0); // featureCount
+    push_u16(&mut buf, 1); // lookupCount
+    push_u16(&mut buf, 4); // lookup offset
+    push_u16(&mut buf, lookup_type);
+    push_u16(&mut buf, 0); // lookupFlag
+    push_u16(&mut buf, 1); // subTableCount
+    push_u16(&mut buf, 8); // subtable offset
+    buf.extend(subtable);
+    buf
+}
+
+fn coverage_format1(buf: &mut Vec<u8>, glyphs: &[u16]) {
+    push_u16(buf, 1);
+    push_u16(buf, glyphs.len() as u16);
+    for &gid in glyphs {
+        push_u16(buf, gid);
+    }
+}
+
+/// Encode a GPOS table with a single mark-to-base lookup (one mark class)
+/// attaching `mark` at `mark_anchor` to `base` at `base_anchor`.
+pub fn mark_to_base_gpos(
+    base: u16,
+    base_anchor: (i16, i16),
+    mark: u16,
+    mark_anchor: (i16, i16),
+) -> Vec<u8> {
+    // MarkBasePosFormat1; offsets are relative to the subtable start.
+    let mut sub = Vec::new();
+    push_u16(&mut sub, 1);
+    push_u16(&mut sub, 12); // markCoverage
+    push_u16(&mut sub, 18); // baseCoverage
+    push_u16(&mut sub, 1); // markClassCount
+    push_u16(&mut sub, 24); // markArray
+    push_u16(&mut sub, 36); // baseArray
+    coverage_format1(&mut sub, &[mark]);
+    coverage_format1(&mut sub, &[base]);
+    push_u16(&mut sub, 1); // markCount
+    push_u16(&mut sub, 0); // markClass
+    push_u16(&mut sub, 6); // markAnchor
+    push_u16(&mut sub, 1);
+    push_i16(&mut sub, mark_anchor.0);
+    push_i16(&mut sub, mark_anchor.1);
+    push_u16(&mut sub, 1); // baseCount
+    push_u16(&mut sub, 4); // baseAnchor[0]
+    push_u16(&mut sub, 1);
+    push_i16(&mut sub, base_anchor.0);
+    push_i16(&mut sub, base_anchor.1);
+    single_lookup_table(4, sub)
+}
+
+/// Encode a GSUB table with a single substitution lookup (format 2) for the
+/// given `(from, to)` pairs. Pairs must be sorted by `from`.
+pub fn single_subst_gsub(pairs: &[(u16, u16)]) -> Vec<u8> {
+    let mut sub = Vec::new();
+    push_u16(&mut sub, 2);
+    push_u16(&mut sub, 6 + pairs.len() as u16 * 2); // coverage
+    push_u16(&mut sub, pairs.len() as u16);
+    for &(_, to) in pairs {
+        push_u16(&mut sub, to);
+    }
+    let from: Vec<u16> = pairs.iter().map(|p| p.0).collect();
+    coverage_format1(&mut sub, &from);
+    single_lookup_table(1, sub)
+}
+
+/// CFF INDEX with 1-byte offsets.
+pub fn cff_index(items: &[&[u8]]) -> Vec<u8> {
+    let mut buf = (items.len() as u16).to_be_bytes().to_vec();
+    buf.push(1);
+    let mut offset = 1u8;
+    buf.push(offset);
+    for item in items {
+        offset += item.len() as u8;
+        buf.push(offset);
+    }
+    for item in items {
+        buf.extend_from_slice(item);
+    }
+    buf
+}
+
+fn cff_int32(v: usize) -> Vec<u8> {
+    let mut b = vec![29];
+    b.extend((v as i32).to_be_bytes());
+    b
+}
+
+/// Name-keyed CFF table with the given charstrings and local subrs. The
+/// Private DICT sets defaultWidthX 500 and nominalWidthX 100.
+pub fn cff_table(charstrings: &[&[u8]], local_subrs: &[&[u8]]) -> Vec<u8> {
+    let charstrings = cff_index(charstrings);
+    let mut private = vec![28];
+    private.extend(500i16.to_be_bytes());
+    private.push(20); // defaultWidthX
+    private.push(28);
+    private.extend(100i16.to_be_bytes());
+    private.push(21); // nominalWidthX
+    if !local_subrs.is_empty() {
+        private.extend(cff_int32(private.len() + 6));
+        private.push(19); // Subrs, right after this DICT
+    }
+
+    // header 4 + Name INDEX 9 + Top DICT INDEX 22 + Strings 2 + GSubrs 2
+    let charstrings_offset = 39;
+    let mut top = cff_int32(charstrings_offset);
+    top.push(17);
+    top.extend(cff_int32(private.len()));
+    top.extend(cff_int32(charstrings_offset + charstrings.len()));
+    top.push(18);
+
+    let mut cff = vec![1, 0, 4, 1];
+    cff.extend(cff_index(&[b"Test"]));
+    cff.extend(cff_index(&[&top]));
+    cff.extend([0, 0, 0, 0]); // String INDEX, Global Subrs
+    debug_assert_eq!(cff.len(), charstrings_offset);
+    cff.extend(charstrings);
+    cff.extend(private);
+    if !local_subrs.is_empty() {
+        cff.extend(cff_index(local_subrs));
+    }
+    cff
+}
+
+impl TestFontBuilder {
+    pub fn new() -> Self {
+        Self {
+            glyphs: Vec::new(),
+            mappings: Vec::new(),
+            variants: Vec::new(),
+            extra_tables: Vec::new(),
+            units_per_em: 1000,
+            long_loca: true,
+            cff: None,
+        }
+    }
+
+    pub fn units_per_em(mut self, upem: u16) -> Self {
+        self.units_per_em = upem;
+        self
+    }
+
+    pub fn short_loca(mut self) -> Self {
+        self.long_loca = false;
+        self
+    }
+
+    /// Append a glyph record; the lsb is taken from the record's xMin.
+    pub fn glyph(mut self, data: Vec<u8>, advance: u16) -> Self {
+        let lsb = if data.len() >= 4 {
+            i16::from_be_bytes([data[2], data[3]])
+        } else {
+            0
+        };
+        self.glyphs.push(TestGlyph { data, advance, lsb });
+        self
+    }
+
+    /// Map a codepoint to a glyph in a format 12 cmap subtable.
+    pub fn map(mut self, codepoint: u32, glyph_id: u16) -> Self {
+        self.mappings.push((codepoint, glyph_id));
+        self
+    }
+
+    /// Map a (codepoint, variation selector) sequence to a glyph in a format 14
+    /// cmap subtable.
+    pub fn variant(mut self, codepoint: u32, selector: u32, glyph_id: u16) -> Self {
+        self.variants.push((selector, codepoint, glyph_id));
+        self
+    }
+
+    /// Use `table` as CFF outlines instead of glyf/loca (glyph records are ignored).
+    pub fn cff(mut self, table: Vec<u8>) -> Self {
+        self.cff = Some(table);
+        self
+    }
+
+    pub fn table(mut self, tag: &[u8; 4], data: Vec<u8>) -> Self {
+        self.extra_tables.push((Tag::new(tag), data));
+        self
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        let num_glyphs = self.glyphs.len() as u16;
+
+        let mut glyf = Vec::new();
+        let mut offsets = Vec::new();
+        for g in &self.glyphs {
+            offsets.push(glyf.len() as u32);
+            glyf.extend_from_slice(&g.data);
+            while !glyf.len().is_multiple_of(4) {
+                glyf.push(0);
+            }
+        }
+        offsets.push(glyf.len() as u32);
+
+        let mut loca = Vec::new();
+        for &o in &offsets {
+            if self.long_loca {
+                push_u32(&mut loca, o);
+            } else {
+                push_u16(&mut loca, (o / 2) as u16);
+            }
+        }
+
+        let mut hmtx = Vec::new();
+        for g in &self.glyphs {
+            push_u16(&mut hmtx, g.advance);
+            push_i16(&mut hmtx, g.lsb);
+        }
+
+        let mut head = Vec::new();
+        push_u32(&mut head, 0x0001_0000); // version
+        push_u32(&mut head, 0x0001_0000); // fontRevision
+        push_u32(&mut head, 0); // checksumAdjustment
+        push_u32(&mut head, 0x5F0F_3CF5); // magicNumber
+        push_u16(&mut head, 0); // flags
+        push_u16(&mut head, self.units_per_em);
+        head.extend([0u8; 16]); // created, modified
+        head.extend([0u8; 8]); // bbox
+        push_u16(&mut head, 0); // macStyle
+        push_u16(&mut head, 8); // lowestRecPPEM
+        push_i16(&mut head, 2); // fontDirectionHint
+        push_i16(&mut head, self.long_loca as i16);
+        push_i16(&mut head, 0); // glyphDataFormat
+
+        let mut hhea = Vec::new();
+        push_u32(&mut hhea, 0x0001_0000);
+        push_i16(&mut hhea, (self.units_per_em as f32 * 0.8) as i16);
+        push_i16(&mut hhea, -((self.units_per_em as f32 * 0.2) as i16));
+        push_i16(&mut hhea, 0); // lineGap
+        push_u16(
+            &mut hhea,
+            self.glyphs.iter().map(|g| g.advance).max().unwrap_or(0),
+        );
+        hhea.extend([0u8; 6]); // minLsb, minRsb, xMaxExtent
+        push_i16(&mut hhea, 1); // caretSlopeRise
+        hhea.extend([0u8; 14]); // caretSlopeRun, caretOffset, reserved, metricDataFormat
+        push_u16(&mut hhea, num_glyphs);
+
+        let mut maxp = Vec::new();
+        push_u32(&mut maxp, 0x0001_0000);
+        push_u16(&mut maxp, num_glyphs);
+        push_u16(&mut maxp, 256); // maxPoints
+        push_u16(&mut maxp, 32); // maxContours
+        push_u16(&mut maxp, 256); // maxCompositePoints
+        push_u16(&mut maxp, 32); // maxCompositeContours
+        push_u16(&mut maxp, 2); // maxZones
+        push_u16(&mut maxp, 0); // maxTwilightPoints
+        push_u16(&mut maxp, 0); // maxStorage
+        push_u16(&mut maxp, 0); // maxFunctionDefs
+        push_u16(&mut maxp, 0); // maxInstructionDefs
+        push_u16(&mut maxp, 0); // maxStackElements
+        push_u16(&mut maxp, 0); // maxSizeOfInstructions
+        push_u16(&mut maxp, 2); // maxComponentElements
+        push_u16(&mut maxp, 2); // maxComponentDepth
+
+        let mut mappings = self.mappings.clone();
+        mappings.sort();
+        let mut format12 = Vec::new();
+        push_u16(&mut format12, 12); // format
+        push_u16(&mut format12, 0);
+        push_u32(&mut format12, 16 + mappings.len() as u32 * 12);
+        push_u32(&mut format12, 0); // language
+        push_u32(&mut format12, mappings.len() as u32);
+        for (cp, gid) in &mappings {
+            push_u32(&mut format12, *cp);
+            push_u32(&mut format12, *cp);
+            push_u32(&mut format12, *gid as u32);
+        }
+
+        let mut subtables = Vec::new();
+        if !self.variants.is_empty() {
+            // Format 14 with one non-default UVS table per selector
+            let mut variants = self.variants.clone();
+            variants.sort();
+            let mut selectors: Vec<u32> = variants.iter().map(|v| v.0).collect();
+            selectors.dedup();
+            let header_len = 10 + selectors.len() * 11;
+            let mut records = Vec::new();
+            let mut uvs = Vec::new();
+            for &sel in &selectors {
+                records.extend(&sel.to_be_bytes()[1..]);
+                push_u32(&mut records, 0); // defaultUVSOffset
+                push_u32(&mut records, (header_len + uvs.len()) as u32);
+                let entries: Vec<_> = variants.iter().filter(|v| v.0 == sel).collect();
+                push_u32(&mut uvs, entries.len() as u32);
+                for (_, cp, gid) in entries {
+                    uvs.extend(&cp.to_be_bytes()[1..]);
+                    push_u16(&mut uvs, *gid);
+                }
+            }
+            let mut format14 = Vec::new();
+            push_u16(&mut format14, 14);
+            push_u32(&mut format14, (header_len + uvs.len()) as u32);
+            push_u32(&mut format14, selectors.len() as u32);
+            format14.extend(records);
+            format14.extend(uvs);
+            subtables.push(((0u16, 5u16), format14)); // platform Unicode, encoding UVS
+        }
+        subtables.push(((3, 10), format12)); // platform Windows, encoding UCS-4
+
+        let mut cmap = Vec::new();
+        push_u16(&mut cmap, 0); // version
+        push_u16(&mut cmap, subtables.len() as u16);
+        let mut offset = 4 + subtables.len() as u32 * 8;
+        for ((platform, encoding), data) in &subtables {
+            push_u16(&mut cmap, *platform);
+            push_u16(&mut cmap, *encoding);
+            push_u32(&mut cmap, offset);
+            offset += data.len() as u32;
+        }
+        for (_, data) in subtables {
+            cmap.extend(data);
+        }
+
+        let mut builder = FontBuilder::new();
+        builder
+            .add_raw(Tag::new(b"head"), head)
+            .add_raw(Tag::new(b"hhea"), hhea)
+            .add_raw(Tag::new(b"maxp"), maxp)
+            .add_raw(Tag::new(b"hmtx"), hmtx)
+            .add_raw(Tag::new(b"cmap"), cmap);
+        match self.cff {
+            Some(cff) => builder.add_raw(Tag::new(b"CFF "), cff),
+            None => builder
+                .add_raw(Tag::new(b"loca"), loca)
+                .add_raw(Tag::new(b"glyf"), glyf),
+        };
+        for (tag, data) in self.extra_tables {
+            builder.add_raw(tag, data);
+        }
+        builder.build()
+    }
+}
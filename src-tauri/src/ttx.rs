@@ -0,0 +1,2217 @@
+use crate::error::FontError;
+use crate::font_parser::{
+    editable_font_bytes, get_font_bytes, save_edited_font, write_atomically, write_font_file,
+    FontCache,
+};
+use crate::metrics::recalc_x_avg_char_width;
+use crate::xml::{self, Element};
+use skrifa::raw::tables::cmap::CmapSubtable;
+use skrifa::raw::tables::glyf::{Anchor, Glyph};
+use skrifa::raw::tables::post::DEFAULT_GLYPH_NAMES;
+use skrifa::raw::types::Tag;
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use write_fonts::from_obj::ToOwnedTable;
+use write_fonts::tables::cmap::{
+    Cmap, CmapSubtable as CmapSubtableOut, ConstantMapGroup, DefaultUvs, EncodingRecord,
+    NonDefaultUvs, PlatformId, SequentialMapGroup, UnicodeRange, UvsMapping, VariationSelector,
+};
+use write_fonts::tables::head::{Flags, Head, MacStyle};
+use write_fonts::tables::hhea::Hhea;
+use write_fonts::tables::hmtx::{Hmtx, LongMetric};
+use write_fonts::tables::maxp::Maxp;
+use write_fonts::tables::name::{Name, NameRecord};
+use write_fonts::tables::os2::{Os2, SelectionFlags};
+use write_fonts::tables::post::Post;
+use write_fonts::types::{FWord, Fixed, LongDateTime, UfWord, Uint24, Version16Dot16};
+use write_fonts::FontBuilder;
+
+// ── Value formatting ─────────────────────────────────────────────────────────
+// These mirror fontTools' own formatting so ttx reads the values back exactly.
+
+/// Shortest decimal that rounds back to the same fixed-point value, always
+/// with a fractional part ("1.0", "-12.5").
+fn fixed_to_str(bits: i64, fraction_bits: u32) -> String {
+    let scale = (1i64 << fraction_bits) as f64;
+    let value = bits as f64 / scale;
+    (1..=10)
+        .map(|digits| format!("{:.*}", digits, value))
+        .find(|s| s.parse::<f64>().map(|v| (v * scale).round() as i64) == Ok(bits))
+        .unwrap_or_else(|| format!("{:?}", value))
+}
+
+/// Binary digits in groups of eight, most significant first.
+fn binary(value: u32, bits: u32) -> String {
+    (0..bits / 8)
+        .rev()
+        .map(|byte| format!("{:08b}", (value >> (byte * 8)) & 0xFF))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Seconds between the LongDateTime epoch (1904) and the Unix epoch
+pub(crate) const EPOCH_OFFSET: i64 = 2_082_844_800;
+
+/// Civil (year, month, day) from days since 1970-01-01 (Howard Hinnant's
+/// algorithm).
+fn civil_date(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + (month <= 2) as i64, month, day)
+}
+
+/// Days since 1970-01-01 from a civil date (Howard Hinnant's algorithm).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// A LongDateTime as asctime() text, clamped to the Unix epoch as fontTools
+/// does ("Thu Jan  1 00:00:00 1970").
+fn timestamp_to_str(secs_since_1904: i64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    let secs = (secs_since_1904 - EPOCH_OFFSET).max(0);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_date(days);
+
+    format!(
+        "{} {} {:>2} {:02}:{:02}:{:02} {}",
+        WEEKDAYS[(days % 7) as usize],
+        MONTHS[(month - 1) as usize],
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        year
+    )
+}
+
+/// Days in `month` (1-12) of the proleptic Gregorian `year`.
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// A LongDateTime as an ISO 8601 UTC string ("2024-03-01T12:00:00Z").
+pub(crate) fn timestamp_to_iso(secs_since_1904: i64) -> String {
+    let secs = secs_since_1904 - EPOCH_OFFSET;
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    let (year, month, day) = civil_date(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// The inverse of `timestamp_to_iso`, also accepting a bare date, minutes
+/// without seconds, fractional seconds (dropped) and a "+HH:MM"/"-HH:MM"
+/// offset. A time without a zone is taken as UTC.
+pub(crate) fn iso_to_timestamp(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (date, time) = match s.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (s, None),
+    };
+
+    let ymd: Vec<i64> = date
+        .split('-')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let [year, month, day] = ymd[..] else {
+        return None;
+    };
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    let mut secs = days_from_civil(year, month, day) * 86_400;
+    if let Some(time) = time {
+        let (clock, offset) = if let Some(clock) = time.strip_suffix('Z') {
+            (clock, 0)
+        } else if let Some(at) = time.rfind(['+', '-']) {
+            let (clock, zone) = time.split_at(at);
+            let sign = if zone.starts_with('-') { -1 } else { 1 };
+            let (h, m) = zone[1..].split_once(':')?;
+            let (h, m): (i64, i64) = (h.parse().ok()?, m.parse().ok()?);
+            if h > 23 || m > 59 {
+                return None;
+            }
+            (clock, sign * (h * 3600 + m * 60))
+        } else {
+            (time, 0)
+        };
+        let hms: Vec<&str> = clock.split(':').collect();
+        let (h, m, sec) = match hms[..] {
+            [h, m] => (h, m, "0"),
+            [h, m, sec] => (h, m, sec.split('.').next()?),
+            _ => return None,
+        };
+        let (h, m, sec): (i64, i64, i64) = (h.parse().ok()?, m.parse().ok()?, sec.parse().ok()?);
+        if !(0..24).contains(&h) || !(0..60).contains(&m) || !(0..61).contains(&sec) {
+            return None;
+        }
+        secs += h * 3600 + m * 60 + sec - offset;
+    }
+    Some(secs + EPOCH_OFFSET)
+}
+
+/// The XML element name fontTools uses for a table tag.
+pub(crate) fn tag_to_xml(tag: &str) -> String {
+    if tag == "OS/2" {
+        return "OS_2".to_string();
+    }
+    let trimmed = tag.trim_end_matches(' ');
+    let is_identifier = trimmed
+        .chars()
+        .enumerate()
+        .all(|(i, c)| c == '_' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit()));
+    if !trimmed.is_empty() && is_identifier {
+        return trimmed.to_string();
+    }
+    // fontTools' tagToIdentifier escaping
+    let mut ident: String = trimmed
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' => c.to_string(),
+            'A'..='Z' => format!("{}_", c),
+            _ => format!("{:x}", c as u32),
+        })
+        .collect();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+// ── Writer ───────────────────────────────────────────────────────────────────
+
+struct TtxWriter {
+    out: String,
+    depth: usize,
+}
+
+impl TtxWriter {
+    fn line(&mut self, text: &str) {
+        self.out.extend(std::iter::repeat_n("  ", self.depth));
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn tag_text(name: &str, attrs: &[(&str, String)]) -> String {
+        let mut text = name.to_string();
+        for (key, value) in attrs {
+            text.push_str(&format!(" {}=\"{}\"", key, xml::escape(value)));
+        }
+        text
+    }
+
+    fn simpletag(&mut self, name: &str, attrs: &[(&str, String)]) {
+        let text = format!("<{}/>", Self::tag_text(name, attrs));
+        self.line(&text);
+    }
+
+    fn value(&mut self, name: &str, value: impl ToString) {
+        self.simpletag(name, &[("value", value.to_string())]);
+    }
+
+    fn begintag(&mut self, name: &str, attrs: &[(&str, String)]) {
+        let text = format!("<{}>", Self::tag_text(name, attrs));
+        self.line(&text);
+        self.depth += 1;
+    }
+
+    fn endtag(&mut self, name: &str) {
+        self.depth -= 1;
+        self.line(&format!("</{}>", name));
+    }
+
+    fn comment(&mut self, text: &str) {
+        self.line(&format!("<!-- {} -->", text.replace("--", "- -")));
+    }
+
+    fn text(&mut self, text: &str) {
+        self.line(&xml::escape(text));
+    }
+
+    /// Hex lines of 16 bytes in groups of four, as fontTools writes them.
+    fn hexdata(&mut self, name: &str, bytes: &[u8]) {
+        self.begintag(name, &[]);
+        for chunk in bytes.chunks(16) {
+            let groups: Vec<String> = chunk
+                .chunks(4)
+                .map(|g| g.iter().map(|b| format!("{:02x}", b)).collect())
+                .collect();
+            self.line(&groups.join(" "));
+        }
+        self.endtag(name);
+    }
+}
+
+// ── Table serializers ────────────────────────────────────────────────────────
+
+/// Unique glyph names (duplicates get fontTools' "#n" suffix) alongside the
+/// names the font itself uses.
+struct GlyphOrder {
+    names: Vec<String>,
+    ps_names: Vec<String>,
+}
+
+fn glyph_order(font: &FontRef<'_>, num_glyphs: u32) -> GlyphOrder {
+    let source = font.glyph_names();
+    let mut seen = HashSet::new();
+    let mut order = GlyphOrder {
+        names: Vec::with_capacity(num_glyphs as usize),
+        ps_names: Vec::with_capacity(num_glyphs as usize),
+    };
+    for gid in 0..num_glyphs {
+        let ps_name = match source.get(GlyphId::new(gid)) {
+            Some(name) if !name.as_str().is_empty() => name.as_str().to_string(),
+            _ if gid == 0 => ".notdef".to_string(),
+            _ => format!("glyph{:05}", gid),
+        };
+        let mut name = ps_name.clone();
+        let mut n = 1;
+        while !seen.insert(name.clone()) {
+            name = format!("{}#{}", ps_name, n);
+            n += 1;
+        }
+        order.names.push(name);
+        order.ps_names.push(ps_name);
+    }
+    order
+}
+
+fn write_head(w: &mut TtxWriter, font: &RawFontRef<'_>) -> Result<(), FontError> {
+    let head = font.head().map_err(|e| FontError::table("head", e))?;
+    let version = head.version();
+    w.begintag("head", &[]);
+    w.comment("Most of this table will be recalculated by the compiler");
+    w.value(
+        "tableVersion",
+        fixed_to_str(((version.major as i64) << 16) | version.minor as i64, 16),
+    );
+    w.value(
+        "fontRevision",
+        fixed_to_str(head.font_revision().to_bits() as i64, 16),
+    );
+    w.value(
+        "checkSumAdjustment",
+        format!("{:#x}", head.checksum_adjustment()),
+    );
+    w.value("magicNumber", format!("{:#x}", head.magic_number()));
+    w.value("flags", binary(head.flags().bits() as u32, 16));
+    w.value("unitsPerEm", head.units_per_em());
+    w.value("created", timestamp_to_str(head.created().as_secs()));
+    w.value("modified", timestamp_to_str(head.modified().as_secs()));
+    w.value("xMin", head.x_min());
+    w.value("yMin", head.y_min());
+    w.value("xMax", head.x_max());
+    w.value("yMax", head.y_max());
+    w.value("macStyle", binary(head.mac_style().bits() as u32, 16));
+    w.value("lowestRecPPEM", head.lowest_rec_ppem());
+    w.value("fontDirectionHint", head.font_direction_hint());
+    w.value("indexToLocFormat", head.index_to_loc_format());
+    w.value("glyphDataFormat", head.glyph_data_format());
+    w.endtag("head");
+    Ok(())
+}
+
+fn write_hhea(w: &mut TtxWriter, font: &RawFontRef<'_>) -> Result<(), FontError> {
+    let hhea = font.hhea().map_err(|e| FontError::table("hhea", e))?;
+    let version = hhea.version();
+    w.begintag("hhea", &[]);
+    w.value(
+        "tableVersion",
+        format!("0x{:04x}{:04x}", version.major, version.minor),
+    );
+    w.value("ascent", hhea.ascender().to_i16());
+    w.value("descent", hhea.descender().to_i16());
+    w.value("lineGap", hhea.line_gap().to_i16());
+    w.value("advanceWidthMax", hhea.advance_width_max().to_u16());
+    w.value("minLeftSideBearing", hhea.min_left_side_bearing().to_i16());
+    w.value(
+        "minRightSideBearing",
+        hhea.min_right_side_bearing().to_i16(),
+    );
+    w.value("xMaxExtent", hhea.x_max_extent().to_i16());
+    w.value("caretSlopeRise", hhea.caret_slope_rise());
+    w.value("caretSlopeRun", hhea.caret_slope_run());
+    w.value("caretOffset", hhea.caret_offset());
+    for reserved in ["reserved0", "reserved1", "reserved2", "reserved3"] {
+        w.value(reserved, 0);
+    }
+    w.value("metricDataFormat", hhea.metric_data_format());
+    w.value("numberOfHMetrics", hhea.number_of_h_metrics());
+    w.endtag("hhea");
+    Ok(())
+}
+
+fn write_maxp(w: &mut TtxWriter, font: &RawFontRef<'_>) -> Result<(), FontError> {
+    let maxp = font.maxp().map_err(|e| FontError::table("maxp", e))?;
+    let data = font
+        .table_data(Tag::new(b"maxp"))
+        .ok_or_else(|| FontError::missing("maxp"))?;
+    let version = data
+        .as_bytes()
+        .get(..4)
+        .map_or(0, |b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    w.begintag("maxp", &[]);
+    if version != 0x5000 {
+        w.comment("Most of this table will be recalculated by the compiler");
+    }
+    w.value("tableVersion", format!("{:#x}", version));
+    w.value("numGlyphs", maxp.num_glyphs());
+    if version != 0x5000 {
+        let fields = [
+            ("maxPoints", maxp.max_points()),
+            ("maxContours", maxp.max_contours()),
+            ("maxCompositePoints", maxp.max_composite_points()),
+            ("maxCompositeContours", maxp.max_composite_contours()),
+            ("maxZones", maxp.max_zones()),
+            ("maxTwilightPoints", maxp.max_twilight_points()),
+            ("maxStorage", maxp.max_storage()),
+            ("maxFunctionDefs", maxp.max_function_defs()),
+            ("maxInstructionDefs", maxp.max_instruction_defs()),
+            ("maxStackElements", maxp.max_stack_elements()),
+            ("maxSizeOfInstructions", maxp.max_size_of_instructions()),
+            ("maxComponentElements", maxp.max_component_elements()),
+            ("maxComponentDepth", maxp.max_component_depth()),
+        ];
+        for (name, value) in fields {
+            w.value(name, value.unwrap_or(0));
+        }
+    }
+    w.endtag("maxp");
+    Ok(())
+}
+
+/// Panose field names in table order.
+pub(crate) const PANOSE_FIELDS: [&str; 10] = [
+    "bFamilyType",
+    "bSerifStyle",
+    "bWeight",
+    "bProportion",
+    "bContrast",
+    "bStrokeVariation",
+    "bArmStyle",
+    "bLetterForm",
+    "bMidline",
+    "bXHeight",
+];
+
+fn write_os2(w: &mut TtxWriter, font: &RawFontRef<'_>) -> Result<(), FontError> {
+    let os2 = font.os2().map_err(|e| FontError::table("OS/2", e))?;
+    let version = os2.version();
+    w.begintag("OS_2", &[]);
+    w.comment(
+        "The fields 'usFirstCharIndex' and 'usLastCharIndex'\n     \
+         will be recalculated by the compiler",
+    );
+    w.value("version", version);
+    w.value("xAvgCharWidth", os2.x_avg_char_width());
+    w.value("usWeightClass", os2.us_weight_class());
+    w.value("usWidthClass", os2.us_width_class());
+    w.value("fsType", binary(os2.fs_type() as u32, 16));
+    w.value("ySubscriptXSize", os2.y_subscript_x_size());
+    w.value("ySubscriptYSize", os2.y_subscript_y_size());
+    w.value("ySubscriptXOffset", os2.y_subscript_x_offset());
+    w.value("ySubscriptYOffset", os2.y_subscript_y_offset());
+    w.value("ySuperscriptXSize", os2.y_superscript_x_size());
+    w.value("ySuperscriptYSize", os2.y_superscript_y_size());
+    w.value("ySuperscriptXOffset", os2.y_superscript_x_offset());
+    w.value("ySuperscriptYOffset", os2.y_superscript_y_offset());
+    w.value("yStrikeoutSize", os2.y_strikeout_size());
+    w.value("yStrikeoutPosition", os2.y_strikeout_position());
+    w.value("sFamilyClass", os2.s_family_class());
+    w.begintag("panose", &[]);
+    for (name, value) in PANOSE_FIELDS.iter().zip(os2.panose_10()) {
+        w.value(name, value);
+    }
+    w.endtag("panose");
+    w.value("ulUnicodeRange1", binary(os2.ul_unicode_range_1(), 32));
+    w.value("ulUnicodeRange2", binary(os2.ul_unicode_range_2(), 32));
+    w.value("ulUnicodeRange3", binary(os2.ul_unicode_range_3(), 32));
+    w.value("ulUnicodeRange4", binary(os2.ul_unicode_range_4(), 32));
+    let vendor: String = os2
+        .ach_vend_id()
+        .to_be_bytes()
+        .iter()
+        .map(|&b| match b {
+            b' '..=b'~' if b != b'\\' => (b as char).to_string(),
+            _ => format!("\\x{:02x}", b),
+        })
+        .collect();
+    w.value("achVendID", vendor);
+    w.value("fsSelection", binary(os2.fs_selection().bits() as u32, 16));
+    w.value("usFirstCharIndex", os2.us_first_char_index());
+    w.value("usLastCharIndex", os2.us_last_char_index());
+    w.value("sTypoAscender", os2.s_typo_ascender());
+    w.value("sTypoDescender", os2.s_typo_descender());
+    w.value("sTypoLineGap", os2.s_typo_line_gap());
+    w.value("usWinAscent", os2.us_win_ascent());
+    w.value("usWinDescent", os2.us_win_descent());
+    if version >= 1 {
+        let ranges = [os2.ul_code_page_range_1(), os2.ul_code_page_range_2()];
+        for (name, value) in ["ulCodePageRange1", "ulCodePageRange2"].iter().zip(ranges) {
+            w.value(name, binary(value.unwrap_or(0), 32));
+        }
+    }
+    if version >= 2 {
+        w.value("sxHeight", os2.sx_height().unwrap_or(0));
+        w.value("sCapHeight", os2.s_cap_height().unwrap_or(0));
+        w.value("usDefaultChar", os2.us_default_char().unwrap_or(0));
+        w.value("usBreakChar", os2.us_break_char().unwrap_or(0));
+        w.value("usMaxContext", os2.us_max_context().unwrap_or(0));
+    }
+    if version >= 5 {
+        let sizes = [
+            os2.us_lower_optical_point_size(),
+            os2.us_upper_optical_point_size(),
+        ];
+        for (name, value) in ["usLowerOpticalPointSize", "usUpperOpticalPointSize"]
+            .iter()
+            .zip(sizes)
+        {
+            w.value(name, value.unwrap_or(0));
+        }
+    }
+    w.endtag("OS_2");
+    Ok(())
+}
+
+fn write_hmtx(
+    w: &mut TtxWriter,
+    font: &RawFontRef<'_>,
+    order: &GlyphOrder,
+) -> Result<(), FontError> {
+    let num_h_metrics = font
+        .hhea()
+        .map_err(|e| FontError::table("hhea", e))?
+        .number_of_h_metrics() as usize;
+    let data = font
+        .table_data(Tag::new(b"hmtx"))
+        .ok_or_else(|| FontError::missing("hmtx"))?;
+    let data = data.as_bytes();
+    let read_u16 = |offset: usize| {
+        data.get(offset..offset + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(|| FontError::malformed("hmtx", "truncated"))
+    };
+
+    w.begintag("hmtx", &[]);
+    let mut advance = 0;
+    for (gid, name) in order.names.iter().enumerate() {
+        let lsb_offset = if gid < num_h_metrics {
+            advance = read_u16(gid * 4)?;
+            gid * 4 + 2
+        } else {
+            num_h_metrics * 4 + (gid - num_h_metrics) * 2
+        };
+        let lsb = read_u16(lsb_offset)? as i16;
+        w.simpletag(
+            "mtx",
+            &[
+                ("name", name.clone()),
+                ("width", advance.to_string()),
+                ("lsb", lsb.to_string()),
+            ],
+        );
+    }
+    w.endtag("hmtx");
+    Ok(())
+}
+
+fn write_cmap(
+    w: &mut TtxWriter,
+    font: &RawFontRef<'_>,
+    order: &GlyphOrder,
+) -> Result<(), FontError> {
+    let cmap = font.cmap().map_err(|e| FontError::table("cmap", e))?;
+    let name = |gid: u32| {
+        order
+            .names
+            .get(gid as usize)
+            .cloned()
+            .unwrap_or_else(|| format!("glyph{:05}", gid))
+    };
+    let write_maps = |w: &mut TtxWriter, mut maps: Vec<(u32, u32)>| {
+        maps.sort_unstable();
+        for (code, gid) in maps.into_iter().filter(|&(_, gid)| gid != 0) {
+            w.simpletag(
+                "map",
+                &[("code", format!("{:#x}", code)), ("name", name(gid))],
+            );
+        }
+    };
+
+    w.begintag("cmap", &[]);
+    w.simpletag("tableVersion", &[("version", cmap.version().to_string())]);
+    for record in cmap.encoding_records() {
+        let platform = (record.platform_id() as u16).to_string();
+        let encoding = record.encoding_id().to_string();
+        let Ok(subtable) = record.subtable(cmap.offset_data()) else {
+            w.comment(&format!(
+                "Unreadable subtable for platform {} encoding {} was omitted",
+                platform, encoding
+            ));
+            continue;
+        };
+        let header = |format: u16, language: u16| {
+            (
+                format!("cmap_format_{}", format),
+                vec![
+                    ("platformID", platform.clone()),
+                    ("platEncID", encoding.clone()),
+                    ("language", language.to_string()),
+                ],
+            )
+        };
+        match subtable {
+            CmapSubtable::Format0(sub) => {
+                let (tag, attrs) = header(0, sub.language());
+                w.begintag(&tag, &attrs);
+                let maps = (0u32..).zip(sub.glyph_id_array().iter().map(|&g| g as u32));
+                write_maps(w, maps.collect());
+                w.endtag(&tag);
+            }
+            CmapSubtable::Format4(sub) => {
+                let (tag, attrs) = header(4, sub.language());
+                w.begintag(&tag, &attrs);
+                write_maps(w, sub.iter().map(|(c, g)| (c, g.to_u32())).collect());
+                w.endtag(&tag);
+            }
+            CmapSubtable::Format6(sub) => {
+                let (tag, attrs) = header(6, sub.language());
+                w.begintag(&tag, &attrs);
+                let first = sub.first_code() as u32;
+                let maps = sub
+                    .glyph_id_array()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, g)| (first + i as u32, g.get() as u32));
+                write_maps(w, maps.collect());
+                w.endtag(&tag);
+            }
+            CmapSubtable::Format12(sub) => {
+                let tag = "cmap_format_12";
+                w.begintag(
+                    tag,
+                    &[
+                        ("platformID", platform.clone()),
+                        ("platEncID", encoding.clone()),
+                        ("format", "12".to_string()),
+                        ("reserved", "0".to_string()),
+                        ("length", sub.length().to_string()),
+                        ("language", sub.language().to_string()),
+                        ("nGroups", sub.num_groups().to_string()),
+                    ],
+                );
+                write_maps(w, sub.iter().map(|(c, g)| (c, g.to_u32())).collect());
+                w.endtag(tag);
+            }
+            CmapSubtable::Format13(sub) => {
+                let tag = "cmap_format_13";
+                w.begintag(
+                    tag,
+                    &[
+                        ("platformID", platform.clone()),
+                        ("platEncID", encoding.clone()),
+                        ("format", "13".to_string()),
+                        ("reserved", "0".to_string()),
+                        ("length", sub.length().to_string()),
+                        ("language", sub.language().to_string()),
+                        ("nGroups", sub.num_groups().to_string()),
+                    ],
+                );
+                write_maps(w, sub.iter().map(|(c, g)| (c, g.to_u32())).collect());
+                w.endtag(tag);
+            }
+            CmapSubtable::Format14(sub) => {
+                use skrifa::raw::tables::cmap::MapVariant;
+                let tag = "cmap_format_14";
+                w.begintag(
+                    tag,
+                    &[("platformID", platform.clone()), ("platEncID", encoding)],
+                );
+                let mut maps: Vec<(u32, u32, Option<u32>)> = sub
+                    .iter()
+                    .map(|(uv, uvs, variant)| match variant {
+                        MapVariant::UseDefault => (uvs, uv, None),
+                        MapVariant::Variant(gid) => (uvs, uv, Some(gid.to_u32())),
+                    })
+                    .collect();
+                maps.sort_unstable_by_key(|&(uvs, uv, gid)| (uvs, gid.is_some(), uv));
+                for (uvs, uv, gid) in maps {
+                    let mut attrs =
+                        vec![("uv", format!("{:#x}", uv)), ("uvs", format!("{:#x}", uvs))];
+                    if let Some(gid) = gid {
+                        attrs.push(("name", name(gid)));
+                    }
+                    w.simpletag("map", &attrs);
+                }
+                w.endtag(tag);
+            }
+            other => {
+                w.comment(&format!(
+                    "cmap subtable format {} for platform {} encoding {} is not supported \
+                     and was omitted",
+                    other.format(),
+                    platform,
+                    encoding
+                ));
+            }
+        }
+    }
+    w.endtag("cmap");
+    Ok(())
+}
+
+fn write_glyf(
+    w: &mut TtxWriter,
+    font: &RawFontRef<'_>,
+    order: &GlyphOrder,
+) -> Result<(), FontError> {
+    const KEPT_COMPONENT_FLAGS: u16 = 0x0004 | 0x0200 | 0x0400 | 0x0800 | 0x1000;
+
+    let loca = font.loca(None).map_err(|e| FontError::table("loca", e))?;
+    let glyf = font.glyf().map_err(|e| FontError::table("glyf", e))?;
+    let name = |gid: u32| {
+        order
+            .names
+            .get(gid as usize)
+            .cloned()
+            .unwrap_or_else(|| format!("glyph{:05}", gid))
+    };
+    let write_instructions = |w: &mut TtxWriter, instructions: &[u8]| {
+        if instructions.is_empty() {
+            w.simpletag("instructions", &[]);
+        } else {
+            w.begintag("instructions", &[]);
+            w.hexdata("bytecode", instructions);
+            w.endtag("instructions");
+        }
+    };
+
+    w.begintag("glyf", &[]);
+    w.comment(
+        "The xMin, yMin, xMax and yMax values\n     \
+         will be recalculated by the compiler.",
+    );
+    for (gid, glyph_name) in order.names.iter().enumerate() {
+        let glyph = loca
+            .get_glyf(GlyphId::new(gid as u32), &glyf)
+            .map_err(|e| FontError::malformed("glyf", format!("glyph {}: {:?}", gid, e)))?;
+        let Some(glyph) = glyph else {
+            w.line(&format!(
+                "<TTGlyph name=\"{}\"/><!-- contains no outline data -->",
+                xml::escape(glyph_name)
+            ));
+            continue;
+        };
+        let bbox = |x_min: i16, y_min: i16, x_max: i16, y_max: i16| {
+            vec![
+                ("name", glyph_name.clone()),
+                ("xMin", x_min.to_string()),
+                ("yMin", y_min.to_string()),
+                ("xMax", x_max.to_string()),
+                ("yMax", y_max.to_string()),
+            ]
+        };
+        match glyph {
+            Glyph::Simple(simple) => {
+                w.begintag(
+                    "TTGlyph",
+                    &bbox(
+                        simple.x_min(),
+                        simple.y_min(),
+                        simple.x_max(),
+                        simple.y_max(),
+                    ),
+                );
+                let overlap = simple.has_overlapping_contours();
+                let mut points = simple.points().enumerate();
+                for end in simple.end_pts_of_contours() {
+                    w.begintag("contour", &[]);
+                    for (i, point) in points.by_ref() {
+                        let mut attrs = vec![
+                            ("x", point.x.to_string()),
+                            ("y", point.y.to_string()),
+                            ("on", (point.on_curve as u8).to_string()),
+                        ];
+                        if i == 0 && overlap {
+                            attrs.push(("overlap", "1".to_string()));
+                        }
+                        w.simpletag("pt", &attrs);
+                        if i >= end.get() as usize {
+                            break;
+                        }
+                    }
+                    w.endtag("contour");
+                }
+                write_instructions(w, simple.instructions());
+            }
+            Glyph::Composite(composite) => {
+                w.begintag(
+                    "TTGlyph",
+                    &bbox(
+                        composite.x_min(),
+                        composite.y_min(),
+                        composite.x_max(),
+                        composite.y_max(),
+                    ),
+                );
+                for component in composite.components() {
+                    let mut attrs = vec![("glyphName", name(component.glyph.to_u32()))];
+                    match component.anchor {
+                        Anchor::Offset { x, y } => {
+                            attrs.push(("x", x.to_string()));
+                            attrs.push(("y", y.to_string()));
+                        }
+                        Anchor::Point { base, component } => {
+                            attrs.push(("firstPt", base.to_string()));
+                            attrs.push(("secondPt", component.to_string()));
+                        }
+                    }
+                    let t = component.transform;
+                    let f2dot14 =
+                        |v: skrifa::raw::types::F2Dot14| fixed_to_str(v.to_bits() as i64, 14);
+                    if t.yx.to_bits() != 0 || t.xy.to_bits() != 0 {
+                        attrs.push(("scalex", f2dot14(t.xx)));
+                        attrs.push(("scale01", f2dot14(t.yx)));
+                        attrs.push(("scale10", f2dot14(t.xy)));
+                        attrs.push(("scaley", f2dot14(t.yy)));
+                    } else if t.xx != t.yy {
+                        attrs.push(("scalex", f2dot14(t.xx)));
+                        attrs.push(("scaley", f2dot14(t.yy)));
+                    } else if t.xx.to_bits() != 0x4000 {
+                        attrs.push(("scale", f2dot14(t.xx)));
+                    }
+                    let flags = component.flags.bits() & KEPT_COMPONENT_FLAGS;
+                    attrs.push(("flags", format!("{:#x}", flags)));
+                    w.simpletag("component", &attrs);
+                }
+                if let Some(instructions) = composite.instructions() {
+                    write_instructions(w, instructions);
+                }
+            }
+        }
+        w.endtag("TTGlyph");
+    }
+    w.endtag("glyf");
+    Ok(())
+}
+
+fn write_name(w: &mut TtxWriter, font: &RawFontRef<'_>) -> Result<(), FontError> {
+    let name = font.name().map_err(|e| FontError::table("name", e))?;
+    w.begintag("name", &[]);
+    for record in name.name_record() {
+        let attrs = [
+            ("nameID", record.name_id().to_u16().to_string()),
+            ("platformID", record.platform_id().to_string()),
+            ("platEncID", record.encoding_id().to_string()),
+            ("langID", format!("{:#x}", record.language_id())),
+        ];
+        match record.string(name.string_data()) {
+            Ok(string) => {
+                w.begintag("namerecord", &attrs);
+                w.text(&string.chars().collect::<String>());
+                w.endtag("namerecord");
+            }
+            Err(_) => w.comment(&format!(
+                "Undecodable name record {} for platform {} was omitted",
+                attrs[0].1, attrs[1].1
+            )),
+        }
+    }
+    w.endtag("name");
+    Ok(())
+}
+
+fn write_post(
+    w: &mut TtxWriter,
+    font: &RawFontRef<'_>,
+    order: &GlyphOrder,
+) -> Result<(), FontError> {
+    let post = font.post().map_err(|e| FontError::table("post", e))?;
+    let data = font
+        .table_data(Tag::new(b"post"))
+        .ok_or_else(|| FontError::missing("post"))?;
+    let version = data
+        .as_bytes()
+        .get(..4)
+        .map_or(0, |b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+
+    w.begintag("post", &[]);
+    w.value("formatType", fixed_to_str(version as i64, 16));
+    w.value(
+        "italicAngle",
+        fixed_to_str(post.italic_angle().to_bits() as i64, 16),
+    );
+    w.value("underlinePosition", post.underline_position().to_i16());
+    w.value("underlineThickness", post.underline_thickness().to_i16());
+    w.value("isFixedPitch", post.is_fixed_pitch());
+    w.value("minMemType42", post.min_mem_type42());
+    w.value("maxMemType42", post.max_mem_type42());
+    w.value("minMemType1", post.min_mem_type1());
+    w.value("maxMemType1", post.max_mem_type1());
+    if version == 0x0002_0000 {
+        w.begintag("psNames", &[]);
+        w.comment(
+            "This file uses unique glyph names based on the information\n     \
+             found in the 'post' table. Since these names might not be unique,\n     \
+             we have to invent artificial names in case of clashes. In order to\n     \
+             be able to retain the original information, we need a name to\n     \
+             ps name mapping for those cases where they differ. That's what\n     \
+             you see below.\n      ",
+        );
+        for (name, ps_name) in order.names.iter().zip(&order.ps_names) {
+            if name != ps_name {
+                w.simpletag(
+                    "psName",
+                    &[("name", name.clone()), ("psName", ps_name.clone())],
+                );
+            }
+        }
+        w.endtag("psNames");
+        w.begintag("extraNames", &[]);
+        w.comment("following are the name that are not taken from the standard Mac glyph order");
+        let mut extra = HashSet::new();
+        for ps_name in &order.ps_names {
+            if !DEFAULT_GLYPH_NAMES.contains(&ps_name.as_str()) && extra.insert(ps_name) {
+                w.simpletag("psName", &[("name", ps_name.clone())]);
+            }
+        }
+        w.endtag("extraNames");
+    }
+    w.endtag("post");
+    Ok(())
+}
+
+// ── Export ───────────────────────────────────────────────────────────────────
+
+/// Tables with a structured TTX form, in the order fontTools writes them.
+const STRUCTURED_TABLES: [&str; 10] = [
+    "head", "hhea", "maxp", "OS/2", "hmtx", "cmap", "loca", "glyf", "name", "post",
+];
+
+/// Normalize a user-supplied table name ("OS_2", "cvt") to its 4-byte tag.
+pub(crate) fn parse_table_tag(name: &str) -> Result<Tag, FontError> {
+    let name = if name == "OS_2" { "OS/2" } else { name };
+    let bytes = name.as_bytes();
+    if bytes.is_empty() || bytes.len() > 4 || !name.is_ascii() {
+        return Err(FontError::input(format!("Invalid table tag: '{}'", name)));
+    }
+    let mut tag = [b' '; 4];
+    tag[..bytes.len()].copy_from_slice(bytes);
+    Ok(Tag::new(&tag))
+}
+
+/// Dump a font, or just `tables`, as TTX. The glyph order is always written
+/// since hmtx, cmap and glyf refer to glyphs by name.
+pub(crate) fn font_to_ttx(bytes: &[u8], tables: Option<&[String]>) -> Result<String, FontError> {
+    let raw = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as u32;
+    let order = glyph_order(&font, num_glyphs);
+
+    let present: Vec<Tag> = raw
+        .table_directory
+        .table_records()
+        .iter()
+        .map(|r| r.tag())
+        .collect();
+    let selected: Vec<Tag> = match tables {
+        Some(names) => {
+            let mut tags = Vec::with_capacity(names.len());
+            for name in names {
+                let tag = parse_table_tag(name)?;
+                if !present.contains(&tag) {
+                    return Err(FontError::missing(name));
+                }
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            tags
+        }
+        None => present.clone(),
+    };
+    let mut ordered: Vec<Tag> = STRUCTURED_TABLES
+        .iter()
+        .map(|t| parse_table_tag(t).unwrap())
+        .filter(|t| selected.contains(t))
+        .collect();
+    let rest: Vec<Tag> = selected
+        .iter()
+        .filter(|t| !ordered.contains(t))
+        .copied()
+        .collect();
+    ordered.extend(rest);
+
+    let sfnt_version: String = raw
+        .table_directory
+        .sfnt_version()
+        .to_be_bytes()
+        .iter()
+        .map(|&b| match b {
+            b' '..=b'~' if b != b'\\' => (b as char).to_string(),
+            _ => format!("\\x{:02x}", b),
+        })
+        .collect();
+
+    let mut w = TtxWriter {
+        out: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"),
+        depth: 0,
+    };
+    w.begintag("ttFont", &[("sfntVersion", sfnt_version)]);
+    w.out.push('\n');
+    w.begintag("GlyphOrder", &[]);
+    w.comment("The 'id' attribute is only for humans; it is ignored when parsed.");
+    for (gid, name) in order.names.iter().enumerate() {
+        w.simpletag(
+            "GlyphID",
+            &[("id", gid.to_string()), ("name", name.clone())],
+        );
+    }
+    w.endtag("GlyphOrder");
+
+    for tag in ordered {
+        w.out.push('\n');
+        let tag_str = tag.to_string();
+        let written = match tag_str.as_str() {
+            "head" => Some(write_head(&mut w, &raw)),
+            "hhea" => Some(write_hhea(&mut w, &raw)),
+            "maxp" => Some(write_maxp(&mut w, &raw)),
+            "OS/2" => Some(write_os2(&mut w, &raw)),
+            "hmtx" => Some(write_hmtx(&mut w, &raw, &order)),
+            "cmap" => Some(write_cmap(&mut w, &raw, &order)),
+            "glyf" => Some(write_glyf(&mut w, &raw, &order)),
+            "name" => Some(write_name(&mut w, &raw)),
+            "post" => Some(write_post(&mut w, &raw, &order)),
+            "loca" => {
+                w.begintag("loca", &[]);
+                w.comment("The 'loca' table will be calculated by the compiler");
+                w.endtag("loca");
+                Some(Ok(()))
+            }
+            _ => None,
+        };
+        if let Some(Ok(())) = written {
+            continue;
+        }
+        // Anything else, or a structured table that failed to parse, is
+        // written as raw bytes, which ttx compiles back unchanged.
+        let data = raw
+            .table_data(tag)
+            .ok_or_else(|| FontError::missing(&tag_str))?;
+        let xml_tag = tag_to_xml(&tag_str);
+        w.begintag(&xml_tag, &[("raw", "True".to_string())]);
+        if tag_str == "CFF " || tag_str == "CFF2" {
+            w.comment(
+                "Raw CFF data: recompile with 'ttx -b' so bounding boxes are \
+                 not recalculated",
+            );
+        }
+        if let Some(Err(e)) = written {
+            w.comment(&format!("Written as raw data: {}", e));
+        }
+        w.hexdata("hexdata", data.as_bytes());
+        w.endtag(&xml_tag);
+    }
+
+    w.out.push('\n');
+    w.endtag("ttFont");
+    Ok(w.out)
+}
+
+pub fn export_ttx(
+    file_path: &str,
+    destination: &str,
+    tables: Option<&[String]>,
+    cache: &FontCache,
+) -> Result<(), FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let ttx = font_to_ttx(&bytes, tables)?;
+    write_atomically(std::path::Path::new(destination), ttx.as_bytes())?;
+    Ok(())
+}
+
+// ── Import ───────────────────────────────────────────────────────────────────
+
+/// An error message that points at the TTX element it concerns.
+fn at(el: &Element, msg: impl std::fmt::Display) -> FontError {
+    FontError::input(format!("line {}: <{}> {}", el.line, el.name, msg))
+}
+
+/// Decimal or 0x-prefixed hexadecimal, optionally negative.
+fn parse_int(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// The inverse of `timestamp_to_str`; the weekday is not checked.
+fn parse_timestamp(s: &str) -> Option<i64> {
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    let [_, month, day, time, year] = parts[..] else {
+        return None;
+    };
+    let month = MONTHS.iter().position(|&m| m == month)? as i64 + 1;
+    let day: i64 = day.parse().ok().filter(|d| (1..=31).contains(d))?;
+    let year: i64 = year.parse().ok()?;
+    let hms: Vec<i64> = time
+        .split(':')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let [h, m, sec] = hms[..] else {
+        return None;
+    };
+    if !(0..24).contains(&h) || !(0..60).contains(&m) || !(0..61).contains(&sec) {
+        return None;
+    }
+
+    Some(days_from_civil(year, month, day) * 86_400 + h * 3600 + m * 60 + sec + EPOCH_OFFSET)
+}
+
+fn attr<'a>(el: &'a Element, name: &str) -> Result<&'a str, FontError> {
+    el.attr(name)
+        .ok_or_else(|| at(el, format!("is missing the '{}' attribute", name)))
+}
+
+fn int_attr(el: &Element, name: &str, min: i64, max: i64) -> Result<i64, FontError> {
+    let raw = attr(el, name)?;
+    let value =
+        parse_int(raw).ok_or_else(|| at(el, format!("{}=\"{}\" is not an integer", name, raw)))?;
+    if value < min || value > max {
+        return Err(at(
+            el,
+            format!(
+                "{}=\"{}\" is outside the range {}..={}",
+                name, raw, min, max
+            ),
+        ));
+    }
+    Ok(value)
+}
+
+/// Value lookups on a table element's `<field value="..."/>` children.
+struct Fields<'a> {
+    table: &'a Element,
+}
+
+impl<'a> Fields<'a> {
+    fn get(&self, name: &str) -> Result<&'a Element, FontError> {
+        self.table
+            .child(name)
+            .ok_or_else(|| at(self.table, format!("is missing <{}>", name)))
+    }
+
+    fn int(&self, name: &str, min: i64, max: i64) -> Result<i64, FontError> {
+        int_attr(self.get(name)?, "value", min, max)
+    }
+
+    fn u8(&self, name: &str) -> Result<u8, FontError> {
+        Ok(self.int(name, 0, u8::MAX as i64)? as u8)
+    }
+
+    fn u16(&self, name: &str) -> Result<u16, FontError> {
+        Ok(self.int(name, 0, u16::MAX as i64)? as u16)
+    }
+
+    fn i16(&self, name: &str) -> Result<i16, FontError> {
+        Ok(self.int(name, i16::MIN as i64, i16::MAX as i64)? as i16)
+    }
+
+    fn u32(&self, name: &str) -> Result<u32, FontError> {
+        Ok(self.int(name, 0, u32::MAX as i64)? as u32)
+    }
+
+    /// A hex table version that must be one of `allowed`.
+    fn version(&self, allowed: &[i64]) -> Result<i64, FontError> {
+        let el = self.get("tableVersion")?;
+        let raw = attr(el, "value")?;
+        match parse_int(raw).filter(|v| allowed.contains(v)) {
+            Some(v) => Ok(v),
+            None => {
+                let expected: Vec<String> = allowed.iter().map(|v| format!("{:#x}", v)).collect();
+                Err(at(
+                    el,
+                    format!(
+                        "version \"{}\" is not supported; expected {}",
+                        raw,
+                        expected.join(" or ")
+                    ),
+                ))
+            }
+        }
+    }
+
+    fn fixed(&self, name: &str, min: f64, max: f64) -> Result<Fixed, FontError> {
+        let el = self.get(name)?;
+        let raw = attr(el, "value")?;
+        let value: f64 = raw
+            .trim()
+            .parse()
+            .map_err(|_| at(el, format!("value \"{}\" is not a number", raw)))?;
+        if !(min..=max).contains(&value) {
+            return Err(at(
+                el,
+                format!("value \"{}\" is outside the range {}..={}", raw, min, max),
+            ));
+        }
+        Ok(Fixed::from_f64(value))
+    }
+
+    /// fontTools' grouped binary digits ("00000000 00000011").
+    fn binary(&self, name: &str, bits: u32) -> Result<u32, FontError> {
+        let el = self.get(name)?;
+        let raw = attr(el, "value")?;
+        let digits: String = raw.chars().filter(|c| !c.is_whitespace()).collect();
+        if digits.is_empty()
+            || digits.len() > bits as usize
+            || !digits.chars().all(|c| c == '0' || c == '1')
+        {
+            return Err(at(
+                el,
+                format!("value \"{}\" is not a {}-bit binary number", raw, bits),
+            ));
+        }
+        Ok(u32::from_str_radix(&digits, 2).unwrap())
+    }
+
+    fn timestamp(&self, name: &str) -> Result<LongDateTime, FontError> {
+        let el = self.get(name)?;
+        let raw = attr(el, "value")?;
+        parse_timestamp(raw).map(LongDateTime::new).ok_or_else(|| {
+            at(
+                el,
+                format!(
+                    "value \"{}\" is not a date like \"Thu Jan  1 00:00:00 1970\"",
+                    raw
+                ),
+            )
+        })
+    }
+}
+
+/// The font being spliced into, with the glyph names the fragment refers to.
+struct ImportContext<'a> {
+    font: RawFontRef<'a>,
+    glyph_names: Vec<String>,
+    glyph_ids: HashMap<String, u32>,
+}
+
+impl ImportContext<'_> {
+    fn glyph_id(&self, el: &Element, name_attr: &str) -> Result<u32, FontError> {
+        let name = attr(el, name_attr)?;
+        self.glyph_ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| at(el, format!("refers to unknown glyph '{}'", name)))
+    }
+}
+
+/// Glyph names from the fragment's `<GlyphOrder>`, or the font's own names
+/// when the fragment has none.
+fn import_glyph_order(
+    root: &Element,
+    font: &FontRef<'_>,
+    num_glyphs: u32,
+) -> Result<Vec<String>, FontError> {
+    let Some(glyph_order) = root.child("GlyphOrder") else {
+        return Ok(glyph_order(font, num_glyphs).names);
+    };
+    let names = glyph_order
+        .children_named("GlyphID")
+        .map(|g| attr(g, "name").map(str::to_string))
+        .collect::<Result<Vec<_>, _>>()?;
+    if names.len() != num_glyphs as usize {
+        return Err(at(
+            glyph_order,
+            format!(
+                "lists {} glyphs but the font has {}",
+                names.len(),
+                num_glyphs
+            ),
+        ));
+    }
+    Ok(names)
+}
+
+fn import_head(t: &Element, ctx: &ImportContext<'_>) -> Result<Vec<u8>, FontError> {
+    let f = Fields { table: t };
+    f.fixed("tableVersion", 1.0, 1.0)?;
+    let index_to_loc_format = f.int("indexToLocFormat", 0, 1)? as i16;
+    // loca offsets are stored in the format head declares
+    if let Ok(head) = ctx.font.head() {
+        let current = head.index_to_loc_format();
+        if ctx.font.table_data(Tag::new(b"loca")).is_some() && index_to_loc_format != current {
+            return Err(at(
+                f.get("indexToLocFormat")?,
+                format!(
+                    "cannot change from {} without rebuilding the loca table",
+                    current
+                ),
+            ));
+        }
+    }
+    let head = Head {
+        font_revision: f.fixed("fontRevision", i16::MIN as f64, i16::MAX as f64)?,
+        flags: Flags::from_bits_truncate(f.binary("flags", 16)? as u16),
+        units_per_em: f.int("unitsPerEm", 16, 16384)? as u16,
+        created: f.timestamp("created")?,
+        modified: f.timestamp("modified")?,
+        x_min: f.i16("xMin")?,
+        y_min: f.i16("yMin")?,
+        x_max: f.i16("xMax")?,
+        y_max: f.i16("yMax")?,
+        mac_style: MacStyle::from_bits_truncate(f.binary("macStyle", 16)? as u16),
+        lowest_rec_ppem: f.u16("lowestRecPPEM")?,
+        font_direction_hint: f.int("fontDirectionHint", -2, 2)? as i16,
+        index_to_loc_format,
+        ..Default::default()
+    };
+    f.int("glyphDataFormat", 0, 0)?;
+    write_fonts::dump_table(&head).map_err(|e| FontError::build("head", e))
+}
+
+fn import_hhea(t: &Element, ctx: &ImportContext<'_>) -> Result<Vec<u8>, FontError> {
+    let f = Fields { table: t };
+    f.version(&[0x0001_0000])?;
+    f.int("metricDataFormat", 0, 0)?;
+    let num_glyphs = ctx.glyph_names.len() as i64;
+    let number_of_h_metrics = f.int("numberOfHMetrics", 1, num_glyphs.max(1))? as u16;
+    // The hmtx layout depends on this count; importing hmtx recomputes it
+    if let Ok(current) = ctx.font.hhea() {
+        let current = current.number_of_h_metrics();
+        if ctx.font.table_data(Tag::new(b"hmtx")).is_some() && number_of_h_metrics != current {
+            return Err(at(
+                f.get("numberOfHMetrics")?,
+                format!(
+                    "must match the font's hmtx table ({}); import hmtx to change it",
+                    current
+                ),
+            ));
+        }
+    }
+    let hhea = Hhea::new(
+        FWord::new(f.i16("ascent")?),
+        FWord::new(f.i16("descent")?),
+        FWord::new(f.i16("lineGap")?),
+        UfWord::new(f.u16("advanceWidthMax")?),
+        FWord::new(f.i16("minLeftSideBearing")?),
+        FWord::new(f.i16("minRightSideBearing")?),
+        FWord::new(f.i16("xMaxExtent")?),
+        f.i16("caretSlopeRise")?,
+        f.i16("caretSlopeRun")?,
+        f.i16("caretOffset")?,
+        number_of_h_metrics,
+    );
+    write_fonts::dump_table(&hhea).map_err(|e| FontError::build("hhea", e))
+}
+
+fn import_maxp(t: &Element, ctx: &ImportContext<'_>) -> Result<Vec<u8>, FontError> {
+    let f = Fields { table: t };
+    let version = f.version(&[0x0001_0000, 0x5000])?;
+    let num_glyphs = f.u16("numGlyphs")?;
+    if num_glyphs as usize != ctx.glyph_names.len() {
+        return Err(at(
+            f.get("numGlyphs")?,
+            format!(
+                "cannot change the glyph count (the font has {} glyphs)",
+                ctx.glyph_names.len()
+            ),
+        ));
+    }
+    let mut maxp = Maxp {
+        num_glyphs,
+        ..Default::default()
+    };
+    if version == 0x0001_0000 {
+        maxp.max_points = Some(f.u16("maxPoints")?);
+        maxp.max_contours = Some(f.u16("maxContours")?);
+        maxp.max_composite_points = Some(f.u16("maxCompositePoints")?);
+        maxp.max_composite_contours = Some(f.u16("maxCompositeContours")?);
+        maxp.max_zones = Some(f.int("maxZones", 1, 2)? as u16);
+        maxp.max_twilight_points = Some(f.u16("maxTwilightPoints")?);
+        maxp.max_storage = Some(f.u16("maxStorage")?);
+        maxp.max_function_defs = Some(f.u16("maxFunctionDefs")?);
+        maxp.max_instruction_defs = Some(f.u16("maxInstructionDefs")?);
+        maxp.max_stack_elements = Some(f.u16("maxStackElements")?);
+        maxp.max_size_of_instructions = Some(f.u16("maxSizeOfInstructions")?);
+        maxp.max_component_elements = Some(f.u16("maxComponentElements")?);
+        maxp.max_component_depth = Some(f.u16("maxComponentDepth")?);
+    }
+    write_fonts::dump_table(&maxp).map_err(|e| FontError::build("maxp", e))
+}
+
+/// Decode fontTools' achVendID text, where non-printable bytes are `\xNN`.
+fn parse_vendor_id(el: &Element) -> Result<Tag, FontError> {
+    let raw = attr(el, "value")?;
+    let invalid = || {
+        at(
+            el,
+            format!("value \"{}\" is not a four-byte vendor ID", raw),
+        )
+    };
+    let mut bytes = Vec::new();
+    let mut rest = raw;
+    while let Some(c) = rest.chars().next() {
+        if let Some(hex) = rest.strip_prefix("\\x") {
+            let byte = hex
+                .get(..2)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(invalid)?;
+            bytes.push(byte);
+            rest = &hex[2..];
+        } else if c.is_ascii() {
+            bytes.push(c as u8);
+            rest = &rest[1..];
+        } else {
+            return Err(invalid());
+        }
+    }
+    if bytes.len() > 4 {
+        return Err(invalid());
+    }
+    bytes.resize(4, b' ');
+    Ok(Tag::new(&[bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn import_os2(t: &Element) -> Result<Vec<u8>, FontError> {
+    let f = Fields { table: t };
+    let version = f.int("version", 0, 5)?;
+    let panose = Fields {
+        table: f.get("panose")?,
+    };
+    let mut panose_10 = [0u8; 10];
+    for (slot, name) in panose_10.iter_mut().zip(PANOSE_FIELDS) {
+        *slot = panose.u8(name)?;
+    }
+    let mut os2 = Os2 {
+        x_avg_char_width: f.i16("xAvgCharWidth")?,
+        us_weight_class: f.int("usWeightClass", 1, 1000)? as u16,
+        us_width_class: f.int("usWidthClass", 1, 9)? as u16,
+        fs_type: f.binary("fsType", 16)? as u16,
+        y_subscript_x_size: f.i16("ySubscriptXSize")?,
+        y_subscript_y_size: f.i16("ySubscriptYSize")?,
+        y_subscript_x_offset: f.i16("ySubscriptXOffset")?,
+        y_subscript_y_offset: f.i16("ySubscriptYOffset")?,
+        y_superscript_x_size: f.i16("ySuperscriptXSize")?,
+        y_superscript_y_size: f.i16("ySuperscriptYSize")?,
+        y_superscript_x_offset: f.i16("ySuperscriptXOffset")?,
+        y_superscript_y_offset: f.i16("ySuperscriptYOffset")?,
+        y_strikeout_size: f.i16("yStrikeoutSize")?,
+        y_strikeout_position: f.i16("yStrikeoutPosition")?,
+        s_family_class: f.i16("sFamilyClass")?,
+        panose_10,
+        ul_unicode_range_1: f.binary("ulUnicodeRange1", 32)?,
+        ul_unicode_range_2: f.binary("ulUnicodeRange2", 32)?,
+        ul_unicode_range_3: f.binary("ulUnicodeRange3", 32)?,
+        ul_unicode_range_4: f.binary("ulUnicodeRange4", 32)?,
+        ach_vend_id: parse_vendor_id(f.get("achVendID")?)?,
+        fs_selection: SelectionFlags::from_bits_truncate(f.binary("fsSelection", 16)? as u16),
+        us_first_char_index: f.u16("usFirstCharIndex")?,
+        us_last_char_index: f.u16("usLastCharIndex")?,
+        s_typo_ascender: f.i16("sTypoAscender")?,
+        s_typo_descender: f.i16("sTypoDescender")?,
+        s_typo_line_gap: f.i16("sTypoLineGap")?,
+        us_win_ascent: f.u16("usWinAscent")?,
+        us_win_descent: f.u16("usWinDescent")?,
+        ..Default::default()
+    };
+    // write-fonts picks the version from the optional fields that are set
+    if version >= 1 {
+        os2.ul_code_page_range_1 = Some(f.binary("ulCodePageRange1", 32)?);
+        os2.ul_code_page_range_2 = Some(f.binary("ulCodePageRange2", 32)?);
+    }
+    if version >= 2 {
+        os2.sx_height = Some(f.i16("sxHeight")?);
+        os2.s_cap_height = Some(f.i16("sCapHeight")?);
+        os2.us_default_char = Some(f.u16("usDefaultChar")?);
+        os2.us_break_char = Some(f.u16("usBreakChar")?);
+        os2.us_max_context = Some(f.u16("usMaxContext")?);
+    }
+    if version >= 5 {
+        os2.us_lower_optical_point_size = Some(f.u16("usLowerOpticalPointSize")?);
+        os2.us_upper_optical_point_size = Some(f.u16("usUpperOpticalPointSize")?);
+    }
+    write_fonts::dump_table(&os2).map_err(|e| FontError::build("OS/2", e))
+}
+
+/// hmtx, plus hhea with its metric count and advanceWidthMax updated to match.
+fn import_hmtx(t: &Element, ctx: &ImportContext<'_>) -> Result<Vec<(Tag, Vec<u8>)>, FontError> {
+    let mut metrics: Vec<Option<(u16, i16)>> = vec![None; ctx.glyph_names.len()];
+    for mtx in t.children_named("mtx") {
+        let gid = ctx.glyph_id(mtx, "name")? as usize;
+        if metrics[gid].is_some() {
+            return Err(at(
+                mtx,
+                format!("repeats the metrics for glyph '{}'", ctx.glyph_names[gid]),
+            ));
+        }
+        let width = int_attr(mtx, "width", 0, u16::MAX as i64)? as u16;
+        let lsb = int_attr(mtx, "lsb", i16::MIN as i64, i16::MAX as i64)? as i16;
+        metrics[gid] = Some((width, lsb));
+    }
+    let metrics = metrics
+        .into_iter()
+        .enumerate()
+        .map(|(gid, m)| {
+            m.ok_or_else(|| {
+                at(
+                    t,
+                    format!("has no <mtx> for glyph '{}'", ctx.glyph_names[gid]),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Trailing glyphs that share the last advance only store a side bearing
+    let mut long_count = metrics.len().max(1);
+    while long_count > 1 && metrics[long_count - 1].0 == metrics[long_count - 2].0 {
+        long_count -= 1;
+    }
+    let hmtx = Hmtx::new(
+        metrics[..long_count.min(metrics.len())]
+            .iter()
+            .map(|&(advance, lsb)| LongMetric::new(advance, lsb))
+            .collect(),
+        metrics[long_count.min(metrics.len())..]
+            .iter()
+            .map(|&(_, lsb)| lsb)
+            .collect(),
+    );
+
+    let mut hhea: Hhea = ctx
+        .font
+        .hhea()
+        .map_err(|e| FontError::table("hhea", e))?
+        .to_owned_table();
+    hhea.number_of_h_metrics = long_count as u16;
+    hhea.advance_width_max = UfWord::new(metrics.iter().map(|m| m.0).max().unwrap_or(0));
+
+    Ok(vec![
+        (
+            Tag::new(b"hmtx"),
+            write_fonts::dump_table(&hmtx).map_err(|e| FontError::build("hmtx", e))?,
+        ),
+        (
+            Tag::new(b"hhea"),
+            write_fonts::dump_table(&hhea).map_err(|e| FontError::build("hhea", e))?,
+        ),
+    ])
+}
+
+/// `<map code name>` entries of a cmap subtable, sorted by code.
+fn cmap_mappings(
+    sub: &Element,
+    ctx: &ImportContext<'_>,
+    max_code: i64,
+) -> Result<Vec<(u32, u32)>, FontError> {
+    let mut maps: Vec<(u32, u32)> = Vec::new();
+    let mut seen: HashMap<u32, u32> = HashMap::new();
+    for map in sub.children_named("map") {
+        let code = int_attr(map, "code", 0, max_code)? as u32;
+        let gid = ctx.glyph_id(map, "name")?;
+        match seen.insert(code, gid) {
+            Some(previous) if previous != gid => {
+                return Err(at(
+                    map,
+                    format!(
+                        "maps {:#x} again; it is already mapped to '{}'",
+                        code, ctx.glyph_names[previous as usize]
+                    ),
+                ))
+            }
+            Some(_) => {}
+            None => maps.push((code, gid)),
+        }
+    }
+    maps.sort_unstable();
+    Ok(maps)
+}
+
+fn import_cmap_subtable(
+    sub: &Element,
+    format: u16,
+    ctx: &ImportContext<'_>,
+) -> Result<CmapSubtableOut, FontError> {
+    let language_max = if format >= 8 {
+        u32::MAX as i64
+    } else {
+        u16::MAX as i64
+    };
+    let language = match format {
+        14 => 0,
+        _ => int_attr(sub, "language", 0, language_max)?,
+    };
+    match format {
+        0 => {
+            let mut glyph_ids = vec![0u8; 256];
+            for (code, gid) in cmap_mappings(sub, ctx, 0xFF)? {
+                glyph_ids[code as usize] =
+                    u8::try_from(gid).map_err(|_| at(sub, "can only map glyph IDs up to 255"))?;
+            }
+            Ok(CmapSubtableOut::format_0(language as u16, glyph_ids))
+        }
+        4 => {
+            let mut mappings = Vec::new();
+            for (code, gid) in cmap_mappings(sub, ctx, 0xFFFF)? {
+                let ch = char::from_u32(code)
+                    .ok_or_else(|| at(sub, format!("cannot map the surrogate {:#x}", code)))?;
+                mappings.push((ch, GlyphId::new(gid)));
+            }
+            let cmap = Cmap::from_mappings(mappings)
+                .map_err(|e| at(sub, format!("could not be compiled: {:?}", e)))?;
+            let mut subtable = cmap
+                .encoding_records
+                .into_iter()
+                .map(|r| (*r.subtable).clone())
+                .find(|s| matches!(s, CmapSubtableOut::Format4(_)))
+                // No mappings at all: just the required 0xFFFF segment
+                .unwrap_or_else(|| {
+                    CmapSubtableOut::format_4(
+                        0,
+                        vec![0xFFFF],
+                        vec![0xFFFF],
+                        vec![1],
+                        vec![0],
+                        vec![],
+                    )
+                });
+            if let CmapSubtableOut::Format4(table) = &mut subtable {
+                table.language = language as u16;
+            }
+            Ok(subtable)
+        }
+        6 => {
+            let maps = cmap_mappings(sub, ctx, 0xFFFF)?;
+            let first = maps.first().map_or(0, |m| m.0);
+            let last = maps.last().map_or(0, |m| m.0 + 1);
+            let mut glyph_ids = vec![0u16; (last - first) as usize];
+            for (code, gid) in maps {
+                glyph_ids[(code - first) as usize] = u16::try_from(gid).unwrap_or(0);
+            }
+            let count = glyph_ids.len() as u16;
+            Ok(CmapSubtableOut::format_6(
+                10 + 2 * count,
+                language as u16,
+                first as u16,
+                count,
+                glyph_ids,
+            ))
+        }
+        12 => {
+            let mut groups: Vec<SequentialMapGroup> = Vec::new();
+            for (code, gid) in cmap_mappings(sub, ctx, 0x10FFFF)? {
+                match groups.last_mut() {
+                    Some(g)
+                        if g.end_char_code + 1 == code
+                            && g.start_glyph_id + (code - g.start_char_code) == gid =>
+                    {
+                        g.end_char_code = code
+                    }
+                    _ => groups.push(SequentialMapGroup::new(code, code, gid)),
+                }
+            }
+            Ok(CmapSubtableOut::format_12(language as u32, groups))
+        }
+        13 => {
+            let mut groups: Vec<ConstantMapGroup> = Vec::new();
+            for (code, gid) in cmap_mappings(sub, ctx, 0x10FFFF)? {
+                match groups.last_mut() {
+                    Some(g) if g.end_char_code + 1 == code && g.glyph_id == gid => {
+                        g.end_char_code = code
+                    }
+                    _ => groups.push(ConstantMapGroup::new(code, code, gid)),
+                }
+            }
+            let count = groups.len() as u32;
+            Ok(CmapSubtableOut::format_13(
+                16 + 12 * count,
+                language as u32,
+                count,
+                groups,
+            ))
+        }
+        14 => {
+            // Per selector: default-UVS code points and explicit variants
+            type Selector = (Vec<u32>, Vec<(u32, u32)>);
+            let mut selectors: BTreeMap<u32, Selector> = BTreeMap::new();
+            for map in sub.children_named("map") {
+                let uv = int_attr(map, "uv", 0, 0x10FFFF)? as u32;
+                let uvs = int_attr(map, "uvs", 0, 0x10FFFF)? as u32;
+                let entry = selectors.entry(uvs).or_default();
+                if map.attr("name").is_some() {
+                    let gid = ctx.glyph_id(map, "name")?;
+                    if gid > u16::MAX as u32 {
+                        return Err(at(map, "maps a glyph ID above 65535"));
+                    }
+                    entry.1.push((uv, gid));
+                } else {
+                    entry.0.push(uv);
+                }
+            }
+            let mut length = 10 + 11 * selectors.len() as u32;
+            let mut records = Vec::new();
+            for (uvs, (mut defaults, mut variants)) in selectors {
+                defaults.sort_unstable();
+                defaults.dedup();
+                variants.sort_unstable();
+                variants.dedup();
+                let mut ranges: Vec<(u32, u32)> = Vec::new();
+                for uv in defaults {
+                    match ranges.last_mut() {
+                        Some((start, count)) if *start + *count + 1 == uv && *count < 255 => {
+                            *count += 1
+                        }
+                        _ => ranges.push((uv, 0)),
+                    }
+                }
+                let default_uvs = (!ranges.is_empty()).then(|| {
+                    length += 4 + 4 * ranges.len() as u32;
+                    DefaultUvs::new(
+                        ranges.len() as u32,
+                        ranges
+                            .iter()
+                            .map(|&(start, count)| {
+                                UnicodeRange::new(Uint24::new(start), count as u8)
+                            })
+                            .collect(),
+                    )
+                });
+                let non_default_uvs = (!variants.is_empty()).then(|| {
+                    length += 4 + 5 * variants.len() as u32;
+                    NonDefaultUvs::new(
+                        variants.len() as u32,
+                        variants
+                            .iter()
+                            .map(|&(uv, gid)| UvsMapping::new(Uint24::new(uv), gid as u16))
+                            .collect(),
+                    )
+                });
+                records.push(VariationSelector::new(
+                    Uint24::new(uvs),
+                    default_uvs,
+                    non_default_uvs,
+                ));
+            }
+            Ok(CmapSubtableOut::format_14(
+                length,
+                records.len() as u32,
+                records,
+            ))
+        }
+        _ => Err(at(
+            sub,
+            format!("uses cmap format {}, which cannot be imported", format),
+        )),
+    }
+}
+
+fn import_cmap(t: &Element, ctx: &ImportContext<'_>) -> Result<Vec<u8>, FontError> {
+    if let Some(version) = t.child("tableVersion") {
+        int_attr(version, "version", 0, 0)?;
+    }
+    let mut records = Vec::new();
+    for sub in &t.children {
+        let Some(format) = sub.name.strip_prefix("cmap_format_") else {
+            if sub.name != "tableVersion" {
+                return Err(at(sub, "is not a cmap subtable"));
+            }
+            continue;
+        };
+        let format: u16 = format
+            .parse()
+            .map_err(|_| at(sub, "has an invalid subtable format"))?;
+        let platform = int_attr(sub, "platformID", 0, 4)? as u16;
+        let encoding = int_attr(sub, "platEncID", 0, u16::MAX as i64)? as u16;
+        if format == 14 && (platform, encoding) != (0, 5) {
+            return Err(at(sub, "format 14 must use platformID 0 and platEncID 5"));
+        }
+        let subtable = import_cmap_subtable(sub, format, ctx)?;
+        records.push(EncodingRecord::new(
+            PlatformId::new(platform),
+            encoding,
+            subtable,
+        ));
+    }
+    records.sort_by_key(|r| (r.platform_id as u16, r.encoding_id));
+    write_fonts::dump_table(&Cmap::new(records)).map_err(|e| FontError::build("cmap", e))
+}
+
+fn import_name(t: &Element) -> Result<Vec<u8>, FontError> {
+    let mut records = Vec::new();
+    for record in &t.children {
+        if record.name != "namerecord" {
+            return Err(at(record, "is not a name record"));
+        }
+        records.push(NameRecord {
+            platform_id: int_attr(record, "platformID", 0, 4)? as u16,
+            encoding_id: int_attr(record, "platEncID", 0, u16::MAX as i64)? as u16,
+            language_id: int_attr(record, "langID", 0, u16::MAX as i64)? as u16,
+            name_id: (int_attr(record, "nameID", 0, 0x7FFF)? as u16).into(),
+            // ttx indents the text on its own line; the indentation is not content
+            string: record.text.trim().to_string().into(),
+        });
+    }
+    records.sort_by_key(|r| (r.platform_id, r.encoding_id, r.language_id, r.name_id));
+    write_fonts::dump_table(&Name::new(records)).map_err(|e| FontError::build("name", e))
+}
+
+fn import_post(t: &Element, ctx: &ImportContext<'_>) -> Result<Vec<u8>, FontError> {
+    let f = Fields { table: t };
+    let format = f.get("formatType")?;
+    let version = match attr(format, "value")?.trim() {
+        "1.0" | "1" => Version16Dot16::VERSION_1_0,
+        "2.0" | "2" => Version16Dot16::VERSION_2_0,
+        "3.0" | "3" => Version16Dot16::VERSION_3_0,
+        other => {
+            return Err(at(
+                format,
+                format!(
+                    "format \"{}\" is not supported; expected 1.0, 2.0 or 3.0",
+                    other
+                ),
+            ))
+        }
+    };
+    let mut post = if version == Version16Dot16::VERSION_2_0 {
+        // psNames maps the unique GlyphOrder names back to the font's own
+        let mut renamed: HashMap<&str, &str> = HashMap::new();
+        if let Some(ps_names) = t.child("psNames") {
+            for ps_name in ps_names.children_named("psName") {
+                renamed.insert(attr(ps_name, "name")?, attr(ps_name, "psName")?);
+            }
+        }
+        Post::new_v2(
+            ctx.glyph_names
+                .iter()
+                .map(|n| renamed.get(n.as_str()).copied().unwrap_or(n)),
+        )
+    } else {
+        Post {
+            version,
+            ..Default::default()
+        }
+    };
+    post.italic_angle = f.fixed("italicAngle", -90.0, 90.0)?;
+    post.underline_position = FWord::new(f.i16("underlinePosition")?);
+    post.underline_thickness = FWord::new(f.i16("underlineThickness")?);
+    post.is_fixed_pitch = f.u32("isFixedPitch")?;
+    post.min_mem_type42 = f.u32("minMemType42")?;
+    post.max_mem_type42 = f.u32("maxMemType42")?;
+    post.min_mem_type1 = f.u32("minMemType1")?;
+    post.max_mem_type1 = f.u32("maxMemType1")?;
+    write_fonts::dump_table(&post).map_err(|e| FontError::build("post", e))
+}
+
+/// Bytes of a `raw="True"` table's `<hexdata>`.
+fn import_raw(t: &Element) -> Result<Vec<u8>, FontError> {
+    let hexdata = t
+        .child("hexdata")
+        .ok_or_else(|| at(t, "is marked raw but has no <hexdata>"))?;
+    let digits: Vec<u8> = hexdata
+        .text
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(at(hexdata, "has an odd number of hex digits"));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| {
+                    at(
+                        hexdata,
+                        format!(
+                            "contains \"{}\", which is not hex",
+                            String::from_utf8_lossy(pair)
+                        ),
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Compile one table from a TTX fragment and splice it into `bytes`. The
+/// fragment is either a bare table element or a `<ttFont>` holding it,
+/// optionally with the `<GlyphOrder>` its glyph names refer to.
+pub(crate) fn splice_ttx_table(
+    bytes: &[u8],
+    table_tag: &str,
+    ttx_xml: &str,
+) -> Result<Vec<u8>, FontError> {
+    let tag = parse_table_tag(table_tag)?;
+    let xml_tag = tag_to_xml(&tag.to_string());
+    let root = xml::parse(ttx_xml).map_err(|e| FontError::input(format!("Invalid TTX: {}", e)))?;
+    let table = if root.name == "ttFont" {
+        root.child(&xml_tag)
+    } else {
+        Some(&root).filter(|r| r.name == xml_tag)
+    }
+    .ok_or_else(|| FontError::input(format!("TTX has no <{}> element", xml_tag)))?;
+
+    let raw = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as u32;
+    let glyph_names = import_glyph_order(&root, &font, num_glyphs)?;
+    let glyph_ids = glyph_names
+        .iter()
+        .enumerate()
+        .map(|(gid, name)| (name.clone(), gid as u32))
+        .collect();
+    let ctx = ImportContext {
+        font: raw.clone(),
+        glyph_names,
+        glyph_ids,
+    };
+
+    let tables = if table.attr("raw") == Some("True") {
+        vec![(tag, import_raw(table)?)]
+    } else {
+        match xml_tag.as_str() {
+            "head" => vec![(tag, import_head(table, &ctx)?)],
+            "hhea" => vec![(tag, import_hhea(table, &ctx)?)],
+            "maxp" => vec![(tag, import_maxp(table, &ctx)?)],
+            "OS_2" => vec![(tag, import_os2(table)?)],
+            "hmtx" => import_hmtx(table, &ctx)?,
+            "cmap" => vec![(tag, import_cmap(table, &ctx)?)],
+            "name" => vec![(tag, import_name(table)?)],
+            "post" => vec![(tag, import_post(table, &ctx)?)],
+            _ => {
+                return Err(at(
+                    table,
+                    "cannot be imported from structured XML; export it with raw data, \
+                     or use one of head, hhea, maxp, OS_2, hmtx, cmap, name, post",
+                ))
+            }
+        }
+    };
+
+    let mut builder = FontBuilder::new();
+    for (tag, data) in tables {
+        builder.add_raw(tag, data);
+    }
+    Ok(builder.copy_missing_tables(raw).build())
+}
+
+pub fn import_ttx_table(
+    file_path: &str,
+    table_tag: &str,
+    ttx_xml: &str,
+    cache: &FontCache,
+) -> Result<(), FontError> {
+    let bytes = editable_font_bytes(file_path, cache)?;
+    let new_bytes = splice_ttx_table(&bytes, table_tag, ttx_xml)?;
+    let new_bytes = match table_tag.trim_end() {
+        "hmtx" | "glyf" => recalc_x_avg_char_width(new_bytes)?,
+        _ => new_bytes,
+    };
+    // An imported head carries its own modified date
+    if table_tag.trim_end() == "head" {
+        write_font_file(file_path, new_bytes, cache)?;
+    } else {
+        save_edited_font(file_path, new_bytes, cache)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{composite_glyph, simple_glyph, TestComponent, TestFontBuilder};
+
+    #[test]
+    fn test_iso_dates_round_trip_across_the_1904_epoch() {
+        for secs in [
+            0,
+            EPOCH_OFFSET - 1,
+            EPOCH_OFFSET,
+            EPOCH_OFFSET + 1_709_294_400, // 2024-03-01T12:00:00Z
+            -86_400,
+        ] {
+            assert_eq!(iso_to_timestamp(&timestamp_to_iso(secs)), Some(secs));
+        }
+        assert_eq!(iso_to_timestamp("1904-01-01"), Some(0));
+        assert_eq!(iso_to_timestamp("1970-01-01T00:00Z"), Some(EPOCH_OFFSET));
+        assert_eq!(
+            iso_to_timestamp("1970-01-01T02:00:00.750+02:00"),
+            Some(EPOCH_OFFSET)
+        );
+        assert_eq!(
+            iso_to_timestamp("1969-12-31 19:00:00-05:00"),
+            Some(EPOCH_OFFSET)
+        );
+        assert_eq!(
+            iso_to_timestamp("2024-02-29T00:00:00Z"),
+            Some(3_792_009_600)
+        );
+        for bad in [
+            "",
+            "2024-13-01",
+            "2023-02-29",
+            "1900-02-29",
+            "2023-04-31",
+            "2024-11-31T00:00:00Z",
+            "2024-03",
+            "12:00:00",
+            "2024-03-01T25:00:00Z",
+        ] {
+            assert_eq!(iso_to_timestamp(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_value_formatting() {
+        assert_eq!(fixed_to_str(0x0001_0000, 16), "1.0");
+        assert_eq!(fixed_to_str(131_400, 16), "2.005");
+        assert_eq!(fixed_to_str(-12 << 16 | 0x8000, 16), "-11.5");
+        assert_eq!(fixed_to_str(0x2000, 14), "0.5");
+        assert_eq!(binary(3, 16), "00000000 00000011");
+        assert_eq!(timestamp_to_str(0), "Thu Jan  1 00:00:00 1970");
+        assert_eq!(timestamp_to_iso(0), "1904-01-01T00:00:00Z");
+        assert_eq!(
+            timestamp_to_str(2_082_844_800 + 1_700_000_000),
+            "Tue Nov 14 22:13:20 2023"
+        );
+        assert_eq!(tag_to_xml("OS/2"), "OS_2");
+        assert_eq!(tag_to_xml("cvt "), "cvt");
+        assert_eq!(tag_to_xml("CFF "), "CFF");
+        assert_eq!(
+            parse_timestamp("Tue Nov 14 22:13:20 2023"),
+            Some(2_082_844_800 + 1_700_000_000)
+        );
+        assert_eq!(parse_timestamp("Tue Nov 14 25:13:20 2023"), None);
+    }
+
+    #[test]
+    fn test_font_to_ttx() {
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, false)]]);
+        let comp = composite_glyph(&[TestComponent {
+            glyph_id: 1,
+            dx: 10,
+            dy: -5,
+            transform: Some([0.5, 0.0, 0.0, 0.5]),
+        }]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(bar, 600)
+            .glyph(comp, 700)
+            .map('a' as u32, 1)
+            .map('b' as u32, 2)
+            .table(b"GSUB", vec![0, 1, 0, 0, 0xAB])
+            .build();
+
+        let ttx = font_to_ttx(&font, None).unwrap();
+        let root = xml::parse(&ttx).unwrap();
+        assert_eq!(root.name, "ttFont");
+        assert_eq!(root.attr("sfntVersion"), Some("\\x00\\x01\\x00\\x00"));
+        assert_eq!(root.children[0].name, "GlyphOrder");
+        assert_eq!(root.children[1].name, "head");
+
+        let glyph_names: Vec<&str> = root.children[0]
+            .children_named("GlyphID")
+            .filter_map(|g| g.attr("name"))
+            .collect();
+        assert_eq!(glyph_names.len(), 3);
+
+        let cmap = root.child("cmap").unwrap();
+        let maps: Vec<(&str, &str)> = cmap
+            .children
+            .iter()
+            .flat_map(|s| s.children_named("map"))
+            .map(|m| (m.attr("code").unwrap(), m.attr("name").unwrap()))
+            .collect();
+        assert!(maps.contains(&("0x61", glyph_names[1])));
+
+        let hmtx = root.child("hmtx").unwrap();
+        let widths: Vec<&str> = hmtx
+            .children_named("mtx")
+            .filter_map(|m| m.attr("width"))
+            .collect();
+        assert_eq!(widths, vec!["500", "600", "700"]);
+
+        let glyf = root.child("glyf").unwrap();
+        let glyphs: Vec<&xml::Element> = glyf.children_named("TTGlyph").collect();
+        assert_eq!(glyphs[1].child("contour").unwrap().children.len(), 3);
+        let component = glyphs[2].child("component").unwrap();
+        assert_eq!(component.attr("glyphName"), Some(glyph_names[1]));
+        assert_eq!(component.attr("x"), Some("10"));
+        assert_eq!(component.attr("scale"), Some("0.5"));
+
+        let gsub = root.child("GSUB").unwrap();
+        assert_eq!(gsub.attr("raw"), Some("True"));
+        assert_eq!(gsub.child("hexdata").unwrap().text.trim(), "00010000 ab");
+
+        // A table selection keeps the glyph order and drops everything else
+        let partial = font_to_ttx(&font, Some(&["hhea".to_string()])).unwrap();
+        let root = xml::parse(&partial).unwrap();
+        let names: Vec<&str> = root.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["GlyphOrder", "hhea"]);
+        assert!(font_to_ttx(&font, Some(&["kern".to_string()])).is_err());
+    }
+
+    #[test]
+    fn test_splice_ttx_table() {
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, false)]]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(bar.clone(), 600)
+            .glyph(bar, 600)
+            .map('a' as u32, 1)
+            .build();
+
+        // Round trip through an edited export of head
+        let ttx = font_to_ttx(&font, Some(&["head".to_string()])).unwrap();
+        let edited = ttx.replace(
+            "<unitsPerEm value=\"1000\"/>",
+            "<unitsPerEm value=\"2048\"/>",
+        );
+        assert_ne!(ttx, edited);
+        let spliced = splice_ttx_table(&font, "head", &edited).unwrap();
+        let raw = RawFontRef::new(&spliced).unwrap();
+        assert_eq!(raw.head().unwrap().units_per_em(), 2048);
+        assert_eq!(
+            raw.table_data(Tag::new(b"glyf")).unwrap().as_bytes(),
+            RawFontRef::new(&font)
+                .unwrap()
+                .table_data(Tag::new(b"glyf"))
+                .unwrap()
+                .as_bytes()
+        );
+
+        // hmtx recomputes the long metric count in hhea
+        let ttx = font_to_ttx(&font, Some(&["hmtx".to_string()])).unwrap();
+        let glyph2 = xml::parse(&ttx).unwrap().children[0].children[2]
+            .attr("name")
+            .unwrap()
+            .to_string();
+        let edited = ttx.replace(
+            &format!("<mtx name=\"{}\" width=\"600\"", glyph2),
+            &format!("<mtx name=\"{}\" width=\"650\"", glyph2),
+        );
+        let spliced = splice_ttx_table(&font, "hmtx", &edited).unwrap();
+        let raw = RawFontRef::new(&spliced).unwrap();
+        assert_eq!(raw.hhea().unwrap().number_of_h_metrics(), 3);
+        assert_eq!(raw.hhea().unwrap().advance_width_max().to_u16(), 650);
+        assert_eq!(raw.hmtx().unwrap().advance(GlyphId::new(2)), Some(650));
+
+        // cmap, and a name table the font did not have before
+        let ttx = font_to_ttx(&font, Some(&["cmap".to_string()])).unwrap();
+        let spliced = splice_ttx_table(&font, "cmap", &ttx).unwrap();
+        let charmap = FontRef::new(&spliced).unwrap().charmap();
+        assert_eq!(charmap.map('a'), Some(GlyphId::new(1)));
+        let name = "<name>\n  <namerecord nameID=\"1\" platformID=\"3\" platEncID=\"1\" \
+                    langID=\"0x409\">\n    Test &amp; Co\n  </namerecord>\n</name>";
+        let spliced = splice_ttx_table(&font, "name", name).unwrap();
+        let exported = font_to_ttx(&spliced, Some(&["name".to_string()])).unwrap();
+        let record = xml::parse(&exported).unwrap().children[1].children[0].clone();
+        assert_eq!(record.text.trim(), "Test & Co");
+
+        // Raw tables splice byte for byte
+        let raw_ttx = "<GSUB raw=\"True\">\n  <hexdata>\n    00010000 ab\n  </hexdata>\n</GSUB>";
+        let spliced = splice_ttx_table(&font, "GSUB", raw_ttx).unwrap();
+        let raw = RawFontRef::new(&spliced).unwrap();
+        assert_eq!(
+            raw.table_data(Tag::new(b"GSUB")).unwrap().as_bytes(),
+            &[0, 1, 0, 0, 0xAB]
+        );
+    }
+
+    #[test]
+    fn test_splice_ttx_table_errors() {
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .map('a' as u32, 0)
+            .build();
+        let head = font_to_ttx(&font, Some(&["head".to_string()])).unwrap();
+
+        let err = splice_ttx_table(
+            &font,
+            "head",
+            &head.replace("<unitsPerEm value=\"1000\"/>", "<unitsPerEm value=\"5\"/>"),
+        )
+        .unwrap_err();
+        let line = head.lines().position(|l| l.contains("unitsPerEm")).unwrap() + 1;
+        assert_eq!(
+            err,
+            FontError::input(format!(
+                "line {}: <unitsPerEm> value=\"5\" is outside the range 16..=16384",
+                line
+            ))
+        );
+
+        let err = splice_ttx_table(&font, "head", &head.replace("<flags value", "<flagz value"))
+            .unwrap_err();
+        assert!(
+            err.to_string().ends_with("<head> is missing <flags>"),
+            "{}",
+            err
+        );
+
+        let cmap =
+            "<cmap>\n  <cmap_format_4 platformID=\"3\" platEncID=\"1\" language=\"0\">\n    \
+                    <map code=\"0x41\" name=\"nosuchglyph\"/>\n  </cmap_format_4>\n</cmap>";
+        let err = splice_ttx_table(&font, "cmap", cmap).unwrap_err();
+        assert_eq!(
+            err,
+            FontError::input("line 3: <map> refers to unknown glyph 'nosuchglyph'")
+        );
+
+        assert_eq!(
+            splice_ttx_table(&font, "OS/2", &head).unwrap_err(),
+            FontError::input("TTX has no <OS_2> element")
+        );
+        assert!(splice_ttx_table(&font, "head", "<head>")
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid TTX: line 1:"));
+    }
+}
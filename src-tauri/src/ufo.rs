@@ -0,0 +1,1213 @@
+use crate::error::FontError;
+use crate::font_parser::write_atomically;
+use crate::woff::encode_simple_glyph;
+use crate::xml::{self, Element};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use write_fonts::tables::cmap::Cmap;
+use write_fonts::tables::head::{Flags, Head, MacStyle};
+use write_fonts::tables::hhea::Hhea;
+use write_fonts::tables::hmtx::{Hmtx, LongMetric};
+use write_fonts::tables::maxp::Maxp;
+use write_fonts::tables::name::{Name, NameRecord};
+use write_fonts::tables::os2::{Os2, SelectionFlags};
+use write_fonts::tables::post::Post;
+use write_fonts::types::{FWord, Fixed, GlyphId, LongDateTime, Tag, UfWord};
+use write_fonts::FontBuilder;
+
+// ── Property lists ───────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq)]
+enum Plist {
+    Dict(Vec<(String, Plist)>),
+    Array(Vec<Plist>),
+    String(String),
+    Integer(i64),
+    Real(f64),
+    Bool(bool),
+}
+
+impl Plist {
+    fn get(&self, key: &str) -> Option<&Plist> {
+        match self {
+            Plist::Dict(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Plist::Integer(v) => Some(*v as f64),
+            Plist::Real(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Plist::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+fn parse_plist_value(el: &Element) -> Result<Plist, FontError> {
+    let number_error =
+        || FontError::input(format!("line {}: invalid <{}> value", el.line, el.name));
+    Ok(match el.name.as_str() {
+        "dict" => {
+            let mut entries = Vec::new();
+            let mut children = el.children.iter();
+            while let Some(key) = children.next() {
+                if key.name != "key" {
+                    return Err(FontError::input(format!(
+                        "line {}: expected <key> in <dict>",
+                        key.line
+                    )));
+                }
+                let value = children.next().ok_or_else(|| {
+                    FontError::input(format!("line {}: <key> without a value", key.line))
+                })?;
+                entries.push((key.text.clone(), parse_plist_value(value)?));
+            }
+            Plist::Dict(entries)
+        }
+        "array" => Plist::Array(
+            el.children
+                .iter()
+                .map(parse_plist_value)
+                .collect::<Result<_, _>>()?,
+        ),
+        "integer" => Plist::Integer(el.text.trim().parse().map_err(|_| number_error())?),
+        "real" => Plist::Real(el.text.trim().parse().map_err(|_| number_error())?),
+        "true" => Plist::Bool(true),
+        "false" => Plist::Bool(false),
+        // Dates and data are kept as their text; nothing we compile uses them
+        "string" | "date" | "data" => Plist::String(el.text.clone()),
+        other => {
+            return Err(FontError::input(format!(
+                "line {}: unknown plist element <{}>",
+                el.line, other
+            )))
+        }
+    })
+}
+
+/// Read a plist file, or `None` if it does not exist.
+fn read_plist(path: &Path) -> Result<Option<Plist>, FontError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(path).map_err(|e| FontError::io(&path.to_string_lossy(), e))?;
+    let root = xml::parse(&text)
+        .map_err(|e| FontError::input(format!("Failed to parse {}: {}", path.display(), e)))?;
+    let value = match root.name.as_str() {
+        "plist" => root.children.first().map(parse_plist_value).transpose(),
+        _ => parse_plist_value(&root).map(Some),
+    };
+    value.map_err(|e| FontError::input(format!("Failed to parse {}: {}", path.display(), e)))
+}
+
+// ── GLIF ─────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PointType {
+    Move,
+    Line,
+    OffCurve,
+    Curve,
+    QCurve,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GlifPoint {
+    x: f64,
+    y: f64,
+    kind: PointType,
+}
+
+#[derive(Debug, Clone)]
+struct GlifComponent {
+    base: String,
+    /// Affine transform as [xx, xy, yx, yy, dx, dy]
+    transform: [f64; 6],
+}
+
+#[derive(Debug, Clone)]
+struct GlifGlyph {
+    name: String,
+    advance: f64,
+    unicodes: Vec<u32>,
+    contours: Vec<Vec<GlifPoint>>,
+    components: Vec<GlifComponent>,
+}
+
+fn parse_glif(text: &str) -> Result<GlifGlyph, FontError> {
+    let root = xml::parse(text).map_err(FontError::input)?;
+    if root.name != "glyph" {
+        return Err(FontError::input(format!(
+            "line {}: expected <glyph>",
+            root.line
+        )));
+    }
+    let number = |el: &Element, attr: &str, default: f64| -> Result<f64, FontError> {
+        match el.attr(attr) {
+            Some(v) => v.trim().parse().map_err(|_| {
+                FontError::input(format!("line {}: invalid {} '{}'", el.line, attr, v))
+            }),
+            None => Ok(default),
+        }
+    };
+
+    let mut glyph = GlifGlyph {
+        name: root.attr("name").unwrap_or_default().to_string(),
+        advance: 0.0,
+        unicodes: Vec::new(),
+        contours: Vec::new(),
+        components: Vec::new(),
+    };
+    if let Some(advance) = root.child("advance") {
+        glyph.advance = number(advance, "width", 0.0)?;
+    }
+    for unicode in root.children_named("unicode") {
+        let hex = unicode.attr("hex").unwrap_or_default();
+        let code = u32::from_str_radix(hex, 16).map_err(|_| {
+            FontError::input(format!("line {}: invalid unicode '{}'", unicode.line, hex))
+        })?;
+        glyph.unicodes.push(code);
+    }
+
+    let Some(outline) = root.child("outline") else {
+        return Ok(glyph);
+    };
+    for child in &outline.children {
+        match child.name.as_str() {
+            "contour" => {
+                let mut contour = Vec::new();
+                for point in child.children_named("point") {
+                    let kind = match point.attr("type").unwrap_or("offcurve") {
+                        "move" => PointType::Move,
+                        "line" => PointType::Line,
+                        "offcurve" => PointType::OffCurve,
+                        "curve" => PointType::Curve,
+                        "qcurve" => PointType::QCurve,
+                        other => {
+                            return Err(FontError::input(format!(
+                                "line {}: unknown point type '{}'",
+                                point.line, other
+                            )))
+                        }
+                    };
+                    if point.attr("x").is_none() || point.attr("y").is_none() {
+                        return Err(FontError::input(format!(
+                            "line {}: point is missing x or y",
+                            point.line
+                        )));
+                    }
+                    contour.push(GlifPoint {
+                        x: number(point, "x", 0.0)?,
+                        y: number(point, "y", 0.0)?,
+                        kind,
+                    });
+                }
+                // Format 1 stores anchors as single-point contours
+                if contour.len() > 1 {
+                    glyph.contours.push(contour);
+                }
+            }
+            "component" => {
+                let base = child.attr("base").ok_or_else(|| {
+                    FontError::input(format!("line {}: component is missing base", child.line))
+                })?;
+                glyph.components.push(GlifComponent {
+                    base: base.to_string(),
+                    transform: [
+                        number(child, "xScale", 1.0)?,
+                        number(child, "xyScale", 0.0)?,
+                        number(child, "yxScale", 0.0)?,
+                        number(child, "yScale", 1.0)?,
+                        number(child, "xOffset", 0.0)?,
+                        number(child, "yOffset", 0.0)?,
+                    ],
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(glyph)
+}
+
+// ── Outline conversion ───────────────────────────────────────────────────────
+
+/// A TrueType contour as `(x, y, on_curve)` points.
+type Contour = Vec<(f64, f64, bool)>;
+
+/// Maximum distance, in font units, between a cubic and its quadratic
+/// approximation.
+const CURVE_TOLERANCE: f64 = 1.0;
+
+fn cubic_at(p: [(f64, f64); 4], t: f64) -> (f64, f64) {
+    let mt = 1.0 - t;
+    let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+    (
+        a * p[0].0 + b * p[1].0 + c * p[2].0 + d * p[3].0,
+        a * p[0].1 + b * p[1].1 + c * p[2].1 + d * p[3].1,
+    )
+}
+
+fn cubic_derivative(p: [(f64, f64); 4], t: f64) -> (f64, f64) {
+    let mt = 1.0 - t;
+    let (a, b, c) = (3.0 * mt * mt, 6.0 * mt * t, 3.0 * t * t);
+    (
+        a * (p[1].0 - p[0].0) + b * (p[2].0 - p[1].0) + c * (p[3].0 - p[2].0),
+        a * (p[1].1 - p[0].1) + b * (p[2].1 - p[1].1) + c * (p[3].1 - p[2].1),
+    )
+}
+
+/// Approximate a cubic with quadratics by splitting it into equal parameter
+/// ranges. Returns the points after `p[0]`, ending with the on-curve `p[3]`.
+fn cubic_to_quadratics(p: [(f64, f64); 4]) -> Contour {
+    // A cubic's distance from its single-quadratic approximation is bounded
+    // by sqrt(3)/36 * |p3 - 3p2 + 3p1 - p0|, and shrinks with the cube of the
+    // number of pieces.
+    let dx = p[3].0 - 3.0 * p[2].0 + 3.0 * p[1].0 - p[0].0;
+    let dy = p[3].1 - 3.0 * p[2].1 + 3.0 * p[1].1 - p[0].1;
+    let error = 3f64.sqrt() / 36.0 * dx.hypot(dy);
+    let pieces = ((error / CURVE_TOLERANCE).cbrt().ceil() as usize).clamp(1, 16);
+
+    let mut out = Vec::with_capacity(pieces * 2);
+    for i in 0..pieces {
+        let (t0, t1) = (i as f64 / pieces as f64, (i + 1) as f64 / pieces as f64);
+        let scale = (t1 - t0) / 3.0;
+        let q0 = cubic_at(p, t0);
+        let q3 = if i + 1 == pieces {
+            p[3]
+        } else {
+            cubic_at(p, t1)
+        };
+        let (d0, d1) = (cubic_derivative(p, t0), cubic_derivative(p, t1));
+        let q1 = (q0.0 + d0.0 * scale, q0.1 + d0.1 * scale);
+        let q2 = (q3.0 - d1.0 * scale, q3.1 - d1.1 * scale);
+        let control = (
+            (3.0 * (q1.0 + q2.0) - q0.0 - q3.0) / 4.0,
+            (3.0 * (q1.1 + q2.1) - q0.1 - q3.1) / 4.0,
+        );
+        out.push((control.0, control.1, false));
+        out.push((q3.0, q3.1, true));
+    }
+    out
+}
+
+/// Convert a GLIF contour to TrueType points. Open contours are closed.
+fn contour_to_quadratic(points: &[GlifPoint]) -> Contour {
+    let is_on = |p: &GlifPoint| p.kind != PointType::OffCurve;
+    let Some(first_on) = points.iter().position(is_on) else {
+        // All off-curve: a quadratic contour with only implied on-curve points
+        return points.iter().map(|p| (p.x, p.y, false)).collect();
+    };
+    let open = points[0].kind == PointType::Move;
+
+    // Walk the segments starting from an on-curve point; a closed contour
+    // wraps around back to that point.
+    let start = points[first_on];
+    let sequence: Vec<GlifPoint> = if open {
+        points[1..].to_vec()
+    } else {
+        points[first_on + 1..]
+            .iter()
+            .chain(&points[..=first_on])
+            .copied()
+            .collect()
+    };
+
+    let mut out: Contour = vec![(start.x, start.y, true)];
+    let mut current = (start.x, start.y);
+    let mut offs: Vec<(f64, f64)> = Vec::new();
+    for p in sequence {
+        if p.kind == PointType::OffCurve {
+            offs.push((p.x, p.y));
+            continue;
+        }
+        if p.kind == PointType::Curve && offs.len() == 2 {
+            out.extend(cubic_to_quadratics([current, offs[0], offs[1], (p.x, p.y)]));
+        } else {
+            // Lines, quadratic splines and degenerate curves map directly
+            out.extend(offs.iter().map(|&(x, y)| (x, y, false)));
+            out.push((p.x, p.y, true));
+        }
+        offs.clear();
+        current = (p.x, p.y);
+    }
+    out.extend(offs.iter().map(|&(x, y)| (x, y, false)));
+    if !open {
+        // The last segment ends on the start point, which glyf closes implicitly
+        out.pop();
+    }
+    out
+}
+
+fn transform_contour(contour: &Contour, m: &[f64; 6]) -> Contour {
+    let mut out: Contour = contour
+        .iter()
+        .map(|&(x, y, on)| (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5], on))
+        .collect();
+    // A mirroring transform flips the winding direction
+    if m[0] * m[3] - m[1] * m[2] < 0.0 {
+        out.reverse();
+    }
+    out
+}
+
+fn fits_f2dot14(v: f64) -> bool {
+    (-2.0..2.0).contains(&v)
+}
+
+fn fits_i16(v: f64) -> bool {
+    (i16::MIN as f64..=i16::MAX as f64).contains(&v.round())
+}
+
+// ── Compilation ──────────────────────────────────────────────────────────────
+
+/// Components nested deeper than this are treated as a cycle.
+const MAX_COMPONENT_DEPTH: usize = 64;
+
+enum GlyphOutline {
+    Simple(Vec<Contour>),
+    Composite(Vec<(u16, [f64; 6])>),
+}
+
+struct GlyphSet {
+    glyphs: Vec<GlifGlyph>,
+    index: HashMap<String, usize>,
+    /// Cubic UFOs wind counter-clockwise; TrueType expects clockwise
+    reverse: bool,
+}
+
+impl GlyphSet {
+    fn own_contours(&self, gid: usize) -> Vec<Contour> {
+        self.glyphs[gid]
+            .contours
+            .iter()
+            .map(|c| {
+                let mut contour = contour_to_quadratic(c);
+                if self.reverse {
+                    contour.reverse();
+                }
+                contour
+            })
+            .collect()
+    }
+
+    /// Every contour of the glyph with components decomposed.
+    fn flattened(&self, gid: usize, depth: usize) -> Result<Vec<Contour>, FontError> {
+        if depth > MAX_COMPONENT_DEPTH {
+            return Err(FontError::input(format!(
+                "Component cycle detected at glyph '{}'",
+                self.glyphs[gid].name
+            )));
+        }
+        let mut contours = self.own_contours(gid);
+        for component in &self.glyphs[gid].components {
+            if let Some(&base) = self.index.get(&component.base) {
+                for contour in self.flattened(base, depth + 1)? {
+                    contours.push(transform_contour(&contour, &component.transform));
+                }
+            }
+        }
+        Ok(contours)
+    }
+
+    /// Nesting depth of composite references below `gid` (1 for a composite
+    /// of simple glyphs).
+    fn component_depth(&self, gid: usize, depth: usize) -> Result<u16, FontError> {
+        if depth > MAX_COMPONENT_DEPTH {
+            return Err(FontError::input(format!(
+                "Component cycle detected at glyph '{}'",
+                self.glyphs[gid].name
+            )));
+        }
+        let mut max = 0;
+        for component in &self.glyphs[gid].components {
+            if let Some(&base) = self.index.get(&component.base) {
+                max = max.max(1 + self.component_depth(base, depth + 1)?);
+            }
+        }
+        Ok(max)
+    }
+
+    fn outline(&self, gid: usize, warnings: &mut Vec<String>) -> Result<GlyphOutline, FontError> {
+        let glyph = &self.glyphs[gid];
+        for component in &glyph.components {
+            if !self.index.contains_key(&component.base) {
+                warnings.push(format!(
+                    "Glyph '{}' references missing glyph '{}'; the component was skipped",
+                    glyph.name, component.base
+                ));
+            }
+        }
+        let components: Vec<(u16, [f64; 6])> = glyph
+            .components
+            .iter()
+            .filter_map(|c| self.index.get(&c.base).map(|&i| (i as u16, c.transform)))
+            .collect();
+        let representable = components.iter().all(|(_, m)| {
+            m[..4].iter().all(|&v| fits_f2dot14(v)) && fits_i16(m[4]) && fits_i16(m[5])
+        });
+
+        if glyph.contours.is_empty() && !components.is_empty() && representable {
+            return Ok(GlyphOutline::Composite(components));
+        }
+        if !components.is_empty() {
+            warnings.push(format!(
+                "Components of glyph '{}' were decomposed",
+                glyph.name
+            ));
+        }
+        Ok(GlyphOutline::Simple(self.flattened(gid, 0)?))
+    }
+}
+
+fn bounding_box(contours: &[Contour]) -> Option<[i16; 4]> {
+    let mut points = contours.iter().flatten();
+    let &(x, y, _) = points.next()?;
+    let (x, y) = (x.round() as i16, y.round() as i16);
+    Some(points.fold([x, y, x, y], |b, &(x, y, _)| {
+        let (x, y) = (x.round() as i16, y.round() as i16);
+        [b[0].min(x), b[1].min(y), b[2].max(x), b[3].max(y)]
+    }))
+}
+
+fn encode_composite_glyph(components: &[(u16, [f64; 6])], bbox: [i16; 4]) -> Vec<u8> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+    const ROUND_XY_TO_GRID: u16 = 0x0004;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+    let f2dot14 = |v: f64| ((v * 16384.0).round() as i16).to_be_bytes();
+    let mut out = Vec::new();
+    out.extend((-1i16).to_be_bytes());
+    for v in bbox {
+        out.extend(v.to_be_bytes());
+    }
+    for (i, &(gid, m)) in components.iter().enumerate() {
+        let mut flags = ARG_1_AND_2_ARE_WORDS | ARGS_ARE_XY_VALUES | ROUND_XY_TO_GRID;
+        if i + 1 < components.len() {
+            flags |= MORE_COMPONENTS;
+        }
+        let mut scale = Vec::new();
+        if m[1] != 0.0 || m[2] != 0.0 {
+            flags |= WE_HAVE_A_TWO_BY_TWO;
+            for v in &m[..4] {
+                scale.extend(f2dot14(*v));
+            }
+        } else if m[0] != m[3] {
+            flags |= WE_HAVE_AN_X_AND_Y_SCALE;
+            scale.extend(f2dot14(m[0]));
+            scale.extend(f2dot14(m[3]));
+        } else if m[0] != 1.0 {
+            flags |= WE_HAVE_A_SCALE;
+            scale.extend(f2dot14(m[0]));
+        }
+        out.extend(flags.to_be_bytes());
+        out.extend(gid.to_be_bytes());
+        out.extend((m[4].round() as i16).to_be_bytes());
+        out.extend((m[5].round() as i16).to_be_bytes());
+        out.extend(scale);
+    }
+    out
+}
+
+/// Read the default layer's glyphs in glyph order, with .notdef first.
+fn read_glyph_set(ufo: &Path, warnings: &mut Vec<String>) -> Result<Vec<GlifGlyph>, FontError> {
+    let layer_dir = read_plist(&ufo.join("layercontents.plist"))?
+        .and_then(|layers| match layers {
+            Plist::Array(layers) => layers.into_iter().find_map(|layer| match layer {
+                Plist::Array(pair) if pair.len() == 2 => pair[1].as_str().map(str::to_string),
+                _ => None,
+            }),
+            _ => None,
+        })
+        .unwrap_or_else(|| "glyphs".to_string());
+    let glyphs_dir = ufo.join(layer_dir);
+    let contents = read_plist(&glyphs_dir.join("contents.plist"))?.ok_or_else(|| {
+        FontError::input(format!(
+            "Missing {}",
+            glyphs_dir.join("contents.plist").display()
+        ))
+    })?;
+    let Plist::Dict(entries) = contents else {
+        return Err(FontError::input("contents.plist is not a dictionary"));
+    };
+
+    let mut glyphs = Vec::with_capacity(entries.len());
+    for (name, file) in entries {
+        let file = file.as_str().ok_or_else(|| {
+            FontError::input(format!("contents.plist: invalid file name for '{}'", name))
+        })?;
+        let path = glyphs_dir.join(file);
+        let text =
+            fs::read_to_string(&path).map_err(|e| FontError::io(&path.to_string_lossy(), e))?;
+        let mut glyph = parse_glif(&text)
+            .map_err(|e| FontError::input(format!("Failed to parse {}: {}", path.display(), e)))?;
+        glyph.name = name;
+        glyphs.push(glyph);
+    }
+
+    // public.glyphOrder first, then the rest alphabetically
+    let order: Vec<String> = match read_plist(&ufo.join("lib.plist"))? {
+        Some(lib) => match lib.get("public.glyphOrder") {
+            Some(Plist::Array(names)) => names
+                .iter()
+                .filter_map(|n| n.as_str().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        },
+        None => Vec::new(),
+    };
+    let rank: HashMap<&str, usize> = order
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (n.as_str(), i))
+        .collect();
+    let mut keyed: Vec<(usize, String, GlifGlyph)> = glyphs
+        .into_iter()
+        .map(|g| {
+            let rank = match g.name.as_str() {
+                ".notdef" => 0,
+                name => rank.get(name).map_or(usize::MAX, |r| r + 1),
+            };
+            (rank, g.name.clone(), g)
+        })
+        .collect();
+    keyed.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+    let mut glyphs: Vec<GlifGlyph> = keyed.into_iter().map(|(_, _, g)| g).collect();
+
+    if glyphs.first().map(|g| g.name.as_str()) != Some(".notdef") {
+        warnings.push("The UFO has no .notdef glyph; an empty one was added".to_string());
+        glyphs.insert(
+            0,
+            GlifGlyph {
+                name: ".notdef".to_string(),
+                advance: 0.0,
+                unicodes: Vec::new(),
+                contours: Vec::new(),
+                components: Vec::new(),
+            },
+        );
+    }
+    Ok(glyphs)
+}
+
+fn font_version(info: &Plist) -> (f64, String) {
+    let major = info
+        .get("versionMajor")
+        .and_then(Plist::as_f64)
+        .unwrap_or(1.0) as i64;
+    let minor = info
+        .get("versionMinor")
+        .and_then(Plist::as_f64)
+        .unwrap_or(0.0) as i64;
+    (
+        major as f64 + minor as f64 / 1000.0,
+        format!("{}.{:03}", major, minor),
+    )
+}
+
+fn name_table(info: &Plist, version: &str, vendor: &str) -> Name {
+    let text = |key: &str| info.get(key).and_then(Plist::as_str).map(str::to_string);
+    let family = text("familyName").unwrap_or_else(|| "Untitled".to_string());
+    let style = text("styleName").unwrap_or_else(|| "Regular".to_string());
+    let style_map_family = text("styleMapFamilyName").unwrap_or_else(|| family.clone());
+    let style_map_style = match text("styleMapStyleName").as_deref() {
+        Some("bold") => "Bold",
+        Some("italic") => "Italic",
+        Some("bold italic") => "Bold Italic",
+        _ => "Regular",
+    };
+    let full_name = format!("{} {}", family, style);
+    let ps_name = text("postscriptFontName").unwrap_or_else(|| {
+        format!("{}-{}", family, style)
+            .chars()
+            .filter(|c| c.is_ascii_graphic() && !"[](){}<>/%".contains(*c))
+            .collect()
+    });
+
+    let mut strings: Vec<(u16, String)> = Vec::new();
+    if let Some(copyright) = text("copyright") {
+        strings.push((0, copyright));
+    }
+    strings.push((1, style_map_family.clone()));
+    strings.push((2, style_map_style.to_string()));
+    strings.push((3, format!("{};{};{}", version, vendor.trim(), ps_name)));
+    strings.push((4, full_name));
+    strings.push((5, format!("Version {}", version)));
+    strings.push((6, ps_name));
+    if let Some(trademark) = text("trademark") {
+        strings.push((7, trademark));
+    }
+    if style_map_family != family || style_map_style != style {
+        strings.push((16, family));
+        strings.push((17, style));
+    }
+
+    Name::new(
+        strings
+            .into_iter()
+            .map(|(id, s)| NameRecord::new(3, 1, 0x409, id.into(), s.into()))
+            .collect(),
+    )
+}
+
+struct CompiledGlyphs {
+    glyf: Vec<u8>,
+    /// Long offsets
+    loca: Vec<u8>,
+    bboxes: Vec<Option<[i16; 4]>>,
+    maxp: Maxp,
+}
+
+/// Encode every glyph into glyf/loca records and collect their bounding
+/// boxes and the maxp totals.
+fn compile_glyphs(set: &GlyphSet, warnings: &mut Vec<String>) -> Result<CompiledGlyphs, FontError> {
+    let mut glyf = Vec::new();
+    let mut loca: Vec<u8> = 0u32.to_be_bytes().to_vec();
+    let mut bboxes: Vec<Option<[i16; 4]>> = Vec::with_capacity(set.glyphs.len());
+    let mut maxp = Maxp {
+        num_glyphs: set.glyphs.len() as u16,
+        max_points: Some(0),
+        max_contours: Some(0),
+        max_composite_points: Some(0),
+        max_composite_contours: Some(0),
+        max_zones: Some(1),
+        max_twilight_points: Some(0),
+        max_storage: Some(0),
+        max_function_defs: Some(0),
+        max_instruction_defs: Some(0),
+        max_stack_elements: Some(0),
+        max_size_of_instructions: Some(0),
+        max_component_elements: Some(0),
+        max_component_depth: Some(0),
+    };
+    let raise = |field: &mut Option<u16>, value: usize| {
+        *field = Some(field.unwrap_or(0).max(value.min(u16::MAX as usize) as u16));
+    };
+    for gid in 0..set.glyphs.len() {
+        let flattened = set.flattened(gid, 0)?;
+        let bbox = bounding_box(&flattened);
+        let point_count: usize = flattened.iter().map(Vec::len).sum();
+        let record = match (set.outline(gid, warnings)?, bbox) {
+            (_, None) => Vec::new(),
+            (GlyphOutline::Composite(components), Some(bbox)) => {
+                raise(&mut maxp.max_composite_points, point_count);
+                raise(&mut maxp.max_composite_contours, flattened.len());
+                raise(&mut maxp.max_component_elements, components.len());
+                raise(
+                    &mut maxp.max_component_depth,
+                    set.component_depth(gid, 0)? as usize,
+                );
+                encode_composite_glyph(&components, bbox)
+            }
+            (GlyphOutline::Simple(contours), Some(bbox)) => {
+                raise(&mut maxp.max_points, point_count);
+                raise(&mut maxp.max_contours, contours.len());
+                let mut end_points = Vec::with_capacity(contours.len());
+                let mut points = Vec::with_capacity(point_count);
+                for contour in &contours {
+                    points.extend(
+                        contour
+                            .iter()
+                            .map(|&(x, y, on)| (x.round() as i32, y.round() as i32, on)),
+                    );
+                    end_points.push((points.len() - 1) as u16);
+                }
+                encode_simple_glyph(&end_points, &points, &[], bbox, false)
+            }
+        };
+        glyf.extend(&record);
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+        loca.extend((glyf.len() as u32).to_be_bytes());
+        bboxes.push(bbox);
+    }
+    Ok(CompiledGlyphs {
+        glyf,
+        loca,
+        bboxes,
+        maxp,
+    })
+}
+
+fn compile_ufo(ufo: &Path, warnings: &mut Vec<String>) -> Result<Vec<u8>, FontError> {
+    let meta = read_plist(&ufo.join("metainfo.plist"))?.ok_or_else(|| {
+        FontError::input(format!(
+            "Not a UFO package: {} has no metainfo.plist",
+            ufo.display()
+        ))
+    })?;
+    let format_version = meta.get("formatVersion").and_then(Plist::as_f64);
+    if !matches!(format_version, Some(v) if (2.0..=3.0).contains(&v)) {
+        return Err(FontError::input(format!(
+            "Unsupported UFO format version: {:?}",
+            format_version
+        )));
+    }
+    let info = read_plist(&ufo.join("fontinfo.plist"))?.unwrap_or(Plist::Dict(Vec::new()));
+    let number = |key: &str| info.get(key).and_then(Plist::as_f64);
+    if ufo.join("features.fea").exists() {
+        warnings.push("Layout features in features.fea were not compiled".to_string());
+    }
+    if ufo.join("kerning.plist").exists() {
+        warnings.push("Kerning was not compiled".to_string());
+    }
+
+    let glyphs = read_glyph_set(ufo, warnings)?;
+    if glyphs.len() > u16::MAX as usize {
+        return Err(FontError::input(format!(
+            "Too many glyphs: {}",
+            glyphs.len()
+        )));
+    }
+    let open_contours = glyphs
+        .iter()
+        .filter(|g| g.contours.iter().any(|c| c[0].kind == PointType::Move))
+        .count();
+    if open_contours > 0 {
+        warnings.push(format!(
+            "{} glyph(s) had open contours, which were closed",
+            open_contours
+        ));
+    }
+    let set = GlyphSet {
+        index: glyphs
+            .iter()
+            .enumerate()
+            .map(|(i, g)| (g.name.clone(), i))
+            .collect(),
+        reverse: glyphs
+            .iter()
+            .flat_map(|g| g.contours.iter().flatten())
+            .any(|p| p.kind == PointType::Curve),
+        glyphs,
+    };
+
+    let CompiledGlyphs {
+        glyf,
+        loca,
+        bboxes,
+        maxp,
+    } = compile_glyphs(&set, warnings)?;
+
+    let upem = number("unitsPerEm").unwrap_or(1000.0);
+    let ascender = number("ascender").unwrap_or(upem * 0.75).round() as i16;
+    let descender = number("descender").unwrap_or(-upem * 0.25).round() as i16;
+    let x_height = number("xHeight").unwrap_or(upem * 0.5).round() as i16;
+    let cap_height = number("capHeight").unwrap_or(upem * 0.7).round() as i16;
+    let italic_angle = number("italicAngle").unwrap_or(0.0);
+    let typo_line_gap = number("openTypeOS2TypoLineGap")
+        .unwrap_or((upem * 1.2 - (ascender as f64 - descender as f64)).max(0.0))
+        .round() as i16;
+    let (revision, version) = font_version(&info);
+    let vendor: String = info
+        .get("openTypeOS2VendorID")
+        .and_then(Plist::as_str)
+        .unwrap_or("NONE")
+        .chars()
+        .filter(char::is_ascii)
+        .chain(std::iter::repeat(' '))
+        .take(4)
+        .collect();
+    let (bold, italic) = match info.get("styleMapStyleName").and_then(Plist::as_str) {
+        Some("bold") => (true, false),
+        Some("italic") => (false, true),
+        Some("bold italic") => (true, true),
+        _ => (false, false),
+    };
+
+    // hmtx and the metrics derived from it
+    let advances: Vec<u16> = set
+        .glyphs
+        .iter()
+        .map(|g| g.advance.round().clamp(0.0, u16::MAX as f64) as u16)
+        .collect();
+    let metrics: Vec<LongMetric> = advances
+        .iter()
+        .zip(&bboxes)
+        .map(|(&advance, bbox)| LongMetric::new(advance, bbox.map_or(0, |b| b[0])))
+        .collect();
+    let inked: Vec<(u16, [i16; 4])> = advances
+        .iter()
+        .zip(&bboxes)
+        .filter_map(|(&a, b)| b.map(|b| (a, b)))
+        .collect();
+    let font_bbox = inked.iter().fold(None, |acc: Option<[i16; 4]>, &(_, b)| {
+        Some(acc.map_or(b, |a| {
+            [
+                a[0].min(b[0]),
+                a[1].min(b[1]),
+                a[2].max(b[2]),
+                a[3].max(b[3]),
+            ]
+        }))
+    });
+    let [x_min, y_min, x_max, y_max] = font_bbox.unwrap_or_default();
+    let min_lsb = inked.iter().map(|(_, b)| b[0]).min().unwrap_or(0);
+    let min_rsb = inked
+        .iter()
+        .map(|&(a, b)| (a as i32 - b[2] as i32) as i16)
+        .min()
+        .unwrap_or(0);
+    let max_extent = inked.iter().map(|(_, b)| b[2]).max().unwrap_or(0);
+    let nonzero: Vec<u32> = advances
+        .iter()
+        .filter(|&&a| a > 0)
+        .map(|&a| a as u32)
+        .collect();
+    let avg_width = if nonzero.is_empty() {
+        0
+    } else {
+        (nonzero.iter().sum::<u32>() as f64 / nonzero.len() as f64).round() as i16
+    };
+
+    // cmap: the first glyph to claim a code point keeps it
+    let mut mappings: Vec<(char, GlyphId)> = Vec::new();
+    let mut claimed: HashMap<u32, &str> = HashMap::new();
+    for (gid, glyph) in set.glyphs.iter().enumerate() {
+        for &code in &glyph.unicodes {
+            let Some(ch) = char::from_u32(code) else {
+                warnings.push(format!(
+                    "Glyph '{}' has invalid unicode {:04X}",
+                    glyph.name, code
+                ));
+                continue;
+            };
+            if let Some(owner) = claimed.get(&code) {
+                warnings.push(format!(
+                    "U+{:04X} is mapped by both '{}' and '{}'; kept '{}'",
+                    code, owner, glyph.name, owner
+                ));
+                continue;
+            }
+            claimed.insert(code, &glyph.name);
+            mappings.push((ch, GlyphId::new(gid as u32)));
+        }
+    }
+    let cmap =
+        Cmap::from_mappings(mappings.iter().copied()).map_err(|e| FontError::build("cmap", e))?;
+    let first_char = claimed.keys().min().map_or(0, |&c| c.min(0xFFFF) as u16);
+    let last_char = claimed.keys().max().map_or(0, |&c| c.min(0xFFFF) as u16);
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    // LongDateTime counts seconds since 1904-01-01
+    let timestamp = LongDateTime::new(now + 2_082_844_800);
+    let mut mac_style = MacStyle::empty();
+    if bold {
+        mac_style |= MacStyle::BOLD;
+    }
+    if italic {
+        mac_style |= MacStyle::ITALIC;
+    }
+    let head = Head {
+        font_revision: Fixed::from_f64(revision),
+        flags: Flags::from_bits_truncate(0x0003),
+        units_per_em: upem.round() as u16,
+        created: timestamp,
+        modified: timestamp,
+        x_min,
+        y_min,
+        x_max,
+        y_max,
+        mac_style,
+        lowest_rec_ppem: number("openTypeHeadLowestRecPPEM").unwrap_or(6.0) as u16,
+        index_to_loc_format: 1,
+        ..Default::default()
+    };
+
+    let hhea = Hhea::new(
+        FWord::new(number("openTypeHheaAscender").map_or(ascender, |v| v.round() as i16)),
+        FWord::new(number("openTypeHheaDescender").map_or(descender, |v| v.round() as i16)),
+        FWord::new(number("openTypeHheaLineGap").map_or(typo_line_gap, |v| v.round() as i16)),
+        UfWord::new(advances.iter().copied().max().unwrap_or(0)),
+        FWord::new(min_lsb),
+        FWord::new(min_rsb),
+        FWord::new(max_extent),
+        if italic_angle == 0.0 {
+            1
+        } else {
+            upem.round() as i16
+        },
+        (-italic_angle.to_radians().tan() * upem).round() as i16,
+        0,
+        set.glyphs.len() as u16,
+    );
+
+    let mut fs_selection = SelectionFlags::empty();
+    if bold {
+        fs_selection |= SelectionFlags::BOLD;
+    }
+    if italic {
+        fs_selection |= SelectionFlags::ITALIC;
+    }
+    if !bold && !italic {
+        fs_selection |= SelectionFlags::REGULAR;
+    }
+    let underline_thickness = number("postscriptUnderlineThickness").unwrap_or(upem * 0.05);
+    let fs_type = match info.get("openTypeOS2Type") {
+        Some(Plist::Array(bits)) => bits
+            .iter()
+            .filter_map(Plist::as_f64)
+            .fold(0u16, |acc, bit| acc | (1 << (bit as u16 & 15))),
+        _ => 0,
+    };
+    let scaled = |ratio: f64| (upem * ratio).round() as i16;
+    let os2 = Os2 {
+        x_avg_char_width: avg_width,
+        us_weight_class: number("openTypeOS2WeightClass").unwrap_or(400.0) as u16,
+        us_width_class: number("openTypeOS2WidthClass").unwrap_or(5.0) as u16,
+        fs_type,
+        y_subscript_x_size: scaled(0.65),
+        y_subscript_y_size: scaled(0.6),
+        y_subscript_y_offset: scaled(0.075),
+        y_superscript_x_size: scaled(0.65),
+        y_superscript_y_size: scaled(0.6),
+        y_superscript_y_offset: scaled(0.35),
+        y_strikeout_size: number("openTypeOS2StrikeoutSize")
+            .unwrap_or(underline_thickness)
+            .round() as i16,
+        y_strikeout_position: number("openTypeOS2StrikeoutPosition")
+            .unwrap_or(x_height as f64 * 0.6)
+            .round() as i16,
+        ach_vend_id: Tag::new(vendor.as_bytes().try_into().unwrap_or(b"NONE")),
+        fs_selection,
+        us_first_char_index: first_char,
+        us_last_char_index: last_char,
+        s_typo_ascender: number("openTypeOS2TypoAscender").map_or(ascender, |v| v.round() as i16),
+        s_typo_descender: number("openTypeOS2TypoDescender")
+            .map_or(descender, |v| v.round() as i16),
+        s_typo_line_gap: typo_line_gap,
+        us_win_ascent: number("openTypeOS2WinAscent")
+            .unwrap_or(y_max.max(ascender) as f64)
+            .max(0.0) as u16,
+        us_win_descent: number("openTypeOS2WinDescent")
+            .unwrap_or(-(y_min.min(descender) as f64))
+            .max(0.0) as u16,
+        ul_code_page_range_1: Some(0),
+        ul_code_page_range_2: Some(0),
+        sx_height: Some(x_height),
+        s_cap_height: Some(cap_height),
+        us_default_char: Some(0),
+        us_break_char: Some(if claimed.contains_key(&0x20) { 0x20 } else { 0 }),
+        us_max_context: Some(0),
+        ..Default::default()
+    };
+
+    let mut post = Post::new_v2(set.glyphs.iter().map(|g| g.name.as_str()));
+    post.italic_angle = Fixed::from_f64(italic_angle);
+    post.underline_position = FWord::new(
+        number("postscriptUnderlinePosition")
+            .unwrap_or(-upem * 0.075)
+            .round() as i16,
+    );
+    post.underline_thickness = FWord::new(underline_thickness.round() as i16);
+    post.is_fixed_pitch =
+        matches!(info.get("postscriptIsFixedPitch"), Some(Plist::Bool(true))) as u32;
+
+    let name = name_table(&info, &version, &vendor);
+    let hmtx = Hmtx::new(metrics, Vec::new());
+
+    let mut builder = FontBuilder::new();
+    builder
+        .add_table(&head)
+        .map_err(|e| FontError::build("head", e))?
+        .add_table(&hhea)
+        .map_err(|e| FontError::build("hhea", e))?
+        .add_table(&maxp)
+        .map_err(|e| FontError::build("maxp", e))?
+        .add_table(&os2)
+        .map_err(|e| FontError::build("OS/2", e))?
+        .add_table(&hmtx)
+        .map_err(|e| FontError::build("hmtx", e))?
+        .add_table(&cmap)
+        .map_err(|e| FontError::build("cmap", e))?
+        .add_table(&name)
+        .map_err(|e| FontError::build("name", e))?
+        .add_table(&post)
+        .map_err(|e| FontError::build("post", e))?
+        .add_raw(Tag::new(b"glyf"), glyf)
+        .add_raw(Tag::new(b"loca"), loca);
+    Ok(builder.build())
+}
+
+/// Compile a UFO 2 or 3 package into a TrueType font at `destination`.
+/// Returns warnings about anything that could not be carried over.
+pub fn import_ufo(source_dir: &str, destination: &str) -> Result<Vec<String>, FontError> {
+    let mut warnings = Vec::new();
+    let bytes = compile_ufo(Path::new(source_dir), &mut warnings)?;
+    write_atomically(Path::new(destination), &bytes)?;
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+    use skrifa::{FontRef, MetadataProvider};
+
+    const PLIST_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \
+        \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n<plist version=\"1.0\">\n";
+
+    fn write_ufo(dir: &Path, glyphs: &[(&str, &str, &str)]) {
+        let glyphs_dir = dir.join("glyphs");
+        fs::create_dir_all(&glyphs_dir).unwrap();
+        let plist = |body: &str| format!("{}{}\n</plist>\n", PLIST_HEADER, body);
+        fs::write(
+            dir.join("metainfo.plist"),
+            plist(
+                "<dict><key>creator</key><string>test</string>\
+                   <key>formatVersion</key><integer>3</integer></dict>",
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("fontinfo.plist"),
+            plist(
+                "<dict><key>familyName</key><string>Icons</string>\
+                   <key>styleName</key><string>Regular</string>\
+                   <key>unitsPerEm</key><integer>1000</integer>\
+                   <key>ascender</key><integer>800</integer>\
+                   <key>descender</key><real>-200.0</real>\
+                   <key>versionMajor</key><integer>2</integer>\
+                   <key>versionMinor</key><integer>5</integer></dict>",
+            ),
+        )
+        .unwrap();
+        let entries: String = glyphs
+            .iter()
+            .map(|(name, file, _)| format!("<key>{}</key><string>{}</string>", name, file))
+            .collect();
+        fs::write(
+            glyphs_dir.join("contents.plist"),
+            plist(&format!("<dict>{}</dict>", entries)),
+        )
+        .unwrap();
+        for (_, file, glif) in glyphs {
+            fs::write(glyphs_dir.join(file), glif).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_import_ufo() {
+        let dir = std::env::temp_dir().join(format!("typebrew-ufo-{}", std::process::id()));
+        let circle = "<?xml version=\"1.0\"?>\n<glyph name=\"circle\" format=\"2\">\n\
+            <advance width=\"600\"/><unicode hex=\"E000\"/>\n<outline><contour>\n\
+            <point x=\"300\" y=\"0\" type=\"curve\" smooth=\"yes\"/>\
+            <point x=\"466\" y=\"0\"/><point x=\"600\" y=\"134\"/>\
+            <point x=\"600\" y=\"300\" type=\"curve\" smooth=\"yes\"/>\
+            <point x=\"600\" y=\"466\"/><point x=\"466\" y=\"600\"/>\
+            <point x=\"300\" y=\"600\" type=\"curve\" smooth=\"yes\"/>\
+            <point x=\"134\" y=\"600\"/><point x=\"0\" y=\"466\"/>\
+            <point x=\"0\" y=\"300\" type=\"curve\" smooth=\"yes\"/>\
+            <point x=\"0\" y=\"134\"/><point x=\"134\" y=\"0\"/>\
+            </contour></outline>\n</glyph>\n";
+        let square = "<glyph name=\"square\" format=\"2\"><advance width=\"500\"/>\
+            <unicode hex=\"E001\"/><outline><contour>\
+            <point x=\"0\" y=\"0\" type=\"line\"/><point x=\"400\" y=\"0\" type=\"line\"/>\
+            <point x=\"400\" y=\"400\" type=\"line\"/><point x=\"0\" y=\"400\" type=\"line\"/>\
+            </contour></outline></glyph>";
+        let shifted = "<glyph name=\"shifted\" format=\"2\"><advance width=\"500\"/>\
+            <unicode hex=\"E002\"/><outline>\
+            <component base=\"square\" xOffset=\"50\" yOffset=\"-10\"/>\
+            </outline></glyph>";
+        write_ufo(
+            &dir,
+            &[
+                ("circle", "circle.glif", circle),
+                ("square", "square.glif", square),
+                ("shifted", "shifted.glif", shifted),
+            ],
+        );
+        let destination = dir.join("out.ttf");
+
+        let warnings = import_ufo(dir.to_str().unwrap(), destination.to_str().unwrap()).unwrap();
+        assert_eq!(
+            warnings,
+            vec!["The UFO has no .notdef glyph; an empty one was added".to_string()]
+        );
+
+        let bytes = fs::read(&destination).unwrap();
+        let font = FontRef::new(&bytes).unwrap();
+        let raw = RawFontRef::new(&bytes).unwrap();
+        assert_eq!(raw.maxp().unwrap().num_glyphs(), 4);
+        assert_eq!(raw.head().unwrap().units_per_em(), 1000);
+        assert!((raw.head().unwrap().font_revision().to_f64() - 2.005).abs() < 0.001);
+        assert_eq!(raw.hhea().unwrap().ascender().to_i16(), 800);
+        let charmap = font.charmap();
+        let circle_gid = charmap.map(0xE000u32).unwrap();
+        let shifted_gid = charmap.map(0xE002u32).unwrap();
+        assert_eq!(
+            font.glyph_names().get(circle_gid).unwrap().as_str(),
+            "circle"
+        );
+        let family = font
+            .localized_strings(skrifa::string::StringId::FAMILY_NAME)
+            .english_or_first()
+            .unwrap()
+            .to_string();
+        assert_eq!(family, "Icons");
+        let version = font
+            .localized_strings(skrifa::string::StringId::VERSION_STRING)
+            .english_or_first()
+            .unwrap()
+            .to_string();
+        assert_eq!(version, "Version 2.005");
+
+        let metrics = font.glyph_metrics(
+            skrifa::instance::Size::unscaled(),
+            skrifa::instance::LocationRef::default(),
+        );
+        assert_eq!(metrics.advance_width(circle_gid), Some(600.0));
+        let circle_box = metrics.bounds(circle_gid).unwrap();
+        assert_eq!((circle_box.x_min, circle_box.y_max), (0.0, 600.0));
+        let shifted_box = metrics.bounds(shifted_gid).unwrap();
+        assert_eq!((shifted_box.x_min, shifted_box.y_min), (50.0, -10.0));
+
+        // The component-only glyph stays a composite
+        let glyf = raw.table_data(Tag::new(b"glyf")).unwrap();
+        let loca = raw.loca(None).unwrap();
+        let start = loca.get_raw(shifted_gid.to_u32() as usize).unwrap() as usize;
+        let contours = i16::from_be_bytes([glyf.as_bytes()[start], glyf.as_bytes()[start + 1]]);
+        assert_eq!(contours, -1);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_cubic_to_quadratics_stays_within_tolerance() {
+        let cubic = [(0.0, 0.0), (0.0, 552.0), (448.0, 1000.0), (1000.0, 1000.0)];
+        let quads = cubic_to_quadratics(cubic);
+        assert!(quads.len() > 2);
+        assert_eq!(quads.last(), Some(&(1000.0, 1000.0, true)));
+
+        let mut start = cubic[0];
+        for pair in quads.chunks(2) {
+            let ((cx, cy, _), (ex, ey, _)) = (pair[0], pair[1]);
+            for i in 0..=10 {
+                let t = i as f64 / 10.0;
+                let mt = 1.0 - t;
+                let x = mt * mt * start.0 + 2.0 * mt * t * cx + t * t * ex;
+                let y = mt * mt * start.1 + 2.0 * mt * t * cy + t * t * ey;
+                let nearest = (0..=1000)
+                    .map(|j| cubic_at(cubic, j as f64 / 1000.0))
+                    .map(|(px, py)| (px - x).hypot(py - y))
+                    .fold(f64::MAX, f64::min);
+                assert!(nearest < CURVE_TOLERANCE + 1.0, "off by {}", nearest);
+            }
+            start = (ex, ey);
+        }
+    }
+}
@@ -0,0 +1,629 @@
+use crate::error::FontError;
+use crate::font_parser::{
+    get_font_bytes, parse_composite_components, parse_loca_offsets, Backing, FontCache,
+};
+use serde::Serialize;
+use skrifa::instance::{LocationRef, Size};
+use skrifa::raw::types::Tag;
+use skrifa::raw::{FontRef as RawFontRef, TableProvider};
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The font is broken or will be rejected by some consumers.
+    Error,
+    /// The font works but a value is stale or inconsistent.
+    Warning,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ValidationFinding {
+    pub severity: Severity,
+    pub table: String,
+    pub message: String,
+    /// The offending values, e.g. `{"glyph_id": 12, "x_max": 1210}`.
+    pub values: BTreeMap<String, i64>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub findings: Vec<ValidationFinding>,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl ValidationReport {
+    pub(crate) fn push(
+        &mut self,
+        severity: Severity,
+        table: &str,
+        message: &str,
+        values: &[(&str, i64)],
+    ) {
+        match severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+        }
+        self.findings.push(ValidationFinding {
+            severity,
+            table: table.to_string(),
+            message: message.to_string(),
+            values: values.iter().map(|&(k, v)| (k.to_string(), v)).collect(),
+        });
+    }
+}
+
+/// Name IDs every font should carry: family, subfamily, unique ID, full
+/// name and PostScript name.
+const REQUIRED_NAME_IDS: [u16; 5] = [1, 2, 3, 4, 6];
+
+/// Sum of a table's big-endian u32 words, zero-padded to a multiple of four.
+pub(crate) fn table_checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ChecksumEntry {
+    pub tag: String,
+    pub stored: u32,
+    pub computed: u32,
+    pub ok: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ChecksumReport {
+    pub tables: Vec<ChecksumEntry>,
+    /// head.checkSumAdjustment against the whole file; absent without head.
+    pub checksum_adjustment: Option<ChecksumEntry>,
+    /// Tables whose directory entry points past the end of the file.
+    pub truncated: Vec<String>,
+}
+
+impl ChecksumEntry {
+    fn new(tag: String, stored: u32, computed: u32) -> Self {
+        Self {
+            tag,
+            stored,
+            computed,
+            ok: stored == computed,
+        }
+    }
+}
+
+/// Recompute every table checksum (head with its checkSumAdjustment zeroed)
+/// and the checkSumAdjustment itself from `bytes`.
+fn checksum_report(bytes: &[u8]) -> Result<ChecksumReport, FontError> {
+    let font = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let mut report = ChecksumReport::default();
+    let mut head_adjustment = None;
+    for record in font.table_directory.table_records() {
+        let tag = record.tag();
+        let start = record.offset() as usize;
+        let Some(data) = bytes.get(start..start + record.length() as usize) else {
+            report.truncated.push(tag.to_string());
+            continue;
+        };
+        let mut data = data.to_vec();
+        if tag == Tag::new(b"head") && data.len() >= 12 {
+            let stored = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+            head_adjustment = Some((start + 8, stored));
+            data[8..12].fill(0);
+        }
+        report.tables.push(ChecksumEntry::new(
+            tag.to_string(),
+            record.checksum(),
+            table_checksum(&data),
+        ));
+    }
+    if let Some((at, stored)) = head_adjustment {
+        let mut whole = bytes.to_vec();
+        whole[at..at + 4].fill(0);
+        let computed = 0xB1B0_AFBAu32.wrapping_sub(table_checksum(&whole));
+        report.checksum_adjustment = Some(ChecksumEntry::new(
+            "head.checkSumAdjustment".to_string(),
+            stored,
+            computed,
+        ));
+    }
+    Ok(report)
+}
+
+fn check_checksums(bytes: &[u8], report: &mut ValidationReport) -> Result<(), FontError> {
+    let checksums = checksum_report(bytes)?;
+    for tag in &checksums.truncated {
+        report.push(
+            Severity::Error,
+            tag,
+            "Table extends past the end of the file",
+            &[("file_length", bytes.len() as i64)],
+        );
+    }
+    for entry in checksums.tables.iter().filter(|e| !e.ok) {
+        report.push(
+            Severity::Error,
+            &entry.tag,
+            "Table checksum does not match the table directory",
+            &[
+                ("stored", entry.stored as i64),
+                ("computed", entry.computed as i64),
+            ],
+        );
+    }
+    if let Some(entry) = checksums.checksum_adjustment.filter(|e| !e.ok) {
+        report.push(
+            Severity::Error,
+            "head",
+            "checkSumAdjustment does not match the file",
+            &[
+                ("stored", entry.stored as i64),
+                ("computed", entry.computed as i64),
+            ],
+        );
+    }
+    Ok(())
+}
+
+/// Check glyf/loca structure, glyph bounds against head, and composite
+/// references. Returns the union of all glyph bounding boxes.
+fn check_glyf(
+    font: &RawFontRef<'_>,
+    num_glyphs: usize,
+    report: &mut ValidationReport,
+) -> Result<Option<[i16; 4]>, FontError> {
+    let (Some(glyf), Some(loca)) = (
+        font.table_data(Tag::new(b"glyf")),
+        font.table_data(Tag::new(b"loca")),
+    ) else {
+        return Ok(None);
+    };
+    let head = font.head().map_err(|e| FontError::table("head", e))?;
+    let is_long = head.index_to_loc_format() != 0;
+    let entry_size = if is_long { 4 } else { 2 };
+    let entries = loca.len() / entry_size;
+    if entries != num_glyphs + 1 {
+        report.push(
+            Severity::Error,
+            "loca",
+            "loca length does not match maxp numGlyphs + 1",
+            &[
+                ("loca_entries", entries as i64),
+                ("num_glyphs", num_glyphs as i64),
+            ],
+        );
+    }
+    let offsets = parse_loca_offsets(loca.as_bytes(), entries.min(num_glyphs + 1), is_long);
+    for (gid, w) in offsets.windows(2).enumerate() {
+        if w[1] < w[0] {
+            report.push(
+                Severity::Error,
+                "loca",
+                "loca offsets decrease",
+                &[
+                    ("glyph_id", gid as i64),
+                    ("offset", w[0] as i64),
+                    ("next_offset", w[1] as i64),
+                ],
+            );
+        }
+    }
+    let glyf = glyf.as_bytes();
+    if let Some(&last) = offsets.last() {
+        let last = last as usize;
+        if last > glyf.len() {
+            report.push(
+                Severity::Error,
+                "loca",
+                "Final loca offset points past the end of glyf",
+                &[
+                    ("final_offset", last as i64),
+                    ("glyf_length", glyf.len() as i64),
+                ],
+            );
+        } else if glyf.len() - last > 3 {
+            report.push(
+                Severity::Warning,
+                "glyf",
+                "glyf has unreferenced bytes after the final loca offset",
+                &[
+                    ("final_offset", last as i64),
+                    ("glyf_length", glyf.len() as i64),
+                ],
+            );
+        }
+    }
+
+    let head_bbox = [head.x_min(), head.y_min(), head.x_max(), head.y_max()];
+    let mut extents: Option<[i16; 4]> = None;
+    for (gid, w) in offsets.windows(2).enumerate() {
+        let (start, end) = (w[0] as usize, w[1] as usize);
+        if start + 10 > end || end > glyf.len() {
+            continue;
+        }
+        let record = &glyf[start..end];
+        let [num_contours, x_min, y_min, x_max, y_max] =
+            [0, 1, 2, 3, 4].map(|i| i16::from_be_bytes([record[i * 2], record[i * 2 + 1]]));
+        if x_min < head_bbox[0]
+            || y_min < head_bbox[1]
+            || x_max > head_bbox[2]
+            || y_max > head_bbox[3]
+        {
+            report.push(
+                Severity::Warning,
+                "head",
+                "Glyph bounding box lies outside the head bounding box",
+                &[
+                    ("glyph_id", gid as i64),
+                    ("x_min", x_min as i64),
+                    ("y_min", y_min as i64),
+                    ("x_max", x_max as i64),
+                    ("y_max", y_max as i64),
+                    ("head_x_min", head_bbox[0] as i64),
+                    ("head_y_min", head_bbox[1] as i64),
+                    ("head_x_max", head_bbox[2] as i64),
+                    ("head_y_max", head_bbox[3] as i64),
+                ],
+            );
+        }
+        extents = Some(match extents {
+            None => [x_min, y_min, x_max, y_max],
+            Some(e) => [
+                e[0].min(x_min),
+                e[1].min(y_min),
+                e[2].max(x_max),
+                e[3].max(y_max),
+            ],
+        });
+        if num_contours < 0 {
+            for component in parse_composite_components(&record[10..]) {
+                if component.glyph_id as usize >= num_glyphs {
+                    report.push(
+                        Severity::Error,
+                        "glyf",
+                        "Composite references a glyph that does not exist",
+                        &[
+                            ("glyph_id", gid as i64),
+                            ("component_glyph_id", component.glyph_id as i64),
+                            ("num_glyphs", num_glyphs as i64),
+                        ],
+                    );
+                }
+            }
+        }
+    }
+    Ok(extents)
+}
+
+fn check_hmtx(
+    font: &FontRef<'_>,
+    raw: &RawFontRef<'_>,
+    num_glyphs: usize,
+    report: &mut ValidationReport,
+) -> Result<(), FontError> {
+    let hhea = raw.hhea().map_err(|e| FontError::table("hhea", e))?;
+    let long_count = hhea.number_of_h_metrics() as usize;
+    if long_count == 0 || long_count > num_glyphs {
+        report.push(
+            Severity::Error,
+            "hhea",
+            "numberOfHMetrics is outside 1..=numGlyphs",
+            &[
+                ("number_of_h_metrics", long_count as i64),
+                ("num_glyphs", num_glyphs as i64),
+            ],
+        );
+        return Ok(());
+    }
+    let hmtx = raw.hmtx().map_err(|e| FontError::table("hmtx", e))?;
+    let max_advance = (0..num_glyphs as u32)
+        .filter_map(|gid| hmtx.advance(GlyphId::new(gid)))
+        .max()
+        .unwrap_or(0);
+    if max_advance != hhea.advance_width_max().to_u16() {
+        report.push(
+            Severity::Warning,
+            "hhea",
+            "advanceWidthMax does not match the largest hmtx advance",
+            &[
+                (
+                    "advance_width_max",
+                    hhea.advance_width_max().to_u16() as i64,
+                ),
+                ("computed", max_advance as i64),
+            ],
+        );
+    }
+    // The side bearing aggregates only count glyphs that have contours
+    let bounds = font.glyph_metrics(Size::unscaled(), LocationRef::default());
+    let inked: Vec<(i32, i32, i32)> = (0..num_glyphs as u32)
+        .filter_map(|gid| {
+            let gid = GlyphId::new(gid);
+            let b = bounds
+                .bounds(gid)
+                .filter(|b| b.x_min != b.x_max || b.y_min != b.y_max)?;
+            let lsb = hmtx.side_bearing(gid)? as i32;
+            let extent = lsb + (b.x_max - b.x_min).round() as i32;
+            Some((lsb, hmtx.advance(gid)? as i32 - extent, extent))
+        })
+        .collect();
+    let aggregates = [
+        (
+            "min_left_side_bearing",
+            "minLeftSideBearing does not match the smallest hmtx left side bearing",
+            hhea.min_left_side_bearing().to_i16(),
+            inked.iter().map(|g| g.0).min(),
+        ),
+        (
+            "min_right_side_bearing",
+            "minRightSideBearing does not match the smallest right side bearing",
+            hhea.min_right_side_bearing().to_i16(),
+            inked.iter().map(|g| g.1).min(),
+        ),
+        (
+            "x_max_extent",
+            "xMaxExtent does not match the largest lsb + glyph width",
+            hhea.x_max_extent().to_i16(),
+            inked.iter().map(|g| g.2).max(),
+        ),
+    ];
+    for (field, message, stored, computed) in aggregates {
+        if let Some(computed) = computed.filter(|&c| c != stored as i32) {
+            report.push(
+                Severity::Warning,
+                "hhea",
+                message,
+                &[(field, stored as i64), ("computed", computed as i64)],
+            );
+        }
+    }
+    Ok(())
+}
+
+fn check_cmap(font: &RawFontRef<'_>, num_glyphs: usize, report: &mut ValidationReport) {
+    use skrifa::raw::tables::cmap::CmapSubtable;
+    let Ok(cmap) = font.cmap() else {
+        report.push(Severity::Error, "cmap", "Font has no cmap table", &[]);
+        return;
+    };
+    // Each mapping is reported once even when several subtables carry it
+    let mut bad = BTreeSet::new();
+    for record in cmap.encoding_records() {
+        match record.subtable(cmap.offset_data()) {
+            Ok(CmapSubtable::Format4(subtable)) => {
+                bad.extend(
+                    subtable
+                        .iter()
+                        .filter(|(_, gid)| gid.to_u32() as usize >= num_glyphs)
+                        .map(|(cp, gid)| (cp, cp, gid.to_u32())),
+                );
+            }
+            // Groups are checked whole; a malformed group can span millions of codepoints
+            Ok(CmapSubtable::Format12(subtable)) => {
+                for group in subtable.groups() {
+                    let (start, end) = (group.start_char_code(), group.end_char_code());
+                    let last_gid = group.start_glyph_id() as u64 + end.saturating_sub(start) as u64;
+                    if last_gid >= num_glyphs as u64 {
+                        bad.insert((start, end, group.start_glyph_id()));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for (start, end, gid) in bad {
+        report.push(
+            Severity::Error,
+            "cmap",
+            "Codepoint maps to a glyph that does not exist",
+            &[
+                ("codepoint", start as i64),
+                ("last_codepoint", end as i64),
+                ("glyph_id", gid as i64),
+                ("num_glyphs", num_glyphs as i64),
+            ],
+        );
+    }
+}
+
+fn check_name(font: &RawFontRef<'_>, report: &mut ValidationReport) {
+    let Ok(name) = font.name() else {
+        report.push(Severity::Error, "name", "Font has no name table", &[]);
+        return;
+    };
+    let present: BTreeSet<u16> = name
+        .name_record()
+        .iter()
+        .map(|r| r.name_id().to_u16())
+        .collect();
+    for id in REQUIRED_NAME_IDS {
+        if !present.contains(&id) {
+            report.push(
+                Severity::Error,
+                "name",
+                "Required name ID is missing",
+                &[("name_id", id as i64)],
+            );
+        }
+    }
+}
+
+fn check_os2(font: &RawFontRef<'_>, extents: Option<[i16; 4]>, report: &mut ValidationReport) {
+    let Ok(os2) = font.os2() else {
+        return;
+    };
+    let [_, y_min, _, y_max] = match (extents, font.head()) {
+        (Some(extents), _) => extents,
+        (None, Ok(head)) => [head.x_min(), head.y_min(), head.x_max(), head.y_max()],
+        (None, Err(_)) => return,
+    };
+    if (os2.us_win_ascent() as i32) < y_max as i32 {
+        report.push(
+            Severity::Warning,
+            "OS/2",
+            "usWinAscent is below the tallest glyph; Windows clips it",
+            &[
+                ("us_win_ascent", os2.us_win_ascent() as i64),
+                ("y_max", y_max as i64),
+            ],
+        );
+    }
+    if (os2.us_win_descent() as i32) < -(y_min as i32) {
+        report.push(
+            Severity::Warning,
+            "OS/2",
+            "usWinDescent is above the lowest glyph; Windows clips it",
+            &[
+                ("us_win_descent", os2.us_win_descent() as i64),
+                ("y_min", y_min as i64),
+            ],
+        );
+    }
+}
+
+fn validate_font_bytes(bytes: &[u8]) -> Result<ValidationReport, FontError> {
+    let raw = RawFontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let font = FontRef::new(bytes).map_err(FontError::invalid_font)?;
+    let num_glyphs = raw
+        .maxp()
+        .map_err(|e| FontError::table("maxp", e))?
+        .num_glyphs() as usize;
+
+    let mut report = ValidationReport::default();
+    let extents = check_glyf(&raw, num_glyphs, &mut report)?;
+    check_hmtx(&font, &raw, num_glyphs, &mut report)?;
+    check_cmap(&raw, num_glyphs, &mut report);
+    check_name(&raw, &mut report);
+    check_os2(&raw, extents, &mut report);
+    check_checksums(bytes, &mut report)?;
+    Ok(report)
+}
+
+/// Lint a font for the structural problems an edit can leave behind:
+/// loca/glyf/maxp agreement, stale head/hhea/OS/2 summary values, dangling
+/// glyph references, missing names and checksums.
+pub fn validate_font(file_path: &str, cache: &FontCache) -> Result<ValidationReport, FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    validate_font_bytes(&bytes)
+}
+
+/// Checksums of the font file as it is on disk, ignoring unsaved edits,
+/// so fonts written by other tools can be checked too. A font opened from
+/// bytes has no file, so its cached bytes are checked.
+pub fn verify_checksums(file_path: &str, cache: &FontCache) -> Result<ChecksumReport, FontError> {
+    if cache.backing(file_path) == Backing::Memory {
+        return checksum_report(&get_font_bytes(file_path, cache)?);
+    }
+    let bytes = fs::read(file_path).map_err(|e| FontError::io(file_path, e))?;
+    checksum_report(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{composite_glyph, simple_glyph, TestComponent, TestFontBuilder};
+
+    fn messages(report: &ValidationReport) -> Vec<&str> {
+        report.findings.iter().map(|f| f.message.as_str()).collect()
+    }
+
+    #[test]
+    fn test_validate_font() {
+        let bar = simple_glyph(&[&[(-20, 0, true), (-20, 800, true), (100, 800, true)]]);
+        let font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(bar, 600)
+            .glyph(
+                composite_glyph(&[TestComponent {
+                    glyph_id: 7,
+                    dx: 0,
+                    dy: 0,
+                    transform: None,
+                }]),
+                500,
+            )
+            .map('A' as u32, 1)
+            .map('B' as u32, 9)
+            .build();
+
+        let report = validate_font_bytes(&font).unwrap();
+        let found = messages(&report);
+        // The test font is freshly assembled: checksums and loca are sound
+        assert!(!found.iter().any(|m| m.contains("checksum")));
+        assert!(!found.iter().any(|m| m.contains("loca")));
+        assert!(found.contains(&"Composite references a glyph that does not exist"));
+        assert!(found.contains(&"Codepoint maps to a glyph that does not exist"));
+        assert!(found.contains(&"Glyph bounding box lies outside the head bounding box"));
+        assert!(found.contains(&"Font has no name table"));
+
+        let cmap = report.findings.iter().find(|f| f.table == "cmap").unwrap();
+        assert_eq!(cmap.severity, Severity::Error);
+        assert_eq!(cmap.values["codepoint"], 'B' as i64);
+        assert_eq!(cmap.values["glyph_id"], 9);
+    }
+
+    #[test]
+    fn test_validate_font_checksums() {
+        let mut font = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(
+                simple_glyph(&[&[(0, 0, true), (0, 500, true), (500, 500, true)]]),
+                600,
+            )
+            .build();
+        let hmtx = RawFontRef::new(&font)
+            .unwrap()
+            .table_directory
+            .table_records()
+            .iter()
+            .find(|r| r.tag() == Tag::new(b"hmtx"))
+            .unwrap()
+            .offset() as usize;
+        font[hmtx + 4] ^= 0x01;
+
+        let report = validate_font_bytes(&font).unwrap();
+        let tables: Vec<&str> = report
+            .findings
+            .iter()
+            .filter(|f| f.message.contains("checksum") || f.message.contains("checkSum"))
+            .map(|f| f.table.as_str())
+            .collect();
+        assert_eq!(tables, vec!["hmtx", "head"]);
+    }
+
+    #[test]
+    fn test_checksum_report() {
+        let mut font = TestFontBuilder::new().glyph(Vec::new(), 500).build();
+        let report = checksum_report(&font).unwrap();
+        assert!(report.tables.iter().all(|e| e.ok));
+        assert!(report.checksum_adjustment.as_ref().unwrap().ok);
+
+        let maxp = RawFontRef::new(&font)
+            .unwrap()
+            .table_directory
+            .table_records()
+            .iter()
+            .find(|r| r.tag() == Tag::new(b"maxp"))
+            .unwrap()
+            .offset() as usize;
+        font[maxp + 4] ^= 0x01;
+        let report = checksum_report(&font).unwrap();
+        let stale: Vec<&str> = report
+            .tables
+            .iter()
+            .filter(|e| !e.ok)
+            .map(|e| e.tag.as_str())
+            .collect();
+        assert_eq!(stale, vec!["maxp"]);
+        let adjustment = report.checksum_adjustment.unwrap();
+        assert_eq!(
+            adjustment.computed,
+            adjustment.stored.wrapping_sub(0x0100_0000)
+        );
+    }
+}
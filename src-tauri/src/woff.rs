@@ -0,0 +1,977 @@
+use crate::error::FontError;
+use crate::font_parser::{get_font_bytes, write_atomically, FontCache};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use skrifa::raw::FontRef as RawFontRef;
+use std::io::{Read, Write};
+use write_fonts::types::Tag;
+use write_fonts::FontBuilder;
+
+const WOFF_HEADER_LEN: usize = 44;
+const WOFF_DIR_ENTRY_LEN: usize = 20;
+const WOFF2_HEADER_LEN: usize = 48;
+
+/// Tags with a 6-bit index in the WOFF2 table directory (spec section 4.1).
+const WOFF2_KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+fn pad4(len: usize) -> usize {
+    len.next_multiple_of(4)
+}
+
+/// One table of an SFNT: (tag, checksum, data).
+type SfntTable<'a> = ([u8; 4], u32, &'a [u8]);
+
+/// Read the SFNT flavor and tables, sorted by tag as both WOFF formats require.
+fn sfnt_tables(sfnt: &[u8]) -> Result<(u32, Vec<SfntTable<'_>>), FontError> {
+    let font = RawFontRef::new(sfnt).map_err(FontError::invalid_font)?;
+    let mut tables = Vec::new();
+    for record in font.table_directory.table_records() {
+        let start = record.offset() as usize;
+        let end = start + record.length() as usize;
+        let data = sfnt.get(start..end).ok_or_else(|| {
+            FontError::malformed(
+                &record.tag().to_string(),
+                "extends past the end of the font",
+            )
+        })?;
+        tables.push((record.tag().to_be_bytes(), record.checksum(), data));
+    }
+    tables.sort_by_key(|t| t.0);
+    Ok((font.table_directory.sfnt_version(), tables))
+}
+
+/// Wrap SFNT bytes in a WOFF 1.0 container. Each table is zlib-compressed
+/// unless that does not make it smaller, in which case it is stored as is.
+pub fn sfnt_to_woff(sfnt: &[u8]) -> Result<Vec<u8>, FontError> {
+    let (flavor, tables) = sfnt_tables(sfnt)?;
+    let num_tables = tables.len();
+
+    let mut directory = Vec::with_capacity(num_tables * WOFF_DIR_ENTRY_LEN);
+    let mut data = Vec::new();
+    let data_start = WOFF_HEADER_LEN + num_tables * WOFF_DIR_ENTRY_LEN;
+    let mut total_sfnt_size = 12 + 16 * num_tables;
+
+    for (tag, checksum, table) in &tables {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder
+            .write_all(table)
+            .map_err(|e| FontError::build("WOFF", e))?;
+        let compressed = encoder.finish().map_err(|e| FontError::build("WOFF", e))?;
+        let stored: &[u8] = if compressed.len() < table.len() {
+            &compressed
+        } else {
+            table
+        };
+
+        directory.extend_from_slice(tag);
+        directory.extend(((data_start + data.len()) as u32).to_be_bytes());
+        directory.extend((stored.len() as u32).to_be_bytes());
+        directory.extend((table.len() as u32).to_be_bytes());
+        directory.extend(checksum.to_be_bytes());
+
+        data.extend_from_slice(stored);
+        data.resize(pad4(data.len()), 0);
+        total_sfnt_size += pad4(table.len());
+    }
+
+    let mut woff = Vec::with_capacity(data_start + data.len());
+    woff.extend(b"wOFF");
+    woff.extend(flavor.to_be_bytes());
+    woff.extend(((data_start + data.len()) as u32).to_be_bytes());
+    woff.extend((num_tables as u16).to_be_bytes());
+    woff.extend(0u16.to_be_bytes()); // reserved
+    woff.extend((total_sfnt_size as u32).to_be_bytes());
+    woff.extend(1u16.to_be_bytes()); // majorVersion
+    woff.extend(0u16.to_be_bytes()); // minorVersion
+    woff.extend([0u8; 20]); // no metadata or private block
+    woff.extend(directory);
+    woff.extend(data);
+    Ok(woff)
+}
+
+/// Append `value` as a WOFF2 UIntBase128 (big-endian, 7 bits per byte).
+fn push_base128(buf: &mut Vec<u8>, value: u32) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        bytes.push((rest & 0x7F) as u8 | 0x80);
+        rest >>= 7;
+    }
+    buf.extend(bytes.iter().rev());
+}
+
+/// Wrap SFNT bytes in a WOFF2 container using the null transform for every
+/// table (glyf/loca included), with all table data in one Brotli stream.
+pub fn sfnt_to_woff2(sfnt: &[u8]) -> Result<Vec<u8>, FontError> {
+    use brotli::enc::backward_references::BrotliEncoderMode;
+    use brotli::enc::BrotliEncoderParams;
+
+    const NULL_TRANSFORM_GLYF_LOCA: u8 = 3 << 6;
+
+    let (flavor, tables) = sfnt_tables(sfnt)?;
+    let num_tables = tables.len();
+
+    let mut directory = Vec::new();
+    let mut stream = Vec::new();
+    let mut total_sfnt_size = 12 + 16 * num_tables;
+    for (tag, _, table) in &tables {
+        let known = WOFF2_KNOWN_TAGS.iter().position(|t| *t == tag);
+        let mut flags = known.unwrap_or(63) as u8;
+        // For glyf/loca, transform version 3 (not 0) is the null transform
+        if tag == b"glyf" || tag == b"loca" {
+            flags |= NULL_TRANSFORM_GLYF_LOCA;
+        }
+        directory.push(flags);
+        if known.is_none() {
+            directory.extend_from_slice(tag);
+        }
+        push_base128(&mut directory, table.len() as u32);
+        stream.extend_from_slice(table);
+        total_sfnt_size += pad4(table.len());
+    }
+
+    let params = BrotliEncoderParams {
+        quality: 11,
+        lgwin: 22,
+        mode: BrotliEncoderMode::BROTLI_MODE_FONT,
+        size_hint: stream.len(),
+        ..Default::default()
+    };
+    let mut compressed = Vec::new();
+    brotli::BrotliCompress(&mut stream.as_slice(), &mut compressed, &params)
+        .map_err(|e| FontError::build("WOFF2", e))?;
+
+    let length = pad4(WOFF2_HEADER_LEN + directory.len() + compressed.len());
+    let mut woff2 = Vec::with_capacity(length);
+    woff2.extend(b"wOF2");
+    woff2.extend(flavor.to_be_bytes());
+    woff2.extend((length as u32).to_be_bytes());
+    woff2.extend((num_tables as u16).to_be_bytes());
+    woff2.extend(0u16.to_be_bytes()); // reserved
+    woff2.extend((total_sfnt_size as u32).to_be_bytes());
+    woff2.extend((compressed.len() as u32).to_be_bytes());
+    woff2.extend(1u16.to_be_bytes()); // majorVersion
+    woff2.extend(0u16.to_be_bytes()); // minorVersion
+    woff2.extend([0u8; 20]); // no metadata or private block
+    woff2.extend(directory);
+    woff2.extend(compressed);
+    woff2.resize(length, 0);
+    Ok(woff2)
+}
+
+pub fn export_woff(file_path: &str, destination: &str, cache: &FontCache) -> Result<(), FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let woff = sfnt_to_woff(&bytes)?;
+    write_atomically(std::path::Path::new(destination), &woff)?;
+    Ok(())
+}
+
+pub fn export_woff2(
+    file_path: &str,
+    destination: &str,
+    cache: &FontCache,
+) -> Result<(), FontError> {
+    let bytes = get_font_bytes(file_path, cache)?;
+    let woff2 = sfnt_to_woff2(&bytes)?;
+    write_atomically(std::path::Path::new(destination), &woff2)?;
+    Ok(())
+}
+
+// ── Decoding ──────────────────────────────────────────────────────────────────
+
+/// Container a font file was stored in on disk.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerFormat {
+    #[default]
+    Sfnt,
+    Woff,
+    Woff2,
+}
+
+/// Bounds-checked big-endian reader over a byte slice.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], FontError> {
+        let out = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| FontError::invalid_data("Unexpected end of WOFF data"))?;
+        self.pos += len;
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, FontError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, FontError> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, FontError> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, FontError> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn base128(&mut self) -> Result<u32, FontError> {
+        let mut value = 0u32;
+        for i in 0..5 {
+            let b = self.u8()?;
+            if i == 0 && b == 0x80 {
+                return Err(FontError::invalid_data(
+                    "Invalid UIntBase128 (leading zero)",
+                ));
+            }
+            if value & 0xFE00_0000 != 0 {
+                return Err(FontError::invalid_data("UIntBase128 overflow"));
+            }
+            value = (value << 7) | (b & 0x7F) as u32;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(FontError::invalid_data(
+            "UIntBase128 is longer than 5 bytes",
+        ))
+    }
+
+    fn uint255(&mut self) -> Result<u16, FontError> {
+        Ok(match self.u8()? {
+            253 => self.u16()?,
+            254 => 506 + self.u8()? as u16,
+            255 => 253 + self.u8()? as u16,
+            code => code as u16,
+        })
+    }
+}
+
+/// Assemble tables into an SFNT (directory, checksums and padding via FontBuilder).
+fn build_sfnt(tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    let mut builder = FontBuilder::new();
+    for (tag, data) in tables {
+        builder.add_raw(Tag::new(&tag), data);
+    }
+    builder.build()
+}
+
+/// Unpack a WOFF 1.0 file into SFNT bytes. Metadata and private blocks are dropped.
+pub fn woff_to_sfnt(woff: &[u8]) -> Result<Vec<u8>, FontError> {
+    let mut header = Reader::new(woff);
+    if header.bytes(4)? != b"wOFF" {
+        return Err(FontError::invalid_data("Not a WOFF file"));
+    }
+    header.pos = 12;
+    let num_tables = header.u16()? as usize;
+    header.pos = WOFF_HEADER_LEN;
+
+    let mut tables = Vec::with_capacity(num_tables);
+    for _ in 0..num_tables {
+        let tag: [u8; 4] = header.bytes(4)?.try_into().unwrap();
+        let offset = header.u32()? as usize;
+        let comp_length = header.u32()? as usize;
+        let orig_length = header.u32()? as usize;
+        header.u32()?; // origChecksum; recomputed when the SFNT is rebuilt
+
+        let stored = woff.get(offset..offset + comp_length).ok_or_else(|| {
+            FontError::invalid_data(format!("WOFF table {} is out of bounds", Tag::new(&tag)))
+        })?;
+        let data = if comp_length < orig_length {
+            let mut out = Vec::with_capacity(orig_length);
+            ZlibDecoder::new(stored)
+                .read_to_end(&mut out)
+                .map_err(|e| {
+                    FontError::invalid_data(format!(
+                        "Failed to decompress {}: {}",
+                        Tag::new(&tag),
+                        e
+                    ))
+                })?;
+            if out.len() != orig_length {
+                return Err(FontError::invalid_data(format!(
+                    "WOFF table {} has the wrong length",
+                    Tag::new(&tag)
+                )));
+            }
+            out
+        } else {
+            stored.to_vec()
+        };
+        tables.push((tag, data));
+    }
+    Ok(build_sfnt(tables))
+}
+
+/// Decode one WOFF2 triplet-encoded point delta (spec section 5.2).
+fn decode_triplet(flag: u8, glyphs: &mut Reader<'_>) -> Result<(i32, i32), FontError> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let flag = flag & 0x7F;
+    Ok(if flag < 10 {
+        let b0 = glyphs.u8()? as i32;
+        (0, with_sign(flag, (((flag & 14) as i32) << 7) + b0))
+    } else if flag < 20 {
+        let b0 = glyphs.u8()? as i32;
+        (with_sign(flag, ((((flag - 10) & 14) as i32) << 7) + b0), 0)
+    } else if flag < 84 {
+        let b0 = (flag - 20) as i32;
+        let b1 = glyphs.u8()? as i32;
+        (
+            with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+            with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (b1 & 0x0F)),
+        )
+    } else if flag < 120 {
+        let b0 = (flag - 84) as i32;
+        let (b1, b2) = (glyphs.u8()? as i32, glyphs.u8()? as i32);
+        (
+            with_sign(flag, 1 + ((b0 / 12) << 8) + b1),
+            with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2),
+        )
+    } else if flag < 124 {
+        let (b1, b2, b3) = (
+            glyphs.u8()? as i32,
+            glyphs.u8()? as i32,
+            glyphs.u8()? as i32,
+        );
+        (
+            with_sign(flag, (b1 << 4) + (b2 >> 4)),
+            with_sign(flag >> 1, ((b2 & 0x0F) << 8) + b3),
+        )
+    } else {
+        let (x, y) = (glyphs.u16()? as i32, glyphs.u16()? as i32);
+        (with_sign(flag, x), with_sign(flag >> 1, y))
+    })
+}
+
+/// Encode a simple glyph record with short/same coordinate flags and
+/// repeated flag runs.
+pub(crate) fn encode_simple_glyph(
+    end_points: &[u16],
+    points: &[(i32, i32, bool)],
+    instructions: &[u8],
+    bbox: [i16; 4],
+    overlap: bool,
+) -> Vec<u8> {
+    const ON_CURVE: u8 = 0x01;
+    const X_SHORT: u8 = 0x02;
+    const Y_SHORT: u8 = 0x04;
+    const X_SAME_OR_POSITIVE: u8 = 0x10;
+    const Y_SAME_OR_POSITIVE: u8 = 0x20;
+    const OVERLAP_SIMPLE: u8 = 0x40;
+
+    let mut out = Vec::new();
+    out.extend((end_points.len() as i16).to_be_bytes());
+    for v in bbox {
+        out.extend(v.to_be_bytes());
+    }
+    for &e in end_points {
+        out.extend(e.to_be_bytes());
+    }
+    out.extend((instructions.len() as u16).to_be_bytes());
+    out.extend_from_slice(instructions);
+
+    let (mut flags, mut xs, mut ys) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut px, mut py) = (0, 0);
+    for (i, &(x, y, on_curve)) in points.iter().enumerate() {
+        let mut flag = if on_curve { ON_CURVE } else { 0 };
+        if i == 0 && overlap {
+            flag |= OVERLAP_SIMPLE;
+        }
+        for (delta, short, same, buf) in [
+            (x - px, X_SHORT, X_SAME_OR_POSITIVE, &mut xs),
+            (y - py, Y_SHORT, Y_SAME_OR_POSITIVE, &mut ys),
+        ] {
+            if delta == 0 {
+                flag |= same;
+            } else if delta.abs() <= 255 {
+                flag |= short;
+                if delta > 0 {
+                    flag |= same;
+                }
+                buf.push(delta.unsigned_abs() as u8);
+            } else {
+                buf.extend((delta as i16).to_be_bytes());
+            }
+        }
+        flags.push(flag);
+        (px, py) = (x, y);
+    }
+    push_glyph_flags(&mut out, &flags);
+    out.extend(xs);
+    out.extend(ys);
+    out
+}
+
+/// Append simple glyph point flags to `out`, folding runs of equal flags
+/// into one flag with REPEAT_FLAG and a repeat count.
+pub(crate) fn push_glyph_flags(out: &mut Vec<u8>, flags: &[u8]) {
+    const REPEAT: u8 = 0x08;
+
+    let mut i = 0;
+    while i < flags.len() {
+        let flag = flags[i];
+        let run = flags[i..]
+            .iter()
+            .take(256)
+            .take_while(|&&f| f == flag)
+            .count();
+        if run > 1 {
+            out.extend([flag | REPEAT, (run - 1) as u8]);
+        } else {
+            out.push(flag);
+        }
+        i += run;
+    }
+}
+
+/// Size in bytes of the component records at the start of `data`, and
+/// whether any component sets WE_HAVE_INSTRUCTIONS.
+fn composite_records_len(data: &[u8]) -> Result<(usize, bool), FontError> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+    const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+    let mut reader = Reader::new(data);
+    let mut has_instructions = false;
+    loop {
+        let flags = reader.u16()?;
+        let mut len = 2 + if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            4
+        } else {
+            2
+        };
+        if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            len += 8;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            len += 4;
+        } else if flags & WE_HAVE_A_SCALE != 0 {
+            len += 2;
+        }
+        reader.bytes(len)?;
+        has_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+        if flags & MORE_COMPONENTS == 0 {
+            return Ok((reader.pos, has_instructions));
+        }
+    }
+}
+
+/// Reconstructed glyf and loca tables plus each glyph's xMin (for hmtx).
+struct GlyfReconstruction {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    x_mins: Vec<i16>,
+}
+
+/// Rebuild glyf/loca from the WOFF2 transformed glyf table (spec section 5.1).
+fn reconstruct_glyf(data: &[u8]) -> Result<GlyfReconstruction, FontError> {
+    let mut header = Reader::new(data);
+    header.u16()?; // reserved
+    let option_flags = header.u16()?;
+    let num_glyphs = header.u16()? as usize;
+    let index_format = header.u16()?;
+    let mut sizes = [0usize; 7];
+    for size in &mut sizes {
+        *size = header.u32()? as usize;
+    }
+    let mut streams = Vec::with_capacity(7);
+    for size in sizes {
+        streams.push(Reader::new(header.bytes(size)?));
+    }
+    let overlap_bitmap = if option_flags & 1 != 0 {
+        Some(header.bytes(num_glyphs.div_ceil(8))?)
+    } else {
+        None
+    };
+    let [mut n_contours, mut n_points, mut flags, mut glyphs, mut composites, mut bboxes, mut instructions]: [Reader; 7] =
+        streams.try_into().map_err(|_| FontError::invalid_data("Invalid transformed glyf"))?;
+    let bbox_bitmap = bboxes.bytes(4 * num_glyphs.div_ceil(32))?;
+    let has_bbox = |gid: usize| bbox_bitmap[gid / 8] & (0x80 >> (gid % 8)) != 0;
+
+    let mut glyf = Vec::new();
+    let mut offsets = Vec::with_capacity(num_glyphs + 1);
+    let mut x_mins = Vec::with_capacity(num_glyphs);
+    for gid in 0..num_glyphs {
+        offsets.push(glyf.len());
+        let contours = n_contours.i16()?;
+        let explicit_bbox = if has_bbox(gid) {
+            Some([bboxes.i16()?, bboxes.i16()?, bboxes.i16()?, bboxes.i16()?])
+        } else {
+            None
+        };
+
+        let record = if contours == 0 {
+            x_mins.push(0);
+            Vec::new()
+        } else if contours < 0 {
+            let bbox = explicit_bbox.ok_or_else(|| {
+                FontError::invalid_data(format!("Composite glyph {} has no bounding box", gid))
+            })?;
+            let rest = &composites.data[composites.pos..];
+            let (len, has_instructions) = composite_records_len(rest)?;
+            let mut out = Vec::new();
+            out.extend((-1i16).to_be_bytes());
+            for v in bbox {
+                out.extend(v.to_be_bytes());
+            }
+            out.extend_from_slice(composites.bytes(len)?);
+            if has_instructions {
+                let n = glyphs.uint255()?;
+                out.extend(n.to_be_bytes());
+                out.extend_from_slice(instructions.bytes(n as usize)?);
+            }
+            x_mins.push(bbox[0]);
+            out
+        } else {
+            let mut end_points = Vec::with_capacity(contours as usize);
+            let mut total = 0u32;
+            for _ in 0..contours {
+                total += n_points.uint255()? as u32;
+                end_points.push(
+                    u16::try_from(total - 1)
+                        .map_err(|_| FontError::invalid_data("Too many points in glyph"))?,
+                );
+            }
+            let mut points = Vec::with_capacity(total as usize);
+            let (mut x, mut y) = (0i32, 0i32);
+            for _ in 0..total {
+                let flag = flags.u8()?;
+                let (dx, dy) = decode_triplet(flag, &mut glyphs)?;
+                x += dx;
+                y += dy;
+                points.push((x, y, flag & 0x80 == 0));
+            }
+            let n = glyphs.uint255()? as usize;
+            let program = instructions.bytes(n)?;
+            let bbox = explicit_bbox.unwrap_or_else(|| {
+                let clamp = |v: i32| v.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+                let xs = points.iter().map(|p| p.0);
+                let ys = points.iter().map(|p| p.1);
+                [
+                    clamp(xs.clone().min().unwrap_or(0)),
+                    clamp(ys.clone().min().unwrap_or(0)),
+                    clamp(xs.max().unwrap_or(0)),
+                    clamp(ys.max().unwrap_or(0)),
+                ]
+            });
+            let overlap = overlap_bitmap.is_some_and(|b| b[gid / 8] & (0x80 >> (gid % 8)) != 0);
+            x_mins.push(bbox[0]);
+            encode_simple_glyph(&end_points, &points, program, bbox, overlap)
+        };
+        glyf.extend(record);
+        glyf.resize(pad4(glyf.len()), 0);
+    }
+    offsets.push(glyf.len());
+
+    let mut loca = Vec::new();
+    for offset in offsets {
+        if index_format == 0 {
+            loca.extend(((offset / 2) as u16).to_be_bytes());
+        } else {
+            loca.extend((offset as u32).to_be_bytes());
+        }
+    }
+    Ok(GlyfReconstruction { glyf, loca, x_mins })
+}
+
+/// Rebuild hmtx from the WOFF2 transformed hmtx table (spec section 5.4),
+/// taking omitted side bearings from the glyphs' xMin.
+fn reconstruct_hmtx(
+    data: &[u8],
+    num_h_metrics: usize,
+    x_mins: &[i16],
+) -> Result<Vec<u8>, FontError> {
+    let mut reader = Reader::new(data);
+    let flags = reader.u8()?;
+    let num_glyphs = x_mins.len();
+    let num_h_metrics = num_h_metrics.min(num_glyphs);
+    let mut advances = Vec::with_capacity(num_h_metrics);
+    for _ in 0..num_h_metrics {
+        advances.push(reader.u16()?);
+    }
+    let mut lsbs = Vec::with_capacity(num_glyphs);
+    for (gid, &x_min) in x_mins.iter().enumerate() {
+        // Bit 0 omits the lsbs of full metrics, bit 1 those of the trailing array
+        let omitted = if gid < num_h_metrics { 1 } else { 2 };
+        lsbs.push(if flags & omitted != 0 {
+            x_min
+        } else {
+            reader.i16()?
+        });
+    }
+
+    let mut hmtx = Vec::with_capacity(num_h_metrics * 4 + (num_glyphs - num_h_metrics) * 2);
+    for (gid, lsb) in lsbs.iter().enumerate() {
+        if gid < num_h_metrics {
+            hmtx.extend(advances[gid].to_be_bytes());
+        }
+        hmtx.extend(lsb.to_be_bytes());
+    }
+    Ok(hmtx)
+}
+
+/// Unpack a WOFF2 file into SFNT bytes, reversing the glyf/loca and hmtx
+/// transforms. Font collections are not supported.
+pub fn woff2_to_sfnt(woff2: &[u8]) -> Result<Vec<u8>, FontError> {
+    let mut header = Reader::new(woff2);
+    if header.bytes(4)? != b"wOF2" {
+        return Err(FontError::invalid_data("Not a WOFF2 file"));
+    }
+    if header.bytes(4)? == b"ttcf" {
+        return Err(FontError::unsupported("WOFF2 font collections"));
+    }
+    header.pos = 12;
+    let num_tables = header.u16()? as usize;
+    header.pos = 20;
+    let compressed_len = header.u32()? as usize;
+    header.pos = WOFF2_HEADER_LEN;
+
+    // (tag, transform version, length in the decompressed stream)
+    let mut entries = Vec::with_capacity(num_tables);
+    for _ in 0..num_tables {
+        let flags = header.u8()?;
+        let tag: [u8; 4] = match flags & 0x3F {
+            63 => header.bytes(4)?.try_into().unwrap(),
+            i => *WOFF2_KNOWN_TAGS[i as usize],
+        };
+        let version = flags >> 6;
+        let orig_length = header.base128()? as usize;
+        let transformed = match &tag {
+            b"glyf" | b"loca" => version == 0,
+            b"hmtx" => version == 1,
+            _ => false,
+        };
+        let length = if transformed {
+            header.base128()? as usize
+        } else {
+            orig_length
+        };
+        entries.push((tag, transformed, length));
+    }
+
+    let mut stream = Vec::new();
+    brotli::BrotliDecompress(&mut header.bytes(compressed_len)?, &mut stream)
+        .map_err(|e| FontError::invalid_data(format!("Failed to decompress WOFF2 data: {}", e)))?;
+
+    let mut data = Reader::new(&stream);
+    let mut tables: Vec<([u8; 4], Vec<u8>)> = Vec::with_capacity(num_tables);
+    let mut transformed_glyf = None;
+    let mut transformed_hmtx = None;
+    for (tag, transformed, length) in entries {
+        let bytes = data.bytes(length)?;
+        match (&tag, transformed) {
+            (b"glyf", true) => transformed_glyf = Some(bytes),
+            (b"loca", true) => {} // rebuilt together with glyf
+            (b"hmtx", true) => transformed_hmtx = Some(bytes),
+            _ => tables.push((tag, bytes.to_vec())),
+        }
+    }
+
+    if let Some(glyf) = transformed_glyf {
+        let rebuilt = reconstruct_glyf(glyf)?;
+        if let Some(hmtx) = transformed_hmtx {
+            let num_h_metrics = tables
+                .iter()
+                .find(|(tag, _)| tag == b"hhea")
+                .and_then(|(_, hhea)| hhea.get(34..36))
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+                .ok_or_else(|| FontError::missing("hhea"))?;
+            tables.push((
+                *b"hmtx",
+                reconstruct_hmtx(hmtx, num_h_metrics, &rebuilt.x_mins)?,
+            ));
+        }
+        tables.push((*b"glyf", rebuilt.glyf));
+        tables.push((*b"loca", rebuilt.loca));
+    } else if transformed_hmtx.is_some() {
+        return Err(FontError::invalid_data(
+            "WOFF2 hmtx transform requires a transformed glyf table",
+        ));
+    }
+    Ok(build_sfnt(tables))
+}
+
+/// Detect the container of raw file bytes and return plain SFNT bytes.
+pub fn decode_font_container(bytes: Vec<u8>) -> Result<(Vec<u8>, ContainerFormat), FontError> {
+    match bytes.get(0..4) {
+        Some(b"wOFF") => Ok((woff_to_sfnt(&bytes)?, ContainerFormat::Woff)),
+        Some(b"wOF2") => Ok((woff2_to_sfnt(&bytes)?, ContainerFormat::Woff2)),
+        _ => Ok((bytes, ContainerFormat::Sfnt)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{simple_glyph, TestFontBuilder};
+    use skrifa::raw::tables::glyf::Glyph;
+    use skrifa::raw::TableProvider;
+    use skrifa::GlyphId;
+
+    fn read_u32(b: &[u8], pos: usize) -> u32 {
+        u32::from_be_bytes([b[pos], b[pos + 1], b[pos + 2], b[pos + 3]])
+    }
+
+    #[test]
+    fn test_woff_round_trip_tables() {
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let mut builder = TestFontBuilder::new().glyph(Vec::new(), 500);
+        for _ in 0..20 {
+            builder = builder.glyph(bar.clone(), 500);
+        }
+        let sfnt = builder.build();
+        let woff = sfnt_to_woff(&sfnt).unwrap();
+
+        assert_eq!(&woff[0..4], b"wOFF");
+        assert_eq!(read_u32(&woff, 8) as usize, woff.len());
+        let num_tables = u16::from_be_bytes([woff[12], woff[13]]) as usize;
+
+        let font = RawFontRef::new(&sfnt).unwrap();
+        assert_eq!(num_tables, font.table_directory.table_records().len());
+        let mut total_sfnt_size = 12 + 16 * num_tables;
+        let mut prev_tag = [0u8; 4];
+        let mut any_compressed = false;
+        for i in 0..num_tables {
+            let entry = WOFF_HEADER_LEN + i * WOFF_DIR_ENTRY_LEN;
+            let tag: [u8; 4] = woff[entry..entry + 4].try_into().unwrap();
+            assert!(tag > prev_tag, "directory must be sorted by tag");
+            prev_tag = tag;
+            let offset = read_u32(&woff, entry + 4) as usize;
+            let comp_len = read_u32(&woff, entry + 8) as usize;
+            let orig_len = read_u32(&woff, entry + 12) as usize;
+            assert_eq!(offset % 4, 0);
+
+            let stored = &woff[offset..offset + comp_len];
+            let table = if comp_len < orig_len {
+                any_compressed = true;
+                let mut out = Vec::new();
+                ZlibDecoder::new(stored).read_to_end(&mut out).unwrap();
+                out
+            } else {
+                stored.to_vec()
+            };
+            let source = font.table_data(skrifa::raw::types::Tag::new(&tag)).unwrap();
+            assert_eq!(table, source.as_bytes());
+            total_sfnt_size += pad4(orig_len);
+        }
+        assert!(any_compressed);
+        assert_eq!(read_u32(&woff, 16) as usize, total_sfnt_size);
+    }
+
+    #[test]
+    fn test_base128_encoding() {
+        let encode = |v| {
+            let mut buf = Vec::new();
+            push_base128(&mut buf, v);
+            buf
+        };
+        assert_eq!(encode(0), [0x00]);
+        assert_eq!(encode(127), [0x7F]);
+        assert_eq!(encode(128), [0x81, 0x00]);
+        assert_eq!(encode(0x3FFF), [0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn test_woff2_round_trip_tables() {
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let sfnt = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(bar, 500)
+            .table(b"ZZZZ", vec![1, 2, 3])
+            .build();
+        let woff2 = sfnt_to_woff2(&sfnt).unwrap();
+
+        assert_eq!(&woff2[0..4], b"wOF2");
+        assert_eq!(read_u32(&woff2, 8) as usize, woff2.len());
+        assert_eq!(woff2.len() % 4, 0);
+        let num_tables = u16::from_be_bytes([woff2[12], woff2[13]]) as usize;
+        let compressed_len = read_u32(&woff2, 20) as usize;
+
+        // Decode the directory independently of the encoder's tag table
+        let mut pos = WOFF2_HEADER_LEN;
+        let mut entries = Vec::new();
+        for _ in 0..num_tables {
+            let flags = woff2[pos];
+            pos += 1;
+            let tag: [u8; 4] = if flags & 0x3F == 63 {
+                pos += 4;
+                woff2[pos - 4..pos].try_into().unwrap()
+            } else {
+                *WOFF2_KNOWN_TAGS[(flags & 0x3F) as usize]
+            };
+            if &tag == b"glyf" || &tag == b"loca" {
+                assert_eq!(flags >> 6, 3, "glyf/loca must use the null transform");
+            }
+            let mut len = 0u32;
+            loop {
+                let b = woff2[pos];
+                pos += 1;
+                len = (len << 7) | (b & 0x7F) as u32;
+                if b & 0x80 == 0 {
+                    break;
+                }
+            }
+            entries.push((tag, len as usize));
+        }
+
+        let mut stream = Vec::new();
+        brotli::BrotliDecompress(&mut &woff2[pos..pos + compressed_len], &mut stream).unwrap();
+        let font = RawFontRef::new(&sfnt).unwrap();
+        let mut offset = 0;
+        for (tag, len) in entries {
+            let source = font.table_data(skrifa::raw::types::Tag::new(&tag)).unwrap();
+            assert_eq!(&stream[offset..offset + len], source.as_bytes());
+            offset += len;
+        }
+        assert_eq!(offset, stream.len());
+    }
+
+    fn assert_same_tables(decoded: &[u8], source: &[u8]) {
+        let decoded = RawFontRef::new(decoded).unwrap();
+        let source = RawFontRef::new(source).unwrap();
+        let records = source.table_directory.table_records();
+        assert_eq!(decoded.table_directory.table_records().len(), records.len());
+        for record in records {
+            let tag = record.tag();
+            assert_eq!(
+                decoded.table_data(tag).unwrap().as_bytes(),
+                source.table_data(tag).unwrap().as_bytes(),
+                "table {} differs",
+                tag
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_woff_and_woff2() {
+        let bar = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let mut builder = TestFontBuilder::new().glyph(Vec::new(), 500);
+        for _ in 0..20 {
+            builder = builder.glyph(bar.clone(), 500);
+        }
+        let sfnt = builder.table(b"ZZZZ", vec![1, 2, 3]).build();
+
+        let (decoded, format) = decode_font_container(sfnt_to_woff(&sfnt).unwrap()).unwrap();
+        assert_eq!(format, ContainerFormat::Woff);
+        assert_same_tables(&decoded, &sfnt);
+
+        let (decoded, format) = decode_font_container(sfnt_to_woff2(&sfnt).unwrap()).unwrap();
+        assert_eq!(format, ContainerFormat::Woff2);
+        assert_same_tables(&decoded, &sfnt);
+
+        let (decoded, format) = decode_font_container(sfnt.clone()).unwrap();
+        assert_eq!(format, ContainerFormat::Sfnt);
+        assert_eq!(decoded, sfnt);
+    }
+
+    #[test]
+    fn test_decode_woff2_transformed_glyf_and_hmtx() {
+        let triangle = simple_glyph(&[&[(0, 0, true), (0, 500, true), (100, 500, true)]]);
+        let reference = TestFontBuilder::new()
+            .glyph(Vec::new(), 500)
+            .glyph(triangle, 500)
+            .build();
+        let source = RawFontRef::new(&reference).unwrap();
+
+        // Transformed glyf: glyph 0 empty, glyph 1 the triangle above
+        let streams: [Vec<u8>; 7] = [
+            vec![0, 0, 0, 1],     // nContour
+            vec![3],              // nPoints
+            vec![1, 3, 11],       // flags: (0,+0) (0,+500) (+100,0), all on-curve
+            vec![0, 244, 100, 0], // triplet data, then instruction length
+            vec![],               // composite
+            vec![0; 4],           // bbox bitmap, no explicit boxes
+            vec![],               // instructions
+        ];
+        let mut glyf = Vec::new();
+        glyf.extend(0u16.to_be_bytes()); // reserved
+        glyf.extend(0u16.to_be_bytes()); // optionFlags
+        glyf.extend(2u16.to_be_bytes()); // numGlyphs
+        glyf.extend(1u16.to_be_bytes()); // indexFormat: long loca
+        for stream in &streams {
+            glyf.extend((stream.len() as u32).to_be_bytes());
+        }
+        glyf.extend(streams.concat());
+        // Transformed hmtx: advances only, lsbs come from xMin
+        let hmtx = vec![1, 0x01, 0xF4, 0x01, 0xF4];
+
+        let mut directory = Vec::new();
+        let mut stream = Vec::new();
+        let mut num_tables = 0u16;
+        for record in source.table_directory.table_records() {
+            let tag = record.tag().to_be_bytes();
+            let index = WOFF2_KNOWN_TAGS.iter().position(|t| **t == tag).unwrap() as u8;
+            let original = source.table_data(record.tag()).unwrap().as_bytes().to_vec();
+            let (flags, transformed) = match &tag {
+                b"glyf" => (index, Some(glyf.clone())),
+                b"loca" => (index, Some(Vec::new())),
+                b"hmtx" => (index | 1 << 6, Some(hmtx.clone())),
+                _ => (index, None),
+            };
+            directory.push(flags);
+            push_base128(&mut directory, record.length());
+            if let Some(transformed) = &transformed {
+                push_base128(&mut directory, transformed.len() as u32);
+            }
+            stream.extend(transformed.unwrap_or(original));
+            num_tables += 1;
+        }
+        let mut compressed = Vec::new();
+        brotli::BrotliCompress(&mut stream.as_slice(), &mut compressed, &Default::default())
+            .unwrap();
+
+        let mut woff2 = Vec::new();
+        woff2.extend(b"wOF2");
+        woff2.extend(0x0001_0000u32.to_be_bytes());
+        woff2.extend(0u32.to_be_bytes()); // length (unchecked)
+        woff2.extend(num_tables.to_be_bytes());
+        woff2.extend([0u8; 6]); // reserved, totalSfntSize
+        woff2.extend((compressed.len() as u32).to_be_bytes());
+        woff2.extend([0u8; 24]); // version, metadata, private block
+        woff2.extend(directory);
+        woff2.extend(compressed);
+
+        let decoded = woff2_to_sfnt(&woff2).unwrap();
+        let font = RawFontRef::new(&decoded).unwrap();
+        assert_eq!(
+            font.hmtx().unwrap().offset_data().as_bytes(),
+            source.hmtx().unwrap().offset_data().as_bytes()
+        );
+        let (loca, glyf) = (font.loca(None).unwrap(), font.glyf().unwrap());
+        assert!(loca.get_glyf(GlyphId::new(0), &glyf).unwrap().is_none());
+        let Some(Glyph::Simple(glyph)) = loca.get_glyf(GlyphId::new(1), &glyf).unwrap() else {
+            panic!("glyph 1 should be a simple glyph");
+        };
+        let points: Vec<(i16, i16, bool)> =
+            glyph.points().map(|p| (p.x, p.y, p.on_curve)).collect();
+        assert_eq!(points, [(0, 0, true), (0, 500, true), (100, 500, true)]);
+        assert_eq!(
+            (glyph.x_min(), glyph.y_min(), glyph.x_max(), glyph.y_max()),
+            (0, 0, 100, 500)
+        );
+    }
+}
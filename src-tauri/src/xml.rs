@@ -0,0 +1,285 @@
+//! A small XML reader for the formats typeBrew imports (UFO plists, glif
+//! files and TTX). It builds an element tree and ignores the prolog, comments,
+//! processing instructions and DOCTYPE declarations. Namespaces and DTD
+//! entities are not supported.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    /// Concatenated character data directly inside this element
+    pub text: String,
+    /// 1-based line of the start tag, for error messages
+    pub line: usize,
+}
+
+impl Element {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn line(&self) -> usize {
+        self.src[..self.pos].matches('\n').count() + 1
+    }
+
+    fn error(&self, msg: &str) -> String {
+        format!("line {}: {}", self.line(), msg)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.src.len() - trimmed.len();
+    }
+
+    fn skip_past(&mut self, terminator: &str) -> Result<(), String> {
+        match self.rest().find(terminator) {
+            Some(i) => {
+                self.pos += i + terminator.len();
+                Ok(())
+            }
+            None => Err(self.error(&format!("expected '{}'", terminator))),
+        }
+    }
+
+    /// Skip comments, processing instructions and DOCTYPE declarations.
+    /// Returns true if anything was skipped.
+    fn skip_misc(&mut self) -> Result<bool, String> {
+        let rest = self.rest();
+        if rest.starts_with("<!--") {
+            self.skip_past("-->")?;
+        } else if rest.starts_with("<?") {
+            self.skip_past("?>")?;
+        } else if rest.starts_with("<!DOCTYPE") {
+            // Internal subsets are not expected in the formats we read
+            self.skip_past(">")?;
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let len = self
+            .rest()
+            .find(|c: char| c.is_whitespace() || matches!(c, '>' | '/' | '='))
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        let name = self.rest()[..len].to_string();
+        self.pos += len;
+        Ok(name)
+    }
+
+    fn element(&mut self) -> Result<Element, String> {
+        let line = self.line();
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected '<'"));
+        }
+        self.pos += 1;
+        let name = self.name()?;
+        let mut element = Element {
+            name,
+            attributes: Vec::new(),
+            children: Vec::new(),
+            text: String::new(),
+            line,
+        };
+
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            }
+            if rest.starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let key = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error(&format!("expected '=' after attribute '{}'", key)));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(q @ ('"' | '\'')) => q,
+                _ => return Err(self.error(&format!("expected quoted value for '{}'", key))),
+            };
+            self.pos += 1;
+            let end = self
+                .rest()
+                .find(quote)
+                .ok_or_else(|| self.error(&format!("unterminated value for '{}'", key)))?;
+            let value = unescape(&self.rest()[..end]).map_err(|e| self.error(&e))?;
+            self.pos += end + 1;
+            element.attributes.push((key, value));
+        }
+
+        loop {
+            if self.skip_misc()? {
+                continue;
+            }
+            let rest = self.rest();
+            if rest.is_empty() {
+                return Err(format!(
+                    "line {}: <{}> is never closed",
+                    element.line, element.name
+                ));
+            }
+            if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata
+                    .find("]]>")
+                    .ok_or_else(|| self.error("unterminated CDATA section"))?;
+                element.text.push_str(&cdata[..end]);
+                self.pos += "<![CDATA[".len() + end + 3;
+            } else if let Some(close) = rest.strip_prefix("</") {
+                let end = close
+                    .find('>')
+                    .ok_or_else(|| self.error("unterminated end tag"))?;
+                let closing = close[..end].trim();
+                if closing != element.name {
+                    return Err(self.error(&format!(
+                        "expected </{}> but found </{}>",
+                        element.name, closing
+                    )));
+                }
+                self.pos += 2 + end + 1;
+                return Ok(element);
+            } else if rest.starts_with('<') {
+                let child = self.element()?;
+                element.children.push(child);
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                let text = unescape(&rest[..end]).map_err(|e| self.error(&e))?;
+                element.text.push_str(&text);
+                self.pos += end;
+            }
+        }
+    }
+}
+
+/// Decode the predefined entities and numeric character references.
+fn unescape(s: &str) -> Result<String, String> {
+    if !s.contains('&') {
+        return Ok(s.to_string());
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        let end = rest[i..]
+            .find(';')
+            .ok_or_else(|| "unterminated entity reference".to_string())?;
+        let entity = &rest[i + 1..i + end];
+        let ch = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32)
+                    .ok_or_else(|| format!("unknown entity '&{};'", entity))?
+            }
+        };
+        out.push(ch);
+        rest = &rest[i + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Escape text for use in character data or a double-quoted attribute.
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\r' => out.push_str("&#13;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Parse a document and return its root element.
+pub fn parse(src: &str) -> Result<Element, String> {
+    let mut parser = Parser {
+        src: src.strip_prefix('\u{feff}').unwrap_or(src),
+        pos: 0,
+    };
+    loop {
+        parser.skip_whitespace();
+        if !parser.skip_misc()? {
+            break;
+        }
+    }
+    let root = parser.element()?;
+    loop {
+        parser.skip_whitespace();
+        if !parser.skip_misc()? {
+            break;
+        }
+    }
+    if !parser.rest().is_empty() {
+        return Err(parser.error("unexpected content after the root element"));
+    }
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_document() {
+        let doc = "<?xml version=\"1.0\"?>\n<!DOCTYPE plist>\n<!-- note -->\n\
+                   <glyph name='a&amp;b' format=\"2\">\n  <advance width=\"500\"/>\n  \
+                   <note>x &lt; y &#x41;<![CDATA[<raw>]]></note>\n</glyph>\n";
+        let root = parse(doc).unwrap();
+        assert_eq!(root.name, "glyph");
+        assert_eq!(root.attr("name"), Some("a&b"));
+        assert_eq!(root.line, 4);
+        assert_eq!(root.child("advance").unwrap().attr("width"), Some("500"));
+        assert_eq!(root.child("note").unwrap().text, "x < y A<raw>");
+
+        let err = parse("<a>\n<b></a>").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{}", err);
+    }
+}
@@ -1,7 +1,53 @@
+import { useEffect } from 'react';
+import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
 import { FontDropzone } from './components/FontDropzone';
 import { Toaster } from 'sonner';
+import { takeLaunchFonts, useFileUpload } from '@/hooks/useFileUpload';
 
 function App() {
+  const { uploadFont, uploadFonts } = useFileUpload();
+
+  // A window opened with a font path goes straight to that font
+  useEffect(() => {
+    const initialFont = window.__TYPEBREW_INITIAL_FONT__;
+    if (!initialFont) return;
+    window.__TYPEBREW_INITIAL_FONT__ = null;
+    uploadFont(initialFont, initialFont.split(/[\\/]/).pop() || '');
+  }, [uploadFont]);
+
+  // Fonts the app was launched with (command line, file association) open together
+  useEffect(() => {
+    takeLaunchFonts()
+      .then((paths) => {
+        if (paths.length > 0) return uploadFonts(paths);
+      })
+      .catch((err: unknown) => console.error('Failed to open launch fonts:', err));
+  }, [uploadFonts]);
+
+  // A font opened from the OS while the start page shows
+  useEffect(() => {
+    const unlisten = getCurrentWebviewWindow().listen<string | null>('menu:open-font', (event) => {
+      if (event.payload) uploadFont(event.payload, event.payload.split(/[\\/]/).pop() || '');
+    });
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten menu event:', err));
+    };
+  }, [uploadFont]);
+
+  // File > Open Recent works from the start page too
+  useEffect(() => {
+    const unlisten = getCurrentWebviewWindow().listen<string>('menu:open-recent', (event) => {
+      uploadFont(event.payload, event.payload.split(/[\\/]/).pop() || '');
+    });
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten menu event:', err));
+    };
+  }, [uploadFont]);
+
   return (
     <div className="bg-background min-h-screen">
       <div className="container mx-auto py-8">
@@ -48,7 +48,7 @@ export function FontDropzone() {
               {isDragging ? 'Drop your fonts here' : 'Upload font files'}
             </h3>
             <p className="text-muted-foreground text-sm">
-              Drag and drop .otf or .ttf files here, or click to browse
+              Drag and drop .otf, .ttf, .woff, .woff2 or .ttc files here, or click to browse
             </p>
           </div>
 
@@ -1,8 +1,10 @@
-import { useState, useEffect, useCallback } from 'react';
+import { useState, useEffect, useCallback, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { toast } from 'sonner';
 import { TableContent } from '@/components/TableContent';
-import { parseGlyphOutlines, type Glyph } from '@/lib/glyphParser';
+import { OUTLINE_FORMAT_VERSION, parseGlyphOutlines, type Glyph } from '@/lib/glyphParser';
+import { errorMessage, isFontError } from '@/lib/errors';
 
 const OUTLINE_TABLES = ['glyf', 'CFF ', 'CFF2'];
 const GLYPH_BATCH_SIZE = 400;
@@ -13,6 +15,12 @@ interface GlyphState {
   unitsPerEm: number;
 }
 
+interface ExtractProgress {
+  path: string;
+  done: number;
+  total: number;
+}
+
 interface TableContentTabProps {
   filePath: string;
   tableName: string;
@@ -23,13 +31,30 @@ export function TableContentTab({ filePath, tableName }: TableContentTabProps) {
   const [glyphState, setGlyphState] = useState<GlyphState | null>(null);
   const [isLoading, setIsLoading] = useState(true);
   const [isLoadingMore, setIsLoadingMore] = useState(false);
+  const [progress, setProgress] = useState<ExtractProgress | null>(null);
+  // Id of the running first load, so extraction of a huge font can be cancelled
+  const operationId = useRef<string | null>(null);
+
+  // First-time outline extraction reports progress; show it instead of a bare skeleton
+  useEffect(() => {
+    const unlisten = listen<ExtractProgress>('font:extract-progress', (event) => {
+      if (event.payload.path === filePath) setProgress(event.payload);
+    });
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten progress event:', err));
+    };
+  }, [filePath]);
 
   const loadGlyphBatch = useCallback(
-    async (offset: number) => {
+    async (offset: number, operationId?: string) => {
       const buffer = await invoke<ArrayBuffer>('get_glyph_outlines', {
         filePath,
         offset,
         limit: GLYPH_BATCH_SIZE,
+        requestedVersion: OUTLINE_FORMAT_VERSION,
+        operationId,
       });
       return parseGlyphOutlines(buffer);
     },
@@ -42,7 +67,8 @@ export function TableContentTab({ filePath, tableName }: TableContentTabProps) {
       setIsLoading(true);
       try {
         if (OUTLINE_TABLES.includes(tableName)) {
-          const data = await loadGlyphBatch(0);
+          operationId.current = crypto.randomUUID();
+          const data = await loadGlyphBatch(0, operationId.current);
           setGlyphState({
             glyphs: data.glyphs,
             totalGlyphs: data.totalGlyphs,
@@ -56,9 +82,15 @@ export function TableContentTab({ filePath, tableName }: TableContentTabProps) {
           setTableData(data);
         }
       } catch (error) {
-        toast.error(`Failed to load ${tableName} table: ${error}`);
+        if (isFontError(error) && error.kind === 'cancelled') {
+          toast.info(`Stopped loading ${tableName} outlines`);
+        } else {
+          toast.error(`Failed to load ${tableName} table: ${errorMessage(error)}`);
+        }
       } finally {
+        operationId.current = null;
         setIsLoading(false);
+        setProgress(null);
       }
     };
     load();
@@ -79,7 +111,7 @@ export function TableContentTab({ filePath, tableName }: TableContentTabProps) {
           : null
       );
     } catch (error) {
-      toast.error(`Failed to load more glyphs: ${error}`);
+      toast.error(`Failed to load more glyphs: ${errorMessage(error)}`);
     } finally {
       setIsLoadingMore(false);
     }
@@ -94,10 +126,17 @@ export function TableContentTab({ filePath, tableName }: TableContentTabProps) {
       });
       setTableData(data);
     } catch (error) {
-      toast.error(`Failed to refresh table: ${error}`);
+      toast.error(`Failed to refresh table: ${errorMessage(error)}`);
     }
   }, [filePath, tableName]);
 
+  const handleCancel = useCallback(() => {
+    if (!operationId.current) return;
+    invoke<boolean>('cancel_operation', { operationId: operationId.current }).catch(
+      (err: unknown) => console.error('Failed to cancel operation:', err)
+    );
+  }, []);
+
   const fileName = filePath.split(/[\\/]/).pop() ?? filePath;
 
   return (
@@ -107,6 +146,24 @@ export function TableContentTab({ filePath, tableName }: TableContentTabProps) {
         <span className="text-border">/</span>
         <span className="text-foreground font-medium">{tableName}</span>
       </div>
+      {isLoading && progress && progress.total > 0 && (
+        <div className="shrink-0 px-4 pt-3">
+          <div className="text-muted-foreground mb-1 flex items-center justify-between text-xs">
+            <span>
+              Extracting outlines… {progress.done} / {progress.total}
+            </span>
+            <button type="button" className="hover:text-foreground underline" onClick={handleCancel}>
+              Cancel
+            </button>
+          </div>
+          <div className="bg-muted h-1.5 overflow-hidden rounded-full">
+            <div
+              className="bg-primary h-full transition-[width]"
+              style={{ width: `${(progress.done / progress.total) * 100}%` }}
+            />
+          </div>
+        </div>
+      )}
       <div className="min-h-0 flex-1 overflow-auto">
         <TableContent
           data={tableData}
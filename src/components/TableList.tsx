@@ -53,7 +53,11 @@ export function TableList({
     () =>
       fonts.map((font) => ({
         id: font.file_path,
-        label: font.file_name,
+        // Members of one collection share a file name, so tell them apart by style
+        label:
+          font.collection_index === null
+            ? font.file_name
+            : `${font.file_name} (${font.family_name} ${font.style_name})`,
         children: font.available_tables.map((table) => ({
           id: tableNodeId(font.file_path, table),
           label: table,
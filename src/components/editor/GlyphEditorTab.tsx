@@ -24,6 +24,7 @@ import { InspectorPanel } from './InspectorPanel';
 import { GlyphPreview } from './GlyphPreview';
 import { HintingPreviewPanel } from './HintingPreviewPanel';
 import type { GlyphEditorTabState, FontMetrics, ViewTransform } from '@/lib/editorTypes';
+import { errorMessage } from '@/lib/errors';
 
 export interface TransformFeedback {
   isActive: boolean;
@@ -271,12 +272,13 @@ export function GlyphEditorTab({ tabState }: Props) {
         const activeComp = getComponentAtPath(state.components, state.activeComponentPath);
         if (activeComp && !activeComp.isComposite && !activeComp.locked && state.paths.length > 0) {
           const svgPathOut = editablePathToSvg(state.paths);
-          await invoke('save_glyph_outline', {
+          const warnings = await invoke<string[]>('save_glyph_outline', {
             filePath,
             glyphId: activeComp.glyphId,
             svgPath: svgPathOut,
             tableName,
           });
+          warnings.forEach((warning) => toast.warning(warning));
           editorEventBus.emitGlyphSaved({
             filePath,
             glyphId: activeComp.glyphId,
@@ -293,19 +295,21 @@ export function GlyphEditorTab({ tabState }: Props) {
         toast.success('Composite glyph saved');
       } else {
         const svgPathOut = editablePathToSvg(state.paths);
-        await invoke('save_glyph_outline', {
+        // CFF saves drop the edited glyph's hints and say so
+        const warnings = await invoke<string[]>('save_glyph_outline', {
           filePath,
           glyphId,
           svgPath: svgPathOut,
           tableName,
         });
+        warnings.forEach((warning) => toast.warning(warning));
         dispatch({ type: 'MARK_SAVED' });
         editorEventBus.emitGlyphSaved({ filePath, glyphId, svgPath: svgPathOut });
         toast.success('Glyph saved');
       }
     } catch (error) {
       dispatch({ type: 'SET_SAVING', saving: false });
-      toast.error(`Failed to save glyph: ${error}`);
+      toast.error(`Failed to save glyph: ${errorMessage(error)}`);
     }
   }, [
     state.paths,
@@ -90,8 +90,12 @@ export function HintingPreviewPanel({ filePath, glyphId, metrics }: Props) {
   const [svgPaths, setSvgPaths] = useState<string[]>([]);
 
   useEffect(() => {
-    invoke<string[]>('get_hinted_glyph_outlines', { filePath, glyphId, pxSizes: PX_SIZES })
-      .then(setSvgPaths)
+    invoke<{ paths: string[] }>('get_hinted_glyph_outlines', {
+      filePath,
+      glyphId,
+      pxSizes: PX_SIZES,
+    })
+      .then((result) => setSvgPaths(result.paths))
       .catch(() => setSvgPaths([]));
   }, [filePath, glyphId]);
 
@@ -5,6 +5,7 @@ import { Button } from '@/components/ui/button';
 import { ScrollArea } from '@/components/ui/scroll-area';
 import { toast } from 'sonner';
 import { Loader2, Save, RotateCcw } from 'lucide-react';
+import { errorMessage } from '@/lib/errors';
 
 interface HeadTableData {
   version: string;
@@ -13,8 +14,9 @@ interface HeadTableData {
   magic_number: number;
   flags: number;
   units_per_em: number;
-  created: number;
-  modified: number;
+  /** ISO 8601 UTC, e.g. "2024-03-01T12:00:00Z". */
+  created: string;
+  modified: string;
   x_min: number;
   y_min: number;
   x_max: number;
@@ -111,8 +113,8 @@ export function HeadTable({ data, filePath, onSaved }: HeadTableProps) {
           font_revision: parseFloat(values.font_revision) || 0,
           flags: parseInt(values.flags) || 0,
           units_per_em: parseInt(values.units_per_em) || 0,
-          created: parseInt(values.created) || 0,
-          modified: parseInt(values.modified) || 0,
+          created: values.created.trim(),
+          modified: values.modified.trim(),
           x_min: parseInt(values.x_min) || 0,
           y_min: parseInt(values.y_min) || 0,
           x_max: parseInt(values.x_max) || 0,
@@ -126,7 +128,7 @@ export function HeadTable({ data, filePath, onSaved }: HeadTableProps) {
       toast.success('Head table updated successfully');
       onSaved();
     } catch (error) {
-      toast.error(`Failed to update head table: ${error}`);
+      toast.error(`Failed to update head table: ${errorMessage(error)}`);
     } finally {
       setIsSaving(false);
     }
@@ -5,6 +5,7 @@ import { Button } from '@/components/ui/button';
 import { ScrollArea } from '@/components/ui/scroll-area';
 import { toast } from 'sonner';
 import { Loader2, Save, RotateCcw } from 'lucide-react';
+import { errorMessage } from '@/lib/errors';
 
 interface HheaTableData {
   version: string;
@@ -107,7 +108,7 @@ export function HheaTable({ data, filePath, onSaved }: HheaTableProps) {
       toast.success('Hhea table updated successfully');
       onSaved();
     } catch (error) {
-      toast.error(`Failed to update hhea table: ${error}`);
+      toast.error(`Failed to update hhea table: ${errorMessage(error)}`);
     } finally {
       setIsSaving(false);
     }
@@ -5,6 +5,7 @@ import { Button } from '@/components/ui/button';
 import { ScrollArea } from '@/components/ui/scroll-area';
 import { toast } from 'sonner';
 import { Loader2, Save, RotateCcw } from 'lucide-react';
+import { errorMessage } from '@/lib/errors';
 
 interface MaxpTableData {
   version: string;
@@ -67,7 +68,7 @@ export function MaxpTable({ data, filePath, onSaved }: MaxpTableProps) {
       toast.success('Maxp table updated successfully');
       onSaved();
     } catch (error) {
-      toast.error(`Failed to update maxp table: ${error}`);
+      toast.error(`Failed to update maxp table: ${errorMessage(error)}`);
     } finally {
       setIsSaving(false);
     }
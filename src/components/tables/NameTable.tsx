@@ -7,6 +7,7 @@ import { ScrollArea } from '@/components/ui/scroll-area';
 import { Separator } from '@/components/ui/separator';
 import { toast } from 'sonner';
 import { Loader2, Save, RotateCcw } from 'lucide-react';
+import { errorMessage } from '@/lib/errors';
 
 interface NameRecord {
   name_id: number;
@@ -117,7 +118,7 @@ export function NameTable({ data, filePath, onSaved }: NameTableProps) {
       toast.success('Name table updated successfully');
       onSaved();
     } catch (error) {
-      toast.error(`Failed to update name table: ${error}`);
+      toast.error(`Failed to update name table: ${errorMessage(error)}`);
     } finally {
       setIsSaving(false);
     }
@@ -1,25 +1,71 @@
 import { useState } from 'react';
 import { useNavigate } from 'react-router';
-import { open } from '@tauri-apps/plugin-dialog';
+import { open, save } from '@tauri-apps/plugin-dialog';
+import { readTextFile } from '@tauri-apps/plugin-fs';
 import { invoke } from '@tauri-apps/api/core';
 import { toast } from 'sonner';
-import type { FontMetadata } from '@/types/font';
+import type {
+  CollectionMember,
+  EditMode,
+  FontMetadata,
+  FontStatus,
+  MergeReport,
+} from '@/types/font';
+import { errorMessage } from '@/lib/errors';
 
-const VALID_EXTENSIONS = ['.otf', '.ttf'];
+const VALID_EXTENSIONS = ['.otf', '.ttf', '.woff', '.woff2', '.ttc', '.otc'];
+const OPEN_EXTENSIONS = ['otf', 'ttf', 'woff', 'woff2', 'ttc', 'otc'];
+const COLLECTION_EXTENSIONS = ['.ttc', '.otc'];
 
 export function validateFontFile(fileName: string): boolean {
   const extension = fileName.toLowerCase().slice(fileName.lastIndexOf('.'));
   return VALID_EXTENSIONS.includes(extension);
 }
 
-export async function parseFontFile(filePath: string): Promise<FontMetadata> {
-  return invoke<FontMetadata>('parse_font_file', { filePath });
+export async function parseFontFile(filePath: string, fontIndex?: number): Promise<FontMetadata> {
+  return invoke<FontMetadata>('parse_font_file', { filePath, fontIndex });
+}
+
+// Open a font from bytes, e.g. one generated in the frontend; its file_path is a
+// "mem://" key that commands accept like a path, and Save As gives it a file
+export async function parseFontBytes(
+  bytes: Uint8Array,
+  displayName: string
+): Promise<FontMetadata> {
+  return invoke<FontMetadata>('parse_font_bytes', { bytes: Array.from(bytes), displayName });
+}
+
+export async function listCollectionFonts(filePath: string): Promise<CollectionMember[]> {
+  return invoke<CollectionMember[]>('list_collection_fonts', { filePath });
+}
+
+// Open every member of a .ttc/.otc collection; any other file yields one font
+async function parseFontFileMembers(filePath: string): Promise<FontMetadata[]> {
+  const extension = filePath.toLowerCase().slice(filePath.lastIndexOf('.'));
+  if (!COLLECTION_EXTENSIONS.includes(extension)) return [await parseFontFile(filePath)];
+
+  const members = await listCollectionFonts(filePath);
+  const results: FontMetadata[] = [];
+  for (const member of members) {
+    results.push(await parseFontFile(filePath, member.index));
+  }
+  return results;
+}
+
+// Edits to a WOFF/WOFF2 font are written back as plain SFNT, so say so up front
+function noteContainerFormat(metadata: FontMetadata) {
+  if (metadata.container_format !== 'sfnt') {
+    toast.info(
+      `${metadata.file_name} was opened from ${metadata.container_format.toUpperCase()}. ` +
+        'Saved edits are written as an uncompressed font; use File > Export to re-wrap it.'
+    );
+  }
 }
 
 export async function openFontDialog(): Promise<FontMetadata[]> {
   const selected = await open({
     multiple: true,
-    filters: [{ name: 'Font Files', extensions: ['otf', 'ttf'] }],
+    filters: [{ name: 'Font Files', extensions: OPEN_EXTENSIONS }],
   });
 
   if (!selected) return [];
@@ -30,50 +76,242 @@ export async function openFontDialog(): Promise<FontMetadata[]> {
   for (const filePath of paths) {
     const fileName = filePath.split(/[\\/]/).pop() || '';
     if (!validateFontFile(fileName)) {
-      toast.error(`Invalid file type: ${fileName}. Only .otf/.ttf supported.`);
+      toast.error(`Invalid file type: ${fileName}. Only .otf/.ttf/.woff/.woff2/.ttc/.otc supported.`);
       continue;
     }
     try {
-      const metadata = await parseFontFile(filePath);
-      results.push(metadata);
+      const fonts = await parseFontFileMembers(filePath);
+      fonts.forEach(noteContainerFormat);
+      results.push(...fonts);
     } catch (error) {
-      toast.error(`Failed to process ${fileName}: ${error}`);
+      toast.error(`Failed to process ${fileName}: ${errorMessage(error)}`);
     }
   }
 
   return results;
 }
 
+// Open a font by path: picked from File > Open Recent, or handed over by the OS
+export async function openFontPath(filePath: string): Promise<FontMetadata[]> {
+  const fonts = await parseFontFileMembers(filePath);
+  fonts.forEach(noteContainerFormat);
+  return fonts;
+}
+
+// Fonts passed on the command line or by the OS before this window loaded;
+// only the first caller gets them
+export async function takeLaunchFonts(): Promise<string[]> {
+  return invoke<string[]>('take_launch_fonts');
+}
+
+export async function getRecentFonts(): Promise<string[]> {
+  return invoke<string[]>('get_recent_fonts');
+}
+
+export async function clearRecentFonts(): Promise<void> {
+  return invoke('clear_recent_fonts');
+}
+
+// Open another window, optionally showing `filePath`, to compare fonts side by side
+export async function openFontWindow(filePath: string | null): Promise<string> {
+  return invoke<string>('open_font_window', { filePath });
+}
+
+// Re-read a font from disk, dropping cached bytes and this session's state
+export async function reloadFont(filePath: string): Promise<FontMetadata> {
+  return invoke<FontMetadata>('reload_font', { filePath });
+}
+
+export async function saveFontAsDialog(filePath: string): Promise<FontMetadata | null> {
+  const destinationPath = await save({
+    defaultPath: filePath,
+    filters: [{ name: 'Font Files', extensions: ['otf', 'ttf'] }],
+  });
+
+  if (!destinationPath) return null;
+
+  // The native save dialog already asks before replacing an existing file
+  return invoke<FontMetadata>('save_font_as', { filePath, destinationPath, overwrite: true });
+}
+
+// Direct edits are written as they are made; Save commits deferred ones
+export async function saveFont(filePath: string): Promise<FontStatus> {
+  return invoke<FontStatus>('flush_pending_edits', { filePath });
+}
+
+// Deferred mode keeps edits in memory until commitFont or discardChanges
+export async function setEditMode(filePath: string, mode: EditMode): Promise<FontStatus> {
+  return invoke<FontStatus>('set_edit_mode', { filePath, mode });
+}
+
+export async function commitFont(filePath: string): Promise<FontStatus> {
+  return invoke<FontStatus>('commit_font', { filePath });
+}
+
+export async function discardChanges(filePath: string): Promise<FontStatus> {
+  return invoke<FontStatus>('discard_changes', { filePath });
+}
+
+// Grey out File menu items that need an open font
+export async function setMenuItemEnabled(id: string, enabled: boolean): Promise<void> {
+  return invoke('set_menu_item_enabled', { id, enabled });
+}
+
+export type ExportFormat = 'woff' | 'woff2' | 'ttx';
+
+export async function exportFontDialog(filePath: string, format: ExportFormat): Promise<boolean> {
+  const baseName = filePath.replace(/\.[^.\\/]+$/, '');
+  const destination = await save({
+    defaultPath: `${baseName}.${format}`,
+    filters: [{ name: format.toUpperCase(), extensions: [format] }],
+  });
+
+  if (!destination) return false;
+
+  await invoke(`export_${format}`, { filePath, destination });
+  return true;
+}
+
+// Write one SVG per glyph into a chosen folder; returns how many were written
+export async function exportGlyphSvgsDialog(filePath: string): Promise<number | null> {
+  const destinationDir = await open({ directory: true, title: 'Export glyphs as SVG to…' });
+  if (!destinationDir || Array.isArray(destinationDir)) return null;
+
+  return invoke<number>('export_glyph_svgs', { filePath, destinationDir });
+}
+
+// Pack every open font into one collection; identical tables are stored once
+export async function exportCollectionDialog(fontPaths: string[]): Promise<boolean> {
+  const destination = await save({
+    defaultPath: 'collection.ttc',
+    filters: [{ name: 'TrueType Collection', extensions: ['ttc'] }],
+  });
+
+  if (!destination) return false;
+
+  await invoke('export_ttc', { fontPaths, destination });
+  return true;
+}
+
+// Compile a UFO package to TrueType and open the result
+export async function importUfoDialog(): Promise<FontMetadata | null> {
+  const sourceDir = await open({ directory: true, title: 'Select a UFO package' });
+  if (!sourceDir || Array.isArray(sourceDir)) return null;
+
+  const baseName = sourceDir.replace(/[\\/]+$/, '').replace(/\.ufo$/i, '');
+  const destinationTtf = await save({
+    defaultPath: `${baseName}.ttf`,
+    filters: [{ name: 'TrueType', extensions: ['ttf'] }],
+  });
+  if (!destinationTtf) return null;
+
+  const warnings = await invoke<string[]>('import_ufo', { sourceDir, destinationTtf });
+  warnings.forEach((warning) => toast.warning(warning));
+  return parseFontFile(destinationTtf);
+}
+
+// Replace one table of a font with the single table in a .ttx file
+export async function importTtxTableDialog(filePath: string): Promise<string | null> {
+  const ttxPath = await open({ filters: [{ name: 'TTX', extensions: ['ttx'] }] });
+  if (!ttxPath || Array.isArray(ttxPath)) return null;
+
+  const ttxXml = await readTextFile(ttxPath);
+  const root = new DOMParser().parseFromString(ttxXml, 'application/xml').documentElement;
+  const tables =
+    root.nodeName === 'ttFont'
+      ? Array.from(root.children)
+          .map((el) => el.nodeName)
+          .filter((name) => name !== 'GlyphOrder')
+      : [root.nodeName];
+  if (tables.length !== 1) {
+    throw new Error(`expected one table in the TTX file, found ${tables.length}`);
+  }
+
+  await invoke('import_ttx_table', { filePath, tableTag: tables[0], ttxXml });
+  return tables[0];
+}
+
+// Add the glyphs another font has for codepoints the base lacks; opens the merged copy
+export async function mergeFontDialog(
+  basePath: string
+): Promise<{ font: FontMetadata; report: MergeReport } | null> {
+  const additionPath = await open({
+    title: 'Select the font to take glyphs from',
+    filters: [{ name: 'TrueType', extensions: ['ttf'] }],
+  });
+  if (!additionPath || Array.isArray(additionPath)) return null;
+
+  const baseName = basePath.replace(/\.[^.\\/]+$/, '');
+  const destination = await save({
+    defaultPath: `${baseName}-merged.ttf`,
+    filters: [{ name: 'TrueType', extensions: ['ttf'] }],
+  });
+  if (!destination) return null;
+
+  const report = await invoke<MergeReport>('merge_fonts', {
+    basePath,
+    additionPath,
+    destination,
+    options: { rename_collisions: false },
+  });
+  return { font: await parseFontFile(destination), report };
+}
+
 export function useFileUpload() {
   const navigate = useNavigate();
   const [isUploading, setIsUploading] = useState(false);
 
   const uploadFont = async (filePath: string, fileName: string) => {
     if (!validateFontFile(fileName)) {
-      toast.error('Invalid file type. Please upload .otf or .ttf files only.');
+      toast.error(
+        'Invalid file type. Please upload .otf, .ttf, .woff, .woff2, .ttc or .otc files only.'
+      );
       return;
     }
 
     try {
       setIsUploading(true);
-      const metadata = await parseFontFile(filePath);
+      const fonts = await parseFontFileMembers(filePath);
 
+      // A collection opens with all of its members; the first one is selected
       navigate(`/font/${encodeURIComponent(fileName)}`, {
-        state: { metadata, filePath },
+        state: { metadata: fonts[0], fonts, filePath },
       });
 
       toast.success(`${fileName} has been parsed successfully.`);
+      fonts.forEach(noteContainerFormat);
     } catch (error) {
-      toast.error(`Failed to process ${fileName}: ${error}`);
+      toast.error(`Failed to process ${fileName}: ${errorMessage(error)}`);
     } finally {
       setIsUploading(false);
     }
   };
 
+  // Open several fonts into one viewer, e.g. the files the app was launched with
+  const uploadFonts = async (filePaths: string[]) => {
+    const fonts: FontMetadata[] = [];
+    setIsUploading(true);
+    for (const filePath of filePaths) {
+      try {
+        fonts.push(...(await parseFontFileMembers(filePath)));
+      } catch (error) {
+        const fileName = filePath.split(/[\\/]/).pop() || '';
+        toast.error(`Failed to process ${fileName}: ${errorMessage(error)}`);
+      }
+    }
+    setIsUploading(false);
+    if (fonts.length === 0) return;
+
+    navigate(`/font/${encodeURIComponent(fonts[0].file_name)}`, {
+      state: { metadata: fonts[0], fonts, filePath: fonts[0].file_path },
+    });
+    fonts.forEach(noteContainerFormat);
+  };
+
   const handleFileDialog = async () => {
     const selected = await open({
       multiple: true,
-      filters: [{ name: 'Font Files', extensions: ['otf', 'ttf'] }],
+      filters: [{ name: 'Font Files', extensions: OPEN_EXTENSIONS }],
     });
 
     if (!selected) return;
@@ -88,6 +326,7 @@ export function useFileUpload() {
   return {
     isUploading,
     uploadFont,
+    uploadFonts,
     handleFileDialog,
     validateFontFile,
   };
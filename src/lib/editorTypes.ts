@@ -343,6 +343,8 @@ export interface BackendComponentOffset {
 export interface GlyphOutlineData {
   glyph_id: number;
   glyph_name?: string;
+  /** Every codepoint the cmap maps to the glyph, ascending. */
+  codepoints?: number[];
   contours: GlyphContour[];
   advance_width: number;
   /** Left side bearing from hmtx table (font-space units). */
@@ -358,6 +360,27 @@ export interface GlyphOutlineData {
   components: BackendComponentOffset[];
 }
 
+/** Result of `get_glyph_source_info`: what editing a glyph acts on. */
+export interface GlyphSourceInfo {
+  source:
+    | 'glyf_simple'
+    | 'glyf_composite'
+    | 'cff_charstring'
+    | 'cff2_charstring'
+    | 'svg_document'
+    | 'bitmap_only'
+    | 'empty';
+  /** Whether save_glyph_outline writes to this source. */
+  savable: boolean;
+  contour_count: number | null;
+  component_count: number | null;
+  /** Bytes in the glyph's own CFF/CFF2 charstring. */
+  charstring_length: number | null;
+  /** SVG document covering the glyph, even when outlines take precedence. */
+  svg_document: { start_glyph_id: number; end_glyph_id: number; length: number } | null;
+  bitmap_strikes: { table: 'sbix' | 'CBDT' | 'EBDT'; ppem: number }[];
+}
+
 // ---- State passed to GoldenLayout for a GlyphEditorTab ----
 
 export interface GlyphEditorTabState {
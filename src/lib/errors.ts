@@ -0,0 +1,30 @@
+/** Error object backend commands reject with. */
+export interface FontError {
+  kind:
+    | 'io'
+    | 'invalid_font'
+    | 'table_missing'
+    | 'table_malformed'
+    | 'glyph_not_found'
+    | 'unsupported'
+    | 'invalid_input'
+    | 'build_failed'
+    | 'write_failed'
+    | 'changed_on_disk'
+    | 'cancelled'
+    | 'other';
+  message: string;
+  /** Structured details, e.g. `{ tag }` for table errors or `{ path }` for I/O. */
+  context: Record<string, unknown>;
+}
+
+export function isFontError(err: unknown): err is FontError {
+  return typeof err === 'object' && err !== null && 'kind' in err && 'message' in err;
+}
+
+// Readable text for a rejected invoke; plugin errors are still plain strings
+export function errorMessage(err: unknown): string {
+  if (isFontError(err)) return err.message;
+  if (err instanceof Error) return err.message;
+  return String(err);
+}
@@ -205,4 +205,78 @@ describe('parseGlyphOutlines', () => {
 
     expect(result.glyphs[0].svg_path).toBe(complexPath);
   });
+
+  it('parses points-encoded pages', () => {
+    const commands: [string, number[]][] = [
+      ['M', [10.25, -0]],
+      ['Q', [-3.5, -20.125, 40, 0]],
+      ['C', [1, 2, 3, 4, 5, 6]],
+      ['Z', []],
+    ];
+    const coordCount = commands.reduce((n, [, coords]) => n + coords.length, 0);
+    const buffer = new ArrayBuffer(6 + 10 + 9 + 2 + 4 + commands.length + coordCount * 4);
+    const view = new DataView(buffer);
+    new Uint8Array(buffer, 0, 4).set(new TextEncoder().encode('TBGP'));
+    view.setUint16(4, 1, true);
+    view.setUint32(6, 50, true);
+    view.setUint32(10, 1, true);
+    view.setUint16(14, 1000, true);
+    let offset = 16;
+    view.setUint32(offset, 3, true);
+    view.setFloat32(offset + 4, 500, true);
+    view.setUint8(offset + 8, 0);
+    view.setUint16(offset + 9, 0, true);
+    offset += 11;
+    view.setUint32(offset, commands.length, true);
+    offset += 4;
+    for (const [op, coords] of commands) {
+      view.setUint8(offset, op.charCodeAt(0));
+      offset += 1;
+      for (const v of coords) {
+        view.setFloat32(offset, v, true);
+        offset += 4;
+      }
+    }
+
+    const result = parseGlyphOutlines(buffer);
+    expect(result.totalGlyphs).toBe(50);
+    expect(result.unitsPerEm).toBe(1000);
+    expect(result.glyphs[0].glyph_id).toBe(3);
+    expect(result.glyphs[0].advance_width).toBe(500);
+    expect(result.glyphs[0].svg_path).toBe('M10.25 0 Q-3.5 -20.125 40 0 C1 2 3 4 5 6 Z');
+  });
+
+  it('parses the versioned page layout', () => {
+    // Same bytes as test_outline_pages_match_golden_bytes in font_parser.rs
+    // prettier-ignore
+    const bytes = new Uint8Array([
+      ...new TextEncoder().encode('TBGO'), 1, 0,
+      5, 0, 0, 0, 1, 0, 0, 0, 0xe8, 0x03,
+      3, 0, 0, 0, 0, 0, 0xfa, 0x43,
+      1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x40, 0, 0, 0x80, 0x3f,
+      1, 0, 0x41,
+      11, 0, 0, 0, ...new TextEncoder().encode('M0 -0L2 -1Z'),
+    ]);
+
+    const result = parseGlyphOutlines(bytes.buffer);
+    expect(result.totalGlyphs).toBe(5);
+    expect(result.unitsPerEm).toBe(1000);
+    expect(result.glyphs).toEqual([
+      {
+        glyph_id: 3,
+        glyph_name: 'A',
+        svg_path: 'M0 -0L2 -1Z',
+        advance_width: 500,
+        bounds: { x_min: 0, y_min: 0, x_max: 2, y_max: 1 },
+      },
+    ]);
+  });
+
+  it('rejects unknown points versions', () => {
+    const buffer = new ArrayBuffer(16);
+    new Uint8Array(buffer, 0, 4).set(new TextEncoder().encode('TBGP'));
+    new DataView(buffer).setUint16(4, 2, true);
+
+    expect(() => parseGlyphOutlines(buffer)).toThrow('version 2');
+  });
 });
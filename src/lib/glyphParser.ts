@@ -20,20 +20,45 @@ export interface GlyphOutlineData {
   glyphs: Glyph[];
 }
 
+/** Leads an SVG-encoded page ("TBGO", little-endian). */
+const OUTLINES_MAGIC = 0x4f474254;
+/** Leads a page encoded with `encoding: 'points'` ("TBGP", little-endian). */
+const POINTS_MAGIC = 0x50474254;
+/** Newest page layout this parser reads; sent as `requestedVersion`. */
+export const OUTLINE_FORMAT_VERSION = 1;
+const COORD_COUNT: Record<string, number> = { M: 2, L: 2, Q: 4, C: 6, Z: 0 };
+
 /**
  * Parse binary glyph outline data from the Rust backend.
  *
- * Binary format (all little-endian):
+ * Binary format (all little-endian), mirroring `encode_glyph_outlines_binary`:
+ *   Prefix: magic(4) + format_version(u16); "TBGO" for SVG pages, "TBGP" for
+ *           points pages
  *   Header: total_glyphs(u32) + batch_count(u32) + units_per_em(u16)
  *   Per glyph: glyph_id(u32) + advance_width(f32) + has_bounds(u8)
  *              + [x_min(f32) + y_min(f32) + x_max(f32) + y_max(f32)]
  *              + name_len(u16) + name_bytes + path_len(u32) + path_bytes
+ *
+ * Points pages replace the path with command_count(u32) + per command an
+ * ASCII opcode byte (M/L/Q/C/Z) followed by its 2/2/4/6/0 coordinates as f32,
+ * Y down like the SVG path. A page without a prefix is a format version 0 SVG
+ * page from an older backend.
  */
 export function parseGlyphOutlines(buffer: ArrayBuffer): GlyphOutlineData {
   const view = new DataView(buffer);
   const decoder = new TextDecoder();
   let offset = 0;
 
+  const magic = buffer.byteLength >= 4 ? view.getUint32(0, true) : 0;
+  const points = magic === POINTS_MAGIC;
+  if (points || magic === OUTLINES_MAGIC) {
+    const version = view.getUint16(4, true);
+    if (version < 1 || version > OUTLINE_FORMAT_VERSION) {
+      throw new Error(`Unsupported glyph outline format version ${version}`);
+    }
+    offset = 6;
+  }
+
   const totalGlyphs = view.getUint32(offset, true);
   offset += 4;
   const batchCount = view.getUint32(offset, true);
@@ -70,10 +95,28 @@ export function parseGlyphOutlines(buffer: ArrayBuffer): GlyphOutlineData {
       nameLen > 0 ? decoder.decode(new Uint8Array(buffer, offset, nameLen)) : undefined;
     offset += nameLen;
 
-    const pathLen = view.getUint32(offset, true);
-    offset += 4;
-    const svg_path = decoder.decode(new Uint8Array(buffer, offset, pathLen));
-    offset += pathLen;
+    let svg_path: string;
+    if (points) {
+      const commandCount = view.getUint32(offset, true);
+      offset += 4;
+      const parts: string[] = [];
+      for (let c = 0; c < commandCount; c++) {
+        const op = String.fromCharCode(view.getUint8(offset));
+        offset += 1;
+        const coords: number[] = [];
+        for (let k = 0; k < (COORD_COUNT[op] ?? 0); k++) {
+          coords.push(view.getFloat32(offset, true));
+          offset += 4;
+        }
+        parts.push(op + coords.join(' '));
+      }
+      svg_path = parts.join(' ');
+    } else {
+      const pathLen = view.getUint32(offset, true);
+      offset += 4;
+      svg_path = decoder.decode(new Uint8Array(buffer, offset, pathLen));
+      offset += pathLen;
+    }
 
     glyphs[i] = { glyph_id, glyph_name, svg_path, advance_width, bounds };
   }
@@ -1,17 +1,52 @@
 import { useState, useEffect, useCallback, useMemo } from 'react';
 import { useLocation } from 'react-router';
 import { listen } from '@tauri-apps/api/event';
+import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
+import { toast } from 'sonner';
 import { SplitPane, Pane } from 'react-split-pane';
 import { Card, CardContent } from '@/components/ui/card';
 import { Input } from '@/components/ui/input';
 import { Search, ChevronDown, ChevronRight, ArrowUp, ArrowDown } from 'lucide-react';
 import { TableList } from '@/components/TableList';
 import type { FontMetadata } from '@/types/font';
-import { openFontDialog } from '@/hooks/useFileUpload';
+import {
+  openFontDialog,
+  saveFontAsDialog,
+  exportFontDialog,
+  exportCollectionDialog,
+  exportGlyphSvgsDialog,
+  importUfoDialog,
+  importTtxTableDialog,
+  mergeFontDialog,
+  reloadFont,
+  openFontWindow,
+  openFontPath,
+  saveFont,
+  setMenuItemEnabled,
+  type ExportFormat,
+} from '@/hooks/useFileUpload';
 import { useGoldenLayout } from '@/hooks/useGoldenLayout';
 import { editorEventBus } from '@/lib/editorEventBus';
+import { errorMessage } from '@/lib/errors';
 import '@/styles/golden-layout.css';
 
+// File menu items that need an open font
+const FONT_MENU_ITEMS = [
+  'save_font',
+  'save_font_as',
+  'export_woff',
+  'export_woff2',
+  'export_svg',
+  'export_ttx',
+  'export_ttc',
+];
+
+// Payload of File → Export menu events
+type MenuExportFormat = ExportFormat | 'svg' | 'ttc';
+
+// Menu events are sent to the focused window only; listen on this one
+const appWindow = getCurrentWebviewWindow();
+
 function ToolbarButton({
   icon,
   title,
@@ -47,6 +82,8 @@ export function FontViewer() {
   const location = useLocation();
 
   const [fonts, setFonts] = useState<FontMetadata[]>(() => {
+    const members = location.state?.fonts as FontMetadata[] | undefined;
+    if (members) return members;
     const initial = location.state?.metadata as FontMetadata | undefined;
     return initial ? [initial] : [];
   });
@@ -67,30 +104,266 @@ export function FontViewer() {
     return () => editorEventBus.clearHandler();
   }, [addEditorTab]);
 
-  // Listen for "Open Font" menu event
+  // Add newly opened fonts to the tree and select the first one
+  const showOpenedFonts = useCallback((newFonts: FontMetadata[]) => {
+    if (newFonts.length === 0) return;
+    setFonts((prev) => {
+      const existing = new Set(prev.map((f) => f.file_path));
+      const unique = newFonts.filter((f) => !existing.has(f.file_path));
+      return [...prev, ...unique];
+    });
+    setSelectedFilePath(newFonts[0].file_path);
+    setSelectedTable(null);
+  }, []);
+
+  // Listen for "Open Font": the menu sends no path and asks with a dialog; a file
+  // opened from the OS or a second launch comes with its path
+  useEffect(() => {
+    const unlisten = appWindow.listen<string | null>('menu:open-font', (event) => {
+      const filePath = event.payload;
+      (filePath ? openFontPath(filePath) : openFontDialog())
+        .then(showOpenedFonts)
+        .catch((err: unknown) =>
+          filePath
+            ? toast.error(`Failed to open ${filePath}: ${errorMessage(err)}`)
+            : console.error('Failed to open font:', err)
+        );
+    });
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten menu event:', err));
+    };
+  }, [showOpenedFonts]);
+
+  // Listen for "Open Recent" menu items; the payload is the font path
+  useEffect(() => {
+    const unlisten = appWindow.listen<string>('menu:open-recent', (event) => {
+      openFontPath(event.payload)
+        .then(showOpenedFonts)
+        .catch((err: unknown) =>
+          toast.error(`Failed to open ${event.payload}: ${errorMessage(err)}`)
+        );
+    });
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten menu event:', err));
+    };
+  }, [showOpenedFonts]);
+
+  // Listen for "Open in New Window" menu event; the new window opens the selected font
+  useEffect(() => {
+    const unlisten = appWindow.listen('menu:open-in-new-window', () => {
+      openFontWindow(selectedFilePath).catch((err: unknown) =>
+        toast.error(`Failed to open window: ${errorMessage(err)}`)
+      );
+    });
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten menu event:', err));
+    };
+  }, [selectedFilePath]);
+
+  // Listen for "Import UFO" menu event; the compiled font joins the tree
+  useEffect(() => {
+    const unlisten = appWindow.listen('menu:import-ufo', () => {
+      importUfoDialog()
+        .then((imported) => {
+          if (!imported) return;
+          setFonts((prev) => [...prev.filter((f) => f.file_path !== imported.file_path), imported]);
+          setSelectedFilePath(imported.file_path);
+          setSelectedTable(null);
+        })
+        .catch((err: unknown) => toast.error(`UFO import failed: ${errorMessage(err)}`));
+    });
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten menu event:', err));
+    };
+  }, []);
+
+  // Listen for "Import TTX Table" menu event; the table replaces the selected font's copy
+  useEffect(() => {
+    const unlisten = appWindow.listen('menu:import-ttx', () => {
+      if (!selectedFilePath) return;
+      importTtxTableDialog(selectedFilePath)
+        .then((table) => {
+          if (table) toast.success(`Imported ${table} table`);
+        })
+        .catch((err: unknown) => toast.error(`TTX import failed: ${errorMessage(err)}`));
+    });
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten menu event:', err));
+    };
+  }, [selectedFilePath]);
+
+  // Listen for "Merge Font" menu event; the merged copy joins the tree
   useEffect(() => {
-    const unlisten = listen('menu:open-font', () => {
-      openFontDialog()
-        .then((newFonts) => {
-          if (newFonts.length > 0) {
-            setFonts((prev) => {
-              const existing = new Set(prev.map((f) => f.file_path));
-              const unique = newFonts.filter((f) => !existing.has(f.file_path));
-              return [...prev, ...unique];
-            });
-            setSelectedFilePath(newFonts[0].file_path);
-            setSelectedTable(null);
+    const unlisten = appWindow.listen('menu:merge-font', () => {
+      if (!selectedFilePath) return;
+      mergeFontDialog(selectedFilePath)
+        .then((result) => {
+          if (!result) return;
+          const { font, report } = result;
+          setFonts((prev) => [...prev.filter((f) => f.file_path !== font.file_path), font]);
+          setSelectedFilePath(font.file_path);
+          setSelectedTable(null);
+          toast.success(
+            `Imported ${report.imported} glyphs (${report.skipped} skipped, ${report.scaled} scaled)`
+          );
+          if (report.dropped_tables.length > 0) {
+            toast.warning(`Dropped tables: ${report.dropped_tables.join(', ')}`);
           }
         })
-        .catch((err: unknown) => console.error('Failed to open font:', err));
+        .catch((err: unknown) => toast.error(`Merge failed: ${errorMessage(err)}`));
     });
     return () => {
       unlisten
         .then((fn) => fn())
         .catch((err: unknown) => console.error('Failed to unlisten menu event:', err));
     };
+  }, [selectedFilePath]);
+
+  // Another program changed an open font; saving over it is refused until it is reloaded
+  useEffect(() => {
+    const replaceFont = (reloaded: FontMetadata) =>
+      setFonts((prev) => prev.map((f) => (f.file_path === reloaded.file_path ? reloaded : f)));
+    const unlisten = listen<{ file_path: string; reloaded: boolean }>(
+      'font:changed-on-disk',
+      (event) => {
+        const { file_path, reloaded } = event.payload;
+        const name = file_path.split(/[\\/]/).pop();
+        if (reloaded) {
+          toast.info(`${name} changed on disk and was reloaded`);
+          return;
+        }
+        toast.warning(`${name} changed on disk; edits cannot be saved until it is reloaded`, {
+          action: {
+            label: 'Reload',
+            onClick: () => {
+              reloadFont(file_path)
+                .then(replaceFont)
+                .catch((err: unknown) => toast.error(`Reload failed: ${errorMessage(err)}`));
+            },
+          },
+        });
+      }
+    );
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten font event:', err));
+    };
   }, []);
 
+  // Save and Export items are only usable while a font is selected; SVG export
+  // also needs outlines (bitmap-only fonts have none), TTC any open font
+  useEffect(() => {
+    const selected = fonts.find((f) => f.file_path === selectedFilePath);
+    const enabled: Record<string, boolean> = {
+      save_font: !!selected,
+      save_font_as: !!selected,
+      export_woff: !!selected,
+      export_woff2: !!selected,
+      export_svg: !!selected && selected.outline_format !== 'none',
+      export_ttx: !!selected,
+      export_ttc: fonts.length > 0,
+    };
+    for (const [id, on] of Object.entries(enabled)) {
+      setMenuItemEnabled(id, on).catch((err: unknown) =>
+        console.error('Failed to update menu item:', err)
+      );
+    }
+  }, [selectedFilePath, fonts]);
+
+  // Grey them out again when leaving for the start page
+  useEffect(() => {
+    return () => {
+      for (const id of FONT_MENU_ITEMS) {
+        setMenuItemEnabled(id, false).catch((err: unknown) =>
+          console.error('Failed to update menu item:', err)
+        );
+      }
+    };
+  }, []);
+
+  // Listen for "Save" menu event
+  useEffect(() => {
+    const unlisten = appWindow.listen('menu:save-font', () => {
+      if (!selectedFilePath) return;
+      saveFont(selectedFilePath)
+        .then(() => toast.success('Font saved'))
+        .catch((err: unknown) => toast.error(`Failed to save font: ${errorMessage(err)}`));
+    });
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten menu event:', err));
+    };
+  }, [selectedFilePath]);
+
+  // Listen for "Save As" menu event; the saved copy replaces the original in the tree
+  useEffect(() => {
+    const unlisten = appWindow.listen('menu:save-font-as', () => {
+      if (!selectedFilePath) return;
+      saveFontAsDialog(selectedFilePath)
+        .then((saved) => {
+          if (!saved) return;
+          setFonts((prev) => [
+            ...prev.filter(
+              (f) => f.file_path !== selectedFilePath && f.file_path !== saved.file_path
+            ),
+            saved,
+          ]);
+          setSelectedFilePath(saved.file_path);
+          setSelectedTable(null);
+        })
+        .catch((err: unknown) => console.error('Failed to save font:', err));
+    });
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten menu event:', err));
+    };
+  }, [selectedFilePath]);
+
+  // Listen for File → Export menu events; the payload is the target format.
+  // A TTC packs every open font, the other formats export the selected one.
+  useEffect(() => {
+    const unlisten = appWindow.listen<MenuExportFormat>('menu:export-font', (event) => {
+      let exporting: Promise<boolean>;
+      if (event.payload === 'ttc') {
+        exporting = exportCollectionDialog(fonts.map((f) => f.file_path));
+      } else if (event.payload === 'svg' && selectedFilePath) {
+        exportGlyphSvgsDialog(selectedFilePath)
+          .then((count) => {
+            if (count !== null) toast.success(`Exported ${count} glyphs as SVG`);
+          })
+          .catch((err: unknown) => toast.error(`Export failed: ${errorMessage(err)}`));
+        return;
+      } else if (selectedFilePath && event.payload !== 'svg') {
+        exporting = exportFontDialog(selectedFilePath, event.payload);
+      } else {
+        return;
+      }
+      exporting
+        .then((exported) => {
+          if (exported) toast.success(`Exported ${event.payload.toUpperCase()}`);
+        })
+        .catch((err: unknown) => toast.error(`Export failed: ${errorMessage(err)}`));
+    });
+    return () => {
+      unlisten
+        .then((fn) => fn())
+        .catch((err: unknown) => console.error('Failed to unlisten menu event:', err));
+    };
+  }, [selectedFilePath, fonts]);
+
   const handleSelectTable = useCallback(
     (filePath: string, table: string) => {
       setSelectedFilePath(filePath);
@@ -1,3 +1,5 @@
+import type { GlyphBounds } from '../lib/glyphParser';
+
 export interface FontMetadata {
   file_name: string;
   file_path: string;
@@ -6,4 +8,322 @@ export interface FontMetadata {
   version: string;
   num_glyphs: number;
   available_tables: string[];
+  /** Container the file was opened from; edits are always saved as plain SFNT. */
+  container_format: 'sfnt' | 'woff' | 'woff2';
+  /** Member index for a font opened from a .ttc/.otc; file_path is then the member key. */
+  collection_index: number | null;
+  units_per_em: number;
+  /** OS/2 usWeightClass/usWidthClass; null without an OS/2 table. */
+  weight_class: number | null;
+  width_class: number | null;
+  is_italic: boolean;
+  fs_type: number | null;
+  embedding: 'installable' | 'restricted' | 'preview_and_print' | 'editable' | null;
+  /** head created/modified as ISO 8601 UTC strings. */
+  created: string | null;
+  modified: string | null;
+  copyright: string | null;
+  license: string | null;
+  license_url: string | null;
+  is_variable: boolean;
+  /** fvar axis tags; empty for a static font. */
+  variation_axes: string[];
+  /** Has COLR, SVG, sbix or CBDT glyphs. */
+  is_color: boolean;
+  /** Which color glyph mechanisms the font carries. */
+  color_formats: ('colr_v0' | 'colr_v1' | 'svg' | 'sbix' | 'cbdt')[];
+  outline_format: 'glyf' | 'cff' | 'cff2' | 'none';
+  /** Has EBDT, CBDT or sbix bitmap strikes. */
+  has_embedded_bitmaps: boolean;
+  /** Has fpgm/prep/cvt programs or CFF outlines; see check_font_hinting for detail. */
+  has_hinting: boolean;
+  /** Has both vhea and vmtx. */
+  has_vertical_metrics: boolean;
+  /** Size of the file on disk; for a collection member, of the whole collection. */
+  file_size: number;
+}
+
+export interface CollectionMember {
+  index: number;
+  file_path: string;
+  family_name: string;
+  style_name: string;
+}
+
+export interface MergeReport {
+  imported: number;
+  skipped: number;
+  renamed: number;
+  scaled: number;
+  mapped_codepoints: number;
+  /** Per-glyph tables of the base that could not be extended. */
+  dropped_tables: string[];
+}
+
+/** Approximate memory the backend cache holds for one open font. */
+export interface CacheEntryStats {
+  file_path: string;
+  font_bytes: number;
+  outline_bytes: number;
+  /** Copy of the bytes as first opened, kept for revert. */
+  original_bytes: number;
+  total_bytes: number;
+}
+
+/** 'deferred' holds edits in memory until commit_font writes them. */
+export type EditMode = 'direct' | 'deferred';
+
+export interface FontStatus {
+  /** Whether the file on disk differs from the bytes originally opened. */
+  modified: boolean;
+  /** Unix time in milliseconds of the last write this session. */
+  last_saved_at: number | null;
+  undo_depth: number;
+  /** The font has no file yet; edits stay in memory until Save As. */
+  in_memory: boolean;
+  edit_mode: EditMode;
+  /** Edits the file does not have yet; worth a prompt before closing. */
+  pending_changes: boolean;
+}
+
+/** One entry of get_language_support, sorted supported → partial → unsupported. */
+export interface LanguageSupport {
+  /** BCP 47 tag. */
+  tag: string;
+  name: string;
+  script: string;
+  status: 'supported' | 'partial' | 'unsupported';
+  /** Exemplar codepoints the font lacks; only listed for partial support. */
+  missing: number[];
+  exemplar_count: number;
+}
+
+/** Options for import_features. */
+export interface FeatureImportOptions {
+  /** 'merge' runs the new lookups after a feature's current ones; 'replace' drops those first. */
+  mode?: 'merge' | 'replace';
+}
+
+/** Result of import_features. */
+export interface FeatureImportReport {
+  /** Tags of the feature blocks, in order of first appearance. */
+  features: string[];
+  gsub_lookups: number;
+  gpos_lookups: number;
+  rules: number;
+}
+
+/** One ligature rule from get_ligatures. */
+export interface LigatureEntry {
+  component_glyph_ids: number[];
+  /** Post/CFF names, or `gidN` for unnamed glyphs. */
+  component_names: string[];
+  ligature_glyph_id: number;
+  /** Characters typing the components, when every component is in the cmap. */
+  text: string | null;
+  feature_tags: string[];
+  /** Ligature outline from the outline cache, when requested and cached. */
+  svg_path: string | null;
+}
+
+export interface LigatureReport {
+  ligatures: LigatureEntry[];
+  /** Contextual lookups not analyzed for the ligatures they trigger. */
+  skipped_contextual_lookups: number;
+}
+
+export type CharacterMapFormat = 'csv' | 'json';
+
+/** Result of export_character_map. */
+export interface CharacterMapSummary {
+  codepoints: number;
+  /** Unmapped glyphs written after the mapped rows; 0 unless requested. */
+  unmapped_glyphs: number;
+}
+
+/** Options of export_specimen; omitted fields take the defaults noted. */
+export interface SpecimenOptions {
+  /** Page width in pixels (1200). */
+  width?: number;
+  /** Page height in pixels; null fits the content (null). */
+  height?: number | null;
+  margin?: number;
+  header?: boolean;
+  character_set?: boolean;
+  /** Most glyphs in the character grid (256). */
+  character_set_limit?: number;
+  samples?: boolean;
+  /** Sample lines used in turn; built-in pangrams when empty. */
+  sample_text?: string[];
+  /** Sample sizes in pixels per em, one line each. */
+  sample_sizes?: number[];
+}
+
+/** Pixel size of the PNG written by export_specimen. */
+export interface SpecimenSize {
+  width: number;
+  height: number;
+}
+
+export type GlyphMatching = 'name' | 'codepoint' | 'glyph_id';
+
+export interface GlyphDiffOptions {
+  /** How many of the most-changed glyphs carry both SVG paths (20). */
+  top_n?: number;
+  include_unchanged?: boolean;
+}
+
+/** One matched glyph pair; font B's values are scaled to font A's unitsPerEm. */
+export interface GlyphDiff {
+  key: string;
+  glyph_id_a: number;
+  glyph_id_b: number;
+  advance_a: number;
+  advance_b: number;
+  lsb_a: number;
+  lsb_b: number;
+  bounds_a: GlyphBounds | null;
+  bounds_b: GlyphBounds | null;
+  advance_changed: boolean;
+  lsb_changed: boolean;
+  bounds_changed: boolean;
+  /** Symmetric-difference area over union area, 0 (same) to 1 (disjoint). */
+  outline_distance: number;
+  svg_path_a: string | null;
+  svg_path_b: string | null;
+}
+
+export interface GlyphDiffReport {
+  matched: number;
+  changed: number;
+  only_in_a: string[];
+  only_in_b: string[];
+  /** Most changed first. */
+  glyphs: GlyphDiff[];
+}
+
+/** One font found by scan_font_directory; open it with parse_font. */
+export interface FamilyMember {
+  /** Member key ("path#index") for collection members. */
+  file_path: string;
+  file_name: string;
+  collection_index: number | null;
+  family_name: string;
+  style_name: string;
+  weight_class: number | null;
+  width_class: number | null;
+  is_italic: boolean;
+  units_per_em: number;
+  variation_axes: string[];
+}
+
+export type FamilyIssue =
+  | { kind: 'mixed_units_per_em'; values: number[] }
+  | { kind: 'duplicate_style'; style_name: string; file_paths: string[] }
+  | { kind: 'missing_regular' };
+
+export interface FontFamily {
+  family_name: string;
+  members: FamilyMember[];
+  issues: FamilyIssue[];
+}
+
+export interface DirectoryScan {
+  families: FontFamily[];
+  skipped: { file_path: string; error: string }[];
+}
+
+export type ConsistencyRule = 'agree' | 'per_font' | 'name_order';
+
+export interface ConsistencyRow {
+  /** Table and field, e.g. "OS/2.usWinAscent". */
+  field: string;
+  rule: ConsistencyRule;
+  /** One per font, in the order of FamilyConsistencyReport.fonts. */
+  values: (number | string | null)[];
+  mismatched: boolean[];
+}
+
+export interface FamilyConsistencyReport {
+  fonts: FamilyMember[];
+  rows: ConsistencyRow[];
+  mismatched_rows: number;
+}
+
+export type CheckProfile = 'webfont';
+
+export type CheckStatus = 'pass' | 'warn' | 'fail';
+
+export interface CheckResult {
+  /** Stable identifier, e.g. "whitespace_widths". */
+  id: string;
+  status: CheckStatus;
+  message: string;
+  /** The measured values behind the verdict. */
+  values: Record<string, number>;
+}
+
+export interface CheckReport {
+  profile: CheckProfile;
+  checks: CheckResult[];
+  passed: number;
+  warnings: number;
+  failures: number;
+}
+
+export interface RequiredGlyphsOptions {
+  basic_latin?: boolean;
+}
+
+/** Invoke `command` with `args` and the font's filePath to apply the fix. */
+export interface FixSuggestion {
+  label: string;
+  command: string;
+  args: Record<string, unknown>;
+}
+
+export interface RequiredGlyph {
+  id: 'notdef' | 'space' | 'nbsp' | 'cr';
+  codepoint: number | null;
+  glyph_id: number | null;
+  advance_width: number | null;
+  contours: number | null;
+  status: CheckStatus;
+  message: string;
+  fix: FixSuggestion | null;
+}
+
+export interface RequiredGlyphsReport {
+  glyphs: RequiredGlyph[];
+  missing_basic_latin: number[] | null;
+}
+
+export type OutlineProblem =
+  | 'open_contour'
+  | 'hole_wound_like_outer'
+  | 'reversed_outer'
+  | 'zero_area'
+  | 'duplicate_point'
+  | 'extreme_coordinate';
+
+export interface OutlineFinding {
+  problem: OutlineProblem;
+  severity: 'error' | 'warning';
+  /** Index of the contour within the glyph. */
+  contour: number;
+  message: string;
+}
+
+export interface GlyphOutlineFindings {
+  glyph_id: number;
+  glyph_name: string;
+  findings: OutlineFinding[];
+}
+
+/** Result of audit_outline_quality; only glyphs with findings are listed. */
+export interface OutlineQualityReport {
+  glyphs: GlyphOutlineFindings[];
+  glyphs_checked: number;
+  errors: number;
+  warnings: number;
 }
@@ -1 +1,6 @@
 /// <reference types="vite/client" />
+
+interface Window {
+  /** Font path a window opened via open_font_window should show on load. */
+  __TYPEBREW_INITIAL_FONT__?: string | null;
+}